@@ -0,0 +1,135 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[rustfmt::skip]
+#[path = "../tests/codegen/numerals.rs"]
+mod numerals;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ucc::core::{Context, Expr};
+use ucc::interp::Interp;
+use ucc::shared::SharedPrelude;
+
+/// The system allocator, counting allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn construct(c: &mut Criterion) {
+    c.bench_function("Interp::default", |b| b.iter(Interp::default));
+}
+
+/// `n6 flip` interpreted, and compiled by `ucc::codegen`
+fn compiled(c: &mut Criterion) {
+    let lib = include_str!("../tests/codegen/numerals.ucc");
+    let prelude = SharedPrelude::default();
+    let mut ctx = Context::with_prelude(&prelude);
+    ctx.define_many(lib.lines()).unwrap();
+    let e = ctx.parse_expr("n6 flip").unwrap();
+    c.bench_function("n6 flip interpreted", |b| {
+        b.iter(|| {
+            let mut vs = Default::default();
+            let mut e = e.clone();
+            while e != Expr::default() {
+                ctx.small_step(&mut vs, &mut e).unwrap();
+            }
+            vs
+        })
+    });
+    c.bench_function("n6 flip compiled", |b| {
+        b.iter(|| {
+            let mut s = vec![];
+            numerals::call("n6", &mut s).unwrap();
+            numerals::call("flip", &mut s).unwrap();
+            s
+        })
+    });
+}
+
+/// How many allocations evaluating expressions takes, written out, as
+/// criterion only measures time
+fn allocations(c: &mut Criterion) {
+    let prelude = SharedPrelude::default();
+    let mut ctx = Context::with_prelude(&prelude);
+    for src in ["n3 n4 mul", "n4 n4 add succ", "true false or"] {
+        let e = ctx.parse_expr(src).unwrap();
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let mut vs = Default::default();
+        let mut e_ = e.clone();
+        let mut steps = 0;
+        while e_ != Expr::default() {
+            ctx.small_step(&mut vs, &mut e_).unwrap();
+            steps += 1;
+        }
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        eprintln!("{}: {} allocations in {} steps", src, allocations, steps);
+        c.bench_function(&format!("{} interpreted", src), |b| {
+            b.iter(|| {
+                let mut vs = Default::default();
+                let mut e = e.clone();
+                while e != Expr::default() {
+                    ctx.small_step(&mut vs, &mut e).unwrap();
+                }
+                vs
+            })
+        });
+    }
+}
+
+/// Writing every state of a long trace, as `:trace` does, with how many
+/// allocations that takes written out
+fn trace(c: &mut Criterion) {
+    let prelude = SharedPrelude::default();
+    let mut ctx = Context::with_prelude(&prelude);
+    let e = ctx.parse_expr("n3 n4 mul").unwrap();
+    let mut states = vec![];
+    let mut vs = Default::default();
+    let mut e_ = e;
+    while e_ != Expr::default() {
+        ctx.small_step(&mut vs, &mut e_).unwrap();
+        states.push((vs.clone(), e_.clone()));
+    }
+    // Written to a buffer that is big enough already, so that only the
+    // allocations of writing are counted
+    let mut w = Vec::with_capacity(1 << 20);
+    let mut write_trace = || {
+        w.clear();
+        for (vs, e) in &states {
+            writeln!(w, "⟶ {} {}", ctx.display(vs), ctx.display(e)).unwrap();
+        }
+        w.len()
+    };
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let len = write_trace();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    eprintln!(
+        "trace: {} allocations in {} steps, {} bytes",
+        allocations,
+        states.len(),
+        len
+    );
+    c.bench_function("trace", |b| b.iter(&mut write_trace));
+}
+
+criterion_group!(benches, construct, compiled, allocations, trace);
+criterion_main!(benches);
@@ -1,3 +1,480 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// The tags, version, and writer the crate's `.uccb` reader expects
+#[allow(dead_code)]
+#[path = "src/uccb.rs"]
+mod uccb;
+
+use uccb::{Writer, INTRINSIC_NAMES, TAG_CALL, TAG_COMPOSE, TAG_INTRINSIC, TAG_QUOTE};
+
 fn main() {
     lalrpop::process_root().unwrap();
+    build_prelude();
+    build_grammar();
+    build_tree_sitter();
+}
+
+/// Precompile `src/prelude.ucc`, which has one definition per line, so that
+/// `Interp::default()` decodes it instead of parsing it. This writes:
+///
+/// - `builtin.rs`, defining `FN_DEF_SRCS`, the source of each definition,
+///   for tests
+/// - `prelude.uccb`, the definitions in the `.uccb` format
+fn build_prelude() {
+    let src = fs::read_to_string("src/prelude.ucc").unwrap();
+    let fn_def_srcs: Vec<&str> = src.lines().filter(|line| !line.is_empty()).collect();
+
+    let mut rs = format!(
+        "#[cfg(test)]\npub(crate) static FN_DEF_SRCS: [&str; {}] = [\n",
+        fn_def_srcs.len()
+    );
+    for fn_def_src in fn_def_srcs.iter() {
+        rs.push_str(&format!("    {:?},\n", fn_def_src));
+    }
+    rs.push_str("];\n");
+
+    let mut writer = Writer::default();
+    writer.varint(fn_def_srcs.len());
+    for fn_def_src in fn_def_srcs.iter() {
+        let def = fn_def_src
+            .strip_prefix("{fn ")
+            .and_then(|def| def.strip_suffix('}'))
+            .unwrap_or_else(|| panic!("expected `{{fn name = ...}}`: {}", fn_def_src));
+        let (name, body) = def.split_once(" = ").unwrap();
+        writer.name(name);
+        let mut tokens = body.split_whitespace().flat_map(split_brackets);
+        encode_compose(&mut writer, &mut tokens, fn_def_src);
+    }
+    let uccb = writer.finish(|name| name);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("builtin.rs"), rs).unwrap();
+    fs::write(Path::new(&out_dir).join("prelude.uccb"), uccb).unwrap();
+}
+
+/// Split `[[clone]` into `[`, `[`, `clone`, `]`
+fn split_brackets(word: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut rest = word;
+    while let Some(i) = rest.find(['[', ']']) {
+        if i > 0 {
+            tokens.push(&rest[..i]);
+        }
+        tokens.push(&rest[i..i + 1]);
+        rest = &rest[i + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(rest);
+    }
+    tokens
+}
+
+/// Encode the tokens up to the end of the definition or a closing `]`. As
+/// in the grammar, a single expression isn't wrapped in a composition.
+fn encode_compose<'a>(
+    writer: &mut Writer<&'a str>,
+    tokens: &mut impl Iterator<Item = &'a str>,
+    src: &str,
+) {
+    let mut es = vec![];
+    while let Some(token) = tokens.next() {
+        let body = std::mem::take(&mut writer.body);
+        match token {
+            "]" => {
+                writer.body = body;
+                break;
+            }
+            "[" => {
+                writer.body.push(TAG_QUOTE);
+                encode_compose(writer, tokens, src);
+            }
+            _ => match INTRINSIC_NAMES.iter().position(|i| *i == token) {
+                Some(i) => writer.body.push(TAG_INTRINSIC + i as u8),
+                None if token.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    writer.body.push(TAG_CALL);
+                    writer.name(token);
+                }
+                None => panic!("unsupported token `{}` in the prelude: {}", token, src),
+            },
+        }
+        es.push(std::mem::replace(&mut writer.body, body));
+    }
+    if es.len() != 1 {
+        writer.body.push(TAG_COMPOSE);
+        writer.varint(es.len());
+    }
+    for e in es {
+        writer.body.extend(e);
+    }
+}
+
+/// Write `grammar.ebnf`, the grammar in `src/parse.lalrpop` in the W3C EBNF
+/// notation, with each token that matches fixed text written as that text.
+fn build_grammar() {
+    let src = fs::read_to_string("src/parse.lalrpop").unwrap();
+    let (rules, tokens) = src.split_once("\nmatch {").unwrap();
+
+    // Token names, and their definitions in EBNF, in the order defined
+    let token_defs: Vec<(&str, String)> = lalrpop_tokens(tokens)
+        .into_iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, re)| (name, regex_to_ebnf(re)))
+        .collect();
+    let is_text = |def: &str| {
+        def.split(' ')
+            .all(|item| item.len() > 1 && (item.starts_with('"') || item.starts_with('\'')))
+    };
+
+    let mut ebnf = "/* The grammar of ucc, generated from src/parse.lalrpop */\n".to_owned();
+    let mut used_tokens: Vec<String> = vec![];
+    let mut lines = rules.lines();
+    while let Some(line) = lines.next() {
+        // A rule starts with `[pub[(crate)]] Name: Type = {`, and ends with
+        // a line `}`
+        let head = line
+            .trim_start_matches("pub(crate) ")
+            .trim_start_matches("pub ");
+        let name = match head.split_once(": ") {
+            Some((name, rest)) if rest.ends_with("= {") && !name.contains(' ') => name,
+            _ => continue,
+        };
+        let body: Vec<&str> = lines.by_ref().take_while(|line| *line != "}").collect();
+        let mut alternatives = vec![];
+        for alternative in split_alternatives(&body.join("\n")) {
+            let pattern = match alternative.find("=>") {
+                Some(i) => &alternative[..i],
+                None => &alternative[..],
+            };
+            let symbols: Vec<String> = pattern
+                .replace(['<', '>'], " ")
+                .split_whitespace()
+                .filter(|word| *word != "mut" && !word.ends_with(':'))
+                .map(|word| {
+                    let word = word.rsplit(':').next().unwrap();
+                    let start = word.find(|c: char| c != '(').unwrap_or(word.len());
+                    let end = word
+                        .rfind(|c: char| !")*?+".contains(c))
+                        .map_or(start, |i| i + 1);
+                    let symbol = &word[start..end];
+                    let symbol = match token_defs.iter().find(|(name, _)| *name == symbol) {
+                        Some((_, def)) if is_text(def) => def.clone(),
+                        Some(_) => {
+                            if !used_tokens.iter().any(|name| name == symbol) {
+                                used_tokens.push(symbol.to_owned());
+                            }
+                            symbol.to_owned()
+                        }
+                        None => symbol.to_owned(),
+                    };
+                    format!("{}{}{}", &word[..start], symbol, &word[end..])
+                })
+                .collect();
+            alternatives.push(symbols.join(" "));
+        }
+        ebnf.push_str(&format!(
+            "\n{} ::= {}\n",
+            name,
+            alternatives.join(&format!("\n{:width$} | ", "", width = name.len()))
+        ));
+    }
+    ebnf.push_str("\n/* Tokens */\n");
+    for (name, def) in token_defs.iter() {
+        if used_tokens.iter().any(|used| used == name) {
+            ebnf.push_str(&format!("\n{} ::= {}\n", name, def));
+        }
+    }
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("grammar.ebnf"), ebnf).unwrap();
+}
+
+const TREE_SITTER_GRAMMAR: &str = r#"// A tree-sitter grammar for ucc source, generated from src/parse.lalrpop
+module.exports = grammar({
+  name: 'ucc',
+
+  extras: $ => [/\s/, $.comment],
+
+  word: $ => $.identifier,
+
+  // `(` starts a group or a pair, told apart by a `,`
+  conflicts: $ => [[$._expr, $._value]],
+
+  rules: {
+    source_file: $ => repeat(choice($.fn_def, $.rule, $.local, $._expr)),
+
+    fn_def: $ => seq(%LBRACE%, %FN%, field('name', $.identifier), %EQ%, repeat($._expr), %RBRACE%),
+
+    rule: $ => seq(%LBRACE%, %RULE%, repeat($._pattern), %FAT_ARROW%, repeat($._pattern), %RBRACE%),
+
+    local: $ => seq(%LBRACE%, %LOCAL%, repeat1($.local_fn_def), %IN%, repeat($._expr), %RBRACE%),
+
+    local_fn_def: $ => seq(%FN%, field('name', $.identifier), %EQ%, repeat($._expr)),
+
+    _expr: $ => choice(
+      $.intrinsic,
+      $.identifier,
+      $.symbol,
+      $.char,
+      $.string,
+      $.quotation,
+      $.pair,
+      $.map,
+      $.group,
+    ),
+
+    quotation: $ => seq(%LBRACKET%, repeat($._expr), %RBRACKET%),
+
+    group: $ => seq(%LPAREN%, repeat($._expr), %RPAREN%),
+
+    pair: $ => seq(%LPAREN%, $._value, %COMMA%, $._value, %RPAREN%),
+
+    _value: $ => choice($.identifier, $.symbol, $.char, $.string, $.quotation, $.pair, $.map),
+
+    map: $ => seq(
+      %HASH_LBRACE%,
+      optional(seq($.map_entry, repeat(seq(%COMMA%, $.map_entry)), optional(%COMMA%))),
+      %RBRACE%,
+    ),
+
+    map_entry: $ => seq($._value, %FAT_ARROW%, $._value),
+
+    _pattern: $ => choice(
+      $.pattern_var,
+      $.intrinsic,
+      $.identifier,
+      $.symbol,
+      $.pattern_quotation,
+      $.pattern_group,
+    ),
+
+    pattern_quotation: $ => seq(%LBRACKET%, repeat($._pattern), %RBRACKET%),
+
+    pattern_group: $ => seq(%LPAREN%, repeat($._pattern), %RPAREN%),
+
+    intrinsic: $ => choice(%INTRINSICS%),
+
+    identifier: $ => /%/IDENT%/,
+
+    symbol: $ => /%/SYMBOL%/,
+
+    char: $ => /%/CHAR%/,
+
+    string: $ => /%/STRING%/,
+
+    pattern_var: $ => /%/PATTERN_VAR%/,
+
+    comment: $ => /%/COMMENT%/,
+  },
+});
+"#;
+
+const TREE_SITTER_HIGHLIGHTS: &str = r#"; Highlight queries for ucc source, generated from src/parse.lalrpop
+(comment) @comment
+(intrinsic) @function.builtin
+(fn_def name: (identifier) @function)
+(local_fn_def name: (identifier) @function)
+(identifier) @function.call
+(symbol) @string.special.symbol
+(char) @character
+(string) @string
+(pattern_var) @variable
+[%FN% %RULE% %LOCAL% %IN%] @keyword
+[%EQ% %FAT_ARROW%] @operator
+[%LBRACKET% %RBRACKET% %LPAREN% %RPAREN% %LBRACE% %HASH_LBRACE% %RBRACE%] @punctuation.bracket
+%COMMA% @punctuation.delimiter
+"#;
+
+/// Write `grammar.js` and `highlights.scm`, a tree-sitter grammar for ucc
+/// source and its highlight queries. They are filled in from the tokens in
+/// `src/parse.lalrpop`: `%NAME%` with the text a token matches, `%/NAME%`
+/// with its regular expression, and `%INTRINSICS%` with the text of the
+/// tokens `PlainSingleExpr` makes intrinsics of.
+fn build_tree_sitter() {
+    let src = fs::read_to_string("src/parse.lalrpop").unwrap();
+    let (rules, tokens) = src.split_once("\nmatch {").unwrap();
+    let mut tokens = lalrpop_tokens(tokens);
+    for (name, re) in tokens.iter_mut() {
+        if name.is_empty() && re.starts_with("--") {
+            *name = "COMMENT";
+        }
+    }
+    let regex = |name: &str| {
+        let (_, re) = tokens
+            .iter()
+            .find(|(token, _)| *token == name)
+            .unwrap_or_else(|| panic!("no token `{}`", name));
+        *re
+    };
+    let text = |name: &str| {
+        fixed_text(regex(name)).unwrap_or_else(|| panic!("token `{}` isn't fixed text", name))
+    };
+    let single_expr = rules.split("\nPlainSingleExpr: Expr = {\n").nth(1).unwrap();
+    let intrinsics: Vec<String> = single_expr
+        .lines()
+        .take_while(|line| *line != "}")
+        .filter(|line| line.contains("Expr::Intrinsic("))
+        .map(|line| text(line.trim().split(' ').next().unwrap()))
+        .collect();
+
+    let fill = |template: &str, quote: &dyn Fn(&str) -> String| {
+        let mut out = String::new();
+        let mut parts = template.split('%');
+        out.push_str(parts.next().unwrap());
+        while let (Some(marker), Some(rest)) = (parts.next(), parts.next()) {
+            match marker {
+                "INTRINSICS" => {
+                    let intrinsics: Vec<String> = intrinsics.iter().map(|i| quote(i)).collect();
+                    out.push_str(&intrinsics.join(", "));
+                }
+                _ => match marker.strip_prefix('/') {
+                    Some(name) => out.push_str(&regex(name).replace('/', "\\/")),
+                    None => out.push_str(&quote(&text(marker))),
+                },
+            }
+            out.push_str(rest);
+        }
+        out
+    };
+    let grammar_js = fill(TREE_SITTER_GRAMMAR, &|text| {
+        format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+    });
+    let highlights = fill(TREE_SITTER_HIGHLIGHTS, &|text| format!("{:?}", text));
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("grammar.js"), grammar_js).unwrap();
+    fs::write(Path::new(&out_dir).join("highlights.scm"), highlights).unwrap();
+}
+
+/// The text `re` matches, if it matches only that
+fn fixed_text(re: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut chars = re.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c if c.is_ascii_alphanumeric() => return None,
+                c => text.push(c),
+            },
+            '[' | ']' | '(' | ')' | '*' | '+' | '?' | '.' | '|' | '^' | '$' => return None,
+            c => text.push(c),
+        }
+    }
+    Some(text)
+}
+
+/// The names and regular expressions of the tokens in the `match` block of
+/// a lalrpop grammar, in order, with the name empty for skipped tokens
+fn lalrpop_tokens(tokens: &str) -> Vec<(&str, &str)> {
+    let mut token_defs = vec![];
+    for line in tokens.lines() {
+        let line = line.trim();
+        let (re, name) = match line.split_once(" => ") {
+            Some((re, name)) if name.starts_with('{') => (re, ""),
+            Some((re, name)) => (re, name.trim_end_matches(',')),
+            None => continue,
+        };
+        let re = re
+            .strip_prefix("r#\"")
+            .and_then(|re| re.strip_suffix("\"#"))
+            .or_else(|| re.strip_prefix("r\"").and_then(|re| re.strip_suffix('"')))
+            .unwrap_or_else(|| panic!("unsupported token `{}`", line));
+        token_defs.push((name, re));
+    }
+    token_defs
+}
+
+/// Split the body of a rule at the commas between its alternatives
+fn split_alternatives(body: &str) -> Vec<String> {
+    let mut alternatives = vec![];
+    let mut alternative = String::new();
+    let mut depth = 0;
+    for c in body.chars() {
+        match c {
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                alternatives.push(std::mem::take(&mut alternative));
+                continue;
+            }
+            _ => {}
+        }
+        alternative.push(c);
+    }
+    alternatives.push(alternative);
+    alternatives
+        .into_iter()
+        .map(|alternative| alternative.trim().to_owned())
+        .filter(|alternative| !alternative.is_empty())
+        .collect()
+}
+
+/// Translate a token's regular expression to EBNF. Only the syntax the
+/// tokens use is supported: classes, groups, repetition, escapes, and `\s`.
+fn regex_to_ebnf(re: &str) -> String {
+    fn quote(text: &str) -> String {
+        if text.contains('"') {
+            format!("'{}'", text)
+        } else {
+            format!("\"{}\"", text)
+        }
+    }
+    let mut items: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut chars = re.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next().unwrap() {
+                // Whitespace separates the text before and after it
+                's' => {
+                    chars.next_if(|c| *c == '+' || *c == '*');
+                    if !text.is_empty() {
+                        items.push(quote(&std::mem::take(&mut text)));
+                    }
+                }
+                c => text.push(c),
+            },
+            '[' => {
+                if !text.is_empty() {
+                    items.push(quote(&std::mem::take(&mut text)));
+                }
+                let mut class = "[".to_owned();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next().unwrap() {
+                            'n' => class.push_str("#xA"),
+                            'r' => class.push_str("#xD"),
+                            c => class.push(c),
+                        },
+                        ']' => break,
+                        c => class.push(c),
+                    }
+                }
+                class.push(']');
+                items.push(class);
+            }
+            '(' | ')' | '|' => {
+                if !text.is_empty() {
+                    items.push(quote(&std::mem::take(&mut text)));
+                }
+                items.push(c.to_string());
+            }
+            '*' | '+' | '?' => {
+                // Repetition applies to the last character of any text
+                if let Some(last) = text.pop() {
+                    if !text.is_empty() {
+                        items.push(quote(&std::mem::take(&mut text)));
+                    }
+                    items.push(quote(&last.to_string()));
+                }
+                items.last_mut().unwrap().push(c);
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        items.push(quote(&text));
+    }
+    items.join(" ").replace("( ", "(").replace(" )", ")")
 }
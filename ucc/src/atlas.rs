@@ -0,0 +1,229 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! An atlas of every small expression over a vocabulary, grouped by what
+//! each does to a canonical value stack, for exploring which expressions
+//! of the calculus behave alike.
+
+use crate::core::{Context, Expr, Interner, Intrinsic, OrderedMap, Symbol, Value, ValueStack};
+use crate::display::Resolve;
+use std::fs;
+use std::path::Path;
+
+/// The largest atlas `:atlas` makes, as the number of expressions grows
+/// exponentially with their size
+pub const MAX_SIZE: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtlasError {
+    Io(String),
+}
+
+/// What an expression does to the canonical stack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// Evaluation finished, leaving these values
+    Halted(ValueStack),
+    /// Evaluation stopped at an error
+    Failed,
+    /// Evaluation took more steps than the fuel allowed
+    OutOfFuel,
+}
+
+pub struct AtlasSpec {
+    /// The intrinsics and calls expressions are built from, besides
+    /// quotations
+    pub vocabulary: Vec<Expr>,
+    /// The most nodes in an expression, where each intrinsic, call, and
+    /// quotation is one node
+    pub size: usize,
+    pub stack: ValueStack,
+    /// The most small steps evaluating each expression may take
+    pub fuel: usize,
+}
+
+pub struct Atlas {
+    pub stack: ValueStack,
+    pub size: usize,
+    pub fuel: usize,
+    /// Expressions with the same outcome, in the order the outcomes were
+    /// first seen, smallest expressions first
+    pub classes: Vec<(Outcome, Vec<Expr>)>,
+}
+
+impl AtlasSpec {
+    /// The intrinsics except `define!`, on the stack `⟨[a] [b] [c]⟩`
+    pub fn new(interner: &mut Interner, size: usize) -> Self {
+        let vocabulary = [
+            Intrinsic::Swap,
+            Intrinsic::Clone,
+            Intrinsic::Drop,
+            Intrinsic::Quote,
+            Intrinsic::Compose,
+            Intrinsic::Apply,
+            Intrinsic::Defined,
+            Intrinsic::BodyOf,
+        ];
+        let stack = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let sym = Symbol(interner.get_or_intern(name));
+                Value::Quote(Box::new(Expr::Call(sym)))
+            })
+            .collect();
+        AtlasSpec {
+            vocabulary: vocabulary.iter().copied().map(Expr::Intrinsic).collect(),
+            size,
+            stack: ValueStack(stack),
+            fuel: 1000,
+        }
+    }
+
+    /// The sequences of items with exactly `n` nodes, indexed by `n`, up to
+    /// `size`
+    fn terms(&self) -> Vec<Vec<Vec<Expr>>> {
+        let mut terms: Vec<Vec<Vec<Expr>>> = vec![vec![vec![]]];
+        for n in 1..=self.size {
+            // The items with exactly `k` nodes are the vocabulary if `k` is
+            // one, and the quotations of terms with `k - 1` nodes
+            let items = |k: usize| {
+                let atoms = if k == 1 { &self.vocabulary[..] } else { &[] };
+                atoms.iter().cloned().chain(
+                    terms[k - 1]
+                        .iter()
+                        .map(|t| Expr::Quote(Box::new(compose(t.clone())))),
+                )
+            };
+            let mut ts = vec![];
+            for k in 1..=n {
+                for item in items(k) {
+                    for rest in terms[n - k].iter() {
+                        let mut t = vec![item.clone()];
+                        t.extend(rest.iter().cloned());
+                        ts.push(t);
+                    }
+                }
+            }
+            terms.push(ts);
+        }
+        terms
+    }
+}
+
+fn compose(mut es: Vec<Expr>) -> Expr {
+    if es.len() == 1 {
+        es.pop().unwrap()
+    } else {
+        Expr::Compose(es)
+    }
+}
+
+impl Context {
+    /// Evaluate every expression allowed by `spec` on its stack, and group
+    /// them by outcome.
+    pub fn atlas(&mut self, spec: &AtlasSpec) -> Atlas {
+        let mut classes: OrderedMap<Outcome, Vec<Expr>> = OrderedMap::default();
+        let allow_define = self.allow_define;
+        self.allow_define = false;
+        for t in spec.terms().into_iter().skip(1).flatten() {
+            let e = compose(t);
+            let outcome = self.outcome(&spec.stack, e.clone(), spec.fuel);
+            classes.entry(outcome).or_default().push(e);
+        }
+        self.allow_define = allow_define;
+        Atlas {
+            stack: spec.stack.clone(),
+            size: spec.size,
+            fuel: spec.fuel,
+            classes: classes.into_iter().collect(),
+        }
+    }
+
+    fn outcome(&mut self, vs: &ValueStack, mut e: Expr, fuel: usize) -> Outcome {
+        let mut vs = vs.clone();
+        for _ in 0..fuel {
+            if e == Expr::default() {
+                self.compress(&mut vs);
+                return Outcome::Halted(vs);
+            }
+            if self.small_step(&mut vs, &mut e).is_err() {
+                return Outcome::Failed;
+            }
+        }
+        if e == Expr::default() {
+            self.compress(&mut vs);
+            Outcome::Halted(vs)
+        } else {
+            Outcome::OutOfFuel
+        }
+    }
+}
+
+impl Atlas {
+    /// The number of expressions evaluated
+    pub fn len(&self) -> usize {
+        self.classes.iter().map(|(_, exprs)| exprs.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// The atlas as JSON, e.g.
+    ///
+    /// ```text
+    /// {"stack": "⟨[a]⟩", "size": 1, "fuel": 1000, "classes": [
+    ///   {"outcome": "halted", "stack": "⟨⟩", "exprs": ["drop"]},
+    ///   ...
+    /// ]}
+    /// ```
+    pub fn to_json(&self, interner: &Interner) -> String {
+        let mut json = format!(
+            "{{\"stack\": {}, \"size\": {}, \"fuel\": {}, \"classes\": [",
+            json_string(&self.stack.resolve(interner).to_string()),
+            self.size,
+            self.fuel
+        );
+        for (i, (outcome, exprs)) in self.classes.iter().enumerate() {
+            json.push_str(if i == 0 { "\n  {" } else { ",\n  {" });
+            match outcome {
+                Outcome::Halted(vs) => json.push_str(&format!(
+                    "\"outcome\": \"halted\", \"stack\": {}",
+                    json_string(&vs.resolve(interner).to_string())
+                )),
+                Outcome::Failed => json.push_str("\"outcome\": \"failed\""),
+                Outcome::OutOfFuel => json.push_str("\"outcome\": \"out-of-fuel\""),
+            }
+            let exprs: Vec<String> = exprs
+                .iter()
+                .map(|e| json_string(&e.resolve(interner).to_string()))
+                .collect();
+            json.push_str(&format!(", \"exprs\": [{}]}}", exprs.join(", ")));
+        }
+        json.push_str("\n]}\n");
+        json
+    }
+
+    pub fn write_json(&self, interner: &Interner, path: &Path) -> Result<(), AtlasError> {
+        fs::write(path, self.to_json(interner)).map_err(|err| AtlasError::Io(err.to_string()))
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The sets of intrinsics a `Context` can be made with. Source parsed by
+//! `Context::parse_expr` and the like may only use the intrinsics of the
+//! context's basis, and the names of the intrinsics outside it are left
+//! free for functions. Expressions can be converted between bases, e.g.
+//!
+//! ```
+//! # use ucc::basis::Basis;
+//! # use ucc::core::Context;
+//! let mut ctx = Context::with_basis(Basis::Forth);
+//! let e = ctx.parse_expr("[a] dup").unwrap();
+//! assert!(ctx.parse_expr("[a] clone").is_err());
+//! let e = Basis::Standard.convert(&e).unwrap();
+//! assert_eq!(ctx.display(&e).to_string(), "[a] clone");
+//! ```
+//!
+//! Host intrinsics are in every basis. The interpreter, and the prelude,
+//! use the standard basis.
+
+use crate::core::{Context, Expr, FnDef, Interner, Intrinsic, Value, ValueStack};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Basis {
+    /// `swap`, `clone`, `drop`, `quote`, `compose`, and `apply`, with the
+    /// reflection intrinsics `defined?`, `body-of`, `define!`, and `evalq`
+    Standard,
+    /// `cake` and `k`, from which the standard six can be defined
+    Minimal,
+    /// `dup`, `drop`, `swap`, `over`, and `rot`, with `quote`, `compose`,
+    /// `apply`, and the reflection intrinsics
+    Forth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BasisError {
+    /// Source used an intrinsic outside the basis
+    NotInBasis(Intrinsic),
+    /// An intrinsic can't be defined in the basis, as the reflection
+    /// intrinsics can't in the minimal basis
+    Inexpressible(Intrinsic),
+}
+
+const STANDARD: [Intrinsic; 10] = [
+    Intrinsic::Swap,
+    Intrinsic::Clone,
+    Intrinsic::Drop,
+    Intrinsic::Quote,
+    Intrinsic::Compose,
+    Intrinsic::Apply,
+    Intrinsic::Defined,
+    Intrinsic::BodyOf,
+    Intrinsic::Define,
+    Intrinsic::EvalQ,
+];
+
+const MINIMAL: [Intrinsic; 2] = [Intrinsic::Cake, Intrinsic::K];
+
+const FORTH: [Intrinsic; 12] = [
+    Intrinsic::Dup,
+    Intrinsic::Drop,
+    Intrinsic::Swap,
+    Intrinsic::Over,
+    Intrinsic::Rot,
+    Intrinsic::Quote,
+    Intrinsic::Compose,
+    Intrinsic::Apply,
+    Intrinsic::Defined,
+    Intrinsic::BodyOf,
+    Intrinsic::Define,
+    Intrinsic::EvalQ,
+];
+
+/// The intrinsics outside the standard basis, which the parser reads as
+/// calls
+const NON_STANDARD: [(&str, Intrinsic); 5] = [
+    ("dup", Intrinsic::Dup),
+    ("over", Intrinsic::Over),
+    ("rot", Intrinsic::Rot),
+    ("cake", Intrinsic::Cake),
+    ("k", Intrinsic::K),
+];
+
+impl Basis {
+    pub fn intrinsics(&self) -> &'static [Intrinsic] {
+        match self {
+            Basis::Standard => &STANDARD,
+            Basis::Minimal => &MINIMAL,
+            Basis::Forth => &FORTH,
+        }
+    }
+
+    pub fn contains(&self, intr: &Intrinsic) -> bool {
+        matches!(intr, Intrinsic::Host(_)) || self.intrinsics().contains(intr)
+    }
+
+    /// Rewrite `e` to use only this basis' intrinsics, replacing the others
+    /// with their definitions in it
+    pub fn convert(&self, e: &Expr) -> Result<Expr, BasisError> {
+        let mut err = None;
+        let e = e.clone().fold(&mut |e| match e {
+            Expr::Intrinsic(intr) if !self.contains(&intr) => match self.define(intr) {
+                Ok(e) => e,
+                Err(e) => {
+                    err.get_or_insert(e);
+                    Expr::Intrinsic(intr)
+                }
+            },
+            e => e,
+        });
+        match err {
+            None => Ok(e),
+            Some(err) => Err(err),
+        }
+    }
+
+    /// The definition of `intr`, which isn't in this basis, in this basis
+    fn define(&self, intr: Intrinsic) -> Result<Expr, BasisError> {
+        let src = match (self, intr) {
+            // In terms of the standard basis
+            (_, Intrinsic::Dup) => "clone",
+            (_, Intrinsic::Over) => "[clone] swap quote compose apply swap",
+            (_, Intrinsic::Rot) => "quote swap quote swap compose swap quote compose apply",
+            (_, Intrinsic::Cake) => "swap quote over over swap compose rot rot compose",
+            (_, Intrinsic::K) => "swap drop apply",
+            (Basis::Forth, Intrinsic::Clone) => "dup",
+            // After Kerby's "The Theory of Concatenative Combinators"
+            (Basis::Minimal, Intrinsic::Drop) => "[] k",
+            (Basis::Minimal, Intrinsic::Apply) => "[[]] cake k k",
+            (Basis::Minimal, Intrinsic::Quote) => "[] cake [] k",
+            (Basis::Minimal, Intrinsic::Swap) => "[] cake [] k cake k",
+            (Basis::Minimal, Intrinsic::Clone) => "[] cake cake k cake k",
+            (Basis::Minimal, Intrinsic::Compose) => {
+                "[[[[]] cake k k] cake k [[]] cake k k] cake [] k cake [] k"
+            }
+            _ => return Err(BasisError::Inexpressible(intr)),
+        };
+        self.convert(&intrinsics(src))
+    }
+
+    /// Check that `e`, as parsed, uses only this basis' intrinsics, and
+    /// make intrinsics of its calls of their names.
+    pub(crate) fn adapt(&self, e: Expr, interner: &Interner) -> Result<Expr, BasisError> {
+        if *self == Basis::Standard {
+            return Ok(e);
+        }
+        let mut err = None;
+        let e = e.fold(&mut |e| match e {
+            Expr::Intrinsic(intr) if !self.contains(&intr) => {
+                err.get_or_insert(BasisError::NotInBasis(intr));
+                e
+            }
+            Expr::Call(sym) => {
+                let name = interner.resolve(&sym.0);
+                match NON_STANDARD.iter().find(|(n, _)| *n == name) {
+                    Some((_, intr)) if self.contains(intr) => Expr::Intrinsic(*intr),
+                    _ => e,
+                }
+            }
+            e => e,
+        });
+        match err {
+            None => Ok(e),
+            Some(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn adapt_value(&self, v: Value, interner: &Interner) -> Result<Value, BasisError> {
+        match v {
+            Value::Quote(e) => Ok(Value::Quote(Box::new(self.adapt(*e, interner)?))),
+            v => Ok(v),
+        }
+    }
+
+    pub(crate) fn adapt_value_stack(
+        &self,
+        vs: ValueStack,
+        interner: &Interner,
+    ) -> Result<ValueStack, BasisError> {
+        let vs: Result<_, _> =
+            vs.0.into_iter()
+                .map(|v| self.adapt_value(v, interner))
+                .collect();
+        Ok(ValueStack(vs?))
+    }
+
+    pub(crate) fn adapt_fn_def(
+        &self,
+        fn_def: FnDef,
+        interner: &Interner,
+    ) -> Result<FnDef, BasisError> {
+        let FnDef(sym, e) = fn_def;
+        Ok(FnDef(sym, self.adapt(e, interner)?))
+    }
+}
+
+/// Parse `src`, which has only intrinsics and brackets
+fn intrinsics(src: &str) -> Expr {
+    let mut stack = vec![vec![]];
+    let src = src.replace('[', " [ ").replace(']', " ] ");
+    for token in src.split_whitespace() {
+        match token {
+            "[" => stack.push(vec![]),
+            "]" => {
+                let es = stack.pop().unwrap();
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .push(Expr::Quote(Box::new(compose(es))));
+            }
+            name => {
+                let intr = STANDARD
+                    .iter()
+                    .chain(NON_STANDARD.iter().map(|(_, intr)| intr))
+                    .find(|intr| intr.to_string() == name)
+                    .unwrap_or_else(|| panic!("unknown intrinsic `{}`", name));
+                stack.last_mut().unwrap().push(Expr::Intrinsic(*intr));
+            }
+        }
+    }
+    compose(stack.pop().unwrap())
+}
+
+fn compose(mut es: Vec<Expr>) -> Expr {
+    if es.len() == 1 {
+        es.pop().unwrap()
+    } else {
+        Expr::Compose(es)
+    }
+}
+
+impl Context {
+    /// A context whose source may use only the intrinsics of `basis`
+    pub fn with_basis(basis: Basis) -> Self {
+        Context {
+            basis,
+            ..Context::default()
+        }
+    }
+
+    pub fn basis(&self) -> Basis {
+        self.basis
+    }
+}
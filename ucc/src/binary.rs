@@ -0,0 +1,431 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The `.uccb` binary format for definitions, which loads much faster than
+//! source. The version goes up with each tag added, and files of an older
+//! version are still read. All integers are LEB128 varints:
+//!
+//! ```text
+//! file    = "UCCB" version:u8 count:varint name* count:varint fn_def*
+//! name    = len:varint utf8-bytes
+//! fn_def  = name-index:varint expr
+//! expr    = 0x00 count:varint expr*      -- composition
+//!         | 0x01 expr                    -- quote
+//!         | 0x02 name-index:varint       -- call
+//!         | 0x03 name-index:varint       -- 'symbol
+//!         | 0x04 name-index:varint       -- host intrinsic
+//!         | 0x05 len:varint u8*          -- number, little-endian, with
+//!                                        -- the `bignum` feature
+//!         | 0x06 sign:u8 len:varint u8* len:varint u8*
+//!                                        -- numerator and denominator,
+//!                                        -- with the `rational` feature
+//!         | 0x07 expr expr               -- pair
+//!         | 0x08 count:varint (expr expr)*
+//!                                        -- map, keys and values
+//!         | 0x09 code-point:varint       -- character
+//!         | 0x0a len:varint utf8-bytes   -- string
+//!         | 0x10..=0x18                  -- swap .. define!
+//!         | 0x19..=0x1d                  -- dup, over, rot, cake, k
+//!         | 0x1e                         -- evalq
+//! ```
+
+use crate::core::{pushing, Context, Expr, FnDef, Intrinsic, Symbol, CORE};
+use crate::display::Resolve;
+use crate::interp::InterpItem;
+use crate::limits::LimitError;
+use crate::load::LoadError;
+use crate::map::ValueMap;
+use crate::pair::item_value;
+use crate::parse::InterpItemsParser;
+use crate::transaction::{DefineError, Validation};
+#[cfg(feature = "bignum")]
+use crate::uccb::TAG_NAT;
+#[cfg(feature = "rational")]
+use crate::uccb::TAG_RATIO;
+use crate::uccb::{
+    Writer, TAG_CALL, TAG_CHAR, TAG_COMPOSE, TAG_HOST, TAG_INTRINSIC, TAG_MAP, TAG_PAIR, TAG_QUOTE,
+    TAG_STR, TAG_SYMBOL,
+};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+pub use crate::uccb::{MAGIC, VERSION};
+
+/// How deeply expressions may nest in a file, so that a corrupt or hostile
+/// one can't overflow the stack while it is read, even on a test thread's
+/// stack in a debug build. Files nested more deeply aren't written either.
+pub const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    BadTag(u8),
+    BadName(usize),
+    BadUtf8,
+    BadVarint,
+    TooDeep,
+    Limit(LimitError),
+}
+
+/// The intrinsics named in `INTRINSIC_NAMES`, in the same order
+pub(crate) const INTRINSICS: [Intrinsic; 15] = [
+    Intrinsic::Swap,
+    Intrinsic::Clone,
+    Intrinsic::Drop,
+    Intrinsic::Quote,
+    Intrinsic::Compose,
+    Intrinsic::Apply,
+    Intrinsic::Defined,
+    Intrinsic::BodyOf,
+    Intrinsic::Define,
+    Intrinsic::Dup,
+    Intrinsic::Over,
+    Intrinsic::Rot,
+    Intrinsic::Cake,
+    Intrinsic::K,
+    Intrinsic::EvalQ,
+];
+#[derive(Default)]
+struct Encoder {
+    writer: Writer<Symbol>,
+    /// How many expressions enclose the one being written
+    depth: usize,
+    /// Whether an expression nested more deeply than `MAX_DEPTH`
+    too_deep: bool,
+}
+
+impl Encoder {
+    fn expr(&mut self, e: &Expr) {
+        if self.depth == MAX_DEPTH {
+            self.too_deep = true;
+            return;
+        }
+        self.depth += 1;
+        self.expr_at_depth(e);
+        self.depth -= 1;
+    }
+
+    fn expr_at_depth(&mut self, e: &Expr) {
+        match e {
+            Expr::Compose(es) => {
+                self.writer.body.push(TAG_COMPOSE);
+                self.writer.varint(es.len());
+                for e in es {
+                    self.expr(e);
+                }
+            }
+            Expr::Quote(e) => {
+                self.writer.body.push(TAG_QUOTE);
+                self.expr(e);
+            }
+            Expr::Call(sym) => {
+                self.writer.body.push(TAG_CALL);
+                self.writer.name(*sym);
+            }
+            Expr::Symbol(sym) => {
+                self.writer.body.push(TAG_SYMBOL);
+                self.writer.name(*sym);
+            }
+            Expr::Intrinsic(Intrinsic::Host(sym)) => {
+                self.writer.body.push(TAG_HOST);
+                self.writer.name(*sym);
+            }
+            #[cfg(feature = "bignum")]
+            Expr::Nat(n) => {
+                let bytes = n.to_bytes_le();
+                self.writer.body.push(TAG_NAT);
+                self.writer.varint(bytes.len());
+                self.writer.body.extend(bytes);
+            }
+            #[cfg(feature = "rational")]
+            Expr::Ratio(r) => {
+                let (sign, numer) = r.numer().to_bytes_le();
+                let (_, denom) = r.denom().to_bytes_le();
+                self.writer.body.push(TAG_RATIO);
+                self.writer
+                    .body
+                    .push((sign == num_bigint::Sign::Minus) as u8);
+                for bytes in [numer, denom] {
+                    self.writer.varint(bytes.len());
+                    self.writer.body.extend(bytes);
+                }
+            }
+            Expr::Pair(p) => {
+                self.writer.body.push(TAG_PAIR);
+                self.expr(&p.0);
+                self.expr(&p.1);
+            }
+            Expr::Char(c) => {
+                self.writer.body.push(TAG_CHAR);
+                self.writer.varint(*c as usize);
+            }
+            Expr::Str(s) => {
+                self.writer.body.push(TAG_STR);
+                self.writer.varint(s.len());
+                self.writer.body.extend_from_slice(s.as_bytes());
+            }
+            Expr::Map(m) => {
+                self.writer.body.push(TAG_MAP);
+                self.writer.varint(m.len());
+                for (k, v) in m.iter() {
+                    self.expr(&pushing(k.clone()));
+                    self.expr(&pushing(v.clone()));
+                }
+            }
+            Expr::Intrinsic(i) => {
+                let n = INTRINSICS.iter().position(|j| j == i).unwrap();
+                self.writer.body.push(TAG_INTRINSIC + n as u8);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    names: Vec<Symbol>,
+    /// How many expressions enclose the one being read
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        let (&b, rest) = self.bytes.split_first().ok_or(BinaryError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> Result<usize, BinaryError> {
+        let mut n = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            n |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+            if shift >= std::mem::size_of::<usize>() * 8 {
+                return Err(BinaryError::BadVarint);
+            }
+        }
+    }
+
+    /// A length, then that many bytes
+    fn bytes(&mut self) -> Result<&[u8], BinaryError> {
+        let len = self.varint()?;
+        if len > self.bytes.len() {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn symbol(&mut self) -> Result<Symbol, BinaryError> {
+        let i = self.varint()?;
+        self.names.get(i).copied().ok_or(BinaryError::BadName(i))
+    }
+
+    fn expr(&mut self) -> Result<Expr, BinaryError> {
+        if self.depth == MAX_DEPTH {
+            return Err(BinaryError::TooDeep);
+        }
+        self.depth += 1;
+        let e = self.expr_at_depth();
+        self.depth -= 1;
+        e
+    }
+
+    fn expr_at_depth(&mut self) -> Result<Expr, BinaryError> {
+        Ok(match self.byte()? {
+            TAG_COMPOSE => {
+                let len = self.varint()?;
+                // Each element is at least one byte, so a corrupt length
+                // can't allocate more than the input's size
+                let mut es = Vec::with_capacity(len.min(self.bytes.len()));
+                for _ in 0..len {
+                    es.push(self.expr()?);
+                }
+                Expr::Compose(es)
+            }
+            TAG_QUOTE => Expr::Quote(Box::new(self.expr()?)),
+            TAG_CALL => Expr::Call(self.symbol()?),
+            TAG_SYMBOL => Expr::Symbol(self.symbol()?),
+            TAG_HOST => Expr::Intrinsic(Intrinsic::Host(self.symbol()?)),
+            #[cfg(feature = "bignum")]
+            TAG_NAT => Expr::Nat(num_bigint::BigUint::from_bytes_le(self.bytes()?)),
+            #[cfg(feature = "rational")]
+            TAG_RATIO => {
+                use num_bigint::{BigInt, Sign};
+                let sign = match self.byte()? {
+                    0 => Sign::Plus,
+                    1 => Sign::Minus,
+                    tag => return Err(BinaryError::BadTag(tag)),
+                };
+                let numer = BigInt::from_bytes_le(sign, self.bytes()?);
+                let denom = BigInt::from_bytes_le(Sign::Plus, self.bytes()?);
+                if denom == BigInt::default() {
+                    return Err(BinaryError::BadTag(TAG_RATIO));
+                }
+                Expr::Ratio(num_rational::BigRational::new(numer, denom))
+            }
+            TAG_PAIR => {
+                let e1 = self.expr()?;
+                Expr::Pair(Box::new((e1, self.expr()?)))
+            }
+            TAG_CHAR => {
+                let code = self.varint()?;
+                let c = u32::try_from(code).ok().and_then(char::from_u32);
+                Expr::Char(c.ok_or(BinaryError::BadTag(TAG_CHAR))?)
+            }
+            TAG_STR => {
+                let s = std::str::from_utf8(self.bytes()?).map_err(|_| BinaryError::BadUtf8)?;
+                Expr::Str(s.to_owned())
+            }
+            TAG_MAP => {
+                let len = self.varint()?;
+                let mut m = ValueMap::new();
+                for _ in 0..len {
+                    let k = item_value(&self.expr()?);
+                    m.insert(k, item_value(&self.expr()?));
+                }
+                Expr::Map(m)
+            }
+            tag => match INTRINSICS.get(tag.wrapping_sub(TAG_INTRINSIC) as usize) {
+                Some(i) if tag >= TAG_INTRINSIC => Expr::Intrinsic(*i),
+                _ => return Err(BinaryError::BadTag(tag)),
+            },
+        })
+    }
+}
+
+impl Context {
+    /// Encode `fn_defs` in the `.uccb` format, or fail if an expression
+    /// nests more deeply than `MAX_DEPTH`, as it couldn't be read back.
+    pub fn encode_fn_defs(&self, fn_defs: &[FnDef]) -> Result<Vec<u8>, BinaryError> {
+        let mut encoder = Encoder::default();
+        encoder.writer.varint(fn_defs.len());
+        for FnDef(sym, e) in fn_defs {
+            encoder.writer.name(*sym);
+            encoder.expr(e);
+        }
+        if encoder.too_deep {
+            return Err(BinaryError::TooDeep);
+        }
+        Ok(encoder.writer.finish(|sym| self.interner.resolve(&sym.0)))
+    }
+
+    pub fn decode_fn_defs(&mut self, bytes: &[u8]) -> Result<Vec<FnDef>, BinaryError> {
+        let bytes = bytes
+            .strip_prefix(&MAGIC[..])
+            .ok_or(BinaryError::BadMagic)?;
+        let mut decoder = Decoder {
+            bytes,
+            names: vec![],
+            depth: 0,
+        };
+        match decoder.byte()? {
+            1..=VERSION => {}
+            version => return Err(BinaryError::UnsupportedVersion(version)),
+        }
+        for _ in 0..decoder.varint()? {
+            let len = decoder.varint()?;
+            if len > decoder.bytes.len() {
+                return Err(BinaryError::UnexpectedEnd);
+            }
+            let (name, rest) = decoder.bytes.split_at(len);
+            decoder.bytes = rest;
+            let name = std::str::from_utf8(name).map_err(|_| BinaryError::BadUtf8)?;
+            decoder
+                .names
+                .push(Symbol(self.interner.get_or_intern(name)));
+        }
+        let count = decoder.varint()?;
+        let mut fn_defs = Vec::with_capacity(count.min(decoder.bytes.len()));
+        for _ in 0..count {
+            let sym = decoder.symbol()?;
+            fn_defs.push(FnDef(sym, decoder.expr()?));
+        }
+        Ok(fn_defs)
+    }
+
+    /// Encode every top-level definition in the `.uccb` format.
+    pub fn export_binary(&self) -> Result<Vec<u8>, BinaryError> {
+        let fn_defs: Vec<FnDef> = self
+            .fn_defs()
+            .map(|(sym, e)| FnDef(*sym, e.clone()))
+            .collect();
+        self.encode_fn_defs(&fn_defs)
+    }
+
+    /// Define everything in `bytes`, or nothing if any of it goes over the
+    /// limits, returning the symbols defined.
+    pub fn import_binary(&mut self, bytes: &[u8]) -> Result<Vec<Symbol>, BinaryError> {
+        let fn_defs = self.decode_fn_defs(bytes)?;
+        let syms = fn_defs.iter().map(|fn_def| fn_def.0).collect();
+        self.define_all(fn_defs, Validation::Limits)
+            .map_err(|err| match err.errors.into_iter().next() {
+                Some(DefineError::Limit(_, err)) => BinaryError::Limit(err),
+                _ => unreachable!("only limits are checked"),
+            })?;
+        Ok(syms)
+    }
+
+    /// Define everything in `bytes` in the `core` namespace, as the prelude,
+    /// returning the symbols defined. Calls between the definitions are
+    /// qualified, so redefining one doesn't change the others.
+    pub fn import_prelude(&mut self, bytes: &[u8]) -> Result<Vec<Symbol>, BinaryError> {
+        let fn_defs = self.decode_fn_defs(bytes)?;
+        let syms = fn_defs.iter().map(|fn_def| fn_def.0).collect();
+        // Values are compressed to the definitions they are, whether built
+        // from them as written or as qualified (see `Context::compress`)
+        let exprs = Arc::make_mut(&mut self.exprs);
+        for FnDef(sym, e) in fn_defs.iter() {
+            exprs.insert(e.clone(), *sym);
+        }
+        let core = Symbol(self.interner.get_or_intern(CORE));
+        let version = env!("CARGO_PKG_VERSION").to_owned();
+        self.define_namespace(core, version, fn_defs);
+        let exprs = Arc::make_mut(&mut self.exprs);
+        for (sym, e) in self.namespaces[&core].fns.iter() {
+            exprs.insert(e.clone(), *sym);
+        }
+        Ok(syms)
+    }
+}
+
+/// Encode the definitions in the source `src` in the `.uccb` format. Any
+/// expressions or rules in `src` are not included.
+pub fn pack(src: &str) -> Result<Vec<u8>, LoadError> {
+    let mut ctx = Context::default();
+    let items = InterpItemsParser::new()
+        .parse(&mut ctx.interner, src)
+        .map_err(|err| LoadError::Parse(format!("{:?}", err)))?;
+    let fn_defs: Vec<FnDef> = items
+        .into_iter()
+        .filter_map(|item| match item {
+            InterpItem::FnDef(fn_def) => Some(fn_def),
+            _ => None,
+        })
+        .collect();
+    ctx.encode_fn_defs(&fn_defs).map_err(LoadError::Binary)
+}
+
+/// Decode `.uccb` definitions back to source, one definition per line.
+pub fn unpack(bytes: &[u8]) -> Result<String, BinaryError> {
+    let mut ctx = Context::default();
+    let mut src = String::new();
+    for FnDef(sym, e) in ctx.decode_fn_defs(bytes)? {
+        src.push_str(&format!(
+            "{{fn {} = {}}}\n",
+            sym.resolve(&ctx.interner),
+            e.resolve(&ctx.interner)
+        ));
+    }
+    Ok(src)
+}
@@ -0,0 +1,218 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Building an `Interp` from parts the embedder chooses: the clock `:bench`
+//! and the `time.` intrinsics read, the files `:load`, `:atlas`, and the
+//! settings file go through, the prelude, and the definition limits. Tests
+//! can supply a fake clock and in-memory files to make whole sessions
+//! deterministic, e.g.
+//!
+//! ```
+//! # use ucc::builder::*;
+//! # use std::time::Duration;
+//! struct Stopped;
+//!
+//! impl Clock for Stopped {
+//!     fn now(&self) -> Duration {
+//!         Duration::default()
+//!     }
+//! }
+//!
+//! let mut interp = InterpBuilder::new()
+//!     .clock(Stopped)
+//!     .prelude(Prelude::None)
+//!     .settings_path(None)
+//!     .build();
+//! let mut output = vec![];
+//! interp.interp_start(":bench [a] clone", &mut output).unwrap();
+//! while !interp.is_done() {
+//!     interp.interp_step(&mut output).unwrap();
+//! }
+//! assert_eq!(output, "⇓ ⟨[a] [a]⟩ \n2 steps in 0ns.\n".as_bytes());
+//! ```
+//!
+//! Bundles loaded by `:use`, plugins, and sources fetched by URL are still
+//! read directly, though a host can refuse plugins and URLs altogether.
+
+use crate::bundle::default_bundle_path;
+use crate::interp::Interp;
+use crate::limits::DefinitionLimits;
+use crate::settings::default_settings_path;
+use crate::shared::SharedPrelude;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+pub trait Clock {
+    /// The time since some fixed instant, which never goes backwards
+    fn now(&self) -> Duration;
+}
+
+/// The system's monotonic clock
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SystemClock(Instant);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock(Instant::now())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A clock that never moves, as `Instant` panics on `wasm32`, where hosts
+/// must supply their own, e.g. from `performance.now()`
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct SystemClock;
+
+#[cfg(target_arch = "wasm32")]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        Duration::default()
+    }
+}
+
+pub trait Files {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Replace the contents of the file at `path`, creating it and any
+    /// missing directories
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+/// The files on disk
+pub struct SystemFiles;
+
+impl Files for SystemFiles {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    }
+}
+
+/// The functions an interpreter starts with
+pub enum Prelude {
+    None,
+    /// Its own copy of the builtin prelude
+    Builtin,
+    /// A prelude shared with other interpreters
+    Shared(SharedPrelude),
+}
+
+pub struct InterpBuilder {
+    clock: Rc<dyn Clock>,
+    files: Rc<dyn Files>,
+    prelude: Prelude,
+    limits: DefinitionLimits,
+    bundle_path: Vec<PathBuf>,
+    settings_path: Option<PathBuf>,
+    allow_plugins: bool,
+    allow_net: bool,
+}
+
+impl Default for InterpBuilder {
+    fn default() -> Self {
+        InterpBuilder {
+            clock: Rc::new(SystemClock::default()),
+            files: Rc::new(SystemFiles),
+            prelude: Prelude::Builtin,
+            limits: DefinitionLimits::default(),
+            bundle_path: default_bundle_path(),
+            settings_path: default_settings_path(),
+            allow_plugins: true,
+            allow_net: true,
+        }
+    }
+}
+
+impl InterpBuilder {
+    /// A builder for an interpreter like `Interp::default()`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    pub fn files<F: Files + 'static>(mut self, files: F) -> Self {
+        self.files = Rc::new(files);
+        self
+    }
+
+    /// The prelude, which also sets whether `:reset` restores it
+    pub fn prelude(mut self, prelude: Prelude) -> Self {
+        self.prelude = prelude;
+        self
+    }
+
+    pub fn limits(mut self, limits: DefinitionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The directories searched by `:use`
+    pub fn bundle_path(mut self, bundle_path: Vec<PathBuf>) -> Self {
+        self.bundle_path = bundle_path;
+        self
+    }
+
+    /// The file used by `:save-settings` and `Interp::load_settings`, or
+    /// `None` for no settings file
+    pub fn settings_path(mut self, settings_path: Option<PathBuf>) -> Self {
+        self.settings_path = settings_path;
+        self
+    }
+
+    /// Whether `:plugin load` may load libraries
+    pub fn allow_plugins(mut self, allow: bool) -> Self {
+        self.allow_plugins = allow;
+        self
+    }
+
+    /// Whether sources may be fetched by URL
+    pub fn allow_net(mut self, allow: bool) -> Self {
+        self.allow_net = allow;
+        self
+    }
+
+    pub fn build(self) -> Interp {
+        let mut interp = match &self.prelude {
+            Prelude::Shared(shared) => Interp::with_prelude(shared),
+            Prelude::Builtin | Prelude::None => Interp::default(),
+        };
+        if let Prelude::None = self.prelude {
+            interp.settings_mut().set("prelude", "off").unwrap();
+            interp.drop_prelude();
+        }
+        interp.set_clock(self.clock);
+        interp.files = self.files;
+        interp.set_definition_limits(self.limits);
+        interp.set_bundle_path(self.bundle_path);
+        interp.set_settings_path(self.settings_path);
+        interp.set_allow_plugins(self.allow_plugins);
+        interp.set_allow_net(self.allow_net);
+        interp
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Library bundles: a directory named `<name>@<version>` holding a
+//! `bundle.manifest` and the `.ucc` sources it lists, e.g.
+//!
+//! ```text
+//! -- combinators@1.2/bundle.manifest
+//! name = combinators
+//! version = 1.2
+//! sources = base.ucc numerals.ucc
+//! ```
+
+use crate::builder::SystemFiles;
+use crate::core::{pushing, Context, Expr, FnDef, Map, Namespace, Symbol, Value};
+use crate::interp::InterpItem;
+use crate::load::{load_source, LoadError};
+use crate::pair::item_value;
+use crate::parse::InterpItemsParser;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub const MANIFEST_FILE: &str = "bundle.manifest";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub sources: Vec<String>,
+}
+
+impl Manifest {
+    pub fn parse(src: &str) -> Result<Manifest, LoadError> {
+        let mut name = None;
+        let mut version = None;
+        let mut sources = None;
+        for line in src.lines() {
+            let line = line.split("--").next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                LoadError::BadManifest(format!("expected `key = value`: {}", line))
+            })?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => name = Some(value.to_owned()),
+                "version" => version = Some(value.to_owned()),
+                "sources" => sources = Some(value.split_whitespace().map(str::to_owned).collect()),
+                key => return Err(LoadError::BadManifest(format!("unknown key `{}`", key))),
+            }
+        }
+        let missing = |key: &str| LoadError::BadManifest(format!("missing `{}`", key));
+        Ok(Manifest {
+            name: name.ok_or_else(|| missing("name"))?,
+            version: version.ok_or_else(|| missing("version"))?,
+            sources: sources.ok_or_else(|| missing("sources"))?,
+        })
+    }
+}
+
+/// The directories searched for bundles: those listed in `UCC_PATH`, or
+/// `ucc_libs` in the working directory.
+pub fn default_bundle_path() -> Vec<PathBuf> {
+    match env::var_os("UCC_PATH") {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from("ucc_libs")],
+    }
+}
+
+/// Find the bundle `name@version` in `search_path`, returning its directory
+/// and manifest.
+fn find_bundle(
+    search_path: &[PathBuf],
+    name: &str,
+    version: &str,
+) -> Result<(PathBuf, Manifest), LoadError> {
+    for dir in search_path {
+        let bundle_dir = dir.join(format!("{}@{}", name, version));
+        let manifest_path = bundle_dir.join(MANIFEST_FILE);
+        if !manifest_path.is_file() {
+            continue;
+        }
+        let src =
+            fs::read_to_string(&manifest_path).map_err(|err| LoadError::Io(err.to_string()))?;
+        let manifest = Manifest::parse(&src)?;
+        if manifest.name != name || manifest.version != version {
+            return Err(LoadError::BadManifest(format!(
+                "{} declares `{}@{}`",
+                manifest_path.display(),
+                manifest.name,
+                manifest.version
+            )));
+        }
+        return Ok((bundle_dir, manifest));
+    }
+    Err(LoadError::BundleNotFound(format!("{}@{}", name, version)))
+}
+
+impl Context {
+    /// Load the bundle `name@version` into the namespace `name`, returning the
+    /// previously loaded version, if any.
+    pub fn use_bundle(
+        &mut self,
+        search_path: &[PathBuf],
+        name: &str,
+        version: &str,
+    ) -> Result<Option<String>, LoadError> {
+        let (bundle_dir, manifest) = find_bundle(search_path, name, version)?;
+        let mut fn_defs = vec![];
+        for src_path in manifest.sources.iter().map(|src| bundle_dir.join(src)) {
+            let src = load_source(&src_path.to_string_lossy(), None, &SystemFiles)?;
+            let is = InterpItemsParser::new()
+                .parse(&mut self.interner, &src)
+                .map_err(|err| LoadError::Parse(format!("{}: {:?}", src_path.display(), err)))?;
+            for i in is {
+                match i {
+                    InterpItem::FnDef(fn_def) => fn_defs.push(fn_def),
+                    _ => {
+                        return Err(LoadError::Parse(format!(
+                            "{}: bundles may only contain definitions",
+                            src_path.display()
+                        )))
+                    }
+                }
+            }
+        }
+        let ns = Symbol(self.interner.get_or_intern(name));
+        Ok(self.define_namespace(ns, manifest.version, fn_defs))
+    }
+
+    /// Define `fn_defs` in the namespace `ns`, replacing any previously loaded
+    /// version of it, which is returned. Calls between the definitions are
+    /// qualified so that they resolve from outside the namespace.
+    pub fn define_namespace(
+        &mut self,
+        ns: Symbol,
+        version: String,
+        fn_defs: Vec<FnDef>,
+    ) -> Option<String> {
+        let ns_name = self.interner.resolve(&ns.0).to_owned();
+        let mut qualified = Map::default();
+        for FnDef(sym, _) in fn_defs.iter() {
+            let name = format!("{}.{}", ns_name, self.interner.resolve(&sym.0));
+            qualified.insert(*sym, Symbol(self.interner.get_or_intern(name)));
+        }
+        let fns = fn_defs
+            .into_iter()
+            .map(|FnDef(sym, mut e)| {
+                qualify(&mut e, &qualified);
+                (sym, e)
+            })
+            .collect();
+        Arc::make_mut(&mut self.namespaces)
+            .insert(ns, Namespace { version, fns })
+            .map(|old| old.version)
+    }
+
+    /// The definitions to make for `new_sym` to be a copy of `sym`, or
+    /// `None` if `sym` isn't defined. If `deep`, the definitions of `sym`'s
+    /// namespace that it calls, however indirectly, are copied too, as
+    /// `<new_sym>.<name>`, and the copies call each other instead of the
+    /// originals, so either may be redefined without affecting the other.
+    pub fn copy_defs(&mut self, sym: Symbol, new_sym: Symbol, deep: bool) -> Option<Vec<FnDef>> {
+        let e = self.lookup_fn(&sym)?.clone();
+        if !deep {
+            return Some(vec![FnDef(new_sym, e)]);
+        }
+        // Helpers are only copied from namespaces
+        let prefix = self
+            .interner
+            .resolve(&sym.0)
+            .rsplit_once('.')
+            .map(|(ns, _)| ns)
+            .filter(|ns| {
+                let ns = self.interner.get(ns);
+                ns.is_some_and(|ns| self.namespaces.contains_key(&Symbol(ns)))
+            })
+            .map(|ns| format!("{}.", ns));
+        let new_name = self.interner.resolve(&new_sym.0).to_owned();
+        let mut renamed = Map::default();
+        renamed.insert(sym, new_sym);
+        let mut copies = vec![(sym, e)];
+        let mut i = 0;
+        while i < copies.len() {
+            let mut calls = vec![];
+            copies[i].1.visit(&mut |e| {
+                if let Expr::Call(sym) = e {
+                    calls.push(*sym);
+                }
+            });
+            for call in calls {
+                if renamed.contains_key(&call) {
+                    continue;
+                }
+                let name = self.interner.resolve(&call.0);
+                let name = match prefix.as_deref().and_then(|p| name.strip_prefix(p)) {
+                    Some(name) => format!("{}.{}", new_name, name),
+                    None => continue,
+                };
+                if let Some(e) = self.lookup_fn(&call) {
+                    copies.push((call, e.clone()));
+                    renamed.insert(call, Symbol(self.interner.get_or_intern(name)));
+                }
+            }
+            i += 1;
+        }
+        // Helpers first, so that none is called before it is defined
+        Some(
+            copies
+                .into_iter()
+                .rev()
+                .map(|(sym, mut e)| {
+                    qualify(&mut e, &renamed);
+                    FnDef(renamed[&sym], e)
+                })
+                .collect(),
+        )
+    }
+}
+
+fn qualify(e: &mut Expr, qualified: &Map<Symbol, Symbol>) {
+    match e {
+        Expr::Intrinsic(_) | Expr::Char(_) | Expr::Str(_) => {}
+        #[cfg(feature = "bignum")]
+        Expr::Nat(_) => {}
+        #[cfg(feature = "rational")]
+        Expr::Ratio(_) => {}
+        Expr::Call(sym) | Expr::Symbol(sym) => {
+            if let Some(q) = qualified.get(sym) {
+                *sym = *q;
+            }
+        }
+        Expr::Quote(e) => qualify(e, qualified),
+        Expr::Pair(p) => {
+            qualify(&mut p.0, qualified);
+            qualify(&mut p.1, qualified);
+        }
+        Expr::Map(m) => {
+            let qualify_value = |v: &Value| {
+                let mut e = pushing(v.clone());
+                qualify(&mut e, qualified);
+                item_value(&e)
+            };
+            *m = m
+                .iter()
+                .map(|(k, v)| (qualify_value(k), qualify_value(v)))
+                .collect();
+        }
+        Expr::Compose(es) => {
+            for e in es.iter_mut() {
+                qualify(e, qualified);
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! What this build of ucc supports, for frontends to adapt to the optional
+//! features compiled in rather than find out by a command failing.
+
+use crate::atlas::json_string;
+use std::fmt;
+
+/// The optional features of the crate, as named in its manifest: `bignum`
+/// for natural number values, `net` to fetch bundles for `:use`,
+/// `persistent-stack` for value stacks copied in constant time, `plugins`
+/// for `:plugin load`, `rational` for ratio values, `regex` for
+/// `regex.match`, `tracing` for spans and events, and `vm` to test the
+/// bytecode VM against the interpreter
+pub const FEATURES: [&str; 8] = [
+    "bignum",
+    "net",
+    "persistent-stack",
+    "plugins",
+    "rational",
+    "regex",
+    "tracing",
+    "vm",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub version: &'static str,
+    /// The features of `FEATURES` compiled in, in that order
+    pub features: Vec<&'static str>,
+}
+
+/// The capabilities of this build
+pub fn capabilities() -> Capabilities {
+    let compiled = [
+        cfg!(feature = "bignum"),
+        cfg!(feature = "net"),
+        cfg!(feature = "persistent-stack"),
+        cfg!(feature = "plugins"),
+        cfg!(feature = "rational"),
+        cfg!(feature = "regex"),
+        cfg!(feature = "tracing"),
+        cfg!(feature = "vm"),
+    ];
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        features: FEATURES
+            .iter()
+            .zip(compiled)
+            .filter(|(_, compiled)| *compiled)
+            .map(|(feature, _)| *feature)
+            .collect(),
+    }
+}
+
+impl Capabilities {
+    /// Whether `feature`, one of `FEATURES`, is compiled in
+    pub fn has(&self, feature: &str) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// As a JSON object, `{"version": "0.1.0", "features": ["net"]}`
+    pub fn to_json(&self) -> String {
+        let features: Vec<String> = self.features.iter().map(|f| json_string(f)).collect();
+        format!(
+            "{{\"version\": {}, \"features\": [{}]}}",
+            json_string(self.version),
+            features.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ucc v{}", self.version)?;
+        if self.features.is_empty() {
+            write!(f, ", with no optional features")
+        } else {
+            write!(f, ", with features: {}", self.features.join(", "))
+        }
+    }
+}
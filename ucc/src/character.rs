@@ -0,0 +1,119 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Characters and strings, for programs that process text. A literal such
+//! as `'a'` pushes the character, with `'\n'`, `'\r'`, `'\t'`, `'\''` and
+//! `'\\'` for the characters that can't be written between the quotes, and
+//! one such as `"abc"` pushes the string, which can't hold a `"`. Every
+//! context has the intrinsics
+//!
+//! - `char.eq`, `⟨'a' 'a'⟩ char.eq ⟶ ⟨[swap drop]⟩`, pushing `true` or
+//!   `false`, as `nat.eq` does,
+//! - `char.lt`, comparing code points,
+//! - `chars`, `⟨"ab"⟩ chars ⟶ ⟨['a' 'b']⟩`, the list of a string's
+//!   characters, a quotation pushing them as `map.keys` pushes keys,
+//! - `uncons_str`, `⟨"ab"⟩ uncons_str ⟶ ⟨'a' "b"⟩`, a string's first
+//!   character and the rest, failing for the empty string.
+//!
+//! A character literal must hold one character, so `'a` is still the
+//! symbol `a`.
+
+use crate::core::{boolean, list, Context, EvalError, Expr, Value, ValueStack};
+use std::fmt;
+
+/// The character a literal such as `'a'` or `'\n'` holds, for the parser
+pub(crate) fn char_literal(literal: &str) -> char {
+    let mut chars = literal[1..literal.len() - 1].chars();
+    match chars.next().unwrap() {
+        '\\' => match chars.next().unwrap() {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            c => c,
+        },
+        c => c,
+    }
+}
+
+/// The length of the character literal, such as `'['` or `'\n'`, that `s`
+/// starts with, if it starts with one rather than a symbol
+pub(crate) fn char_literal_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\'') => return None,
+        (_, '\\') => {
+            chars.next()?;
+        }
+        _ => {}
+    }
+    match chars.next()? {
+        (i, '\'') => Some(i + 1),
+        _ => None,
+    }
+}
+
+/// Write `c` as a literal that parses back to it
+pub(crate) fn fmt_char(c: char, f: &mut fmt::Formatter) -> fmt::Result {
+    match c {
+        '\n' => f.write_str("'\\n'"),
+        '\r' => f.write_str("'\\r'"),
+        '\t' => f.write_str("'\\t'"),
+        '\'' => f.write_str("'\\''"),
+        '\\' => f.write_str("'\\\\'"),
+        c => write!(f, "'{}'", c),
+    }
+}
+
+/// Pop two characters, the top one second
+fn pop_chars(vs: &mut ValueStack) -> Result<(char, char), EvalError> {
+    let mut pop = || match vs.pop() {
+        Some(Value::Char(c)) => Ok(c),
+        _ => Err(EvalError::Host("expected a character".to_owned())),
+    };
+    let b = pop()?;
+    let a = pop()?;
+    Ok((a, b))
+}
+
+/// Pop a string, or fail
+pub(crate) fn pop_str(vs: &mut ValueStack) -> Result<String, EvalError> {
+    match vs.pop() {
+        Some(Value::Str(s)) => Ok(s),
+        _ => Err(EvalError::Host("expected a string".to_owned())),
+    }
+}
+
+impl Context {
+    /// Register the `char.` intrinsics, `chars` and `uncons_str`, as every
+    /// context does
+    pub(crate) fn register_char_intrinsics(&mut self) {
+        self.register_intrinsic("char.eq", 2, |vs| {
+            let (a, b) = pop_chars(vs)?;
+            vs.push(boolean(a == b));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("char.lt", 2, |vs| {
+            let (a, b) = pop_chars(vs)?;
+            vs.push(boolean(a < b));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("chars", 1, |vs| {
+            let s = pop_str(vs)?;
+            vs.push(list(s.chars().map(Value::Char)));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("uncons_str", 1, |vs| {
+            let s = pop_str(vs)?;
+            let mut chars = s.chars();
+            let c = chars
+                .next()
+                .ok_or_else(|| EvalError::Host("empty string".to_owned()))?;
+            vs.push(Value::Char(c));
+            vs.push(Value::Str(chars.as_str().to_owned()));
+            Ok(Expr::default())
+        });
+    }
+}
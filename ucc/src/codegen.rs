@@ -0,0 +1,464 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Compilation of definitions to a standalone Rust module, so that hot
+//! combinator libraries can run as native code. Each definition becomes a
+//! function on a `Vec<Value>` stack, with the intrinsics as calls into a
+//! small runtime at the top of the module, and quotations as lists of ops
+//! that `apply` runs.
+//!
+//! ```
+//! let src = ucc::codegen::rust("{fn swap2 = swap swap}").unwrap();
+//! assert!(src.contains("/// `swap2`\npub fn f0(s: &mut Vec<Value>) -> Result<(), Error> {"));
+//! ```
+//!
+//! The generated module has no dependencies. Its `call` runs a function by
+//! name, and its values display as they do in the interpreter. Calls are
+//! native calls, so recursion is limited by the native stack. The
+//! reflection intrinsics and host intrinsics can't be compiled.
+
+use crate::core::{Context, Expr, Intrinsic, Map, Symbol};
+use crate::interp::InterpItem;
+use crate::parse::InterpItemsParser;
+use crate::shared::SharedPrelude;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    Parse(String),
+    /// A definition uses an intrinsic that can't be compiled
+    Unsupported(String),
+}
+
+/// The runtime every generated module starts with
+const RUNTIME: &str = r#"#![allow(dead_code)]
+
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Quote(Rc<Vec<Op>>),
+    /// A quotation that is the body of the function at an index into
+    /// `NAMES`, shown as its name
+    Call(usize, Rc<Vec<Op>>),
+    /// An index into `NAMES`
+    Symbol(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Swap,
+    Clone,
+    Drop,
+    Quote,
+    Compose,
+    Apply,
+    Dup,
+    Over,
+    Rot,
+    Cake,
+    K,
+    /// A call of the function at an index into `NAMES`
+    Call(usize),
+    Push(Value),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    TooFewValues { available: usize, expected: usize },
+    UndefinedFn(String),
+}
+
+type Func = fn(&mut Vec<Value>) -> Result<(), Error>;
+
+fn need(s: &[Value], expected: usize) -> Result<(), Error> {
+    if s.len() < expected {
+        Err(Error::TooFewValues {
+            available: s.len(),
+            expected,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn unquote(v: Value) -> Rc<Vec<Op>> {
+    match v {
+        Value::Quote(ops) | Value::Call(_, ops) => ops,
+        // Symbols unquote to a call of the function they name
+        Value::Symbol(i) => Rc::new(vec![Op::Call(i)]),
+    }
+}
+
+fn concat(ops1: &[Op], ops2: &[Op]) -> Rc<Vec<Op>> {
+    Rc::new(ops1.iter().chain(ops2).cloned().collect())
+}
+
+pub fn swap(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let v = s.remove(s.len() - 2);
+    s.push(v);
+    Ok(())
+}
+
+pub fn clone(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    s.push(s[s.len() - 1].clone());
+    Ok(())
+}
+
+pub fn drop(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    s.pop();
+    Ok(())
+}
+
+pub fn quote(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    let v = s.pop().unwrap();
+    s.push(Value::Quote(Rc::new(vec![Op::Push(v)])));
+    Ok(())
+}
+
+pub fn compose(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let ops2 = unquote(s.pop().unwrap());
+    let ops1 = unquote(s.pop().unwrap());
+    s.push(Value::Quote(concat(&ops1, &ops2)));
+    Ok(())
+}
+
+pub fn apply(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    let ops = unquote(s.pop().unwrap());
+    run(s, &ops)
+}
+
+pub fn over(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    s.push(s[s.len() - 2].clone());
+    Ok(())
+}
+
+pub fn rot(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 3)?;
+    let v = s.remove(s.len() - 3);
+    s.push(v);
+    Ok(())
+}
+
+pub fn cake(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let ops2 = unquote(s.pop().unwrap());
+    let push1 = [Op::Push(s.pop().unwrap())];
+    s.push(Value::Quote(concat(&push1, &ops2)));
+    s.push(Value::Quote(concat(&ops2, &push1)));
+    Ok(())
+}
+
+pub fn k(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let ops = unquote(s.pop().unwrap());
+    s.pop();
+    run(s, &ops)
+}
+
+/// Run the ops of a quotation
+pub fn run(s: &mut Vec<Value>, ops: &[Op]) -> Result<(), Error> {
+    for op in ops {
+        match op {
+            Op::Swap => swap(s)?,
+            Op::Clone | Op::Dup => clone(s)?,
+            Op::Drop => drop(s)?,
+            Op::Quote => quote(s)?,
+            Op::Compose => compose(s)?,
+            Op::Apply => apply(s)?,
+            Op::Over => over(s)?,
+            Op::Rot => rot(s)?,
+            Op::Cake => cake(s)?,
+            Op::K => k(s)?,
+            Op::Call(i) => call_index(*i, s)?,
+            Op::Push(v) => s.push(v.clone()),
+        }
+    }
+    Ok(())
+}
+
+fn call_index(i: usize, s: &mut Vec<Value>) -> Result<(), Error> {
+    match NAMES[i] {
+        (_, Some(f)) => f(s),
+        (name, None) => Err(Error::UndefinedFn(name.to_owned())),
+    }
+}
+
+/// Run the function named `name`, or else, as in the interpreter, the
+/// prelude's `core.<name>`
+pub fn call(name: &str, s: &mut Vec<Value>) -> Result<(), Error> {
+    let position = |name: &str| NAMES.iter().position(|(n, _)| *n == name);
+    match position(name).or_else(|| position(&format!("core.{}", name))) {
+        Some(i) => call_index(i, s),
+        None => Err(Error::UndefinedFn(name.to_owned())),
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Quote(ops) => {
+                write!(f, "[")?;
+                for (i, op) in ops.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", op)?;
+                }
+                write!(f, "]")
+            }
+            Value::Call(i, _) => write!(f, "{}", NAMES[*i].0),
+            Value::Symbol(i) => write!(f, "'{}", NAMES[*i].0),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Swap => write!(f, "swap"),
+            Op::Clone => write!(f, "clone"),
+            Op::Drop => write!(f, "drop"),
+            Op::Quote => write!(f, "quote"),
+            Op::Compose => write!(f, "compose"),
+            Op::Apply => write!(f, "apply"),
+            Op::Dup => write!(f, "dup"),
+            Op::Over => write!(f, "over"),
+            Op::Rot => write!(f, "rot"),
+            Op::Cake => write!(f, "cake"),
+            Op::K => write!(f, "k"),
+            Op::Call(i) => write!(f, "{}", NAMES[*i].0),
+            Op::Push(v) => write!(f, "{}", v),
+        }
+    }
+}
+"#;
+
+/// The names a module uses, shared by the backends
+pub(crate) struct Codegen<'a> {
+    pub(crate) ctx: &'a Context,
+    /// Every name used, in the order first used, indexing `NAMES`
+    pub(crate) names: Vec<Symbol>,
+    indices: Map<Symbol, usize>,
+}
+
+impl<'a> Codegen<'a> {
+    /// Start with the names `syms`, in order
+    pub(crate) fn new(ctx: &'a Context, syms: &[Symbol]) -> Self {
+        let mut codegen = Codegen {
+            ctx,
+            names: vec![],
+            indices: Map::default(),
+        };
+        for sym in syms {
+            codegen.index(*sym);
+        }
+        codegen
+    }
+
+    pub(crate) fn index(&mut self, sym: Symbol) -> usize {
+        let names = &mut self.names;
+        *self.indices.entry(sym).or_insert_with(|| {
+            names.push(sym);
+            names.len() - 1
+        })
+    }
+
+    /// The statements that evaluate `e`
+    fn stmts(&mut self, e: &Expr, out: &mut String) -> Result<(), CodegenError> {
+        match e {
+            Expr::Intrinsic(intr) => {
+                let f = match intr {
+                    Intrinsic::Dup => "clone".to_owned(),
+                    intr => runtime_name(*intr)?.to_lowercase(),
+                };
+                writeln!(out, "    {}(s)?;", f).unwrap();
+            }
+            Expr::Call(sym) => {
+                let i = self.index(*sym);
+                if self.ctx.lookup_fn(sym).is_some() {
+                    writeln!(out, "    f{}(s)?;", i).unwrap();
+                } else {
+                    // As in the interpreter, calling it is an error
+                    writeln!(out, "    call_index({}, s)?;", i).unwrap();
+                }
+            }
+            Expr::Symbol(sym) => {
+                writeln!(out, "    s.push(Value::Symbol({}));", self.index(*sym)).unwrap();
+            }
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            Expr::Pair(_) => return Err(CodegenError::Unsupported("pairs".to_owned())),
+            Expr::Map(_) => return Err(CodegenError::Unsupported("maps".to_owned())),
+            Expr::Char(_) => return Err(CodegenError::Unsupported("characters".to_owned())),
+            Expr::Str(_) => return Err(CodegenError::Unsupported("strings".to_owned())),
+            Expr::Quote(e) => {
+                let mut ops = vec![];
+                self.ops(e, &mut ops)?;
+                writeln!(out, "    s.push({});", quote(&ops)).unwrap();
+            }
+            Expr::Compose(es) => {
+                for e in es {
+                    self.stmts(e, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `Op` constructors of a quotation of `e`
+    fn ops(&mut self, e: &Expr, ops: &mut Vec<String>) -> Result<(), CodegenError> {
+        match e {
+            Expr::Intrinsic(intr) => ops.push(format!("Op::{}", runtime_name(*intr)?)),
+            Expr::Call(sym) => ops.push(format!("Op::Call({})", self.index(*sym))),
+            Expr::Symbol(sym) => {
+                ops.push(format!("Op::Push(Value::Symbol({}))", self.index(*sym)));
+            }
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            Expr::Pair(_) => return Err(CodegenError::Unsupported("pairs".to_owned())),
+            Expr::Map(_) => return Err(CodegenError::Unsupported("maps".to_owned())),
+            Expr::Char(_) => return Err(CodegenError::Unsupported("characters".to_owned())),
+            Expr::Str(_) => return Err(CodegenError::Unsupported("strings".to_owned())),
+            Expr::Quote(e) => {
+                let mut quoted = vec![];
+                self.ops(e, &mut quoted)?;
+                ops.push(format!("Op::Push({})", quote(&quoted)));
+            }
+            Expr::Compose(es) => {
+                for e in es {
+                    self.ops(e, ops)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The name of the `Op` for `intr`
+pub(crate) fn runtime_name(intr: Intrinsic) -> Result<&'static str, CodegenError> {
+    match intr {
+        Intrinsic::Swap => Ok("Swap"),
+        Intrinsic::Clone => Ok("Clone"),
+        Intrinsic::Drop => Ok("Drop"),
+        Intrinsic::Quote => Ok("Quote"),
+        Intrinsic::Compose => Ok("Compose"),
+        Intrinsic::Apply => Ok("Apply"),
+        Intrinsic::Dup => Ok("Dup"),
+        Intrinsic::Over => Ok("Over"),
+        Intrinsic::Rot => Ok("Rot"),
+        Intrinsic::Cake => Ok("Cake"),
+        Intrinsic::K => Ok("K"),
+        Intrinsic::Defined | Intrinsic::BodyOf | Intrinsic::Define | Intrinsic::EvalQ => {
+            Err(CodegenError::Unsupported(intr.to_string()))
+        }
+        Intrinsic::Host(_) => Err(CodegenError::Unsupported("host intrinsics".to_owned())),
+    }
+}
+
+fn quote(ops: &[String]) -> String {
+    format!("Value::Quote(Rc::new(vec![{}]))", ops.join(", "))
+}
+
+impl Context {
+    /// Compile the definitions of `syms`, and of the functions they use, to
+    /// a Rust module. Calls of undefined functions are compiled to errors,
+    /// as they are in the interpreter.
+    pub fn codegen(&self, syms: &[Symbol]) -> Result<String, CodegenError> {
+        let mut codegen = Codegen::new(self, syms);
+        let mut fns = String::new();
+        // Names are added as the functions using them are compiled
+        let mut i = 0;
+        while i < codegen.names.len() {
+            let sym = codegen.names[i];
+            if let Some(body) = self.lookup_fn(&sym) {
+                let name = self.interner.resolve(&sym.0);
+                writeln!(fns, "\n/// `{}`", name).unwrap();
+                writeln!(
+                    fns,
+                    "pub fn f{}(s: &mut Vec<Value>) -> Result<(), Error> {{",
+                    i
+                )
+                .unwrap();
+                match body {
+                    // As in the interpreter, these push their name
+                    Expr::Quote(e) => {
+                        let mut ops = vec![];
+                        codegen.ops(e, &mut ops)?;
+                        writeln!(
+                            fns,
+                            "    s.push(Value::Call({}, Rc::new(vec![{}])));",
+                            i,
+                            ops.join(", ")
+                        )
+                        .unwrap();
+                    }
+                    body => codegen.stmts(body, &mut fns)?,
+                }
+                writeln!(fns, "    Ok(())\n}}").unwrap();
+            }
+            i += 1;
+        }
+
+        let mut out = String::from("// Generated by `ucci codegen`\n\n");
+        out.push_str(RUNTIME);
+        writeln!(
+            out,
+            "\n/// Every name used, with its function if it is defined\npub static NAMES: [(&str, Option<Func>); {}] = [",
+            codegen.names.len()
+        )
+        .unwrap();
+        for (i, sym) in codegen.names.iter().enumerate() {
+            let name = self.interner.resolve(&sym.0);
+            if self.lookup_fn(sym).is_some() {
+                writeln!(out, "    ({:?}, Some(f{})),", name, i).unwrap();
+            } else {
+                writeln!(out, "    ({:?}, None),", name).unwrap();
+            }
+        }
+        out.push_str("];\n");
+        out.push_str(&fns);
+        Ok(out)
+    }
+}
+
+/// Compile the definitions in the source `lib`, and the prelude functions
+/// they use, to a Rust module. Expressions and rules in `lib` are left out.
+pub fn rust(lib: &str) -> Result<String, CodegenError> {
+    let (ctx, syms) = load(lib)?;
+    ctx.codegen(&syms)
+}
+
+/// A context with the prelude and the definitions in `lib`, and the
+/// symbols they define
+pub(crate) fn load(lib: &str) -> Result<(Context, Vec<Symbol>), CodegenError> {
+    let prelude = SharedPrelude::default();
+    let mut ctx = Context::with_prelude(&prelude);
+    let items = InterpItemsParser::new()
+        .parse(&mut ctx.interner, lib)
+        .map_err(|err| CodegenError::Parse(format!("{:?}", err)))?;
+    let mut syms = vec![];
+    for item in items {
+        if let InterpItem::FnDef(fn_def) = item {
+            syms.push(fn_def.0);
+            ctx.define_fn(fn_def)
+                .map_err(|err| CodegenError::Parse(format!("{:?}", err)))?;
+        }
+    }
+    Ok((ctx, syms))
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The hand-written config file read at startup. Every top-level key but
+//! `load` is a setting, and `load` lists files to load before the first
+//! prompt, e.g.
+//!
+//! ```text
+//! # ~/.config/ucc/config.toml
+//! prelude = false
+//! theme = "ascii"
+//! show-steps = 10
+//! load = ["~/ucc/numerals.ucc"]
+//! ```
+
+use crate::settings::{config_dir, SettingError, SettingValue, Settings};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Settings in the order they appear
+    pub settings: Vec<(String, SettingValue)>,
+    /// Files to load, with a leading `~/` expanded
+    pub load: Vec<String>,
+}
+
+impl Config {
+    pub fn parse(src: &str) -> Result<Config, SettingError> {
+        let bad = |msg: String| SettingError::BadSettingsFile(msg);
+        let table = match src.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err(bad("expected a table".to_owned())),
+            Err(err) => return Err(bad(err.to_string())),
+        };
+        let mut config = Config::default();
+        for (key, value) in table {
+            let value = match (key.as_str(), value) {
+                ("load", toml::Value::Array(paths)) => {
+                    for path in paths {
+                        match path {
+                            toml::Value::String(path) => config.load.push(expand_home(&path)),
+                            _ => return Err(bad("expected `load` to list strings".to_owned())),
+                        }
+                    }
+                    continue;
+                }
+                (_, toml::Value::Boolean(b)) => SettingValue::Bool(b),
+                (_, toml::Value::Integer(n)) if n >= 0 => SettingValue::Int(n as usize),
+                (_, toml::Value::String(s)) => SettingValue::Str(s),
+                (key, value) => {
+                    return Err(bad(format!("unexpected value for `{}`: {}", key, value)))
+                }
+            };
+            config.settings.push((key, value));
+        }
+        Ok(config)
+    }
+
+    /// Read the config file at `path`, or the empty config if there is none.
+    pub fn load(path: &Path) -> Result<Config, SettingError> {
+        match fs::read_to_string(path) {
+            Ok(src) => Config::parse(&src),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(SettingError::Io(err.to_string())),
+        }
+    }
+
+    /// Apply the config's settings, stopping at the first invalid one.
+    pub fn apply_settings(&self, settings: &mut Settings) -> Result<(), SettingError> {
+        for (name, value) in self.settings.iter() {
+            match value {
+                // Strings are parsed as if typed, so `show-steps = "off"` works
+                SettingValue::Str(text) => settings.set(name, text)?,
+                value => settings.set_value(name, value.clone())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_owned(),
+    }
+}
+
+/// The config file: `ucc/config.toml` in `XDG_CONFIG_HOME`, or in `~/.config`.
+pub fn default_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join(CONFIG_FILE))
+}
@@ -0,0 +1,141 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Names that look alike. Names are ASCII, so input using a look-alike
+//! from another script, like Cyrillic `о` for `o`, or a fullwidth form, is
+//! normalized to ASCII before parsing, rather than failing to parse. Among
+//! ASCII names, those with the same skeleton, after mapping look-alikes
+//! like `1` and `l`, or `rn` and `m`, to one spelling, are confusable, in
+//! the spirit of Unicode TR39.
+
+use crate::character::char_literal_len;
+
+/// Characters from other scripts that look like an ASCII character
+const LOOKALIKES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('в', 'B'),
+    ('е', 'e'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ѕ', 's'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('һ', 'h'),
+    ('ԁ', 'd'),
+    ('ԛ', 'q'),
+    ('ԝ', 'w'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    ('Ѕ', 'S'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    // Greek
+    ('ο', 'o'),
+    ('ν', 'v'),
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    // Punctuation
+    ('ı', 'i'),
+    ('‐', '-'),
+    ('‑', '-'),
+    ('−', '-'),
+    ('‘', '\''),
+    ('’', '\''),
+    ('ʼ', '\''),
+    ('“', '"'),
+    ('”', '"'),
+];
+
+/// The ASCII character `c` looks like, if it isn't ASCII
+fn lookalike(c: char) -> Option<char> {
+    match c {
+        // Fullwidth forms
+        '\u{ff01}'..='\u{ff5e}' => std::char::from_u32(c as u32 - 0xff01 + 0x21),
+        '\u{3000}' => Some(' '),
+        c => LOOKALIKES
+            .iter()
+            .find(|(lookalike, _)| *lookalike == c)
+            .map(|(_, ascii)| *ascii),
+    }
+}
+
+/// `input` with look-alikes of ASCII characters replaced by them, outside
+/// of strings, character literals and comments, and the characters
+/// replaced, in the order they first appear.
+pub fn normalize(input: &str) -> (String, Vec<(char, char)>) {
+    let mut normalized = String::with_capacity(input.len());
+    let mut replaced = vec![];
+    let mut in_string = false;
+    let mut in_comment = false;
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        // Character literals are kept as written, as strings are
+        if c == '\'' && !in_string && !in_comment {
+            if let Some(len) = char_literal_len(&input[i..]) {
+                normalized.push_str(&input[i..i + len]);
+                while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+                continue;
+            }
+        }
+        let c = match lookalike(c) {
+            Some(ascii) if !in_string && !in_comment => {
+                if !replaced.contains(&(c, ascii)) {
+                    replaced.push((c, ascii));
+                }
+                ascii
+            }
+            _ => c,
+        };
+        match c {
+            '"' if !in_comment => in_string = !in_string,
+            '-' if !in_string && chars.peek().map(|&(_, c)| c) == Some('-') => in_comment = true,
+            '\n' | '\r' => in_comment = false,
+            _ => {}
+        }
+        normalized.push(c);
+    }
+    (normalized, replaced)
+}
+
+/// The spelling `name` shares with the names it could be mistaken for
+pub fn skeleton(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '1' | 'I' => 'l',
+            '0' => 'O',
+            c => c,
+        })
+        .collect();
+    name.replace("rn", "m")
+        .replace("vv", "w")
+        .replace("cl", "d")
+}
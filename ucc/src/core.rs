@@ -4,10 +4,27 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-pub(crate) use lasso::Rodeo as Interner;
+use crate::basis::Basis;
+use crate::diagnostics::Warning;
+use crate::hooks::Hooks;
+use crate::host::HostIntrinsic;
+use crate::limits::{DefinitionLimits, LimitError};
+use crate::locals::Scope;
+use crate::map::ValueMap;
+use crate::pair::pair_value;
+use crate::rewrite::Rule;
+use crate::shared::SharedPrelude;
+use lasso::{Rodeo, Spur, ThreadedRodeo};
+#[cfg(feature = "bignum")]
+use num_bigint::BigUint;
+#[cfg(feature = "rational")]
+use num_rational::BigRational;
 use std::hash::Hash;
+use std::sync::Arc;
 
 pub(crate) type Map<K, V> = fxhash::FxHashMap<K, V>;
+/// An insertion-ordered map, for anything whose iteration order is observable
+pub(crate) type OrderedMap<K, V> = indexmap::IndexMap<K, V, fxhash::FxBuildHasher>;
 
 #[macro_export]
 macro_rules! map {
@@ -18,18 +35,125 @@ macro_rules! map {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
-pub struct Symbol(pub(crate) lasso::Spur);
+pub struct Symbol(pub(crate) Spur);
+
+/// The names of symbols, either owned by one context and its forks, copied
+/// on write, or shared by every context made from a `SharedPrelude`
+#[derive(Clone)]
+pub enum Interner {
+    Local(Arc<Rodeo>),
+    Shared(Arc<ThreadedRodeo>),
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Interner::Local(Arc::default())
+    }
+}
+
+/// `rodeo`, copied first if a fork shares it. A `Rodeo` can't be cloned,
+/// so the copy interns its names again, in order, to give them the same
+/// keys.
+fn rodeo_mut(rodeo: &mut Arc<Rodeo>) -> &mut Rodeo {
+    if Arc::get_mut(rodeo).is_none() {
+        let mut copy = Rodeo::new();
+        for (key, name) in rodeo.iter() {
+            let copied = copy.get_or_intern(name);
+            debug_assert_eq!(copied, key);
+        }
+        *rodeo = Arc::new(copy);
+    }
+    Arc::get_mut(rodeo).unwrap()
+}
+
+impl Interner {
+    pub(crate) fn get_or_intern<T: AsRef<str>>(&mut self, val: T) -> Spur {
+        match self {
+            Interner::Local(rodeo) => match Arc::get_mut(rodeo) {
+                Some(rodeo) => rodeo.get_or_intern(val),
+                // Only copied if the name is new
+                None => match rodeo.get(&val) {
+                    Some(key) => key,
+                    None => rodeo_mut(rodeo).get_or_intern(val),
+                },
+            },
+            Interner::Shared(rodeo) => rodeo.get_or_intern(val),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_or_intern_static(&mut self, string: &'static str) -> Spur {
+        match self {
+            Interner::Local(rodeo) => rodeo_mut(rodeo).get_or_intern_static(string),
+            Interner::Shared(rodeo) => rodeo.get_or_intern_static(string),
+        }
+    }
+
+    pub(crate) fn get<T: AsRef<str>>(&self, val: T) -> Option<Spur> {
+        match self {
+            Interner::Local(rodeo) => rodeo.get(val),
+            Interner::Shared(rodeo) => rodeo.get(val),
+        }
+    }
+
+    pub(crate) fn resolve(&self, key: &Spur) -> &str {
+        match self {
+            Interner::Local(rodeo) => rodeo.resolve(key),
+            Interner::Shared(rodeo) => rodeo.resolve(key),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Interner::Local(rodeo) => rodeo.len(),
+            Interner::Shared(rodeo) => rodeo.len(),
+        }
+    }
+
+    pub(crate) fn strings(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Interner::Local(rodeo) => Box::new(rodeo.strings()),
+            Interner::Shared(rodeo) => Box::new(rodeo.strings()),
+        }
+    }
+}
 
 ////////////
 // Syntax //
 ////////////
 
-/// Expressions
+/// Expressions. Features such as `bignum` add variants, so matches outside
+/// this crate need a wildcard arm.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Expr {
     Intrinsic(Intrinsic),
     Call(Symbol),
+    Symbol(Symbol),
     Quote(Box<Expr>),
+    /// A natural number literal, which pushes itself
+    #[cfg(feature = "bignum")]
+    Nat(BigUint),
+    /// A rational number literal, which pushes itself
+    #[cfg(feature = "rational")]
+    Ratio(BigRational),
+    /// A pair literal, `(v1, v2)`, which pushes itself. Each item is an
+    /// expression that pushes one value, as `pushing` makes; see
+    /// `crate::pair`
+    Pair(Box<(Expr, Expr)>),
+    /// A map literal, `#{k => v, ...}`, which pushes itself; see
+    /// `crate::map`
+    Map(ValueMap),
+    /// A character literal, `'a'`, which pushes itself; see
+    /// `crate::character`
+    Char(char),
+    /// A string literal, `"abc"`, which pushes itself; see
+    /// `crate::character`
+    Str(String),
+    /// A composition. Evaluation takes items from the front and splices
+    /// expansions in place, reusing the `Vec`'s buffer, so stepping through
+    /// one allocates only when an expansion outgrows it; see the
+    /// `allocations` benchmark.
     Compose(Vec<Expr>),
 }
 
@@ -41,6 +165,24 @@ pub enum Intrinsic {
     Quote,
     Compose,
     Apply,
+    Defined,
+    BodyOf,
+    Define,
+    /// `⟨[defs] [e]⟩ evalq ⟶ ⟨[vs]⟩`, evaluating `e` on an empty stack with
+    /// only the definitions `defs`; see `crate::evalq`
+    EvalQ,
+    /// `clone`, as spelled in the Forth basis
+    Dup,
+    /// `⟨v1 v2⟩ over ⟶ ⟨v1 v2 v1⟩`
+    Over,
+    /// `⟨v1 v2 v3⟩ rot ⟶ ⟨v2 v3 v1⟩`
+    Rot,
+    /// `⟨[e1] [e2]⟩ cake ⟶ ⟨[[e1] e2] [e2 [e1]]⟩`
+    Cake,
+    /// `⟨[e1] [e2]⟩ k ⟶ ⟨⟩ e2`
+    K,
+    /// An intrinsic registered by the host with `Context::register_intrinsic`
+    Host(Symbol),
 }
 
 impl Default for Expr {
@@ -49,60 +191,306 @@ impl Default for Expr {
     }
 }
 
+impl Expr {
+    /// The leftmost non-composition subexpression, which is the next to be
+    /// evaluated
+    pub fn head(&self) -> &Expr {
+        match self {
+            Expr::Compose(es) if !es.is_empty() => es[0].head(),
+            e => e,
+        }
+    }
+
+    /// The number of elements remaining to be evaluated, counting any
+    /// nested compositions at the head as flattened
+    pub(crate) fn pending_len(&self) -> usize {
+        match self {
+            Expr::Compose(es) if es.is_empty() => 0,
+            Expr::Compose(es) => es[0].pending_len() + es.len() - 1,
+            _ => 1,
+        }
+    }
+}
+
 ///////////////
 // Semantics //
 ///////////////
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Values, which, as with `Expr`, features add variants to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Value {
     Call(Symbol),
+    Symbol(Symbol),
     Quote(Box<Expr>),
+    #[cfg(feature = "bignum")]
+    Nat(BigUint),
+    #[cfg(feature = "rational")]
+    Ratio(BigRational),
+    Pair(Box<(Value, Value)>),
+    Map(ValueMap),
+    Char(char),
+    Str(String),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
-pub struct ValueStack(pub(crate) Vec<Value>);
+/// The values of a `ValueStack`, bottom first: a `Vec`, or with the
+/// `persistent-stack` feature, a `Stack`, which is copied in constant time
+#[cfg(not(feature = "persistent-stack"))]
+pub(crate) type Values = Vec<Value>;
+#[cfg(feature = "persistent-stack")]
+pub(crate) type Values = crate::stack::Stack<Value>;
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ValueStack(pub(crate) Values);
+
+impl From<Vec<Value>> for ValueStack {
+    fn from(vs: Vec<Value>) -> Self {
+        ValueStack(vs.into_iter().collect())
+    }
+}
+
+impl ValueStack {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, v: Value) {
+        self.0.push(v)
+    }
+
+    pub fn pop(&mut self) -> Option<Value> {
+        self.0.pop()
+    }
+}
+
+/// Definitions, rules and symbols behind an `Arc` are shared with forks of
+/// the context (see `fork`), and copied by whichever changes them first.
 pub struct Context {
     pub(crate) interner: Interner,
-    pub(crate) fns: Map<Symbol, Expr>,
-    pub(crate) exprs: Map<Expr, Symbol>,
+    /// Definitions shared with other contexts, which those in `fns` shadow
+    pub(crate) shared: Option<SharedPrelude>,
+    pub(crate) fns: Arc<OrderedMap<Symbol, Expr>>,
+    /// Scopes of local definitions, innermost last, which shadow `fns`
+    pub(crate) locals: Vec<Scope>,
+    pub(crate) namespaces: Arc<OrderedMap<Symbol, Namespace>>,
+    pub(crate) exprs: Arc<Map<Expr, Symbol>>,
+    pub(crate) rules: Arc<Vec<Rule>>,
+    pub(crate) consecutive_rewrites: usize,
+    pub(crate) allow_define: bool,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) host_intrinsics: Map<Symbol, HostIntrinsic>,
+    pub(crate) hooks: Hooks,
+    pub(crate) limits: DefinitionLimits,
+    /// The intrinsics source may use
+    pub(crate) basis: Basis,
+    /// Whether definitions are folded as they are made (see `prefold`)
+    pub(crate) prefold: bool,
+    /// What `it` pushes while nothing defines `it` (see `set_it`)
+    pub(crate) it: Option<Value>,
+}
+
+/// The namespace of the prelude, which unqualified calls fall back to when
+/// nothing else defines them
+pub const CORE: &str = "core";
+
+/// Definitions loaded from a versioned bundle, keyed by unqualified name
+#[derive(Clone)]
+pub(crate) struct Namespace {
+    pub(crate) version: String,
+    pub(crate) fns: OrderedMap<Symbol, Expr>,
 }
 
+/// The chain of `Expr::Call` expansions in progress during an evaluation,
+/// outermost first. Each frame records how many elements followed the call
+/// when it was expanded, so it is finished once the expression is that short.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExpansionTrace(pub(crate) Vec<(Symbol, usize)>);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum EvalError {
-    TooFewValues { available: usize, expected: usize },
+    TooFewValues {
+        available: usize,
+        expected: usize,
+    },
     UndefinedFn(Symbol),
+    RewriteLimitExceeded,
+    ExpectedSymbol,
+    /// A value that isn't a quotation, such as a number, was unquoted
+    ExpectedQuote,
+    DefineDisabled,
+    /// Raised by a host intrinsic
+    Host(String),
+    /// `define!` went over the definition limits, or evaluation made a
+    /// value over the limit on body size
+    Limit(LimitError),
+    /// The definitions given to `evalq` weren't `['f1 [e1] 'f2 [e2] ...]`
+    ExpectedDefinitions,
+    /// The program given to `evalq` took over `EVALQ_FUEL` steps
+    OutOfFuel,
+    /// The program given to `evalq` failed
+    Sandbox(Box<EvalError>),
 }
 
 impl Default for Context {
     fn default() -> Self {
         let interner = Interner::default();
-        Context {
+        let mut ctx = Context {
             interner,
-            fns: Map::default(),
-            exprs: Map::default(),
-        }
+            shared: None,
+            fns: Arc::default(),
+            locals: Vec::default(),
+            namespaces: Arc::default(),
+            exprs: Arc::default(),
+            rules: Arc::default(),
+            consecutive_rewrites: 0,
+            allow_define: true,
+            warnings: Vec::default(),
+            host_intrinsics: Map::default(),
+            hooks: Hooks::default(),
+            limits: DefinitionLimits::default(),
+            basis: Basis::Standard,
+            prefold: false,
+            it: None,
+        };
+        ctx.register_pair_intrinsics();
+        ctx.register_map_intrinsics();
+        ctx.register_char_intrinsics();
+        #[cfg(feature = "bignum")]
+        ctx.register_nat_intrinsics();
+        #[cfg(feature = "rational")]
+        ctx.register_ratio_intrinsics();
+        #[cfg(feature = "regex")]
+        ctx.register_regex_intrinsics();
+        ctx
     }
 }
 
 impl Context {
-    fn unquote_value(&self, v: Value) -> Result<Expr, EvalError> {
+    /// Look up the definition of `sym`, resolving qualified `ns.name`
+    /// symbols in the namespace `ns`, and unqualified ones not otherwise
+    /// defined in the `core` namespace of the prelude.
+    pub(crate) fn lookup_fn(&self, sym: &Symbol) -> Option<&Expr> {
+        if !self.locals.is_empty() {
+            if let Some(e) = self.lookup_local(sym) {
+                return Some(e);
+            }
+        }
+        if let Some(e) = self.fns.get(sym) {
+            return Some(e);
+        }
+        if let Some(e) = self.shared.as_ref().and_then(|shared| shared.fns.get(sym)) {
+            return Some(e);
+        }
+        let name = self.interner.resolve(&sym.0);
+        let (ns, name) = name.rsplit_once('.').unwrap_or((CORE, name));
+        if ns == CORE {
+            return self.prelude_fns()?.get(&Symbol(self.interner.get(name)?));
+        }
+        let ns = self.namespaces.get(&Symbol(self.interner.get(ns)?))?;
+        ns.fns.get(&Symbol(self.interner.get(name)?))
+    }
+
+    /// The definitions of the prelude, shared or the context's own, by
+    /// unqualified name
+    pub(crate) fn prelude_fns(&self) -> Option<&OrderedMap<Symbol, Expr>> {
+        match &self.shared {
+            Some(shared) => Some(&shared.fns),
+            None => Some(&self.namespaces.get(&Symbol(self.interner.get(CORE)?))?.fns),
+        }
+    }
+
+    /// Have calls to `it` push `v`, unless `it` is defined, or fail as
+    /// undefined if `None`. An interpreter sets it to the top value after
+    /// each evaluation, for the next to refer to, without defining it.
+    pub fn set_it(&mut self, v: Option<Value>) {
+        self.it = v;
+    }
+
+    fn is_it(&self, sym: &Symbol) -> bool {
+        self.interner.resolve(&sym.0) == "it"
+    }
+
+    pub(crate) fn unquote_value(&self, v: Value) -> Result<Expr, EvalError> {
         match v {
             Value::Call(sym) => {
-                if let Some(e) = self.fns.get(&sym) {
+                if let Some(e) = self.lookup_fn(&sym) {
                     match e {
                         Expr::Quote(e) => Ok((**e).clone()),
-                        _ => panic!(),
+                        // Redefined since as something else
+                        _ => Err(EvalError::ExpectedQuote),
                     }
                 } else {
                     Err(EvalError::UndefinedFn(sym))
                 }
             }
+            // Symbols unquote to a call of the function they name
+            Value::Symbol(sym) => Ok(Expr::Call(sym)),
             Value::Quote(e) => Ok(*e),
+            #[cfg(feature = "bignum")]
+            Value::Nat(_) => Err(EvalError::ExpectedQuote),
+            #[cfg(feature = "rational")]
+            Value::Ratio(_) => Err(EvalError::ExpectedQuote),
+            Value::Pair(_) | Value::Map(_) | Value::Char(_) | Value::Str(_) => {
+                Err(EvalError::ExpectedQuote)
+            }
         }
     }
 
+    fn unquote_symbol(&self, v: Value) -> Result<Symbol, EvalError> {
+        match self.unquote_value(v)? {
+            Expr::Call(sym) => Ok(sym),
+            _ => Err(EvalError::ExpectedSymbol),
+        }
+    }
+
+    /// Enable or disable the `define!` intrinsic, which lets programs add
+    /// definitions at runtime.
+    pub fn set_allow_define(&mut self, allow_define: bool) {
+        self.allow_define = allow_define;
+    }
+
+    /// Take one step of evaluating `e` on `vs`. What is left to evaluate
+    /// stays in `e` rather than on the native stack, so recursion of any
+    /// depth, as through `fix`, runs in constant native stack.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(values = vs.0.len()), err)
+    )]
     pub fn small_step(&mut self, vs: &mut ValueStack, e: &mut Expr) -> Result<(), EvalError> {
+        self.step_hooks(vs, e);
+        let rule = self.hooked_rule(e);
+        let result = self.step(vs, e);
+        match &result {
+            Ok(()) => self.rule_hooks(rule, vs, e),
+            Err(err) => self.error_hooks(err, vs, e),
+        }
+        result
+    }
+
+    pub(crate) fn step(&mut self, vs: &mut ValueStack, e: &mut Expr) -> Result<(), EvalError> {
+        // Only intrinsics, the host's too, make values larger than those
+        // they take, which the limit on body size holds them to
+        let makes_value = match e {
+            Expr::Intrinsic(_) => true,
+            Expr::Call(sym) => self.host_intrinsic(sym).is_some(),
+            _ => false,
+        };
+        self.step_unchecked(vs, e)?;
+        if makes_value {
+            self.check_value_size(vs)?;
+        }
+        Ok(())
+    }
+
+    fn step_unchecked(&mut self, vs: &mut ValueStack, e: &mut Expr) -> Result<(), EvalError> {
+        if !self.rules.is_empty() && self.rewrite(e)? {
+            return Ok(());
+        }
         match e {
             Expr::Intrinsic(intr) => match intr {
                 Intrinsic::Swap => {
@@ -118,7 +506,7 @@ impl Context {
                         Ok(())
                     }
                 }
-                Intrinsic::Clone => {
+                Intrinsic::Clone | Intrinsic::Dup => {
                     if vs.0.len() < 1 {
                         Err(EvalError::TooFewValues {
                             available: vs.0.len(),
@@ -149,11 +537,7 @@ impl Context {
                             expected: 1,
                         })
                     } else {
-                        let v = vs.0.pop().unwrap();
-                        let qe = match v {
-                            Value::Call(sym) => Expr::Call(sym),
-                            Value::Quote(e) => Expr::Quote(e),
-                        };
+                        let qe = pushing(vs.0.pop().unwrap());
                         vs.0.push(Value::Quote(Box::new(qe)));
                         *e = Expr::default();
                         Ok(())
@@ -168,27 +552,7 @@ impl Context {
                     } else {
                         let e2 = self.unquote_value(vs.0.pop().unwrap())?;
                         let e1 = self.unquote_value(vs.0.pop().unwrap())?;
-                        let mut new_es = match (e1, e2) {
-                            (Expr::Compose(mut e1s), Expr::Compose(mut e2s)) => {
-                                e1s.extend(e2s.drain(..));
-                                e1s
-                            }
-                            (Expr::Compose(mut e1s), e2) => {
-                                e1s.push(e2);
-                                e1s
-                            }
-                            (e1, Expr::Compose(mut e2s)) => {
-                                e2s.insert(0, e1);
-                                e2s
-                            }
-                            (e1, e2) => vec![e1, e2],
-                        };
-                        let new_e = if new_es.len() == 1 {
-                            new_es.drain(..).next().unwrap()
-                        } else {
-                            Expr::Compose(new_es)
-                        };
-                        vs.0.push(Value::Quote(Box::new(new_e)));
+                        vs.0.push(Value::Quote(Box::new(compose(e1, e2))));
                         *e = Expr::default();
                         Ok(())
                     }
@@ -205,9 +569,126 @@ impl Context {
                         Ok(())
                     }
                 }
+                Intrinsic::Defined => {
+                    if vs.0.is_empty() {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 1,
+                        })
+                    } else {
+                        let sym = self.unquote_symbol(vs.0.pop().unwrap())?;
+                        // Church booleans, compressed to `true`/`false` if defined
+                        vs.0.push(boolean(self.lookup_fn(&sym).is_some()));
+                        *e = Expr::default();
+                        Ok(())
+                    }
+                }
+                Intrinsic::BodyOf => {
+                    if vs.0.is_empty() {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 1,
+                        })
+                    } else {
+                        let sym = self.unquote_symbol(vs.0.pop().unwrap())?;
+                        if let Some(body) = self.lookup_fn(&sym) {
+                            vs.0.push(Value::Quote(Box::new(body.clone())));
+                            *e = Expr::default();
+                            Ok(())
+                        } else {
+                            Err(EvalError::UndefinedFn(sym))
+                        }
+                    }
+                }
+                Intrinsic::Define => {
+                    if !self.allow_define {
+                        Err(EvalError::DefineDisabled)
+                    } else if vs.0.len() < 2 {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 2,
+                        })
+                    } else {
+                        let body_v = vs.0.pop().unwrap();
+                        let sym_v = vs.0.pop().unwrap();
+                        let body = self.unquote_value(body_v.clone())?;
+                        let sym = self.unquote_symbol(sym_v.clone())?;
+                        self.expand_calls(vs, sym);
+                        if let Err(err) = self.define_fn(FnDef(sym, body)) {
+                            // Leave the values, so the error is shown with
+                            // what caused it
+                            vs.0.push(sym_v);
+                            vs.0.push(body_v);
+                            return Err(EvalError::Limit(err));
+                        }
+                        *e = Expr::default();
+                        Ok(())
+                    }
+                }
+                Intrinsic::EvalQ => self.evalq_step(vs, e),
+                Intrinsic::Over => {
+                    if vs.0.len() < 2 {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 2,
+                        })
+                    } else {
+                        vs.0.push(vs.0[vs.0.len() - 2].clone());
+                        *e = Expr::default();
+                        Ok(())
+                    }
+                }
+                Intrinsic::Rot => {
+                    if vs.0.len() < 3 {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 3,
+                        })
+                    } else {
+                        let v = vs.0.remove(vs.0.len() - 3);
+                        vs.0.push(v);
+                        *e = Expr::default();
+                        Ok(())
+                    }
+                }
+                Intrinsic::Cake => {
+                    if vs.0.len() < 2 {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 2,
+                        })
+                    } else {
+                        let e2 = self.unquote_value(vs.0.pop().unwrap())?;
+                        let qe1 = pushing(vs.0.pop().unwrap());
+                        let e1 = compose(qe1.clone(), e2.clone());
+                        let e2 = compose(e2, qe1);
+                        vs.0.push(Value::Quote(Box::new(e1)));
+                        vs.0.push(Value::Quote(Box::new(e2)));
+                        *e = Expr::default();
+                        Ok(())
+                    }
+                }
+                Intrinsic::K => {
+                    if vs.0.len() < 2 {
+                        Err(EvalError::TooFewValues {
+                            available: vs.0.len(),
+                            expected: 2,
+                        })
+                    } else {
+                        let e2 = self.unquote_value(vs.0.pop().unwrap())?;
+                        vs.0.pop();
+                        *e = e2;
+                        Ok(())
+                    }
+                }
+                Intrinsic::Host(sym) => {
+                    let sym = *sym;
+                    self.host_step(sym, vs, e)
+                }
             },
             Expr::Call(sym) => {
-                if let Some(new_e) = self.fns.get(sym) {
+                self.call_hooks(*sym, vs);
+                if let Some(new_e) = self.lookup_fn(sym) {
                     match new_e {
                         Expr::Quote(_) => {
                             vs.0.push(Value::Call(*sym));
@@ -219,35 +700,75 @@ impl Context {
                             Ok(())
                         }
                     }
+                } else if self.host_intrinsic(sym).is_some() {
+                    let sym = *sym;
+                    self.host_step(sym, vs, e)
+                } else if let Some(v) = self.it.as_ref().filter(|_| self.is_it(sym)) {
+                    vs.0.push(v.clone());
+                    *e = Expr::default();
+                    Ok(())
                 } else {
                     Err(EvalError::UndefinedFn(*sym))
                 }
             }
-            Expr::Quote(qe) => {
-                vs.0.push(Value::Quote(qe.clone()));
+            Expr::Symbol(sym) => {
+                vs.0.push(Value::Symbol(*sym));
+                *e = Expr::default();
+                Ok(())
+            }
+            #[cfg(feature = "bignum")]
+            Expr::Nat(n) => {
+                vs.0.push(Value::Nat(n.clone()));
+                *e = Expr::default();
+                Ok(())
+            }
+            #[cfg(feature = "rational")]
+            Expr::Ratio(r) => {
+                vs.0.push(Value::Ratio(r.clone()));
+                *e = Expr::default();
+                Ok(())
+            }
+            Expr::Pair(p) => {
+                vs.0.push(pair_value(p));
+                *e = Expr::default();
+                Ok(())
+            }
+            Expr::Map(m) => {
+                vs.0.push(Value::Map(m.clone()));
+                *e = Expr::default();
+                Ok(())
+            }
+            Expr::Char(c) => {
+                vs.0.push(Value::Char(*c));
                 *e = Expr::default();
                 Ok(())
             }
+            Expr::Str(s) => {
+                vs.0.push(Value::Str(std::mem::take(s)));
+                *e = Expr::default();
+                Ok(())
+            }
+            Expr::Quote(_) => {
+                if let Expr::Quote(qe) = std::mem::take(e) {
+                    vs.0.push(Value::Quote(qe));
+                }
+                Ok(())
+            }
             Expr::Compose(ref mut es) => {
                 let es_len = es.len();
                 if es_len == 0 {
                     Ok(())
                 } else {
                     let e1 = es.first_mut().unwrap();
-                    self.small_step(vs, e1)?;
-                    match e1 {
-                        Expr::Compose(e1s) => {
-                            let mut new_es = Vec::with_capacity(e1s.len() + es_len - 1);
-                            new_es.extend(e1s.drain(..));
-                            new_es.extend(es.drain(1..));
-                            let new_e = if new_es.len() == 1 {
-                                new_es.drain(..).next().unwrap()
-                            } else {
-                                Expr::Compose(new_es)
-                            };
-                            *e = new_e;
+                    self.step(vs, e1)?;
+                    if let Expr::Compose(e1s) = e1 {
+                        // Spliced into the composition's own buffer, which
+                        // has room for them unless `e1` expanded
+                        let e1s = std::mem::take(e1s);
+                        es.splice(0..1, e1s);
+                        if es.len() == 1 {
+                            *e = es.pop().unwrap();
                         }
-                        _ => {}
                     }
                     Ok(())
                 }
@@ -255,14 +776,67 @@ impl Context {
         }
     }
 
+    /// Like `small_step`, but keeps `trace` up to date with the function
+    /// expansions in progress, so that errors can be reported in context.
+    pub fn small_step_traced(
+        &mut self,
+        vs: &mut ValueStack,
+        e: &mut Expr,
+        trace: &mut ExpansionTrace,
+    ) -> Result<(), EvalError> {
+        self.step_hooks(vs, e);
+        let rule = self.hooked_rule(e);
+        let result = self.traced_step(vs, e, trace);
+        match &result {
+            Ok(()) => self.rule_hooks(rule, vs, e),
+            Err(err) => self.error_hooks(err, vs, e),
+        }
+        result
+    }
+
+    fn traced_step(
+        &mut self,
+        vs: &mut ValueStack,
+        e: &mut Expr,
+        trace: &mut ExpansionTrace,
+    ) -> Result<(), EvalError> {
+        if !self.rules.is_empty() && self.rewrite(e)? {
+            trace.pop_finished(e);
+            return Ok(());
+        }
+        let expanding = match e.head() {
+            Expr::Call(sym) => match self.lookup_fn(sym) {
+                Some(Expr::Quote(_)) | None => None,
+                Some(_) => Some((*sym, e.pending_len() - 1)),
+            },
+            _ => None,
+        };
+        self.step(vs, e)?;
+        trace.0.extend(expanding);
+        trace.pop_finished(e);
+        Ok(())
+    }
+
     pub fn compress(&mut self, vs: &mut ValueStack) -> bool {
         let mut compressed = false;
         for v in vs.0.iter_mut() {
             match v {
-                Value::Call(_) => {}
+                Value::Call(_) | Value::Symbol(_) => {}
+                #[cfg(feature = "bignum")]
+                Value::Nat(_) => {}
+                #[cfg(feature = "rational")]
+                Value::Ratio(_) => {}
+                Value::Pair(_) | Value::Map(_) | Value::Char(_) | Value::Str(_) => {}
                 Value::Quote(e) => {
                     // TODO: we shouldn't have to clone this expr in order to hash it
-                    if let Some(sym) = self.exprs.get(&Expr::Quote((*e).clone())) {
+                    let e = Expr::Quote((*e).clone());
+                    let sym = self
+                        .exprs
+                        .get(&e)
+                        .or_else(|| self.shared.as_ref()?.exprs.get(&e));
+                    // Unless it has been redefined, or a local definition
+                    // shadows it
+                    if let Some(sym) = sym.filter(|sym| self.defines(sym, &e)) {
                         *v = Value::Call(*sym);
                         compressed = true;
                     }
@@ -271,6 +845,140 @@ impl Context {
         }
         compressed
     }
+
+    /// Replace calls of `sym` on `vs` with the quotation `sym` is defined
+    /// as, before it is redefined, so that they keep meaning that quotation
+    pub(crate) fn expand_calls(&self, vs: &mut ValueStack, sym: Symbol) {
+        if let Some(Expr::Quote(q)) = self.lookup_fn(&sym) {
+            for v in vs.0.iter_mut() {
+                if *v == Value::Call(sym) {
+                    *v = Value::Quote(q.clone());
+                }
+            }
+        }
+    }
+
+    /// Whether `sym` is defined as `e`, either exactly, or but for calls
+    /// qualified in the `core` namespace that `e` makes unqualified, as
+    /// quotations built at runtime from the prelude's definitions do
+    fn defines(&self, sym: &Symbol, e: &Expr) -> bool {
+        match self.lookup_fn(sym) {
+            Some(body) => body == e || self.qualifies(body, e),
+            None => false,
+        }
+    }
+
+    /// Whether `qualified` is `e` with some of its calls qualified as
+    /// `core.<name>`, each of which `e` calls unshadowed
+    fn qualifies(&self, qualified: &Expr, e: &Expr) -> bool {
+        match (qualified, e) {
+            (Expr::Call(q), Expr::Call(sym)) if q != sym => {
+                let name = self.interner.resolve(&sym.0);
+                let is_qualified = self
+                    .interner
+                    .resolve(&q.0)
+                    .strip_prefix(CORE)
+                    .and_then(|q| q.strip_prefix('.'))
+                    == Some(name);
+                is_qualified
+                    && matches!(
+                        (self.lookup_fn(q), self.lookup_fn(sym)),
+                        (Some(q), Some(e)) if std::ptr::eq(q, e)
+                    )
+            }
+            (Expr::Quote(q), Expr::Quote(e)) => self.qualifies(q, e),
+            (Expr::Compose(qs), Expr::Compose(es)) => {
+                qs.len() == es.len() && qs.iter().zip(es).all(|(q, e)| self.qualifies(q, e))
+            }
+            (q, e) => q == e,
+        }
+    }
+}
+
+/// The expression that pushes `v`
+pub(crate) fn pushing(v: Value) -> Expr {
+    match v {
+        Value::Call(sym) => Expr::Call(sym),
+        Value::Symbol(sym) => Expr::Symbol(sym),
+        Value::Quote(e) => Expr::Quote(e),
+        #[cfg(feature = "bignum")]
+        Value::Nat(n) => Expr::Nat(n),
+        #[cfg(feature = "rational")]
+        Value::Ratio(r) => Expr::Ratio(r),
+        Value::Pair(p) => {
+            let (v1, v2) = *p;
+            Expr::Pair(Box::new((pushing(v1), pushing(v2))))
+        }
+        Value::Map(m) => Expr::Map(m),
+        Value::Char(c) => Expr::Char(c),
+        Value::Str(s) => Expr::Str(s),
+    }
+}
+
+/// The Church boolean for `b`, `[swap drop]` or `[drop]`
+pub(crate) fn boolean(b: bool) -> Value {
+    let e = if b {
+        Expr::Compose(vec![
+            Expr::Intrinsic(Intrinsic::Swap),
+            Expr::Intrinsic(Intrinsic::Drop),
+        ])
+    } else {
+        Expr::Intrinsic(Intrinsic::Drop)
+    };
+    Value::Quote(Box::new(e))
+}
+
+/// A list of `values`, the quotation that pushes them in order, e.g.
+/// `['a 'b]`
+pub(crate) fn list(values: impl IntoIterator<Item = Value>) -> Value {
+    let mut es: Vec<Expr> = values.into_iter().map(pushing).collect();
+    let e = if es.len() == 1 {
+        es.pop().unwrap()
+    } else {
+        Expr::Compose(es)
+    };
+    Value::Quote(Box::new(e))
+}
+
+/// `e1` followed by `e2`, with compositions flattened
+fn compose(e1: Expr, e2: Expr) -> Expr {
+    let mut new_es = match (e1, e2) {
+        (Expr::Compose(mut e1s), Expr::Compose(mut e2s)) => {
+            e1s.append(&mut e2s);
+            e1s
+        }
+        (Expr::Compose(mut e1s), e2) => {
+            e1s.push(e2);
+            e1s
+        }
+        (e1, Expr::Compose(mut e2s)) => {
+            e2s.insert(0, e1);
+            e2s
+        }
+        (e1, e2) => vec![e1, e2],
+    };
+    if new_es.len() == 1 {
+        new_es.pop().unwrap()
+    } else {
+        Expr::Compose(new_es)
+    }
+}
+
+impl ExpansionTrace {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn pop_finished(&mut self, e: &Expr) {
+        let len = e.pending_len();
+        while matches!(self.0.last(), Some(&(_, rest)) if len <= rest) {
+            self.0.pop();
+        }
+    }
 }
 
 //////////////////////////
@@ -281,10 +989,51 @@ impl Context {
 pub struct FnDef(pub Symbol, pub Expr);
 
 impl Context {
-    pub fn define_fn(&mut self, fn_def: FnDef) -> Option<FnDef> {
-        let result = self.fns.remove(&fn_def.0).map(|e| FnDef(fn_def.0, e));
-        self.fns.insert(fn_def.0, fn_def.1.clone());
-        self.exprs.insert(fn_def.1, fn_def.0);
-        result
+    /// Define a function, replacing and returning any previous definition,
+    /// unless it goes over the definition limits. Suspicious definitions are
+    /// still made, but produce warnings which can be retrieved with
+    /// `take_warnings`. If prefolding is on, the body is folded after.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(name = self.interner.resolve(&fn_def.0 .0)),
+            err
+        )
+    )]
+    pub fn define_fn(&mut self, fn_def: FnDef) -> Result<Option<FnDef>, LimitError> {
+        self.check_limits(&fn_def)?;
+        let warnings = self.diagnose(&fn_def);
+        self.warnings.extend(warnings);
+        // Redefinitions keep their original position
+        let result = Arc::make_mut(&mut self.fns)
+            .insert(fn_def.0, fn_def.1.clone())
+            .map(|e| FnDef(fn_def.0, e));
+        Arc::make_mut(&mut self.exprs).insert(fn_def.1, fn_def.0);
+        if self.prefold {
+            self.prefold_fn(fn_def.0);
+        }
+        Ok(result)
+    }
+
+    /// Every top-level definition, the prelude's first, by unqualified
+    /// name, in the order they were first defined
+    pub(crate) fn fn_defs(&self) -> impl Iterator<Item = (&Symbol, &Expr)> {
+        let prelude = self.prelude_fns();
+        let shadowed = prelude.into_iter().flat_map(move |prelude| {
+            prelude
+                .iter()
+                .map(move |(sym, e)| (sym, self.fns.get(sym).unwrap_or(e)))
+        });
+        let own = self
+            .fns
+            .iter()
+            .filter(move |(sym, _)| !prelude.is_some_and(|prelude| prelude.contains_key(*sym)));
+        shadowed.chain(own)
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
     }
 }
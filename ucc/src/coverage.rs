@@ -0,0 +1,124 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Which defined functions evaluation calls, counted with an `on_call`
+//! hook, so library authors can find the ones their tests never run.
+
+use crate::core::{Context, Map, Symbol};
+use crate::display::Resolve;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// The number of times each symbol has been called since it was created by
+/// `Context::track_coverage`
+#[derive(Clone, Default)]
+pub struct Coverage {
+    calls: Rc<RefCell<Map<Symbol, usize>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// Each function, in the order they were defined, and the number of
+    /// times it was called
+    pub fns: Vec<(String, usize)>,
+}
+
+impl Context {
+    /// Start counting the calls of each symbol.
+    pub fn track_coverage(&mut self) -> Coverage {
+        let coverage = Coverage::default();
+        let calls = Rc::clone(&coverage.calls);
+        self.on_call(move |sym, _| *calls.borrow_mut().entry(sym).or_default() += 1);
+        coverage
+    }
+
+    /// The calls counted by `coverage` of the functions `syms`.
+    pub fn coverage_report(&self, coverage: &Coverage, syms: &[Symbol]) -> CoverageReport {
+        let calls = coverage.calls.borrow();
+        CoverageReport {
+            fns: syms
+                .iter()
+                .map(|sym| {
+                    let name = sym.resolve(&self.interner);
+                    (name, calls.get(sym).copied().unwrap_or(0))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl CoverageReport {
+    /// The number of functions called at least once
+    pub fn covered(&self) -> usize {
+        self.fns.iter().filter(|(_, calls)| *calls > 0).count()
+    }
+
+    /// The report as a standalone HTML page, with a row per function.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <title>ucc coverage</title>\n\
+             <style>\n\
+             body { font-family: sans-serif; }\n\
+             td { padding: 0.2em 1em; font-family: monospace; }\n\
+             .called { background: #dfd; }\n\
+             .uncalled { background: #fdd; }\n\
+             </style>\n\
+             </head>\n\
+             <body>\n",
+        );
+        html.push_str(&format!(
+            "<h1>Called {} of {} functions</h1>\n<table>\n\
+             <tr><th>Function</th><th>Calls</th></tr>\n",
+            self.covered(),
+            self.fns.len()
+        ));
+        for (name, calls) in self.fns.iter() {
+            let class = if *calls > 0 { "called" } else { "uncalled" };
+            html.push_str(&format!(
+                "<tr class=\"{}\"><td>{}</td><td>{}</td></tr>\n",
+                class,
+                html_escape(name),
+                calls
+            ));
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+/// A summary, listing the functions never called
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Called {} of {} functions.",
+            self.covered(),
+            self.fns.len()
+        )?;
+        let uncalled: Vec<&str> = self
+            .fns
+            .iter()
+            .filter(|(_, calls)| *calls == 0)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !uncalled.is_empty() {
+            writeln!(f, "Never called: {}", uncalled.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::confusables::skeleton;
+use crate::core::{Context, Expr, FnDef, Intrinsic, Symbol};
+
+/// Suspicious, but legal, definitions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    UndefinedCall {
+        caller: Symbol,
+        callee: Symbol,
+    },
+    UnguardedRecursion(Symbol),
+    AlwaysUnderflows(Symbol),
+    /// A call to an undefined name one edit away from an intrinsic's
+    IntrinsicTypo {
+        name: Symbol,
+        intrinsic: Intrinsic,
+    },
+    /// A definition of a name that looks like one already defined
+    ConfusableName {
+        name: Symbol,
+        other: Symbol,
+    },
+}
+
+/// Intrinsics that can be misspelled, i.e. all but host intrinsics
+const INTRINSICS: [Intrinsic; 10] = [
+    Intrinsic::Swap,
+    Intrinsic::Clone,
+    Intrinsic::Drop,
+    Intrinsic::Quote,
+    Intrinsic::Compose,
+    Intrinsic::Apply,
+    Intrinsic::Defined,
+    Intrinsic::BodyOf,
+    Intrinsic::Define,
+    Intrinsic::EvalQ,
+];
+
+impl Intrinsic {
+    /// The number of values this intrinsic pops, and the number it pushes if
+    /// that is known statically
+    pub(crate) fn arity(&self) -> (usize, Option<usize>) {
+        match self {
+            Intrinsic::Swap => (2, Some(2)),
+            Intrinsic::Clone => (1, Some(2)),
+            Intrinsic::Drop => (1, Some(0)),
+            Intrinsic::Quote => (1, Some(1)),
+            Intrinsic::Compose => (2, Some(1)),
+            Intrinsic::Apply => (1, None),
+            Intrinsic::Defined => (1, Some(1)),
+            Intrinsic::BodyOf => (1, Some(1)),
+            Intrinsic::Define => (2, Some(0)),
+            Intrinsic::EvalQ => (2, Some(1)),
+            Intrinsic::Dup => (1, Some(2)),
+            Intrinsic::Over => (2, Some(3)),
+            Intrinsic::Rot => (3, Some(3)),
+            Intrinsic::Cake => (2, Some(2)),
+            Intrinsic::K => (2, None),
+            // See `Context::always_underflows`
+            Intrinsic::Host(_) => (0, None),
+        }
+    }
+}
+
+impl Context {
+    pub(crate) fn diagnose(&self, fn_def: &FnDef) -> Vec<Warning> {
+        let FnDef(sym, e) = fn_def;
+        let mut warnings = vec![];
+        let mut callees = vec![];
+        calls(e, &mut callees);
+        for callee in callees {
+            if callee != *sym
+                && self.lookup_fn(&callee).is_none()
+                && self.host_intrinsic(&callee).is_none()
+                && !warnings.contains(&Warning::UndefinedCall {
+                    caller: *sym,
+                    callee,
+                })
+            {
+                warnings.push(Warning::UndefinedCall {
+                    caller: *sym,
+                    callee,
+                });
+            }
+        }
+        if calls_unguarded(e, *sym) {
+            warnings.push(Warning::UnguardedRecursion(*sym));
+        }
+        if self.always_underflows(*sym, e) {
+            warnings.push(Warning::AlwaysUnderflows(*sym));
+        }
+        if self.lookup_fn(sym).is_none() {
+            let name = skeleton(self.interner.resolve(&sym.0));
+            if let Some((other, _)) = self
+                .fn_defs()
+                .find(|(other, _)| skeleton(self.interner.resolve(&other.0)) == name)
+            {
+                warnings.push(Warning::ConfusableName {
+                    name: *sym,
+                    other: *other,
+                });
+            }
+        }
+        warnings
+    }
+
+    /// Find calls in `e` to undefined names, other than those in `defining`,
+    /// that are one edit away from an intrinsic, replacing them with the
+    /// intrinsic if `fix`.
+    pub(crate) fn intrinsic_typos(
+        &self,
+        e: &mut Expr,
+        defining: &[Symbol],
+        fix: bool,
+        warnings: &mut Vec<Warning>,
+    ) {
+        match e {
+            Expr::Call(name)
+                if !defining.contains(name)
+                    && self.lookup_fn(name).is_none()
+                    && self.host_intrinsic(name).is_none() =>
+            {
+                let name = *name;
+                let spelling = self.interner.resolve(&name.0);
+                if let Some(&intrinsic) = INTRINSICS
+                    .iter()
+                    .find(|i| is_one_edit(spelling, &i.to_string()))
+                {
+                    let warning = Warning::IntrinsicTypo { name, intrinsic };
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                    if fix {
+                        *e = Expr::Intrinsic(intrinsic);
+                    }
+                }
+            }
+            Expr::Quote(e) => self.intrinsic_typos(e, defining, fix, warnings),
+            Expr::Compose(es) => {
+                for e in es {
+                    self.intrinsic_typos(e, defining, fix, warnings);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether evaluating `e` as the body of `sym` on an empty stack must
+    /// fail, determined by tracking the stack depth until it is unknown.
+    fn always_underflows(&self, sym: Symbol, e: &Expr) -> bool {
+        let mut depth = 0;
+        for e in elements(e) {
+            match e {
+                Expr::Quote(_) | Expr::Symbol(_) => depth += 1,
+                #[cfg(feature = "bignum")]
+                Expr::Nat(_) => depth += 1,
+                #[cfg(feature = "rational")]
+                Expr::Ratio(_) => depth += 1,
+                Expr::Pair(_) | Expr::Map(_) | Expr::Char(_) | Expr::Str(_) => depth += 1,
+                Expr::Intrinsic(Intrinsic::Host(host)) | Expr::Call(host)
+                    if self.lookup_fn(host).is_none() =>
+                {
+                    // What a host intrinsic leaves on the stack is unknown
+                    return match self.host_intrinsics.get(host) {
+                        Some(host) => depth < host.arity,
+                        None => false,
+                    };
+                }
+                Expr::Call(callee) => match self.lookup_fn(callee) {
+                    Some(Expr::Quote(_)) if *callee != sym => depth += 1,
+                    _ => return false,
+                },
+                Expr::Intrinsic(intr) => {
+                    let (pops, pushes) = intr.arity();
+                    if depth < pops {
+                        return true;
+                    }
+                    match pushes {
+                        Some(pushes) => depth = depth - pops + pushes,
+                        None => return false,
+                    }
+                }
+                Expr::Compose(_) => return false,
+            }
+        }
+        false
+    }
+}
+
+/// Whether `a` becomes `b` with one insertion, deletion, substitution, or
+/// transposition of adjacent characters
+fn is_one_edit(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    if a.len() == b.len() {
+        !a.is_empty()
+            && (a[1..] == b[1..]
+                || (a.len() >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..]))
+    } else if a.len() == b.len() + 1 {
+        a[1..] == *b
+    } else if a.len() + 1 == b.len() {
+        *a == b[1..]
+    } else {
+        false
+    }
+}
+
+fn elements(e: &Expr) -> &[Expr] {
+    match e {
+        Expr::Compose(es) => es,
+        e => std::slice::from_ref(e),
+    }
+}
+
+fn calls_unguarded(e: &Expr, sym: Symbol) -> bool {
+    match e {
+        Expr::Call(callee) => *callee == sym,
+        Expr::Compose(es) => es.iter().any(|e| calls_unguarded(e, sym)),
+        _ => false,
+    }
+}
+
+fn calls(e: &Expr, syms: &mut Vec<Symbol>) {
+    e.visit(&mut |e| {
+        if let Expr::Call(sym) = e {
+            syms.push(*sym);
+        }
+    });
+}
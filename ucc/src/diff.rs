@@ -0,0 +1,98 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Differences between a context's definitions and others, as a file's,
+//! with the bodies of changed definitions compared item by item.
+
+use crate::core::{Context, Expr, FnDef, OrderedMap, Symbol};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefDiff {
+    /// Defined only by the others
+    Added(FnDef),
+    /// Defined only by the context
+    Removed(FnDef),
+    /// Defined differently, with the edits from the context's body to the
+    /// others'
+    Changed(Symbol, Vec<Edit>),
+}
+
+/// An item of a body, kept, or removed from or added to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Same(Expr),
+    Removed(Expr),
+    Added(Expr),
+}
+
+/// The items of a body, as composed at its top level
+fn items(e: &Expr) -> &[Expr] {
+    match e {
+        Expr::Compose(es) => es,
+        e => std::slice::from_ref(e),
+    }
+}
+
+/// The fewest removals and additions of items that make `old` into `new`,
+/// in order, with the items they share
+pub fn diff_exprs(old: &Expr, new: &Expr) -> Vec<Edit> {
+    let (old, new) = (items(old), items(new));
+    // `lcs[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut edits = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Same(old[i].clone()));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Removed(old[i].clone()));
+            i += 1;
+        } else {
+            edits.push(Edit::Added(new[j].clone()));
+            j += 1;
+        }
+    }
+    edits
+}
+
+impl Context {
+    /// How `fn_defs` differ from this context's definitions: those added
+    /// and changed, in the order of `fn_defs`, then those removed, in the
+    /// order they were defined. A name defined more than once in `fn_defs`
+    /// is compared by its last definition.
+    pub fn diff_defs(&self, fn_defs: Vec<FnDef>) -> Vec<DefDiff> {
+        let mut others: OrderedMap<Symbol, Expr> = OrderedMap::default();
+        for FnDef(sym, e) in fn_defs {
+            others.insert(sym, e);
+        }
+        let mut diffs = vec![];
+        for (sym, e) in others.iter() {
+            match self.lookup_fn(sym) {
+                None => diffs.push(DefDiff::Added(FnDef(*sym, e.clone()))),
+                Some(old) if old != e => diffs.push(DefDiff::Changed(*sym, diff_exprs(old, e))),
+                Some(_) => {}
+            }
+        }
+        for (sym, e) in self.fn_defs() {
+            if !others.contains_key(sym) {
+                diffs.push(DefDiff::Removed(FnDef(*sym, e.clone())));
+            }
+        }
+        diffs
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Listings of the code the bytecode VM compiles for a definition, and
+//! for the definitions it calls, as `:disasm` shows them:
+//!
+//! ```text
+//! f:
+//!    0  push #0
+//!    1  call compose3 @3
+//!    2  return
+//! compose3:
+//!    3  compose
+//!    4  call compose2 @6
+//!    5  return
+//! compose2:
+//!    6  compose
+//!    7  return
+//! constants:
+//!   #0  [a]
+//! ```
+//!
+//! Calls are followed by the address they jump to, and pushes refer to
+//! the constant pool, listed last. Quotations are compiled only as they
+//! are applied, so their code isn't listed.
+
+use crate::core::{Context, EvalError, Expr, Symbol};
+use crate::display::Resolve;
+use crate::vm::{Op, Vm, VmError};
+use std::collections::VecDeque;
+
+impl Vm {
+    /// Compile `sym`, and the functions it calls, directly or not, and list
+    /// their code, each under its name, then the constants they push.
+    pub fn disassemble(&mut self, ctx: &Context, sym: Symbol) -> Result<String, VmError> {
+        // Where the code of each function listed starts and ends
+        let mut ranges = vec![];
+        let mut pending = VecDeque::from(vec![sym]);
+        while let Some(sym) = pending.pop_front() {
+            let body = ctx.lookup_fn(&sym).ok_or(EvalError::UndefinedFn(sym))?;
+            let start = self.code.len();
+            if self.compile_fn(ctx, sym, body)? < start {
+                // Listed already
+                continue;
+            }
+            for op in &self.code[start..] {
+                if let Op::Call(callee) = op {
+                    // Calls of quotations push them instead of jumping
+                    let jumps = !matches!(ctx.lookup_fn(callee), None | Some(Expr::Quote(_)));
+                    if jumps && !self.entries.contains_key(callee) && !pending.contains(callee) {
+                        pending.push_back(*callee);
+                    }
+                }
+            }
+            ranges.push((sym, start, self.code.len()));
+        }
+        let mut listing = String::new();
+        for (sym, start, end) in ranges {
+            listing.push_str(&format!("{}:\n", sym.resolve(&ctx.interner)));
+            for pc in start..end {
+                listing.push_str(&format!("{:>4}  {}\n", pc, self.disasm_op(pc, ctx)));
+            }
+        }
+        if !self.constants.is_empty() {
+            listing.push_str("constants:\n");
+            for (i, v) in self.constants.iter().enumerate() {
+                let index = format!("#{}", i);
+                listing.push_str(&format!("{:>4}  {}\n", index, v.resolve(&ctx.interner)));
+            }
+        }
+        Ok(listing)
+    }
+
+    /// The op at `pc`, with its constant as an index into the pool and its
+    /// call's target, if compiled
+    fn disasm_op(&self, pc: usize, ctx: &Context) -> String {
+        match &self.code[pc] {
+            Op::Push(i) => format!("push #{}", i),
+            Op::Call(sym) => match self.entries.get(sym) {
+                Some(entry) => format!("call {} @{}", sym.resolve(&ctx.interner), entry),
+                None => format!("call {}", sym.resolve(&ctx.interner)),
+            },
+            _ => self.op_text(pc, &ctx.interner),
+        }
+    }
+}
@@ -4,9 +4,47 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::core::{EvalError, Expr, Interner, Intrinsic, Symbol, Value, ValueStack};
+//! Writing expressions and values as source text. Their symbols are only
+//! names in the interner of the `Context` they were made by, so embedders
+//! display them with `Context::display`, and make them from source text
+//! with `Context::parse_expr` and the like, e.g.
+//!
+//! ```
+//! # use ucc::core::Context;
+//! let mut ctx = Context::default();
+//! let e = ctx.parse_expr("[swap] apply").unwrap();
+//! assert_eq!(ctx.display(&e).to_string(), "[swap] apply");
+//! ```
+
+use crate::character::fmt_char;
+use crate::core::{
+    Context, EvalError, ExpansionTrace, Expr, FnDef, Interner, Intrinsic, Symbol, Value, ValueStack,
+};
+use crate::diagnostics::Warning;
+use crate::diff::{DefDiff, Edit};
+use crate::flatten::FlattenError;
+use crate::limits::LimitError;
+use crate::load::LoadError;
+use crate::map::ValueMap;
+use crate::parse::{ExprParser, FnDefParser, ValueParser, ValueStackParser};
+use crate::rewrite::{Pattern, RuleError};
+use crate::settings::StackTop;
+use crate::stack_pattern::{StackPattern, ValuePattern};
+use crate::substructural::{Location, Violation};
+use crate::transaction::DefineError;
+#[cfg(feature = "bignum")]
+use num_bigint::BigUint;
+#[cfg(feature = "rational")]
+use num_rational::BigRational;
 use std::fmt;
 
+/// An `Expr`, `Value`, `ValueStack`, or `FnDef`, with the interner its
+/// symbols are in, so that it can be displayed
+pub struct Resolved<'a, T> {
+    value: &'a T,
+    interner: &'a Interner,
+}
+
 pub(crate) type ResolvedSymbol = String;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,23 +52,113 @@ pub enum ResolvedExpr {
     Empty,
     Intrinsic(Intrinsic),
     Call(ResolvedSymbol),
+    Symbol(ResolvedSymbol),
     Quote(Box<ResolvedExpr>),
+    #[cfg(feature = "bignum")]
+    Nat(BigUint),
+    #[cfg(feature = "rational")]
+    Ratio(BigRational),
+    Pair(Box<(ResolvedExpr, ResolvedExpr)>),
+    Map(Vec<(ResolvedValue, ResolvedValue)>),
+    Char(char),
+    Str(String),
     Compose(Vec<ResolvedExpr>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ResolvedValue {
     Call(ResolvedSymbol),
+    Symbol(ResolvedSymbol),
     Quote(Box<ResolvedExpr>),
+    #[cfg(feature = "bignum")]
+    Nat(BigUint),
+    #[cfg(feature = "rational")]
+    Ratio(BigRational),
+    Pair(Box<(ResolvedValue, ResolvedValue)>),
+    Map(Vec<(ResolvedValue, ResolvedValue)>),
+    Char(char),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedValueStack {
+    pub(crate) values: Vec<ResolvedValue>,
+    pub(crate) top: StackTop,
+    /// The most values shown, those nearest the top, if not all
+    pub(crate) max: Option<usize>,
+}
+
+/// A stack pattern, with `_`, captures, and calls as `ResolvedExpr::Call`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedStackPattern {
+    pub bottom: Vec<ResolvedExpr>,
+    pub top: Option<Vec<ResolvedExpr>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ResolvedValueStack(pub(crate) Vec<ResolvedValue>);
+pub struct ResolvedExpansionTrace(pub(crate) Vec<ResolvedSymbol>);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ResolvedEvalError {
     TooFewValues { available: usize, expected: usize },
     UndefinedFn(String),
+    RewriteLimitExceeded,
+    ExpectedSymbol,
+    ExpectedQuote,
+    DefineDisabled,
+    Host(String),
+    Limit(LimitError),
+    ExpectedDefinitions,
+    OutOfFuel,
+    Sandbox(Box<ResolvedEvalError>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedWarning {
+    UndefinedCall {
+        caller: ResolvedSymbol,
+        callee: ResolvedSymbol,
+    },
+    UnguardedRecursion(ResolvedSymbol),
+    AlwaysUnderflows(ResolvedSymbol),
+    IntrinsicTypo {
+        name: ResolvedSymbol,
+        intrinsic: Intrinsic,
+    },
+    ConfusableName {
+        name: ResolvedSymbol,
+        other: ResolvedSymbol,
+    },
+}
+
+/// A violation, written with each location as its items' indices counting
+/// from 1, e.g. `2.1` for the first item of the quotation that is the
+/// second item of a body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedViolation {
+    /// The intrinsic, a call if it is the host's
+    pub intrinsic: ResolvedExpr,
+    pub sites: Vec<(ResolvedSymbol, Location)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedDefineError {
+    Limit(ResolvedSymbol, LimitError),
+    Warning(ResolvedWarning),
+    Parse(usize, LoadError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedRuleError {
+    EmptyLhs,
+    UnboundVar(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedFlattenError {
+    Cycle(Vec<ResolvedSymbol>),
+    TooLarge(usize),
 }
 
 pub(crate) trait Resolve {
@@ -66,13 +194,35 @@ impl Resolve for Symbol {
     }
 }
 
+impl Resolve for Intrinsic {
+    type Output = ResolvedExpr;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            // Host intrinsics are written as calls, as they are parsed
+            Intrinsic::Host(sym) => ResolvedExpr::Call(sym.resolve(interner)),
+            i => ResolvedExpr::Intrinsic(*i),
+        }
+    }
+}
+
 impl Resolve for Expr {
     type Output = ResolvedExpr;
     fn resolve(&self, interner: &Interner) -> Self::Output {
         match self {
-            Expr::Intrinsic(i) => ResolvedExpr::Intrinsic(*i),
+            Expr::Intrinsic(i) => i.resolve(interner),
             Expr::Call(sym) => ResolvedExpr::Call(sym.resolve(interner)),
+            Expr::Symbol(sym) => ResolvedExpr::Symbol(sym.resolve(interner)),
             Expr::Quote(e) => ResolvedExpr::Quote(Box::new(e.resolve(interner))),
+            #[cfg(feature = "bignum")]
+            Expr::Nat(n) => ResolvedExpr::Nat(n.clone()),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(r) => ResolvedExpr::Ratio(r.clone()),
+            Expr::Pair(p) => {
+                ResolvedExpr::Pair(Box::new((p.0.resolve(interner), p.1.resolve(interner))))
+            }
+            Expr::Map(m) => ResolvedExpr::Map(resolve_map(m, interner)),
+            Expr::Char(c) => ResolvedExpr::Char(*c),
+            Expr::Str(s) => ResolvedExpr::Str(s.clone()),
             Expr::Compose(es) => {
                 ResolvedExpr::Compose(es.iter().map(|e| e.resolve(interner)).collect())
             }
@@ -85,21 +235,64 @@ impl Resolve for Value {
     fn resolve(&self, interner: &Interner) -> Self::Output {
         match self {
             Value::Call(sym) => ResolvedValue::Call(sym.resolve(interner)),
+            Value::Symbol(sym) => ResolvedValue::Symbol(sym.resolve(interner)),
             Value::Quote(e) => ResolvedValue::Quote(Box::new(e.resolve(interner))),
+            #[cfg(feature = "bignum")]
+            Value::Nat(n) => ResolvedValue::Nat(n.clone()),
+            #[cfg(feature = "rational")]
+            Value::Ratio(r) => ResolvedValue::Ratio(r.clone()),
+            Value::Pair(p) => {
+                ResolvedValue::Pair(Box::new((p.0.resolve(interner), p.1.resolve(interner))))
+            }
+            Value::Map(m) => ResolvedValue::Map(resolve_map(m, interner)),
+            Value::Char(c) => ResolvedValue::Char(*c),
+            Value::Str(s) => ResolvedValue::Str(s.clone()),
+        }
+    }
+}
+
+fn resolve_map(m: &ValueMap, interner: &Interner) -> Vec<(ResolvedValue, ResolvedValue)> {
+    m.iter()
+        .map(|(k, v)| (k.resolve(interner), v.resolve(interner)))
+        .collect()
+}
+
+/// Write the entries of a map as `#{k1 => v1, k2 => v2}`
+fn write_map<K: fmt::Display, V: fmt::Display>(
+    f: &mut fmt::Formatter,
+    entries: impl Iterator<Item = (K, V)>,
+) -> fmt::Result {
+    f.write_str("#{")?;
+    for (i, (k, v)) in entries.enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
         }
+        write!(f, "{} => {}", k, v)?;
     }
+    f.write_str("}")
 }
 
 impl Resolve for ValueStack {
     type Output = ResolvedValueStack;
     fn resolve(&self, interner: &Interner) -> Self::Output {
-        ResolvedValueStack(self.0.iter().map(|v| v.resolve(interner)).collect())
+        ResolvedValueStack {
+            values: self.0.iter().map(|v| v.resolve(interner)).collect(),
+            top: StackTop::Right,
+            max: None,
+        }
     }
 }
 
 impl Resolve for EvalError {
     type Output = ResolvedEvalError;
     fn resolve(&self, interner: &Interner) -> Self::Output {
+        self.resolve_by(&|sym| sym.resolve(interner))
+    }
+}
+
+impl EvalError {
+    /// This error with each symbol named by `name`
+    pub(crate) fn resolve_by(&self, name: &dyn Fn(Symbol) -> ResolvedSymbol) -> ResolvedEvalError {
         match self {
             &EvalError::TooFewValues {
                 available,
@@ -108,9 +301,208 @@ impl Resolve for EvalError {
                 available,
                 expected,
             },
-            &EvalError::UndefinedFn(sym) => ResolvedEvalError::UndefinedFn(sym.resolve(interner)),
+            &EvalError::UndefinedFn(sym) => ResolvedEvalError::UndefinedFn(name(sym)),
+            &EvalError::RewriteLimitExceeded => ResolvedEvalError::RewriteLimitExceeded,
+            &EvalError::ExpectedSymbol => ResolvedEvalError::ExpectedSymbol,
+            &EvalError::ExpectedQuote => ResolvedEvalError::ExpectedQuote,
+            &EvalError::DefineDisabled => ResolvedEvalError::DefineDisabled,
+            EvalError::Host(msg) => ResolvedEvalError::Host(msg.clone()),
+            EvalError::Limit(err) => ResolvedEvalError::Limit(err.clone()),
+            EvalError::ExpectedDefinitions => ResolvedEvalError::ExpectedDefinitions,
+            EvalError::OutOfFuel => ResolvedEvalError::OutOfFuel,
+            EvalError::Sandbox(err) => ResolvedEvalError::Sandbox(Box::new(err.resolve_by(name))),
+        }
+    }
+}
+
+impl Resolve for ExpansionTrace {
+    type Output = ResolvedExpansionTrace;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        ResolvedExpansionTrace(
+            self.0
+                .iter()
+                .map(|(sym, _)| sym.resolve(interner))
+                .collect(),
+        )
+    }
+}
+
+impl Resolve for Warning {
+    type Output = ResolvedWarning;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            Warning::UndefinedCall { caller, callee } => ResolvedWarning::UndefinedCall {
+                caller: caller.resolve(interner),
+                callee: callee.resolve(interner),
+            },
+            Warning::UnguardedRecursion(sym) => {
+                ResolvedWarning::UnguardedRecursion(sym.resolve(interner))
+            }
+            Warning::AlwaysUnderflows(sym) => {
+                ResolvedWarning::AlwaysUnderflows(sym.resolve(interner))
+            }
+            Warning::IntrinsicTypo { name, intrinsic } => ResolvedWarning::IntrinsicTypo {
+                name: name.resolve(interner),
+                intrinsic: *intrinsic,
+            },
+            Warning::ConfusableName { name, other } => ResolvedWarning::ConfusableName {
+                name: name.resolve(interner),
+                other: other.resolve(interner),
+            },
+        }
+    }
+}
+
+impl Resolve for Violation {
+    type Output = ResolvedViolation;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        ResolvedViolation {
+            intrinsic: Expr::Intrinsic(self.intrinsic).resolve(interner),
+            sites: self
+                .sites
+                .iter()
+                .map(|(sym, location)| (sym.resolve(interner), location.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl Resolve for Pattern {
+    type Output = ResolvedExpr;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            Pattern::Var(sym) | Pattern::Call(sym) => ResolvedExpr::Call(sym.resolve(interner)),
+            Pattern::Symbol(sym) => ResolvedExpr::Symbol(sym.resolve(interner)),
+            Pattern::Intrinsic(i) => i.resolve(interner),
+            Pattern::Quote(p) => ResolvedExpr::Quote(Box::new(p.resolve(interner))),
+            Pattern::Compose(ps) => {
+                ResolvedExpr::Compose(ps.iter().map(|p| p.resolve(interner)).collect())
+            }
+        }
+    }
+}
+
+impl Resolve for ValuePattern {
+    type Output = ResolvedExpr;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            ValuePattern::Any => ResolvedExpr::Call("_".to_owned()),
+            ValuePattern::Capture(sym) | ValuePattern::Call(sym) => {
+                ResolvedExpr::Call(sym.resolve(interner))
+            }
+            ValuePattern::Symbol(sym) => ResolvedExpr::Symbol(sym.resolve(interner)),
+            ValuePattern::Quote(p) => ResolvedExpr::Quote(Box::new(p.resolve(interner))),
+        }
+    }
+}
+
+impl Resolve for StackPattern {
+    type Output = ResolvedStackPattern;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        let resolve = |ps: &Vec<ValuePattern>| ps.iter().map(|p| p.resolve(interner)).collect();
+        ResolvedStackPattern {
+            bottom: resolve(&self.bottom),
+            top: self.top.as_ref().map(resolve),
+        }
+    }
+}
+
+impl Resolve for DefineError {
+    type Output = ResolvedDefineError;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            DefineError::Limit(sym, err) => {
+                ResolvedDefineError::Limit(sym.resolve(interner), err.clone())
+            }
+            DefineError::Warning(warning) => {
+                ResolvedDefineError::Warning(warning.resolve(interner))
+            }
+            DefineError::Parse(i, err) => ResolvedDefineError::Parse(*i, err.clone()),
+        }
+    }
+}
+
+impl Resolve for RuleError {
+    type Output = ResolvedRuleError;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            RuleError::EmptyLhs => ResolvedRuleError::EmptyLhs,
+            RuleError::UnboundVar(sym) => ResolvedRuleError::UnboundVar(sym.resolve(interner)),
+        }
+    }
+}
+
+impl Resolve for FlattenError {
+    type Output = ResolvedFlattenError;
+    fn resolve(&self, interner: &Interner) -> Self::Output {
+        match self {
+            FlattenError::Cycle(syms) => {
+                ResolvedFlattenError::Cycle(syms.iter().map(|sym| sym.resolve(interner)).collect())
+            }
+            FlattenError::TooLarge(limit) => ResolvedFlattenError::TooLarge(*limit),
+        }
+    }
+}
+
+impl Context {
+    pub fn display<'a, T>(&'a self, value: &'a T) -> Resolved<'a, T> {
+        Resolved {
+            value,
+            interner: &self.interner,
         }
     }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub fn parse_expr(&mut self, src: &str) -> Result<Expr, LoadError> {
+        let parsed = ExprParser::new()
+            .parse(&mut self.interner, src)
+            .map_err(|err| LoadError::Parse(format!("{:?}", err)))?;
+        self.basis
+            .adapt(parsed, &self.interner)
+            .map_err(LoadError::Basis)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub fn parse_value(&mut self, src: &str) -> Result<Value, LoadError> {
+        let parsed = ValueParser::new()
+            .parse(&mut self.interner, src)
+            .map_err(|err| LoadError::Parse(format!("{:?}", err)))?;
+        self.basis
+            .adapt_value(parsed, &self.interner)
+            .map_err(LoadError::Basis)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub fn parse_value_stack(&mut self, src: &str) -> Result<ValueStack, LoadError> {
+        let parsed = ValueStackParser::new()
+            .parse(&mut self.interner, src)
+            .map_err(|err| LoadError::Parse(format!("{:?}", err)))?;
+        self.basis
+            .adapt_value_stack(parsed, &self.interner)
+            .map_err(LoadError::Basis)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), err)
+    )]
+    pub fn parse_fn_def(&mut self, src: &str) -> Result<FnDef, LoadError> {
+        let parsed = FnDefParser::new()
+            .parse(&mut self.interner, src)
+            .map_err(|err| LoadError::Parse(format!("{:?}", err)))?;
+        self.basis
+            .adapt_fn_def(parsed, &self.interner)
+            .map_err(LoadError::Basis)
+    }
 }
 
 impl ResolvedExpr {
@@ -131,6 +523,18 @@ impl fmt::Display for Intrinsic {
             Intrinsic::Quote => "quote".fmt(f),
             Intrinsic::Compose => "compose".fmt(f),
             Intrinsic::Apply => "apply".fmt(f),
+            Intrinsic::Defined => "defined?".fmt(f),
+            Intrinsic::BodyOf => "body-of".fmt(f),
+            Intrinsic::Define => "define!".fmt(f),
+            Intrinsic::EvalQ => "evalq".fmt(f),
+            Intrinsic::Dup => "dup".fmt(f),
+            Intrinsic::Over => "over".fmt(f),
+            Intrinsic::Rot => "rot".fmt(f),
+            Intrinsic::Cake => "cake".fmt(f),
+            Intrinsic::K => "k".fmt(f),
+            // Its name is in the interner, which only resolved and
+            // borrowed views have, and they write it as a call
+            Intrinsic::Host(_) => "<host intrinsic>".fmt(f),
         }
     }
 }
@@ -141,7 +545,16 @@ impl fmt::Display for ResolvedExpr {
             ResolvedExpr::Empty => Ok(()),
             ResolvedExpr::Intrinsic(i) => i.fmt(f),
             ResolvedExpr::Call(sym) => sym.fmt(f),
+            ResolvedExpr::Symbol(sym) => write!(f, "'{}", sym),
             ResolvedExpr::Quote(e) => write!(f, "[{}]", e),
+            #[cfg(feature = "bignum")]
+            ResolvedExpr::Nat(n) => n.fmt(f),
+            #[cfg(feature = "rational")]
+            ResolvedExpr::Ratio(r) => crate::ratio::fmt_ratio(r, f),
+            ResolvedExpr::Pair(p) => write!(f, "({}, {})", p.0, p.1),
+            ResolvedExpr::Map(m) => write_map(f, m.iter().map(|(k, v)| (k, v))),
+            ResolvedExpr::Char(c) => fmt_char(*c, f),
+            ResolvedExpr::Str(s) => write!(f, "\"{}\"", s),
             ResolvedExpr::Compose(es) => {
                 if let Some(e) = es.first() {
                     if e.is_compose() {
@@ -168,21 +581,359 @@ impl fmt::Display for ResolvedValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ResolvedValue::Call(sym) => sym.fmt(f),
+            ResolvedValue::Symbol(sym) => write!(f, "'{}", sym),
             ResolvedValue::Quote(v) => write!(f, "[{}]", v),
+            #[cfg(feature = "bignum")]
+            ResolvedValue::Nat(n) => n.fmt(f),
+            #[cfg(feature = "rational")]
+            ResolvedValue::Ratio(r) => crate::ratio::fmt_ratio(r, f),
+            ResolvedValue::Pair(p) => write!(f, "({}, {})", p.0, p.1),
+            ResolvedValue::Map(m) => write_map(f, m.iter().map(|(k, v)| (k, v))),
+            ResolvedValue::Char(c) => fmt_char(*c, f),
+            ResolvedValue::Str(s) => write!(f, "\"{}\"", s),
         }
     }
 }
 
+impl ResolvedValueStack {
+    /// The stack, shown with its top at the end `top`
+    pub fn with_top(self, top: StackTop) -> Self {
+        ResolvedValueStack { top, ..self }
+    }
+
+    /// The stack, shown with at most `max` values, those nearest the top,
+    /// and how many more there are, as `⟨…(120 more)… [x] [y] [z]⟩`
+    pub fn with_max(self, max: Option<usize>) -> Self {
+        ResolvedValueStack { max, ..self }
+    }
+}
+
 impl fmt::Display for ResolvedValueStack {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        "⟨".fmt(f)?;
-        if let Some(v) = self.0.first() {
-            v.fmt(f)?;
+        write_stack(f, self.values.iter(), self.top, self.max)
+    }
+}
+
+/// Writes the values of a stack, bottom first in `values`, as
+/// `⟨…(120 more)… [x] [y] [z]⟩`, or `⟨top: [z] [y] [x] …(120 more)…⟩`
+/// with the top on the left
+fn write_stack<I>(
+    f: &mut fmt::Formatter,
+    values: I,
+    top: StackTop,
+    max: Option<usize>,
+) -> fmt::Result
+where
+    I: DoubleEndedIterator + ExactSizeIterator,
+    I::Item: fmt::Display,
+{
+    let len = values.len();
+    let hidden = match max {
+        Some(max) => len.saturating_sub(max),
+        None => 0,
+    };
+    let mut shown = values.skip(hidden);
+    f.write_str("⟨")?;
+    if top == StackTop::Left {
+        if len > 0 {
+            f.write_str("top: ")?;
+        }
+        if let Some(v) = shown.next_back() {
+            write!(f, "{}", v)?;
+        }
+        for v in shown.rev() {
+            write!(f, " {}", v)?;
+        }
+        if hidden > 0 {
+            let space = if hidden < len { " " } else { "" };
+            write!(f, "{}…({} more)…", space, hidden)?;
+        }
+    } else {
+        if hidden > 0 {
+            write!(f, "…({} more)…", hidden)?;
+        }
+        for (i, v) in shown.enumerate() {
+            if i > 0 || hidden > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", v)?;
+        }
+    }
+    f.write_str("⟩")
+}
+
+impl fmt::Display for ResolvedStackPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut items: Vec<String> = self.bottom.iter().map(|e| e.to_string()).collect();
+        if let Some(top) = &self.top {
+            items.push("...".to_owned());
+            items.extend(top.iter().map(|e| e.to_string()));
+        }
+        write!(f, "⟨{}⟩", items.join(" "))
+    }
+}
+
+impl fmt::Display for ResolvedExpansionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(sym) = self.0.first() {
+            write!(f, "in {}", sym)?;
+        }
+        for sym in self.0.iter().skip(1) {
+            write!(f, " → in {}", sym)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ResolvedWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolvedWarning::UndefinedCall { caller, callee } => {
+                write!(f, "`{}` calls undefined `{}`", caller, callee)
+            }
+            ResolvedWarning::UnguardedRecursion(sym) => {
+                write!(f, "`{}` calls itself outside of any quote", sym)
+            }
+            ResolvedWarning::AlwaysUnderflows(sym) => {
+                write!(f, "`{}` always underflows on an empty stack", sym)
+            }
+            ResolvedWarning::IntrinsicTypo { name, intrinsic } => {
+                write!(f, "`{}` may be a misspelling of `{}`", name, intrinsic)
+            }
+            ResolvedWarning::ConfusableName { name, other } => {
+                write!(f, "`{}` looks like `{}`", name, other)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ResolvedViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`", self.intrinsic)?;
+        for (i, (sym, location)) in self.sites.iter().enumerate() {
+            if i > 0 {
+                ", called".fmt(f)?;
+            }
+            if !location.is_empty() {
+                let indices: Vec<String> = location.iter().map(|i| (i + 1).to_string()).collect();
+                write!(f, " at {}", indices.join("."))?;
+            }
+            write!(f, " in `{}`", sym)?;
+        }
+        Ok(())
+    }
+}
+
+/// Written as `ResolvedExpr` is, but straight from the interner, without
+/// building a resolved tree of owned names first
+impl fmt::Display for Resolved<'_, Expr> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let interner = self.interner;
+        match self.value {
+            Expr::Intrinsic(Intrinsic::Host(sym)) | Expr::Call(sym) => {
+                f.write_str(interner.resolve(&sym.0))
+            }
+            Expr::Intrinsic(i) => i.fmt(f),
+            Expr::Symbol(sym) => write!(f, "'{}", interner.resolve(&sym.0)),
+            #[cfg(feature = "bignum")]
+            Expr::Nat(n) => n.fmt(f),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(r) => crate::ratio::fmt_ratio(r, f),
+            Expr::Pair(p) => write!(
+                f,
+                "({}, {})",
+                Resolved {
+                    value: &p.0,
+                    interner
+                },
+                Resolved {
+                    value: &p.1,
+                    interner
+                }
+            ),
+            Expr::Char(c) => fmt_char(*c, f),
+            Expr::Str(s) => write!(f, "\"{}\"", s),
+            Expr::Map(m) => write_map(
+                f,
+                m.iter().map(|(k, v)| {
+                    (
+                        Resolved { value: k, interner },
+                        Resolved { value: v, interner },
+                    )
+                }),
+            ),
+            Expr::Quote(e) => write!(
+                f,
+                "[{}]",
+                Resolved {
+                    value: &**e,
+                    interner
+                }
+            ),
+            Expr::Compose(es) => {
+                for (i, value) in es.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    if let Expr::Compose(..) = value {
+                        write!(f, "({})", Resolved { value, interner })?;
+                    } else {
+                        Resolved { value, interner }.fmt(f)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Resolved<'_, Value> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let interner = self.interner;
+        match self.value {
+            Value::Call(sym) => f.write_str(interner.resolve(&sym.0)),
+            Value::Symbol(sym) => write!(f, "'{}", interner.resolve(&sym.0)),
+            #[cfg(feature = "bignum")]
+            Value::Nat(n) => n.fmt(f),
+            #[cfg(feature = "rational")]
+            Value::Ratio(r) => crate::ratio::fmt_ratio(r, f),
+            Value::Pair(p) => write!(
+                f,
+                "({}, {})",
+                Resolved {
+                    value: &p.0,
+                    interner
+                },
+                Resolved {
+                    value: &p.1,
+                    interner
+                }
+            ),
+            Value::Char(c) => fmt_char(*c, f),
+            Value::Str(s) => write!(f, "\"{}\"", s),
+            Value::Map(m) => write_map(
+                f,
+                m.iter().map(|(k, v)| {
+                    (
+                        Resolved { value: k, interner },
+                        Resolved { value: v, interner },
+                    )
+                }),
+            ),
+            Value::Quote(e) => write!(
+                f,
+                "[{}]",
+                Resolved {
+                    value: &**e,
+                    interner
+                }
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Resolved<'_, ValueStack> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        StackView::new(self.value, self.interner).fmt(f)
+    }
+}
+
+/// A `ValueStack`, borrowed with the interner its symbols are in, and
+/// shown as `ResolvedValueStack` is, with its top at one end and only so
+/// many values, but written without resolving it first. Traces write a
+/// stack every step, so this keeps them from allocating every name on it.
+pub struct StackView<'a> {
+    values: &'a ValueStack,
+    interner: &'a Interner,
+    top: StackTop,
+    max: Option<usize>,
+}
+
+impl<'a> StackView<'a> {
+    pub fn new(values: &'a ValueStack, interner: &'a Interner) -> Self {
+        StackView {
+            values,
+            interner,
+            top: StackTop::Right,
+            max: None,
         }
-        for v in self.0.iter().skip(1) {
-            " ".fmt(f)?;
-            v.fmt(f)?;
+    }
+
+    /// The stack, shown with its top at the end `top`
+    pub fn with_top(self, top: StackTop) -> Self {
+        StackView { top, ..self }
+    }
+
+    /// The stack, shown with at most `max` values, those nearest the top
+    pub fn with_max(self, max: Option<usize>) -> Self {
+        StackView { max, ..self }
+    }
+}
+
+impl fmt::Display for StackView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let interner = self.interner;
+        let values = self
+            .values
+            .0
+            .iter()
+            .map(|value| Resolved { value, interner });
+        write_stack(f, values, self.top, self.max)
+    }
+}
+
+/// Written as it is defined, `{fn name = body}`
+impl fmt::Display for Resolved<'_, FnDef> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let FnDef(sym, e) = self.value;
+        write!(
+            f,
+            "{{fn {} = {}}}",
+            sym.resolve(self.interner),
+            e.resolve(self.interner)
+        )
+    }
+}
+
+/// The brackets a run of edits of one kind is written in, as `[-swap-]`
+/// for removed items and `{+drop+}` for added ones
+fn edit_brackets(edit: &Edit) -> (&'static str, &'static str) {
+    match edit {
+        Edit::Same(_) => ("", ""),
+        Edit::Removed(_) => ("[-", "-]"),
+        Edit::Added(_) => ("{+", "+}"),
+    }
+}
+
+/// Written `+ {fn f = e}` when added, `- {fn f = e}` when removed, and
+/// `~ {fn f = ...}` when changed, with the changes to the body bracketed
+impl fmt::Display for Resolved<'_, DefDiff> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (sym, edits) = match self.value {
+            DefDiff::Added(value) => {
+                let interner = self.interner;
+                return write!(f, "+ {}", Resolved { value, interner });
+            }
+            DefDiff::Removed(value) => {
+                let interner = self.interner;
+                return write!(f, "- {}", Resolved { value, interner });
+            }
+            DefDiff::Changed(sym, edits) => (sym, edits),
+        };
+        write!(f, "~ {{fn {} =", sym.resolve(self.interner))?;
+        let mut close = "";
+        for (i, edit) in edits.iter().enumerate() {
+            let brackets = edit_brackets(edit);
+            let e = match edit {
+                Edit::Same(e) | Edit::Removed(e) | Edit::Added(e) => e,
+            };
+            if i > 0 && edit_brackets(&edits[i - 1]) == brackets {
+                write!(f, " {}", e.resolve(self.interner))?;
+            } else {
+                write!(f, "{} {}{}", close, brackets.0, e.resolve(self.interner))?;
+                close = brackets.1;
+            }
         }
-        "⟩".fmt(f)
+        write!(f, "{}}}", close)
     }
 }
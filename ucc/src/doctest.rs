@@ -0,0 +1,97 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Doctests, for `ucci test --doc`: the big step assertions in the comment
+//! documenting a definition, checked so the examples there can't rot, e.g.
+//!
+//! ```text
+//! -- Drops the value under the top one:
+//! -- ⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩
+//! {fn nip = swap drop}
+//! ```
+//!
+//! A definition's doc comment is the run of `--` lines right above it.
+
+use crate::core::Context;
+use crate::interp::InterpItem;
+use crate::parse::{BigStepAssertionParser, InterpItemsParser};
+use crate::shared::SharedPrelude;
+
+/// A big step assertion in a doc comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocTest {
+    /// The function the comment documents
+    pub fn_name: String,
+    /// The line of the assertion, from 1
+    pub line: usize,
+    pub assertion: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocTestError {
+    Parse(String),
+    /// A definition or rule of the source can't be made
+    Define(String),
+}
+
+/// The doctests of the definitions in the source `src`, in order
+pub fn doc_tests(src: &str) -> Vec<DocTest> {
+    let mut tests = vec![];
+    // The assertions in the comment lines since the last other line
+    let mut doc = vec![];
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix("--") {
+            let comment = comment.trim();
+            if comment.contains('⇓') {
+                doc.push((i + 1, comment));
+            }
+            continue;
+        }
+        if let Some(def) = line.strip_prefix("{fn") {
+            let mut names = def.split(|c: char| c.is_whitespace() || c == '=');
+            let fn_name = names.find(|name| !name.is_empty()).unwrap_or_default();
+            tests.extend(doc.drain(..).map(|(line, assertion)| DocTest {
+                fn_name: fn_name.to_owned(),
+                line,
+                assertion: assertion.to_owned(),
+            }));
+        }
+        doc.clear();
+    }
+    tests
+}
+
+/// Check each doctest in the source `src`, with its definitions and rules,
+/// with `fuel` small steps for each, giving whether it passed
+pub fn run_doc_tests(src: &str, fuel: usize) -> Result<Vec<(DocTest, bool)>, DocTestError> {
+    let prelude = SharedPrelude::default();
+    let mut ctx = Context::with_prelude(&prelude);
+    let items = InterpItemsParser::new()
+        .parse(&mut ctx.interner, src)
+        .map_err(|err| DocTestError::Parse(format!("{:?}", err)))?;
+    for item in items {
+        match item {
+            InterpItem::FnDef(fn_def) => {
+                ctx.define_fn(fn_def)
+                    .map_err(|err| DocTestError::Define(format!("{:?}", err)))?;
+            }
+            InterpItem::Rule(rule) => ctx
+                .define_rule(rule)
+                .map_err(|err| DocTestError::Define(format!("{:?}", err)))?,
+            _ => {}
+        }
+    }
+    let mut results = vec![];
+    for test in doc_tests(src) {
+        let assertion = BigStepAssertionParser::new()
+            .parse(&mut ctx.interner, &test.assertion)
+            .map_err(|err| DocTestError::Parse(format!("{:?}", err)))?;
+        let passed = ctx.check(&assertion, fuel);
+        results.push((test, passed));
+    }
+    Ok(results)
+}
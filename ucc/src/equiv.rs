@@ -0,0 +1,203 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Testing whether two expressions are observationally equivalent, by
+//! running them on many small stacks. Results are related if they stop at
+//! the same error, or leave stacks of the same length whose values are
+//! equal or, up to a nesting bound, give related results when applied to
+//! the same stacks in turn.
+
+use crate::core::{Context, EvalError, Expr, Intrinsic, Symbol, Value, ValueStack};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EquivLimits {
+    /// The most small steps each evaluation may take
+    pub fuel: usize,
+    /// The most values on the stacks tried
+    pub depth: usize,
+    /// The most nested applications of result values tried
+    pub index: usize,
+}
+
+impl Default for EquivLimits {
+    fn default() -> Self {
+        EquivLimits {
+            fuel: 1000,
+            depth: 3,
+            index: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Observation {
+    Halted(ValueStack),
+    Failed(EvalError),
+    OutOfFuel,
+}
+
+/// A stack on which two expressions, or two values when applied, are
+/// observably different
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distinction {
+    pub stack: ValueStack,
+    pub observations: (Observation, Observation),
+    /// If the results differ only in the values at this index, how
+    /// applying them tells them apart
+    pub inner: Option<(usize, Box<Distinction>)>,
+}
+
+struct Tester {
+    limits: EquivLimits,
+    stacks: Vec<ValueStack>,
+}
+
+impl Context {
+    /// Find the first stack, shortest first, on which `e1` and `e2` give
+    /// unrelated results, or `None` if they are indistinguishable up to
+    /// `limits`.
+    pub fn distinguish(
+        &mut self,
+        e1: &Expr,
+        e2: &Expr,
+        limits: EquivLimits,
+    ) -> Option<Distinction> {
+        let tester = Tester {
+            limits,
+            stacks: self.test_stacks(limits.depth),
+        };
+        let allow_define = self.allow_define;
+        self.allow_define = false;
+        let mut distinction = None;
+        for vs in tester.stacks.iter() {
+            let o1 = self.observe(vs, e1.clone(), limits.fuel);
+            let o2 = self.observe(vs, e2.clone(), limits.fuel);
+            if let Some(inner) = self.unrelated(&tester, &o1, &o2, limits.index) {
+                distinction = Some(Distinction {
+                    stack: vs.clone(),
+                    observations: (o1, o2),
+                    inner,
+                });
+                break;
+            }
+        }
+        self.allow_define = allow_define;
+        distinction
+    }
+
+    /// Every stack of up to `depth` values from a small pool: two opaque
+    /// quotations, which fail differently when applied, and a few that
+    /// rearrange the stack
+    fn test_stacks(&mut self, depth: usize) -> Vec<ValueStack> {
+        let mut pool: Vec<Value> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let sym = Symbol(self.interner.get_or_intern(name));
+                Value::Quote(Box::new(Expr::Call(sym)))
+            })
+            .collect();
+        pool.push(Value::Quote(Box::default()));
+        for intr in [Intrinsic::Clone, Intrinsic::Drop, Intrinsic::Swap] {
+            pool.push(Value::Quote(Box::new(Expr::Intrinsic(intr))));
+        }
+        let mut stacks = vec![ValueStack::default()];
+        let mut start = 0;
+        for _ in 0..depth {
+            let end = stacks.len();
+            for i in start..end {
+                for v in pool.iter() {
+                    let mut vs = stacks[i].clone();
+                    vs.push(v.clone());
+                    stacks.push(vs);
+                }
+            }
+            start = end;
+        }
+        stacks
+    }
+
+    fn observe(&mut self, vs: &ValueStack, mut e: Expr, fuel: usize) -> Observation {
+        let mut vs = vs.clone();
+        for _ in 0..fuel {
+            if e == Expr::default() {
+                return Observation::Halted(vs);
+            }
+            if let Err(err) = self.small_step(&mut vs, &mut e) {
+                return Observation::Failed(err);
+            }
+        }
+        if e == Expr::default() {
+            Observation::Halted(vs)
+        } else {
+            Observation::OutOfFuel
+        }
+    }
+
+    /// `None` if `o1` and `o2` are related at `index`, or how they differ
+    fn unrelated(
+        &mut self,
+        tester: &Tester,
+        o1: &Observation,
+        o2: &Observation,
+        index: usize,
+    ) -> Option<Option<(usize, Box<Distinction>)>> {
+        match (o1, o2) {
+            // Either might differ given more fuel, but can't be told apart
+            // with this much
+            (Observation::OutOfFuel, _) | (_, Observation::OutOfFuel) => None,
+            (Observation::Failed(err1), Observation::Failed(err2)) => {
+                if err1 == err2 {
+                    None
+                } else {
+                    Some(None)
+                }
+            }
+            (Observation::Halted(vs1), Observation::Halted(vs2)) => {
+                if vs1.len() != vs2.len() {
+                    return Some(None);
+                }
+                if index == 0 {
+                    return None;
+                }
+                for (i, (v1, v2)) in vs1.0.iter().zip(vs2.0.iter()).enumerate() {
+                    if v1 != v2 {
+                        if let Some(d) = self.distinguish_values(tester, v1, v2, index - 1) {
+                            return Some(Some((i, Box::new(d))));
+                        }
+                    }
+                }
+                None
+            }
+            _ => Some(None),
+        }
+    }
+
+    fn distinguish_values(
+        &mut self,
+        tester: &Tester,
+        v1: &Value,
+        v2: &Value,
+        index: usize,
+    ) -> Option<Distinction> {
+        let apply = Expr::Intrinsic(Intrinsic::Apply);
+        for vs in tester.stacks.iter() {
+            let mut vs1 = vs.clone();
+            vs1.push(v1.clone());
+            let o1 = self.observe(&vs1, apply.clone(), tester.limits.fuel);
+            let mut vs2 = vs.clone();
+            vs2.push(v2.clone());
+            let o2 = self.observe(&vs2, apply.clone(), tester.limits.fuel);
+            if let Some(inner) = self.unrelated(tester, &o1, &o2, index) {
+                return Some(Distinction {
+                    stack: vs.clone(),
+                    observations: (o1, o2),
+                    inner,
+                });
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `Error`, which the crate's errors convert into, so embedders can
+//! propagate any of them with `?`, as into `anyhow::Error`:
+//!
+//! ```
+//! # use ucc::core::*;
+//! fn run(ctx: &mut Context, src: &str) -> Result<ValueStack, ucc::Error> {
+//!     let mut e = ctx.parse_expr(src)?;
+//!     let mut vs = ValueStack::default();
+//!     while e != Expr::default() {
+//!         ctx.small_step(&mut vs, &mut e)
+//!             .map_err(|err| ctx.eval_error(&err))?;
+//!     }
+//!     Ok(vs)
+//! }
+//! let mut ctx = Context::default();
+//! let err = run(&mut ctx, "[a] f").unwrap_err();
+//! assert_eq!(err.to_string(), "`f` is not defined");
+//! ```
+//!
+//! Every error implements `std::error::Error`, with the errors it wraps as
+//! its `source`. Errors that hold symbols are resolved by their context,
+//! so their messages name them.
+
+use crate::basis::BasisError;
+use crate::binary::BinaryError;
+use crate::core::{Context, EvalError};
+use crate::display::{Resolve, ResolvedDefineError, ResolvedEvalError};
+use crate::limits::LimitError;
+use crate::load::LoadError;
+use crate::transaction::DefineAllError;
+use lasso::Key;
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    Eval(ResolvedEvalError),
+    /// Reading, parsing or decoding source failed
+    Load(LoadError),
+    Limit(LimitError),
+    /// Definitions made all or none failed, so none were made
+    Define(Vec<ResolvedDefineError>),
+}
+
+impl Context {
+    /// `err`, with its symbols named as in this context
+    pub fn eval_error(&self, err: &EvalError) -> Error {
+        Error::Eval(err.resolve(&self.interner))
+    }
+
+    /// `err`, with its symbols named as in this context
+    pub fn define_error(&self, err: &DefineAllError) -> Error {
+        Error::Define(
+            err.errors
+                .iter()
+                .map(|err| err.resolve(&self.interner))
+                .collect(),
+        )
+    }
+}
+
+impl From<ResolvedEvalError> for Error {
+    fn from(err: ResolvedEvalError) -> Self {
+        Error::Eval(err)
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(err: LoadError) -> Self {
+        Error::Load(err)
+    }
+}
+
+impl From<LimitError> for Error {
+    fn from(err: LimitError) -> Self {
+        Error::Limit(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Eval(err) => err.fmt(f),
+            Error::Load(err) => err.fmt(f),
+            Error::Limit(err) => err.fmt(f),
+            Error::Define(errs) => {
+                "no definitions were made: ".fmt(f)?;
+                for (i, err) in errs.iter().enumerate() {
+                    if i > 0 {
+                        "; ".fmt(f)?;
+                    }
+                    err.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Eval(err) => Some(err),
+            Error::Load(err) => Some(err),
+            Error::Limit(err) => Some(err),
+            Error::Define(errs) => errs.first().map(|err| err as _),
+        }
+    }
+}
+
+impl fmt::Display for ResolvedEvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolvedEvalError::TooFewValues {
+                available,
+                expected,
+            } => write!(
+                f,
+                "too few values: expected {}, but there are {}",
+                expected, available
+            ),
+            ResolvedEvalError::UndefinedFn(name) => write!(f, "`{}` is not defined", name),
+            ResolvedEvalError::RewriteLimitExceeded => {
+                write!(f, "rewrite rules applied too many times in a row")
+            }
+            ResolvedEvalError::ExpectedSymbol => write!(f, "expected a quoted symbol"),
+            ResolvedEvalError::ExpectedQuote => write!(f, "expected a quotation"),
+            ResolvedEvalError::DefineDisabled => write!(f, "`define!` is disabled"),
+            ResolvedEvalError::Host(msg) => write!(f, "host intrinsic failed: {}", msg),
+            ResolvedEvalError::Limit(LimitError::ValueTooLarge { .. }) => {
+                write!(f, "evaluation made a value over the size limit")
+            }
+            ResolvedEvalError::Limit(_) => write!(f, "`define!` went over the definition limits"),
+            ResolvedEvalError::ExpectedDefinitions => {
+                write!(f, "expected definitions, as ['f1 [e1] 'f2 [e2] ...]")
+            }
+            ResolvedEvalError::OutOfFuel => write!(f, "`evalq` ran out of fuel"),
+            ResolvedEvalError::Sandbox(_) => write!(f, "the program given to `evalq` failed"),
+        }
+    }
+}
+
+impl error::Error for ResolvedEvalError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ResolvedEvalError::Limit(err) => Some(err),
+            ResolvedEvalError::Sandbox(err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+/// Written with each symbol as its key, e.g. `#3`, as there is no context
+/// to name it; `Context::eval_error` names them.
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.resolve_by(&|sym| format!("#{}", sym.0.into_usize()))
+            .fmt(f)
+    }
+}
+
+impl error::Error for EvalError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            EvalError::Limit(err) => Some(err),
+            EvalError::Sandbox(err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolvedDefineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolvedDefineError::Limit(name, _) => {
+                write!(f, "`{}` goes over the definition limits", name)
+            }
+            ResolvedDefineError::Warning(warning) => warning.fmt(f),
+            ResolvedDefineError::Parse(i, err) => write!(f, "source {}: {}", i + 1, err),
+        }
+    }
+}
+
+impl error::Error for ResolvedDefineError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ResolvedDefineError::Limit(_, err) => Some(err),
+            ResolvedDefineError::Warning(_) => None,
+            ResolvedDefineError::Parse(_, err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(msg) => write!(f, "couldn't read source: {}", msg),
+            LoadError::Parse(msg) => write!(f, "couldn't parse source: {}", msg),
+            LoadError::Net(msg) => write!(f, "couldn't fetch source: {}", msg),
+            LoadError::NetUnsupported => write!(f, "fetching URLs needs the `net` feature"),
+            LoadError::NetDisabled => write!(f, "fetching URLs is disabled"),
+            LoadError::InsecureUrl(url) => write!(f, "`{}` isn't an https:// URL", url),
+            LoadError::ChecksumUnsupported => write!(f, "checksums aren't supported here"),
+            LoadError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {}, but got {}",
+                expected, actual
+            ),
+            LoadError::BundleNotFound(bundle) => write!(f, "bundle `{}` not found", bundle),
+            LoadError::BadManifest(msg) => write!(f, "bad bundle manifest: {}", msg),
+            LoadError::Binary(_) => write!(f, "couldn't decode definitions"),
+            LoadError::Basis(_) => write!(f, "source uses intrinsics outside the basis"),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Binary(err) => Some(err),
+            LoadError::Basis(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitError::BodyTooLarge { size, limit } => {
+                write!(f, "body has {} nodes, over the limit of {}", size, limit)
+            }
+            LimitError::TooManyFns { limit } => {
+                write!(f, "over the limit of {} functions", limit)
+            }
+            LimitError::NameTooLong { len, limit } => {
+                write!(f, "name is {} bytes long, over the limit of {}", len, limit)
+            }
+            LimitError::ValueTooLarge { size, limit } => {
+                write!(f, "value has {} nodes, over the limit of {}", size, limit)
+            }
+        }
+    }
+}
+
+impl error::Error for LimitError {}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryError::BadMagic => write!(f, "not a .uccb file"),
+            BinaryError::UnsupportedVersion(version) => {
+                write!(f, "unsupported .uccb version {}", version)
+            }
+            BinaryError::UnexpectedEnd => write!(f, "unexpected end of file"),
+            BinaryError::BadTag(tag) => write!(f, "bad tag {:#04x}", tag),
+            BinaryError::BadName(i) => write!(f, "bad name index {}", i),
+            BinaryError::BadUtf8 => write!(f, "name isn't UTF-8"),
+            BinaryError::BadVarint => write!(f, "bad varint"),
+            BinaryError::TooDeep => write!(f, "expressions nested too deeply"),
+            BinaryError::Limit(_) => write!(f, "definitions go over the definition limits"),
+        }
+    }
+}
+
+impl error::Error for BinaryError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BinaryError::Limit(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BasisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BasisError::NotInBasis(intr) => write!(f, "`{}` isn't in the basis", intr),
+            BasisError::Inexpressible(intr) => {
+                write!(f, "`{}` can't be defined in the basis", intr)
+            }
+        }
+    }
+}
+
+impl error::Error for BasisError {}
@@ -0,0 +1,159 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The `evalq` intrinsic, which evaluates a quoted program with quoted
+//! definitions, apart from the context's own, e.g.
+//!
+//! ```text
+//! ⟨['t [swap drop] 'f [drop]] [[a] [b] t]⟩ evalq ⟶ ⟨[[b]]⟩
+//! ```
+//!
+//! so programs can experiment with interpreting programs, as a
+//! meta-circular interpreter would, without defining anything. The
+//! definitions are a flat list of symbols each followed by its body, and
+//! the program runs on an empty stack with only them, and `define!`, if
+//! enabled, defines only for the program's run. Its result is its stack
+//! quoted, with values that are calls of its definitions replaced by their
+//! bodies, as they mean nothing outside it.
+
+use crate::core::{pushing, Context, EvalError, Expr, FnDef, Map, Namespace, OrderedMap};
+use crate::core::{Symbol, Value, ValueStack};
+use crate::diagnostics::Warning;
+use crate::hooks::Hooks;
+use crate::host::HostIntrinsic;
+use crate::locals::Scope;
+use crate::rewrite::Rule;
+use crate::shared::SharedPrelude;
+use std::mem;
+use std::sync::Arc;
+
+/// The most steps the program given to `evalq` may take. Each `evalq` it
+/// evaluates in turn counts as one step, and has as many of its own.
+pub const EVALQ_FUEL: usize = 1_000_000;
+
+/// Everything a context is defined with, which `evalq` sets aside
+#[derive(Default)]
+struct Definitions {
+    shared: Option<SharedPrelude>,
+    fns: Arc<OrderedMap<Symbol, Expr>>,
+    locals: Vec<Scope>,
+    namespaces: Arc<OrderedMap<Symbol, Namespace>>,
+    exprs: Arc<Map<Expr, Symbol>>,
+    rules: Arc<Vec<Rule>>,
+    consecutive_rewrites: usize,
+    warnings: Vec<Warning>,
+    host_intrinsics: Map<Symbol, HostIntrinsic>,
+    hooks: Hooks,
+}
+
+impl Context {
+    /// Take the step of `evalq`, out of line, as `Context::step` recurses
+    /// through compositions and so should have a small frame.
+    #[inline(never)]
+    pub(crate) fn evalq_step(
+        &mut self,
+        vs: &mut ValueStack,
+        e: &mut Expr,
+    ) -> Result<(), EvalError> {
+        if vs.0.len() < 2 {
+            return Err(EvalError::TooFewValues {
+                available: vs.0.len(),
+                expected: 2,
+            });
+        }
+        let program = vs.0.pop().unwrap();
+        let defs = vs.0.pop().unwrap();
+        match self.evalq(defs.clone(), program.clone()) {
+            Ok(v) => {
+                vs.0.push(v);
+                *e = Expr::default();
+                Ok(())
+            }
+            Err(err) => {
+                // Leave the values, so the error is shown with what caused it
+                vs.0.push(defs);
+                vs.0.push(program);
+                Err(err)
+            }
+        }
+    }
+
+    /// Evaluate the program `program` with only the definitions `defs`,
+    /// returning its stack quoted.
+    fn evalq(&mut self, defs: Value, program: Value) -> Result<Value, EvalError> {
+        let fn_defs = fn_defs(self.unquote_value(defs)?)?;
+        let e = self.unquote_value(program)?;
+        let mut ambient = Definitions::default();
+        self.swap_definitions(&mut ambient);
+        let result = self.sandboxed(fn_defs, e);
+        self.swap_definitions(&mut ambient);
+        result
+    }
+
+    fn swap_definitions(&mut self, defs: &mut Definitions) {
+        mem::swap(&mut self.shared, &mut defs.shared);
+        mem::swap(&mut self.fns, &mut defs.fns);
+        mem::swap(&mut self.locals, &mut defs.locals);
+        mem::swap(&mut self.namespaces, &mut defs.namespaces);
+        mem::swap(&mut self.exprs, &mut defs.exprs);
+        mem::swap(&mut self.rules, &mut defs.rules);
+        mem::swap(
+            &mut self.consecutive_rewrites,
+            &mut defs.consecutive_rewrites,
+        );
+        mem::swap(&mut self.warnings, &mut defs.warnings);
+        mem::swap(&mut self.host_intrinsics, &mut defs.host_intrinsics);
+        mem::swap(&mut self.hooks, &mut defs.hooks);
+    }
+
+    fn sandboxed(&mut self, fn_defs: Vec<FnDef>, mut e: Expr) -> Result<Value, EvalError> {
+        for fn_def in fn_defs {
+            self.define_fn(fn_def).map_err(EvalError::Limit)?;
+        }
+        let mut vs = ValueStack::default();
+        let mut fuel = EVALQ_FUEL;
+        while e != Expr::default() {
+            if fuel == 0 {
+                return Err(EvalError::OutOfFuel);
+            }
+            fuel -= 1;
+            self.small_step(&mut vs, &mut e)
+                .map_err(|err| EvalError::Sandbox(Box::new(err)))?;
+        }
+        let mut es: Vec<Expr> =
+            vs.0.into_iter()
+                .map(|v| match v {
+                    // The definition is a quotation, as only those are pushed
+                    Value::Call(sym) => self.lookup_fn(&sym).unwrap().clone(),
+                    v => pushing(v),
+                })
+                .collect();
+        let e = if es.len() == 1 {
+            es.pop().unwrap()
+        } else {
+            Expr::Compose(es)
+        };
+        Ok(Value::Quote(Box::new(e)))
+    }
+}
+
+/// Read `['f1 [e1] 'f2 [e2] ...]` as the definitions `{fn f1 = e1}`,
+/// `{fn f2 = e2}`, and so on
+fn fn_defs(e: Expr) -> Result<Vec<FnDef>, EvalError> {
+    let es = match e {
+        Expr::Compose(es) if es.len() % 2 == 0 => es,
+        _ => return Err(EvalError::ExpectedDefinitions),
+    };
+    let mut fn_defs = Vec::with_capacity(es.len() / 2);
+    let mut es = es.into_iter();
+    while let (Some(name), Some(body)) = (es.next(), es.next()) {
+        match (name, body) {
+            (Expr::Symbol(sym), Expr::Quote(body)) => fn_defs.push(FnDef(sym, *body)),
+            _ => return Err(EvalError::ExpectedDefinitions),
+        }
+    }
+    Ok(fn_defs)
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Exercises, for `:exercise` and `:check-exercise`: a prompt asking for a
+//! definition, and hidden big step assertions it should meet, checked
+//! without showing them, e.g.
+//!
+//! ```text
+//! Define `nip`, which drops the value under the top one.
+//! ⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩
+//! ⟨[c] [a] [b]⟩ nip ⇓ ⟨[c] [b]⟩
+//! ```
+//!
+//! Lines that are big step assertions are the cases, `--` lines are left
+//! out, and the rest is the prompt.
+
+use crate::core::Context;
+use crate::parse::BigStepAssertionParser;
+
+/// The most small steps each case may take
+pub const EXERCISE_FUEL: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exercise {
+    pub prompt: String,
+    /// The big step assertions, as written
+    pub cases: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExerciseError {
+    /// The case with this number, from 1, is invalid
+    BadCase(usize),
+}
+
+/// The exercise in the source `src`
+pub fn parse_exercise(src: &str) -> Exercise {
+    let mut prompt = String::new();
+    let mut cases = vec![];
+    for line in src.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("--") {
+            continue;
+        }
+        if trimmed.starts_with('⟨') && trimmed.contains('⇓') {
+            cases.push(trimmed.to_owned());
+        } else {
+            prompt.push_str(line);
+            prompt.push('\n');
+        }
+    }
+    Exercise {
+        prompt: prompt.trim().to_owned(),
+        cases,
+    }
+}
+
+impl Context {
+    /// The number of the cases of `exercise` that pass with the functions
+    /// defined, each with `EXERCISE_FUEL` small steps
+    pub fn check_exercise(&mut self, exercise: &Exercise) -> Result<usize, ExerciseError> {
+        let mut passed = 0;
+        for (i, case) in exercise.cases.iter().enumerate() {
+            let assertion = BigStepAssertionParser::new()
+                .parse(&mut self.interner, case)
+                .map_err(|_| ExerciseError::BadCase(i + 1))?;
+            if self.check(&assertion, EXERCISE_FUEL) {
+                passed += 1;
+            }
+        }
+        Ok(passed)
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Flattening, for `:flatten`: an expression with every call to a defined
+//! function replaced by its body, again and again, then simplified, so
+//! what's left is the combinator term it stands for, e.g.
+//!
+//! ```text
+//! {fn f = [drop] apply}  {fn g = [a] f [b]}
+//! g  ⟶  [b]
+//! ```
+//!
+//! Calls to undefined functions are left as they are. Recursive functions
+//! have no such term, so flattening a call to one is an error.
+
+use crate::core::{Context, Expr, Intrinsic, Map, Symbol};
+use crate::limits::body_size;
+
+/// The most nodes an expression may flatten to
+pub const FLATTEN_MAX_NODES: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlattenError {
+    /// A function calls itself, through the rest of these, in turn
+    Cycle(Vec<Symbol>),
+    /// The flattened expression would have more than this many nodes
+    TooLarge(usize),
+}
+
+fn is_value(e: &Expr) -> bool {
+    matches!(e, Expr::Quote(_) | Expr::Symbol(_))
+}
+
+/// `e` with each of its items that pushes a value, then does something
+/// with it that's known from that value alone, replaced by what it does:
+///
+/// ```text
+/// v drop  ⟶
+/// v1 v2 swap  ⟶  v2 v1
+/// v1 v2 v3 rot  ⟶  v2 v3 v1
+/// v quote  ⟶  [v]
+/// [e1] [e2] compose  ⟶  [e1 e2]
+/// [e] apply  ⟶  e
+/// [e1] [e2] k  ⟶  e2
+/// ```
+///
+/// inside quotations too. Each of these makes the expression smaller, and
+/// none changes what it does on any stack, errors included.
+pub fn simplify(e: &Expr) -> Expr {
+    let mut items = vec![];
+    push_simplified(&mut items, e.clone());
+    if items.len() == 1 {
+        items.pop().unwrap()
+    } else {
+        Expr::Compose(items)
+    }
+}
+
+/// Push `e` onto the simplified `items`, simplifying what it meets there.
+fn push_simplified(items: &mut Vec<Expr>, e: Expr) {
+    let i = match e {
+        Expr::Compose(es) => {
+            for e in es {
+                push_simplified(items, e);
+            }
+            return;
+        }
+        Expr::Quote(e) => return items.push(Expr::Quote(Box::new(simplify(&e)))),
+        Expr::Intrinsic(i) => i,
+        e => return items.push(e),
+    };
+    let n = items.len();
+    // The values at the end of `items`, up to three of them
+    let values = items
+        .iter()
+        .rev()
+        .take(3)
+        .take_while(|e| is_value(e))
+        .count();
+    match (i, &items[n.saturating_sub(2)..]) {
+        (Intrinsic::Drop, _) if values >= 1 => {
+            items.pop();
+        }
+        (Intrinsic::Swap, _) if values >= 2 => items.swap(n - 2, n - 1),
+        (Intrinsic::Rot, _) if values >= 3 => items[n - 3..].rotate_left(1),
+        (Intrinsic::Quote, _) if values >= 1 => {
+            let v = items.pop().unwrap();
+            items.push(Expr::Quote(Box::new(v)));
+        }
+        (Intrinsic::Compose, [Expr::Quote(_), Expr::Quote(_)]) => {
+            let e2 = unquote(items.pop().unwrap());
+            let e1 = unquote(items.pop().unwrap());
+            items.push(Expr::Quote(Box::new(simplify(&Expr::Compose(vec![
+                e1, e2,
+            ])))));
+        }
+        (Intrinsic::Apply, [.., Expr::Quote(_)]) => {
+            let e = unquote(items.pop().unwrap());
+            push_simplified(items, e);
+        }
+        (Intrinsic::K, [Expr::Quote(_), Expr::Quote(_)]) => {
+            let e2 = unquote(items.pop().unwrap());
+            items.pop();
+            push_simplified(items, e2);
+        }
+        (i, _) => items.push(Expr::Intrinsic(i)),
+    }
+}
+
+fn unquote(e: Expr) -> Expr {
+    match e {
+        Expr::Quote(e) => *e,
+        e => e,
+    }
+}
+
+impl Context {
+    /// `e` with every call to a defined function expanded, then
+    /// simplified; see `simplify`
+    pub fn flatten(&self, e: &Expr) -> Result<Expr, FlattenError> {
+        let mut flattener = Flattener {
+            ctx: self,
+            calls: vec![],
+            expanded: Map::default(),
+        };
+        let e = simplify(&flattener.expand(e)?);
+        match body_size(&e) {
+            n if n > FLATTEN_MAX_NODES => Err(FlattenError::TooLarge(FLATTEN_MAX_NODES)),
+            _ => Ok(e),
+        }
+    }
+}
+
+struct Flattener<'a> {
+    ctx: &'a Context,
+    /// The functions being expanded, so cycles can be found
+    calls: Vec<Symbol>,
+    /// The functions already expanded, flattened
+    expanded: Map<Symbol, Expr>,
+}
+
+impl Flattener<'_> {
+    fn expand(&mut self, e: &Expr) -> Result<Expr, FlattenError> {
+        Ok(match e {
+            Expr::Call(sym) => return self.expand_call(*sym),
+            Expr::Quote(e) => Expr::Quote(Box::new(self.expand(e)?)),
+            Expr::Compose(es) => {
+                let mut expanded = Vec::with_capacity(es.len());
+                let mut n = 0;
+                for e in es {
+                    let e = self.expand(e)?;
+                    n += body_size(&e);
+                    if n > FLATTEN_MAX_NODES {
+                        return Err(FlattenError::TooLarge(FLATTEN_MAX_NODES));
+                    }
+                    expanded.push(e);
+                }
+                Expr::Compose(expanded)
+            }
+            e => e.clone(),
+        })
+    }
+
+    fn expand_call(&mut self, sym: Symbol) -> Result<Expr, FlattenError> {
+        if let Some(e) = self.expanded.get(&sym) {
+            return Ok(e.clone());
+        }
+        if let Some(at) = self.calls.iter().position(|call| *call == sym) {
+            let mut cycle = self.calls[at..].to_vec();
+            cycle.push(sym);
+            return Err(FlattenError::Cycle(cycle));
+        }
+        let body = match self.ctx.lookup_fn(&sym) {
+            Some(body) => body.clone(),
+            None => return Ok(Expr::Call(sym)),
+        };
+        self.calls.push(sym);
+        let expanded = self.expand(&body);
+        self.calls.pop();
+        let e = simplify(&expanded?);
+        if body_size(&e) > FLATTEN_MAX_NODES {
+            return Err(FlattenError::TooLarge(FLATTEN_MAX_NODES));
+        }
+        self.expanded.insert(sym, e.clone());
+        Ok(e)
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Forks of a context, for throwaway evaluations such as checking
+//! equivalences, searching for programs, or trying out a definition. A fork
+//! shares the definitions, rules and symbols of the context it was forked
+//! from until one of them changes them, so thousands of forks cost little
+//! more than one, however much is defined.
+
+use crate::core::Context;
+use std::sync::Arc;
+
+impl Context {
+    /// A context defined as this one is, with the same hooks and host
+    /// intrinsics but no warnings, that can be changed and evaluated in
+    /// without affecting it. Symbols interned in either after the fork
+    /// aren't known to the other.
+    pub fn fork(&self) -> Context {
+        Context {
+            interner: self.interner.clone(),
+            shared: self.shared.clone(),
+            fns: Arc::clone(&self.fns),
+            locals: self.locals.clone(),
+            namespaces: Arc::clone(&self.namespaces),
+            exprs: Arc::clone(&self.exprs),
+            rules: Arc::clone(&self.rules),
+            consecutive_rewrites: self.consecutive_rewrites,
+            allow_define: self.allow_define,
+            warnings: Vec::default(),
+            host_intrinsics: self.host_intrinsics.clone(),
+            hooks: self.hooks.clone(),
+            limits: self.limits,
+            basis: self.basis,
+            prefold: self.prefold,
+            it: self.it.clone(),
+        }
+    }
+}
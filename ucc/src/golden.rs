@@ -0,0 +1,165 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Golden transcripts of REPL sessions, for catching changes to the
+//! interpreter's output. A transcript is each input, on a line starting
+//! with `>>> `, followed by the output it gave, e.g.
+//!
+//! ```text
+//! >>> [a] [b] swap
+//! ⟨⟩ [a] [b] swap
+//! ⇓ ⟨[b] [a]⟩
+//! ```
+//!
+//! `check` replays the inputs of a checked-in transcript in a fresh
+//! interpreter and compares the output, or in update mode rewrites the
+//! transcript with the output given.
+
+use crate::builder::{Clock, InterpBuilder};
+use crate::interp::Interp;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// The start of an input line
+pub const INPUT_PREFIX: &str = ">>> ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoldenError {
+    Io(String),
+    /// The first input whose output differs from the transcript's
+    Mismatch {
+        input: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Transcript {
+    /// Any lines before the first input, as a description
+    pub header: String,
+    /// Inputs and their outputs, in order
+    pub entries: Vec<(String, String)>,
+}
+
+/// A clock that never moves, so `:bench` always reports no time taken
+struct Stopped;
+
+impl Clock for Stopped {
+    fn now(&self) -> Duration {
+        Duration::default()
+    }
+}
+
+/// An interpreter whose output depends only on its input: it has no
+/// settings file or bundle path, and its clock never moves.
+pub fn interp() -> Interp {
+    InterpBuilder::new()
+        .clock(Stopped)
+        .bundle_path(vec![])
+        .settings_path(None)
+        .build()
+}
+
+impl Transcript {
+    pub fn parse(text: &str) -> Self {
+        let mut transcript = Transcript::default();
+        for line in text.split_inclusive('\n') {
+            match line.strip_prefix(INPUT_PREFIX) {
+                Some(input) => {
+                    let input = input.trim_end().to_owned();
+                    transcript.entries.push((input, String::new()));
+                }
+                None => match transcript.entries.last_mut() {
+                    Some((_, output)) => output.push_str(line),
+                    None => transcript.header.push_str(line),
+                },
+            }
+        }
+        transcript
+    }
+
+    /// Run each of `inputs` in `interp` to completion, recording the
+    /// output of each.
+    pub fn record<'a, I>(header: &str, interp: &mut Interp, inputs: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut entries = vec![];
+        for input in inputs {
+            let mut output = vec![];
+            // Writing to a `Vec` can't fail
+            interp.interp_start(input, &mut output).unwrap();
+            while !interp.is_done() {
+                interp.interp_step(&mut output).unwrap();
+            }
+            let output = String::from_utf8_lossy(&output).into_owned();
+            entries.push((input.to_owned(), output));
+        }
+        Transcript {
+            header: header.to_owned(),
+            entries,
+        }
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(input, _)| input.as_str())
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.header)?;
+        for (input, output) in self.entries.iter() {
+            writeln!(f, "{}{}", INPUT_PREFIX, input)?;
+            f.write_str(output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replay the transcript at `path` in a fresh `interp()`, and compare the
+/// output. With `update`, rewrite the transcript with the output instead.
+pub fn check(path: &Path, update: bool) -> Result<(), GoldenError> {
+    let text = fs::read_to_string(path).map_err(|err| GoldenError::Io(err.to_string()))?;
+    let expected = Transcript::parse(&text);
+    let actual = Transcript::record(&expected.header, &mut interp(), expected.inputs());
+    if update {
+        if actual != expected {
+            fs::write(path, actual.to_string()).map_err(|err| GoldenError::Io(err.to_string()))?;
+        }
+        return Ok(());
+    }
+    for ((input, expected), (_, actual)) in expected.entries.into_iter().zip(actual.entries) {
+        if expected != actual {
+            return Err(GoldenError::Mismatch {
+                input,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoldenError::Io(err) => write!(f, "{}", err),
+            GoldenError::Mismatch {
+                input,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "output of `{}` differs\n--- expected\n{}--- actual\n{}",
+                input, expected, actual
+            ),
+        }
+    }
+}
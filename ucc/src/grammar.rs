@@ -0,0 +1,22 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The grammar accepted by the parsers, for editors and documentation
+//! tools. It is generated from `src/parse.lalrpop` at build time, so it
+//! always matches the parsers.
+
+/// The grammar in the W3C EBNF notation, which railroad diagram generators
+/// read. Tokens that match fixed text are written as that text, and the
+/// others are defined after the rules.
+pub static EBNF: &str = include_str!(concat!(env!("OUT_DIR"), "/grammar.ebnf"));
+
+/// A tree-sitter grammar for ucc source, i.e. definitions, rules, and
+/// expressions, to build a parser from with `tree-sitter generate`
+pub static TREE_SITTER_GRAMMAR: &str = include_str!(concat!(env!("OUT_DIR"), "/grammar.js"));
+
+/// Highlight queries for the nodes of `TREE_SITTER_GRAMMAR`, to install as
+/// `queries/highlights.scm`
+pub static TREE_SITTER_HIGHLIGHTS: &str = include_str!(concat!(env!("OUT_DIR"), "/highlights.scm"));
@@ -0,0 +1,171 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Searching definitions, for `:grep`: by a pattern, in the language of
+//! rewrite rules, that any run of consecutive items of a body matches, at
+//! any depth, or by a substring of the name. E.g. `:grep [$f] apply`
+//! finds the definitions that apply a quotation they push.
+
+use crate::core::{Context, Expr, Symbol};
+use crate::display::Resolve;
+use crate::rewrite::{Bindings, Pattern};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrepQuery {
+    Pattern(Pattern),
+    Name(String),
+}
+
+/// A run of items within an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fragment {
+    /// The indices of the items, or the quotations, leading from the
+    /// expression to the composition the run is in
+    pub path: Vec<usize>,
+    pub items: Range<usize>,
+}
+
+/// A definition matching a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub name: Symbol,
+    /// The definition, as `:show` writes it
+    pub line: String,
+    /// The characters of `line` that matched
+    pub highlight: Range<usize>,
+}
+
+/// The items of `e`, as composed at its top level
+fn items(e: &Expr) -> &[Expr] {
+    match e {
+        Expr::Compose(es) => es,
+        e => std::slice::from_ref(e),
+    }
+}
+
+/// The first run of items in `e` matching `pattern`, outermost then
+/// leftmost, or `None` if there is none or `pattern` is empty
+pub fn find_pattern(e: &Expr, pattern: &Pattern) -> Option<Fragment> {
+    let ps = pattern.elements();
+    if ps.is_empty() {
+        return None;
+    }
+    let es = items(e);
+    for start in 0..(es.len() + 1).saturating_sub(ps.len()) {
+        let mut bindings = Bindings::default();
+        if ps
+            .iter()
+            .zip(&es[start..])
+            .all(|(p, e)| p.match_expr(e, &mut bindings))
+        {
+            return Some(Fragment {
+                path: vec![],
+                items: start..start + ps.len(),
+            });
+        }
+    }
+    for (i, e) in es.iter().enumerate() {
+        let inner = match e {
+            Expr::Quote(e) => e,
+            Expr::Compose(_) => e,
+            _ => continue,
+        };
+        if let Some(mut fragment) = find_pattern(inner, pattern) {
+            fragment.path.insert(0, i);
+            return Some(fragment);
+        }
+    }
+    None
+}
+
+impl Context {
+    /// The definitions matching `query`, in the order they were defined,
+    /// each with the first fragment of it that matched
+    pub fn grep(&self, query: &GrepQuery) -> Vec<GrepMatch> {
+        let mut matches = vec![];
+        for (sym, e) in self.fn_defs() {
+            let name = sym.resolve(&self.interner);
+            let prefix = format!("{{fn {} = ", name);
+            let (body, highlight) = match query {
+                GrepQuery::Pattern(pattern) => match find_pattern(e, pattern) {
+                    Some(fragment) => {
+                        let (body, range) = self.write_marked(e, &fragment.path, &fragment.items);
+                        let at = prefix.chars().count();
+                        (body, at + range.start..at + range.end)
+                    }
+                    None => continue,
+                },
+                GrepQuery::Name(part) => match name.find(part.as_str()) {
+                    Some(at) => {
+                        let start = "{fn ".len() + name[..at].chars().count();
+                        let body = e.resolve(&self.interner).to_string();
+                        (body, start..start + part.chars().count())
+                    }
+                    None => continue,
+                },
+            };
+            matches.push(GrepMatch {
+                name: *sym,
+                line: format!("{}{}}}", prefix, body),
+                highlight,
+            });
+        }
+        matches
+    }
+
+    /// `e` as displayed, with the characters of the run of `items` at
+    /// `path` in it
+    fn write_marked(
+        &self,
+        e: &Expr,
+        path: &[usize],
+        items: &Range<usize>,
+    ) -> (String, Range<usize>) {
+        let es = match e {
+            Expr::Compose(es) => &es[..],
+            e => std::slice::from_ref(e),
+        };
+        let mut text = String::new();
+        let mut range = 0..0;
+        for (i, item) in es.iter().enumerate() {
+            if i > 0 {
+                text.push(' ');
+            }
+            if i == items.start && path.is_empty() {
+                range.start = text.chars().count();
+            }
+            let (open, close) = match item {
+                Expr::Compose(_) => ("(", ")"),
+                Expr::Quote(_) => ("[", "]"),
+                _ => ("", ""),
+            };
+            let inner = match (path.split_first(), item) {
+                (Some((&at, rest)), Expr::Quote(inner)) if at == i => Some((&**inner, rest)),
+                (Some((&at, rest)), Expr::Compose(_)) if at == i => Some((item, rest)),
+                _ => None,
+            };
+            match inner {
+                Some((inner, rest)) => {
+                    text.push_str(open);
+                    let at = text.chars().count();
+                    let (inner, inner_range) = self.write_marked(inner, rest, items);
+                    text.push_str(&inner);
+                    text.push_str(close);
+                    range = at + inner_range.start..at + inner_range.end;
+                }
+                None if open == "(" => {
+                    text.push_str(&format!("({})", item.resolve(&self.interner)));
+                }
+                None => text.push_str(&item.resolve(&self.interner).to_string()),
+            }
+            if i + 1 == items.end && path.is_empty() {
+                range.end = text.chars().count();
+            }
+        }
+        (text, range)
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A persistent hash map, a hash array mapped trie, which `Value::Map`
+//! keeps its entries in. Copying a map takes constant time, and a copy
+//! shares its nodes with the original until either changes them, when
+//! only the nodes on the path to the changed entry are copied, so
+//! `insert`, `remove` and `get` take time logarithmic in the map's size.
+//!
+//! Each branch holds the children for 5 bits of their keys' hashes, those
+//! present flagged in a bitmap. A leaf holds the entries whose keys hash
+//! the same, and sits as near the root as the other keys allow, so maps
+//! with the same entries have the same shape.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::mem;
+use std::sync::Arc;
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+/// A map sharing its entries with its copies until either changes them
+pub struct Hamt<K, V> {
+    root: Option<Arc<Node<K, V>>>,
+    len: usize,
+}
+
+#[derive(Clone)]
+enum Node<K, V> {
+    /// The children for each 5 bits of hash set in the bitmap, in order
+    Branch(u32, Vec<Arc<Node<K, V>>>),
+    /// The entries whose keys have the hash
+    Leaf(u64, Vec<(K, V)>),
+}
+
+fn hash<T: Hash>(t: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    t.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The bit of a branch's bitmap for `hash` at `shift`
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+/// The index in a branch's children of the child for `bit`
+fn index(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl<K, V> Node<K, V> {
+    fn is_empty(&self) -> bool {
+        match self {
+            Node::Branch(_, children) => children.is_empty(),
+            Node::Leaf(_, entries) => entries.is_empty(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Hamt<K, V> {
+    pub fn new() -> Self {
+        Hamt { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let h = hash(key);
+        let mut node = self.root.as_deref()?;
+        let mut shift = 0;
+        loop {
+            match node {
+                Node::Branch(bitmap, children) => {
+                    let bit = bit(h, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    node = &children[index(*bitmap, bit)];
+                    shift += BITS;
+                }
+                Node::Leaf(leaf_hash, entries) => {
+                    if *leaf_hash != h {
+                        return None;
+                    }
+                    return entries.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+                }
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Map `key` to `value`, returning the value it replaces, if any
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let h = hash(&key);
+        let old = match &mut self.root {
+            Some(root) => insert(root, 0, h, key, value),
+            None => {
+                self.root = Some(Arc::new(Node::Leaf(h, vec![(key, value)])));
+                None
+            }
+        };
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Remove `key`, returning its value, if any
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        // Checked first, so that nodes shared with a copy aren't copied
+        // for a key that isn't there
+        if !self.contains_key(key) {
+            return None;
+        }
+        let root = self.root.as_mut().unwrap();
+        let value = remove(root, 0, hash(key), key);
+        if root.is_empty() {
+            self.root = None;
+        }
+        self.len -= 1;
+        value
+    }
+
+    /// The entries, in an order fixed by the hashes of their keys
+    pub fn iter(&self) -> std::vec::IntoIter<(&K, &V)> {
+        fn walk<'a, K, V>(node: &'a Node<K, V>, entries: &mut Vec<(&'a K, &'a V)>) {
+            match node {
+                Node::Branch(_, children) => children.iter().for_each(|c| walk(c, entries)),
+                Node::Leaf(_, leaf) => entries.extend(leaf.iter().map(|(k, v)| (k, v))),
+            }
+        }
+        let mut entries = Vec::with_capacity(self.len);
+        if let Some(root) = &self.root {
+            walk(root, &mut entries);
+        }
+        entries.into_iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+}
+
+fn insert<K: Clone + Eq, V: Clone>(
+    node: &mut Arc<Node<K, V>>,
+    shift: u32,
+    h: u64,
+    key: K,
+    value: V,
+) -> Option<V> {
+    if let Node::Leaf(leaf_hash, _) = **node {
+        if leaf_hash != h {
+            // Move the leaf down into a branch, beside the new entry
+            let leaf = node.clone();
+            *node = Arc::new(Node::Branch(bit(leaf_hash, shift), vec![leaf]));
+        }
+    }
+    match Arc::make_mut(node) {
+        Node::Branch(bitmap, children) => {
+            let bit = bit(h, shift);
+            let i = index(*bitmap, bit);
+            if *bitmap & bit == 0 {
+                *bitmap |= bit;
+                children.insert(i, Arc::new(Node::Leaf(h, vec![(key, value)])));
+                None
+            } else {
+                insert(&mut children[i], shift + BITS, h, key, value)
+            }
+        }
+        Node::Leaf(_, entries) => match entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => Some(mem::replace(v, value)),
+            None => {
+                entries.push((key, value));
+                None
+            }
+        },
+    }
+}
+
+/// Remove `key`, which must be in the map, then lift a lone leaf left in a
+/// branch into the branch's place
+fn remove<K: Clone + Eq, V: Clone>(
+    node: &mut Arc<Node<K, V>>,
+    shift: u32,
+    h: u64,
+    key: &K,
+) -> Option<V> {
+    let value = match Arc::make_mut(node) {
+        Node::Branch(bitmap, children) => {
+            let bit = bit(h, shift);
+            let i = index(*bitmap, bit);
+            let value = remove(&mut children[i], shift + BITS, h, key);
+            if children[i].is_empty() {
+                children.remove(i);
+                *bitmap &= !bit;
+            }
+            value
+        }
+        Node::Leaf(_, entries) => {
+            let i = entries.iter().position(|(k, _)| k == key)?;
+            Some(entries.remove(i).1)
+        }
+    };
+    if let Node::Branch(_, children) = &**node {
+        if let [child] = &children[..] {
+            if let Node::Leaf(..) = **child {
+                *node = child.clone();
+            }
+        }
+    }
+    value
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Default for Hamt<K, V> {
+    fn default() -> Self {
+        Hamt::new()
+    }
+}
+
+impl<K, V> Clone for Hamt<K, V> {
+    /// Share the entries, in constant time
+    fn clone(&self) -> Self {
+        Hamt {
+            root: self.root.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq> PartialEq for Hamt<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + Eq> Eq for Hamt<K, V> {}
+
+impl<K: Clone + Eq + Hash, V: Clone + Hash> Hash for Hamt<K, V> {
+    /// Hash the entries in any order, as equal maps may order those whose
+    /// keys hash the same differently
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        let sum = self
+            .iter()
+            .fold(0u64, |sum, entry| sum.wrapping_add(hash(&entry)));
+        sum.hash(state);
+    }
+}
+
+impl<K: Clone + Eq + Hash + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug for Hamt<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Extend<(K, V)> for Hamt<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> FromIterator<(K, V)> for Hamt<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut map = Hamt::new();
+        map.extend(entries);
+        map
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Content hashes of expressions and values that are stable across
+//! interners, runs, and platforms, unlike their `Hash` impls, which hash
+//! interner IDs. Symbols are hashed by name, so equal source gives equal
+//! hashes however its symbols were interned, e.g. for caches kept on disk.
+//!
+//! The hash is 64-bit FNV-1a over the `.uccb` encoding of the expression,
+//! with each name written in place of its index.
+
+use crate::binary::INTRINSICS;
+use crate::core::{Expr, Interner, Intrinsic, Symbol, Value, ValueStack};
+use crate::map::ValueMap;
+#[cfg(feature = "bignum")]
+use crate::uccb::TAG_NAT;
+#[cfg(feature = "rational")]
+use crate::uccb::TAG_RATIO;
+use crate::uccb::{
+    TAG_CALL, TAG_CHAR, TAG_COMPOSE, TAG_HOST, TAG_INTRINSIC, TAG_MAP, TAG_PAIR, TAG_QUOTE,
+    TAG_STR, TAG_SYMBOL,
+};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Values and stacks, which the `.uccb` format doesn't encode
+const TAG_VALUE_CALL: u8 = 0x20;
+const TAG_STACK: u8 = 0x21;
+
+struct StableHasher<'a> {
+    interner: &'a Interner,
+    hash: u64,
+}
+
+impl StableHasher<'_> {
+    fn byte(&mut self, b: u8) {
+        self.hash ^= b as u64;
+        self.hash = self.hash.wrapping_mul(FNV_PRIME);
+    }
+
+    fn len(&mut self, n: usize) {
+        (n as u64).to_le_bytes().iter().for_each(|b| self.byte(*b));
+    }
+
+    fn name(&mut self, tag: u8, sym: &Symbol) {
+        let name = self.interner.resolve(&sym.0);
+        self.byte(tag);
+        self.len(name.len());
+        name.bytes().for_each(|b| self.byte(b));
+    }
+
+    #[cfg(feature = "bignum")]
+    fn nat(&mut self, n: &num_bigint::BigUint) {
+        let bytes = n.to_bytes_le();
+        self.byte(TAG_NAT);
+        self.len(bytes.len());
+        bytes.iter().for_each(|b| self.byte(*b));
+    }
+
+    #[cfg(feature = "rational")]
+    fn ratio(&mut self, r: &num_rational::BigRational) {
+        let (sign, numer) = r.numer().to_bytes_le();
+        let (_, denom) = r.denom().to_bytes_le();
+        self.byte(TAG_RATIO);
+        self.byte((sign == num_bigint::Sign::Minus) as u8);
+        for bytes in [numer, denom] {
+            self.len(bytes.len());
+            bytes.iter().for_each(|b| self.byte(*b));
+        }
+    }
+
+    fn char(&mut self, c: char) {
+        self.byte(TAG_CHAR);
+        (c as u32).to_le_bytes().iter().for_each(|b| self.byte(*b));
+    }
+
+    fn str(&mut self, s: &str) {
+        self.byte(TAG_STR);
+        (s.len() as u64)
+            .to_le_bytes()
+            .iter()
+            .for_each(|b| self.byte(*b));
+        s.bytes().for_each(|b| self.byte(b));
+    }
+
+    /// A map's entries hashed in any order, as the order they are kept in
+    /// depends on the symbols' numbering, which the hash doesn't
+    fn map(&mut self, m: &ValueMap) {
+        self.byte(TAG_MAP);
+        self.len(m.len());
+        let sum = m.iter().fold(0u64, |sum, (k, v)| {
+            sum.wrapping_add(stable_hash(self.interner, |h| {
+                h.value(k);
+                h.value(v);
+            }))
+        });
+        sum.to_le_bytes().iter().for_each(|b| self.byte(*b));
+    }
+
+    fn expr(&mut self, e: &Expr) {
+        match e {
+            Expr::Compose(es) => {
+                self.byte(TAG_COMPOSE);
+                self.len(es.len());
+                es.iter().for_each(|e| self.expr(e));
+            }
+            Expr::Quote(e) => {
+                self.byte(TAG_QUOTE);
+                self.expr(e);
+            }
+            Expr::Call(sym) => self.name(TAG_CALL, sym),
+            Expr::Symbol(sym) => self.name(TAG_SYMBOL, sym),
+            Expr::Intrinsic(Intrinsic::Host(sym)) => self.name(TAG_HOST, sym),
+            #[cfg(feature = "bignum")]
+            Expr::Nat(n) => self.nat(n),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(r) => self.ratio(r),
+            Expr::Pair(p) => {
+                self.byte(TAG_PAIR);
+                self.expr(&p.0);
+                self.expr(&p.1);
+            }
+            Expr::Map(m) => self.map(m),
+            Expr::Char(c) => self.char(*c),
+            Expr::Str(s) => self.str(s),
+            Expr::Intrinsic(intr) => {
+                let n = INTRINSICS.iter().position(|i| i == intr).unwrap();
+                self.byte(TAG_INTRINSIC + n as u8);
+            }
+        }
+    }
+
+    fn value(&mut self, v: &Value) {
+        match v {
+            Value::Call(sym) => self.name(TAG_VALUE_CALL, sym),
+            Value::Symbol(sym) => self.name(TAG_SYMBOL, sym),
+            #[cfg(feature = "bignum")]
+            Value::Nat(n) => self.nat(n),
+            #[cfg(feature = "rational")]
+            Value::Ratio(r) => self.ratio(r),
+            Value::Pair(p) => {
+                self.byte(TAG_PAIR);
+                self.value(&p.0);
+                self.value(&p.1);
+            }
+            Value::Map(m) => self.map(m),
+            Value::Char(c) => self.char(*c),
+            Value::Str(s) => self.str(s),
+            Value::Quote(e) => {
+                self.byte(TAG_QUOTE);
+                self.expr(e);
+            }
+        }
+    }
+}
+
+fn stable_hash<F: FnOnce(&mut StableHasher)>(interner: &Interner, f: F) -> u64 {
+    let mut hasher = StableHasher {
+        interner,
+        hash: FNV_OFFSET,
+    };
+    f(&mut hasher);
+    hasher.hash
+}
+
+impl Expr {
+    pub fn stable_hash(&self, interner: &Interner) -> u64 {
+        stable_hash(interner, |h| h.expr(self))
+    }
+}
+
+impl Value {
+    pub fn stable_hash(&self, interner: &Interner) -> u64 {
+        stable_hash(interner, |h| h.value(self))
+    }
+}
+
+impl ValueStack {
+    pub fn stable_hash(&self, interner: &Interner) -> u64 {
+        stable_hash(interner, |h| {
+            h.byte(TAG_STACK);
+            h.len(self.0.len());
+            self.0.iter().for_each(|v| h.value(v));
+        })
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The text shown by `:help` and `:help <topic>`, where a topic is one of
+//! `TOPICS` or the name of an intrinsic.
+
+pub struct HelpTopic {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub text: &'static str,
+}
+
+pub struct IntrinsicHelp {
+    pub name: &'static str,
+    /// The small step rule, in the notation of the evaluation output
+    pub rule: &'static str,
+    pub summary: &'static str,
+}
+
+pub static TOPICS: &[HelpTopic] = &[
+    HelpTopic {
+        name: "commands",
+        summary: "the commands available (the default)",
+        text: COMMANDS,
+    },
+    HelpTopic {
+        name: "syntax",
+        summary: "expressions, definitions, and rules",
+        text: SYNTAX,
+    },
+    HelpTopic {
+        name: "intrinsics",
+        summary: "the builtin operations and their rules",
+        text: "Intrinsics, and the small step each takes:\n",
+    },
+    HelpTopic {
+        name: "trace",
+        summary: "how to read evaluation output",
+        text: TRACE,
+    },
+];
+
+pub static INTRINSICS: &[IntrinsicHelp] = &[
+    IntrinsicHelp {
+        name: "swap",
+        rule: "⟨v1 v2⟩ swap ⟶ ⟨v2 v1⟩",
+        summary: "exchange the top two values",
+    },
+    IntrinsicHelp {
+        name: "clone",
+        rule: "⟨v⟩ clone ⟶ ⟨v v⟩",
+        summary: "duplicate the top value",
+    },
+    IntrinsicHelp {
+        name: "drop",
+        rule: "⟨v⟩ drop ⟶ ⟨⟩",
+        summary: "discard the top value",
+    },
+    IntrinsicHelp {
+        name: "quote",
+        rule: "⟨v⟩ quote ⟶ ⟨[v]⟩",
+        summary: "wrap the top value in a quotation",
+    },
+    IntrinsicHelp {
+        name: "compose",
+        rule: "⟨[e1] [e2]⟩ compose ⟶ ⟨[e1 e2]⟩",
+        summary: "join the top two quotations",
+    },
+    IntrinsicHelp {
+        name: "apply",
+        rule: "⟨[e]⟩ apply ⟶ ⟨⟩ e",
+        summary: "evaluate the quotation on top of the stack",
+    },
+    IntrinsicHelp {
+        name: "defined?",
+        rule: "⟨'s⟩ defined? ⟶ ⟨true⟩, or ⟨false⟩ if `s` is undefined",
+        summary: "test whether a symbol is defined",
+    },
+    IntrinsicHelp {
+        name: "body-of",
+        rule: "⟨'s⟩ body-of ⟶ ⟨[e]⟩, given {fn s = e}",
+        summary: "get the definition of a symbol",
+    },
+    IntrinsicHelp {
+        name: "define!",
+        rule: "⟨'s [e]⟩ define! ⟶ ⟨⟩, defining {fn s = e}",
+        summary: "define a symbol while evaluating",
+    },
+    IntrinsicHelp {
+        name: "evalq",
+        rule: "⟨[defs] [e]⟩ evalq ⟶ ⟨[vs]⟩, given ⟨⟩ e ⇓ ⟨vs⟩ with only `defs`",
+        summary: "evaluate a quoted program with quoted definitions",
+    },
+];
+
+static COMMANDS: &str = "\
+Commands available:
+
+   <expr>                   evaluate <expr>
+   {fn <sym> = <expr>}      define <sym> as <expr>
+   {rule <pat> => <pat>}    rewrite matches of the first <pat> to the second
+   {local fn <sym> = <expr> ... in <expr>}
+                            evaluate the last <expr> with each <sym>
+                            defined as its <expr> only meanwhile
+   :trace <expr>            trace the evaluation of <expr>
+   :trace-until ⟨<pattern>⟩ <expr>
+                            trace the evaluation of <expr> until the stack
+                            matches <pattern>, e.g. ⟨... [$f] _⟩
+   :trace-vm <expr>         compile <expr> for the bytecode VM, and trace
+                            the ops it runs
+   :disasm <sym>            show the bytecode compiled for <sym> and the
+                            functions it calls, then optimized
+   :bench <expr>            evaluate <expr>, showing its result and how many
+                            steps it took and how long
+   :show <sym>              show the definition of <sym>
+   :copy <sym> <new>        define <new> as a copy of <sym>
+   :copy deep <sym> <new>   also copy the definitions of <sym>'s namespace
+                            it uses, as <new>.<name>, and have the copies
+                            call each other
+   :grep <pattern>          list the definitions with a run of items that
+                            matches <pattern>, e.g. [$f] apply
+   :grep \"<text>\"           list the definitions whose names contain <text>
+   :flatten <expr>          expand every call in <expr>, again and again,
+                            and simplify what's left
+   :minimize <expr>         shrink <expr>, which fails, to the smallest part
+                            of it found to fail the same way
+   :subst <sym> <e1> in <e2>
+                            replace every call to <sym> in <e2> by <e1>,
+                            without defining anything
+   :export-latex <expr> \"<path>\"
+                            save to <path> the derivation of <expr>, as
+                            LaTeX, with the rule each step takes
+   :exercise \"<path>\"       show the prompt of the exercise at <path>
+   :check-exercise          check the definitions against the hidden cases
+                            of the exercise, showing how many pass
+   :load \"<path>\"           load definitions and expressions from <path>,
+                            from the ucc blocks of a .md file, or
+                            definitions from a packed .uccb file; the
+                            definitions are made first, all or none
+   :diff-defs \"<path>\"      show how the definitions in <path> differ from
+                            those of the session
+   :use <lib>@<version>     load the bundle <lib> into the namespace <lib>
+   :plugin load \"<path>\"    load the plugin library at <path>
+   :set <name> <value>      change the setting <name> to <value>
+   :unset <name>            restore the setting <name> to its default
+   :settings                list the settings and their values
+   :save-settings           save the settings for future sessions
+   :list                    list the defined symbols
+   :stack                   show the value stack
+   :stack full              show all of the value stack, however deep
+   :ref                     list the results of the last 100 evaluations,
+                            which %1, %2, ... push in later expressions
+   :rules                   list the rewrite rules
+   :drop                    drop the current value stack
+   :stats interner          show how many symbols have been interned
+   :jobs                    list the running evaluations
+   :kill <n>                abort the evaluation numbered <n>
+   :watchpoint ⟨<pattern>⟩  pause evaluations when the stack comes to match
+                            <pattern>, e.g. ⟨[true] ...⟩
+   :watchpoints             list the watchpoints
+   :unwatch <n>             remove the watchpoint numbered <n>
+   :continue [<n>]          continue the evaluation paused at a watchpoint,
+                            or the one numbered <n>
+   :terminates? <sym>       check whether <sym> terminates on every stack
+   :check <mode> <sym>      check that <sym> is linear, using no clone or
+                            drop, or affine, using no clone, and show where
+                            it does
+   :why [<sym>]             explain how the top value, or the topmost <sym>,
+                            was made, if the provenance setting is on
+   :equiv [<expr>] [<expr>] test whether the expressions behave the same on
+                            many small stacks
+   :search ⟨<values>⟩ ⇓ ⟨<values>⟩
+                            find compositions that evaluate the first
+                            values to the second
+   :atlas <n> [<expr>] \"<path>\"
+                            save to <path>, as JSON, every expression of up
+                            to <n> nodes built from the intrinsics and calls
+                            in <expr>, or all intrinsics, grouped by their
+                            result on ⟨[a] [b] [c]⟩
+   :clear                   clear all definitions
+   :reset                   reset the interpreter
+   :version                 show the version and the optional features
+                            compiled in
+   :quit [<code>]           exit, with the exit code <code>, or 0
+   :help [<topic>]          display this list of commands, or help on <topic>
+";
+
+static SYNTAX: &str = "\
+Syntax:
+
+   swap clone ...           an intrinsic (see :help intrinsics)
+   <sym>                    a call to the function <sym>
+   '<sym>                   the symbol <sym>, as a value
+   [<expr>]                 a quotation, a value holding <expr>
+   (<expr>)                 grouping
+   <expr> <expr>            composition, evaluated left to right
+   {fn <sym> = <expr>}      a definition
+   {rule <pat> => <pat>}    a rewrite rule, where `$x` in the first pattern
+                            matches any expression, and the same in both
+   <lib>.<sym>              <sym> from the bundle loaded as <lib>
+   it                       the top value the last evaluation left, unless
+                            a function `it` is defined
+   -- <comment>             a comment, up to the end of the line
+";
+
+static TRACE: &str = "\
+Evaluation output:
+
+   ⟨<values>⟩ <expr>        the value stack, and the expression to evaluate
+   ⟶ ⟨<values>⟩ <expr>      the result of a step, shown by :trace, and by
+                            :set show-steps <n> every <n>th step
+   = ⟨<values>⟩ <expr>      the same, with values replaced by the names of
+                            the functions they define
+   ⇓ ⟨<values>⟩ <expr>      the final result, where <expr> is empty unless
+                            evaluation failed
+   in f → in g → drop       where evaluation failed, inside calls to f and g
+";
+
+/// The help text for `topic`, or for the commands if `topic` is `None`
+pub fn help(topic: Option<&str>) -> Option<String> {
+    let name = topic.unwrap_or("commands");
+    if let Some(topic) = TOPICS.iter().find(|topic| topic.name == name) {
+        let mut text = topic.text.to_owned();
+        match topic.name {
+            "commands" => text.push_str(&topics()),
+            "intrinsics" => {
+                text.push('\n');
+                for intrinsic in INTRINSICS {
+                    text.push_str(&format!(
+                        "   {:<11}{}\n              {}\n",
+                        intrinsic.name, intrinsic.summary, intrinsic.rule
+                    ));
+                }
+            }
+            _ => {}
+        }
+        return Some(text);
+    }
+    let intrinsic = INTRINSICS.iter().find(|intrinsic| intrinsic.name == name)?;
+    Some(format!(
+        "{}: {}\n\n   {}\n",
+        intrinsic.name, intrinsic.summary, intrinsic.rule
+    ))
+}
+
+/// The names of the commands, as `:load`, from their list in the help
+pub(crate) fn command_names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.lines().filter_map(|line| {
+        let line = line
+            .strip_prefix("   ")
+            .filter(|line| line.starts_with(':'))?;
+        Some(line.split(' ').next().unwrap_or(line))
+    })
+}
+
+/// The list of topics
+pub fn topics() -> String {
+    let mut text = "\nHelp topics, shown by :help <topic>:\n\n".to_owned();
+    for topic in TOPICS {
+        text.push_str(&format!("   {:<25}{}\n", topic.name, topic.summary));
+    }
+    text.push_str("   <intrinsic>              an intrinsic, e.g. :help swap\n");
+    text
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Callbacks run during evaluation, so that tools such as profilers,
+//! debuggers, and coverage reports can observe it without changing the
+//! evaluator, e.g.
+//!
+//! ```
+//! # use ucc::core::*;
+//! # use std::cell::Cell;
+//! # use std::rc::Rc;
+//! let mut ctx = Context::default();
+//! let steps = Rc::new(Cell::new(0));
+//! let counter = Rc::clone(&steps);
+//! ctx.on_step(move |_vs, _e| counter.set(counter.get() + 1));
+//! ```
+
+use crate::core::{Context, EvalError, Expr, Symbol, ValueStack};
+use crate::rules::RuleId;
+use std::rc::Rc;
+
+/// Run before each small step, with the state it starts from
+pub type StepHook = dyn Fn(&ValueStack, &Expr);
+/// Run when a call is evaluated, with the value stack it is called on,
+/// whether it names a function, a host intrinsic, or nothing
+pub type CallHook = dyn Fn(Symbol, &ValueStack);
+/// Run when a small step fails, with the state it failed in
+pub type ErrorHook = dyn Fn(&EvalError, &ValueStack, &Expr);
+/// Run after each small step that succeeds, with the rule it took and the
+/// state it led to
+pub type RuleHook = dyn Fn(RuleId, &ValueStack, &Expr);
+
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    on_step: Vec<Rc<StepHook>>,
+    on_call: Vec<Rc<CallHook>>,
+    on_error: Vec<Rc<ErrorHook>>,
+    on_rule: Vec<Rc<RuleHook>>,
+}
+
+impl Context {
+    /// Run `f` before each small step, after any registered before it.
+    pub fn on_step<F>(&mut self, f: F)
+    where
+        F: Fn(&ValueStack, &Expr) + 'static,
+    {
+        self.hooks.on_step.push(Rc::new(f));
+    }
+
+    /// Run `f` whenever a call is evaluated, after any registered before
+    /// it.
+    pub fn on_call<F>(&mut self, f: F)
+    where
+        F: Fn(Symbol, &ValueStack) + 'static,
+    {
+        self.hooks.on_call.push(Rc::new(f));
+    }
+
+    /// Run `f` whenever a small step fails, after any registered before it.
+    pub fn on_error<F>(&mut self, f: F)
+    where
+        F: Fn(&EvalError, &ValueStack, &Expr) + 'static,
+    {
+        self.hooks.on_error.push(Rc::new(f));
+    }
+
+    /// Run `f` after each small step that succeeds, with the rule it took,
+    /// after any registered before it.
+    pub fn on_rule<F>(&mut self, f: F)
+    where
+        F: Fn(RuleId, &ValueStack, &Expr) + 'static,
+    {
+        self.hooks.on_rule.push(Rc::new(f));
+    }
+
+    /// Remove every hook.
+    pub fn clear_hooks(&mut self) {
+        self.hooks = Hooks::default();
+    }
+
+    pub(crate) fn step_hooks(&self, vs: &ValueStack, e: &Expr) {
+        for f in self.hooks.on_step.iter() {
+            f(vs, e);
+        }
+    }
+
+    pub(crate) fn call_hooks(&self, sym: Symbol, vs: &ValueStack) {
+        for f in self.hooks.on_call.iter() {
+            f(sym, vs);
+        }
+    }
+
+    /// The rule the next step on `e` takes, if any hook is run with it
+    pub(crate) fn hooked_rule(&self, e: &Expr) -> Option<RuleId> {
+        if self.hooks.on_rule.is_empty() {
+            None
+        } else {
+            self.next_rule(e)
+        }
+    }
+
+    pub(crate) fn rule_hooks(&self, rule: Option<RuleId>, vs: &ValueStack, e: &Expr) {
+        if let Some(rule) = rule {
+            for f in self.hooks.on_rule.iter() {
+                f(rule, vs, e);
+            }
+        }
+    }
+
+    pub(crate) fn error_hooks(&self, err: &EvalError, vs: &ValueStack, e: &Expr) {
+        for f in self.hooks.on_error.iter() {
+            f(err, vs, e);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Intrinsics defined by the embedding program. A registered name is
+//! evaluated by the host's function, unless the name is also defined as a
+//! function, e.g.
+//!
+//! ```
+//! # use ucc::core::*;
+//! let mut ctx = Context::default();
+//! // `dup` behaves like `clone`
+//! ctx.register_intrinsic("dup", 1, |vs| {
+//!     let v = vs.pop().unwrap();
+//!     vs.push(v.clone());
+//!     vs.push(v);
+//!     Ok(Expr::default())
+//! });
+//! ```
+
+use crate::core::{Context, EvalError, Expr, Intrinsic, Symbol, ValueStack};
+use std::rc::Rc;
+
+pub type HostFn = dyn Fn(&mut ValueStack) -> Result<Expr, EvalError>;
+
+/// A host-defined intrinsic, which pops `arity` values and continues by
+/// evaluating the returned expression
+#[derive(Clone)]
+pub(crate) struct HostIntrinsic {
+    pub(crate) arity: usize,
+    pub(crate) f: Rc<HostFn>,
+}
+
+impl Context {
+    /// Register `name` as an intrinsic that needs `arity` values on the
+    /// stack, replacing any intrinsic already registered with that name.
+    pub fn register_intrinsic<F>(&mut self, name: &str, arity: usize, f: F)
+    where
+        F: Fn(&mut ValueStack) -> Result<Expr, EvalError> + 'static,
+    {
+        let sym = Symbol(self.interner.get_or_intern(name));
+        self.host_intrinsics.insert(
+            sym,
+            HostIntrinsic {
+                arity,
+                f: Rc::new(f),
+            },
+        );
+    }
+
+    /// The intrinsic registered as `sym`, if any
+    pub(crate) fn host_intrinsic(&self, sym: &Symbol) -> Option<Intrinsic> {
+        if self.host_intrinsics.contains_key(sym) {
+            Some(Intrinsic::Host(*sym))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn host_step(
+        &mut self,
+        sym: Symbol,
+        vs: &mut ValueStack,
+        e: &mut Expr,
+    ) -> Result<(), EvalError> {
+        let host = self
+            .host_intrinsics
+            .get(&sym)
+            .ok_or(EvalError::UndefinedFn(sym))?;
+        if vs.0.len() < host.arity {
+            return Err(EvalError::TooFewValues {
+                available: vs.0.len(),
+                expected: host.arity,
+            });
+        }
+        *e = (host.f)(vs)?;
+        Ok(())
+    }
+}
@@ -4,96 +4,893 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::builtin::FN_DEF_SRCS;
+use crate::atlas::{AtlasSpec, MAX_SIZE};
+use crate::builder::{Clock, Files, SystemClock, SystemFiles};
+use crate::builtin::PRELUDE_UCCB;
+use crate::bundle::default_bundle_path;
+use crate::capabilities::{capabilities, Capabilities};
+use crate::config::Config;
+use crate::confusables::normalize;
 use crate::core::*;
+use crate::coverage::{Coverage, CoverageReport};
+use crate::diff::DefDiff;
 use crate::display::*;
+use crate::equiv::{Distinction, EquivLimits, Observation};
+use crate::exercise::{parse_exercise, Exercise};
+use crate::grep::GrepQuery;
+use crate::help::{help, topics};
+use crate::latex::LatexError;
+use crate::limits::DefinitionLimits;
+use crate::load::{is_url, load_source, LoadError};
+use crate::locals::Scope;
+use crate::markdown::{code_blocks, matches_expected};
 use crate::parse::*;
-use std::io;
+use crate::plugin::{Plugin, PluginError};
+use crate::provenance::Provenance;
+use crate::rewrite::*;
+use crate::search::SearchLimits;
+use crate::settings::*;
+use crate::shared::SharedPrelude;
+use crate::stack_pattern::StackPattern;
+use crate::stream::ItemReader;
+use crate::substructural::{Discipline, Violation};
+use crate::termination::Termination;
+use crate::transaction::Validation;
+use crate::vm::{Thread, Vm, VmError};
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+/// The most states of a loop shown by `:terminates?`
+const MAX_LOOP_LINES: usize = 10;
+/// The most violations shown by `:check`
+const MAX_VIOLATION_LINES: usize = 10;
+/// The most results kept for `%1`, `%2`, ..., the earliest expiring first
+const MAX_RESULTS: usize = 100;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum InterpItem {
     FnDef(FnDef),
+    Rule(Rule),
     Expr(Expr),
+    /// `{local fn <sym> = <expr> ... in <expr>}`
+    Local(Vec<FnDef>, Expr),
+    /// The end of the scope of a `Local`, put after its expression once
+    /// its definitions are pushed
+    EndLocal,
+    /// The value stack expected at this point, as written by an `output`
+    /// block of a literate source
+    Expect(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum InterpCommand {
     Eval(Vec<InterpItem>),
     Trace(Expr),
+    TraceUntil(StackPattern, Expr),
+    /// An expression to trace the ops of, with the machine running it once
+    /// it has started
+    TraceVm(Expr, Option<Box<(Vm, Thread)>>),
+    Disasm(Symbol),
+    /// An expression to time, with the time it started and the steps taken
+    /// once it has
+    Bench(Expr, Option<(Duration, usize)>),
     Show(Symbol),
+    /// Copy a definition under a new name, with its helpers if set
+    Copy(Symbol, Symbol, bool),
+    Grep(GrepQuery),
+    Flatten(Expr),
+    Minimize(Expr),
+    /// Replace the calls to a symbol in an expression by another
+    Subst(Symbol, Expr, Expr),
+    ExportLatex(Expr, String),
+    Exercise(String),
+    CheckExercise,
+    Load(String, Option<String>),
+    DiffDefs(String),
+    Use(String, String),
+    PluginLoad(String),
+    Set(String, String),
+    Unset(String),
+    Settings,
+    SaveSettings,
     List,
+    /// Show the value stack, all of it if set
+    Stack(bool),
+    /// Ask the host to exit, with this code, or 0
+    Quit(Option<String>),
+    Refs,
+    Rules,
     Drop,
+    InternerStats,
+    Jobs,
+    Kill(String),
+    Watchpoint(StackPattern),
+    Watchpoints,
+    Unwatch(String),
+    Continue(Option<String>),
+    Terminates(Symbol),
+    /// `:check <discipline> <sym>`
+    Check(String, Symbol),
+    Why(Option<Symbol>),
+    Equiv(Expr, Expr),
+    Search(ValueStack, ValueStack),
+    Atlas(String, Option<Expr>, String),
     Clear,
     Reset,
-    Help,
+    Version,
+    Help(Option<String>),
+}
+
+/// The most steps of an expression `interp_step` takes at once, writing
+/// nothing between them unless the `show-steps` setting asks to
+const EVAL_CHUNK_STEPS: usize = 4096;
+
+/// The interpreter version and prelude, for display by frontends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub prelude: Vec<String>,
+    pub capabilities: Capabilities,
+}
+
+/// Counts of the work an interpreter has done, for hosts to export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InterpStats {
+    /// Commands started that evaluate, as expressions, traces, and loads
+    pub evaluations: u64,
+    pub small_steps: u64,
+    /// Small steps that failed
+    pub errors: u64,
+}
+
+impl std::ops::AddAssign for InterpStats {
+    fn add_assign(&mut self, other: Self) {
+        self.evaluations += other.evaluations;
+        self.small_steps += other.small_steps;
+        self.errors += other.errors;
+    }
+}
+
+/// A small step, as recorded for hosts streaming them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// The value stack the step starts from
+    pub stack: String,
+    /// The expression it steps
+    pub expr: String,
 }
 
-pub(crate) static HELP: &'static str = "\
-Commands available:
+/// Why an evaluation stopped early, each exiting a batch run with its own
+/// code, so scripts can tell them apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Evaluation failed, as by calling an undefined function
+    Eval,
+    /// The input didn't parse
+    Parse,
+    /// A stack didn't match the `⟨...⟩` expected after it
+    Assertion,
+    /// A definition or rule was refused
+    Define,
+}
 
-   <expr>                   evaluate <expr>
-   {fn <sym> = <expr>}      define <sym> as <expr>
-   :trace <expr>            trace the evaluation of <expr>
-   :show <sym>              show the definition of <sym>
-   :list                    list the defined symbols
-   :drop                    drop the current value stack
-   :clear                   clear all definitions
-   :reset                   reset the interpreter
-   :help                    display this list of commands
-";
+impl Failure {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Failure::Eval => 1,
+            Failure::Parse => 2,
+            Failure::Assertion => 3,
+            Failure::Define => 4,
+        }
+    }
+}
 
 pub struct Interp {
     ctx: Context,
     vs: ValueStack,
     command: Option<InterpCommand>,
     is_first_eval_step: bool,
+    /// The number of steps since the last one shown
+    eval_steps: usize,
+    trace: ExpansionTrace,
+    /// Where the values on `vs` came from, while the `provenance` setting
+    /// is on
+    provenance: Provenance,
+    bundle_path: Vec<PathBuf>,
+    prelude: Vec<Symbol>,
+    /// The prelude shared with other interpreters, if there is one
+    shared: Option<SharedPrelude>,
+    settings: Settings,
+    settings_path: Option<PathBuf>,
+    /// Why the last evaluation stopped early, if it failed
+    failure: Option<Failure>,
+    /// The exit code `:quit` asked for
+    quit: Option<i32>,
+    /// Set from outside, as by a signal handler, to stop every job
+    interrupt: Arc<AtomicBool>,
+    /// Running jobs, in the order they next step
+    jobs: VecDeque<Job>,
+    next_job_id: usize,
+    /// Patterns that pause a job when its stack comes to match them
+    watchpoints: Vec<(usize, StackPattern)>,
+    next_watchpoint_id: usize,
+    /// Read by `:bench`
+    pub(crate) clock: Rc<dyn Clock>,
+    /// Read and written by `:load`, `:atlas`, and the settings
+    pub(crate) files: Rc<dyn Files>,
+    /// Declared after `ctx`, so that libraries outlive their intrinsics
+    plugins: Vec<Plugin>,
+    /// Whether `:plugin load` may load libraries
+    allow_plugins: bool,
+    /// Whether `:load` and the like may fetch URLs
+    allow_net: bool,
+    /// The most steps of an expression `interp_step` takes at once
+    chunk_steps: usize,
+    stats: InterpStats,
+    /// The small steps taken while recording, until taken by the host
+    recorded_steps: Option<Vec<TraceStep>>,
+    /// Set by `:exercise`, checked by `:check-exercise`
+    exercise: Option<Exercise>,
+    /// The bytes the current command has printed, up to `output-max`
+    output_written: usize,
+    /// Whether the output of the current command has been cut
+    output_cut: bool,
+    /// The top value after each of the last `MAX_RESULTS` evaluations
+    /// that left one
+    results: VecDeque<Value>,
+    /// How many results have expired, which the first kept follows, so
+    /// it is `%{expired_results + 1}`
+    expired_results: usize,
+    /// The lines of the trace in progress, while the `align` setting holds
+    /// them back to line up their columns
+    trace_rows: Vec<TraceRow>,
+}
+
+/// A line of a trace: the arrow it starts with, if any, and the stack and
+/// expression after it
+struct TraceRow {
+    arrow: &'static str,
+    stack: String,
+    expr: String,
+}
+
+/// An evaluation in progress. Input entered while the foreground job runs
+/// starts a background job, with its own value stack that starts empty.
+struct Job {
+    id: usize,
+    input: String,
+    /// The state of a background job, or `None` for the foreground job,
+    /// whose state is the interpreter's own
+    state: Option<JobState>,
+    /// Whether the job stopped at a watchpoint, until `:continue`
+    paused: bool,
+}
+
+#[derive(Default)]
+struct JobState {
+    command: Option<InterpCommand>,
+    vs: ValueStack,
+    is_first_eval_step: bool,
+    eval_steps: usize,
+    trace: ExpansionTrace,
+    provenance: Provenance,
+    locals: Vec<Scope>,
+    trace_rows: Vec<TraceRow>,
 }
 
 impl Default for Interp {
     fn default() -> Self {
         let mut ctx = Context::default();
-        for fn_def_src in FN_DEF_SRCS.iter() {
-            let fn_def = FnDefParser::new()
-                .parse(&mut ctx.interner, fn_def_src)
-                .unwrap();
-            assert_eq!(ctx.define_fn(fn_def), None);
-        }
-        Self {
+        let prelude = ctx.import_prelude(PRELUDE_UCCB).unwrap();
+        Self::new(ctx, prelude, None)
+    }
+}
+
+impl Interp {
+    /// An interpreter using `prelude` in place of its own copy of the
+    /// builtin prelude, for hosts running many interpreters at once.
+    pub fn with_prelude(prelude: &SharedPrelude) -> Self {
+        Self::new(
+            Context::with_prelude(prelude),
+            prelude.symbols(),
+            Some(prelude.clone()),
+        )
+    }
+
+    #[cfg_attr(not(feature = "bignum"), allow(unused_mut))]
+    fn new(ctx: Context, prelude: Vec<Symbol>, shared: Option<SharedPrelude>) -> Self {
+        let mut interp = Self {
             ctx,
             vs: ValueStack::default(),
             command: None,
             is_first_eval_step: true,
+            eval_steps: 0,
+            trace: ExpansionTrace::default(),
+            provenance: Provenance::default(),
+            bundle_path: default_bundle_path(),
+            prelude,
+            shared,
+            settings: Settings::default(),
+            settings_path: default_settings_path(),
+            failure: None,
+            quit: None,
+            interrupt: Arc::default(),
+            jobs: VecDeque::new(),
+            next_job_id: 1,
+            watchpoints: vec![],
+            next_watchpoint_id: 1,
+            clock: Rc::new(SystemClock::default()),
+            files: Rc::new(SystemFiles),
+            plugins: vec![],
+            allow_plugins: true,
+            allow_net: true,
+            chunk_steps: EVAL_CHUNK_STEPS,
+            stats: InterpStats::default(),
+            recorded_steps: None,
+            exercise: None,
+            output_written: 0,
+            output_cut: false,
+            results: VecDeque::new(),
+            expired_results: 0,
+            trace_rows: vec![],
+        };
+        #[cfg(feature = "bignum")]
+        interp.ctx.register_time_intrinsics(interp.clock.clone());
+        interp
+    }
+
+    /// Read `clock` for `:bench` and the `time.` intrinsics
+    pub(crate) fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        #[cfg(feature = "bignum")]
+        self.ctx.register_time_intrinsics(clock.clone());
+        self.clock = clock;
+    }
+
+    /// Set the directories searched by `:use` for library bundles.
+    pub fn set_bundle_path(&mut self, bundle_path: Vec<PathBuf>) {
+        self.bundle_path = bundle_path;
+    }
+
+    /// Allow or refuse `:plugin load`, which runs a library's code.
+    pub fn set_allow_plugins(&mut self, allow: bool) {
+        self.allow_plugins = allow;
+    }
+
+    /// Allow or refuse fetching sources by URL.
+    pub fn set_allow_net(&mut self, allow: bool) {
+        self.allow_net = allow;
+    }
+
+    /// Take at most `steps` steps of an expression in each `interp_step`,
+    /// for hosts that count the steps between calls. With none, a step
+    /// only finishes what needs no more evaluation.
+    pub fn set_chunk_steps(&mut self, steps: usize) {
+        self.chunk_steps = steps.min(EVAL_CHUNK_STEPS);
+    }
+
+    /// Limit the definitions made from now on, as with
+    /// `Context::set_definition_limits`, not counting the prelude's
+    /// functions. The limits survive `:reset`.
+    pub fn set_definition_limits(&mut self, limits: DefinitionLimits) {
+        self.ctx.set_definition_limits(limits);
+    }
+
+    /// Register a host intrinsic, as with `Context::register_intrinsic`.
+    /// Registered intrinsics survive `:reset` and `:clear`.
+    pub fn register_intrinsic<F>(&mut self, name: &str, arity: usize, f: F)
+    where
+        F: Fn(&mut ValueStack) -> Result<Expr, EvalError> + 'static,
+    {
+        self.ctx.register_intrinsic(name, arity, f);
+    }
+
+    /// Start counting the calls of each function, for `coverage_report`.
+    pub fn track_coverage(&mut self) -> Coverage {
+        self.ctx.track_coverage()
+    }
+
+    /// Which functions defined since the prelude were called while
+    /// `coverage` was tracked.
+    pub fn coverage_report(&self, coverage: &Coverage) -> CoverageReport {
+        let syms: Vec<Symbol> = self
+            .ctx
+            .fn_defs()
+            .map(|(sym, _)| *sym)
+            .filter(|sym| !self.prelude.contains(sym))
+            .collect();
+        self.ctx.coverage_report(coverage, &syms)
+    }
+
+    /// The prompt frontends should show before reading a command.
+    pub fn prompt(&self) -> &str {
+        self.settings.prompt()
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Set the file used by `:save-settings` and `load_settings`.
+    pub fn set_settings_path(&mut self, settings_path: Option<PathBuf>) {
+        self.settings_path = settings_path;
+    }
+
+    /// Apply the settings in `config` and load its files, writing any
+    /// output to `w`.
+    pub fn apply_config(&mut self, config: &Config, w: &mut dyn io::Write) -> io::Result<()> {
+        if let Err(err) = config.apply_settings(&mut self.settings) {
+            w.write_fmt(format_args!("{:?}\n", err))?;
+        }
+        if !self.settings.prelude() {
+            self.drop_prelude();
+        }
+        for path in config.load.iter() {
+            self.load(path, None, w)?;
+            while !self.is_done() {
+                self.interp_step(w)?;
+            }
         }
+        w.flush()
     }
-}
 
-impl Interp {
+    pub(crate) fn drop_prelude(&mut self) {
+        self.ctx.shared = None;
+        self.prelude.clear();
+        if let Some(core) = self.ctx.interner.get(CORE) {
+            Arc::make_mut(&mut self.ctx.namespaces).shift_remove(&Symbol(core));
+        }
+    }
+
+    /// Update the settings from the settings file, if there is one.
+    pub fn load_settings(&mut self) -> Result<(), SettingError> {
+        let path = match &self.settings_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        match self.files.read(path) {
+            Ok(bytes) => {
+                let src =
+                    String::from_utf8(bytes).map_err(|err| SettingError::Io(err.to_string()))?;
+                self.settings.update_from_file_string(&src)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(SettingError::Io(err.to_string())),
+        }
+    }
+
+    pub fn version_info(&self) -> VersionInfo {
+        VersionInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            prelude: self
+                .prelude
+                .iter()
+                .map(|sym| sym.resolve(&self.ctx.interner))
+                .collect(),
+            capabilities: capabilities(),
+        }
+    }
+
+    pub fn stats(&self) -> InterpStats {
+        self.stats
+    }
+
+    /// A rough estimate of the bytes held by the values of every job, the
+    /// definitions made besides any shared prelude's, and the names of the
+    /// symbols, to compare sessions by
+    pub fn memory_estimate(&self) -> usize {
+        fn expr_bytes(e: &Expr) -> usize {
+            let mut nodes = 0;
+            e.visit(&mut |_| nodes += 1);
+            nodes * std::mem::size_of::<Expr>()
+        }
+        fn value_bytes(v: &Value) -> usize {
+            std::mem::size_of::<Value>()
+                + match v {
+                    Value::Quote(e) => expr_bytes(e),
+                    Value::Call(_) | Value::Symbol(_) | Value::Char(_) => 0,
+                    Value::Str(s) => s.len(),
+                    #[cfg(feature = "bignum")]
+                    Value::Nat(n) => n.to_bytes_le().len(),
+                    #[cfg(feature = "rational")]
+                    Value::Ratio(r) => ((r.numer().bits() + r.denom().bits()) / 8) as usize,
+                    Value::Pair(p) => value_bytes(&p.0) + value_bytes(&p.1),
+                    Value::Map(m) => m.iter().map(|(k, v)| value_bytes(k) + value_bytes(v)).sum(),
+                }
+        }
+        let stacks = std::iter::once(&self.vs).chain(
+            self.jobs
+                .iter()
+                .filter_map(|job| job.state.as_ref().map(|state| &state.vs)),
+        );
+        let values: usize = stacks.flat_map(|vs| vs.0.iter()).map(value_bytes).sum();
+        let fns: usize = self.ctx.fns.values().map(expr_bytes).sum();
+        values + fns + self.ctx.interner_stats().bytes
+    }
+
+    /// The startup banner, built from `version_info`.
+    pub fn banner(&self) -> String {
+        let info = self.version_info();
+        format!(
+            "Untyped Concatenative Calculus Interpreter (UCCI) v{}\n\
+             Loaded prelude: {}\n\
+             Type \":help\" to see the available commands.\n",
+            info.version,
+            info.prelude.join(" ")
+        )
+    }
+
+    /// Record each small step taken from now on, for `take_steps`, or stop
+    /// and forget those not yet taken.
+    pub fn record_steps(&mut self, on: bool) {
+        self.recorded_steps = if on { Some(vec![]) } else { None };
+    }
+
+    /// The small steps recorded since they were last taken
+    pub fn take_steps(&mut self) -> Vec<TraceStep> {
+        self.recorded_steps
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Stop every job, as `:kill` would each, leaving the values on the
+    /// stack as the foreground job left them.
+    pub fn cancel(&mut self) {
+        self.jobs.clear();
+        self.trace_rows.clear();
+        if self.command.take().is_some() {
+            self.ctx.clear_locals(&mut self.vs);
+        }
+    }
+
+    /// A flag that, once set, as by a handler of Ctrl-C, has the next call
+    /// to `interp_step` cancel every job and print `Interrupted.`. Input
+    /// clears it, so it is only set while nothing has handled it.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    /// Why the last input or run stopped early, if it failed
+    pub fn failure(&self) -> Option<Failure> {
+        self.failure
+    }
+
+    /// The exit code `:quit` asked the host to exit with, if it has
+    pub fn quit_code(&self) -> Option<i32> {
+        self.quit
+    }
+
+    /// Whether every job has finished or is paused at a watchpoint
     pub fn is_done(&self) -> bool {
-        self.command.is_none()
+        let paused = self
+            .jobs
+            .iter()
+            .any(|job| job.state.is_none() && job.paused);
+        (self.command.is_none() || paused) && self.jobs.iter().all(|job| job.paused)
+    }
+
+    /// How many jobs `interp_step` takes turns stepping, counting the
+    /// foreground one even before its first step
+    pub fn running_jobs(&self) -> usize {
+        let unqueued = self.command.is_some() && self.jobs.iter().all(|job| job.state.is_some());
+        self.jobs.iter().filter(|job| !job.paused).count() + unqueued as usize
     }
 
     pub fn interp_start(&mut self, input: &str, w: &mut dyn io::Write) -> io::Result<()> {
-        match InterpCommandParser::new().parse(&mut self.ctx.interner, input) {
+        self.ctx.set_prefold(self.settings.prefold());
+        self.output_written = 0;
+        self.output_cut = false;
+        self.failure = None;
+        self.interrupt.store(false, Ordering::SeqCst);
+        self.guarded(w, |interp, w| interp.start(input, w))
+    }
+
+    pub fn interp_step(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
+        if self.interrupt.swap(false, Ordering::SeqCst) && !self.is_done() {
+            self.cancel();
+            w.write_fmt(format_args!("Interrupted.\n"))?;
+            return w.flush();
+        }
+        self.guarded(w, Interp::step_jobs)
+    }
+
+    /// Run `f` writing to `w` as the settings say: in ASCII if set, and
+    /// cut once the command has printed `output-max` bytes, stopping it
+    /// then if `output-overflow` is `abort`.
+    fn guarded<F>(&mut self, w: &mut dyn io::Write, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Interp, &mut dyn io::Write) -> io::Result<()>,
+    {
+        let mut guard = OutputGuard {
+            inner: w,
+            written: self.output_written,
+            max: self.settings.output_max(),
+            cut: self.output_cut,
+            at_line_start: true,
+        };
+        let result = if self.settings.ascii() {
+            f(self, &mut AsciiWriter(&mut guard))
+        } else {
+            f(self, &mut guard)
+        };
+        let newly_cut = guard.cut && !self.output_cut;
+        self.output_written = guard.written;
+        self.output_cut = guard.cut;
+        if newly_cut && self.settings.abort_on_output_overflow() {
+            self.cancel();
+            guard.inner.write_all(b"Stopped.\n")?;
+        }
+        result
+    }
+
+    /// Take steps until every job is done or `budget` has passed on the
+    /// interpreter's clock, taking at least one if any job is running, so
+    /// frontends can run for a frame at a time. Returns how many steps
+    /// were taken.
+    pub fn interp_run_for(&mut self, budget: Duration, w: &mut dyn io::Write) -> io::Result<usize> {
+        let start = self.clock.now();
+        let mut steps = 0;
+        while !self.is_done() {
+            self.interp_step(w)?;
+            steps += 1;
+            if self.clock.now().saturating_sub(start) >= budget {
+                break;
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Parse and evaluate the items in `reader` one at a time, without
+    /// reading it all into memory (see `stream`), stopping at the first
+    /// error or watchpoint. Returns whether it read every item.
+    pub fn run<R: BufRead>(&mut self, reader: R, w: &mut dyn io::Write) -> io::Result<bool> {
+        let mut items = ItemReader::new(reader);
+        self.failure = None;
+        while self.failure.is_none() {
+            match items.parse_next_item(&mut self.ctx.interner) {
+                Err(err) => {
+                    w.write_fmt(format_args!("{:?}\n", err))?;
+                    self.failure = Some(Failure::Parse);
+                    return Ok(false);
+                }
+                Ok(None) => return Ok(true),
+                Ok(Some(item)) => {
+                    let mut is = vec![item];
+                    self.check_typos(&mut is, w)?;
+                    self.is_first_eval_step = true;
+                    self.output_written = 0;
+                    self.output_cut = false;
+                    self.command = Some(InterpCommand::Eval(is));
+                    while !self.is_done() {
+                        self.interp_step(w)?;
+                    }
+                    if self.command.is_some() {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn start(&mut self, input: &str, w: &mut dyn io::Write) -> io::Result<()> {
+        let normalized = if self.settings.normalize_input() {
+            let (normalized, replaced) = normalize(input);
+            for (c, ascii) in replaced {
+                w.write_fmt(format_args!(
+                    "Warning: read `{}` (U+{:04X}) as `{}`.\n",
+                    c, c as u32, ascii
+                ))?;
+            }
+            Some(normalized)
+        } else {
+            None
+        };
+        let input = normalized.as_deref().unwrap_or(input);
+        let command = match InterpCommandParser::new().parse(&mut self.ctx.interner, input) {
             Err(err) => {
+                #[cfg(feature = "tracing")]
+                trace_parse_error(input, &err);
                 // TODO: better error messages
                 w.write_fmt(format_args!("{:?}\n", err))?;
+                self.failure = Some(Failure::Parse);
+                return w.flush();
+            }
+            Ok(command) => command,
+        };
+        let id = self.next_job_id;
+        let starts_job = matches!(
+            command,
+            InterpCommand::Eval(_)
+                | InterpCommand::Trace(_)
+                | InterpCommand::TraceUntil(..)
+                | InterpCommand::TraceVm(..)
+                | InterpCommand::Bench(..)
+                | InterpCommand::Load(..)
+        );
+        if starts_job {
+            self.stats.evaluations += 1;
+        }
+        if starts_job && self.command.is_some() {
+            let mut state = JobState::default();
+            self.swap_job_state(&mut state);
+            let result = self.start_command(command, &mut PrefixWriter::new(w, id));
+            self.swap_job_state(&mut state);
+            result?;
+            if state.command.is_some() {
+                self.push_job(input, Some(state));
+            }
+        } else {
+            let was_idle = self.command.is_none();
+            self.start_command(command, w)?;
+            if was_idle && self.command.is_some() {
+                self.push_job(input, None);
+            }
+        }
+        Ok(())
+    }
+
+    fn push_job(&mut self, input: &str, state: Option<JobState>) {
+        self.jobs.push_back(Job {
+            id: self.next_job_id,
+            input: input.trim().to_owned(),
+            state,
+            paused: false,
+        });
+        self.next_job_id += 1;
+    }
+
+    fn swap_job_state(&mut self, state: &mut JobState) {
+        std::mem::swap(&mut self.command, &mut state.command);
+        std::mem::swap(&mut self.vs, &mut state.vs);
+        std::mem::swap(&mut self.is_first_eval_step, &mut state.is_first_eval_step);
+        std::mem::swap(&mut self.eval_steps, &mut state.eval_steps);
+        std::mem::swap(&mut self.trace, &mut state.trace);
+        std::mem::swap(&mut self.provenance, &mut state.provenance);
+        std::mem::swap(&mut self.ctx.locals, &mut state.locals);
+        std::mem::swap(&mut self.trace_rows, &mut state.trace_rows);
+    }
+
+    /// Step the next job, prefixing its output with its number if other
+    /// jobs are running.
+    fn step_jobs(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
+        if self.command.is_some() && self.jobs.iter().all(|job| job.state.is_some()) {
+            // The foreground job was started other than by input, as by `run`
+            self.push_job("", None);
+        }
+        let mut job = match self.jobs.iter().position(|job| !job.paused) {
+            Some(i) => self.jobs.remove(i).unwrap(),
+            None => return w.flush(),
+        };
+        let mut prefixed;
+        let w: &mut dyn io::Write = if self.jobs.is_empty() {
+            w
+        } else {
+            prefixed = PrefixWriter::new(w, job.id);
+            &mut prefixed
+        };
+        let running = match job.state.as_mut() {
+            None => {
+                job.paused = self.watched_step(w)?;
+                self.command.is_some()
+            }
+            Some(state) => {
+                self.swap_job_state(state);
+                let result = self.watched_step(w);
+                self.swap_job_state(state);
+                job.paused = result?;
+                state.command.is_some()
             }
-            Ok(InterpCommand::Eval(is)) => {
+        };
+        if running {
+            self.jobs.push_back(job);
+        }
+        Ok(())
+    }
+
+    /// Step the current job, and return whether its stack came to match a
+    /// watchpoint, after writing where it paused.
+    fn watched_step(&mut self, w: &mut dyn io::Write) -> io::Result<bool> {
+        let matched: Vec<bool> = self
+            .watchpoints
+            .iter()
+            .map(|(_, pattern)| pattern.matches(&self.vs).is_some())
+            .collect();
+        self.step(w)?;
+        if self.command.is_none() {
+            // Stopping at an error leaves scopes of local definitions open
+            self.ctx.clear_locals(&mut self.vs);
+        }
+        let residual = match &self.command {
+            Some(InterpCommand::Eval(is)) => match is.first() {
+                Some(InterpItem::Expr(e)) => e.clone(),
+                _ => Expr::default(),
+            },
+            Some(InterpCommand::Trace(e))
+            | Some(InterpCommand::TraceUntil(_, e))
+            | Some(InterpCommand::Bench(e, _)) => e.clone(),
+            _ => return Ok(false),
+        };
+        let paused_at = self
+            .watchpoints
+            .iter()
+            .zip(matched)
+            .find(|((_, pattern), matched)| !matched && pattern.matches(&self.vs).is_some())
+            .map(|((id, _), _)| *id);
+        let id = match paused_at {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+        self.write_trace_rows(w)?;
+        w.write_fmt(format_args!(
+            "Paused at watchpoint {}: {} {}\n",
+            id,
+            self.show_stack(&self.vs),
+            self.ctx.display(&residual)
+        ))?;
+        w.flush()?;
+        Ok(true)
+    }
+
+    fn start_command(&mut self, command: InterpCommand, w: &mut dyn io::Write) -> io::Result<()> {
+        match command {
+            InterpCommand::Eval(mut is) => {
+                self.check_typos(&mut is, w)?;
                 self.is_first_eval_step = true;
                 self.command = Some(InterpCommand::Eval(is));
             }
-            Ok(InterpCommand::Trace(e)) => {
-                w.write_fmt(format_args!(
-                    "{} {}\n",
-                    self.vs.resolve(&self.ctx.interner),
-                    e.resolve(&self.ctx.interner)
-                ))?;
+            InterpCommand::Trace(mut e) => {
+                self.check_expr_typos(&mut e, &[], w)?;
+                self.trace_rows.clear();
+                self.trace_row("", &e, w)?;
+                self.trace.clear();
+                self.provenance.start();
                 self.command = Some(InterpCommand::Trace(e));
             }
-            Ok(InterpCommand::Show(sym)) => {
-                if let Some(e) = self.ctx.fns.get(&sym) {
+            InterpCommand::TraceVm(e, _) => self.start_trace_vm(e, w)?,
+            InterpCommand::Disasm(sym) => self.disasm(sym, w)?,
+            InterpCommand::Grep(query) => self.grep(&query, w)?,
+            InterpCommand::Flatten(e) => self.flatten(&e, w)?,
+            InterpCommand::Minimize(e) => self.minimize(&e, w)?,
+            InterpCommand::Subst(sym, by, e) => self.subst(sym, &by, &e, w)?,
+            InterpCommand::ExportLatex(e, path) => self.export_latex(&e, &path, w)?,
+            InterpCommand::Exercise(path) => self.exercise(&path, w)?,
+            InterpCommand::CheckExercise => self.check_exercise(w)?,
+            InterpCommand::TraceUntil(pattern, mut e) => {
+                self.check_expr_typos(&mut e, &[], w)?;
+                self.trace_rows.clear();
+                self.trace_row("", &e, w)?;
+                self.trace.clear();
+                self.provenance.start();
+                self.command = Some(InterpCommand::TraceUntil(pattern, e));
+            }
+            InterpCommand::Bench(mut e, _) => {
+                self.check_expr_typos(&mut e, &[], w)?;
+                self.trace.clear();
+                self.provenance.start();
+                self.command = Some(InterpCommand::Bench(e, Some((self.clock.now(), 0))));
+            }
+            InterpCommand::Show(sym) => {
+                if let Some(e) = self.ctx.lookup_fn(&sym) {
+                    // As while a job that defined it is paused
+                    let local = if self.ctx.is_local(&sym) {
+                        "local "
+                    } else {
+                        ""
+                    };
                     w.write_fmt(format_args!(
-                        "{{fn {} = {}}}\n",
+                        "{{{}fn {} = {}}}\n",
+                        local,
                         sym.resolve(&self.ctx.interner),
                         e.resolve(&self.ctx.interner)
                     ))?;
@@ -101,13 +898,157 @@ impl Interp {
                     w.write_fmt(format_args!("Not defined.\n"))?;
                 }
             }
-            Ok(InterpCommand::List) => {
+            InterpCommand::Copy(sym, new_sym, deep) => {
+                let fn_defs = match self.ctx.copy_defs(sym, new_sym, deep) {
+                    Some(fn_defs) => fn_defs,
+                    None => {
+                        w.write_fmt(format_args!("Not defined.\n"))?;
+                        return w.flush();
+                    }
+                };
+                for fn_def in fn_defs {
+                    let name = fn_def.0.resolve(&self.ctx.interner);
+                    self.expand_calls(fn_def.0);
+                    match self.ctx.define_fn(fn_def) {
+                        Ok(Some(_)) => {
+                            w.write_fmt(format_args!("Redefined `{}`.\n", name))?;
+                        }
+                        Ok(None) => {
+                            w.write_fmt(format_args!("Defined `{}`.\n", name))?;
+                        }
+                        Err(err) => {
+                            w.write_fmt(format_args!("{:?}\n", err))?;
+                            self.failure = Some(Failure::Define);
+                            return w.flush();
+                        }
+                    }
+                }
+                self.write_warnings(w)?;
+            }
+            InterpCommand::Load(path, checksum) => {
+                self.load(&path, checksum.as_deref(), w)?;
+            }
+            InterpCommand::DiffDefs(path) => {
+                self.diff_defs(&path, w)?;
+            }
+            InterpCommand::Use(name, version) => {
+                match self.ctx.use_bundle(&self.bundle_path, &name, &version) {
+                    Err(err) => {
+                        w.write_fmt(format_args!("{:?}\n", err))?;
+                    }
+                    Ok(None) => {
+                        w.write_fmt(format_args!("Loaded `{}@{}`.\n", name, version))?;
+                    }
+                    Ok(Some(old_version)) => {
+                        w.write_fmt(format_args!(
+                            "Replaced `{}@{}` with `{}@{}`.\n",
+                            name, old_version, name, version
+                        ))?;
+                    }
+                }
+            }
+            InterpCommand::PluginLoad(_) if !self.allow_plugins => {
+                w.write_fmt(format_args!("{:?}\n", PluginError::Disabled))?;
+            }
+            InterpCommand::PluginLoad(path) => match self.ctx.load_plugin(&path) {
+                Err((err, plugin)) => {
+                    self.plugins.extend(plugin);
+                    w.write_fmt(format_args!("{:?}\n", err))?;
+                }
+                Ok((plugin, defined)) => {
+                    self.plugins.push(plugin);
+                    let defined: Vec<String> =
+                        defined.iter().map(|name| format!("`{}`", name)).collect();
+                    w.write_fmt(format_args!(
+                        "Loaded plugin defining {}.\n",
+                        defined.join(", ")
+                    ))?;
+                    self.write_warnings(w)?;
+                }
+            },
+            InterpCommand::Set(name, value) => match self.settings.set(&name, &value) {
+                Err(err) => {
+                    w.write_fmt(format_args!("{:?}\n", err))?;
+                }
+                Ok(()) => {
+                    w.write_fmt(format_args!("Set `{}`.\n", name))?;
+                }
+            },
+            InterpCommand::Unset(name) => match self.settings.unset(&name) {
+                Err(err) => {
+                    w.write_fmt(format_args!("{:?}\n", err))?;
+                }
+                Ok(()) => {
+                    w.write_fmt(format_args!("Unset `{}`.\n", name))?;
+                }
+            },
+            InterpCommand::Version => {
+                w.write_fmt(format_args!("{}\n", capabilities()))?;
+            }
+            InterpCommand::Settings => {
+                for (spec, value) in self.settings.iter() {
+                    w.write_fmt(format_args!(
+                        "{} = {}    -- {}\n",
+                        spec.name, value, spec.help
+                    ))?;
+                }
+            }
+            InterpCommand::SaveSettings => match &self.settings_path {
+                None => {
+                    w.write_fmt(format_args!("No settings file.\n"))?;
+                }
+                Some(path) => match self
+                    .files
+                    .write(path, self.settings.to_file_string().as_bytes())
+                {
+                    Err(err) => {
+                        w.write_fmt(format_args!("{:?}\n", SettingError::Io(err.to_string())))?;
+                    }
+                    Ok(()) => {
+                        w.write_fmt(format_args!("Saved settings to {}.\n", path.display()))?;
+                    }
+                },
+            },
+            InterpCommand::Stack(full) => {
+                let mut vs = self.show_stack(&self.vs);
+                if full {
+                    vs = vs.with_max(None);
+                }
+                w.write_fmt(format_args!("{}\n", vs))?;
+            }
+            InterpCommand::Quit(code) => match code.as_deref().map(str::parse::<u8>) {
+                None => self.quit = Some(0),
+                Some(Ok(code)) => self.quit = Some(code.into()),
+                Some(Err(_)) => w.write_fmt(format_args!("Exit codes are 0 to 255.\n"))?,
+            },
+            InterpCommand::Refs => {
+                if self.results.is_empty() {
+                    w.write_fmt(format_args!("No results yet.\n"))?;
+                }
+                for (i, v) in self.results.iter().enumerate() {
+                    let n = self.expired_results + i + 1;
+                    w.write_fmt(format_args!("%{} = {}\n", n, self.ctx.display(v)))?;
+                }
+            }
+            InterpCommand::List => {
                 let mut names: Vec<String> = self
                     .ctx
-                    .fns
-                    .keys()
-                    .map(|sym| sym.resolve(&self.ctx.interner))
+                    .fn_defs()
+                    .map(|(sym, _)| sym.resolve(&self.ctx.interner))
                     .collect();
+                for (ns, namespace) in self.ctx.namespaces.iter() {
+                    let ns = ns.resolve(&self.ctx.interner);
+                    // Listed unqualified, as they are called
+                    if ns == CORE {
+                        continue;
+                    }
+                    names.extend(
+                        namespace
+                            .fns
+                            .keys()
+                            .map(|sym| format!("{}.{}", ns, sym.resolve(&self.ctx.interner))),
+                    );
+                }
                 names.sort_unstable();
                 if let Some(name) = names.first() {
                     w.write_all(name.as_bytes())?;
@@ -118,103 +1059,1276 @@ impl Interp {
                 }
                 w.write_all("\n".as_bytes())?;
             }
-            Ok(InterpCommand::Drop) => {
+            InterpCommand::Rules => {
+                for Rule(lhs, rhs) in self.ctx.rules.iter() {
+                    w.write_fmt(format_args!(
+                        "{{rule {} => {}}}\n",
+                        lhs.resolve(&self.ctx.interner),
+                        rhs.resolve(&self.ctx.interner)
+                    ))?;
+                }
+            }
+            InterpCommand::Drop => {
                 self.vs = ValueStack::default();
+                self.provenance.clear();
                 w.write_fmt(format_args!("Values dropped.\n"))?;
             }
-            Ok(InterpCommand::Clear) => {
-                self.ctx.fns.clear();
-                self.ctx.exprs.clear();
-                w.write_fmt(format_args!("Definitions cleared.\n"))?;
-            }
-            Ok(InterpCommand::Reset) => {
-                *self = Self::default();
-                w.write_fmt(format_args!("Reset.\n"))?;
+            InterpCommand::InternerStats => {
+                let stats = self.ctx.interner_stats();
+                w.write_fmt(format_args!(
+                    "{} symbols interned ({} bytes of names).\n",
+                    stats.symbols, stats.bytes
+                ))?;
             }
-            Ok(InterpCommand::Help) => {
-                w.write_all(HELP.as_bytes())?;
+            InterpCommand::Why(sym) => {
+                let index = match sym {
+                    None => self.vs.len().checked_sub(1),
+                    Some(sym) => self.vs.0.iter().rposition(|v| match v {
+                        Value::Call(s) | Value::Symbol(s) => *s == sym,
+                        Value::Quote(e) => **e == Expr::Call(sym),
+                        #[cfg(feature = "bignum")]
+                        Value::Nat(_) => false,
+                        #[cfg(feature = "rational")]
+                        Value::Ratio(_) => false,
+                        Value::Pair(_) | Value::Map(_) | Value::Char(_) | Value::Str(_) => false,
+                    }),
+                };
+                if !self.settings.provenance() {
+                    w.write_fmt(format_args!(
+                        "Provenance is off; `:set provenance on` to track it.\n"
+                    ))?;
+                } else if let Some(index) = index {
+                    self.provenance.sync(&self.vs);
+                    let why = self.provenance.why(index, &self.ctx.interner);
+                    w.write_all(why.as_bytes())?;
+                } else {
+                    w.write_fmt(format_args!("No such value on the stack.\n"))?;
+                }
             }
-        }
-        w.flush()
-    }
-
-    pub fn interp_step(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
-        match self.command.take() {
-            Some(InterpCommand::Eval(mut is)) => {
-                if !is.is_empty() {
-                    match is.remove(0) {
-                        InterpItem::FnDef(fn_def) => {
-                            let name = fn_def.0.resolve(&self.ctx.interner);
-                            if let Some(_) = self.ctx.define_fn(fn_def) {
-                                w.write_fmt(format_args!("Redefined `{}`.\n", name))?;
-                            } else {
-                                w.write_fmt(format_args!("Defined `{}`.\n", name))?;
-                            }
-                        }
-                        InterpItem::Expr(mut e) => {
-                            if self.is_first_eval_step {
-                                w.write_fmt(format_args!(
-                                    "{} {}\n",
-                                    self.vs.resolve(&self.ctx.interner),
-                                    e.resolve(&self.ctx.interner)
-                                ))?;
-                            }
-                            if e != Expr::default() {
-                                if let Err(err) = self.ctx.small_step(&mut self.vs, &mut e) {
-                                    w.write_fmt(format_args!(
-                                        "⇓ {} {}\n",
-                                        self.vs.resolve(&self.ctx.interner),
-                                        e.resolve(&self.ctx.interner)
-                                    ))?;
-                                    // TODO: better error messages
-                                    w.write_fmt(format_args!(
-                                        "{:?}\n",
-                                        err.resolve(&self.ctx.interner)
-                                    ))?;
-                                    return w.flush();
-                                } else {
-                                    self.ctx.compress(&mut self.vs);
-                                    is.insert(0, InterpItem::Expr(e));
-                                    self.is_first_eval_step = false;
-                                }
-                            } else {
-                                w.write_fmt(format_args!(
-                                    "⇓ {} {}\n",
-                                    self.vs.resolve(&self.ctx.interner),
-                                    e.resolve(&self.ctx.interner)
-                                ))?;
-                                self.is_first_eval_step = true;
-                            }
+            InterpCommand::Terminates(sym) => match self.ctx.terminates(sym) {
+                None => {
+                    w.write_fmt(format_args!("Not defined.\n"))?;
+                }
+                Some(Termination::Terminates) => {
+                    w.write_fmt(format_args!("Yes.\n"))?;
+                }
+                Some(Termination::Unknown) => {
+                    w.write_fmt(format_args!("Unknown.\n"))?;
+                }
+                Some(Termination::Loops { input, cycle }) => {
+                    w.write_fmt(format_args!(
+                        "No, on {} it loops:\n",
+                        input.resolve(&self.ctx.interner)
+                    ))?;
+                    for (i, (vs, e)) in cycle.iter().enumerate() {
+                        if i == MAX_LOOP_LINES && cycle.len() > MAX_LOOP_LINES + 1 {
+                            w.write_fmt(format_args!("⟶ ... ({} more steps)\n", cycle.len() - i))?;
+                            break;
                         }
+                        w.write_fmt(format_args!(
+                            "{}{} {}\n",
+                            if i == 0 { "" } else { "⟶ " },
+                            self.show_stack(vs),
+                            self.ctx.display(e)
+                        ))?;
                     }
-                    self.command = Some(InterpCommand::Eval(is));
                 }
-            }
-            Some(InterpCommand::Trace(mut e)) => {
-                if e != Expr::default() {
-                    if let Err(err) = self.ctx.small_step(&mut self.vs, &mut e) {
-                        // TODO: better error messages
-                        w.write_fmt(format_args!("{:?}\n", err.resolve(&self.ctx.interner)))?;
-                        return w.flush();
-                    }
-                    // TODO: show function expansion as equality, not as small step?
+            },
+            InterpCommand::Check(discipline, sym) => match Discipline::parse(&discipline) {
+                None => {
                     w.write_fmt(format_args!(
-                        "⟶ {} {}\n",
-                        self.vs.resolve(&self.ctx.interner),
-                        e.resolve(&self.ctx.interner)
+                        "Unknown check `{}`; try linear or affine.\n",
+                        discipline
                     ))?;
-                    if self.ctx.compress(&mut self.vs) {
+                }
+                Some(d) => match self.ctx.check_substructural(sym, d) {
+                    None => {
+                        w.write_fmt(format_args!("Not defined.\n"))?;
+                    }
+                    Some(violations) if violations.is_empty() => {
                         w.write_fmt(format_args!(
-                            "= {} {}\n",
-                            self.vs.resolve(&self.ctx.interner),
-                            e.resolve(&self.ctx.interner)
+                            "Yes, `{}` is {}.\n",
+                            sym.resolve(&self.ctx.interner),
+                            discipline
                         ))?;
                     }
+                    Some(mut violations) => {
+                        if violations.iter().all(Violation::is_unknown) {
+                            w.write_fmt(format_args!(
+                                "Unknown, as host intrinsics can't be checked:\n"
+                            ))?;
+                        } else {
+                            violations.retain(|violation| !violation.is_unknown());
+                            w.write_fmt(format_args!("No:\n"))?;
+                        }
+                        for violation in violations.iter().take(MAX_VIOLATION_LINES) {
+                            w.write_fmt(format_args!(
+                                "{}\n",
+                                violation.resolve(&self.ctx.interner)
+                            ))?;
+                        }
+                        if violations.len() > MAX_VIOLATION_LINES {
+                            w.write_fmt(format_args!(
+                                "... and {} more.\n",
+                                violations.len() - MAX_VIOLATION_LINES
+                            ))?;
+                        }
+                    }
+                },
+            },
+            InterpCommand::Equiv(e1, e2) => {
+                match self.ctx.distinguish(&e1, &e2, EquivLimits::default()) {
+                    None => {
+                        w.write_fmt(format_args!("Indistinguishable up to bound.\n"))?;
+                    }
+                    Some(distinction) => {
+                        w.write_fmt(format_args!("Distinguishable.\n"))?;
+                        self.write_distinction(&distinction, w)?;
+                    }
+                }
+            }
+            InterpCommand::Search(input, output) => {
+                let limits = SearchLimits {
+                    size: self.settings.search_size(),
+                    ..SearchLimits::default()
+                };
+                let found = self.ctx.search(&input, &output, limits);
+                if found.is_empty() {
+                    w.write_fmt(format_args!(
+                        "Nothing of size {} or less found.\n",
+                        limits.size
+                    ))?;
+                }
+                for e in found {
+                    w.write_fmt(format_args!(
+                        "{} {} ⇓ {}\n",
+                        input.resolve(&self.ctx.interner),
+                        e.resolve(&self.ctx.interner),
+                        output.resolve(&self.ctx.interner)
+                    ))?;
+                }
+            }
+            InterpCommand::Atlas(size, vocabulary, path) => match size.parse() {
+                Ok(size) if size <= MAX_SIZE => {
+                    let mut spec = AtlasSpec::new(&mut self.ctx.interner, size);
+                    match vocabulary {
+                        Some(Expr::Compose(es)) => spec.vocabulary = es,
+                        Some(e) => spec.vocabulary = vec![e],
+                        None => {}
+                    }
+                    let atlas = self.ctx.atlas(&spec);
+                    let json = atlas.to_json(&self.ctx.interner);
+                    match self.files.write(Path::new(&path), json.as_bytes()) {
+                        Err(err) => {
+                            w.write_fmt(format_args!("{:?}\n", err))?;
+                        }
+                        Ok(()) => {
+                            w.write_fmt(format_args!(
+                                "Saved {} expressions, with {} outcomes, to {}.\n",
+                                atlas.len(),
+                                atlas.classes.len(),
+                                path
+                            ))?;
+                        }
+                    }
+                }
+                _ => {
+                    w.write_fmt(format_args!("The most nodes allowed is {}.\n", MAX_SIZE))?;
+                }
+            },
+            InterpCommand::Clear => {
+                self.ctx.shared = None;
+                self.ctx.fns = Arc::default();
+                self.ctx.exprs = Arc::default();
+                self.ctx.rules = Arc::default();
+                self.ctx.namespaces = Arc::default();
+                // They may call what was defined
+                self.results.clear();
+                self.expired_results = 0;
+                self.ctx.set_it(None);
+                if self.settings.compact_symbols()
+                    && (!self.jobs.is_empty() || !self.watchpoints.is_empty())
+                {
+                    // Their stacks, expressions and patterns hold symbols
+                    w.write_fmt(format_args!(
+                        "Definitions cleared, but symbols kept for the jobs and watchpoints.\n"
+                    ))?;
+                } else if self.settings.compact_symbols() {
+                    self.trace.clear();
+                    self.provenance.clear();
+                    let dropped = self.ctx.compact_interner(&mut self.vs, &mut self.prelude);
+                    w.write_fmt(format_args!(
+                        "Definitions cleared, and {} unused symbols dropped.\n",
+                        dropped
+                    ))?;
+                } else {
+                    w.write_fmt(format_args!("Definitions cleared.\n"))?;
+                }
+            }
+            InterpCommand::Reset => {
+                let bundle_path = std::mem::take(&mut self.bundle_path);
+                let settings = std::mem::take(&mut self.settings);
+                let settings_path = self.settings_path.take();
+                let plugins = std::mem::take(&mut self.plugins);
+                let limits = self.ctx.definition_limits();
+                let clock = self.clock.clone();
+                let interrupt = self.interrupt.clone();
+                let files = self.files.clone();
+                let stats = self.stats;
+                let recorded_steps = self.recorded_steps.take();
+                // Re-register host intrinsics by name, as symbols are not
+                // shared between contexts, keeping those every context has
+                let interner = &self.ctx.interner;
+                let host_intrinsics: Vec<_> = std::mem::take(&mut self.ctx.host_intrinsics)
+                    .into_iter()
+                    .map(|(sym, host)| (sym.resolve(interner), host))
+                    .collect();
+                *self = match self.shared.take() {
+                    Some(shared) => Self::with_prelude(&shared),
+                    None => Self::default(),
+                };
+                for (name, host) in host_intrinsics {
+                    let sym = Symbol(self.ctx.interner.get_or_intern(name));
+                    self.ctx.host_intrinsics.entry(sym).or_insert(host);
+                }
+                self.bundle_path = bundle_path;
+                self.settings = settings;
+                self.settings_path = settings_path;
+                self.plugins = plugins;
+                self.ctx.set_definition_limits(limits);
+                self.set_clock(clock);
+                self.interrupt = interrupt;
+                self.files = files;
+                self.stats = stats;
+                self.recorded_steps = recorded_steps;
+                if !self.settings.prelude() {
+                    self.drop_prelude();
+                }
+                w.write_fmt(format_args!("Reset.\n"))?;
+            }
+            InterpCommand::Jobs => {
+                if self.jobs.is_empty() {
+                    w.write_fmt(format_args!("No jobs.\n"))?;
+                }
+                for job in self.jobs.iter() {
+                    let stack = if job.state.is_some() { "own" } else { "shared" };
+                    let paused = if job.paused { ", paused" } else { "" };
+                    w.write_fmt(format_args!(
+                        "[{}] {}    -- {} value stack{}\n",
+                        job.id, job.input, stack, paused
+                    ))?;
+                }
+            }
+            InterpCommand::Kill(n) => {
+                match self.jobs.iter().position(|job| job.id.to_string() == n) {
+                    Some(i) => {
+                        if self.jobs.remove(i).unwrap().state.is_none() {
+                            self.command = None;
+                            self.ctx.clear_locals(&mut self.vs);
+                        }
+                        w.write_fmt(format_args!("Killed job {}.\n", n))?;
+                    }
+                    None => {
+                        w.write_fmt(format_args!("No job {}.\n", n))?;
+                    }
+                }
+            }
+            InterpCommand::Watchpoint(pattern) => {
+                let id = self.next_watchpoint_id;
+                self.watchpoints.push((id, pattern));
+                self.next_watchpoint_id += 1;
+                w.write_fmt(format_args!("Set watchpoint {}.\n", id))?;
+            }
+            InterpCommand::Watchpoints => {
+                if self.watchpoints.is_empty() {
+                    w.write_fmt(format_args!("No watchpoints.\n"))?;
+                }
+                for (id, pattern) in self.watchpoints.iter() {
+                    w.write_fmt(format_args!(
+                        "{}: {}\n",
+                        id,
+                        pattern.resolve(&self.ctx.interner)
+                    ))?;
+                }
+            }
+            InterpCommand::Unwatch(n) => {
+                match self
+                    .watchpoints
+                    .iter()
+                    .position(|(id, _)| id.to_string() == n)
+                {
+                    Some(i) => {
+                        self.watchpoints.remove(i);
+                        w.write_fmt(format_args!("Removed watchpoint {}.\n", n))?;
+                    }
+                    None => {
+                        w.write_fmt(format_args!("No watchpoint {}.\n", n))?;
+                    }
+                }
+            }
+            InterpCommand::Continue(n) => {
+                let job = self
+                    .jobs
+                    .iter_mut()
+                    .find(|job| job.paused && n.iter().all(|n| job.id.to_string() == *n));
+                match job {
+                    Some(job) => {
+                        job.paused = false;
+                        w.write_fmt(format_args!("Continuing job {}.\n", job.id))?;
+                    }
+                    None => {
+                        w.write_fmt(format_args!("No paused job.\n"))?;
+                    }
+                }
+            }
+            InterpCommand::Help(topic) => match help(topic.as_deref()) {
+                Some(text) => {
+                    w.write_all(text.as_bytes())?;
+                }
+                None => {
+                    w.write_fmt(format_args!("No help on `{}`.\n", topic.unwrap()))?;
+                    w.write_all(topics().as_bytes())?;
+                }
+            },
+        }
+        w.flush()
+    }
+
+    /// Make the definitions in the source at `path`, all or none, and start
+    /// evaluating its rules and expressions.
+    fn load(
+        &mut self,
+        path: &str,
+        checksum: Option<&str>,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        if path.ends_with(".uccb") {
+            return self.load_binary(path, w);
+        }
+        match self.load_source(path, checksum) {
+            Err(err) => {
+                w.write_fmt(format_args!("{:?}\n", err))?;
+            }
+            Ok(src) => match self.parse_source(path, &src) {
+                Err(err) => {
+                    // TODO: better error messages
+                    w.write_fmt(format_args!("{}\n", err))?;
+                    self.failure = Some(Failure::Parse);
+                }
+                Ok(mut is) => {
+                    self.check_typos(&mut is, w)?;
+                    if !self.define_loaded(&mut is, w)? {
+                        self.failure = Some(Failure::Define);
+                        return Ok(());
+                    }
+                    self.is_first_eval_step = true;
+                    self.command = Some(InterpCommand::Eval(is));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// The items of the source `src` loaded from `path`, or the parse
+    /// error, written out
+    fn parse_source(&mut self, path: &str, src: &str) -> Result<Vec<InterpItem>, String> {
+        if !path.ends_with(".md") {
+            return InterpItemsParser::new()
+                .parse(&mut self.ctx.interner, src)
+                .map_err(|err| format!("{:?}", err));
+        }
+        let mut is = vec![];
+        for block in code_blocks(src) {
+            let block_is = InterpItemsParser::new()
+                .parse(&mut self.ctx.interner, &block.code)
+                .map_err(|err| format!("{:?}", err))?;
+            is.extend(block_is);
+            is.extend(block.expected.map(InterpItem::Expect));
+        }
+        Ok(is)
+    }
+
+    /// Write how the definitions in the file at `path` differ from the
+    /// session's, other than by leaving out the prelude's.
+    fn diff_defs(&mut self, path: &str, w: &mut dyn io::Write) -> io::Result<()> {
+        let src = match self.load_source(path, None) {
+            Err(err) => return w.write_fmt(format_args!("{:?}\n", err)),
+            Ok(src) => src,
+        };
+        let is = match InterpItemsParser::new().parse(&mut self.ctx.interner, &src) {
+            Err(err) => return w.write_fmt(format_args!("{:?}\n", err)),
+            Ok(is) => is,
+        };
+        let fn_defs = is
+            .into_iter()
+            .filter_map(|i| match i {
+                InterpItem::FnDef(fn_def) => Some(fn_def),
+                _ => None,
+            })
+            .collect();
+        let diffs: Vec<_> = self
+            .ctx
+            .diff_defs(fn_defs)
+            .into_iter()
+            .filter(|diff| !matches!(diff, DefDiff::Removed(FnDef(sym, _)) if self.prelude.contains(sym)))
+            .collect();
+        if diffs.is_empty() {
+            return w.write_fmt(format_args!("No differences.\n"));
+        }
+        for diff in diffs.iter() {
+            w.write_fmt(format_args!("{}\n", self.ctx.display(diff)))?;
+        }
+        Ok(())
+    }
+
+    /// Make the definitions in `is`, a loaded file's items, all together,
+    /// leaving its rules and expressions to evaluate. Returns whether they
+    /// were made, and if not, writes why and defines nothing.
+    fn define_loaded(
+        &mut self,
+        is: &mut Vec<InterpItem>,
+        w: &mut dyn io::Write,
+    ) -> io::Result<bool> {
+        let mut fn_defs = vec![];
+        is.retain(|item| match item {
+            InterpItem::FnDef(fn_def) => {
+                fn_defs.push(fn_def.clone());
+                false
+            }
+            _ => true,
+        });
+        let syms: Vec<Symbol> = fn_defs.iter().map(|fn_def| fn_def.0).collect();
+        for sym in syms.iter() {
+            self.expand_calls(*sym);
+        }
+        match self.ctx.define_all(fn_defs, Validation::Limits) {
+            Ok(replaced) => {
+                for (sym, replaced) in syms.into_iter().zip(replaced) {
+                    let name = sym.resolve(&self.ctx.interner);
+                    if replaced.is_some() {
+                        w.write_fmt(format_args!("Redefined `{}`.\n", name))?;
+                    } else {
+                        w.write_fmt(format_args!("Defined `{}`.\n", name))?;
+                    }
+                }
+                self.write_warnings(w)?;
+                Ok(true)
+            }
+            Err(err) => {
+                for err in err.errors {
+                    match err.resolve(&self.ctx.interner) {
+                        ResolvedDefineError::Limit(name, err) => {
+                            w.write_fmt(format_args!("`{}`: {:?}\n", name, err))?
+                        }
+                        ResolvedDefineError::Warning(warning) => {
+                            w.write_fmt(format_args!("{}.\n", warning))?
+                        }
+                        ResolvedDefineError::Parse(..) => unreachable!(),
+                    }
+                }
+                w.write_fmt(format_args!("Nothing loaded.\n"))?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Warn about, or fix, calls in `is` that look like misspelled
+    /// intrinsics, as the `intrinsic-typos` setting says.
+    fn check_typos(&mut self, is: &mut [InterpItem], w: &mut dyn io::Write) -> io::Result<()> {
+        let defining: Vec<Symbol> = is
+            .iter()
+            .flat_map(|item| match item {
+                InterpItem::FnDef(fn_def) => vec![fn_def.0],
+                InterpItem::Local(fn_defs, _) => fn_defs.iter().map(|fn_def| fn_def.0).collect(),
+                _ => vec![],
+            })
+            .collect();
+        for item in is.iter_mut() {
+            match item {
+                InterpItem::FnDef(FnDef(_, e)) | InterpItem::Expr(e) => {
+                    self.check_expr_typos(e, &defining, w)?
+                }
+                InterpItem::Local(fn_defs, e) => {
+                    for FnDef(_, e) in fn_defs.iter_mut() {
+                        self.check_expr_typos(e, &defining, w)?;
+                    }
+                    self.check_expr_typos(e, &defining, w)?
+                }
+                InterpItem::Rule(_) | InterpItem::EndLocal | InterpItem::Expect(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the references to earlier results in `e`, as `%2`, with
+    /// what pushes them, then warn about or fix its intrinsic typos
+    fn check_expr_typos(
+        &mut self,
+        e: &mut Expr,
+        defining: &[Symbol],
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        if !self.results.is_empty() {
+            // References to results that don't exist are left as calls,
+            // which fail as undefined
+            let mut expired = vec![];
+            *e = std::mem::take(e).fold(&mut |e| match e {
+                Expr::Call(sym) => match self.result(sym) {
+                    Some(v) => pushing(v.clone()),
+                    None => {
+                        let number = self.result_number(sym);
+                        if number.map_or(false, |n| n <= self.expired_results) {
+                            expired.push(sym);
+                        }
+                        e
+                    }
+                },
+                e => e,
+            });
+            for sym in expired {
+                w.write_fmt(format_args!(
+                    "`{}` has expired, as only the last {} results are kept.\n",
+                    sym.resolve(&self.ctx.interner),
+                    MAX_RESULTS
+                ))?;
+            }
+        }
+        let fix = match self.settings.intrinsic_typos() {
+            IntrinsicTypos::Ignore => return Ok(()),
+            IntrinsicTypos::Warn => false,
+            IntrinsicTypos::Fix => true,
+        };
+        let mut warnings = vec![];
+        self.ctx.intrinsic_typos(e, defining, fix, &mut warnings);
+        for warning in warnings {
+            match (fix, warning.resolve(&self.ctx.interner)) {
+                (true, ResolvedWarning::IntrinsicTypo { name, intrinsic }) => {
+                    w.write_fmt(format_args!("Corrected `{}` to `{}`.\n", name, intrinsic))?
+                }
+                (_, warning) => w.write_fmt(format_args!("Warning: {}.\n", warning))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace calls of `sym` on every job's stack with the quotation it
+    /// is defined as, before it is redefined
+    fn expand_calls(&mut self, sym: Symbol) {
+        self.ctx.expand_calls(&mut self.vs, sym);
+        for state in self.jobs.iter_mut().filter_map(|job| job.state.as_mut()) {
+            self.ctx.expand_calls(&mut state.vs, sym);
+        }
+    }
+
+    /// Read the source at `path`, as `load::load_source` does, unless it
+    /// is a URL and fetching them isn't allowed
+    fn load_source(&self, path: &str, checksum: Option<&str>) -> Result<String, LoadError> {
+        if !self.allow_net && is_url(path) {
+            return Err(LoadError::NetDisabled);
+        }
+        load_source(path, checksum, &*self.files)
+    }
+
+    /// Define everything in the `.uccb` file at `path`.
+    fn load_binary(&mut self, path: &str, w: &mut dyn io::Write) -> io::Result<()> {
+        let result = self
+            .files
+            .read(Path::new(path))
+            .map_err(|err| LoadError::Io(err.to_string()))
+            .and_then(|bytes| self.ctx.decode_fn_defs(&bytes).map_err(LoadError::Binary));
+        match result {
+            Err(err) => {
+                w.write_fmt(format_args!("{:?}\n", err))?;
+            }
+            Ok(fn_defs) => {
+                // Made all together, as a text file's are
+                let mut is = fn_defs.into_iter().map(InterpItem::FnDef).collect();
+                if !self.define_loaded(&mut is, w)? {
+                    self.failure = Some(Failure::Define);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_eval_error(&self, err: &EvalError, e: &Expr, w: &mut dyn io::Write) -> io::Result<()> {
+        // TODO: better error messages
+        w.write_fmt(format_args!("{:?}\n", err.resolve(&self.ctx.interner)))?;
+        if !self.trace.is_empty() {
+            w.write_fmt(format_args!(
+                "{} → {}\n",
+                self.trace.resolve(&self.ctx.interner),
+                e.head().resolve(&self.ctx.interner)
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Start `:trace-vm`, out of line, as `start_command` runs checks that
+    /// recurse deeply and so should have a small frame.
+    #[inline(never)]
+    fn start_trace_vm(&mut self, mut e: Expr, w: &mut dyn io::Write) -> io::Result<()> {
+        self.check_expr_typos(&mut e, &[], w)?;
+        let mut vm = Vm::default();
+        match vm.start(&self.ctx, &e) {
+            Err(err) => self.write_vm_error(&err, w),
+            Ok(thread) => {
+                w.write_fmt(format_args!(
+                    "{}{}\n",
+                    vm.listing(0, vm.code_len(), &self.ctx.interner),
+                    self.show_stack(&self.vs)
+                ))?;
+                self.command = Some(InterpCommand::TraceVm(e, Some(Box::new((vm, thread)))));
+                Ok(())
+            }
+        }
+    }
+
+    /// Run the next op of `:trace-vm`, writing it and the stack after.
+    #[inline(never)]
+    fn step_trace_vm(
+        &mut self,
+        e: Expr,
+        mut state: Box<(Vm, Thread)>,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let (vm, thread) = &mut *state;
+        let pc = thread.pc;
+        let op = vm.op_text(pc, &self.ctx.interner);
+        let compiled = vm.code_len();
+        match vm.step(&mut self.ctx, &mut self.vs, thread) {
+            Err(err) => {
+                w.write_fmt(format_args!("@{} {}\n", pc, op))?;
+                self.write_vm_error(&err, w)?;
+                self.failure = Some(Failure::Eval);
+                w.flush()
+            }
+            Ok(running) => {
+                self.ctx.compress(&mut self.vs);
+                w.write_fmt(format_args!(
+                    "@{} {} ⟶ {}\n",
+                    pc,
+                    op,
+                    self.show_stack(&self.vs)
+                ))?;
+                // Code compiled as it was called or applied
+                if vm.code_len() > compiled {
+                    w.write_fmt(format_args!(
+                        "{}",
+                        vm.listing(compiled, vm.code_len(), &self.ctx.interner)
+                    ))?;
+                }
+                if running {
+                    self.command = Some(InterpCommand::TraceVm(e, Some(state)));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the code compiled for `sym`, both as is and optimized.
+    #[inline(never)]
+    fn disasm(&mut self, sym: Symbol, w: &mut dyn io::Write) -> io::Result<()> {
+        if self.ctx.lookup_fn(&sym).is_none() {
+            return w.write_fmt(format_args!("Not defined.\n"));
+        }
+        for (heading, mut vm) in [("", Vm::default()), ("optimized:\n", Vm::optimized())] {
+            match vm.disassemble(&self.ctx, sym) {
+                Ok(listing) => w.write_fmt(format_args!("{}{}", heading, listing))?,
+                Err(err) => return self.write_vm_error(&err, w),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the definitions matching `query`, each with the fragment
+    /// that matched underlined.
+    #[inline(never)]
+    fn grep(&self, query: &GrepQuery, w: &mut dyn io::Write) -> io::Result<()> {
+        let matches = self.ctx.grep(query);
+        if matches.is_empty() {
+            return w.write_fmt(format_args!("No matches.\n"));
+        }
+        for m in matches {
+            w.write_fmt(format_args!(
+                "{}\n{}{}\n",
+                m.line,
+                " ".repeat(m.highlight.start),
+                "^".repeat(m.highlight.len())
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Write `e` flattened, or why it can't be.
+    #[inline(never)]
+    fn flatten(&self, e: &Expr, w: &mut dyn io::Write) -> io::Result<()> {
+        match self.ctx.flatten(e) {
+            Ok(e) => w.write_fmt(format_args!("{}\n", e.resolve(&self.ctx.interner))),
+            Err(err) => w.write_fmt(format_args!("{:?}\n", err.resolve(&self.ctx.interner))),
+        }
+    }
+
+    #[inline(never)]
+    fn minimize(&mut self, e: &Expr, w: &mut dyn io::Write) -> io::Result<()> {
+        match self.ctx.minimize(e) {
+            Ok((e, err)) => w.write_fmt(format_args!(
+                "{}\n{:?}\n",
+                e.resolve(&self.ctx.interner),
+                err.resolve(&self.ctx.interner)
+            )),
+            Err(err) => w.write_fmt(format_args!("{:?}\n", err)),
+        }
+    }
+
+    #[inline(never)]
+    fn subst(&self, sym: Symbol, by: &Expr, e: &Expr, w: &mut dyn io::Write) -> io::Result<()> {
+        let e = e.subst(sym, by);
+        w.write_fmt(format_args!("{}\n", e.resolve(&self.ctx.interner)))
+    }
+
+    #[inline(never)]
+    fn export_latex(&mut self, e: &Expr, path: &str, w: &mut dyn io::Write) -> io::Result<()> {
+        let (tex, steps) = match self.ctx.derivation_latex(e) {
+            Ok(derivation) => derivation,
+            Err(LatexError::Eval(err, _)) => {
+                return w.write_fmt(format_args!("{:?}\n", err.resolve(&self.ctx.interner)));
+            }
+            Err(err) => return w.write_fmt(format_args!("{:?}\n", err)),
+        };
+        match self.files.write(Path::new(path), tex.as_bytes()) {
+            Ok(()) => w.write_fmt(format_args!("Exported {} steps to {}.\n", steps, path)),
+            Err(err) => w.write_fmt(format_args!("{:?}\n", err)),
+        }
+    }
+
+    #[inline(never)]
+    fn exercise(&mut self, path: &str, w: &mut dyn io::Write) -> io::Result<()> {
+        match self.load_source(path, None) {
+            Ok(src) => {
+                let exercise = parse_exercise(&src);
+                w.write_fmt(format_args!(
+                    "{}\n({} hidden cases; check them with :check-exercise.)\n",
+                    exercise.prompt,
+                    exercise.cases.len()
+                ))?;
+                self.exercise = Some(exercise);
+                Ok(())
+            }
+            Err(err) => w.write_fmt(format_args!("{:?}\n", err)),
+        }
+    }
+
+    #[inline(never)]
+    fn check_exercise(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
+        let exercise = match &self.exercise {
+            Some(exercise) => exercise,
+            None => return w.write_all(b"No exercise loaded; load one with :exercise.\n"),
+        };
+        match self.ctx.check_exercise(exercise) {
+            Ok(passed) if passed == exercise.cases.len() => {
+                w.write_fmt(format_args!("Passed all {} hidden cases.\n", passed))
+            }
+            Ok(passed) => w.write_fmt(format_args!(
+                "Passed {} of {} hidden cases.\n",
+                passed,
+                exercise.cases.len()
+            )),
+            Err(err) => w.write_fmt(format_args!("{:?}\n", err)),
+        }
+    }
+
+    /// The number of the result `sym` refers to, if it is `%1` or the like
+    fn result_number(&self, sym: Symbol) -> Option<usize> {
+        let name = self.ctx.interner.resolve(&sym.0);
+        let n: usize = name.strip_prefix('%')?.parse().ok()?;
+        Some(n).filter(|n| *n > 0)
+    }
+
+    /// The result `sym` refers to, if it is `%1` or the like and hasn't
+    /// expired
+    fn result(&self, sym: Symbol) -> Option<&Value> {
+        let n = self.result_number(sym)?;
+        self.results.get(n.checked_sub(self.expired_results + 1)?)
+    }
+
+    /// `vs`, shown as the settings say, with its top at one end and only
+    /// so many values
+    fn show_stack<'a>(&'a self, vs: &'a ValueStack) -> StackView<'a> {
+        StackView::new(vs, &self.ctx.interner)
+            .with_top(self.settings.stack_top())
+            .with_max(self.settings.stack_display_max())
+    }
+
+    /// Write a line of a trace, `arrow` and the current state, or hold it
+    /// back until the trace finishes, if the `align` setting is on
+    fn trace_row(
+        &mut self,
+        arrow: &'static str,
+        e: &Expr,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        if self.settings.align() {
+            let stack = self.show_stack(&self.vs).to_string();
+            let expr = self.ctx.display(e).to_string();
+            self.trace_rows.push(TraceRow { arrow, stack, expr });
+            return Ok(());
+        }
+        let space = if arrow.is_empty() { "" } else { " " };
+        w.write_fmt(format_args!(
+            "{}{}{} {}\n",
+            arrow,
+            space,
+            self.show_stack(&self.vs),
+            self.ctx.display(e)
+        ))
+    }
+
+    /// Write the lines of the trace held back, as a table, with the
+    /// arrows, stacks, and expressions each starting in one column
+    fn write_trace_rows(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
+        let rows = std::mem::take(&mut self.trace_rows);
+        // As wide as printed, as `…` is three columns in ASCII
+        let ascii = self.settings.ascii();
+        let width = |s: &str| {
+            if ascii {
+                to_ascii(s).width()
+            } else {
+                s.width()
+            }
+        };
+        let arrow_width = rows.iter().map(|row| width(row.arrow)).max().unwrap_or(0);
+        let stack_width = rows.iter().map(|row| width(&row.stack)).max().unwrap_or(0);
+        for row in &rows {
+            let mut line = String::new();
+            line.push_str(row.arrow);
+            line.push_str(&" ".repeat(arrow_width - width(row.arrow)));
+            line.push(' ');
+            line.push_str(&row.stack);
+            line.push_str(&" ".repeat(stack_width - width(&row.stack)));
+            line.push(' ');
+            line.push_str(&row.expr);
+            w.write_fmt(format_args!("{}\n", line.trim_end()))?;
+        }
+        Ok(())
+    }
+
+    fn write_vm_error(&self, err: &VmError, w: &mut dyn io::Write) -> io::Result<()> {
+        match err {
+            VmError::Eval(err) => {
+                w.write_fmt(format_args!("{:?}\n", err.resolve(&self.ctx.interner)))
+            }
+            err => w.write_fmt(format_args!("{:?}\n", err)),
+        }
+    }
+
+    fn write_warnings(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
+        for warning in self.ctx.take_warnings() {
+            w.write_fmt(format_args!(
+                "Warning: {}.\n",
+                warning.resolve(&self.ctx.interner)
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Write the stack on which two evaluations differ, and their results,
+    /// and how applying differing values tells them apart.
+    fn write_distinction(
+        &self,
+        distinction: &Distinction,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let (o1, o2) = &distinction.observations;
+        for o in [o1, o2] {
+            match o {
+                Observation::Halted(vs) => {
+                    w.write_fmt(format_args!(
+                        "   {} ⇓ {}\n",
+                        self.show_stack(&distinction.stack),
+                        self.show_stack(vs)
+                    ))?;
+                }
+                Observation::Failed(err) => {
+                    w.write_fmt(format_args!(
+                        "   {} ⇓ {:?}\n",
+                        self.show_stack(&distinction.stack),
+                        err.resolve(&self.ctx.interner)
+                    ))?;
+                }
+                Observation::OutOfFuel => {}
+            }
+        }
+        if let Some((i, inner)) = &distinction.inner {
+            w.write_fmt(format_args!(
+                "Applying value {} of each on the stack:\n",
+                i + 1
+            ))?;
+            self.write_distinction(inner, w)?;
+        }
+        Ok(())
+    }
+
+    /// Take a small step of `e`, keeping track of where values came from
+    /// if the `provenance` setting is on.
+    fn small_step(&mut self, e: &mut Expr) -> Result<(), EvalError> {
+        self.stats.small_steps += 1;
+        if self.recorded_steps.is_some() {
+            self.record_step(e);
+        }
+        let result = if !self.settings.provenance() {
+            self.ctx.small_step_traced(&mut self.vs, e, &mut self.trace)
+        } else {
+            self.provenance.sync(&self.vs);
+            let head = e.head().clone();
+            let calls = self.trace.0.iter().map(|&(sym, _)| sym).collect();
+            let result = self.ctx.small_step_traced(&mut self.vs, e, &mut self.trace);
+            if result.is_ok() {
+                self.provenance.record(&mut self.ctx, head, calls, &self.vs);
+            }
+            result
+        };
+        if result.is_err() {
+            self.stats.errors += 1;
+        }
+        result
+    }
+
+    /// Check that the value stack is `expected`, writing what it is
+    /// instead if not.
+    #[inline(never)]
+    fn check_expected(&self, expected: &str, w: &mut dyn io::Write) -> io::Result<bool> {
+        let actual = self.vs.resolve(&self.ctx.interner).to_string();
+        if matches_expected(&actual, expected) {
+            return Ok(true);
+        }
+        w.write_fmt(format_args!(
+            "Expected {}, but the stack is {}.\n",
+            expected.trim(),
+            actual.trim()
+        ))?;
+        Ok(false)
+    }
+
+    #[inline(never)]
+    fn record_step(&mut self, e: &Expr) {
+        let step = TraceStep {
+            stack: self.vs.resolve(&self.ctx.interner).to_string(),
+            expr: e.resolve(&self.ctx.interner).to_string(),
+        };
+        if let Some(steps) = self.recorded_steps.as_mut() {
+            steps.push(step);
+        }
+    }
+
+    fn step(&mut self, w: &mut dyn io::Write) -> io::Result<()> {
+        match self.command.take() {
+            Some(InterpCommand::Eval(mut is)) => {
+                if !is.is_empty() {
+                    match is.remove(0) {
+                        InterpItem::FnDef(fn_def) => {
+                            let name = fn_def.0.resolve(&self.ctx.interner);
+                            self.expand_calls(fn_def.0);
+                            match self.ctx.define_fn(fn_def) {
+                                Ok(Some(_)) => {
+                                    w.write_fmt(format_args!("Redefined `{}`.\n", name))?;
+                                }
+                                Ok(None) => {
+                                    w.write_fmt(format_args!("Defined `{}`.\n", name))?;
+                                }
+                                Err(err) => {
+                                    w.write_fmt(format_args!("{:?}\n", err))?;
+                                    self.failure = Some(Failure::Define);
+                                    return w.flush();
+                                }
+                            }
+                        }
+                        InterpItem::Rule(rule) => {
+                            if let Err(err) = self.ctx.define_rule(rule) {
+                                w.write_fmt(format_args!(
+                                    "{:?}\n",
+                                    err.resolve(&self.ctx.interner)
+                                ))?;
+                                self.failure = Some(Failure::Define);
+                                return w.flush();
+                            } else {
+                                w.write_fmt(format_args!("Defined rule.\n"))?;
+                            }
+                        }
+                        InterpItem::Expr(mut e) => {
+                            if self.is_first_eval_step {
+                                if self.settings.show_steps() != ShowSteps::Off {
+                                    w.write_fmt(format_args!(
+                                        "{} {}\n",
+                                        self.show_stack(&self.vs),
+                                        self.ctx.display(&e)
+                                    ))?;
+                                }
+                                self.trace.clear();
+                                self.provenance.start();
+                                self.eval_steps = 0;
+                            }
+                            if e != Expr::default() {
+                                // Watchpoints are checked between calls, so
+                                // must have them after every step
+                                let chunk = if self.watchpoints.is_empty() {
+                                    self.chunk_steps
+                                } else {
+                                    1
+                                };
+                                for _ in 0..chunk {
+                                    if let Err(err) = self.small_step(&mut e) {
+                                        w.write_fmt(format_args!(
+                                            "⇓ {} {}\n",
+                                            self.show_stack(&self.vs),
+                                            self.ctx.display(&e)
+                                        ))?;
+                                        self.write_eval_error(&err, &e, w)?;
+                                        self.failure = Some(Failure::Eval);
+                                        return w.flush();
+                                    }
+                                    self.ctx.compress(&mut self.vs);
+                                    self.eval_steps += 1;
+                                    if e == Expr::default() {
+                                        break;
+                                    }
+                                    if let ShowSteps::Every(n) = self.settings.show_steps() {
+                                        if self.eval_steps == n {
+                                            self.eval_steps = 0;
+                                            w.write_fmt(format_args!(
+                                                "⟶ {} {}\n",
+                                                self.show_stack(&self.vs),
+                                                self.ctx.display(&e)
+                                            ))?;
+                                        }
+                                    }
+                                }
+                                is.insert(0, InterpItem::Expr(e));
+                                self.is_first_eval_step = false;
+                            } else {
+                                if is.first() == Some(&InterpItem::EndLocal) {
+                                    is.remove(0);
+                                    self.ctx.pop_locals(&mut self.vs);
+                                    self.ctx.compress(&mut self.vs);
+                                }
+                                w.write_fmt(format_args!(
+                                    "⇓ {} {}\n",
+                                    self.show_stack(&self.vs),
+                                    self.ctx.display(&e)
+                                ))?;
+                                let top = self.vs.0.last().cloned();
+                                if let Some(v) = &top {
+                                    if self.results.len() == MAX_RESULTS {
+                                        self.results.pop_front();
+                                        self.expired_results += 1;
+                                    }
+                                    self.results.push_back(v.clone());
+                                }
+                                self.ctx.set_it(top);
+                                self.is_first_eval_step = true;
+                            }
+                        }
+                        InterpItem::Local(fn_defs, e) => {
+                            self.ctx.push_locals(fn_defs);
+                            is.insert(0, InterpItem::EndLocal);
+                            is.insert(0, InterpItem::Expr(e));
+                        }
+                        InterpItem::EndLocal => self.ctx.pop_locals(&mut self.vs),
+                        InterpItem::Expect(expected) => {
+                            if !self.check_expected(&expected, w)? {
+                                self.failure = Some(Failure::Assertion);
+                                return w.flush();
+                            }
+                        }
+                    }
+                    self.command = Some(InterpCommand::Eval(is));
+                }
+            }
+            Some(InterpCommand::Trace(mut e)) => {
+                if e != Expr::default() {
+                    if let Err(err) = self.small_step(&mut e) {
+                        self.write_trace_rows(w)?;
+                        self.write_eval_error(&err, &e, w)?;
+                        self.failure = Some(Failure::Eval);
+                        return w.flush();
+                    }
+                    // TODO: show function expansion as equality, not as small step?
+                    self.trace_row("⟶", &e, w)?;
+                    if self.ctx.compress(&mut self.vs) {
+                        self.trace_row("=", &e, w)?;
+                    }
                     self.command = Some(InterpCommand::Trace(e));
+                } else {
+                    self.write_trace_rows(w)?;
+                }
+            }
+            Some(InterpCommand::Bench(mut e, Some((start, steps)))) => {
+                if e == Expr::default() {
+                    let elapsed = self.clock.now().saturating_sub(start);
+                    w.write_fmt(format_args!(
+                        "⇓ {} \n{} steps in {:?}.\n",
+                        self.show_stack(&self.vs),
+                        steps,
+                        elapsed
+                    ))?;
+                } else if let Err(err) = self.small_step(&mut e) {
+                    w.write_fmt(format_args!(
+                        "⇓ {} {}\n",
+                        self.show_stack(&self.vs),
+                        self.ctx.display(&e)
+                    ))?;
+                    self.write_eval_error(&err, &e, w)?;
+                    self.failure = Some(Failure::Eval);
+                    return w.flush();
+                } else {
+                    self.ctx.compress(&mut self.vs);
+                    self.command = Some(InterpCommand::Bench(e, Some((start, steps + 1))));
+                }
+            }
+            Some(InterpCommand::TraceVm(e, Some(state))) => self.step_trace_vm(e, state, w)?,
+            Some(InterpCommand::TraceUntil(pattern, e)) => {
+                if let Some(captures) = pattern.matches(&self.vs) {
+                    self.write_trace_rows(w)?;
+                    let captures: Vec<String> = captures
+                        .iter()
+                        .map(|(sym, e)| {
+                            format!(
+                                "{} = {}",
+                                sym.resolve(&self.ctx.interner),
+                                e.resolve(&self.ctx.interner)
+                            )
+                        })
+                        .collect();
+                    if captures.is_empty() {
+                        w.write_fmt(format_args!("Matched.\n"))?;
+                    } else {
+                        w.write_fmt(format_args!("Matched, with {}.\n", captures.join(", ")))?;
+                    }
+                } else if e == Expr::default() {
+                    self.write_trace_rows(w)?;
+                    w.write_fmt(format_args!("Never matched.\n"))?;
+                } else {
+                    // Step as `:trace` does, and check again before the next
+                    self.command = Some(InterpCommand::Trace(e));
+                    self.step(w)?;
+                    if let Some(InterpCommand::Trace(e)) = self.command.take() {
+                        self.command = Some(InterpCommand::TraceUntil(pattern, e));
+                    }
+                    return Ok(());
                 }
             }
             _ => panic!(),
         }
+        self.write_warnings(w)?;
         w.flush()
     }
 }
+
+/// Record input that didn't parse as a command, out of line, as `start`
+/// should have a small frame
+#[cfg(feature = "tracing")]
+#[inline(never)]
+fn trace_parse_error(input: &str, err: &dyn std::fmt::Debug) {
+    tracing::debug!(input, error = ?err, "couldn't parse command");
+}
+
+/// Prefixes each line with a job number
+struct PrefixWriter<'a> {
+    inner: &'a mut dyn io::Write,
+    prefix: String,
+    at_line_start: bool,
+}
+
+impl<'a> PrefixWriter<'a> {
+    fn new(inner: &'a mut dyn io::Write, id: usize) -> Self {
+        Self {
+            inner,
+            prefix: format!("[{}] ", id),
+            at_line_start: true,
+        }
+    }
+}
+
+impl io::Write for PrefixWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if self.at_line_start {
+                self.inner.write_all(self.prefix.as_bytes())?;
+            }
+            self.inner.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A writer that stops writing once `max` bytes have been written, with a
+/// notice that the rest is cut
+struct OutputGuard<'a> {
+    inner: &'a mut dyn io::Write,
+    written: usize,
+    max: Option<usize>,
+    /// Whether the output has been cut
+    cut: bool,
+    at_line_start: bool,
+}
+
+impl io::Write for OutputGuard<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.max {
+            _ if self.cut => {}
+            Some(max) if self.written + buf.len() > max => {
+                if !self.at_line_start {
+                    self.inner.write_all(b"\n")?;
+                }
+                self.inner.write_fmt(format_args!(
+                    "Output cut after {} bytes; see :set output-max.\n",
+                    self.written
+                ))?;
+                self.cut = true;
+            }
+            _ => {
+                self.inner.write_all(buf)?;
+                self.written += buf.len();
+                if let Some(&last) = buf.last() {
+                    self.at_line_start = last == b'\n';
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Replaces the non-ASCII symbols in the interpreter's output
+struct AsciiWriter<'a>(&'a mut dyn io::Write);
+
+/// `s`, as the `ascii` theme prints it
+fn to_ascii(s: &str) -> String {
+    s.replace('⟨', "<")
+        .replace('⟩', ">")
+        .replace(['⟶', '→'], "->")
+        .replace('⇓', "=>")
+        .replace('…', "...")
+}
+
+impl io::Write for AsciiWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(s) => self.0.write_all(to_ascii(s).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
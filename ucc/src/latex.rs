@@ -0,0 +1,119 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Derivations as LaTeX, for `:export-latex`, so those in papers are ones
+//! the evaluator took. Each small step is a row of an `array`, with the
+//! rule it took, e.g. for `[a] clone`
+//!
+//! ```text
+//! \[
+//! \begin{array}{lll}
+//!   & \uccstate{}{\texttt{[a] clone}} & \\
+//!   \uccstep & \uccstate{\texttt{[a]}}{\texttt{clone}} & \uccrule{quote-push} \\
+//!   \uccstep & \uccstate{\texttt{[a] [a]}}{} & \uccrule{clone} \\
+//! \end{array}
+//! \]
+//! ```
+//!
+//! The macros are defined with `\providecommand`, so a paper can restyle
+//! them by defining its own first.
+
+use crate::core::{Context, EvalError, Expr, ValueStack};
+use crate::display::Resolve;
+use crate::rules::RuleId;
+
+/// The most small steps a derivation may take
+pub const LATEX_MAX_STEPS: usize = 1000;
+
+const MACROS: &str = "\
+\\providecommand{\\uccstate}[2]{\\langle #1 \\rangle\\ #2}
+\\providecommand{\\uccstep}{\\longrightarrow}
+\\providecommand{\\uccrule}[1]{\\textsc{#1}}
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LatexError {
+    /// Evaluation stopped at an error, after this many steps
+    Eval(EvalError, usize),
+    /// Evaluation took more than this many steps
+    TooManySteps(usize),
+}
+
+/// `text` in a `\texttt`, with LaTeX's special characters escaped
+fn texttt(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let mut tex = "\\texttt{".to_owned();
+    for c in text.chars() {
+        match c {
+            '\\' => tex.push_str("\\textbackslash{}"),
+            '^' => tex.push_str("\\textasciicircum{}"),
+            '~' => tex.push_str("\\textasciitilde{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                tex.push('\\');
+                tex.push(c);
+            }
+            c => tex.push(c),
+        }
+    }
+    tex.push('}');
+    tex
+}
+
+impl Context {
+    /// The derivation of `e` from an empty stack, as LaTeX, and the number
+    /// of steps it took
+    pub fn derivation_latex(&mut self, e: &Expr) -> Result<(String, usize), LatexError> {
+        let mut tex = format!(
+            "% The derivation of `{}`, by ucc\n{}\\[\n\\begin{{array}}{{lll}}\n",
+            self.display(e),
+            MACROS
+        );
+        let mut vs = ValueStack::default();
+        let mut e = e.clone();
+        tex.push_str(&format!("  & {} & \\\\\n", self.latex_state(&vs, &e)));
+        let mut steps = 0;
+        while e != Expr::default() {
+            if steps == LATEX_MAX_STEPS {
+                return Err(LatexError::TooManySteps(LATEX_MAX_STEPS));
+            }
+            let rule = self.next_rule(&e);
+            if let Err(err) = self.small_step(&mut vs, &mut e) {
+                return Err(LatexError::Eval(err, steps));
+            }
+            steps += 1;
+            tex.push_str(&format!(
+                "  \\uccstep & {} & {} \\\\\n",
+                self.latex_state(&vs, &e),
+                rule.map(|rule| self.latex_rule(rule)).unwrap_or_default()
+            ));
+        }
+        tex.push_str("\\end{array}\n\\]\n");
+        Ok((tex, steps))
+    }
+
+    fn latex_state(&self, vs: &ValueStack, e: &Expr) -> String {
+        let values: Vec<String> = vs.0.iter().map(|v| self.display(v).to_string()).collect();
+        format!(
+            "\\uccstate{{{}}}{{{}}}",
+            texttt(&values.join(" ")),
+            texttt(&self.display(e).to_string())
+        )
+    }
+
+    /// The name of `rule`, with the function or host intrinsic it is for
+    fn latex_rule(&self, rule: RuleId) -> String {
+        let name = format!("\\uccrule{{{}}}", rule.name());
+        match rule {
+            RuleId::CallExpand(sym) | RuleId::CallPush(sym) | RuleId::Host(sym) => {
+                format!("{}~{}", name, texttt(&sym.resolve(&self.interner)))
+            }
+            RuleId::Rewrite(i) => format!("{}~{}", name, i),
+            _ => name,
+        }
+    }
+}
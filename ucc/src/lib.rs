@@ -8,15 +8,305 @@ pub mod core;
 #[cfg(test)]
 mod test_core;
 
+pub mod error;
+pub use crate::error::Error;
+#[cfg(test)]
+mod test_error;
+
+pub mod capabilities;
+pub use crate::capabilities::capabilities;
+#[cfg(test)]
+mod test_capabilities;
+
+#[cfg(test)]
+mod test_visit;
+pub mod visit;
+
+pub mod stack;
+#[cfg(test)]
+mod test_stack;
+
+pub mod basis;
+#[cfg(test)]
+mod test_basis;
+
 mod builtin;
 
+pub mod nat;
+#[cfg(all(test, feature = "bignum"))]
+mod test_nat;
+
+pub mod ratio;
+#[cfg(all(test, feature = "rational"))]
+mod test_ratio;
+
+#[cfg(all(test, feature = "bignum"))]
+mod test_time;
+#[cfg(feature = "bignum")]
+pub mod time;
+
+pub mod pair;
+#[cfg(test)]
+mod test_pair;
+
+pub mod hamt;
+#[cfg(test)]
+mod test_hamt;
+
+pub mod map;
+#[cfg(test)]
+mod test_map;
+
+pub mod character;
+#[cfg(test)]
+mod test_character;
+
+#[cfg(feature = "regex")]
+pub mod regex_match;
+#[cfg(all(test, feature = "regex"))]
+mod test_regex_match;
+
+pub mod host;
+#[cfg(test)]
+mod test_host;
+
+pub mod limits;
+#[cfg(test)]
+mod test_limits;
+
+#[cfg(test)]
+mod test_transaction;
+pub mod transaction;
+
+#[cfg(test)]
+mod test_vm;
+pub mod vm;
+
+pub mod peephole;
+#[cfg(test)]
+mod test_peephole;
+
+pub mod disasm;
+#[cfg(test)]
+mod test_disasm;
+
+pub mod grep;
+#[cfg(test)]
+mod test_grep;
+
+pub mod flatten;
+#[cfg(test)]
+mod test_flatten;
+
+pub mod redex;
+#[cfg(test)]
+mod test_redex;
+
+pub mod rules;
+#[cfg(test)]
+mod test_rules;
+
+pub mod latex;
+#[cfg(test)]
+mod test_latex;
+
+pub mod markdown;
+#[cfg(test)]
+mod test_markdown;
+
+pub mod prefold;
+#[cfg(test)]
+mod test_prefold;
+
+pub mod evalq;
+#[cfg(test)]
+mod test_evalq;
+
+pub mod fork;
+#[cfg(test)]
+mod test_fork;
+
+pub mod diff;
+#[cfg(test)]
+mod test_diff;
+
+pub mod locals;
+#[cfg(test)]
+mod test_locals;
+
+pub mod hooks;
+#[cfg(test)]
+mod test_hooks;
+
+pub mod coverage;
+#[cfg(test)]
+mod test_coverage;
+
+pub mod mutate;
+#[cfg(test)]
+mod test_mutate;
+
+pub mod doctest;
+#[cfg(test)]
+mod test_doctest;
+
+#[cfg(test)]
+mod test_tutor;
+
+pub mod exercise;
+#[cfg(test)]
+mod test_exercise;
+
+pub mod minimize;
+#[cfg(test)]
+mod test_minimize;
+
+pub mod steps;
+#[cfg(test)]
+mod test_steps;
+#[cfg(test)]
+mod test_validate;
+pub mod tutor;
+pub mod validate;
+
+pub mod codegen;
+#[cfg(test)]
+mod test_codegen;
+
+#[cfg(test)]
+mod test_wat;
+pub mod wat;
+
+pub mod plugin;
+#[cfg(test)]
+mod test_plugin;
+
+pub mod confusables;
+#[cfg(test)]
+mod test_confusables;
+
+pub mod diagnostics;
+#[cfg(test)]
+mod test_diagnostics;
+
+pub mod load;
+
+pub mod binary;
+#[cfg(test)]
+mod test_binary;
+mod uccb;
+
+pub mod hash;
+#[cfg(test)]
+mod test_hash;
+
+pub mod bundle;
+#[cfg(test)]
+mod test_bundle;
+
 pub mod display;
+#[cfg(test)]
+mod test_display;
+
+pub mod help;
+#[cfg(test)]
+mod test_help;
+
+pub mod rewrite;
+#[cfg(test)]
+mod test_rewrite;
+
+pub mod stack_pattern;
+#[cfg(test)]
+mod test_stack_pattern;
+
+pub mod symbols;
+#[cfg(test)]
+mod test_symbols;
+
+pub mod termination;
+#[cfg(test)]
+mod test_termination;
+
+pub mod substructural;
+#[cfg(test)]
+mod test_substructural;
+
+pub mod equiv;
+#[cfg(test)]
+mod test_equiv;
+
+pub mod search;
+#[cfg(test)]
+mod test_search;
+
+pub mod atlas;
+#[cfg(test)]
+mod test_atlas;
+
+pub mod shared;
+#[cfg(test)]
+mod test_shared;
+
+pub mod settings;
+#[cfg(test)]
+mod test_settings;
+
+pub mod config;
+#[cfg(test)]
+mod test_config;
+
+pub mod stream;
+#[cfg(test)]
+mod test_stream;
+
+mod provenance;
+#[cfg(test)]
+mod test_provenance;
 
 pub mod interp;
 #[cfg(test)]
 mod test_interp;
 
+pub mod builder;
+#[cfg(test)]
+mod test_builder;
+
+pub mod metrics;
+#[cfg(test)]
+mod test_metrics;
+
+pub mod serve;
+#[cfg(test)]
+mod test_serve;
+
+pub mod quota;
+#[cfg(test)]
+mod test_quota;
+
+#[cfg(test)]
+mod test_websocket;
+pub mod websocket;
+
+pub mod playground;
+#[cfg(test)]
+mod test_playground;
+
+pub mod golden;
+#[cfg(test)]
+mod test_golden;
+
+pub mod grammar;
+#[cfg(test)]
+mod test_grammar;
+
 use lalrpop_util::lalrpop_mod;
 lalrpop_mod!(pub parse);
 #[cfg(test)]
 mod test_parse;
+
+#[cfg(test)]
+mod test_util;
+
+#[cfg(all(test, feature = "tracing"))]
+mod test_tracing;
@@ -0,0 +1,118 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Limits on definitions, for hosts evaluating untrusted input, so it
+//! can't exhaust memory by defining giant terms or endless functions. The
+//! limit on a body's size holds for the values evaluation makes, too, as
+//! those could otherwise double in size with each step.
+
+use crate::core::{Context, EvalError, Expr, FnDef, Symbol, Value, ValueStack};
+
+/// The most each definition may hold, where `None` is unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DefinitionLimits {
+    /// The most nodes in a body, where each intrinsic, call, symbol, and
+    /// quotation is one node, and in each value evaluation makes, where
+    /// each 64 bytes of a string, 64 bits of a number, and item of a pair
+    /// or a map, are nodes too
+    pub body_size: Option<usize>,
+    /// The most functions defined, besides any shared prelude's
+    pub fns: Option<usize>,
+    /// The longest name, in bytes, defined or used in a body
+    pub name_len: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    BodyTooLarge { size: usize, limit: usize },
+    TooManyFns { limit: usize },
+    NameTooLong { len: usize, limit: usize },
+    ValueTooLarge { size: usize, limit: usize },
+}
+
+/// The number of nodes in `e`, as the limit on body size counts them
+pub(crate) fn body_size(e: &Expr) -> usize {
+    let mut size = 0;
+    e.visit(&mut |e| {
+        if !matches!(e, Expr::Compose(_)) {
+            size += 1;
+        }
+    });
+    size
+}
+
+/// The number of nodes in `v`, as the limit on body size counts them
+pub(crate) fn value_size(v: &Value) -> usize {
+    match v {
+        Value::Call(_) | Value::Symbol(_) | Value::Char(_) => 1,
+        Value::Str(s) => 1 + s.len() / 64,
+        Value::Quote(e) => 1 + body_size(e),
+        #[cfg(feature = "bignum")]
+        Value::Nat(n) => 1 + n.bits() as usize / 64,
+        #[cfg(feature = "rational")]
+        Value::Ratio(r) => 1 + (r.numer().bits() + r.denom().bits()) as usize / 64,
+        Value::Pair(p) => 1 + value_size(&p.0) + value_size(&p.1),
+        Value::Map(m) => {
+            1 + m
+                .iter()
+                .map(|(k, v)| value_size(k) + value_size(v))
+                .sum::<usize>()
+        }
+    }
+}
+
+impl Context {
+    /// Limit the definitions made from now on.
+    pub fn set_definition_limits(&mut self, limits: DefinitionLimits) {
+        self.limits = limits;
+    }
+
+    pub fn definition_limits(&self) -> DefinitionLimits {
+        self.limits
+    }
+
+    pub(crate) fn check_limits(&self, fn_def: &FnDef) -> Result<(), LimitError> {
+        let FnDef(sym, body) = fn_def;
+        if let Some(limit) = self.limits.fns {
+            if self.fns.len() >= limit && !self.fns.contains_key(sym) {
+                return Err(LimitError::TooManyFns { limit });
+            }
+        }
+        if let Some(limit) = self.limits.body_size {
+            let size = body_size(body);
+            if size > limit {
+                return Err(LimitError::BodyTooLarge { size, limit });
+            }
+        }
+        if let Some(limit) = self.limits.name_len {
+            let mut len = self.name_len(sym);
+            body.visit(&mut |e| match e {
+                Expr::Call(sym) | Expr::Symbol(sym) => len = len.max(self.name_len(sym)),
+                _ => {}
+            });
+            if len > limit {
+                return Err(LimitError::NameTooLong { len, limit });
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail if the value on top of `vs`, the only one a step makes that
+    /// can be larger than those it took, is over the limit on body size.
+    pub(crate) fn check_value_size(&self, vs: &ValueStack) -> Result<(), EvalError> {
+        if let (Some(limit), Some(v)) = (self.limits.body_size, vs.0.last()) {
+            let size = value_size(v);
+            if size > limit {
+                return Err(EvalError::Limit(LimitError::ValueTooLarge { size, limit }));
+            }
+        }
+        Ok(())
+    }
+
+    fn name_len(&self, sym: &Symbol) -> usize {
+        self.interner.resolve(&sym.0).len()
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::basis::BasisError;
+use crate::binary::BinaryError;
+use crate::builder::Files;
+#[cfg(feature = "net")]
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadError {
+    Io(String),
+    Parse(String),
+    Net(String),
+    NetUnsupported,
+    /// The interpreter doesn't allow fetching URLs
+    NetDisabled,
+    /// The URL isn't `https://`, so its source could be tampered with
+    InsecureUrl(String),
+    ChecksumUnsupported,
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+    BundleNotFound(String),
+    BadManifest(String),
+    Binary(BinaryError),
+    Basis(BasisError),
+}
+
+/// Read the source at `path`, which may be a path in `files` or, with the
+/// `net` feature, an `https://` URL. Remote sources are cached on disk, and
+/// if a sha256 checksum is given the source must match it, whether fetched
+/// or cached.
+pub(crate) fn load_source(
+    path: &str,
+    checksum: Option<&str>,
+    files: &dyn Files,
+) -> Result<String, LoadError> {
+    if is_url(path) {
+        if !path.starts_with("https://") {
+            return Err(LoadError::InsecureUrl(path.to_owned()));
+        }
+        return fetch(path, checksum);
+    }
+    let bytes = files
+        .read(Path::new(path))
+        .map_err(|err| LoadError::Io(err.to_string()))?;
+    let src = String::from_utf8(bytes).map_err(|err| LoadError::Io(err.to_string()))?;
+    if let Some(expected) = checksum {
+        verify(&src, expected)?;
+    }
+    Ok(src)
+}
+
+/// Whether `path` is a URL rather than a file, even one that won't be
+/// fetched
+pub(crate) fn is_url(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch(_url: &str, _checksum: Option<&str>) -> Result<String, LoadError> {
+    Err(LoadError::NetUnsupported)
+}
+
+#[cfg(not(feature = "net"))]
+fn verify(_src: &str, _expected: &str) -> Result<(), LoadError> {
+    Err(LoadError::ChecksumUnsupported)
+}
+
+#[cfg(feature = "net")]
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(feature = "net")]
+fn verify(src: &str, expected: &str) -> Result<(), LoadError> {
+    let actual = sha256_hex(src.as_bytes());
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(LoadError::ChecksumMismatch {
+            expected: expected.to_owned(),
+            actual,
+        })
+    }
+}
+
+#[cfg(feature = "net")]
+fn cache_dir() -> Option<std::path::PathBuf> {
+    use std::env;
+    use std::path::PathBuf;
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(PathBuf::from(dir).join("ucc"))
+    } else {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("ucc"))
+    }
+}
+
+/// The source cached at `path`, if there is one matching `checksum`. One
+/// that doesn't match is removed, to be fetched again.
+#[cfg(feature = "net")]
+pub(crate) fn read_cache(path: &Path, checksum: Option<&str>) -> Option<String> {
+    let src = fs::read_to_string(path).ok()?;
+    match checksum.map(|expected| verify(&src, expected)) {
+        None | Some(Ok(())) => Some(src),
+        Some(Err(_)) => {
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+fn fetch(url: &str, checksum: Option<&str>) -> Result<String, LoadError> {
+    let cache_path = cache_dir().map(|dir| dir.join(sha256_hex(url.as_bytes())));
+    if let Some(src) = cache_path
+        .as_ref()
+        .and_then(|path| read_cache(path, checksum))
+    {
+        return Ok(src);
+    }
+    let src = ureq::get(url)
+        .call()
+        .map_err(|err| LoadError::Net(err.to_string()))?
+        .into_string()
+        .map_err(|err| LoadError::Net(err.to_string()))?;
+    if let Some(expected) = checksum {
+        verify(&src, expected)?;
+    }
+    if let Some(path) = cache_path {
+        // Failing to cache is not fatal; the source is simply fetched again
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, &src);
+    }
+    Ok(src)
+}
@@ -0,0 +1,61 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Local definitions, which last only while an expression is evaluated,
+//! so helpers don't have to be defined for good. The interpreter reads
+//!
+//! ```text
+//! {local fn sq = clone compose fn quad = sq sq in [a] quad}
+//! ```
+//!
+//! as `[a] quad` evaluated with `sq` and `quad` defined, after which
+//! neither is. Local definitions shadow any others of their names, and
+//! inner scopes shadow outer ones.
+
+use crate::core::{Context, Expr, FnDef, OrderedMap, Symbol, Value, ValueStack};
+
+impl Context {
+    /// Define `fn_defs` in a new innermost scope, until `pop_locals`.
+    pub fn push_locals(&mut self, fn_defs: Vec<FnDef>) {
+        let scope = fn_defs.into_iter().map(|FnDef(sym, e)| (sym, e)).collect();
+        self.locals.push(scope);
+    }
+
+    /// End the innermost scope. Calls of its quotations left on `vs` are
+    /// replaced by the quotations, as they won't be defined after.
+    pub fn pop_locals(&mut self, vs: &mut ValueStack) {
+        let scope = match self.locals.pop() {
+            Some(scope) => scope,
+            None => return,
+        };
+        for v in vs.0.iter_mut() {
+            if let Value::Call(sym) = v {
+                if let Some(Expr::Quote(e)) = scope.get(sym) {
+                    *v = Value::Quote(e.clone());
+                }
+            }
+        }
+    }
+
+    /// End every scope, as when an evaluation stops early.
+    pub(crate) fn clear_locals(&mut self, vs: &mut ValueStack) {
+        while !self.locals.is_empty() {
+            self.pop_locals(vs);
+        }
+    }
+
+    /// The innermost local definition of `sym`, if it has one
+    pub(crate) fn lookup_local(&self, sym: &Symbol) -> Option<&Expr> {
+        self.locals.iter().rev().find_map(|scope| scope.get(sym))
+    }
+
+    pub fn is_local(&self, sym: &Symbol) -> bool {
+        self.lookup_local(sym).is_some()
+    }
+}
+
+/// The definitions of a scope, by name
+pub(crate) type Scope = OrderedMap<Symbol, Expr>;
@@ -0,0 +1,77 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Maps from values to values, kept in a `Hamt`, so that a program can
+//! keep a table and change it as it goes without copying it each time. A
+//! literal such as `#{'a => [x], 'b => [y]}` pushes itself, `#{}` being
+//! the empty map, and every context has the intrinsics
+//!
+//! - `map.insert`, `⟨#{} 'a [x]⟩ map.insert ⟶ ⟨#{'a => [x]}⟩`, replacing
+//!   any value the key had,
+//! - `map.lookup`, `⟨#{'a => [x]} 'a⟩ map.lookup ⟶ ⟨[x]⟩`, failing for a
+//!   key the map doesn't have,
+//! - `map.contains`, pushing `true` or `false`, as `nat.eq` does,
+//! - `map.remove`, `⟨#{'a => [x]} 'a⟩ map.remove ⟶ ⟨#{}⟩`,
+//! - `map.keys`, `⟨#{'a => [x]}⟩ map.keys ⟶ ⟨['a]⟩`, a quotation pushing
+//!   the keys, in an order fixed by their hashes.
+//!
+//! Each consumes the map, so `clone` it first to keep it; a copy shares the
+//! map's entries, so it takes constant time.
+
+use crate::core::{boolean, list, Context, EvalError, Expr, Value, ValueStack};
+use crate::hamt::Hamt;
+
+/// The maps of `Value::Map` and `Expr::Map`
+pub type ValueMap = Hamt<Value, Value>;
+
+/// Pop a map, or fail
+fn pop_map(vs: &mut ValueStack) -> Result<ValueMap, EvalError> {
+    match vs.pop() {
+        Some(Value::Map(m)) => Ok(m),
+        _ => Err(EvalError::Host("expected a map".to_owned())),
+    }
+}
+
+impl Context {
+    /// Register the `map.` intrinsics, as every context does
+    pub(crate) fn register_map_intrinsics(&mut self) {
+        self.register_intrinsic("map.insert", 3, |vs| {
+            let value = vs.pop().unwrap();
+            let key = vs.pop().unwrap();
+            let mut m = pop_map(vs)?;
+            m.insert(key, value);
+            vs.push(Value::Map(m));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("map.lookup", 2, |vs| {
+            let key = vs.pop().unwrap();
+            let m = pop_map(vs)?;
+            let value = m
+                .get(&key)
+                .ok_or_else(|| EvalError::Host("key not found".to_owned()))?;
+            vs.push(value.clone());
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("map.contains", 2, |vs| {
+            let key = vs.pop().unwrap();
+            let m = pop_map(vs)?;
+            vs.push(boolean(m.contains_key(&key)));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("map.remove", 2, |vs| {
+            let key = vs.pop().unwrap();
+            let mut m = pop_map(vs)?;
+            m.remove(&key);
+            vs.push(Value::Map(m));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("map.keys", 1, |vs| {
+            let m = pop_map(vs)?;
+            vs.push(list(m.keys().cloned()));
+            Ok(Expr::default())
+        });
+    }
+}
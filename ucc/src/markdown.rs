@@ -0,0 +1,88 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Literate sources, for `:load` of a `.md` file: the code of its fenced
+//! `ucc` blocks is loaded in order, and the prose around them is skipped.
+//! An `output` block right after a code block, with only blank lines
+//! between, gives the value stack the code should leave, e.g.
+//!
+//! ````text
+//! Swapping exchanges the top two values:
+//!
+//! ```ucc
+//! [a] [b] swap
+//! ```
+//!
+//! ```output
+//! ⟨[b] [a]⟩
+//! ```
+//! ````
+
+/// A fenced `ucc` block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    pub code: String,
+    /// The text of the `output` block after it
+    pub expected: Option<String>,
+}
+
+/// A fence opening a block, with its info string's first word, or closing
+/// one if that is empty
+fn fence(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(c).len();
+    if len < 3 {
+        return None;
+    }
+    let info = line[len..].split_whitespace().next().unwrap_or("");
+    Some((&line[..len], info))
+}
+
+/// The `ucc` blocks of the Markdown `src`, in order
+pub fn code_blocks(src: &str) -> Vec<CodeBlock> {
+    let mut blocks: Vec<CodeBlock> = vec![];
+    let mut lines = src.lines();
+    // Whether an `output` block would be the last code block's
+    let mut after_code = false;
+    while let Some(line) = lines.next() {
+        let (marker, info) = match fence(line) {
+            Some(fence) => fence,
+            None => {
+                after_code &= line.trim().is_empty();
+                continue;
+            }
+        };
+        let mut text = String::new();
+        for line in lines.by_ref() {
+            if matches!(fence(line), Some((close, "")) if close.starts_with(marker)) {
+                break;
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+        match info {
+            "ucc" => {
+                blocks.push(CodeBlock {
+                    code: text,
+                    expected: None,
+                });
+                after_code = true;
+            }
+            "output" if after_code => {
+                blocks.last_mut().unwrap().expected = Some(text);
+                after_code = false;
+            }
+            _ => after_code = false,
+        }
+    }
+    blocks
+}
+
+/// Whether `actual` is `expected`, but for whitespace
+pub fn matches_expected(actual: &str, expected: &str) -> bool {
+    actual.split_whitespace().eq(expected.split_whitespace())
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Metrics of a server's sessions, written in the Prometheus text format,
+//! as `ucci serve` exposes them on `/metrics`, e.g.
+//!
+//! ```text
+//! # HELP ucc_small_steps_total Small steps taken.
+//! # TYPE ucc_small_steps_total counter
+//! ucc_small_steps_total 1042
+//! ```
+
+use crate::interp::InterpStats;
+
+/// What a server's sessions have done, and hold
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Metrics {
+    /// The work done by every session, including those since ended
+    pub totals: InterpStats,
+    /// The name and memory estimate of each session open
+    pub sessions: Vec<(String, usize)>,
+}
+
+impl Metrics {
+    /// The metrics, in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let counters = [
+            (
+                "ucc_evaluations_total",
+                "Commands started that evaluate.",
+                self.totals.evaluations,
+            ),
+            (
+                "ucc_small_steps_total",
+                "Small steps taken.",
+                self.totals.small_steps,
+            ),
+            (
+                "ucc_errors_total",
+                "Small steps that failed.",
+                self.totals.errors,
+            ),
+        ];
+        for (name, help, value) in counters {
+            header(&mut text, name, "counter", help);
+            text.push_str(&format!("{} {}\n", name, value));
+        }
+        header(&mut text, "ucc_active_sessions", "gauge", "Sessions open.");
+        text.push_str(&format!("ucc_active_sessions {}\n", self.sessions.len()));
+        header(
+            &mut text,
+            "ucc_session_memory_bytes",
+            "gauge",
+            "Estimated bytes each session holds.",
+        );
+        for (session, bytes) in self.sessions.iter() {
+            text.push_str(&format!(
+                "ucc_session_memory_bytes{{session=\"{}\"}} {}\n",
+                escape_label(session),
+                bytes
+            ));
+        }
+        text
+    }
+}
+
+fn header(text: &mut String, name: &str, kind: &str, help: &str) {
+    text.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} {}\n",
+        name, help, name, kind
+    ));
+}
+
+/// `value` escaped as the value of a label
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,300 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Shrinking, for `:minimize`: the smallest part of a failing expression
+//! found to fail the same way, by delta debugging. Items are deleted from
+//! the expression, in chunks that halve in size, and quotations that are
+//! applied right away are unwrapped, while it still fails, then the same
+//! is done inside each quotation in turn, e.g.
+//!
+//! ```text
+//! [a] [b] swap [c] compose drop drop drop  ⟶  drop
+//! ```
+//!
+//! Two failures are the same if their errors are, but for the counts of
+//! `TooFewValues`. Since candidates mustn't change the definitions,
+//! `define!` fails with `DefineDisabled` while shrinking.
+//!
+//! Counterexamples found by the randomized tests shrink the same way,
+//! their value stacks included, and are given as a big step assertion to
+//! reproduce them with.
+
+use crate::core::{Context, EvalError, Expr, Intrinsic, ValueStack};
+
+/// The most small steps each candidate is evaluated for
+pub const MINIMIZE_FUEL: usize = 10000;
+
+/// The most candidates evaluated, after which the smallest found so far
+/// is given
+pub const MINIMIZE_MAX_TESTS: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinimizeError {
+    /// The expression halts without an error, or runs out of fuel
+    NoFailure,
+}
+
+struct Shrinker<'a> {
+    /// The value stack candidates are evaluated on
+    vs: ValueStack,
+    /// Whether a candidate still shows what's being shrunk for
+    test: &'a mut dyn FnMut(&mut Context, &ValueStack, &Expr) -> bool,
+    tests: usize,
+}
+
+fn same_failure(err1: &EvalError, err2: &EvalError) -> bool {
+    match (err1, err2) {
+        (EvalError::TooFewValues { .. }, EvalError::TooFewValues { .. }) => true,
+        (err1, err2) => err1 == err2,
+    }
+}
+
+/// The items of `e`, with nested compositions flattened
+fn flat_items(e: &Expr) -> Vec<Expr> {
+    match e {
+        Expr::Compose(es) => es.iter().flat_map(flat_items).collect(),
+        e => vec![e.clone()],
+    }
+}
+
+fn compose(mut items: Vec<Expr>) -> Expr {
+    if items.len() == 1 {
+        items.pop().unwrap()
+    } else {
+        Expr::Compose(items)
+    }
+}
+
+impl Context {
+    /// The smallest expression found by deleting parts of `e` that fails
+    /// evaluated from an empty stack the way `e` does, and that failure
+    pub fn minimize(&mut self, e: &Expr) -> Result<(Expr, EvalError), MinimizeError> {
+        // Candidates mustn't change the definitions
+        let allow_define = self.allow_define;
+        self.allow_define = false;
+        let result = match self.failure(e) {
+            Some(failure) => {
+                let mut test = |ctx: &mut Context, _: &ValueStack, e: &Expr| matches!(ctx.failure(e), Some(err) if same_failure(&err, &failure));
+                let mut shrinker = Shrinker {
+                    vs: ValueStack::default(),
+                    test: &mut test,
+                    tests: 0,
+                };
+                let e = self.shrink_expr(&mut shrinker, e);
+                let err = self.failure(&e).unwrap();
+                Ok((e, err))
+            }
+            None => Err(MinimizeError::NoFailure),
+        };
+        self.allow_define = allow_define;
+        result
+    }
+
+    /// The smallest value stack and expression found by deleting parts of
+    /// `vs` and `e` for which `test` still holds, e.g. that two engines
+    /// evaluating `e` on `vs` differ
+    pub fn shrink_counterexample<F>(
+        &mut self,
+        vs: &ValueStack,
+        e: &Expr,
+        mut test: F,
+    ) -> (ValueStack, Expr)
+    where
+        F: FnMut(&mut Context, &ValueStack, &Expr) -> bool,
+    {
+        let mut shrinker = Shrinker {
+            vs: vs.clone(),
+            test: &mut test,
+            tests: 0,
+        };
+        let mut e = e.clone();
+        loop {
+            let before = (shrinker.vs.clone(), e.clone());
+            let mut i = 0;
+            while i < shrinker.vs.len() {
+                let mut candidate = shrinker.vs.clone();
+                candidate.0.remove(i);
+                if self.still_holds_on(&mut shrinker, &candidate, &e) {
+                    shrinker.vs = candidate;
+                } else {
+                    i += 1;
+                }
+            }
+            e = self.shrink_expr(&mut shrinker, &e);
+            if (shrinker.vs.clone(), e.clone()) == before {
+                return before;
+            }
+        }
+    }
+
+    /// A big step assertion of where the evaluation of `e` on `vs` by small
+    /// steps ends, within `fuel` of them: with an empty expression, unless
+    /// it fails or runs out of fuel
+    pub fn big_step_assertion(&mut self, vs: &ValueStack, e: &Expr, fuel: usize) -> String {
+        let mut out_vs = vs.clone();
+        let mut out_e = e.clone();
+        for _ in 0..fuel {
+            if out_e == Expr::default() || self.small_step(&mut out_vs, &mut out_e).is_err() {
+                break;
+            }
+        }
+        let assertion = format!(
+            "{} {} ⇓ {} {}",
+            self.display(vs),
+            self.display(e),
+            self.display(&out_vs),
+            self.display(&out_e)
+        );
+        assertion.trim_end().to_owned()
+    }
+
+    fn shrink_expr(&mut self, shrinker: &mut Shrinker, e: &Expr) -> Expr {
+        let items = self.shrink(shrinker, flat_items(e), &|items| compose(items.to_vec()));
+        compose(items)
+    }
+
+    /// The error evaluating `e` from an empty stack fails with, if any
+    fn failure(&mut self, e: &Expr) -> Option<EvalError> {
+        let mut vs = ValueStack::default();
+        let mut e = e.clone();
+        for _ in 0..MINIMIZE_FUEL {
+            if e == Expr::default() {
+                return None;
+            }
+            if let Err(err) = self.small_step(&mut vs, &mut e) {
+                return Some(err);
+            }
+        }
+        None
+    }
+
+    /// Whether the test still holds of the whole expression, `whole(items)`
+    fn still_holds(
+        &mut self,
+        shrinker: &mut Shrinker,
+        items: &[Expr],
+        whole: &dyn Fn(&[Expr]) -> Expr,
+    ) -> bool {
+        let vs = shrinker.vs.clone();
+        self.still_holds_on(shrinker, &vs, &whole(items))
+    }
+
+    /// Whether the test still holds of `vs` and `e`, within the candidates
+    /// left to test
+    fn still_holds_on(&mut self, shrinker: &mut Shrinker, vs: &ValueStack, e: &Expr) -> bool {
+        if shrinker.tests == MINIMIZE_MAX_TESTS {
+            return false;
+        }
+        shrinker.tests += 1;
+        (shrinker.test)(self, vs, e)
+    }
+
+    /// `items`, shrunk as far as they go with the test still holding of the
+    /// whole expression they are in, `whole(items)`
+    fn shrink(
+        &mut self,
+        shrinker: &mut Shrinker,
+        mut items: Vec<Expr>,
+        whole: &dyn Fn(&[Expr]) -> Expr,
+    ) -> Vec<Expr> {
+        loop {
+            let before = items.clone();
+            loop {
+                items = self.delete(shrinker, items, whole);
+                match self.unwrap_applied(shrinker, &items, whole) {
+                    Some(unwrapped) => items = unwrapped,
+                    None => break,
+                }
+            }
+            for i in 0..items.len() {
+                let quoted = match &items[i] {
+                    Expr::Quote(e) => flat_items(e),
+                    _ => continue,
+                };
+                let (before, after) = (items[..i].to_vec(), items[i + 1..].to_vec());
+                let inner = |inner: &[Expr]| {
+                    let mut new = before.clone();
+                    new.push(Expr::Quote(Box::new(compose(inner.to_vec()))));
+                    new.extend_from_slice(&after);
+                    whole(&new)
+                };
+                let shrunk = self.shrink(shrinker, quoted, &inner);
+                items[i] = Expr::Quote(Box::new(compose(shrunk)));
+            }
+            // Shrinking a quotation may let more be deleted around it
+            if items == before {
+                return items;
+            }
+        }
+    }
+
+    /// `items` with chunks of them deleted while the test still holds,
+    /// halving the size of the chunks when none can be, down to single
+    /// items, then pairs of them
+    fn delete(
+        &mut self,
+        shrinker: &mut Shrinker,
+        mut items: Vec<Expr>,
+        whole: &dyn Fn(&[Expr]) -> Expr,
+    ) -> Vec<Expr> {
+        let mut chunks = 2;
+        while !items.is_empty() {
+            let size = items.len().div_ceil(chunks);
+            let mut deleted = false;
+            for start in (0..items.len()).step_by(size) {
+                let mut candidate = items[..start].to_vec();
+                candidate.extend_from_slice(&items[(start + size).min(items.len())..]);
+                if self.still_holds(shrinker, &candidate, whole) {
+                    items = candidate;
+                    deleted = true;
+                    break;
+                }
+            }
+            if deleted {
+                chunks = (chunks - 1).max(2);
+            } else if size > 1 {
+                chunks = (chunks * 2).min(items.len());
+            } else {
+                // No one item can go, but two together might, like `[] swap`
+                let pair = (1..items.len()).find(|&i| {
+                    let mut candidate = items[..i - 1].to_vec();
+                    candidate.extend_from_slice(&items[i + 1..]);
+                    self.still_holds(shrinker, &candidate, whole)
+                });
+                match pair {
+                    Some(i) => {
+                        items.drain(i - 1..=i);
+                        chunks = 2;
+                    }
+                    None => break,
+                }
+            }
+        }
+        items
+    }
+
+    /// `items` with the first `[e] apply` in them replaced by `e` such
+    /// that the test still holds, if there is one
+    fn unwrap_applied(
+        &mut self,
+        shrinker: &mut Shrinker,
+        items: &[Expr],
+        whole: &dyn Fn(&[Expr]) -> Expr,
+    ) -> Option<Vec<Expr>> {
+        for i in 1..items.len() {
+            if let (Expr::Quote(e), Expr::Intrinsic(Intrinsic::Apply)) = (&items[i - 1], &items[i])
+            {
+                let mut candidate = items[..i - 1].to_vec();
+                candidate.extend(flat_items(e));
+                candidate.extend_from_slice(&items[i + 1..]);
+                if self.still_holds(shrinker, &candidate, whole) {
+                    return Some(candidate);
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Mutation testing: make small changes to a library's definitions, one at
+//! a time, and check that its big step assertions notice. A mutant that
+//! passes every assertion survives, and shows behavior the assertions
+//! don't pin down.
+//!
+//! An assertion `⟨input⟩ e ⇓ ⟨output⟩ e'` passes if evaluating `e` on
+//! `⟨input⟩` reaches `⟨output⟩ e'` without an error, within the fuel.
+
+use crate::core::{Context, Expr, FnDef, Intrinsic, ValueStack};
+use crate::interp::InterpItem;
+use crate::parse::{BigStepAssertionParser, InterpItemsParser};
+use crate::shared::SharedPrelude;
+
+/// The intrinsics a mutant may swap in, leaving out `define!`, which has
+/// side effects
+const INTRINSICS: [Intrinsic; 8] = [
+    Intrinsic::Swap,
+    Intrinsic::Clone,
+    Intrinsic::Drop,
+    Intrinsic::Quote,
+    Intrinsic::Compose,
+    Intrinsic::Apply,
+    Intrinsic::Defined,
+    Intrinsic::BodyOf,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MutateError {
+    Parse(String),
+    /// An assertion, given by its line, fails without any mutation
+    Fails(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MutantResult {
+    /// The mutated definition
+    pub fn_def: String,
+    /// What was changed, e.g. "deleted `swap`"
+    pub mutation: String,
+    pub survived: bool,
+}
+
+pub(crate) type Assertion = (ValueStack, Expr, ValueStack, Expr);
+
+/// Check each mutant of the definitions in the source `lib` against the big
+/// step assertions in `tests`, one per line, with `fuel` small steps for
+/// each. Expressions and rules in `lib` are left out of the mutants.
+pub fn mutation_test(
+    lib: &str,
+    tests: &str,
+    fuel: usize,
+) -> Result<Vec<MutantResult>, MutateError> {
+    let prelude = SharedPrelude::default();
+    let mut ctx = Context::with_prelude(&prelude);
+    let fn_defs: Vec<FnDef> = InterpItemsParser::new()
+        .parse(&mut ctx.interner, lib)
+        .map_err(|err| MutateError::Parse(format!("{:?}", err)))?
+        .into_iter()
+        .filter_map(|item| match item {
+            InterpItem::FnDef(fn_def) => Some(fn_def),
+            _ => None,
+        })
+        .collect();
+    let mut assertions = vec![];
+    for line in tests.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("--") {
+            continue;
+        }
+        let assertion = BigStepAssertionParser::new()
+            .parse(&mut ctx.interner, line)
+            .map_err(|err| MutateError::Parse(format!("{:?}", err)))?;
+        assertions.push((line, assertion));
+    }
+    for fn_def in fn_defs.iter() {
+        ctx.define_fn(fn_def.clone()).unwrap();
+    }
+    for (line, assertion) in assertions.iter() {
+        if !ctx.check(assertion, fuel) {
+            return Err(MutateError::Fails(line.to_string()));
+        }
+    }
+    let mut results = vec![];
+    for FnDef(sym, body) in fn_defs.iter() {
+        for (mutation, body) in ctx.mutants(body) {
+            let mut mutant_ctx = ctx.fork();
+            let fn_def = FnDef(*sym, body);
+            mutant_ctx.define_fn(fn_def.clone()).unwrap();
+            let survived = assertions
+                .iter()
+                .all(|(_, assertion)| mutant_ctx.check(assertion, fuel));
+            results.push(MutantResult {
+                fn_def: mutant_ctx.display(&fn_def).to_string(),
+                mutation,
+                survived,
+            });
+        }
+    }
+    Ok(results)
+}
+
+impl Context {
+    /// Whether `assertion` holds within `fuel` small steps
+    pub(crate) fn check(&mut self, assertion: &Assertion, fuel: usize) -> bool {
+        let (mut vs, mut e, expected_vs, expected_e) = assertion.clone();
+        for _ in 0..fuel {
+            if vs == expected_vs && e == expected_e {
+                return true;
+            }
+            if e == Expr::default() || self.small_step(&mut vs, &mut e).is_err() {
+                return false;
+            }
+        }
+        vs == expected_vs && e == expected_e
+    }
+
+    /// Each way of deleting an intrinsic or call in `body`, swapping the
+    /// arguments it is given, or replacing an intrinsic with another, with
+    /// a description of the change
+    fn mutants(&self, body: &Expr) -> Vec<(String, Expr)> {
+        let mut atoms = vec![];
+        body.visit(&mut |e| {
+            if let Expr::Intrinsic(_) | Expr::Call(_) = e {
+                atoms.push(e.clone());
+            }
+        });
+        let mut mutants = vec![];
+        for (i, atom) in atoms.iter().enumerate() {
+            let name = self.display(atom).to_string();
+            mutants.push((
+                format!("deleted `{}`", name),
+                replace_atom(body, i, Expr::default()),
+            ));
+            if *atom != Expr::Intrinsic(Intrinsic::Swap) {
+                let swapped = Expr::Compose(vec![Expr::Intrinsic(Intrinsic::Swap), atom.clone()]);
+                mutants.push((
+                    format!("swapped the arguments of `{}`", name),
+                    replace_atom(body, i, swapped),
+                ));
+            }
+            if let Expr::Intrinsic(intr) = atom {
+                for other in INTRINSICS.iter().filter(|other| *other != intr) {
+                    let other = Expr::Intrinsic(*other);
+                    mutants.push((
+                        format!("replaced `{}` with `{}`", name, self.display(&other)),
+                        replace_atom(body, i, other),
+                    ));
+                }
+            }
+        }
+        mutants
+    }
+}
+
+/// `body` with its `index`th intrinsic or call replaced by `with`
+fn replace_atom(body: &Expr, index: usize, with: Expr) -> Expr {
+    let mut i = 0;
+    let mut with = Some(with);
+    body.clone().fold(&mut |e| match e {
+        Expr::Intrinsic(_) | Expr::Call(_) => {
+            i += 1;
+            if i - 1 == index {
+                with.take().unwrap()
+            } else {
+                e
+            }
+        }
+        // Splice in compositions, so mutants are written as they would be
+        // in source
+        Expr::Compose(es) => {
+            let mut es: Vec<Expr> = es
+                .into_iter()
+                .flat_map(|e| match e {
+                    Expr::Compose(es) => es,
+                    e => vec![e],
+                })
+                .collect();
+            if es.len() == 1 {
+                es.pop().unwrap()
+            } else {
+                Expr::Compose(es)
+            }
+        }
+        e => e,
+    })
+}
@@ -0,0 +1,156 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Natural numbers of any size, with the `bignum` feature, for experiments
+//! with numerals too large to count in their Church encoding. A literal
+//! such as `42` pushes itself, and every context has the intrinsics
+//!
+//! - `nat.add`, `nat.sub`, `nat.mul`, `nat.div` and `nat.mod`, where
+//!   `⟨7 2⟩ nat.sub ⟶ ⟨5⟩`, and a difference below 0 is 0,
+//! - `nat.eq` and `nat.lt`, pushing `true` or `false`, as `defined?` does,
+//! - `nat.succ`,
+//! - `nat.to_church`, `⟨3⟩ nat.to_church ⟶ ⟨[clone clone compose compose
+//!   apply]⟩`, a numeral that applies a quotation 3 times, as `n3` does,
+//! - `nat.from_church`, `⟨n3⟩ nat.from_church ⇓ ⟨3⟩`, counting the times
+//!   a numeral applies a quotation.
+//!
+//! Without the feature, a literal doesn't parse.
+
+#[cfg(feature = "bignum")]
+use crate::core::{boolean, pushing, Context, EvalError, Intrinsic, Symbol, ValueStack};
+use crate::core::{Expr, Value};
+#[cfg(feature = "bignum")]
+use num_bigint::BigUint;
+#[cfg(feature = "bignum")]
+use std::convert::TryFrom;
+
+/// The largest number `nat.to_church` converts, as its numeral grows with
+/// the number
+#[cfg(feature = "bignum")]
+pub const MAX_CHURCH: u32 = 1 << 16;
+
+/// The literal `digits`, for the parser
+#[cfg_attr(not(feature = "bignum"), allow(unused_variables))]
+pub(crate) fn nat_literal(digits: &str) -> Result<Expr, &'static str> {
+    #[cfg(feature = "bignum")]
+    return Ok(Expr::Nat(digits.parse().unwrap()));
+    #[cfg(not(feature = "bignum"))]
+    return Err("number literals need the `bignum` feature");
+}
+
+/// The literal `digits` as a value, for the parser
+#[cfg_attr(not(feature = "bignum"), allow(unused_variables))]
+pub(crate) fn nat_value(digits: &str) -> Result<Value, &'static str> {
+    #[cfg(feature = "bignum")]
+    return Ok(Value::Nat(digits.parse().unwrap()));
+    #[cfg(not(feature = "bignum"))]
+    return Err("number literals need the `bignum` feature");
+}
+
+/// The Church numeral for `n`, which applies the quotation on top of the
+/// stack `n` times: `drop` for 0, or `n - 1` clones composed and applied
+#[cfg(feature = "bignum")]
+pub fn church(n: u32) -> Expr {
+    if n == 0 {
+        return Expr::Intrinsic(Intrinsic::Drop);
+    }
+    let n = n as usize - 1;
+    let mut es = vec![Expr::Intrinsic(Intrinsic::Clone); n];
+    es.extend(vec![Expr::Intrinsic(Intrinsic::Compose); n]);
+    es.push(Expr::Intrinsic(Intrinsic::Apply));
+    if es.len() == 1 {
+        es.pop().unwrap()
+    } else {
+        Expr::Compose(es)
+    }
+}
+
+/// Pop a number, or fail
+#[cfg(feature = "bignum")]
+fn pop_nat(vs: &mut ValueStack) -> Result<BigUint, EvalError> {
+    match vs.pop() {
+        Some(Value::Nat(n)) => Ok(n),
+        _ => Err(EvalError::Host("expected a number".to_owned())),
+    }
+}
+
+/// Pop two numbers, the top one second
+#[cfg(feature = "bignum")]
+fn pop_nats(vs: &mut ValueStack) -> Result<(BigUint, BigUint), EvalError> {
+    let b = pop_nat(vs)?;
+    let a = pop_nat(vs)?;
+    Ok((a, b))
+}
+
+#[cfg(feature = "bignum")]
+impl Context {
+    /// Register the `nat.` intrinsics, as every context does
+    pub(crate) fn register_nat_intrinsics(&mut self) {
+        fn binary<F>(ctx: &mut Context, name: &str, f: F)
+        where
+            F: Fn(BigUint, BigUint) -> Result<Value, EvalError> + 'static,
+        {
+            ctx.register_intrinsic(name, 2, move |vs| {
+                let (a, b) = pop_nats(vs)?;
+                vs.push(f(a, b)?);
+                Ok(Expr::default())
+            });
+        }
+        let division_by_zero = || EvalError::Host("division by zero".to_owned());
+        binary(self, "nat.add", |a, b| Ok(Value::Nat(a + b)));
+        binary(self, "nat.sub", |a, b| {
+            Ok(Value::Nat(if a > b { a - b } else { BigUint::default() }))
+        });
+        binary(self, "nat.mul", |a, b| Ok(Value::Nat(a * b)));
+        binary(self, "nat.div", move |a, b| {
+            if b == BigUint::default() {
+                Err(division_by_zero())
+            } else {
+                Ok(Value::Nat(a / b))
+            }
+        });
+        binary(self, "nat.mod", move |a, b| {
+            if b == BigUint::default() {
+                Err(division_by_zero())
+            } else {
+                Ok(Value::Nat(a % b))
+            }
+        });
+        binary(self, "nat.eq", |a, b| Ok(boolean(a == b)));
+        binary(self, "nat.lt", |a, b| Ok(boolean(a < b)));
+        self.register_intrinsic("nat.succ", 1, |vs| {
+            let n = pop_nat(vs)?;
+            vs.push(Value::Nat(n + 1u32));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("nat.to_church", 1, |vs| {
+            let n = pop_nat(vs)?;
+            match u32::try_from(&n) {
+                Ok(n) if n <= MAX_CHURCH => {
+                    vs.push(Value::Quote(Box::new(church(n))));
+                    Ok(Expr::default())
+                }
+                _ => Err(EvalError::Host(format!(
+                    "{} is over {}, too large for a numeral",
+                    n, MAX_CHURCH
+                ))),
+            }
+        });
+        // ⟨[n]⟩ ⟶ ⟨0 [nat.succ]⟩ [n] apply
+        let succ = Symbol(self.interner.get_or_intern("nat.succ"));
+        self.register_intrinsic("nat.from_church", 1, move |vs| {
+            let numeral = vs.pop().unwrap();
+            vs.push(Value::Nat(BigUint::default()));
+            vs.push(Value::Quote(Box::new(Expr::Intrinsic(Intrinsic::Host(
+                succ,
+            )))));
+            Ok(Expr::Compose(vec![
+                pushing(numeral),
+                Expr::Intrinsic(Intrinsic::Apply),
+            ]))
+        });
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Pairs of values. A literal such as `('a, [b])` pushes itself, and every
+//! context has the intrinsics
+//!
+//! - `pair`, `⟨'a [b]⟩ pair ⟶ ⟨('a, [b])⟩`,
+//! - `fst`, `⟨('a, [b])⟩ fst ⟶ ⟨'a⟩`,
+//! - `snd`, `⟨('a, [b])⟩ snd ⟶ ⟨[b]⟩`,
+//!
+//! which take a step each, where the same pair encoded as the quotation
+//! `['a [b]]` takes several to take apart. The prelude converts between the
+//! two with `pair_to_quote`, `⟨('a, [b])⟩ pair_to_quote ⇓ ⟨['a [b]]⟩`, and
+//! `quote_to_pair`. As with other intrinsics, a function defined as
+//! `pair`, `fst` or `snd` is called instead.
+
+use crate::core::{pushing, Context, EvalError, Expr, Value, ValueStack};
+
+/// The value the literal `(e1, e2)` pushes
+pub(crate) fn pair_value(p: &(Expr, Expr)) -> Value {
+    Value::Pair(Box::new((item_value(&p.0), item_value(&p.1))))
+}
+
+/// The value `e` pushes, the inverse of `pushing`. An item that doesn't
+/// push one value, as `Expr::fold` may leave, is pushed quoted.
+pub(crate) fn item_value(e: &Expr) -> Value {
+    match e {
+        Expr::Call(sym) => Value::Call(*sym),
+        Expr::Symbol(sym) => Value::Symbol(*sym),
+        Expr::Quote(e) => Value::Quote(e.clone()),
+        #[cfg(feature = "bignum")]
+        Expr::Nat(n) => Value::Nat(n.clone()),
+        #[cfg(feature = "rational")]
+        Expr::Ratio(r) => Value::Ratio(r.clone()),
+        Expr::Pair(p) => pair_value(p),
+        Expr::Map(m) => Value::Map(m.clone()),
+        Expr::Char(c) => Value::Char(*c),
+        Expr::Str(s) => Value::Str(s.clone()),
+        e => Value::Quote(Box::new(e.clone())),
+    }
+}
+
+/// The literal `(v1, v2)`, for the parser
+pub(crate) fn pair_literal(v1: Value, v2: Value) -> Expr {
+    Expr::Pair(Box::new((pushing(v1), pushing(v2))))
+}
+
+/// Pop a pair, or fail
+fn pop_pair(vs: &mut ValueStack) -> Result<(Value, Value), EvalError> {
+    match vs.pop() {
+        Some(Value::Pair(p)) => Ok(*p),
+        _ => Err(EvalError::Host("expected a pair".to_owned())),
+    }
+}
+
+impl Context {
+    /// Register `pair`, `fst` and `snd`, as every context does
+    pub(crate) fn register_pair_intrinsics(&mut self) {
+        self.register_intrinsic("pair", 2, |vs| {
+            let v2 = vs.pop().unwrap();
+            let v1 = vs.pop().unwrap();
+            vs.push(Value::Pair(Box::new((v1, v2))));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("fst", 1, |vs| {
+            let (v1, _) = pop_pair(vs)?;
+            vs.push(v1);
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("snd", 1, |vs| {
+            let (_, v2) = pop_pair(vs)?;
+            vs.push(v2);
+            Ok(Expr::default())
+        });
+    }
+}
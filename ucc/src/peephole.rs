@@ -0,0 +1,61 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Peephole optimization of the ops `Vm::optimized` compiles. Calls of
+//! small functions are replaced by their bodies, then ops that cancel out
+//! are removed:
+//!
+//! ```text
+//! push [a] drop  ⟶
+//! swap swap      ⟶  check 2
+//! clone drop     ⟶  check 1
+//! ```
+//!
+//! where `check n` fails as the ops it replaced would, when there are
+//! fewer than `n` values. Optimized code evaluates to the same values and
+//! errors, but in fewer ops, and so less fuel.
+
+use crate::core::{Expr, Intrinsic};
+use crate::vm::Op;
+
+/// The most items a function's body may have to be inlined
+pub const INLINE_MAX_ITEMS: usize = 8;
+
+/// Whether calls of a function with the body `body` are inlined
+pub(crate) fn inlinable(body: &Expr) -> bool {
+    fn items(e: &Expr) -> usize {
+        match e {
+            Expr::Compose(es) => es.iter().map(items).sum(),
+            _ => 1,
+        }
+    }
+    items(body) <= INLINE_MAX_ITEMS
+}
+
+/// `ops` with each pair that cancels out removed or replaced by a check,
+/// including those that come together once others are removed
+pub fn peephole(ops: Vec<Op>) -> Vec<Op> {
+    let mut optimized: Vec<Op> = Vec::with_capacity(ops.len());
+    for op in ops {
+        let replacement = match (optimized.last(), &op) {
+            (Some(Op::Push(_)), Op::Intrinsic(Intrinsic::Drop)) => None,
+            (Some(Op::Intrinsic(Intrinsic::Swap)), Op::Intrinsic(Intrinsic::Swap)) => {
+                Some(Op::Check(2))
+            }
+            (
+                Some(Op::Intrinsic(Intrinsic::Clone | Intrinsic::Dup)),
+                Op::Intrinsic(Intrinsic::Drop),
+            ) => Some(Op::Check(1)),
+            _ => {
+                optimized.push(op);
+                continue;
+            }
+        };
+        optimized.pop();
+        optimized.extend(replacement);
+    }
+    optimized
+}
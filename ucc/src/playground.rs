@@ -0,0 +1,200 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The messages of the playground protocol, spoken over a WebSocket at
+//! `/sessions/<id>/ws`, so a web trace viewer can show each step as it is
+//! taken rather than wait for the evaluation to finish. Each message is a
+//! JSON object with a `type`. The client sends
+//!
+//! - `{"type": "eval", "input": "[a] clone"}` to start input in the
+//!   session,
+//! - `{"type": "pause"}` and `{"type": "resume"}` to stop and start
+//!   stepping it,
+//! - `{"type": "cancel"}` to stop every job it has running,
+//! - `{"type": "capabilities"}` to ask what the server was built with,
+//!
+//! and the server sends
+//!
+//! - `{"type": "step", "stack": "⟨[a]⟩", "expr": "clone"}` before each
+//!   small step,
+//! - `{"type": "output", "text": "⇓ ⟨[a] [a]⟩ \n"}` with what the
+//!   interpreter writes,
+//! - `{"type": "paused"}`, `{"type": "resumed"}`, and
+//!   `{"type": "cancelled"}` in answer to the client's,
+//! - `{"type": "done"}` when every job has finished,
+//! - `{"type": "capabilities", "capabilities": {"version": "0.1.0",
+//!   "features": ["net"]}}` in answer to the client's,
+//! - `{"type": "error", "message": "..."}` for a message it can't read.
+
+use crate::atlas::json_string;
+use crate::capabilities::Capabilities;
+use crate::interp::TraceStep;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientMessage {
+    Eval(String),
+    Pause,
+    Resume,
+    Cancel,
+    Capabilities,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerMessage {
+    Step(TraceStep),
+    Output(String),
+    Paused,
+    Resumed,
+    Cancelled,
+    Done,
+    Capabilities(Capabilities),
+    Error(String),
+}
+
+impl ClientMessage {
+    pub fn parse(json: &str) -> Result<ClientMessage, String> {
+        let fields = parse_object(json).ok_or_else(|| "expected a JSON object".to_owned())?;
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        match field("type") {
+            Some("eval") => match field("input") {
+                Some(input) => Ok(ClientMessage::Eval(input.to_owned())),
+                None => Err("expected an `input`".to_owned()),
+            },
+            Some("pause") => Ok(ClientMessage::Pause),
+            Some("resume") => Ok(ClientMessage::Resume),
+            Some("cancel") => Ok(ClientMessage::Cancel),
+            Some("capabilities") => Ok(ClientMessage::Capabilities),
+            Some(other) => Err(format!("unknown message type `{}`", other)),
+            None => Err("expected a `type`".to_owned()),
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn to_json(&self) -> String {
+        let kind = |kind: &str| format!("{{\"type\": \"{}\"}}", kind);
+        match self {
+            ServerMessage::Step(step) => format!(
+                "{{\"type\": \"step\", \"stack\": {}, \"expr\": {}}}",
+                json_string(&step.stack),
+                json_string(&step.expr)
+            ),
+            ServerMessage::Output(text) => {
+                format!("{{\"type\": \"output\", \"text\": {}}}", json_string(text))
+            }
+            ServerMessage::Paused => kind("paused"),
+            ServerMessage::Resumed => kind("resumed"),
+            ServerMessage::Cancelled => kind("cancelled"),
+            ServerMessage::Done => kind("done"),
+            ServerMessage::Capabilities(capabilities) => format!(
+                "{{\"type\": \"capabilities\", \"capabilities\": {}}}",
+                capabilities.to_json()
+            ),
+            ServerMessage::Error(msg) => {
+                format!("{{\"type\": \"error\", \"message\": {}}}", json_string(msg))
+            }
+        }
+    }
+}
+
+/// The fields of a JSON object whose values are all strings
+fn parse_object(json: &str) -> Option<Vec<(String, String)>> {
+    let mut chars = json.chars().peekable();
+    let mut fields = vec![];
+    skip_whitespace(&mut chars);
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            let key = parse_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            if chars.next()? != ':' {
+                return None;
+            }
+            let value = parse_string(&mut chars)?;
+            fields.push((key, value));
+            skip_whitespace(&mut chars);
+            match chars.next()? {
+                ',' => {}
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Some(fields),
+        Some(_) => None,
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while matches!(chars.peek(), Some(c) if c.is_ascii_whitespace()) {
+        chars.next();
+    }
+}
+
+/// A JSON string, after any whitespace
+fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    skip_whitespace(chars);
+    if chars.next()? != '"' {
+        return None;
+    }
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                '"' => s.push('"'),
+                '\\' => s.push('\\'),
+                '/' => s.push('/'),
+                'b' => s.push('\u{8}'),
+                'f' => s.push('\u{c}'),
+                'n' => s.push('\n'),
+                'r' => s.push('\r'),
+                't' => s.push('\t'),
+                'u' => {
+                    let high = parse_hex4(chars)?;
+                    let c = if (0xD800..0xDC00).contains(&high) {
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = parse_hex4(chars)?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return None;
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    } else {
+                        high
+                    };
+                    s.push(char::from_u32(c)?);
+                }
+                _ => return None,
+            },
+            c if (c as u32) < 0x20 => return None,
+            c => s.push(c),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Option<u32> {
+    let mut n = 0;
+    for _ in 0..4 {
+        n = n * 16 + chars.next()?.to_digit(16)?;
+    }
+    Some(n)
+}
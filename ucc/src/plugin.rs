@@ -0,0 +1,193 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Plugins: dynamic libraries, loaded with `:plugin load "<path>"` when the
+//! `plugins` feature is enabled, that register intrinsics and definitions.
+//! A plugin is a `cdylib` built against the same version of this crate,
+//! with the same features, and with the same compiler, e.g.
+//!
+//! ```ignore
+//! use ucc::plugin::PluginRegistrar;
+//!
+//! fn register(registrar: &mut PluginRegistrar) -> Result<(), String> {
+//!     registrar.register_intrinsic("dup", 1, |vs| { ... });
+//!     registrar.define("{fn dup2 = dup dup}")
+//! }
+//!
+//! ucc::declare_plugin!(register);
+//! ```
+
+use crate::core::{Context, EvalError, Expr, ValueStack};
+use crate::parse::FnDefParser;
+
+/// Bumped whenever `PluginRegistrar`, or the `Context`, `Expr` and `Value`
+/// it passes plugins, changes incompatibly
+pub const PLUGIN_ABI_VERSION: u32 = 2;
+
+/// The features that add variants to `Expr` and `Value`, and so change
+/// their layout, one bit each: `bignum`, then `rational`
+pub const PLUGIN_FEATURES: u32 =
+    cfg!(feature = "bignum") as u32 | (cfg!(feature = "rational") as u32) << 1;
+
+/// The type of the `UCC_PLUGIN_REGISTER` static plugins export
+pub type PluginRegisterFn = fn(&mut PluginRegistrar) -> Result<(), String>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginError {
+    Unsupported,
+    /// The interpreter doesn't allow plugins, as when serving untrusted
+    /// clients
+    Disabled,
+    Load(String),
+    AbiMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// Built with other features, as `PLUGIN_FEATURES` sets bits for
+    FeatureMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    Register(String),
+}
+
+/// What a plugin may change while it is being loaded
+pub struct PluginRegistrar<'a> {
+    ctx: &'a mut Context,
+    defined: Vec<String>,
+}
+
+impl<'a> PluginRegistrar<'a> {
+    pub(crate) fn new(ctx: &'a mut Context) -> Self {
+        Self {
+            ctx,
+            defined: vec![],
+        }
+    }
+
+    pub fn register_intrinsic<F>(&mut self, name: &str, arity: usize, f: F)
+    where
+        F: Fn(&mut ValueStack) -> Result<Expr, EvalError> + 'static,
+    {
+        self.ctx.register_intrinsic(name, arity, f);
+        self.defined.push(name.to_owned());
+    }
+
+    /// Define a function from its source, e.g. `{fn dup2 = dup dup}`.
+    pub fn define(&mut self, src: &str) -> Result<(), String> {
+        let fn_def = FnDefParser::new()
+            .parse(&mut self.ctx.interner, src)
+            .map_err(|err| format!("{:?}", err))?;
+        self.defined
+            .push(self.ctx.interner.resolve(&(fn_def.0).0).to_owned());
+        self.ctx
+            .define_fn(fn_def)
+            .map_err(|err| format!("{:?}", err))?;
+        Ok(())
+    }
+
+    /// The names of the intrinsics and functions registered so far
+    pub fn defined(&self) -> &[String] {
+        &self.defined
+    }
+}
+
+/// Export `$register`, a `PluginRegisterFn`, and the ABI version and
+/// features it was built against.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        pub static UCC_PLUGIN_ABI_VERSION: u32 = $crate::plugin::PLUGIN_ABI_VERSION;
+
+        #[no_mangle]
+        pub static UCC_PLUGIN_FEATURES: u32 = $crate::plugin::PLUGIN_FEATURES;
+
+        #[no_mangle]
+        pub static UCC_PLUGIN_REGISTER: $crate::plugin::PluginRegisterFn = $register;
+    };
+}
+
+/// A loaded plugin library, which must outlive the intrinsics it registered
+#[cfg(feature = "plugins")]
+pub(crate) struct Plugin(#[allow(dead_code)] libloading::Library);
+
+#[cfg(not(feature = "plugins"))]
+pub(crate) enum Plugin {}
+
+impl Context {
+    /// Load the plugin at `path`, returning it and the names it defined. If
+    /// its registration function fails, the error comes with the plugin,
+    /// which ran code that may still be referred to, so must be kept.
+    #[cfg(feature = "plugins")]
+    pub(crate) fn load_plugin(
+        &mut self,
+        path: &str,
+    ) -> Result<(Plugin, Vec<String>), (PluginError, Option<Plugin>)> {
+        let load_err = |err: libloading::Error| (PluginError::Load(err.to_string()), None);
+        // Safety: loading a library runs its initializers, and the exported
+        // statics are trusted to have the declared types, as `declare_plugin`
+        // guarantees.
+        unsafe {
+            let lib = libloading::Library::new(path).map_err(load_err)?;
+            let version = **lib
+                .get::<*const u32>(b"UCC_PLUGIN_ABI_VERSION\0")
+                .map_err(load_err)?;
+            if version != PLUGIN_ABI_VERSION {
+                return Err((
+                    PluginError::AbiMismatch {
+                        expected: PLUGIN_ABI_VERSION,
+                        actual: version,
+                    },
+                    None,
+                ));
+            }
+            let features = **lib
+                .get::<*const u32>(b"UCC_PLUGIN_FEATURES\0")
+                .map_err(load_err)?;
+            if features != PLUGIN_FEATURES {
+                return Err((
+                    PluginError::FeatureMismatch {
+                        expected: PLUGIN_FEATURES,
+                        actual: features,
+                    },
+                    None,
+                ));
+            }
+            let register = **lib
+                .get::<*const PluginRegisterFn>(b"UCC_PLUGIN_REGISTER\0")
+                .map_err(load_err)?;
+            match self.register_plugin(register) {
+                Ok(defined) => Ok((Plugin(lib), defined)),
+                Err(err) => Err((err, Some(Plugin(lib)))),
+            }
+        }
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub(crate) fn load_plugin(
+        &mut self,
+        _path: &str,
+    ) -> Result<(Plugin, Vec<String>), (PluginError, Option<Plugin>)> {
+        Err((PluginError::Unsupported, None))
+    }
+
+    /// Run a plugin's registration function, returning the names it defined.
+    /// It registers into a fork, kept only if it succeeds, so a plugin that
+    /// fails partway leaves nothing behind.
+    pub fn register_plugin(
+        &mut self,
+        register: PluginRegisterFn,
+    ) -> Result<Vec<String>, PluginError> {
+        let mut fork = self.fork();
+        let mut registrar = PluginRegistrar::new(&mut fork);
+        register(&mut registrar).map_err(PluginError::Register)?;
+        let defined = registrar.defined;
+        fork.warnings.splice(0..0, self.warnings.drain(..));
+        *self = fork;
+        Ok(defined)
+    }
+}
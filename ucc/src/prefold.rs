@@ -0,0 +1,87 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Constant folding of definitions, as they are made, when prefolding is
+//! on. A body's closed prefix, which evaluates on an empty stack, is
+//! evaluated once then, and replaced by the values it leaves:
+//!
+//! ```text
+//! {fn f = [a] [b] compose swap}  ⟶  {fn f = [a b] swap}
+//! ```
+//!
+//! Prefixes are evaluated by the bytecode VM, so those that use rewrite
+//! rules, reflection, or host intrinsics, which may have effects, aren't
+//! folded, and only within `PREFOLD_FUEL` steps. A prefix is replaced only
+//! if its values are smaller, so `[a] clone` is kept as it is.
+
+use crate::core::{pushing, Context, Expr, Symbol, ValueStack};
+use crate::limits::body_size;
+use std::sync::Arc;
+
+/// The most steps a prefix may take to be folded
+pub const PREFOLD_FUEL: usize = 1000;
+
+impl Context {
+    /// Fold the closed prefixes of definitions made from now on, or stop.
+    pub fn set_prefold(&mut self, prefold: bool) {
+        self.prefold = prefold;
+    }
+
+    /// `e` with the prefix of its items that folds the most replaced by the
+    /// values it leaves on an empty stack, or as it is if none gets smaller
+    pub fn prefold(&mut self, e: &Expr) -> Expr {
+        let items = match e {
+            Expr::Compose(es) => &es[..],
+            e => std::slice::from_ref(e),
+        };
+        // The folded items, and how many nodes they save
+        let mut best: Option<(Vec<Expr>, usize)> = None;
+        let mut prefix_nodes = 0;
+        for len in 1..=items.len() {
+            prefix_nodes += body_size(&items[len - 1]);
+            let prefix = Expr::Compose(items[..len].to_vec());
+            let run = self.run_compiled(&ValueStack::default(), &prefix, PREFOLD_FUEL);
+            // Longer prefixes evaluate this one first, so fail too
+            if run.result.is_err() {
+                break;
+            }
+            let mut folded: Vec<Expr> = run.vs.0.into_iter().map(pushing).collect();
+            let folded_nodes = folded.iter().map(body_size).sum::<usize>();
+            if folded_nodes >= prefix_nodes {
+                continue;
+            }
+            let saved = prefix_nodes - folded_nodes;
+            // The longest of those that save the most
+            if !matches!(best, Some((_, most)) if saved < most) {
+                folded.extend_from_slice(&items[len..]);
+                best = Some((folded, saved));
+            }
+        }
+        match best {
+            None => e.clone(),
+            Some((mut es, _)) if es.len() == 1 => es.pop().unwrap(),
+            Some((es, _)) => Expr::Compose(es),
+        }
+    }
+
+    /// Fold the body of `sym`, just defined, so that its prefix is
+    /// evaluated as it will be, with `sym` defined as written.
+    pub(crate) fn prefold_fn(&mut self, sym: Symbol) {
+        let body = match self.fns.get(&sym) {
+            Some(body) => body.clone(),
+            None => return,
+        };
+        let folded = self.prefold(&body);
+        if folded != body {
+            Arc::make_mut(&mut self.fns).insert(sym, folded.clone());
+            let exprs = Arc::make_mut(&mut self.exprs);
+            if exprs.get(&body) == Some(&sym) {
+                exprs.remove(&body);
+            }
+            exprs.insert(folded, sym);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Where the values on the stack came from, for `:why`. While the
+//! `provenance` setting is on, each step that makes values records the
+//! expression that made them, the function expansions in progress, and the
+//! values it used, so a value's derivation can be followed back to the
+//! literals and calls that pushed its parts.
+
+use crate::core::{Context, Expr, Interner, Intrinsic, Symbol, Value, ValueStack};
+use crate::display::{Resolve, ResolvedExpansionTrace};
+use std::rc::Rc;
+
+/// The most lines of derivation `:why` shows
+const MAX_WHY_LINES: usize = 20;
+
+/// How a value was made
+pub(crate) struct Origin {
+    value: Value,
+    /// The step of its evaluation that made the value, counting from 1
+    step: usize,
+    /// The expression evaluated by that step, or `None` if the value was on
+    /// the stack before tracking started
+    by: Option<Expr>,
+    /// The rewrite rules applied just before that step
+    rewrites: usize,
+    /// The function expansions in progress, outermost first
+    calls: Vec<Symbol>,
+    /// The values it was made from
+    inputs: Vec<Rc<Origin>>,
+}
+
+/// The origins of the values on a stack
+#[derive(Default)]
+pub(crate) struct Provenance {
+    origins: Vec<Rc<Origin>>,
+    steps: usize,
+    rewrites: usize,
+}
+
+impl Provenance {
+    /// Start counting the steps of a new evaluation
+    pub(crate) fn start(&mut self) {
+        self.steps = 0;
+        self.rewrites = 0;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.origins.clear();
+        self.start();
+    }
+
+    /// Match the origins to `vs`, forgetting those of values no longer on
+    /// it, as after an error, and marking any new values as untracked
+    pub(crate) fn sync(&mut self, vs: &ValueStack) {
+        self.origins.truncate(vs.len());
+        for v in vs.0.iter().skip(self.origins.len()) {
+            self.origins.push(Rc::new(Origin {
+                value: v.clone(),
+                step: 0,
+                by: None,
+                rewrites: 0,
+                calls: vec![],
+                inputs: vec![],
+            }));
+        }
+    }
+
+    /// Record the effect of a small step of `head`, with `calls` in
+    /// progress, that took the synced stack to `vs`.
+    pub(crate) fn record(
+        &mut self,
+        ctx: &mut Context,
+        head: Expr,
+        calls: Vec<Symbol>,
+        vs: &ValueStack,
+    ) {
+        self.steps += 1;
+        if !ctx.rules.is_empty() && ctx.consecutive_rewrites > 0 {
+            self.rewrites += 1;
+            return;
+        }
+        let len = self.origins.len();
+        let consumed = match &head {
+            Expr::Intrinsic(Intrinsic::Swap) => {
+                let origin = self.origins.remove(len - 2);
+                self.origins.push(origin);
+                None
+            }
+            Expr::Intrinsic(Intrinsic::Clone) | Expr::Intrinsic(Intrinsic::Dup) => {
+                self.origins.push(self.origins[len - 1].clone());
+                None
+            }
+            Expr::Intrinsic(Intrinsic::Over) => {
+                self.origins.push(self.origins[len - 2].clone());
+                None
+            }
+            Expr::Intrinsic(Intrinsic::Rot) => {
+                let origin = self.origins.remove(len - 3);
+                self.origins.push(origin);
+                None
+            }
+            Expr::Intrinsic(Intrinsic::Drop)
+            | Expr::Intrinsic(Intrinsic::Quote)
+            | Expr::Intrinsic(Intrinsic::Apply)
+            | Expr::Intrinsic(Intrinsic::Defined)
+            | Expr::Intrinsic(Intrinsic::BodyOf) => Some(1),
+            Expr::Intrinsic(Intrinsic::Compose)
+            | Expr::Intrinsic(Intrinsic::Define)
+            | Expr::Intrinsic(Intrinsic::EvalQ)
+            | Expr::Intrinsic(Intrinsic::Cake)
+            | Expr::Intrinsic(Intrinsic::K) => Some(2),
+            Expr::Intrinsic(Intrinsic::Host(sym)) => Some(host_arity(ctx, sym)),
+            Expr::Call(sym) if ctx.lookup_fn(sym).is_none() => Some(host_arity(ctx, sym)),
+            _ => Some(0),
+        };
+        if let Some(consumed) = consumed {
+            let inputs = self.origins.split_off(len - consumed);
+            for v in vs.0.iter().skip(self.origins.len()) {
+                let mut value = ValueStack::from(vec![v.clone()]);
+                ctx.compress(&mut value);
+                self.origins.push(Rc::new(Origin {
+                    value: value.0.pop().unwrap(),
+                    step: self.steps,
+                    by: Some(head.clone()),
+                    rewrites: self.rewrites,
+                    calls: calls.clone(),
+                    inputs: inputs.clone(),
+                }));
+            }
+        }
+        self.rewrites = 0;
+    }
+
+    /// Explain how the value at `index` of the synced stack was made, one
+    /// line per value in its derivation.
+    pub(crate) fn why(&self, index: usize, interner: &Interner) -> String {
+        let mut lines = vec![];
+        explain(&self.origins[index], 0, interner, &mut lines);
+        if lines.len() > MAX_WHY_LINES {
+            lines.truncate(MAX_WHY_LINES);
+            lines.push("...".to_owned());
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+fn host_arity(ctx: &Context, sym: &Symbol) -> usize {
+    ctx.host_intrinsics.get(sym).map_or(0, |host| host.arity)
+}
+
+fn explain(origin: &Origin, depth: usize, interner: &Interner, lines: &mut Vec<String>) {
+    if lines.len() > MAX_WHY_LINES {
+        return;
+    }
+    let indent = "  ".repeat(depth);
+    let value = origin.value.resolve(interner);
+    let mut line = match &origin.by {
+        None => format!("{}`{}`: from before provenance was on", indent, value),
+        Some(Expr::Symbol(_)) | Some(Expr::Quote(_)) => {
+            format!("{}`{}`: pushed at step {}", indent, value, origin.step)
+        }
+        Some(by) => format!(
+            "{}`{}`: made by `{}` at step {}",
+            indent,
+            value,
+            by.resolve(interner),
+            origin.step
+        ),
+    };
+    if origin.rewrites == 1 {
+        line.push_str(", after a rewrite");
+    } else if origin.rewrites > 1 {
+        line.push_str(&format!(", after {} rewrites", origin.rewrites));
+    }
+    if !origin.calls.is_empty() {
+        let calls = ResolvedExpansionTrace(
+            origin
+                .calls
+                .iter()
+                .map(|sym| sym.resolve(interner))
+                .collect(),
+        );
+        line.push_str(&format!(", {}", calls));
+    }
+    if !origin.inputs.is_empty() {
+        line.push_str(", from");
+    }
+    lines.push(line);
+    for input in origin.inputs.iter() {
+        explain(input, depth + 1, interner, lines);
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! What each session of `ucci serve` may use, so no client can take more
+//! than its share of the host, read from a file given by `--config`, e.g.
+//!
+//! ```text
+//! isolation = "private"
+//! max-sessions = 100
+//! session-idle-seconds = 600
+//! steps-per-second = 100000
+//! max-definitions = 500
+//! max-term-size = 1000
+//! max-name-length = 64
+//! ```
+//!
+//! Every key is optional, and those left out are unlimited, but for
+//! `isolation`, which is `private` unless set.
+
+use crate::limits::DefinitionLimits;
+use crate::settings::SettingError;
+use std::fs;
+use std::path::Path;
+
+/// What sessions share with each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Isolation {
+    /// Sessions share the prelude, and the interner it is in, so each
+    /// costs little, but the symbols one interns take memory for all, and
+    /// aren't freed when it ends
+    Shared,
+    /// Each session has its own copy of the prelude, and its own interner,
+    /// freed when it ends
+    #[default]
+    Private,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quotas {
+    pub isolation: Isolation,
+    /// The most sessions at once, and the most playgrounds connected at
+    /// once, where `None` is unlimited
+    pub max_sessions: Option<usize>,
+    /// How long a session may go unused before it ends, making room for
+    /// others, where `None` is forever
+    pub session_idle_seconds: Option<usize>,
+    /// The most small steps each session takes in a second, on average,
+    /// where `None` is unlimited. A session may take a second's worth at
+    /// once.
+    pub steps_per_second: Option<usize>,
+    /// The limits on each session's definitions: `max-definitions` sets
+    /// `fns`, `max-term-size` sets `body_size`, which also limits the
+    /// values evaluation makes, and `max-name-length` sets `name_len`
+    pub limits: DefinitionLimits,
+}
+
+impl Quotas {
+    pub fn parse(src: &str) -> Result<Quotas, SettingError> {
+        let table = match src.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err(SettingError::BadSettingsFile("expected a table".to_owned())),
+            Err(err) => return Err(SettingError::BadSettingsFile(err.to_string())),
+        };
+        let mut quotas = Quotas::default();
+        for (key, value) in table {
+            let invalid = || SettingError::InvalidValue {
+                name: key.clone(),
+                value: value.to_string(),
+            };
+            if key == "isolation" {
+                quotas.isolation = match value.as_str() {
+                    Some("shared") => Isolation::Shared,
+                    Some("private") => Isolation::Private,
+                    _ => return Err(invalid()),
+                };
+                continue;
+            }
+            let quota = match value.as_integer() {
+                Some(n) if n >= 0 => Some(n as usize),
+                _ => return Err(invalid()),
+            };
+            match key.as_str() {
+                "max-sessions" => quotas.max_sessions = quota,
+                "session-idle-seconds" => quotas.session_idle_seconds = quota,
+                "steps-per-second" => quotas.steps_per_second = quota,
+                "max-definitions" => quotas.limits.fns = quota,
+                "max-term-size" => quotas.limits.body_size = quota,
+                "max-name-length" => quotas.limits.name_len = quota,
+                _ => return Err(SettingError::Unknown(key)),
+            }
+        }
+        Ok(quotas)
+    }
+
+    /// Read the quotas in the file at `path`.
+    pub fn load(path: &Path) -> Result<Quotas, SettingError> {
+        match fs::read_to_string(path) {
+            Ok(src) => Quotas::parse(&src),
+            Err(err) => Err(SettingError::Io(err.to_string())),
+        }
+    }
+}
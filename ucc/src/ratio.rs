@@ -0,0 +1,104 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Exact rational numbers, with the `rational` feature, which implies
+//! `bignum`. A literal such as `3/4` or `-1/3` pushes itself, always shown
+//! in lowest terms with its denominator, and every context has the
+//! intrinsics
+//!
+//! - `ratio.add`, `ratio.sub`, `ratio.mul` and `ratio.div`, where
+//!   `⟨1/2 1/3⟩ ratio.add ⟶ ⟨5/6⟩`,
+//! - `ratio.eq` and `ratio.lt`, pushing `true` or `false`, as `nat.eq` does.
+//!
+//! Each takes numbers as well as ratios, `⟨1 1/2⟩ ratio.sub ⟶ ⟨1/2⟩`, and
+//! pushes a ratio. Without the feature, a literal doesn't parse.
+
+#[cfg(feature = "rational")]
+use crate::core::{boolean, Context, EvalError, ValueStack};
+use crate::core::{Expr, Value};
+#[cfg(feature = "rational")]
+use num_bigint::BigInt;
+#[cfg(feature = "rational")]
+use num_rational::BigRational;
+#[cfg(feature = "rational")]
+use std::fmt;
+
+/// The ratio `n/d`, or why it isn't one
+#[cfg(feature = "rational")]
+fn parse_ratio(literal: &str) -> Result<BigRational, &'static str> {
+    let (numer, denom) = literal.split_at(literal.find('/').unwrap());
+    let numer: BigInt = numer.parse().unwrap();
+    let denom: BigInt = denom[1..].parse().unwrap();
+    if denom == BigInt::default() {
+        return Err("a ratio's denominator can't be 0");
+    }
+    Ok(BigRational::new(numer, denom))
+}
+
+/// The literal `n/d`, for the parser
+#[cfg_attr(not(feature = "rational"), allow(unused_variables))]
+pub(crate) fn ratio_literal(literal: &str) -> Result<Expr, &'static str> {
+    #[cfg(feature = "rational")]
+    return parse_ratio(literal).map(Expr::Ratio);
+    #[cfg(not(feature = "rational"))]
+    return Err("ratio literals need the `rational` feature");
+}
+
+/// The literal `n/d` as a value, for the parser
+#[cfg_attr(not(feature = "rational"), allow(unused_variables))]
+pub(crate) fn ratio_value(literal: &str) -> Result<Value, &'static str> {
+    #[cfg(feature = "rational")]
+    return parse_ratio(literal).map(Value::Ratio);
+    #[cfg(not(feature = "rational"))]
+    return Err("ratio literals need the `rational` feature");
+}
+
+/// Write `r` as a literal, `n/d`, even when `d` is 1, so it reads back as
+/// a ratio rather than a number
+#[cfg(feature = "rational")]
+pub(crate) fn fmt_ratio(r: &BigRational, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}/{}", r.numer(), r.denom())
+}
+
+/// Pop a ratio or a number, or fail
+#[cfg(feature = "rational")]
+fn pop_ratio(vs: &mut ValueStack) -> Result<BigRational, EvalError> {
+    match vs.pop() {
+        Some(Value::Ratio(r)) => Ok(r),
+        Some(Value::Nat(n)) => Ok(BigRational::from_integer(n.into())),
+        _ => Err(EvalError::Host("expected a ratio".to_owned())),
+    }
+}
+
+#[cfg(feature = "rational")]
+impl Context {
+    /// Register the `ratio.` intrinsics, as every context does
+    pub(crate) fn register_ratio_intrinsics(&mut self) {
+        fn binary<F>(ctx: &mut Context, name: &str, f: F)
+        where
+            F: Fn(BigRational, BigRational) -> Result<Value, EvalError> + 'static,
+        {
+            ctx.register_intrinsic(name, 2, move |vs| {
+                let b = pop_ratio(vs)?;
+                let a = pop_ratio(vs)?;
+                vs.push(f(a, b)?);
+                Ok(Expr::default())
+            });
+        }
+        binary(self, "ratio.add", |a, b| Ok(Value::Ratio(a + b)));
+        binary(self, "ratio.sub", |a, b| Ok(Value::Ratio(a - b)));
+        binary(self, "ratio.mul", |a, b| Ok(Value::Ratio(a * b)));
+        binary(self, "ratio.div", |a, b| {
+            if b == BigRational::default() {
+                Err(EvalError::Host("division by zero".to_owned()))
+            } else {
+                Ok(Value::Ratio(a / b))
+            }
+        });
+        binary(self, "ratio.eq", |a, b| Ok(boolean(a == b)));
+        binary(self, "ratio.lt", |a, b| Ok(boolean(a < b)));
+    }
+}
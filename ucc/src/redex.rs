@@ -0,0 +1,180 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The reduction graph: every step that could be taken from a state, not
+//! only the leftmost one evaluation takes, for exploring other orders of
+//! evaluation. Besides the step at the head of the expression, which has
+//! the value stack to work on, a redex is
+//!
+//! - a call to a defined function, anywhere, expanded to its body,
+//! - an intrinsic with the values it takes pushed right before it, in the
+//!   same composition, e.g. the `swap` in `a [b] [c] swap`,
+//! - a match of a rewrite rule's left-hand side, starting at any item.
+//!
+//! Quotations are searched too. Steps with effects, by `define!` or host
+//! intrinsics, are left out.
+
+use crate::core::{pushing, Context, Expr, Intrinsic, Value, ValueStack};
+use crate::pair::pair_value;
+use crate::rules::RuleId;
+
+/// The items of `e`, with nested compositions flattened
+fn items(e: &Expr) -> Vec<Expr> {
+    let mut items = vec![];
+    push_items(&mut items, e);
+    items
+}
+
+fn push_items(items: &mut Vec<Expr>, e: &Expr) {
+    match e {
+        Expr::Compose(es) => {
+            for e in es {
+                push_items(items, e);
+            }
+        }
+        e => items.push(e.clone()),
+    }
+}
+
+fn compose_items(mut items: Vec<Expr>) -> Expr {
+    if items.len() == 1 {
+        items.pop().unwrap()
+    } else {
+        Expr::Compose(items)
+    }
+}
+
+/// `items` with those in `range` replaced by the items of `by`
+fn splice(items: &[Expr], range: std::ops::Range<usize>, by: &Expr) -> Expr {
+    let mut new = items[..range.start].to_vec();
+    push_items(&mut new, by);
+    new.extend_from_slice(&items[range.end..]);
+    compose_items(new)
+}
+
+/// The value an item pushes, if it only pushes one
+fn pushed(e: &Expr) -> Option<Value> {
+    match e {
+        Expr::Quote(e) => Some(Value::Quote(e.clone())),
+        Expr::Symbol(sym) => Some(Value::Symbol(*sym)),
+        #[cfg(feature = "bignum")]
+        Expr::Nat(n) => Some(Value::Nat(n.clone())),
+        #[cfg(feature = "rational")]
+        Expr::Ratio(r) => Some(Value::Ratio(r.clone())),
+        Expr::Pair(p) => Some(pair_value(p)),
+        Expr::Map(m) => Some(Value::Map(m.clone())),
+        Expr::Char(c) => Some(Value::Char(*c)),
+        Expr::Str(s) => Some(Value::Str(s.clone())),
+        _ => None,
+    }
+}
+
+impl Context {
+    /// Every step that could be taken from `⟨vs⟩ e`, each with the state it
+    /// leads to and the rule it takes, those at the head of `e` first, then
+    /// from left to right
+    pub fn all_steps(&mut self, vs: &ValueStack, e: &Expr) -> Vec<(ValueStack, Expr, RuleId)> {
+        let mut steps = vec![];
+        let es = items(e);
+        let head = match es.first() {
+            Some(head) => head,
+            None => return steps,
+        };
+        for (i, rule) in self.rules.iter().enumerate() {
+            if let Some(new_e) = rule.apply(e) {
+                steps.push((vs.clone(), new_e, RuleId::Rewrite(i)));
+            }
+        }
+        match head {
+            Expr::Intrinsic(Intrinsic::Define) | Expr::Intrinsic(Intrinsic::Host(_)) => {}
+            Expr::Intrinsic(intr) => {
+                if let Some((vs, new_e)) = self.intrinsic_step(vs, *intr) {
+                    let new_e = splice(&es, 0..1, &new_e);
+                    steps.push((vs, new_e, RuleId::intrinsic(*intr)));
+                }
+            }
+            Expr::Call(sym) => match self.lookup_fn(sym) {
+                Some(Expr::Quote(_)) => {
+                    let mut vs = vs.clone();
+                    vs.0.push(Value::Call(*sym));
+                    steps.push((vs, compose_items(es[1..].to_vec()), RuleId::CallPush(*sym)));
+                }
+                Some(body) => {
+                    let new_e = splice(&es, 0..1, body);
+                    steps.push((vs.clone(), new_e, RuleId::CallExpand(*sym)));
+                }
+                None => {}
+            },
+            head => {
+                let rule = match head {
+                    Expr::Symbol(_) => RuleId::SymbolPush,
+                    Expr::Quote(_) => RuleId::QuotePush,
+                    _ => RuleId::LiteralPush,
+                };
+                let mut vs = vs.clone();
+                vs.0.extend(pushed(head));
+                steps.push((vs, compose_items(es[1..].to_vec()), rule));
+            }
+        }
+        for (new_e, rule) in self.inner_steps(&es, 1) {
+            steps.push((vs.clone(), new_e, rule));
+        }
+        steps
+    }
+
+    /// The steps that could be taken within `es`, without a value stack,
+    /// at its items from `from` on
+    fn inner_steps(&mut self, es: &[Expr], from: usize) -> Vec<(Expr, RuleId)> {
+        let mut steps = vec![];
+        for i in from..es.len() {
+            let rest = compose_items(es[i..].to_vec());
+            for (r, rule) in self.rules.iter().enumerate() {
+                if let Some(new_rest) = rule.apply(&rest) {
+                    steps.push((splice(es, i..es.len(), &new_rest), RuleId::Rewrite(r)));
+                }
+            }
+            match &es[i] {
+                Expr::Intrinsic(Intrinsic::Define) | Expr::Intrinsic(Intrinsic::Host(_)) => {}
+                Expr::Intrinsic(intr) => {
+                    let values = es[..i].iter().rev().take_while(|e| pushed(e).is_some());
+                    let start = i - values.count();
+                    let vs = ValueStack(es[start..i].iter().filter_map(pushed).collect());
+                    if let Some((vs, new_e)) = self.intrinsic_step(&vs, *intr) {
+                        let mut new = vs.0.into_iter().map(pushing).collect();
+                        push_items(&mut new, &new_e);
+                        let by = Expr::Compose(new);
+                        steps.push((splice(es, start..i + 1, &by), RuleId::intrinsic(*intr)));
+                    }
+                }
+                Expr::Call(sym) => {
+                    if let Some(body) = self.lookup_fn(sym) {
+                        let new_e = splice(es, i..i + 1, body);
+                        steps.push((new_e, RuleId::CallExpand(*sym)));
+                    }
+                }
+                Expr::Quote(e) => {
+                    for (new_e, rule) in self.inner_steps(&items(e), 0) {
+                        let by = Expr::Quote(Box::new(new_e));
+                        steps.push((splice(es, i..i + 1, &by), rule));
+                    }
+                }
+                _ => {}
+            }
+        }
+        steps
+    }
+
+    /// The state `intr` leaves on `vs`, or `None` if it fails on it
+    fn intrinsic_step(&mut self, vs: &ValueStack, intr: Intrinsic) -> Option<(ValueStack, Expr)> {
+        let mut vs = vs.clone();
+        let mut e = Expr::Intrinsic(intr);
+        // The rules are tried where the intrinsic is, not at it alone
+        let rules = std::mem::take(&mut self.rules);
+        let result = self.step(&mut vs, &mut e);
+        self.rules = rules;
+        result.ok().map(|()| (vs, e))
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Regular expressions, with the `regex` feature, for quick experiments
+//! with text in the REPL. Every context has the intrinsic
+//!
+//! - `regex.match`, `⟨"a(b)" "xab"⟩ regex.match ⟶ ⟨["ab" "b"] true⟩`, the
+//!   text of the first match and of each of its groups, as a list of
+//!   strings, and `true`, or `⟨[] false⟩` where the pattern doesn't match.
+//!   A group that took no part in the match is `""`.
+//!
+//! Patterns are written in the `regex` crate's syntax, and one that
+//! doesn't parse fails with the crate's error.
+
+use crate::character::pop_str;
+use crate::core::{boolean, list, Context, EvalError, Expr, Value};
+use regex::Regex;
+
+impl Context {
+    /// Register `regex.match`, as every context does with the feature
+    pub(crate) fn register_regex_intrinsics(&mut self) {
+        self.register_intrinsic("regex.match", 2, |vs| {
+            let subject = pop_str(vs)?;
+            let pattern = pop_str(vs)?;
+            let re = Regex::new(&pattern).map_err(|err| EvalError::Host(err.to_string()))?;
+            match re.captures(&subject) {
+                Some(captures) => {
+                    let groups = captures
+                        .iter()
+                        .map(|group| Value::Str(group.map_or("", |m| m.as_str()).to_owned()));
+                    vs.push(list(groups));
+                    vs.push(boolean(true));
+                }
+                None => {
+                    vs.push(list(vec![]));
+                    vs.push(boolean(false));
+                }
+            }
+            Ok(Expr::default())
+        });
+    }
+}
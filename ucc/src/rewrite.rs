@@ -0,0 +1,158 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::{Context, EvalError, Expr, Intrinsic, OrderedMap, Symbol};
+use std::sync::Arc;
+
+/// Maximum number of rewrites that may fire in a row without an ordinary
+/// small step in between, guarding against non-terminating rule sets.
+pub const MAX_CONSECUTIVE_REWRITES: usize = 1000;
+
+/// Expression patterns, as they appear in rewrite rules
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    Var(Symbol),
+    Intrinsic(Intrinsic),
+    Call(Symbol),
+    Symbol(Symbol),
+    Quote(Box<Pattern>),
+    Compose(Vec<Pattern>),
+}
+
+/// A rewrite rule, `{rule lhs => rhs}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule(pub Pattern, pub Pattern);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    EmptyLhs,
+    UnboundVar(Symbol),
+}
+
+/// The expressions bound to pattern variables, in the order they were bound
+pub(crate) type Bindings = OrderedMap<Symbol, Expr>;
+
+impl Pattern {
+    pub(crate) fn elements(&self) -> &[Pattern] {
+        match self {
+            Pattern::Compose(ps) => ps,
+            p => std::slice::from_ref(p),
+        }
+    }
+
+    fn vars(&self, vars: &mut Vec<Symbol>) {
+        match self {
+            Pattern::Var(sym) => vars.push(*sym),
+            Pattern::Intrinsic(_) | Pattern::Call(_) | Pattern::Symbol(_) => {}
+            Pattern::Quote(p) => p.vars(vars),
+            Pattern::Compose(ps) => {
+                for p in ps {
+                    p.vars(vars);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn match_expr(&self, e: &Expr, bindings: &mut Bindings) -> bool {
+        match (self, e) {
+            (Pattern::Var(sym), e) => {
+                if let Some(bound) = bindings.get(sym) {
+                    bound == e
+                } else {
+                    bindings.insert(*sym, e.clone());
+                    true
+                }
+            }
+            (Pattern::Intrinsic(pi), Expr::Intrinsic(ei)) => pi == ei,
+            (Pattern::Call(psym), Expr::Call(esym)) => psym == esym,
+            (Pattern::Symbol(psym), Expr::Symbol(esym)) => psym == esym,
+            (Pattern::Quote(p), Expr::Quote(e)) => p.match_expr(e, bindings),
+            (Pattern::Compose(ps), Expr::Compose(es)) => {
+                ps.len() == es.len() && ps.iter().zip(es).all(|(p, e)| p.match_expr(e, bindings))
+            }
+            _ => false,
+        }
+    }
+
+    fn instantiate(&self, bindings: &Bindings) -> Expr {
+        match self {
+            Pattern::Var(sym) => bindings[sym].clone(),
+            Pattern::Intrinsic(i) => Expr::Intrinsic(*i),
+            Pattern::Call(sym) => Expr::Call(*sym),
+            Pattern::Symbol(sym) => Expr::Symbol(*sym),
+            Pattern::Quote(p) => Expr::Quote(Box::new(p.instantiate(bindings))),
+            Pattern::Compose(ps) => {
+                Expr::Compose(ps.iter().map(|p| p.instantiate(bindings)).collect())
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// Rewrite the leftmost elements of `e` if they match this rule's
+    /// left-hand side.
+    pub(crate) fn apply(&self, e: &Expr) -> Option<Expr> {
+        let lhs = self.0.elements();
+        let es: &[Expr] = match e {
+            Expr::Compose(es) => es,
+            e => std::slice::from_ref(e),
+        };
+        if lhs.is_empty() || es.len() < lhs.len() {
+            return None;
+        }
+        let mut bindings = Bindings::default();
+        if !lhs
+            .iter()
+            .zip(es)
+            .all(|(p, e)| p.match_expr(e, &mut bindings))
+        {
+            return None;
+        }
+        let mut new_es = match self.1.instantiate(&bindings) {
+            Expr::Compose(rhs_es) => rhs_es,
+            rhs_e => vec![rhs_e],
+        };
+        new_es.extend(es[lhs.len()..].iter().cloned());
+        Some(if new_es.len() == 1 {
+            new_es.drain(..).next().unwrap()
+        } else {
+            Expr::Compose(new_es)
+        })
+    }
+}
+
+impl Context {
+    pub fn define_rule(&mut self, rule: Rule) -> Result<(), RuleError> {
+        if rule.0.elements().is_empty() {
+            return Err(RuleError::EmptyLhs);
+        }
+        let mut lhs_vars = vec![];
+        rule.0.vars(&mut lhs_vars);
+        let mut rhs_vars = vec![];
+        rule.1.vars(&mut rhs_vars);
+        if let Some(sym) = rhs_vars.into_iter().find(|sym| !lhs_vars.contains(sym)) {
+            return Err(RuleError::UnboundVar(sym));
+        }
+        Arc::make_mut(&mut self.rules).push(rule);
+        Ok(())
+    }
+
+    /// Apply the first matching rewrite rule to the head of `e`, if any.
+    pub(crate) fn rewrite(&mut self, e: &mut Expr) -> Result<bool, EvalError> {
+        if let Some(new_e) = self.rules.iter().find_map(|rule| rule.apply(e)) {
+            if self.consecutive_rewrites >= MAX_CONSECUTIVE_REWRITES {
+                self.consecutive_rewrites = 0;
+                return Err(EvalError::RewriteLimitExceeded);
+            }
+            *e = new_e;
+            self.consecutive_rewrites += 1;
+            Ok(true)
+        } else {
+            self.consecutive_rewrites = 0;
+            Ok(false)
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The rules of the small step semantics, so that traces, statistics, and
+//! visualizers can name the one each step takes rather than infer it from
+//! the shape of the expression, e.g.
+//!
+//! ```
+//! # use ucc::core::*;
+//! # use ucc::rules::RuleId;
+//! # use std::cell::RefCell;
+//! # use std::rc::Rc;
+//! let mut ctx = Context::default();
+//! let rules = Rc::new(RefCell::new(vec![]));
+//! let taken = Rc::clone(&rules);
+//! ctx.on_rule(move |rule, _vs, _e| taken.borrow_mut().push(rule));
+//! let mut vs = ValueStack::default();
+//! let mut e = ctx.parse_expr("[a] clone").unwrap();
+//! while e != Expr::default() {
+//!     ctx.small_step(&mut vs, &mut e).unwrap();
+//! }
+//! assert_eq!(*rules.borrow(), [RuleId::QuotePush, RuleId::Clone]);
+//! ```
+
+use crate::core::{Context, Expr, Intrinsic, Symbol};
+use crate::help::INTRINSICS;
+
+/// A rule of the small step semantics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleId {
+    Swap,
+    Clone,
+    Drop,
+    Quote,
+    Compose,
+    Apply,
+    Defined,
+    BodyOf,
+    Define,
+    EvalQ,
+    Dup,
+    Over,
+    Rot,
+    Cake,
+    K,
+    /// The host intrinsic with this name
+    Host(Symbol),
+    /// A call replaced by the body of the function it names
+    CallExpand(Symbol),
+    /// A call of a function defined as a quotation, pushing the call
+    CallPush(Symbol),
+    QuotePush,
+    SymbolPush,
+    /// A literal, such as a number, pushing itself
+    LiteralPush,
+    /// An empty composition at the head removed
+    ComposeFlatten,
+    /// The rewrite rule at this index, in the order they were made
+    Rewrite(usize),
+}
+
+impl RuleId {
+    /// The rule `intr` takes
+    pub fn intrinsic(intr: Intrinsic) -> RuleId {
+        match intr {
+            Intrinsic::Swap => RuleId::Swap,
+            Intrinsic::Clone => RuleId::Clone,
+            Intrinsic::Drop => RuleId::Drop,
+            Intrinsic::Quote => RuleId::Quote,
+            Intrinsic::Compose => RuleId::Compose,
+            Intrinsic::Apply => RuleId::Apply,
+            Intrinsic::Defined => RuleId::Defined,
+            Intrinsic::BodyOf => RuleId::BodyOf,
+            Intrinsic::Define => RuleId::Define,
+            Intrinsic::EvalQ => RuleId::EvalQ,
+            Intrinsic::Dup => RuleId::Dup,
+            Intrinsic::Over => RuleId::Over,
+            Intrinsic::Rot => RuleId::Rot,
+            Intrinsic::Cake => RuleId::Cake,
+            Intrinsic::K => RuleId::K,
+            Intrinsic::Host(sym) => RuleId::Host(sym),
+        }
+    }
+
+    /// The name of the rule: that of its intrinsic, if it has one
+    pub fn name(&self) -> &'static str {
+        match self {
+            RuleId::Swap => "swap",
+            RuleId::Clone => "clone",
+            RuleId::Drop => "drop",
+            RuleId::Quote => "quote",
+            RuleId::Compose => "compose",
+            RuleId::Apply => "apply",
+            RuleId::Defined => "defined?",
+            RuleId::BodyOf => "body-of",
+            RuleId::Define => "define!",
+            RuleId::EvalQ => "evalq",
+            RuleId::Dup => "dup",
+            RuleId::Over => "over",
+            RuleId::Rot => "rot",
+            RuleId::Cake => "cake",
+            RuleId::K => "k",
+            RuleId::Host(_) => "host",
+            RuleId::CallExpand(_) => "call-expand",
+            RuleId::CallPush(_) => "call-push",
+            RuleId::QuotePush => "quote-push",
+            RuleId::SymbolPush => "symbol-push",
+            RuleId::LiteralPush => "literal-push",
+            RuleId::ComposeFlatten => "compose-flatten",
+            RuleId::Rewrite(_) => "rewrite",
+        }
+    }
+
+    /// The rule, in the notation of the evaluation output
+    pub fn formal(&self) -> &'static str {
+        match self {
+            RuleId::Dup => "⟨v⟩ dup ⟶ ⟨v v⟩",
+            RuleId::Over => "⟨v1 v2⟩ over ⟶ ⟨v1 v2 v1⟩",
+            RuleId::Rot => "⟨v1 v2 v3⟩ rot ⟶ ⟨v2 v3 v1⟩",
+            RuleId::Cake => "⟨[e1] [e2]⟩ cake ⟶ ⟨[[e1] e2] [e2 [e1]]⟩",
+            RuleId::K => "⟨[e1] [e2]⟩ k ⟶ ⟨⟩ e2",
+            RuleId::Host(_) => "as the host defines it",
+            RuleId::CallExpand(_) => "⟨⟩ f ⟶ ⟨⟩ e, given {fn f = e}",
+            RuleId::CallPush(_) => "⟨⟩ f ⟶ ⟨f⟩, given {fn f = [e]}",
+            RuleId::QuotePush => "⟨⟩ [e] ⟶ ⟨[e]⟩",
+            RuleId::SymbolPush => "⟨⟩ 's ⟶ ⟨'s⟩",
+            RuleId::LiteralPush => "⟨⟩ l ⟶ ⟨l⟩, for a literal l",
+            RuleId::ComposeFlatten => "⟨⟩ () e ⟶ ⟨⟩ e",
+            RuleId::Rewrite(_) => "⟨⟩ p1 e ⟶ ⟨⟩ p2 e, given {rule p1 => p2}",
+            rule => {
+                let name = rule.name();
+                let intrinsic = INTRINSICS.iter().find(|intr| intr.name == name);
+                intrinsic.expect("help on each core intrinsic").rule
+            }
+        }
+    }
+}
+
+impl Context {
+    /// The rule the next small step on `e` takes, or `None` if `e` is
+    /// empty or the step can only fail
+    pub fn next_rule(&self, e: &Expr) -> Option<RuleId> {
+        if let Some(i) = self.rules.iter().position(|rule| rule.apply(e).is_some()) {
+            return Some(RuleId::Rewrite(i));
+        }
+        match e.head() {
+            Expr::Intrinsic(intr) => Some(RuleId::intrinsic(*intr)),
+            Expr::Call(sym) => match self.lookup_fn(sym) {
+                Some(Expr::Quote(_)) => Some(RuleId::CallPush(*sym)),
+                Some(_) => Some(RuleId::CallExpand(*sym)),
+                None if self.host_intrinsic(sym).is_some() => Some(RuleId::Host(*sym)),
+                None => None,
+            },
+            Expr::Symbol(_) => Some(RuleId::SymbolPush),
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => Some(RuleId::LiteralPush),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => Some(RuleId::LiteralPush),
+            Expr::Pair(_) | Expr::Map(_) | Expr::Char(_) | Expr::Str(_) => {
+                Some(RuleId::LiteralPush)
+            }
+            Expr::Quote(_) => Some(RuleId::QuotePush),
+            Expr::Compose(_) if *e == Expr::default() => None,
+            Expr::Compose(_) => Some(RuleId::ComposeFlatten),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Bounded program synthesis: find the compositions of intrinsics and
+//! defined functions that fill the hole in a big step assertion,
+//! `⟨input⟩ ? ⇓ ⟨output⟩`, trying the smallest first.
+
+use crate::core::{Context, Expr, Intrinsic, ValueStack};
+
+/// Bounds on a search, so it always finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// The most intrinsics and calls in a composition
+    pub size: usize,
+    /// The most small steps evaluating each composition may take
+    pub steps: usize,
+    /// The most solutions to find
+    pub results: usize,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            size: 3,
+            steps: 1000,
+            results: 10,
+        }
+    }
+}
+
+/// The intrinsics tried, leaving out `define!`, which has side effects
+const INTRINSICS: [Intrinsic; 8] = [
+    Intrinsic::Swap,
+    Intrinsic::Clone,
+    Intrinsic::Drop,
+    Intrinsic::Quote,
+    Intrinsic::Compose,
+    Intrinsic::Apply,
+    Intrinsic::Defined,
+    Intrinsic::BodyOf,
+];
+
+struct Search<'a> {
+    atoms: &'a [Expr],
+    expected: &'a ValueStack,
+    limits: SearchLimits,
+    prefix: Vec<Expr>,
+    found: Vec<Expr>,
+}
+
+impl Context {
+    /// Find the smallest compositions of intrinsics and defined functions,
+    /// up to `limits.size` long, that evaluate `input` to `output`, in the
+    /// order of the intrinsics and then of the definitions. Larger
+    /// compositions aren't tried once some are found, as they are mostly
+    /// the same padded with no-ops like `swap swap`.
+    pub fn search(
+        &mut self,
+        input: &ValueStack,
+        output: &ValueStack,
+        limits: SearchLimits,
+    ) -> Vec<Expr> {
+        let mut atoms: Vec<Expr> = INTRINSICS.iter().copied().map(Expr::Intrinsic).collect();
+        atoms.extend(self.fn_defs().map(|(sym, _)| Expr::Call(*sym)));
+        let mut expected = output.clone();
+        self.compress(&mut expected);
+        let mut search = Search {
+            atoms: &atoms,
+            expected: &expected,
+            limits,
+            prefix: vec![],
+            found: vec![],
+        };
+        // Functions calling `define!` mustn't change the definitions
+        let allow_define = self.allow_define;
+        self.allow_define = false;
+        for size in 1..=limits.size {
+            self.search_from(&mut search, input, limits.steps, size);
+            if !search.found.is_empty() {
+                break;
+            }
+        }
+        self.allow_define = allow_define;
+        search.found
+    }
+
+    /// Extend `search.prefix`, which evaluated to `vs` leaving `steps`, by
+    /// `size` more atoms.
+    fn search_from(&mut self, search: &mut Search, vs: &ValueStack, steps: usize, size: usize) {
+        if search.found.len() >= search.limits.results {
+            return;
+        }
+        if size == 0 {
+            let mut vs = vs.clone();
+            self.compress(&mut vs);
+            if vs == *search.expected {
+                search.found.push(match &search.prefix[..] {
+                    [e] => e.clone(),
+                    es => Expr::Compose(es.to_vec()),
+                });
+            }
+            return;
+        }
+        for atom in search.atoms {
+            let mut vs = vs.clone();
+            let mut e = atom.clone();
+            let mut steps = steps;
+            while e != Expr::default() && steps > 0 && self.small_step(&mut vs, &mut e).is_ok() {
+                steps -= 1;
+            }
+            // A prefix that fails fails in every extension too
+            if e == Expr::default() {
+                search.prefix.push(atom.clone());
+                self.search_from(search, &vs, steps, size - 1);
+                search.prefix.pop();
+            }
+        }
+    }
+}
@@ -0,0 +1,790 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A server for `ucci serve`, running a session, with its own interpreter,
+//! for each client. It speaks just enough HTTP/1.1, a request for each
+//! connection, besides WebSockets:
+//!
+//! - `POST /sessions/<id>` runs the body as input in the session `<id>`,
+//!   starting it if there isn't one, for up to `EVAL_BUDGET`, and responds
+//!   with the output. Jobs that run longer go on during later requests.
+//! - `DELETE /sessions/<id>` ends the session.
+//! - `GET /metrics` responds with the metrics of every session, in the
+//!   Prometheus text format.
+//! - `GET /sessions/<id>/ws` upgrades to a WebSocket speaking the
+//!   playground protocol (see `playground`), which streams each step
+//!   of the session as it is taken.
+//!
+//! Sessions made by `Server::with_quotas` can't read or write files, load
+//! plugins, or fetch URLs, and are held to its `Quotas`. A session left
+//! unused for longer than they allow ends when another is asked for.
+//! One with a playground connected is in use all the while.
+
+use crate::builder::{Clock, Files, InterpBuilder, Prelude, SystemClock};
+use crate::capabilities::capabilities;
+use crate::core::OrderedMap;
+use crate::interp::{Interp, InterpStats};
+use crate::metrics::Metrics;
+use crate::playground::{ClientMessage, ServerMessage};
+use crate::quota::{Isolation, Quotas};
+use crate::shared::SharedPrelude;
+use crate::websocket::{accept_key, encode_frame, parse_frame, Frame};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a request may run its session's jobs
+pub const EVAL_BUDGET: Duration = Duration::from_millis(100);
+
+/// How long each poll of a playground may run its session's jobs, so
+/// others, and new connections, get their turn
+pub const POLL_BUDGET: Duration = Duration::from_millis(10);
+
+/// How long to wait between polls when there is nothing to do
+const IDLE_WAIT: Duration = Duration::from_millis(5);
+
+/// How long a client has to send its whole request, however it spaces
+/// it out, as every session waits meanwhile
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for a client to take a response, as every session
+/// waits too. Playgrounds queue what they send instead.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many bytes may wait for a playground's client to take them before
+/// its messages go unread, and its session's jobs unstepped for it, until
+/// it catches up
+const MAX_QUEUED: usize = 64 << 10;
+
+/// The longest request body read
+const MAX_BODY: usize = 1 << 20;
+
+/// The longest request line, or header line, read
+const MAX_LINE: usize = 8 << 10;
+
+/// The most headers read
+const MAX_HEADERS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The value of the first header named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn text(status: u16, body: String) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body,
+        }
+    }
+}
+
+/// Refuses every read and write
+struct NoFiles;
+
+impl Files for NoFiles {
+    fn read(&self, _: &Path) -> io::Result<Vec<u8>> {
+        Err(io::ErrorKind::PermissionDenied.into())
+    }
+
+    fn write(&self, _: &Path, _: &[u8]) -> io::Result<()> {
+        Err(io::ErrorKind::PermissionDenied.into())
+    }
+}
+
+struct Session {
+    interp: Interp,
+    /// How many small steps the session may take before it must wait
+    allowance: usize,
+    /// When the allowance was last topped up, on the server's clock
+    refilled: Duration,
+    /// When the session was last used, on the server's clock
+    used: Duration,
+}
+
+/// A playground client's WebSocket connection to a session, served by
+/// `Server::poll`. A client slow to read what it is sent holds back only
+/// its own session, never loses what it was sent, and is never dropped for
+/// it.
+pub struct Playground {
+    id: String,
+    stream: TcpStream,
+    /// Bytes read but not yet parsed into frames
+    buf: Vec<u8>,
+    /// Frames written but not yet taken by the client
+    queued: Vec<u8>,
+    /// Whether the session's jobs are stepped for this client
+    running: bool,
+    paused: bool,
+    /// Whether the session last ran out of its allowance of steps, so
+    /// the client is told only once
+    stalled: bool,
+    /// Whether a close frame is queued, after which nothing more is sent
+    closing: bool,
+    closed: bool,
+}
+
+impl Playground {
+    fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        self.send_frame(&Frame::Text(message.to_json()))
+    }
+
+    /// Queue `frame`, and write as much of the queue as the client takes
+    fn send_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.queued.extend(encode_frame(frame));
+        self.flush()
+    }
+
+    /// Write as much of the queue as the client takes without blocking
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.queued.is_empty() {
+            match self.stream.write(&self.queued) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.queued.drain(..n);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the connection once the client has taken what is queued
+    fn close(&mut self) -> io::Result<()> {
+        self.send_frame(&Frame::Close)?;
+        self.closing = true;
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+pub struct Server {
+    sessions: OrderedMap<String, Session>,
+    /// The work done by sessions since ended, so the totals only grow
+    ended: InterpStats,
+    new_session: Box<dyn Fn() -> Interp>,
+    max_sessions: Option<usize>,
+    session_idle: Option<Duration>,
+    /// How many playgrounds are connected, which `max_sessions` limits too
+    playgrounds: usize,
+    steps_per_second: Option<usize>,
+    clock: Rc<dyn Clock>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::with_quotas(Quotas::default())
+    }
+}
+
+impl Server {
+    /// A server starting each session with an interpreter from
+    /// `new_session`, without a limit on its steps
+    pub fn new<F: Fn() -> Interp + 'static>(new_session: F) -> Self {
+        Server {
+            sessions: OrderedMap::default(),
+            ended: InterpStats::default(),
+            new_session: Box::new(new_session),
+            max_sessions: None,
+            session_idle: None,
+            playgrounds: 0,
+            steps_per_second: None,
+            clock: Rc::new(SystemClock::default()),
+        }
+    }
+
+    /// A server holding each session to `quotas`
+    pub fn with_quotas(quotas: Quotas) -> Self {
+        let prelude = match quotas.isolation {
+            Isolation::Shared => Some(SharedPrelude::default()),
+            Isolation::Private => None,
+        };
+        let mut server = Server::new(move || {
+            let prelude = match &prelude {
+                Some(prelude) => Prelude::Shared(prelude.clone()),
+                None => Prelude::Builtin,
+            };
+            InterpBuilder::new()
+                .prelude(prelude)
+                .limits(quotas.limits)
+                .files(NoFiles)
+                .allow_plugins(false)
+                .allow_net(false)
+                .bundle_path(vec![])
+                .settings_path(None)
+                .build()
+        });
+        server.max_sessions = quotas.max_sessions;
+        server.session_idle = quotas
+            .session_idle_seconds
+            .map(|secs| Duration::from_secs(secs as u64));
+        server.steps_per_second = quotas.steps_per_second;
+        server
+    }
+
+    /// Measure requests' budgets, and refill sessions' allowances, by
+    /// `clock`.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Rc::new(clock);
+        self
+    }
+
+    pub fn handle(&mut self, request: &Request) -> Response {
+        let session = request.path.strip_prefix("/sessions/");
+        match (request.method.as_str(), request.path.as_str(), session) {
+            ("GET", "/metrics", _) => Response {
+                status: 200,
+                content_type: "text/plain; version=0.0.4",
+                body: self.metrics().to_prometheus(),
+            },
+            (_, _, Some(id)) if matches!(id.strip_suffix("/ws"), Some(id) if is_session_id(id)) => {
+                Response::text(400, "Expected a WebSocket upgrade.\n".to_owned())
+            }
+            (_, _, Some(id)) if !is_session_id(id) => {
+                Response::text(400, "Bad session id.\n".to_owned())
+            }
+            (_, _, Some(id)) if !self.has_room_for(id) => {
+                Response::text(503, "Too many sessions.\n".to_owned())
+            }
+            ("POST", _, Some(id)) => match std::str::from_utf8(&request.body) {
+                Ok(input) => Response::text(200, self.eval(id, input)),
+                Err(_) => Response::text(400, "Input isn't UTF-8.\n".to_owned()),
+            },
+            ("DELETE", _, Some(id)) => match self.sessions.shift_remove(id) {
+                Some(session) => {
+                    self.ended += session.interp.stats();
+                    Response::text(200, "Ended.\n".to_owned())
+                }
+                None => Response::text(404, "No session.\n".to_owned()),
+            },
+            _ => Response::text(404, "Not found.\n".to_owned()),
+        }
+    }
+
+    /// Whether there is the session `id`, or room to start it, once the
+    /// sessions idle for too long have ended
+    fn has_room_for(&mut self, id: &str) -> bool {
+        self.end_idle_sessions();
+        match self.max_sessions {
+            Some(max) => self.sessions.len() < max || self.sessions.contains_key(id),
+            None => true,
+        }
+    }
+
+    /// End the sessions unused for longer than `session_idle`
+    fn end_idle_sessions(&mut self) {
+        let idle = match self.session_idle {
+            Some(idle) => idle,
+            None => return,
+        };
+        let now = self.clock.now();
+        let ended = &mut self.ended;
+        self.sessions.retain(|_, session| {
+            let keep = now.saturating_sub(session.used) < idle;
+            if !keep {
+                *ended += session.interp.stats();
+            }
+            keep
+        });
+    }
+
+    /// The session `id`, started if there isn't one, with its allowance
+    /// topped up for the time since it was last
+    fn session(&mut self, id: &str) -> &mut Session {
+        let now = self.clock.now();
+        let (new_session, steps_per_second) = (&self.new_session, self.steps_per_second);
+        let session = self
+            .sessions
+            .entry(id.to_owned())
+            .or_insert_with(|| Session {
+                interp: new_session(),
+                allowance: steps_per_second.unwrap_or(0),
+                refilled: now,
+                used: now,
+            });
+        session.used = now;
+        if let Some(rate) = steps_per_second {
+            let earned = now.saturating_sub(session.refilled).as_secs_f64() * rate as f64;
+            session.allowance = (session.allowance + earned as usize).min(rate);
+            session.refilled = now;
+        }
+        session
+    }
+
+    /// Run `input` in the session `id`, and the jobs it has running, for
+    /// up to `EVAL_BUDGET` and the session's allowance of steps, returning
+    /// the output.
+    fn eval(&mut self, id: &str, input: &str) -> String {
+        let mut output = vec![];
+        // Writing to a `Vec` can't fail
+        let interp = &mut self.session(id).interp;
+        interp.interp_start(input, &mut output).unwrap();
+        let stalled = self.run(id, EVAL_BUDGET, &mut |_, step_output| {
+            output.extend_from_slice(step_output)
+        });
+        if let (true, Some(rate)) = (stalled, self.steps_per_second) {
+            writeln!(
+                output,
+                "Over the quota of {} steps a second; the rest runs in later requests.",
+                rate
+            )
+            .unwrap();
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    }
+
+    /// Step the jobs of the session `id` for up to `budget` and its
+    /// allowance of steps, passing the interpreter and the output of each
+    /// step to `each`. Returns whether it ran out of steps first.
+    fn run(
+        &mut self,
+        id: &str,
+        budget: Duration,
+        each: &mut dyn FnMut(&mut Interp, &[u8]),
+    ) -> bool {
+        let clock = self.clock.clone();
+        let start_time = clock.now();
+        let steps_per_second = self.steps_per_second;
+        let Session {
+            interp, allowance, ..
+        } = self.session(id);
+        let start = interp.stats().small_steps;
+        let mut stalled = false;
+        let mut output = vec![];
+        // Out of steps, jobs still take turns, taking none, while that
+        // finishes the evaluations that took the last of them
+        let mut idle_turns = 0;
+        while !interp.is_done() {
+            let taken = (interp.stats().small_steps - start) as usize;
+            let out_of_steps = steps_per_second.is_some() && taken >= *allowance;
+            if out_of_steps && (taken > *allowance || idle_turns >= interp.running_jobs()) {
+                stalled = true;
+                break;
+            }
+            if steps_per_second.is_some() {
+                interp.set_chunk_steps(allowance.saturating_sub(taken));
+            }
+            let jobs = interp.running_jobs();
+            // Writing to a `Vec` can't fail
+            interp.interp_step(&mut output).unwrap();
+            let idle = output.is_empty() && interp.running_jobs() >= jobs;
+            idle_turns = if out_of_steps && idle {
+                idle_turns + 1
+            } else {
+                0
+            };
+            each(interp, &output);
+            output.clear();
+            if clock.now().saturating_sub(start_time) >= budget {
+                break;
+            }
+        }
+        let taken = (interp.stats().small_steps - start) as usize;
+        *allowance = allowance.saturating_sub(taken);
+        stalled
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let mut totals = self.ended;
+        for session in self.sessions.values() {
+            totals += session.interp.stats();
+        }
+        Metrics {
+            totals,
+            sessions: self
+                .sessions
+                .iter()
+                .map(|(id, session)| (id.clone(), session.interp.memory_estimate()))
+                .collect(),
+        }
+    }
+
+    /// Respond to the connections to `listener`, and serve the
+    /// playgrounds among them, until accepting one fails.
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let mut playgrounds: Vec<Playground> = vec![];
+        loop {
+            let mut busy = false;
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    busy = true;
+                    stream.set_nonblocking(false)?;
+                    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    // A client's failure ends only its own connection
+                    if let Ok(Some(playground)) = self.respond(stream) {
+                        playgrounds.push(playground);
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => return Err(err),
+            }
+            playgrounds.retain_mut(|playground| match self.poll(playground) {
+                Ok(polled) => {
+                    busy |= polled;
+                    !playground.is_closed()
+                }
+                Err(_) => false,
+            });
+            if !busy {
+                thread::sleep(IDLE_WAIT);
+            }
+        }
+    }
+
+    /// Read a request from `stream`, and write the response. A request to
+    /// upgrade `/sessions/<id>/ws` to a WebSocket is answered with the
+    /// playground for the session, for `poll` to serve. A request not
+    /// read within `READ_TIMEOUT` fails as timed out.
+    pub fn respond(&mut self, mut stream: TcpStream) -> io::Result<Option<Playground>> {
+        let mut reader = BufReader::new(Deadline {
+            stream: &stream,
+            deadline: Instant::now() + READ_TIMEOUT,
+        });
+        let request = match read_request(&mut reader)? {
+            Ok(request) => request,
+            Err(response) => return write_response(&mut stream, &response).map(|_| None),
+        };
+        // Clients may send frames as soon as they read the handshake, so
+        // none should be buffered yet, but keep any that are
+        let buf = reader.buffer().to_vec();
+        let (id, key) = match websocket_upgrade(&request) {
+            Some(upgrade) => upgrade,
+            None => return write_response(&mut stream, &self.handle(&request)).map(|_| None),
+        };
+        if !self.has_room_for(id) {
+            let response = Response::text(503, "Too many sessions.\n".to_owned());
+            return write_response(&mut stream, &response).map(|_| None);
+        }
+        if matches!(self.max_sessions, Some(max) if self.playgrounds >= max) {
+            let response = Response::text(503, "Too many playgrounds.\n".to_owned());
+            return write_response(&mut stream, &response).map(|_| None);
+        }
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        )?;
+        stream.flush()?;
+        stream.set_nonblocking(true)?;
+        self.session(id).interp.record_steps(true);
+        self.playgrounds += 1;
+        Ok(Some(Playground {
+            id: id.to_owned(),
+            stream,
+            buf,
+            queued: vec![],
+            running: false,
+            paused: false,
+            stalled: false,
+            closing: false,
+            closed: false,
+        }))
+    }
+
+    /// Read and answer the messages `playground` has sent, and step its
+    /// session's jobs for up to `POLL_BUDGET` unless it paused them,
+    /// streaming the steps. While more than `MAX_QUEUED` bytes wait for
+    /// the client to take them, only those are written. Returns whether
+    /// there was anything to do. Once the connection closes or fails, the
+    /// session stops recording its steps.
+    pub fn poll(&mut self, playground: &mut Playground) -> io::Result<bool> {
+        if playground.closed {
+            return Ok(false);
+        }
+        if let Some(session) = self.sessions.get_mut(&playground.id) {
+            session.used = self.clock.now();
+        }
+        let result = playground.flush().and_then(|()| {
+            if playground.closing {
+                playground.closed = playground.queued.is_empty();
+                return Ok(false);
+            }
+            if playground.queued.len() >= MAX_QUEUED {
+                return Ok(false);
+            }
+            let busy = self.poll_messages(playground)?;
+            if playground.running && !playground.paused && !playground.closing {
+                self.step_playground(playground)?;
+                return Ok(true);
+            }
+            Ok(busy)
+        });
+        if result.is_err() || playground.closed {
+            playground.closed = true;
+            self.playgrounds -= 1;
+            if let Some(session) = self.sessions.get_mut(&playground.id) {
+                session.interp.record_steps(false);
+            }
+        }
+        result
+    }
+
+    fn poll_messages(&mut self, playground: &mut Playground) -> io::Result<bool> {
+        let mut chunk = [0; 4096];
+        loop {
+            match playground.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => playground.buf.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        let mut busy = false;
+        while !playground.closing {
+            let (frame, len) = match parse_frame(&playground.buf) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(_) => {
+                    playground.close()?;
+                    return Ok(true);
+                }
+            };
+            playground.buf.drain(..len);
+            busy = true;
+            match frame {
+                Frame::Text(text) => self.receive(playground, &text)?,
+                Frame::Binary(_) => {
+                    playground.send(&ServerMessage::Error("expected text".to_owned()))?
+                }
+                Frame::Ping(bytes) => playground.send_frame(&Frame::Pong(bytes))?,
+                Frame::Pong(_) => {}
+                Frame::Close => playground.close()?,
+            }
+        }
+        Ok(busy)
+    }
+
+    fn receive(&mut self, playground: &mut Playground, text: &str) -> io::Result<()> {
+        match ClientMessage::parse(text) {
+            Ok(ClientMessage::Eval(input)) => {
+                let interp = &mut self.session(&playground.id).interp;
+                let mut output = vec![];
+                // Writing to a `Vec` can't fail
+                interp.interp_start(&input, &mut output).unwrap();
+                send_steps(playground, interp, &output)?;
+                playground.running = true;
+                playground.stalled = false;
+            }
+            Ok(ClientMessage::Pause) => {
+                playground.paused = true;
+                playground.send(&ServerMessage::Paused)?;
+            }
+            Ok(ClientMessage::Resume) => {
+                playground.paused = false;
+                playground.send(&ServerMessage::Resumed)?;
+            }
+            Ok(ClientMessage::Cancel) => {
+                self.session(&playground.id).interp.cancel();
+                playground.running = false;
+                playground.send(&ServerMessage::Cancelled)?;
+            }
+            Ok(ClientMessage::Capabilities) => {
+                playground.send(&ServerMessage::Capabilities(capabilities()))?;
+            }
+            Err(msg) => playground.send(&ServerMessage::Error(msg))?,
+        }
+        Ok(())
+    }
+
+    fn step_playground(&mut self, playground: &mut Playground) -> io::Result<()> {
+        let id = playground.id.clone();
+        let mut result = Ok(());
+        let stalled = self.run(&id, POLL_BUDGET, &mut |interp, output| {
+            if result.is_ok() {
+                result = send_steps(playground, interp, output);
+            }
+        });
+        result?;
+        if stalled && !playground.stalled {
+            if let Some(rate) = self.steps_per_second {
+                playground.send(&ServerMessage::Output(format!(
+                    "Over the quota of {} steps a second; the rest runs when there are more.\n",
+                    rate
+                )))?;
+            }
+        }
+        playground.stalled = stalled;
+        if self.session(&id).interp.is_done() {
+            playground.running = false;
+            playground.send(&ServerMessage::Done)?;
+        }
+        Ok(())
+    }
+}
+
+/// Send the steps `interp` recorded, then `output`, if any
+fn send_steps(playground: &mut Playground, interp: &mut Interp, output: &[u8]) -> io::Result<()> {
+    for step in interp.take_steps() {
+        playground.send(&ServerMessage::Step(step))?;
+    }
+    if !output.is_empty() {
+        let text = String::from_utf8_lossy(output).into_owned();
+        playground.send(&ServerMessage::Output(text))?;
+    }
+    Ok(())
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+/// The session and `Sec-WebSocket-Key` of a request to upgrade
+/// `/sessions/<id>/ws` to a WebSocket
+fn websocket_upgrade(request: &Request) -> Option<(&str, &str)> {
+    let id = request
+        .path
+        .strip_prefix("/sessions/")?
+        .strip_suffix("/ws")?;
+    let upgrade = request.header("upgrade")?;
+    if request.method != "GET" || !is_session_id(id) || !upgrade.eq_ignore_ascii_case("websocket") {
+        return None;
+    }
+    Some((id, request.header("sec-websocket-key")?))
+}
+
+/// Session ids are names of letters, digits, `-` and `_`
+fn is_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A stream read from until `deadline`, after which reads fail as timed
+/// out, however little a client sends at a time
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        match stream.read(buf) {
+            // As some platforms report a read timing out
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                Err(io::ErrorKind::TimedOut.into())
+            }
+            result => result,
+        }
+    }
+}
+
+/// Read a line into `line`, reading no more than one byte past
+/// `MAX_LINE`, so a line that's too long is one longer than that
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    line.clear();
+    reader.take(MAX_LINE as u64 + 1).read_line(line)
+}
+
+/// Read a request, or the response to one that can't be read
+pub(crate) fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Result<Request, Response>> {
+    let bad_request = || Ok(Err(Response::text(400, "Bad request.\n".to_owned())));
+    let too_large = || {
+        let body = "Request headers too large.\n".to_owned();
+        Ok(Err(Response::text(431, body)))
+    };
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    if line.len() > MAX_LINE {
+        return bad_request();
+    }
+    let mut words = line.split_whitespace();
+    let (method, path) = match (words.next(), words.next()) {
+        (Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+        _ => return bad_request(),
+    };
+    let mut headers = vec![];
+    let mut lines = 0;
+    let mut len = 0;
+    loop {
+        if read_line(reader, &mut line)? == 0 {
+            return bad_request();
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        // Counting lines that aren't headers too, as they are read all
+        // the same
+        lines += 1;
+        if line.len() > MAX_LINE || lines > MAX_HEADERS {
+            return too_large();
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = match value.trim().parse() {
+                    Ok(len) => len,
+                    Err(_) => return bad_request(),
+                };
+            }
+            headers.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+    if len > MAX_BODY {
+        return Ok(Err(Response::text(413, "Input too long.\n".to_owned())));
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
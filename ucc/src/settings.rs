@@ -0,0 +1,484 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Interpreter options changed with `:set` and `:unset`, and saved to a
+//! TOML settings file, read as a table of setting names to values, e.g.
+//!
+//! ```text
+//! # ~/.config/ucc/settings.toml
+//! prompt = "λ> "
+//! show-steps = 10
+//! ```
+
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// How much of each evaluation is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowSteps {
+    /// Show only the final line
+    Off,
+    /// Show the initial and final lines
+    On,
+    /// Show the initial and final lines, and every nth step in between
+    Every(usize),
+}
+
+/// What to do about calls that look like misspelled intrinsics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntrinsicTypos {
+    Ignore,
+    Warn,
+    /// Replace them with the intrinsic
+    Fix,
+}
+
+/// Which end of a value stack is shown as its top
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackTop {
+    /// `⟨[a] [b]⟩`, as stacks are parsed
+    Right,
+    /// `⟨top: [b] [a]⟩`
+    Left,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingValue {
+    Bool(bool),
+    Int(usize),
+    Str(String),
+}
+
+/// The values a setting accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    Bool,
+    Int,
+    Str,
+    /// `on`, `off`, or a positive step interval
+    Steps,
+    /// One of the listed strings
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingError {
+    Unknown(String),
+    InvalidValue { name: String, value: String },
+    Io(String),
+    BadSettingsFile(String),
+}
+
+pub struct SettingSpec {
+    pub name: &'static str,
+    pub kind: SettingKind,
+    pub default: &'static str,
+    pub help: &'static str,
+}
+
+/// Every known setting, in the order `:settings` lists them
+pub static SETTING_SPECS: &[SettingSpec] = &[
+    SettingSpec {
+        name: "prompt",
+        kind: SettingKind::Str,
+        default: ">>> ",
+        help: "the REPL prompt",
+    },
+    SettingSpec {
+        name: "show-steps",
+        kind: SettingKind::Steps,
+        default: "on",
+        help: "off (result only), on (show inputs), or <n> (every <n>th step)",
+    },
+    SettingSpec {
+        name: "theme",
+        kind: SettingKind::Choice(&["unicode", "ascii"]),
+        default: "unicode",
+        help: "unicode, or ascii to print `<`, `>`, `->`, and `=>` instead",
+    },
+    SettingSpec {
+        name: "prelude",
+        kind: SettingKind::Bool,
+        default: "on",
+        help: "whether :reset defines the builtin functions",
+    },
+    SettingSpec {
+        name: "compact-symbols",
+        kind: SettingKind::Bool,
+        default: "off",
+        help: "whether :clear also drops unused symbols (:reset always does)",
+    },
+    SettingSpec {
+        name: "intrinsic-typos",
+        kind: SettingKind::Choice(&["ignore", "warn", "fix"]),
+        default: "warn",
+        help: "ignore, warn about, or fix calls one typo away from an intrinsic",
+    },
+    SettingSpec {
+        name: "search-size",
+        kind: SettingKind::Int,
+        default: "3",
+        help: "the most intrinsics and calls :search puts together",
+    },
+    SettingSpec {
+        name: "normalize-input",
+        kind: SettingKind::Bool,
+        default: "on",
+        help: "whether input may use look-alikes of ASCII, like Cyrillic `о`",
+    },
+    SettingSpec {
+        name: "provenance",
+        kind: SettingKind::Bool,
+        default: "off",
+        help: "whether to track how each value was made, for :why",
+    },
+    SettingSpec {
+        name: "prefold",
+        kind: SettingKind::Bool,
+        default: "off",
+        help: "whether definitions are made with closed prefixes evaluated",
+    },
+    SettingSpec {
+        name: "stack-top",
+        kind: SettingKind::Choice(&["right", "left"]),
+        default: "right",
+        help: "right, or left to show stacks reversed, as ⟨top: [b] [a]⟩",
+    },
+    SettingSpec {
+        name: "stack-display-max",
+        kind: SettingKind::Int,
+        default: "100",
+        help: "the most values of a stack shown, those nearest the top (0 for all)",
+    },
+    SettingSpec {
+        name: "output-max",
+        kind: SettingKind::Int,
+        default: "1000000",
+        help: "the most bytes a command prints before the rest is cut (0 for all)",
+    },
+    SettingSpec {
+        name: "output-overflow",
+        kind: SettingKind::Choice(&["continue", "abort"]),
+        default: "continue",
+        help: "continue, or abort a command once its output is cut",
+    },
+    SettingSpec {
+        name: "align",
+        kind: SettingKind::Bool,
+        default: "off",
+        help: "whether :trace lines up its columns, printing once it finishes",
+    },
+];
+
+const PROMPT: usize = 0;
+const SHOW_STEPS: usize = 1;
+const THEME: usize = 2;
+const PRELUDE: usize = 3;
+const COMPACT_SYMBOLS: usize = 4;
+const INTRINSIC_TYPOS: usize = 5;
+const SEARCH_SIZE: usize = 6;
+const NORMALIZE_INPUT: usize = 7;
+const PROVENANCE: usize = 8;
+const PREFOLD: usize = 9;
+const STACK_TOP: usize = 10;
+const STACK_DISPLAY_MAX: usize = 11;
+const OUTPUT_MAX: usize = 12;
+const OUTPUT_OVERFLOW: usize = 13;
+const ALIGN: usize = 14;
+
+impl SettingKind {
+    /// Parse `text` as typed at the REPL
+    pub fn parse(&self, text: &str) -> Option<SettingValue> {
+        let as_bool = || match text {
+            "on" | "true" => Some(SettingValue::Bool(true)),
+            "off" | "false" => Some(SettingValue::Bool(false)),
+            _ => None,
+        };
+        let as_int = || text.parse().ok().map(SettingValue::Int);
+        let value = match self {
+            SettingKind::Bool => as_bool(),
+            SettingKind::Int => as_int(),
+            SettingKind::Str => Some(SettingValue::Str(text.to_owned())),
+            SettingKind::Steps => as_bool().or_else(as_int),
+            SettingKind::Choice(_) => Some(SettingValue::Str(text.to_owned())),
+        }?;
+        if self.accepts(&value) {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn accepts(&self, value: &SettingValue) -> bool {
+        match (self, value) {
+            (SettingKind::Bool, SettingValue::Bool(_))
+            | (SettingKind::Int, SettingValue::Int(_))
+            | (SettingKind::Str, SettingValue::Str(_))
+            | (SettingKind::Steps, SettingValue::Bool(_)) => true,
+            (SettingKind::Steps, SettingValue::Int(n)) => *n > 0,
+            (SettingKind::Choice(choices), SettingValue::Str(s)) => choices.contains(&s.as_str()),
+            _ => false,
+        }
+    }
+}
+
+impl SettingValue {
+    /// Render as a value in the settings file
+    fn to_toml(&self) -> String {
+        match self {
+            SettingValue::Bool(b) => b.to_string(),
+            SettingValue::Int(n) => n.to_string(),
+            SettingValue::Str(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+        }
+    }
+
+    fn from_toml(value: &toml::Value) -> Option<SettingValue> {
+        match value {
+            toml::Value::Boolean(b) => Some(SettingValue::Bool(*b)),
+            toml::Value::Integer(n) => usize::try_from(*n).ok().map(SettingValue::Int),
+            toml::Value::String(s) => Some(SettingValue::Str(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingValue::Bool(true) => write!(f, "on"),
+            SettingValue::Bool(false) => write!(f, "off"),
+            SettingValue::Int(n) => write!(f, "{}", n),
+            SettingValue::Str(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// The current value of every setting in `SETTING_SPECS`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    values: Vec<SettingValue>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            values: SETTING_SPECS.iter().map(default_value).collect(),
+        }
+    }
+}
+
+fn default_value(spec: &SettingSpec) -> SettingValue {
+    spec.kind.parse(spec.default).unwrap()
+}
+
+fn index_of(name: &str) -> Result<usize, SettingError> {
+    SETTING_SPECS
+        .iter()
+        .position(|spec| spec.name == name)
+        .ok_or_else(|| SettingError::Unknown(name.to_owned()))
+}
+
+impl Settings {
+    pub fn prompt(&self) -> &str {
+        match &self.values[PROMPT] {
+            SettingValue::Str(prompt) => prompt,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn show_steps(&self) -> ShowSteps {
+        match self.values[SHOW_STEPS] {
+            SettingValue::Bool(false) => ShowSteps::Off,
+            SettingValue::Bool(true) => ShowSteps::On,
+            SettingValue::Int(n) => ShowSteps::Every(n),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether to print only ASCII
+    pub fn ascii(&self) -> bool {
+        self.values[THEME] == SettingValue::Str("ascii".to_owned())
+    }
+
+    pub fn prelude(&self) -> bool {
+        self.values[PRELUDE] == SettingValue::Bool(true)
+    }
+
+    pub fn intrinsic_typos(&self) -> IntrinsicTypos {
+        match &self.values[INTRINSIC_TYPOS] {
+            SettingValue::Str(s) if s == "ignore" => IntrinsicTypos::Ignore,
+            SettingValue::Str(s) if s == "fix" => IntrinsicTypos::Fix,
+            _ => IntrinsicTypos::Warn,
+        }
+    }
+
+    /// Whether `:clear` rebuilds the interner
+    pub fn compact_symbols(&self) -> bool {
+        self.values[COMPACT_SYMBOLS] == SettingValue::Bool(true)
+    }
+
+    pub fn search_size(&self) -> usize {
+        match self.values[SEARCH_SIZE] {
+            SettingValue::Int(n) => n,
+            _ => 3,
+        }
+    }
+
+    pub fn normalize_input(&self) -> bool {
+        self.values[NORMALIZE_INPUT] == SettingValue::Bool(true)
+    }
+
+    pub fn provenance(&self) -> bool {
+        self.values[PROVENANCE] == SettingValue::Bool(true)
+    }
+
+    /// Whether definitions are folded as they are made
+    pub fn prefold(&self) -> bool {
+        self.values[PREFOLD] == SettingValue::Bool(true)
+    }
+
+    /// Which end of a value stack is shown as its top
+    pub fn stack_top(&self) -> StackTop {
+        match &self.values[STACK_TOP] {
+            SettingValue::Str(s) if s == "left" => StackTop::Left,
+            _ => StackTop::Right,
+        }
+    }
+
+    /// The most values of a stack shown, or `None` to show them all
+    pub fn stack_display_max(&self) -> Option<usize> {
+        match self.values[STACK_DISPLAY_MAX] {
+            SettingValue::Int(0) => None,
+            SettingValue::Int(n) => Some(n),
+            _ => Some(100),
+        }
+    }
+
+    /// The most bytes a command prints, or `None` for no limit
+    pub fn output_max(&self) -> Option<usize> {
+        match self.values[OUTPUT_MAX] {
+            SettingValue::Int(0) => None,
+            SettingValue::Int(n) => Some(n),
+            _ => Some(1_000_000),
+        }
+    }
+
+    /// Whether a command is aborted once its output is cut
+    pub fn abort_on_output_overflow(&self) -> bool {
+        self.values[OUTPUT_OVERFLOW] == SettingValue::Str("abort".to_owned())
+    }
+
+    /// Whether traces are printed as a table, with their columns aligned
+    pub fn align(&self) -> bool {
+        self.values[ALIGN] == SettingValue::Bool(true)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SettingValue> {
+        index_of(name).ok().map(|i| &self.values[i])
+    }
+
+    /// Set `name` from `text` as typed at the REPL.
+    pub fn set(&mut self, name: &str, text: &str) -> Result<(), SettingError> {
+        let i = index_of(name)?;
+        self.values[i] =
+            SETTING_SPECS[i]
+                .kind
+                .parse(text)
+                .ok_or_else(|| SettingError::InvalidValue {
+                    name: name.to_owned(),
+                    value: text.to_owned(),
+                })?;
+        Ok(())
+    }
+
+    pub fn set_value(&mut self, name: &str, value: SettingValue) -> Result<(), SettingError> {
+        let i = index_of(name)?;
+        if !SETTING_SPECS[i].kind.accepts(&value) {
+            return Err(SettingError::InvalidValue {
+                name: name.to_owned(),
+                value: value.to_string(),
+            });
+        }
+        self.values[i] = value;
+        Ok(())
+    }
+
+    /// Restore `name` to its default value.
+    pub fn unset(&mut self, name: &str) -> Result<(), SettingError> {
+        let i = index_of(name)?;
+        self.values[i] = default_value(&SETTING_SPECS[i]);
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static SettingSpec, &SettingValue)> {
+        SETTING_SPECS.iter().zip(self.values.iter())
+    }
+
+    /// The settings file contents, listing only settings that differ from
+    /// their defaults.
+    pub fn to_file_string(&self) -> String {
+        let mut s = String::new();
+        for (spec, value) in self.iter() {
+            if *value != default_value(spec) {
+                s.push_str(&format!("{} = {}\n", spec.name, value.to_toml()));
+            }
+        }
+        s
+    }
+
+    pub fn update_from_file_string(&mut self, src: &str) -> Result<(), SettingError> {
+        let table = match src.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err(SettingError::BadSettingsFile("expected a table".to_owned())),
+            Err(err) => return Err(SettingError::BadSettingsFile(err.to_string())),
+        };
+        for (name, value) in table {
+            let value =
+                SettingValue::from_toml(&value).ok_or_else(|| SettingError::InvalidValue {
+                    name: name.clone(),
+                    value: value.to_string(),
+                })?;
+            self.set_value(&name, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SettingError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| SettingError::Io(err.to_string()))?;
+        }
+        fs::write(path, self.to_file_string()).map_err(|err| SettingError::Io(err.to_string()))
+    }
+
+    /// Update the settings from the file at `path`, if there is one.
+    pub fn load(&mut self, path: &Path) -> Result<(), SettingError> {
+        match fs::read_to_string(path) {
+            Ok(src) => self.update_from_file_string(&src),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(SettingError::Io(err.to_string())),
+        }
+    }
+}
+
+/// `ucc` in `XDG_CONFIG_HOME`, or in `~/.config`
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("ucc"))
+}
+
+/// The settings file: `ucc/settings.toml` in `XDG_CONFIG_HOME`, or in
+/// `~/.config`.
+pub fn default_settings_path() -> Option<PathBuf> {
+    Some(config_dir()?.join(SETTINGS_FILE))
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A prelude shared between contexts, for hosts such as servers that run
+//! an interpreter per session. Each context interns its symbols in the
+//! prelude's thread-safe interner and looks up the prelude's definitions
+//! behind an `Arc`, rather than decoding and holding its own copy, e.g.
+//!
+//! ```
+//! # use ucc::core::Context;
+//! # use ucc::shared::SharedPrelude;
+//! let prelude = SharedPrelude::default();
+//! let sessions: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let prelude = prelude.clone();
+//!         std::thread::spawn(move || Context::with_prelude(&prelude).interner_stats())
+//!     })
+//!     .collect();
+//! for session in sessions {
+//!     session.join().unwrap();
+//! }
+//! ```
+
+use crate::binary::BinaryError;
+use crate::builtin::PRELUDE_UCCB;
+use crate::core::{Context, Expr, Interner, Map, OrderedMap, Symbol, CORE};
+use lasso::ThreadedRodeo;
+use std::sync::Arc;
+
+/// Definitions that can't change, and the interner their symbols are in.
+/// Clones share both.
+#[derive(Clone)]
+pub struct SharedPrelude {
+    pub(crate) interner: Arc<ThreadedRodeo>,
+    pub(crate) fns: Arc<OrderedMap<Symbol, Expr>>,
+    pub(crate) exprs: Arc<Map<Expr, Symbol>>,
+}
+
+impl Default for SharedPrelude {
+    /// The builtin prelude
+    fn default() -> Self {
+        SharedPrelude::new(PRELUDE_UCCB).unwrap()
+    }
+}
+
+impl SharedPrelude {
+    /// Decode the definitions in `bytes`, in the `.uccb` format, as those
+    /// of the `core` namespace.
+    pub fn new(bytes: &[u8]) -> Result<Self, BinaryError> {
+        let interner = Arc::new(ThreadedRodeo::new());
+        let mut ctx = Context {
+            interner: Interner::Shared(Arc::clone(&interner)),
+            ..Context::default()
+        };
+        ctx.import_prelude(bytes)?;
+        let core = Symbol(ctx.interner.get_or_intern(CORE));
+        Ok(SharedPrelude {
+            interner,
+            fns: Arc::new(
+                Arc::make_mut(&mut ctx.namespaces)
+                    .swap_remove(&core)
+                    .unwrap()
+                    .fns,
+            ),
+            exprs: ctx.exprs,
+        })
+    }
+
+    /// The symbols defined, in the order they were defined
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.fns.keys().copied().collect()
+    }
+}
+
+impl Context {
+    /// A context with the definitions of `prelude`, whose symbols are
+    /// interned in its interner. Definitions made in the context shadow
+    /// the prelude's, and aren't seen by other contexts.
+    pub fn with_prelude(prelude: &SharedPrelude) -> Self {
+        let mut ctx = Context {
+            interner: Interner::Shared(Arc::clone(&prelude.interner)),
+            shared: Some(prelude.clone()),
+            host_intrinsics: Map::default(),
+            ..Context::default()
+        };
+        ctx.register_pair_intrinsics();
+        ctx.register_map_intrinsics();
+        ctx.register_char_intrinsics();
+        #[cfg(feature = "bignum")]
+        ctx.register_nat_intrinsics();
+        #[cfg(feature = "rational")]
+        ctx.register_ratio_intrinsics();
+        #[cfg(feature = "regex")]
+        ctx.register_regex_intrinsics();
+        ctx
+    }
+}
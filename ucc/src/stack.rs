@@ -0,0 +1,223 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A persistent stack, which `ValueStack` keeps its values in when the
+//! `persistent-stack` feature is on, so that copying a stack to keep as it
+//! was, as for tracing or the debugger, takes constant time instead of
+//! copying every value. It has the methods of `Vec` the crate uses, with
+//! its elements in the same order, bottom first, so either can be used;
+//! those near the top, as `push`, `pop` and `last`, take constant time,
+//! and the others time linear in the stack's length.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::rc::Rc;
+
+/// A stack sharing its values with its copies until either changes them
+pub struct Stack<T> {
+    top: Option<Rc<Node<T>>>,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct Node<T> {
+    value: T,
+    below: Option<Rc<Node<T>>>,
+}
+
+impl<T: Clone> Stack<T> {
+    pub fn new() -> Self {
+        Stack { top: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, value: T) {
+        let below = self.top.take();
+        self.top = Some(Rc::new(Node { value, below }));
+        self.len += 1;
+    }
+
+    /// Pop the top value, copying it if another stack shares it
+    pub fn pop(&mut self) -> Option<T> {
+        let top = self.top.take()?;
+        self.len -= 1;
+        match Rc::try_unwrap(top) {
+            Ok(node) => {
+                self.top = node.below;
+                Some(node.value)
+            }
+            Err(top) => {
+                self.top = top.below.clone();
+                Some(top.value.clone())
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.top.as_ref().map(|node| &node.value)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn clear(&mut self) {
+        // Popping one at a time, so dropping a long stack doesn't recurse
+        while self.pop().is_some() {}
+    }
+
+    /// Pop values, or push copies of `value`, until there are `len`
+    pub fn resize(&mut self, len: usize, value: T) {
+        while self.len > len {
+            self.pop();
+        }
+        while self.len < len {
+            self.push(value.clone());
+        }
+    }
+
+    /// Remove and return the value at `index`, from the bottom
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
+        let mut above = Vec::with_capacity(self.len - index - 1);
+        for _ in index + 1..self.len {
+            above.push(self.pop().unwrap());
+        }
+        let value = self.pop().unwrap();
+        self.extend(above.into_iter().rev());
+        value
+    }
+
+    /// The values, bottom first
+    pub fn iter(&self) -> std::vec::IntoIter<&T> {
+        let mut values = Vec::with_capacity(self.len);
+        let mut node = &self.top;
+        while let Some(n) = node {
+            values.push(&n.value);
+            node = &n.below;
+        }
+        values.reverse();
+        values.into_iter()
+    }
+
+    /// The values, bottom first, copying first those another stack shares
+    pub fn iter_mut(&mut self) -> std::vec::IntoIter<&mut T> {
+        let mut values = Vec::with_capacity(self.len);
+        let mut node = &mut self.top;
+        while let Some(n) = node {
+            let n = Rc::make_mut(n);
+            values.push(&mut n.value);
+            node = &mut n.below;
+        }
+        values.reverse();
+        values.into_iter()
+    }
+}
+
+impl<T: Clone> Default for Stack<T> {
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
+impl<T> Clone for Stack<T> {
+    /// Share the values, in constant time
+    fn clone(&self) -> Self {
+        Stack {
+            top: self.top.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        // Unlink the nodes no other stack shares one at a time, so
+        // dropping a long stack doesn't recurse
+        let mut node = self.top.take();
+        while let Some(n) = node {
+            node = match Rc::try_unwrap(n) {
+                Ok(mut n) => n.below.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+impl<T: Clone> Extend<T> for Stack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        for value in values {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Stack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let mut stack = Stack::new();
+        stack.extend(values);
+        stack
+    }
+}
+
+impl<T: Clone> IntoIterator for Stack<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut values = Vec::with_capacity(self.len);
+        while let Some(value) = self.pop() {
+            values.push(value);
+        }
+        values.reverse();
+        values.into_iter()
+    }
+}
+
+impl<T: Clone> Index<usize> for Stack<T> {
+    type Output = T;
+
+    /// The value at `index`, from the bottom
+    fn index(&self, index: usize) -> &T {
+        assert!(index < self.len, "index out of bounds");
+        let mut node = self.top.as_ref().unwrap();
+        for _ in index + 1..self.len {
+            node = node.below.as_ref().unwrap();
+        }
+        &node.value
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for Stack<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for Stack<T> {}
+
+impl<T: Clone + Hash> Hash for Stack<T> {
+    /// Hashed as a `Vec` of the values would be
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        self.iter().for_each(|value| value.hash(state));
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for Stack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Patterns over value stacks, shared by the commands that wait for a stack
+//! of some shape, e.g.
+//!
+//! ```text
+//! ⟨... [$f] _ $x⟩
+//! ```
+//!
+//! matches any stack of at least three values whose third from the top is
+//! a quotation, capturing its contents as `$f` and the top value as `$x`.
+//! `_` matches any value, and `...` any number of values, at most once per
+//! pattern. A capture used twice matches only equal values, and a value
+//! captured with `$x` is bound to the expression `quote` would make of it,
+//! so `⟨$x [$x]⟩` matches `⟨[a] [[a]]⟩`. Patterns are matched against the
+//! stack as displayed, so `true` matches a compressed `true` but not
+//! `[swap drop]`.
+
+use crate::core::{pushing, Context, Expr, OrderedMap, Symbol, Value, ValueStack};
+use crate::load::LoadError;
+use crate::parse::StackPatternParser;
+use crate::rewrite::Pattern;
+
+/// The expressions bound by a match, in the order they were captured
+pub type Captures = OrderedMap<Symbol, Expr>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValuePattern {
+    /// `_`
+    Any,
+    /// `$x`
+    Capture(Symbol),
+    Call(Symbol),
+    Symbol(Symbol),
+    /// A quotation whose contents match, as in a rewrite rule
+    Quote(Pattern),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackPattern {
+    /// The patterns of the bottom values, or of every value if there is no
+    /// `...`
+    pub bottom: Vec<ValuePattern>,
+    /// The patterns of the top values, after a `...`
+    pub top: Option<Vec<ValuePattern>>,
+}
+
+impl ValuePattern {
+    fn match_value(&self, v: &Value, captures: &mut Captures) -> bool {
+        match (self, v) {
+            (ValuePattern::Any, _) => true,
+            (ValuePattern::Capture(sym), v) => {
+                let e = pushing(v.clone());
+                match captures.get(sym) {
+                    Some(bound) => *bound == e,
+                    None => {
+                        captures.insert(*sym, e);
+                        true
+                    }
+                }
+            }
+            (ValuePattern::Call(psym), Value::Call(vsym)) => psym == vsym,
+            (ValuePattern::Symbol(psym), Value::Symbol(vsym)) => psym == vsym,
+            (ValuePattern::Quote(p), Value::Quote(e)) => p.match_expr(e, captures),
+            _ => false,
+        }
+    }
+}
+
+impl StackPattern {
+    /// Match `vs`, returning what was captured, or `None` if it doesn't
+    /// match.
+    pub fn matches(&self, vs: &ValueStack) -> Option<Captures> {
+        let values = &vs.0;
+        let top: &[ValuePattern] = match &self.top {
+            Some(top) if self.bottom.len() + top.len() <= values.len() => top,
+            None if self.bottom.len() == values.len() => &[],
+            _ => return None,
+        };
+        let mut captures = Captures::default();
+        let bottom = self.bottom.iter().zip(values.iter());
+        let top = top.iter().zip(values.iter().skip(values.len() - top.len()));
+        for (p, v) in bottom.chain(top) {
+            if !p.match_value(v, &mut captures) {
+                return None;
+            }
+        }
+        Some(captures)
+    }
+}
+
+impl Context {
+    pub fn parse_stack_pattern(&mut self, src: &str) -> Result<StackPattern, LoadError> {
+        StackPatternParser::new()
+            .parse(&mut self.interner, src)
+            .map_err(|err| LoadError::Parse(format!("{:?}", err)))
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Evaluation as an iterator of the states small steps reach, so traces
+//! can be taken, filtered, and collected like any other, e.g.
+//!
+//! ```
+//! # use ucc::core::*;
+//! let mut ctx = Context::default();
+//! let e = ctx.parse_expr("[a] clone drop").unwrap();
+//! let states: Vec<(ValueStack, Expr)> = ctx
+//!     .steps(&ValueStack::default(), &e)
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! let stacks: Vec<String> = states
+//!     .iter()
+//!     .map(|(vs, _)| ctx.display(vs).to_string())
+//!     .collect();
+//! assert_eq!(stacks, ["⟨[a]⟩", "⟨[a] [a]⟩", "⟨[a]⟩"]);
+//! ```
+
+use crate::core::{Context, EvalError, Expr, ValueStack};
+
+/// The states evaluating an expression reaches, one per small step, ending
+/// when the expression is empty, or with the error a step fails with
+pub struct Steps<'a> {
+    ctx: &'a mut Context,
+    vs: ValueStack,
+    e: Expr,
+    done: bool,
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Result<(ValueStack, Expr), EvalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.e == Expr::default() {
+            return None;
+        }
+        match self.ctx.small_step(&mut self.vs, &mut self.e) {
+            Ok(()) => Some(Ok((self.vs.clone(), self.e.clone()))),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Context {
+    /// The states evaluating `e` on `vs` reaches, lazily, each after one
+    /// more small step; see `Steps`
+    pub fn steps(&mut self, vs: &ValueStack, e: &Expr) -> Steps<'_> {
+        Steps {
+            ctx: self,
+            vs: vs.clone(),
+            e: e.clone(),
+            done: false,
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Incremental parsing of a source too large to hold in memory, one item at
+//! a time. A definition or rule ends at its closing `}`, and, unlike in
+//! `:load`, an expression ends at the end of the line on which its brackets
+//! are closed, or at the start of a definition.
+
+use crate::character::char_literal_len;
+use crate::core::Interner;
+use crate::interp::InterpItem;
+use crate::load::LoadError;
+use crate::parse::InterpItemsParser;
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Def,
+    Expr,
+}
+
+pub(crate) struct ItemReader<R> {
+    reader: R,
+    parser: InterpItemsParser,
+    /// Text read but not yet parsed
+    buf: String,
+    /// The line number of the start of `buf`
+    line: usize,
+    /// How much of `buf` has been scanned for the end of the next item
+    scanned: usize,
+    depth: usize,
+    kind: Option<ItemKind>,
+    pending: VecDeque<InterpItem>,
+}
+
+impl<R: BufRead> ItemReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            parser: InterpItemsParser::new(),
+            buf: String::new(),
+            line: 1,
+            scanned: 0,
+            depth: 0,
+            kind: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Parse the next item, reading only as much as it needs, or return
+    /// `None` at the end of the input.
+    pub(crate) fn parse_next_item(
+        &mut self,
+        interner: &mut Interner,
+    ) -> Result<Option<InterpItem>, LoadError> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Ok(Some(item));
+            }
+            let end = match self.item_end() {
+                Some(end) => end,
+                None => {
+                    let n = self
+                        .reader
+                        .read_line(&mut self.buf)
+                        .map_err(|err| LoadError::Io(err.to_string()))?;
+                    if n > 0 {
+                        continue;
+                    } else if self.buf.is_empty() {
+                        return Ok(None);
+                    }
+                    self.buf.len()
+                }
+            };
+            let chunk: String = self.buf.drain(..end).collect();
+            let line = self.line;
+            self.line += chunk.matches('\n').count();
+            self.scanned = 0;
+            self.depth = 0;
+            self.kind = None;
+            let items = self
+                .parser
+                .parse(interner, &chunk)
+                .map_err(|err| LoadError::Parse(format!("line {}: {:?}", line, err)))?;
+            self.pending.extend(items);
+        }
+    }
+
+    /// The end of the first item in `buf`, if it has all been read. Only
+    /// the text added since the last call is scanned.
+    fn item_end(&mut self) -> Option<usize> {
+        let bytes = self.buf.as_bytes();
+        let mut i = self.scanned;
+        while i < bytes.len() {
+            match bytes[i] {
+                // Skip `--` comments, up to the newline
+                b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                    match bytes[i..].iter().position(|&b| b == b'\n') {
+                        Some(n) => i += n,
+                        None => break,
+                    }
+                    continue;
+                }
+                // Brackets in string and character literals don't count
+                b'"' => {
+                    match bytes[i + 1..].iter().position(|&b| b == b'"') {
+                        Some(n) => i += n + 2,
+                        None => break,
+                    }
+                    self.kind.get_or_insert(ItemKind::Expr);
+                    continue;
+                }
+                b'\'' => {
+                    self.kind.get_or_insert(ItemKind::Expr);
+                    i += char_literal_len(&self.buf[i..]).unwrap_or(1);
+                    continue;
+                }
+                // A map literal's `#{` opens an expression, not a definition
+                b'#' if bytes.get(i + 1) == Some(&b'{') => {
+                    self.kind.get_or_insert(ItemKind::Expr);
+                    self.depth += 1;
+                    i += 2;
+                    continue;
+                }
+                b'{' if self.depth == 0 && self.kind == Some(ItemKind::Expr) => return Some(i),
+                b'{' | b'[' | b'(' => {
+                    if self.kind.is_none() {
+                        self.kind = Some(if bytes[i] == b'{' {
+                            ItemKind::Def
+                        } else {
+                            ItemKind::Expr
+                        });
+                    }
+                    self.depth += 1;
+                }
+                b'}' | b']' | b')' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 && self.kind == Some(ItemKind::Def) {
+                        return Some(i + 1);
+                    }
+                }
+                // Blank and comment lines are parsed alone, so they don't
+                // accumulate
+                b'\n' if self.depth == 0 && self.kind != Some(ItemKind::Def) => return Some(i + 1),
+                b if !b.is_ascii_whitespace() && self.kind.is_none() => {
+                    self.kind = Some(ItemKind::Expr)
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        self.scanned = i;
+        None
+    }
+}
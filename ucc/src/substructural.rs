@@ -0,0 +1,171 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Substructural checks, for experimenting with variants of the calculus
+//! in which values can't be freely duplicated or discarded. A definition is
+//! linear if it never uses `clone` or `drop`, so it uses each value exactly
+//! once, and affine if it never uses `clone`, so it uses each at most once.
+//! The intrinsics of other bases that duplicate or discard values, like
+//! `dup` and `k`, count as `clone` and `drop`. The quotations in a
+//! definition are checked too, since they may be applied, as are the
+//! functions it calls. Values it is given and applies are trusted, but
+//! host intrinsics, which may do anything with their arguments, can't be
+//! checked, so are reported apart from the intrinsics ruled out.
+
+use crate::core::{Context, Expr, Intrinsic, Map, Symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Discipline {
+    /// No `clone` or `drop`
+    Linear,
+    /// No `clone`
+    Affine,
+}
+
+/// The position of a subexpression in a definition: the index of the item
+/// it is in the body, then in that item, and so on, looking through
+/// quotations
+pub type Location = Vec<usize>;
+
+/// A use of an intrinsic the discipline rules out, or of a host intrinsic,
+/// which it may or may not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub intrinsic: Intrinsic,
+    /// The function it is in and where, then the call that led there and
+    /// where, and so on out to the function checked
+    pub sites: Vec<(Symbol, Location)>,
+}
+
+impl Violation {
+    /// Whether it is of a host intrinsic, so may not be a violation at all
+    pub fn is_unknown(&self) -> bool {
+        matches!(self.intrinsic, Intrinsic::Host(_))
+    }
+}
+
+impl Discipline {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Discipline::Linear),
+            "affine" => Some(Discipline::Affine),
+            _ => None,
+        }
+    }
+
+    fn forbids(&self, intr: &Intrinsic) -> bool {
+        match intr {
+            Intrinsic::Clone | Intrinsic::Dup | Intrinsic::Over | Intrinsic::Cake => true,
+            Intrinsic::Drop | Intrinsic::K => *self == Discipline::Linear,
+            _ => false,
+        }
+    }
+}
+
+struct Checker<'a> {
+    ctx: &'a Context,
+    discipline: Discipline,
+    /// The functions being checked, so recursive calls are only checked once
+    checking: Vec<Symbol>,
+    /// The violations found in each function already checked
+    checked: Map<Symbol, Vec<Violation>>,
+}
+
+impl Checker<'_> {
+    fn check_fn(&mut self, sym: Symbol, body: &Expr) -> Vec<Violation> {
+        if let Some(violations) = self.checked.get(&sym) {
+            return violations.clone();
+        }
+        self.checking.push(sym);
+        let mut violations = vec![];
+        self.check_expr(sym, body, &mut vec![], &mut violations);
+        self.checking.pop();
+        self.checked.insert(sym, violations.clone());
+        violations
+    }
+
+    fn check_expr(
+        &mut self,
+        sym: Symbol,
+        e: &Expr,
+        location: &mut Location,
+        violations: &mut Vec<Violation>,
+    ) {
+        match e {
+            Expr::Intrinsic(intr) if self.discipline.forbids(intr) => {
+                violations.push(Violation {
+                    intrinsic: *intr,
+                    sites: vec![(sym, location.clone())],
+                });
+            }
+            Expr::Intrinsic(Intrinsic::Host(host)) => self.unknown(sym, *host, location, violations),
+            Expr::Intrinsic(_) | Expr::Symbol(_) | Expr::Map(_) | Expr::Char(_) | Expr::Str(_) => {}
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => {}
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => {}
+            Expr::Call(callee) => {
+                if self.checking.contains(callee) {
+                    return;
+                }
+                // Calls to undefined functions are left to the diagnostics
+                if let Some(body) = self.ctx.lookup_fn(callee) {
+                    for mut violation in self.check_fn(*callee, body) {
+                        violation.sites.push((sym, location.clone()));
+                        violations.push(violation);
+                    }
+                } else if self.ctx.host_intrinsic(callee).is_some() {
+                    self.unknown(sym, *callee, location, violations);
+                }
+            }
+            Expr::Quote(e) => self.check_expr(sym, e, location, violations),
+            Expr::Pair(p) => {
+                self.check_expr(sym, &p.0, location, violations);
+                self.check_expr(sym, &p.1, location, violations);
+            }
+            Expr::Compose(es) => {
+                for (i, e) in es.iter().enumerate() {
+                    location.push(i);
+                    self.check_expr(sym, e, location, violations);
+                    location.pop();
+                }
+            }
+        }
+    }
+
+    fn unknown(
+        &self,
+        sym: Symbol,
+        host: Symbol,
+        location: &Location,
+        violations: &mut Vec<Violation>,
+    ) {
+        violations.push(Violation {
+            intrinsic: Intrinsic::Host(host),
+            sites: vec![(sym, location.clone())],
+        });
+    }
+}
+
+impl Context {
+    /// Find the uses of intrinsics `discipline` rules out, and of host
+    /// intrinsics, in the definition of `sym` and the functions it calls,
+    /// or return `None` if `sym` isn't defined.
+    pub fn check_substructural(
+        &self,
+        sym: Symbol,
+        discipline: Discipline,
+    ) -> Option<Vec<Violation>> {
+        let body = self.lookup_fn(&sym)?;
+        let mut checker = Checker {
+            ctx: self,
+            discipline,
+            checking: vec![],
+            checked: Map::default(),
+        };
+        Some(checker.check_fn(sym, body))
+    }
+}
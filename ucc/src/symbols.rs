@@ -0,0 +1,179 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Interner statistics, and compaction to drop symbols that are no longer
+//! used, such as misspelled names from long sessions.
+
+use crate::core::{Context, Expr, Interner, Intrinsic, Symbol, Value, ValueStack};
+use crate::diagnostics::Warning;
+use crate::map::ValueMap;
+use crate::rewrite::{Pattern, Rule};
+use std::mem;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InternerStats {
+    pub symbols: usize,
+    /// The total length of the symbols' names
+    pub bytes: usize,
+}
+
+/// Re-interns symbols from an old interner in a new one
+struct Remap<'a> {
+    old: &'a Interner,
+    new: Interner,
+}
+
+impl Remap<'_> {
+    fn symbol(&mut self, sym: &mut Symbol) {
+        *sym = Symbol(self.new.get_or_intern(self.old.resolve(&sym.0)));
+    }
+
+    fn expr(&mut self, e: &mut Expr) {
+        e.visit_mut(&mut |e| match e {
+            Expr::Intrinsic(Intrinsic::Host(sym)) | Expr::Call(sym) | Expr::Symbol(sym) => {
+                self.symbol(sym)
+            }
+            Expr::Map(m) => *m = self.map(m),
+            _ => {}
+        });
+    }
+
+    /// The map with its keys and values remapped, rebuilt as their hashes
+    /// change with their symbols
+    fn map(&mut self, m: &ValueMap) -> ValueMap {
+        m.iter()
+            .map(|(k, v)| {
+                let (mut k, mut v) = (k.clone(), v.clone());
+                self.value(&mut k);
+                self.value(&mut v);
+                (k, v)
+            })
+            .collect()
+    }
+
+    fn pattern(&mut self, p: &mut Pattern) {
+        match p {
+            Pattern::Intrinsic(Intrinsic::Host(sym))
+            | Pattern::Var(sym)
+            | Pattern::Call(sym)
+            | Pattern::Symbol(sym) => self.symbol(sym),
+            Pattern::Intrinsic(_) => {}
+            Pattern::Quote(p) => self.pattern(p),
+            Pattern::Compose(ps) => ps.iter_mut().for_each(|p| self.pattern(p)),
+        }
+    }
+
+    fn value(&mut self, v: &mut Value) {
+        match v {
+            Value::Call(sym) | Value::Symbol(sym) => self.symbol(sym),
+            Value::Quote(e) => self.expr(e),
+            #[cfg(feature = "bignum")]
+            Value::Nat(_) => {}
+            #[cfg(feature = "rational")]
+            Value::Ratio(_) => {}
+            Value::Pair(p) => {
+                self.value(&mut p.0);
+                self.value(&mut p.1);
+            }
+            Value::Map(m) => *m = self.map(m),
+            Value::Char(_) | Value::Str(_) => {}
+        }
+    }
+
+    fn warning(&mut self, warning: &mut Warning) {
+        match warning {
+            Warning::UndefinedCall { caller, callee } => {
+                self.symbol(caller);
+                self.symbol(callee);
+            }
+            Warning::ConfusableName { name, other } => {
+                self.symbol(name);
+                self.symbol(other);
+            }
+            Warning::UnguardedRecursion(sym)
+            | Warning::AlwaysUnderflows(sym)
+            | Warning::IntrinsicTypo { name: sym, .. } => self.symbol(sym),
+        }
+    }
+}
+
+impl Context {
+    pub fn interner_stats(&self) -> InternerStats {
+        InternerStats {
+            symbols: self.interner.len(),
+            bytes: self.interner.strings().map(str::len).sum(),
+        }
+    }
+
+    /// Rebuild the interner with only the symbols used by definitions,
+    /// rules, host intrinsics, `vs`, and `syms`, updating them all to the
+    /// new symbols. Returns the number of symbols dropped. An interner
+    /// shared with other contexts is left as it is.
+    pub fn compact_interner(&mut self, vs: &mut ValueStack, syms: &mut [Symbol]) -> usize {
+        if let Interner::Shared(_) = self.interner {
+            return 0;
+        }
+        let old = mem::take(&mut self.interner);
+        let mut remap = Remap {
+            old: &old,
+            new: Interner::default(),
+        };
+        self.fns = Arc::new(
+            Arc::unwrap_or_clone(mem::take(&mut self.fns))
+                .into_iter()
+                .map(|(mut sym, mut e)| {
+                    remap.symbol(&mut sym);
+                    remap.expr(&mut e);
+                    (sym, e)
+                })
+                .collect(),
+        );
+        self.exprs = Arc::new(
+            Arc::unwrap_or_clone(mem::take(&mut self.exprs))
+                .into_iter()
+                .map(|(mut e, mut sym)| {
+                    remap.expr(&mut e);
+                    remap.symbol(&mut sym);
+                    (e, sym)
+                })
+                .collect(),
+        );
+        self.namespaces = Arc::new(
+            Arc::unwrap_or_clone(mem::take(&mut self.namespaces))
+                .into_iter()
+                .map(|(mut sym, mut ns)| {
+                    remap.symbol(&mut sym);
+                    ns.fns = mem::take(&mut ns.fns)
+                        .into_iter()
+                        .map(|(mut sym, mut e)| {
+                            remap.symbol(&mut sym);
+                            remap.expr(&mut e);
+                            (sym, e)
+                        })
+                        .collect();
+                    (sym, ns)
+                })
+                .collect(),
+        );
+        for Rule(lhs, rhs) in Arc::make_mut(&mut self.rules).iter_mut() {
+            remap.pattern(lhs);
+            remap.pattern(rhs);
+        }
+        self.host_intrinsics = mem::take(&mut self.host_intrinsics)
+            .into_iter()
+            .map(|(mut sym, host)| {
+                remap.symbol(&mut sym);
+                (sym, host)
+            })
+            .collect();
+        self.warnings.iter_mut().for_each(|w| remap.warning(w));
+        vs.0.iter_mut().for_each(|v| remap.value(v));
+        syms.iter_mut().for_each(|sym| remap.symbol(sym));
+        self.interner = remap.new;
+        old.len() - self.interner.len()
+    }
+}
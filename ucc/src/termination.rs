@@ -0,0 +1,266 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A conservative termination check. A function terminates on every value
+//! stack if evaluating it abstractly, with the values it doesn't push
+//! itself unknown, never applies an unknown value, recurses, or uses
+//! `define!`, `evalq`, or a host intrinsic. A function loops if
+//! evaluating it on a small stack returns to a state it was in before.
+
+use crate::core::{Context, Expr, Intrinsic, Map, Symbol, Value, ValueStack};
+
+/// The most abstract steps taken while checking for termination
+const ABSTRACT_STEP_LIMIT: usize = 1000;
+/// The most small steps taken while looking for a loop, from each stack
+const LOOP_STEP_LIMIT: usize = 1000;
+/// The most values on the stacks loops are looked for from
+const LOOP_INPUTS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Termination {
+    /// Evaluation finishes, or stops at an error, on every value stack
+    Terminates,
+    /// Evaluation on `input` reaches the first state of `cycle` and
+    /// returns to it, through the rest
+    Loops {
+        input: ValueStack,
+        cycle: Vec<(ValueStack, Expr)>,
+    },
+    Unknown,
+}
+
+/// A value in abstract evaluation: a quotation whose items are known, or
+/// an unknown value
+#[derive(Clone)]
+enum Abstract {
+    Known(Vec<Item>),
+    Unknown,
+}
+
+/// An item of a known quotation
+#[derive(Clone)]
+enum Item {
+    Expr(Expr),
+    /// Push a value, as the quotation made by `quote` does
+    Push(Abstract),
+}
+
+struct AbstractEval<'a> {
+    ctx: &'a Context,
+    vs: Vec<Abstract>,
+    /// The functions being expanded, so recursion can be rejected
+    calls: Vec<Symbol>,
+    steps: usize,
+}
+
+impl AbstractEval<'_> {
+    fn pop(&mut self) -> Abstract {
+        self.vs.pop().unwrap_or(Abstract::Unknown)
+    }
+
+    /// Evaluate the items of `v`, or return `None` if it might not
+    /// terminate
+    fn apply(&mut self, v: Abstract) -> Option<()> {
+        match v {
+            Abstract::Known(items) => {
+                for item in items {
+                    match item {
+                        Item::Expr(e) => self.eval(&e)?,
+                        Item::Push(v) => self.vs.push(v),
+                    }
+                }
+                Some(())
+            }
+            Abstract::Unknown => None,
+        }
+    }
+
+    /// Evaluate `e`, or return `None` if it might not terminate
+    fn eval(&mut self, e: &Expr) -> Option<()> {
+        self.steps += 1;
+        if self.steps > ABSTRACT_STEP_LIMIT {
+            return None;
+        }
+        match e {
+            Expr::Intrinsic(Intrinsic::Apply) => {
+                let v = self.pop();
+                self.apply(v)?;
+            }
+            Expr::Intrinsic(Intrinsic::K) => {
+                let v = self.pop();
+                self.pop();
+                self.apply(v)?;
+            }
+            Expr::Intrinsic(intr) => self.stack_op(intr)?,
+            Expr::Call(sym) => {
+                if self.calls.contains(sym) || self.ctx.host_intrinsic(sym).is_some() {
+                    return None;
+                }
+                // Calling an undefined function stops evaluation
+                if let Some(e) = self.ctx.lookup_fn(sym) {
+                    self.calls.push(*sym);
+                    self.eval(e)?;
+                    self.calls.pop();
+                }
+            }
+            Expr::Symbol(sym) => {
+                let items = vec![Item::Expr(Expr::Call(*sym))];
+                self.vs.push(Abstract::Known(items));
+            }
+            Expr::Quote(e) => {
+                let items = vec![Item::Expr((**e).clone())];
+                self.vs.push(Abstract::Known(items));
+            }
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => self.vs.push(Abstract::Unknown),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => self.vs.push(Abstract::Unknown),
+            Expr::Pair(_) | Expr::Map(_) | Expr::Char(_) | Expr::Str(_) => {
+                self.vs.push(Abstract::Unknown)
+            }
+            Expr::Compose(es) => {
+                for e in es {
+                    self.eval(e)?;
+                }
+            }
+        }
+        Some(())
+    }
+    /// Evaluate an intrinsic that doesn't apply a value. Kept out of
+    /// `eval`, which recurses as deep as a value is applied, so that its
+    /// frames stay small.
+    #[inline(never)]
+    fn stack_op(&mut self, intr: &Intrinsic) -> Option<()> {
+        match intr {
+            Intrinsic::Swap => {
+                let (v2, v1) = (self.pop(), self.pop());
+                self.vs.push(v2);
+                self.vs.push(v1);
+            }
+            Intrinsic::Clone | Intrinsic::Dup => {
+                let v = self.pop();
+                self.vs.push(v.clone());
+                self.vs.push(v);
+            }
+            Intrinsic::Drop => {
+                self.pop();
+            }
+            Intrinsic::Quote => {
+                let v = self.pop();
+                self.vs.push(Abstract::Known(vec![Item::Push(v)]));
+            }
+            Intrinsic::Compose => {
+                let v = match (self.pop(), self.pop()) {
+                    (Abstract::Known(items2), Abstract::Known(mut items1)) => {
+                        items1.extend(items2);
+                        Abstract::Known(items1)
+                    }
+                    _ => Abstract::Unknown,
+                };
+                self.vs.push(v);
+            }
+            Intrinsic::Defined | Intrinsic::BodyOf => {
+                self.pop();
+                self.vs.push(Abstract::Unknown);
+            }
+            Intrinsic::Over => {
+                let (v2, v1) = (self.pop(), self.pop());
+                self.vs.push(v1.clone());
+                self.vs.push(v2);
+                self.vs.push(v1);
+            }
+            Intrinsic::Rot => {
+                let (v3, v2, v1) = (self.pop(), self.pop(), self.pop());
+                self.vs.push(v2);
+                self.vs.push(v3);
+                self.vs.push(v1);
+            }
+            Intrinsic::Cake => {
+                let (v2, v1) = (self.pop(), self.pop());
+                match v2 {
+                    Abstract::Known(items2) => {
+                        let mut items = vec![Item::Push(v1.clone())];
+                        items.extend(items2.iter().cloned());
+                        self.vs.push(Abstract::Known(items));
+                        let mut items = items2;
+                        items.push(Item::Push(v1));
+                        self.vs.push(Abstract::Known(items));
+                    }
+                    Abstract::Unknown => {
+                        self.vs.push(Abstract::Unknown);
+                        self.vs.push(Abstract::Unknown);
+                    }
+                }
+            }
+            Intrinsic::Define | Intrinsic::EvalQ | Intrinsic::Host(_) => return None,
+            Intrinsic::Apply | Intrinsic::K => unreachable!(),
+        }
+        Some(())
+    }
+}
+
+impl Context {
+    /// Check whether calling `sym` terminates on every value stack, or
+    /// `None` if `sym` is undefined.
+    pub fn terminates(&mut self, sym: Symbol) -> Option<Termination> {
+        self.lookup_fn(&sym)?;
+        // Rewrite rules can change what any expression does
+        if self.rules.is_empty() {
+            let mut eval = AbstractEval {
+                ctx: self,
+                vs: vec![],
+                calls: vec![],
+                steps: 0,
+            };
+            if eval.eval(&Expr::Call(sym)).is_some() {
+                return Some(Termination::Terminates);
+            }
+        }
+        let allow_define = self.allow_define;
+        self.allow_define = false;
+        let mut termination = Termination::Unknown;
+        for n in 0..=LOOP_INPUTS {
+            let input = ValueStack(
+                (0..n)
+                    .map(|i| {
+                        let name = ((b'a' + i as u8) as char).to_string();
+                        Value::Quote(Box::new(Expr::Call(Symbol(
+                            self.interner.get_or_intern(name),
+                        ))))
+                    })
+                    .collect(),
+            );
+            if let Some(cycle) = self.find_cycle(&input, Expr::Call(sym)) {
+                termination = Termination::Loops { input, cycle };
+                break;
+            }
+        }
+        self.allow_define = allow_define;
+        Some(termination)
+    }
+
+    /// Evaluate `e` on `vs` until a state repeats, and return the states
+    /// from its first occurrence to its repetition
+    fn find_cycle(&mut self, vs: &ValueStack, mut e: Expr) -> Option<Vec<(ValueStack, Expr)>> {
+        let mut vs = vs.clone();
+        let mut states = vec![];
+        let mut seen = Map::default();
+        self.consecutive_rewrites = 0;
+        for _ in 0..LOOP_STEP_LIMIT {
+            let state = (vs.clone(), e.clone(), self.consecutive_rewrites);
+            if let Some(&i) = seen.get(&state) {
+                states.push((vs, e));
+                return Some(states.split_off(i));
+            }
+            seen.insert(state, states.len());
+            states.push((vs.clone(), e.clone()));
+            if e == Expr::default() || self.small_step(&mut vs, &mut e).is_err() {
+                return None;
+            }
+        }
+        None
+    }
+}
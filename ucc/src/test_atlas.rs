@@ -0,0 +1,77 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::atlas::*;
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_atlas() {
+    let mut ctx = Context::default();
+    let mut spec = AtlasSpec::new(&mut ctx.interner, 2);
+    spec.vocabulary = vec![
+        Expr::Intrinsic(Intrinsic::Swap),
+        Expr::Intrinsic(Intrinsic::Apply),
+    ];
+    spec.stack = ValueStackParser::new()
+        .parse(&mut ctx.interner, "⟨[a] [swap]⟩")
+        .unwrap();
+    spec.fuel = 2;
+    let atlas = ctx.atlas(&spec);
+    assert_eq!(atlas.len(), 15);
+    let classes: Vec<(String, Vec<String>)> = atlas
+        .classes
+        .iter()
+        .map(|(outcome, exprs)| {
+            let outcome = match outcome {
+                Outcome::Halted(vs) => vs.resolve(&ctx.interner).to_string(),
+                outcome => format!("{:?}", outcome),
+            };
+            let exprs = exprs
+                .iter()
+                .map(|e| e.resolve(&ctx.interner).to_string())
+                .collect();
+            (outcome, exprs)
+        })
+        .collect();
+    let expected = [
+        ("⟨[swap] [a]⟩", &["swap"][..]),
+        (
+            "Failed",
+            &["apply", "apply swap", "apply apply", "apply []"],
+        ),
+        ("⟨[a] [swap] []⟩", &["[]"]),
+        ("⟨[a] [swap]⟩", &["swap swap", "[] apply"]),
+        ("OutOfFuel", &["swap apply"]),
+        ("⟨[swap] [a] []⟩", &["swap []"]),
+        ("⟨[a] [] [swap]⟩", &["[] swap"]),
+        ("⟨[a] [swap] [] []⟩", &["[] []"]),
+        ("⟨[a] [swap] [swap]⟩", &["[swap]"]),
+        ("⟨[a] [swap] [apply]⟩", &["[apply]"]),
+        ("⟨[a] [swap] [[]]⟩", &["[[]]"]),
+    ];
+    let expected: Vec<(String, Vec<String>)> = expected
+        .iter()
+        .map(|(outcome, exprs)| {
+            let exprs = exprs.iter().map(|e| e.to_string()).collect();
+            (outcome.to_string(), exprs)
+        })
+        .collect();
+    assert_eq!(classes, expected);
+
+    spec.size = 1;
+    spec.vocabulary.truncate(1);
+    assert_eq!(
+        ctx.atlas(&spec).to_json(&ctx.interner),
+        concat!(
+            "{\"stack\": \"⟨[a] [swap]⟩\", \"size\": 1, \"fuel\": 2, \"classes\": [\n",
+            "  {\"outcome\": \"halted\", \"stack\": \"⟨[swap] [a]⟩\", \"exprs\": [\"swap\"]},\n",
+            "  {\"outcome\": \"halted\", \"stack\": \"⟨[a] [swap] []⟩\", \"exprs\": [\"[]\"]}\n",
+            "]}\n",
+        )
+    );
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::basis::*;
+use crate::core::*;
+use crate::load::LoadError;
+use crate::test_util::eval;
+
+const BASES: [Basis; 3] = [Basis::Standard, Basis::Minimal, Basis::Forth];
+
+#[test]
+fn test_parse_basis() {
+    let mut ctx = Context::with_basis(Basis::Forth);
+    assert_eq!(ctx.basis(), Basis::Forth);
+    assert_eq!(
+        ctx.parse_expr("dup over rot").unwrap(),
+        Expr::Compose(vec![
+            Expr::Intrinsic(Intrinsic::Dup),
+            Expr::Intrinsic(Intrinsic::Over),
+            Expr::Intrinsic(Intrinsic::Rot),
+        ])
+    );
+    assert_eq!(
+        ctx.parse_expr("[clone]"),
+        Err(LoadError::Basis(BasisError::NotInBasis(Intrinsic::Clone)))
+    );
+    // Names of intrinsics outside the basis are calls
+    assert!(matches!(ctx.parse_expr("k").unwrap(), Expr::Call(_)));
+
+    let mut ctx = Context::with_basis(Basis::Minimal);
+    let fn_def = ctx.parse_fn_def("{fn i = [[]] cake k k}").unwrap();
+    assert_eq!(ctx.display(&fn_def).to_string(), "{fn i = [[]] cake k k}");
+    assert_eq!(
+        ctx.parse_value_stack("⟨[a] [swap]⟩"),
+        Err(LoadError::Basis(BasisError::NotInBasis(Intrinsic::Swap)))
+    );
+
+    let mut ctx = Context::default();
+    assert!(
+        matches!(ctx.parse_value("[dup]").unwrap(), Value::Quote(e) if matches!(*e, Expr::Call(_)))
+    );
+}
+
+/// Parse `src` with the intrinsics of every basis
+fn parse_any(ctx: &mut Context, src: &str) -> Expr {
+    let e = ctx.parse_expr(src).unwrap();
+    let interner = &ctx.interner;
+    e.fold(&mut |e| match e {
+        Expr::Call(sym) => match interner.resolve(&sym.0) {
+            "dup" => Expr::Intrinsic(Intrinsic::Dup),
+            "over" => Expr::Intrinsic(Intrinsic::Over),
+            "rot" => Expr::Intrinsic(Intrinsic::Rot),
+            "cake" => Expr::Intrinsic(Intrinsic::Cake),
+            "k" => Expr::Intrinsic(Intrinsic::K),
+            _ => e,
+        },
+        e => e,
+    })
+}
+
+#[test]
+fn test_convert() {
+    let cases = [
+        ("⟨['a] ['b]⟩", "swap", "⟨['b] ['a]⟩"),
+        ("⟨['a]⟩", "clone", "⟨['a] ['a]⟩"),
+        ("⟨['a] ['b]⟩", "drop", "⟨['a]⟩"),
+        ("⟨['a]⟩", "quote", "⟨[['a]]⟩"),
+        ("⟨['a] ['b]⟩", "compose apply", "⟨'a 'b⟩"),
+        ("⟨['a]⟩", "apply", "⟨'a⟩"),
+        ("⟨['a]⟩", "dup", "⟨['a] ['a]⟩"),
+        ("⟨['a] ['b]⟩", "over", "⟨['a] ['b] ['a]⟩"),
+        ("⟨['a] ['b] ['c]⟩", "rot", "⟨['b] ['c] ['a]⟩"),
+        ("⟨['a] ['b]⟩", "k", "⟨'b⟩"),
+        ("⟨['a] ['b]⟩", "cake apply rot apply", "⟨'b ['a] ['a] 'b⟩"),
+        ("⟨'a ['b]⟩", "cake k", "⟨'b 'a⟩"),
+    ];
+    let mut ctx = Context::default();
+    for (input, src, output) in cases {
+        let e = parse_any(&mut ctx, src);
+        let output = ctx.parse_value_stack(output).unwrap();
+        for basis in BASES {
+            let converted = basis.convert(&e).unwrap();
+            converted.visit(&mut |e| {
+                if let Expr::Intrinsic(intr) = e {
+                    assert!(basis.contains(intr), "{} in {:?}", src, basis);
+                }
+            });
+            let mut vs = ctx.parse_value_stack(input).unwrap();
+            eval(&mut ctx, &mut vs, converted).unwrap();
+            assert_eq!(vs, output, "{} in {:?}", src, basis);
+        }
+    }
+
+    let e = Expr::Intrinsic(Intrinsic::Defined);
+    assert_eq!(Basis::Forth.convert(&e), Ok(e.clone()));
+    assert_eq!(
+        Basis::Minimal.convert(&e),
+        Err(BasisError::Inexpressible(Intrinsic::Defined))
+    );
+}
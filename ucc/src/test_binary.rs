@@ -0,0 +1,126 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::binary::*;
+use crate::core::*;
+use crate::display::*;
+use crate::load::LoadError;
+use crate::uccb::INTRINSIC_NAMES;
+
+static SRC: &str = "\
+{fn false = [drop]}
+{fn true = [swap drop]}
+{fn or = clone apply}
+{fn name = 'or body-of}
+{fn empty = }
+";
+
+#[test]
+fn test_pack_unpack() {
+    let bytes = pack(SRC).unwrap();
+    assert!(bytes.starts_with(MAGIC));
+    assert_eq!(unpack(&bytes), Ok(SRC.to_owned()));
+}
+
+#[test]
+fn test_import_export() {
+    let bytes = pack(SRC).unwrap();
+    let mut ctx = Context::default();
+    let syms = ctx.import_binary(&bytes).unwrap();
+    let names: Vec<String> = syms.iter().map(|sym| sym.resolve(&ctx.interner)).collect();
+    assert_eq!(names, ["false", "true", "or", "name", "empty"]);
+    assert_eq!(ctx.export_binary(), Ok(bytes));
+
+    let mut ctx = Context::default();
+    let host = Symbol(ctx.interner.get_or_intern("host"));
+    let fn_def = FnDef(host, Expr::Intrinsic(Intrinsic::Host(host)));
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+}
+
+#[test]
+fn test_decode_error() {
+    let bytes = pack(SRC).unwrap();
+    let mut bad_version = bytes.clone();
+    bad_version[MAGIC.len()] = 0;
+    let mut newer_version = bytes.clone();
+    newer_version[MAGIC.len()] = VERSION + 1;
+    // A name, then a definition of it quoted more deeply than allowed
+    let mut too_deep = b"UCCB\x01\x01\x01a\x01\x00".to_vec();
+    too_deep.extend(vec![0x01; MAX_DEPTH]);
+    too_deep.push(0x02);
+    too_deep.push(0);
+    let cases = [
+        (&b"UCC"[..], BinaryError::BadMagic),
+        (&bytes[..bytes.len() - 1], BinaryError::UnexpectedEnd),
+        (
+            &b"UCCB\x01\x01\x01a\x01\x00\x0f"[..],
+            BinaryError::BadTag(0x0f),
+        ),
+        (&bad_version[..], BinaryError::UnsupportedVersion(0)),
+        (
+            &newer_version[..],
+            BinaryError::UnsupportedVersion(VERSION + 1),
+        ),
+        (&too_deep[..], BinaryError::TooDeep),
+        (&b"UCCB\x01\x00\x01\x00"[..], BinaryError::BadName(0)),
+        (
+            &b"UCCB\x01\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff"[..],
+            BinaryError::BadVarint,
+        ),
+    ];
+    for (bytes, expected) in cases {
+        let mut ctx = Context::default();
+        assert_eq!(
+            ctx.decode_fn_defs(bytes),
+            Err(expected.clone()),
+            "Failed on {:?}",
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_encode_too_deep() {
+    let mut ctx = Context::default();
+    let sym = Symbol(ctx.interner.get_or_intern("deep"));
+    let quoted = |depth| (0..depth).fold(Expr::Call(sym), |e, _| Expr::Quote(Box::new(e)));
+    let fn_def = FnDef(sym, quoted(MAX_DEPTH - 1));
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+    let fn_def = FnDef(sym, quoted(MAX_DEPTH));
+    assert_eq!(
+        ctx.encode_fn_defs(std::slice::from_ref(&fn_def)),
+        Err(BinaryError::TooDeep)
+    );
+    let src = format!(
+        "{{fn deep = {}a{}}}",
+        "[".repeat(MAX_DEPTH),
+        "]".repeat(MAX_DEPTH)
+    );
+    assert_eq!(pack(&src), Err(LoadError::Binary(BinaryError::TooDeep)));
+}
+
+#[test]
+fn test_precompiled_prelude() {
+    use crate::builtin::{FN_DEF_SRCS, PRELUDE_UCCB};
+    use crate::parse::FnDefParser;
+
+    let mut ctx = Context::default();
+    let fn_defs: Vec<FnDef> = FN_DEF_SRCS
+        .iter()
+        .map(|src| FnDefParser::new().parse(&mut ctx.interner, src).unwrap())
+        .collect();
+    assert_eq!(ctx.decode_fn_defs(PRELUDE_UCCB), Ok(fn_defs));
+}
+
+#[test]
+fn test_intrinsic_names() {
+    // `build.rs` encodes the prelude's intrinsics by these names
+    for (name, intrinsic) in INTRINSIC_NAMES.iter().zip(INTRINSICS.iter()) {
+        assert_eq!(*name, intrinsic.to_string());
+    }
+}
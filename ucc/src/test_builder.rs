@@ -0,0 +1,227 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::builder::*;
+use crate::interp::{Failure, Interp};
+use crate::limits::DefinitionLimits;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// A clock that moves on a millisecond each time it is read
+#[derive(Default)]
+struct Ticking(Cell<Duration>);
+
+impl Clock for Ticking {
+    fn now(&self) -> Duration {
+        let now = self.0.get();
+        self.0.set(now + Duration::from_millis(1));
+        now
+    }
+}
+
+#[derive(Clone, Default)]
+struct MemFiles(Rc<RefCell<BTreeMap<PathBuf, Vec<u8>>>>);
+
+impl Files for MemFiles {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.0
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.0
+            .borrow_mut()
+            .insert(path.to_owned(), contents.to_owned());
+        Ok(())
+    }
+}
+
+fn session(interp: &mut Interp, inputs: &[&str]) -> String {
+    let mut output = vec![];
+    for input in inputs {
+        interp.interp_start(input, &mut output).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut output).unwrap();
+        }
+    }
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_builder() {
+    let files = MemFiles::default();
+    files
+        .write(Path::new("lib.ucc"), b"{fn dup = clone}\n[a] dup\n")
+        .unwrap();
+    let mut interp = InterpBuilder::new()
+        .clock(Ticking::default())
+        .files(files.clone())
+        .prelude(Prelude::None)
+        .limits(DefinitionLimits {
+            fns: Some(1),
+            ..DefinitionLimits::default()
+        })
+        .settings_path(Some(PathBuf::from("ucc/settings.toml")))
+        .build();
+    let output = session(
+        &mut interp,
+        &[
+            ":list",
+            ":set show-steps off",
+            ":load \"lib.ucc\"",
+            "{fn dup2 = dup dup}",
+            ":bench dup drop",
+            ":load \"missing.ucc\"",
+            ":atlas 1 [drop] \"atlas.json\"",
+            ":export-latex [b] dup \"paper/dup.tex\"",
+            ":export-latex drop \"paper/drop.tex\"",
+            ":save-settings",
+            ":reset",
+            ":list",
+            ":bench [b]",
+        ],
+    );
+    assert_eq!(
+        output,
+        concat!(
+            "\n",
+            "Set `show-steps`.\n",
+            "Defined `dup`.\n",
+            "Warning: `dup` always underflows on an empty stack.\n",
+            "⇓ ⟨[a] [a]⟩ \n",
+            "TooManyFns { limit: 1 }\n",
+            "⇓ ⟨[a] [a]⟩ \n",
+            "3 steps in 1ms.\n",
+            "Io(\"no such file\")\n",
+            "Saved 2 expressions, with 2 outcomes, to atlas.json.\n",
+            "Exported 3 steps to paper/dup.tex.\n",
+            "TooFewValues { available: 0, expected: 1 }\n",
+            "Saved settings to ucc/settings.toml.\n",
+            "Reset.\n",
+            "\n",
+            "⇓ ⟨[b]⟩ \n",
+            "1 steps in 1ms.\n",
+        )
+    );
+    let files = files.0.borrow();
+    assert!(String::from_utf8_lossy(&files[Path::new("paper/dup.tex")])
+        .contains("\\uccrule{call-expand}~\\texttt{dup}"));
+    assert!(!files.contains_key(Path::new("paper/drop.tex")));
+    assert!(files[Path::new("atlas.json")].starts_with(b"{\"stack\": \"\xe2\x9f\xa8[a] [b] [c]"));
+    assert!(
+        String::from_utf8_lossy(&files[Path::new("ucc/settings.toml")])
+            .contains("show-steps = false")
+    );
+}
+
+#[test]
+fn test_exercise() {
+    let files = MemFiles::default();
+    let src = concat!(
+        "Define `nip`, which drops the value under the top one.\n",
+        "⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩\n",
+        "⟨[c] [a] [b]⟩ nip ⇓ ⟨[c] [b]⟩\n",
+    );
+    files.write(Path::new("nip.ucx"), src.as_bytes()).unwrap();
+    let mut interp = InterpBuilder::new()
+        .files(files)
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let output = session(
+        &mut interp,
+        &[
+            ":check-exercise",
+            ":exercise \"nip.ucx\"",
+            "{fn nip = drop}",
+            ":check-exercise",
+            "{fn nip = swap drop}",
+            ":check-exercise",
+        ],
+    );
+    assert_eq!(
+        output,
+        concat!(
+            "No exercise loaded; load one with :exercise.\n",
+            "Define `nip`, which drops the value under the top one.\n",
+            "(2 hidden cases; check them with :check-exercise.)\n",
+            "Defined `nip`.\n",
+            "Warning: `nip` always underflows on an empty stack.\n",
+            "Passed 0 of 2 hidden cases.\n",
+            "Redefined `nip`.\n",
+            "Warning: `nip` always underflows on an empty stack.\n",
+            "Passed all 2 hidden cases.\n",
+        )
+    );
+}
+
+#[test]
+fn test_load_markdown() {
+    let files = MemFiles::default();
+    let src = concat!(
+        "# Pairs\n\n",
+        "```ucc\n{fn pair = [quote] dip compose}\n```\n\n",
+        "```ucc\n[a] [b] swap\n```\n\n",
+        "```output\n⟨[b] [a]⟩\n```\n\n",
+        "```ucc\ndrop\n```\n\n",
+        "```output\n⟨⟩\n```\n\n",
+        "```ucc\nclone\n```\n",
+    );
+    files.write(Path::new("pairs.md"), src.as_bytes()).unwrap();
+    let mut interp = InterpBuilder::new()
+        .files(files)
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let output = session(&mut interp, &[":set show-steps off", ":load \"pairs.md\""]);
+    assert_eq!(
+        output,
+        concat!(
+            "Set `show-steps`.\n",
+            "Defined `pair`.\n",
+            "Warning: `pair` calls undefined `dip`.\n",
+            "⇓ ⟨[b] [a]⟩ \n",
+            "⇓ ⟨[b]⟩ \n",
+            "Expected ⟨⟩, but the stack is ⟨[b]⟩.\n",
+        )
+    );
+    assert_eq!(interp.failure(), Some(Failure::Assertion));
+}
+
+#[test]
+fn test_interp_run_for() {
+    let mut interp = InterpBuilder::new()
+        .clock(Ticking::default())
+        .prelude(Prelude::None)
+        .build();
+    let mut output = vec![];
+    // One step evaluates the expression, the next writes its result, and
+    // the last ends the command
+    interp
+        .interp_start("[a] [b] [c] swap drop swap drop", &mut output)
+        .unwrap();
+    let budget = Duration::from_secs(60);
+    assert_eq!(interp.interp_run_for(budget, &mut output).unwrap(), 3);
+    assert!(interp.is_done());
+    assert_eq!(interp.interp_run_for(budget, &mut output).unwrap(), 0);
+    assert!(String::from_utf8(output).unwrap().ends_with("⇓ ⟨[c]⟩ \n"));
+
+    // The clock moves on a millisecond each step
+    let mut output = vec![];
+    interp
+        .interp_start("[clone apply] clone apply", &mut output)
+        .unwrap();
+    let budget = Duration::from_millis(3);
+    assert_eq!(interp.interp_run_for(budget, &mut output).unwrap(), 3);
+    assert!(!interp.is_done());
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::bundle::*;
+use crate::interp::Interp;
+use crate::load::LoadError;
+use std::fs;
+
+#[test]
+fn test_parse_manifest() {
+    let manifest =
+        Manifest::parse("-- a bundle\nname = combs\nversion = 1.2\nsources = a.ucc  b.ucc\n");
+    assert_eq!(
+        manifest,
+        Ok(Manifest {
+            name: "combs".to_owned(),
+            version: "1.2".to_owned(),
+            sources: vec!["a.ucc".to_owned(), "b.ucc".to_owned()],
+        })
+    );
+    let cases = [
+        ("name = combs\nversion = 1.2", "missing `sources`"),
+        (
+            "name = combs\nversion 1.2",
+            "expected `key = value`: version 1.2",
+        ),
+        ("author = me", "unknown key `author`"),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(
+            Manifest::parse(src),
+            Err(LoadError::BadManifest(expected.to_owned())),
+            "Failed on {:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_use_bundle() {
+    let dir = std::env::temp_dir().join("ucc_test_use_bundle");
+    for (version, swap2) in [("1.0", "swap swap"), ("1.1", "swap2_impl")] {
+        let bundle_dir = dir.join(format!("combs@{}", version));
+        fs::create_dir_all(&bundle_dir).unwrap();
+        fs::write(
+            bundle_dir.join(MANIFEST_FILE),
+            format!("name = combs\nversion = {}\nsources = a.ucc", version),
+        )
+        .unwrap();
+        fs::write(
+            bundle_dir.join("a.ucc"),
+            format!("{{fn swap2_impl = swap swap}}\n{{fn swap2 = {}}}\n", swap2),
+        )
+        .unwrap();
+    }
+    let cases = [
+        (":use combs@1.0", "Loaded `combs@1.0`.\n"),
+        (
+            "[a] [b] combs.swap2",
+            "⟨⟩ [a] [b] combs.swap2\n⇓ ⟨[a] [b]⟩ \n",
+        ),
+        (":use combs@1.1", "Replaced `combs@1.0` with `combs@1.1`.\n"),
+        (":show combs.swap2", "{fn combs.swap2 = combs.swap2_impl}\n"),
+        (":list", "combs.swap2 combs.swap2_impl\n"),
+        (":copy combs.swap2 swap2", "Defined `swap2`.\n"),
+        (":show swap2", "{fn swap2 = combs.swap2_impl}\n"),
+        (
+            ":copy deep combs.swap2 swap2",
+            concat!(
+                "Defined `swap2.swap2_impl`.\n",
+                "Redefined `swap2`.\n",
+                "Warning: `swap2.swap2_impl` always underflows on an empty stack.\n",
+            ),
+        ),
+        (":show swap2", "{fn swap2 = swap2.swap2_impl}\n"),
+        (
+            ":show swap2.swap2_impl",
+            "{fn swap2.swap2_impl = swap swap}\n",
+        ),
+        (":use combs@2.0", "BundleNotFound(\"combs@2.0\")\n"),
+    ];
+    let mut interp = Interp::default();
+    interp.set_bundle_path(vec![dir.clone()]);
+    interp.interp_start(":clear", &mut vec![]).unwrap();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, expected_output, "Failed on {:?}", input);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
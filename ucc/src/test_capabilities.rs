@@ -0,0 +1,51 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::capabilities::*;
+
+#[test]
+fn test_capabilities() {
+    let capabilities = capabilities();
+    assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(capabilities.has("net"), cfg!(feature = "net"));
+    assert_eq!(capabilities.has("vm"), cfg!(feature = "vm"));
+    assert!(!capabilities.has("serde"));
+    assert!(capabilities
+        .features
+        .iter()
+        .all(|feature| FEATURES.contains(feature)));
+}
+
+#[test]
+fn test_display() {
+    let cases = [
+        (vec![], "ucc v0.1.0, with no optional features"),
+        (vec!["net"], "ucc v0.1.0, with features: net"),
+        (
+            vec!["plugins", "vm"],
+            "ucc v0.1.0, with features: plugins, vm",
+        ),
+    ];
+    for (features, expected) in cases {
+        let capabilities = Capabilities {
+            version: "0.1.0",
+            features,
+        };
+        assert_eq!(capabilities.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_to_json() {
+    let capabilities = Capabilities {
+        version: "0.1.0",
+        features: vec!["persistent-stack", "tracing"],
+    };
+    assert_eq!(
+        capabilities.to_json(),
+        r#"{"version": "0.1.0", "features": ["persistent-stack", "tracing"]}"#
+    );
+}
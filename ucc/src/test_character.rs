@@ -0,0 +1,90 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_char_intrinsics() {
+    let cases = [
+        ("⟨⟩ 'a' ⟶ ⟨'a'⟩", Ok(())),
+        ("⟨⟩ '\\n' ⟶ ⟨'\\n'⟩", Ok(())),
+        ("⟨⟩ 'λ' ⟶ ⟨'λ'⟩", Ok(())),
+        ("⟨'a' 'a'⟩ char.eq ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨'a' 'b'⟩ char.eq ⟶ ⟨[drop]⟩", Ok(())),
+        ("⟨'a' 'b'⟩ char.lt ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨'b' 'a'⟩ char.lt ⟶ ⟨[drop]⟩", Ok(())),
+        ("⟨'a' 'b'⟩ pair ⟶ ⟨('a', 'b')⟩", Ok(())),
+        ("⟨⟩ \"ab\" ⟶ ⟨\"ab\"⟩", Ok(())),
+        ("⟨\"ab\"⟩ chars ⟶ ⟨['a' 'b']⟩", Ok(())),
+        ("⟨\"a\"⟩ chars ⟶ ⟨['a']⟩", Ok(())),
+        ("⟨\"\"⟩ chars ⟶ ⟨[]⟩", Ok(())),
+        ("⟨\"ab\"⟩ uncons_str ⟶ ⟨'a' \"b\"⟩", Ok(())),
+        ("⟨\"λ\"⟩ uncons_str ⟶ ⟨'λ' \"\"⟩", Ok(())),
+        (
+            "⟨\"\"⟩ uncons_str ⟶ ⟨⟩ uncons_str",
+            Err(ResolvedEvalError::Host("empty string".to_owned())),
+        ),
+        (
+            "⟨'a'⟩ chars ⟶ ⟨⟩ chars",
+            Err(ResolvedEvalError::Host("expected a string".to_owned())),
+        ),
+        (
+            "⟨'a 'a'⟩ char.eq ⟶ ⟨⟩ char.eq",
+            Err(ResolvedEvalError::Host("expected a character".to_owned())),
+        ),
+        (
+            "⟨'a'⟩ apply ⟶ ⟨⟩ apply",
+            Err(ResolvedEvalError::ExpectedQuote),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+/// Each literal displays as one that parses back to the same character
+#[test]
+fn test_char_display() {
+    let mut ctx = Context::default();
+    for literal in ["'a'", "'\\n'", "'\\r'", "'\\t'", "'\\''", "'\\\\'", "' '"] {
+        let v = ctx.parse_value(literal).unwrap();
+        assert_eq!(ctx.display(&v).to_string(), literal);
+    }
+    assert_eq!(ctx.parse_value("'\\n'"), Ok(Value::Char('\n')));
+    assert!(matches!(ctx.parse_value("'a"), Ok(Value::Symbol(_))));
+    assert!(ctx.parse_value("'ab'").is_err());
+}
+
+#[test]
+fn test_char_binary() {
+    let mut ctx = Context::default();
+    let fn_def = ctx
+        .parse_fn_def("{fn newline = ['\\n' 'λ'] apply char.eq \"a[λ]\" chars}")
+        .unwrap();
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+
+    let a = ctx.parse_value("'a'").unwrap();
+    let b = ctx.parse_value("'b'").unwrap();
+    assert_ne!(a.stable_hash(&ctx.interner), b.stable_hash(&ctx.interner));
+    let ab = ctx.parse_value("\"ab\"").unwrap();
+    assert_eq!(ctx.display(&ab).to_string(), "\"ab\"");
+    assert_ne!(a.stable_hash(&ctx.interner), ab.stable_hash(&ctx.interner));
+}
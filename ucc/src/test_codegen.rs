@@ -0,0 +1,52 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::codegen::*;
+
+#[test]
+fn test_rust() {
+    let src = rust("{fn not = [false] [true] rotate3 apply}").unwrap();
+    let fns: Vec<_> = src
+        .lines()
+        .filter(|line| line.starts_with("/// `"))
+        .collect();
+    assert_eq!(
+        fns,
+        [
+            "/// `not`",
+            "/// `false`",
+            "/// `true`",
+            "/// `rotate3`",
+            "/// `core.quote2`"
+        ]
+    );
+    assert!(src.contains(
+        "/// `not`\n\
+         pub fn f0(s: &mut Vec<Value>) -> Result<(), Error> {\n    \
+             s.push(Value::Quote(Rc::new(vec![Op::Call(1)])));\n    \
+             s.push(Value::Quote(Rc::new(vec![Op::Call(2)])));\n    \
+             f3(s)?;\n    \
+             apply(s)?;\n    \
+             Ok(())\n\
+         }\n"
+    ));
+    assert!(src.contains("    (\"false\", Some(f1)),\n"));
+
+    let src = rust("{fn f = 'g apply undefined} {fn h = [[dup] drop]}").unwrap();
+    assert!(src.contains("    s.push(Value::Symbol(2));\n"));
+    assert!(src.contains("    call_index(3, s)?;\n"));
+    assert!(src.contains("    (\"g\", None),\n"));
+    assert!(src.contains("Op::Push(Value::Quote(Rc::new(vec![Op::Call(4)]))), Op::Drop"));
+}
+
+#[test]
+fn test_rust_errors() {
+    assert_eq!(
+        rust("{fn f = [[a] body-of]}"),
+        Err(CodegenError::Unsupported("body-of".to_owned()))
+    );
+    assert!(matches!(rust("{fn f ="), Err(CodegenError::Parse(_))));
+}
@@ -0,0 +1,58 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::config::*;
+use crate::settings::*;
+
+#[test]
+fn test_parse_config() {
+    let config = Config::parse(
+        "# comment\nshow-steps = \"off\"\nprelude = false\nload = [\"a.ucc\", \"b.ucc\"]\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            settings: vec![
+                ("show-steps".to_owned(), SettingValue::Str("off".to_owned())),
+                ("prelude".to_owned(), SettingValue::Bool(false)),
+            ],
+            load: vec!["a.ucc".to_owned(), "b.ucc".to_owned()],
+        }
+    );
+    let mut settings = Settings::default();
+    assert_eq!(config.apply_settings(&mut settings), Ok(()));
+    assert_eq!(settings.show_steps(), ShowSteps::Off);
+    assert!(!settings.prelude());
+}
+
+#[test]
+fn test_bad_config() {
+    let cases = [
+        (
+            "load = [1]",
+            Err(SettingError::BadSettingsFile(
+                "expected `load` to list strings".to_owned(),
+            )),
+        ),
+        (
+            "theme = \"sepia\"",
+            Err(SettingError::InvalidValue {
+                name: "theme".to_owned(),
+                value: "sepia".to_owned(),
+            }),
+        ),
+        (
+            "colour = 1",
+            Err(SettingError::Unknown("colour".to_owned())),
+        ),
+    ];
+    for (src, expected) in cases {
+        let result =
+            Config::parse(src).and_then(|config| config.apply_settings(&mut Settings::default()));
+        assert_eq!(result, expected, "Failed on {}", src);
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::confusables::*;
+
+#[test]
+fn test_normalize() {
+    // Cyrillic `о`, fullwidth `ｄ`, and a curly quote
+    assert_eq!(
+        normalize("{fn fоｄ = ’x} \"fо\" -- fо\nfо"),
+        (
+            "{fn fod = 'x} \"fо\" -- fо\nfo".to_owned(),
+            vec![('о', 'o'), ('ｄ', 'd'), ('’', '\'')]
+        )
+    );
+    assert_eq!(normalize("⟨⟩ swap"), ("⟨⟩ swap".to_owned(), vec![]));
+    assert_eq!(
+        normalize("'о' 'fо '\\'' fо"),
+        ("'о' 'fo '\\'' fo".to_owned(), vec![('о', 'o')])
+    );
+}
+
+#[test]
+fn test_skeleton() {
+    assert_eq!(skeleton("fool"), skeleton("foo1"));
+    assert_eq!(skeleton("modern"), skeleton("modem"));
+    assert_eq!(skeleton("x0"), skeleton("xO"));
+    assert_ne!(skeleton("foo"), skeleton("Foo"));
+}
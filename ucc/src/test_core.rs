@@ -8,6 +8,7 @@ use crate::builtin::FN_DEF_SRCS;
 use crate::core::*;
 use crate::display::*;
 use crate::parse::*;
+use crate::test_util::eval;
 
 #[test]
 fn test_small_step() {
@@ -18,6 +19,9 @@ fn test_small_step() {
         "⟨[e1]⟩ quote ⟶ ⟨[[e1]]⟩",
         "⟨[e1] [e2]⟩ compose ⟶ ⟨[e1 e2]⟩",
         "⟨[e1]⟩ apply ⟶ ⟨⟩ e1",
+        "⟨⟩ 'e1 ⟶ ⟨'e1⟩",
+        "⟨'e1⟩ quote ⟶ ⟨['e1]⟩",
+        "⟨'e1⟩ apply ⟶ ⟨⟩ e1",
     ];
     for case in cases {
         let mut ctx = Context::default();
@@ -63,7 +67,7 @@ fn test_compress() {
             let fn_def = FnDefParser::new()
                 .parse(&mut ctx.interner, fn_def_src)
                 .unwrap();
-            assert_eq!(ctx.define_fn(fn_def), None);
+            assert_eq!(ctx.define_fn(fn_def), Ok(None));
         }
         let mut input = ValueStackParser::new()
             .parse(&mut ctx.interner, input_src)
@@ -95,12 +99,29 @@ fn test_define_fn() {
         .unwrap();
     let e2 = ExprParser::new().parse(&mut ctx.interner, "e2").unwrap();
     assert_eq!(ctx.fns.get(&sym), None);
-    assert_eq!(ctx.define_fn(fn_def1), None);
+    assert_eq!(ctx.define_fn(fn_def1), Ok(None));
     assert_eq!(ctx.fns.get(&sym), Some(&e1));
-    assert_eq!(ctx.define_fn(fn_def2), Some(FnDef(sym, e1)));
+    assert_eq!(ctx.define_fn(fn_def2), Ok(Some(FnDef(sym, e1))));
     assert_eq!(ctx.fns.get(&sym), Some(&e2));
 }
 
+#[test]
+fn test_define_fn_order() {
+    let mut ctx = Context::default();
+    for fn_def_src in ["{fn c = }", "{fn a = }", "{fn b = }", "{fn a = drop}"] {
+        let fn_def = FnDefParser::new()
+            .parse(&mut ctx.interner, fn_def_src)
+            .unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let names: Vec<String> = ctx
+        .fns
+        .keys()
+        .map(|sym| sym.resolve(&ctx.interner))
+        .collect();
+    assert_eq!(names, ["c", "a", "b"]);
+}
+
 #[test]
 fn test_big_step() {
     const MAX_SMALL_STEPS: usize = 1000;
@@ -144,13 +165,17 @@ fn test_big_step() {
         "⟨[e] n1 n2⟩ mul apply ⇓ ⟨⟩ e e",
         "⟨[e] n2 n1⟩ mul apply ⇓ ⟨⟩ e e",
         "⟨[e] n2 n2⟩ mul apply ⇓ ⟨⟩ e e e e",
+        "⟨v1⟩ [drop] fix ⇓ ⟨v1⟩",
+        "⟨[drop]⟩ [swap apply] fix ⇓ ⟨⟩",
+        "⟨[drop] [apply] [apply]⟩ [swap apply] fix ⇓ ⟨⟩",
+        "⟨v1 [drop] [apply]⟩ [swap apply quote] fix ⇓ ⟨[[v1]]⟩",
     ];
     let mut ctx = Context::default();
     for fn_def_src in FN_DEF_SRCS.iter() {
         let fn_def = FnDefParser::new()
             .parse(&mut ctx.interner, fn_def_src)
             .unwrap();
-        assert_eq!(ctx.define_fn(fn_def), None);
+        assert_eq!(ctx.define_fn(fn_def), Ok(None));
     }
     for case in cases {
         println!("\n{}", case);
@@ -178,3 +203,142 @@ fn test_big_step() {
         }
     }
 }
+
+fn prelude_context() -> Context {
+    let mut ctx = Context::default();
+    for fn_def_src in FN_DEF_SRCS.iter() {
+        let fn_def = FnDefParser::new()
+            .parse(&mut ctx.interner, fn_def_src)
+            .unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    ctx
+}
+
+/// Recursion through `fix` runs in constant native stack, however deep it
+/// goes. Each `[apply]` on the stack recurses once more, and `[drop]` ends
+/// the recursion.
+#[test]
+fn test_fix_depth() {
+    const DEPTH: usize = 1_000_000;
+    let mut ctx = prelude_context();
+    let mut vs = ctx.parse_value_stack("⟨[drop]⟩").unwrap();
+    let recurse = ctx.parse_value("[apply]").unwrap();
+    vs.0.resize(DEPTH + 1, recurse);
+    let e = ctx.parse_expr("[swap apply] fix").unwrap();
+    eval(&mut ctx, &mut vs, e).unwrap();
+    assert_eq!(vs.len(), 0);
+
+    // Without a tail call, each level leaves a `quote` to run after the
+    // levels below it, which the evaluator keeps in the expression rather
+    // than on the native stack. This is quadratic in the depth, so it is
+    // tested less deep.
+    const NON_TAIL_DEPTH: usize = 1_000;
+    let mut vs = ctx.parse_value_stack("⟨v1 [drop]⟩").unwrap();
+    let recurse = ctx.parse_value("[apply]").unwrap();
+    vs.0.resize(NON_TAIL_DEPTH + 2, recurse);
+    let e = ctx.parse_expr("[swap apply quote] fix").unwrap();
+    eval(&mut ctx, &mut vs, e).unwrap();
+    let mut expected = ctx.parse_expr("v1").unwrap();
+    for _ in 0..NON_TAIL_DEPTH {
+        expected = Expr::Quote(Box::new(expected));
+    }
+    assert_eq!(vs, ValueStack::from(vec![Value::Quote(Box::new(expected))]));
+}
+
+/// Self-application loops forever in constant space
+#[test]
+fn test_self_application() {
+    let mut ctx = prelude_context();
+    for src in ["[clone apply] clone apply", "[apply] fix"] {
+        let mut vs = ValueStack::default();
+        let mut e = ctx.parse_expr(src).unwrap();
+        let mut max_len = 0;
+        for _ in 0..100_000 {
+            ctx.small_step(&mut vs, &mut e).unwrap();
+            max_len = max_len.max(vs.len() + e.pending_len());
+        }
+        assert!(max_len < 20, "Failed on {}", src);
+    }
+}
+
+#[test]
+fn test_reflection() {
+    let cases = [
+        ("⟨[foo]⟩ defined? ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨[bar]⟩ defined? ⟶ ⟨[drop]⟩", Ok(())),
+        ("⟨'foo⟩ defined? ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨'foo⟩ body-of ⟶ ⟨[clone swap]⟩", Ok(())),
+        ("⟨[foo]⟩ body-of ⟶ ⟨[clone swap]⟩", Ok(())),
+        (
+            "⟨[bar]⟩ body-of ⟶ ⟨⟩",
+            Err(ResolvedEvalError::UndefinedFn("bar".to_owned())),
+        ),
+        ("⟨[bar] [foo foo]⟩ define! ⟶ ⟨⟩", Ok(())),
+        (
+            "⟨[foo bar]⟩ defined? ⟶ ⟨⟩",
+            Err(ResolvedEvalError::ExpectedSymbol),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let fn_def = FnDefParser::new()
+            .parse(&mut ctx.interner, "{fn foo = clone swap}")
+            .unwrap();
+        ctx.define_fn(fn_def).unwrap();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        if result.is_ok() {
+            assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+            assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+        }
+    }
+
+    let mut ctx = Context::default();
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨[bar] [foo foo]⟩ define! ⟶ ⟨⟩")
+        .unwrap();
+    assert_eq!(ctx.small_step(&mut ssa.0, &mut ssa.1), Ok(()));
+    let bar = Symbol(ctx.interner.get("bar").unwrap());
+    let foo = Symbol(ctx.interner.get("foo").unwrap());
+    assert_eq!(
+        ctx.fns.get(&bar),
+        Some(&Expr::Compose(vec![Expr::Call(foo), Expr::Call(foo)]))
+    );
+    ctx.set_allow_define(false);
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨[bar] [foo]⟩ define! ⟶ ⟨⟩")
+        .unwrap();
+    assert_eq!(
+        ctx.small_step(&mut ssa.0, &mut ssa.1),
+        Err(EvalError::DefineDisabled)
+    );
+
+    // Calls of the quotation redefined on the stack keep their meaning
+    let mut ctx = Context::default();
+    let fn_def = FnDefParser::new()
+        .parse(&mut ctx.interner, "{fn x = [a]}")
+        .unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨x 'x [b]⟩ define! ⟶ ⟨[a]⟩")
+        .unwrap();
+    assert_eq!(ctx.small_step(&mut ssa.0, &mut ssa.1), Ok(()));
+    assert_eq!(ssa.0, ssa.2);
+    // Or, where the stack wasn't at hand, fail to unquote
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨x⟩ apply ⟶ ⟨⟩")
+        .unwrap();
+    assert_eq!(
+        ctx.small_step(&mut ssa.0, &mut ssa.1),
+        Err(EvalError::ExpectedQuote)
+    );
+}
@@ -0,0 +1,35 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::interp::Interp;
+
+#[test]
+fn test_coverage() {
+    let mut interp = Interp::default();
+    let coverage = interp.track_coverage();
+    let src = "{fn swap2 = [swap] apply}\n\
+               {fn rot = swap2 swap}\n\
+               {fn unused = drop}\n\
+               true false swap2 or\n\
+               true false true rot\n";
+    assert!(interp.run(src.as_bytes(), &mut vec![]).unwrap());
+    let report = interp.coverage_report(&coverage);
+    assert_eq!(
+        report.fns,
+        [
+            ("swap2".to_string(), 2),
+            ("rot".to_string(), 1),
+            ("unused".to_string(), 0),
+        ]
+    );
+    assert_eq!(
+        report.to_string(),
+        "Called 2 of 3 functions.\nNever called: unused\n"
+    );
+    assert!(report
+        .to_html()
+        .contains("<tr class=\"uncalled\"><td>unused</td><td>0</td></tr>"));
+}
@@ -0,0 +1,121 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::builtin::FN_DEF_SRCS;
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_diagnose() {
+    let cases = [
+        ("{fn foo = [swap] [drop] compose}", vec![]),
+        ("{fn foo = true false or}", vec![]),
+        ("{fn foo = [foo] apply}", vec![]),
+        (
+            "{fn foo = bar [bar baz]}",
+            vec!["`foo` calls undefined `bar`", "`foo` calls undefined `baz`"],
+        ),
+        (
+            "{fn foo = clone foo}",
+            vec![
+                "`foo` calls itself outside of any quote",
+                "`foo` always underflows on an empty stack",
+            ],
+        ),
+        (
+            "{fn foo = [clone] (drop foo)}",
+            vec!["`foo` calls itself outside of any quote"],
+        ),
+        (
+            "{fn foo = [clone] swap}",
+            vec!["`foo` always underflows on an empty stack"],
+        ),
+        ("{fn foo = true [clone] compose}", vec![]),
+        ("{fn foo = [clone] apply drop}", vec![]),
+    ];
+    for (src, expected) in cases {
+        let mut ctx = Context::default();
+        for fn_def_src in FN_DEF_SRCS.iter() {
+            let fn_def = FnDefParser::new()
+                .parse(&mut ctx.interner, fn_def_src)
+                .unwrap();
+            ctx.define_fn(fn_def).unwrap();
+        }
+        ctx.take_warnings();
+        let fn_def = FnDefParser::new().parse(&mut ctx.interner, src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+        let warnings: Vec<String> = ctx
+            .take_warnings()
+            .iter()
+            .map(|w| w.resolve(&ctx.interner).to_string())
+            .collect();
+        assert_eq!(warnings, expected, "Failed on {}", src);
+    }
+}
+
+#[test]
+fn test_intrinsic_typos() {
+    let cases = [
+        (
+            "swpa",
+            vec!["`swpa` may be a misspelling of `swap`"],
+            "swap",
+        ),
+        (
+            "[clon] dorp",
+            vec![
+                "`clon` may be a misspelling of `clone`",
+                "`dorp` may be a misspelling of `drop`",
+            ],
+            "[clone] drop",
+        ),
+        (
+            "defined body_of applys",
+            vec![
+                "`defined` may be a misspelling of `defined?`",
+                "`body_of` may be a misspelling of `body-of`",
+                "`applys` may be a misspelling of `apply`",
+            ],
+            "defined? body-of apply",
+        ),
+        (
+            "swap swpa swpa",
+            vec!["`swpa` may be a misspelling of `swap`"],
+            "swap swap swap",
+        ),
+        ("sw quotes2 true defined", vec![], "sw quotes2 true defined"),
+    ];
+    for (src, expected, fixed) in cases {
+        let mut ctx = Context::default();
+        for fn_def_src in FN_DEF_SRCS.iter() {
+            let fn_def = FnDefParser::new()
+                .parse(&mut ctx.interner, fn_def_src)
+                .unwrap();
+            ctx.define_fn(fn_def).unwrap();
+        }
+        let mut e = ExprParser::new().parse(&mut ctx.interner, src).unwrap();
+        let defining = [Symbol(ctx.interner.get_or_intern("defined"))];
+        let defining = if expected.is_empty() {
+            &defining[..]
+        } else {
+            &[]
+        };
+        let mut warnings = vec![];
+        ctx.intrinsic_typos(&mut e, defining, true, &mut warnings);
+        let warnings: Vec<String> = warnings
+            .iter()
+            .map(|w| w.resolve(&ctx.interner).to_string())
+            .collect();
+        assert_eq!(warnings, expected, "Failed on {}", src);
+        assert_eq!(
+            e.resolve(&ctx.interner).to_string(),
+            fixed,
+            "Failed on {}",
+            src
+        );
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+
+#[test]
+fn test_diff_exprs() {
+    let cases = [
+        ("clone swap apply", "clone apply", "clone [-swap-] apply"),
+        (
+            "clone apply",
+            "clone drop drop apply",
+            "clone {+drop drop+} apply",
+        ),
+        ("[a] swap", "[b] swap", "[-[a]-] {+[b]+} swap"),
+        ("", "drop", "{+drop+}"),
+        ("clone", "", "[-clone-]"),
+        ("a b c d", "b c d e", "[-a-] b c d {+e+}"),
+    ];
+    for (old, new, expected) in cases {
+        let mut ctx = Context::default();
+        let old = ctx.parse_fn_def(&format!("{{fn f = {}}}", old)).unwrap();
+        ctx.define_fn(old).unwrap();
+        let new = ctx.parse_fn_def(&format!("{{fn f = {}}}", new)).unwrap();
+        let diffs = ctx.diff_defs(vec![new]);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(
+            ctx.display(&diffs[0]).to_string(),
+            format!("~ {{fn f = {}}}", expected),
+            "Failed on {:?}",
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_diff_defs() {
+    let mut ctx = Context::default();
+    for src in ["{fn f = clone swap}", "{fn g = drop}", "{fn h = f g}"] {
+        let fn_def = ctx.parse_fn_def(src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let fn_defs = [
+        "{fn k = g}",
+        "{fn h = f g}",
+        "{fn f = clone}",
+        "{fn f = swap}",
+    ]
+    .iter()
+    .map(|src| ctx.parse_fn_def(src).unwrap())
+    .collect();
+    let diffs: Vec<String> = ctx
+        .diff_defs(fn_defs)
+        .iter()
+        .map(|diff| ctx.display(diff).to_string())
+        .collect();
+    assert_eq!(
+        diffs,
+        [
+            "+ {fn k = g}",
+            "~ {fn f = [-clone-] swap}",
+            "- {fn g = drop}"
+        ]
+    );
+}
@@ -0,0 +1,34 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::shared::SharedPrelude;
+use crate::vm::*;
+
+#[test]
+fn test_disassemble() {
+    let mut ctx = Context::with_prelude(&SharedPrelude::default());
+    for src in [
+        "{fn twice = clone quote2 [a] drop swap swap true}",
+        "{fn a = [b]}",
+        "{fn spin = a drop spin}",
+    ] {
+        let fn_def = ctx.parse_fn_def(src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let sym = Symbol(ctx.interner.get_or_intern("twice"));
+    let listing = Vm::default().disassemble(&ctx, sym).unwrap();
+    assert_eq!(listing, "twice:\n   0  clone\n   1  call quote2 @8\n   2  push #0\n   3  drop\n   4  swap\n   5  swap\n   6  call true\n   7  return\nquote2:\n   8  quote\n   9  swap\n  10  quote\n  11  swap\n  12  compose\n  13  return\nconstants:\n  #0  [a]\n");
+    let listing = Vm::optimized().disassemble(&ctx, sym).unwrap();
+    assert_eq!(listing, "twice:\n   0  clone\n   1  quote\n   2  swap\n   3  quote\n   4  swap\n   5  compose\n   6  check 2\n   7  push #1\n   8  return\nconstants:\n  #0  [a]\n  #1  true\n");
+    let sym = Symbol(ctx.interner.get_or_intern("spin"));
+    let listing = Vm::default().disassemble(&ctx, sym).unwrap();
+    // Recursive calls jump back, and calls of quotations push them
+    assert_eq!(
+        listing,
+        "spin:\n   0  call a\n   1  drop\n   2  call spin @0\n   3  return\n"
+    );
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::{Resolve, StackView};
+use crate::load::LoadError;
+use crate::settings::StackTop;
+
+#[test]
+fn test_parse_and_display() {
+    let mut ctx = Context::default();
+    let e = ctx.parse_expr("[swap] apply  foo").unwrap();
+    assert_eq!(ctx.display(&e).to_string(), "[swap] apply foo");
+    let v = ctx.parse_value("[clone [drop]]").unwrap();
+    assert_eq!(ctx.display(&v).to_string(), "[clone [drop]]");
+    let vs = ctx.parse_value_stack("⟨[a] [b]⟩").unwrap();
+    assert_eq!(ctx.display(&vs).to_string(), "⟨[a] [b]⟩");
+    let fn_def = ctx.parse_fn_def("{fn swap2 = [swap] apply}").unwrap();
+    assert_eq!(
+        ctx.display(&fn_def).to_string(),
+        "{fn swap2 = [swap] apply}"
+    );
+    assert!(matches!(ctx.parse_expr("[swap"), Err(LoadError::Parse(_))));
+}
+
+#[test]
+fn test_borrowed_display() {
+    let mut ctx = Context::default();
+    for src in ["", "a 'b [c (d e) f] (g) swap", "[[]] ((x y) z)"] {
+        let e = ctx.parse_expr(src).unwrap();
+        let resolved = e.resolve(&ctx.interner).to_string();
+        assert_eq!(ctx.display(&e).to_string(), resolved);
+    }
+    let vs = ctx.parse_value_stack("⟨[a] 'b c [d [e f]]⟩").unwrap();
+    for top in [StackTop::Right, StackTop::Left] {
+        for max in [None, Some(0), Some(2), Some(4), Some(9)] {
+            let resolved = vs.resolve(&ctx.interner).with_top(top).with_max(max);
+            let view = StackView::new(&vs, &ctx.interner)
+                .with_top(top)
+                .with_max(max);
+            assert_eq!(view.to_string(), resolved.to_string());
+        }
+    }
+    let view = StackView::new(&vs, &ctx.interner)
+        .with_top(StackTop::Left)
+        .with_max(Some(0));
+    assert_eq!(view.to_string(), "⟨top: …(4 more)…⟩");
+    let vs = ValueStack::default();
+    let view = StackView::new(&vs, &ctx.interner).with_top(StackTop::Left);
+    assert_eq!(view.to_string(), "⟨⟩");
+}
+
+#[test]
+fn test_display_host_intrinsic() {
+    let mut ctx = Context::default();
+    let sym = Symbol(ctx.interner.get_or_intern("host.f"));
+    let e = Expr::Intrinsic(Intrinsic::Host(sym));
+    assert_eq!(ctx.display(&e).to_string(), "host.f");
+    assert_eq!(e.resolve(&ctx.interner).to_string(), "host.f");
+    assert_eq!(Intrinsic::Host(sym).to_string(), "<host intrinsic>");
+}
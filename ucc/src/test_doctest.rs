@@ -0,0 +1,51 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::doctest::*;
+
+const SRC: &str = "\
+-- Drops the value under the top one:
+-- ⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩
+{fn nip = swap drop}
+
+-- Not documenting anything: ⟨[a]⟩ drop ⇓ ⟨⟩
+
+-- ⟨[a] [b]⟩ tuck ⇓ ⟨[b] [a]⟩
+--   ⟨[a]⟩ tuck ⇓ ⟨[a] [a]⟩
+{fn tuck = clone}
+";
+
+#[test]
+fn test_doc_tests() {
+    let tests = doc_tests(SRC);
+    let lines: Vec<_> = tests
+        .iter()
+        .map(|test| (test.fn_name.as_str(), test.line, test.assertion.as_str()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            ("nip", 2, "⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩"),
+            ("tuck", 7, "⟨[a] [b]⟩ tuck ⇓ ⟨[b] [a]⟩"),
+            ("tuck", 8, "⟨[a]⟩ tuck ⇓ ⟨[a] [a]⟩"),
+        ]
+    );
+}
+
+#[test]
+fn test_run_doc_tests() {
+    let passed: Vec<_> = run_doc_tests(SRC, 100)
+        .unwrap()
+        .into_iter()
+        .map(|(test, passed)| (test.line, passed))
+        .collect();
+    assert_eq!(passed, [(2, true), (7, false), (8, true)]);
+
+    assert!(matches!(
+        run_doc_tests("-- ⟨⟩ f ⇓ ⟨\n{fn f = }", 100),
+        Err(DocTestError::Parse(_))
+    ));
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::equiv::*;
+use crate::parse::*;
+
+#[test]
+fn test_distinguish() {
+    let mut ctx = Context::default();
+    let cases = [
+        ("[] apply", "", None),
+        ("swap swap swap", "swap", None),
+        ("clone apply", "clone apply", None),
+        // Stopping at different errors is observable
+        ("swap swap", "", Some("⟨⟩")),
+        ("drop", "swap drop", Some("⟨⟩")),
+        ("[swap swap]", "[]", Some("⟨⟩ 0 ⟨⟩")),
+        ("swap drop", "swap swap drop", Some("⟨[a] [b]⟩ 0 ⟨⟩")),
+        ("[clone]", "[clone clone]", Some("⟨⟩ 0 ⟨[a]⟩")),
+    ];
+    for (e1, e2, expected) in cases {
+        let e1 = ExprParser::new().parse(&mut ctx.interner, e1).unwrap();
+        let e2 = ExprParser::new().parse(&mut ctx.interner, e2).unwrap();
+        let limits = EquivLimits {
+            depth: 2,
+            ..EquivLimits::default()
+        };
+        let distinction = ctx.distinguish(&e1, &e2, limits).map(|mut d| {
+            let mut path = d.stack.resolve(&ctx.interner).to_string();
+            while let Some((i, inner)) = d.inner {
+                path.push_str(&format!(" {} {}", i, inner.stack.resolve(&ctx.interner)));
+                d = *inner;
+            }
+            path
+        });
+        assert_eq!(distinction.as_deref(), expected, "{:?} {:?}", e1, e2);
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::basis::BasisError;
+use crate::core::*;
+use crate::error::*;
+use crate::limits::{DefinitionLimits, LimitError};
+use crate::load::LoadError;
+use crate::transaction::Validation;
+use std::error::Error as _;
+
+#[test]
+fn test_eval_error() {
+    let mut ctx = Context::default();
+    let f = Symbol(ctx.interner.get_or_intern("f"));
+    let err = ctx.eval_error(&EvalError::UndefinedFn(f));
+    assert_eq!(err.to_string(), "`f` is not defined");
+    assert!(err.source().unwrap().source().is_none());
+
+    let err = EvalError::Sandbox(Box::new(EvalError::TooFewValues {
+        available: 0,
+        expected: 1,
+    }));
+    assert_eq!(err.to_string(), "the program given to `evalq` failed");
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "too few values: expected 1, but there are 0"
+    );
+    let err = ctx.eval_error(&EvalError::Sandbox(Box::new(EvalError::UndefinedFn(f))));
+    // The chain, as `anyhow` prints it
+    let mut chain = vec![err.to_string()];
+    let mut source = err.source();
+    while let Some(err) = source {
+        chain.push(err.to_string());
+        source = err.source();
+    }
+    assert_eq!(
+        chain,
+        [
+            "the program given to `evalq` failed",
+            "the program given to `evalq` failed",
+            "`f` is not defined",
+        ]
+    );
+}
+
+#[test]
+fn test_question_mark() {
+    fn define(ctx: &mut Context, src: &str) -> Result<(), Error> {
+        let fn_def = ctx.parse_fn_def(src)?;
+        ctx.define_fn(fn_def)?;
+        Ok(())
+    }
+    let mut ctx = Context::default();
+    ctx.set_definition_limits(DefinitionLimits {
+        body_size: Some(2),
+        ..DefinitionLimits::default()
+    });
+    assert!(matches!(
+        define(&mut ctx, "{fn f ="),
+        Err(Error::Load(LoadError::Parse(_)))
+    ));
+    let err = define(&mut ctx, "{fn f = [a] [b]}").unwrap_err();
+    assert_eq!(
+        err,
+        Error::Limit(LimitError::BodyTooLarge { size: 4, limit: 2 })
+    );
+    assert_eq!(err.to_string(), "body has 4 nodes, over the limit of 2");
+}
+
+#[test]
+fn test_define_error() {
+    let mut ctx = Context::default();
+    let fn_defs = vec![
+        ctx.parse_fn_def("{fn f = g}").unwrap(),
+        ctx.parse_fn_def("{fn h = [h] drop}").unwrap(),
+    ];
+    let err = ctx.define_all(fn_defs, Validation::Strict).unwrap_err();
+    let err = ctx.define_error(&err);
+    assert_eq!(
+        err.to_string(),
+        "no definitions were made: `f` calls undefined `g`"
+    );
+}
+
+#[test]
+fn test_load_error() {
+    let err = LoadError::Basis(BasisError::NotInBasis(Intrinsic::Swap));
+    assert_eq!(err.to_string(), "source uses intrinsics outside the basis");
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "`swap` isn't in the basis"
+    );
+}
@@ -0,0 +1,88 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_evalq() {
+    let cases = [
+        ("⟨['t [swap drop]] [[a] [b] t]⟩ evalq ⟶ ⟨[[b]]⟩", Ok(())),
+        ("⟨[] [[a] [b]]⟩ evalq ⟶ ⟨[[a] [b]]⟩", Ok(())),
+        ("⟨[] []⟩ evalq ⟶ ⟨[]⟩", Ok(())),
+        // The program's definitions shadow the context's
+        ("⟨['foo [drop]] [[a] foo]⟩ evalq ⟶ ⟨[]⟩", Ok(())),
+        // Calls of quotations defined for the program leave as quotations
+        ("⟨['v [[x]]] [v]⟩ evalq ⟶ ⟨[[x]]⟩", Ok(())),
+        ("⟨[] ['bar [[x]] define! bar]⟩ evalq ⟶ ⟨[[x]]⟩", Ok(())),
+        ("⟨[] [[] [[a]] evalq]⟩ evalq ⟶ ⟨[[[a]]]⟩", Ok(())),
+        (
+            "⟨[] [[a] foo]⟩ evalq ⟶ ⟨⟩",
+            Err(ResolvedEvalError::Sandbox(Box::new(
+                ResolvedEvalError::UndefinedFn("foo".to_owned()),
+            ))),
+        ),
+        (
+            "⟨['f] [a]⟩ evalq ⟶ ⟨⟩",
+            Err(ResolvedEvalError::ExpectedDefinitions),
+        ),
+        (
+            "⟨[[f] [a]] []⟩ evalq ⟶ ⟨⟩",
+            Err(ResolvedEvalError::ExpectedDefinitions),
+        ),
+        (
+            "⟨[] [[clone apply] clone apply]⟩ evalq ⟶ ⟨⟩",
+            Err(ResolvedEvalError::OutOfFuel),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let fn_def = ctx.parse_fn_def("{fn foo = clone swap}").unwrap();
+        ctx.define_fn(fn_def).unwrap();
+        let warnings = ctx.warnings.len();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let before = ssa.0.clone();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        if result.is_ok() {
+            assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+            assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+        } else {
+            // The values are left, so the error is shown with what caused it
+            assert_eq!(ssa.0, before, "Failed on {}", case);
+        }
+        // The context is as it was
+        assert_eq!(ctx.fns.len(), 1, "Failed on {}", case);
+        assert_eq!(ctx.warnings.len(), warnings, "Failed on {}", case);
+    }
+}
+
+/// The context's rules don't apply to the program
+#[test]
+fn test_evalq_rules() {
+    let mut ctx = Context::default();
+    let rule = RuleParser::new()
+        .parse(&mut ctx.interner, "{rule a => b}")
+        .unwrap();
+    ctx.define_rule(rule).unwrap();
+    let mut vs = ctx.parse_value_stack("⟨[] [a]⟩").unwrap();
+    let mut e = Expr::Intrinsic(Intrinsic::EvalQ);
+    assert_eq!(
+        ctx.small_step(&mut vs, &mut e).resolve(&ctx.interner),
+        Err(ResolvedEvalError::Sandbox(Box::new(
+            ResolvedEvalError::UndefinedFn("a".to_owned())
+        )))
+    );
+    assert_eq!(ctx.rules.len(), 1);
+}
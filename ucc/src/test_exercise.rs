@@ -0,0 +1,50 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::Context;
+use crate::exercise::*;
+
+const SRC: &str = "\
+-- nip.ucx, for week 1
+Define `nip`, which drops the value
+under the top one.
+
+⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩
+  ⟨[c] [a] [b]⟩ nip ⇓ ⟨[c] [b]⟩
+";
+
+#[test]
+fn test_parse_exercise() {
+    assert_eq!(
+        parse_exercise(SRC),
+        Exercise {
+            prompt: "Define `nip`, which drops the value\nunder the top one.".to_owned(),
+            cases: vec![
+                "⟨[a] [b]⟩ nip ⇓ ⟨[b]⟩".to_owned(),
+                "⟨[c] [a] [b]⟩ nip ⇓ ⟨[c] [b]⟩".to_owned(),
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_check_exercise() {
+    let exercise = parse_exercise(SRC);
+    let mut ctx = Context::default();
+    assert_eq!(ctx.check_exercise(&exercise), Ok(0));
+    let fn_def = ctx.parse_fn_def("{fn nip = drop}").unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    assert_eq!(ctx.check_exercise(&exercise), Ok(0));
+    let fn_def = ctx.parse_fn_def("{fn nip = swap drop}").unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    assert_eq!(ctx.check_exercise(&exercise), Ok(2));
+
+    let exercise = parse_exercise("⟨[a]⟩ nip ⇓ ⟨\n");
+    assert_eq!(
+        ctx.check_exercise(&exercise),
+        Err(ExerciseError::BadCase(1))
+    );
+}
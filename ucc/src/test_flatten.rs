@@ -0,0 +1,103 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::flatten::*;
+use crate::shared::SharedPrelude;
+
+#[test]
+fn test_simplify() {
+    let cases = [
+        ("[a] drop", ""),
+        ("[a] 'b swap", "'b [a]"),
+        ("'a quote", "['a]"),
+        ("[a] [b] compose", "[a b]"),
+        ("[a] [drop] apply", ""),
+        ("[[a] [b] swap] apply", "[b] [a]"),
+        ("x [[a] drop] compose", "x [] compose"),
+        ("[[x] drop y]", "[y]"),
+        // Not known from the values alone
+        ("drop", "drop"),
+        ("a swap", "a swap"),
+        ("'a apply", "'a apply"),
+        ("[a] clone", "[a] clone"),
+    ];
+    let mut ctx = Context::default();
+    for (e, expected) in cases {
+        let e = ctx.parse_expr(e).unwrap();
+        assert_eq!(
+            ctx.display(&simplify(&e)).to_string(),
+            expected,
+            "Failed on {}",
+            ctx.display(&e)
+        );
+    }
+    // Intrinsics only in other bases
+    let cases = [
+        ("[a] [b] [c]", Intrinsic::Rot, "[b] [c] [a]"),
+        ("[a] [b]", Intrinsic::K, "b"),
+    ];
+    for (e, i, expected) in cases {
+        let mut e = ctx.parse_expr(e).unwrap();
+        if let Expr::Compose(es) = &mut e {
+            es.push(Expr::Intrinsic(i));
+        }
+        let expected = ctx.parse_expr(expected).unwrap();
+        assert_eq!(simplify(&e), expected, "Failed on {:?}", i);
+    }
+}
+
+#[test]
+fn test_flatten() {
+    let cases = [
+        ("true true swap drop", Ok("[swap drop]")),
+        ("[a] false apply", Ok("")),
+        ("{fn f = g}", Ok("g")),
+        // Expanded before the quotation is dropped
+        ("{fn g = [f] drop}", Err(vec!["g", "f", "g"])),
+        ("f", Err(vec!["f", "g", "f"])),
+        ("{fn h = [h] drop}", Err(vec!["h", "h"])),
+        ("undefined true", Ok("undefined [swap drop]")),
+    ];
+    let mut ctx = Context::with_prelude(&SharedPrelude::default());
+    for (src, expected) in cases {
+        let e = if src.starts_with('{') {
+            let fn_def = ctx.parse_fn_def(src).unwrap();
+            let sym = fn_def.0;
+            ctx.define_fn(fn_def).unwrap();
+            Expr::Call(sym)
+        } else {
+            ctx.parse_expr(src).unwrap()
+        };
+        let expected = expected
+            .map(|flattened| ctx.parse_expr(flattened).unwrap())
+            .map_err(|cycle| {
+                FlattenError::Cycle(
+                    cycle
+                        .into_iter()
+                        .map(|name| Symbol(ctx.interner.get_or_intern(name)))
+                        .collect(),
+                )
+            });
+        assert_eq!(ctx.flatten(&e), expected, "Failed on {}", src);
+    }
+}
+
+#[test]
+fn test_flatten_too_large() {
+    let mut ctx = Context::default();
+    // Each twice as large as the last
+    for i in 1..20 {
+        let src = format!("{{fn f{} = f{} f{}}}", i, i - 1, i - 1);
+        let fn_def = ctx.parse_fn_def(&src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let e = ctx.parse_expr("f19").unwrap();
+    assert_eq!(
+        ctx.flatten(&e),
+        Err(FlattenError::TooLarge(FLATTEN_MAX_NODES))
+    );
+}
@@ -0,0 +1,46 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::test_util::eval;
+use std::sync::Arc;
+
+/// Evaluate `src` to completion on an empty stack, showing the result
+fn eval_shown(ctx: &mut Context, src: &str) -> String {
+    let mut vs = ValueStack::default();
+    let e = ctx.parse_expr(src).unwrap();
+    match eval(ctx, &mut vs, e) {
+        Ok(()) => ctx.display(&vs).to_string(),
+        Err(err) => format!("{:?}", err.resolve(&ctx.interner)),
+    }
+}
+
+#[test]
+fn test_fork() {
+    let mut ctx = Context::default();
+    for src in ["{fn f = [a]}", "{fn g = f f}"] {
+        let fn_def = ctx.parse_fn_def(src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let mut fork = ctx.fork();
+    // Nothing is copied until it is changed
+    assert!(Arc::ptr_eq(&ctx.fns, &fork.fns));
+    assert!(Arc::ptr_eq(&ctx.exprs, &fork.exprs));
+    assert_eq!(eval_shown(&mut fork, "g"), "⟨f f⟩");
+
+    let fn_def = fork.parse_fn_def("{fn f = [b]}").unwrap();
+    fork.define_fn(fn_def).unwrap();
+    assert!(!Arc::ptr_eq(&ctx.fns, &fork.fns));
+    assert_eq!(eval_shown(&mut fork, "g"), "⟨f f⟩");
+    assert_eq!(eval_shown(&mut fork, "f apply"), "UndefinedFn(\"b\")");
+    assert_eq!(eval_shown(&mut ctx, "f apply"), "UndefinedFn(\"a\")");
+
+    let fn_def = ctx.parse_fn_def("{fn h = g}").unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    assert_eq!(eval_shown(&mut fork, "h"), "UndefinedFn(\"h\")");
+    assert_eq!(eval_shown(&mut ctx, "h"), "⟨f f⟩");
+}
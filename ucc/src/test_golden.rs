@@ -0,0 +1,85 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::golden::*;
+use std::fs;
+
+#[test]
+fn test_parse() {
+    let text = concat!(
+        "Swapping\n",
+        "\n",
+        ">>> [a] [b] swap\n",
+        "⇓ ⟨[b] [a]⟩ \n",
+        ">>> :list\n",
+        ">>> [a]\n",
+        "⇓ ⟨[a]⟩ \n",
+    );
+    let transcript = Transcript::parse(text);
+    assert_eq!(transcript.header, "Swapping\n\n");
+    assert_eq!(
+        transcript.entries,
+        vec![
+            ("[a] [b] swap".to_owned(), "⇓ ⟨[b] [a]⟩ \n".to_owned()),
+            (":list".to_owned(), "".to_owned()),
+            ("[a]".to_owned(), "⇓ ⟨[a]⟩ \n".to_owned()),
+        ]
+    );
+    assert_eq!(transcript.to_string(), text);
+}
+
+#[test]
+fn test_record() {
+    let transcript = Transcript::record(
+        "",
+        &mut interp(),
+        vec!["{fn f = swap}", "[a] [b] f", ":bench [c]"],
+    );
+    assert_eq!(
+        transcript.to_string(),
+        concat!(
+            ">>> {fn f = swap}\n",
+            "Defined `f`.\n",
+            "Warning: `f` always underflows on an empty stack.\n",
+            ">>> [a] [b] f\n",
+            "⟨⟩ [a] [b] f\n",
+            "⇓ ⟨[b] [a]⟩ \n",
+            ">>> :bench [c]\n",
+            "⇓ ⟨[b] [a] [c]⟩ \n",
+            "1 steps in 0ns.\n",
+        )
+    );
+}
+
+#[test]
+fn test_check() {
+    let path = std::env::temp_dir().join(format!("ucc-golden-{}.txt", std::process::id()));
+    fs::write(&path, "A test\n>>> [a] clone\nwrong\n>>> [b]\n").unwrap();
+    assert_eq!(
+        check(&path, false),
+        Err(GoldenError::Mismatch {
+            input: "[a] clone".to_owned(),
+            expected: "wrong\n".to_owned(),
+            actual: "⟨⟩ [a] clone\n⇓ ⟨[a] [a]⟩ \n".to_owned(),
+        })
+    );
+    assert_eq!(check(&path, true), Ok(()));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        concat!(
+            "A test\n",
+            ">>> [a] clone\n",
+            "⟨⟩ [a] clone\n",
+            "⇓ ⟨[a] [a]⟩ \n",
+            ">>> [b]\n",
+            "⟨[a] [a]⟩ [b]\n",
+            "⇓ ⟨[a] [a] [b]⟩ \n",
+        )
+    );
+    assert_eq!(check(&path, false), Ok(()));
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(check(&path, false), Err(GoldenError::Io(_))));
+}
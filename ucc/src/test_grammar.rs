@@ -0,0 +1,46 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::grammar::*;
+
+#[test]
+fn test_ebnf() {
+    for rule in [
+        "FnDef ::= \"{\" \"fn\" Symbol \"=\" Expr \"}\"\n",
+        "ValueStack ::= \"⟨\" Value* \"⟩\"\n",
+        "InterpItems ::= (Expr Def)* Expr\n",
+        "Def ::= FnDef\n    | Rule\n",
+        "      | \"(\" Value \",\" Value \")\"\n",
+        "STRING ::= '\"' [^\"]* '\"'\n",
+        "IDENT ::= [_a-zA-Z] [_a-zA-Z0-9]* (\".\" [_a-zA-Z] [_a-zA-Z0-9]*)*\n",
+        "CHAR ::= \"'\" ([^'\\#xA#xD] | \"\\\" [nrt'\\]) \"'\"\n",
+    ] {
+        assert!(EBNF.contains(rule), "{}", rule);
+    }
+    // Tokens matching fixed text are written as it, not defined
+    assert!(EBNF.contains("| \":plugin\" \"load\" StringLit\n"));
+    assert!(!EBNF.contains("LBRACKET"));
+}
+
+#[test]
+fn test_tree_sitter() {
+    // The tokens are filled in from the parser's
+    for rule in [
+        "fn_def: $ => seq('{', 'fn', field('name', $.identifier), '=', repeat($._expr), '}'),\n",
+        "intrinsic: $ => choice('swap', 'clone', 'drop', 'quote', 'compose', 'apply', \
+         'defined?', 'body-of', 'define!', 'evalq'),\n",
+        "identifier: $ => /[_a-zA-Z][_a-zA-Z0-9]*(\\.[_a-zA-Z][_a-zA-Z0-9]*)*/,\n",
+        "comment: $ => /--[^\\n\\r]*[\\n\\r]*/,\n",
+        "pair: $ => seq('(', $._value, ',', $._value, ')'),\n",
+        "map_entry: $ => seq($._value, '=>', $._value),\n",
+        "char: $ => /'([^'\\\\\\n\\r]|\\\\[nrt'\\\\])'/,\n",
+        "string: $ => /\"[^\"]*\"/,\n",
+    ] {
+        assert!(TREE_SITTER_GRAMMAR.contains(rule), "{}", rule);
+    }
+    assert!(!TREE_SITTER_GRAMMAR.contains('%'));
+    assert!(TREE_SITTER_HIGHLIGHTS.contains("[\"fn\" \"rule\" \"local\" \"in\"] @keyword\n"));
+}
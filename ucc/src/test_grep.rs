@@ -0,0 +1,86 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::grep::*;
+use crate::interp::InterpCommand;
+use crate::parse::InterpCommandParser;
+use crate::rewrite::Pattern;
+
+fn parse_pattern(ctx: &mut Context, src: &str) -> Pattern {
+    match InterpCommandParser::new().parse(&mut ctx.interner, &format!(":grep {}", src)) {
+        Ok(InterpCommand::Grep(GrepQuery::Pattern(pattern))) => pattern,
+        parsed => panic!("expected a pattern, but parsed {:?}", parsed),
+    }
+}
+
+#[test]
+fn test_find_pattern() {
+    let cases = [
+        ("clone apply", "[a] clone apply", Some((vec![], 1..3))),
+        ("$x $x", "a b b", Some((vec![], 1..3))),
+        ("[$f] apply", "a [b [c] apply]", Some((vec![1], 1..3))),
+        ("drop", "a [(b drop) c]", Some((vec![1, 0], 1..2))),
+        ("clone", "[a] drop", None),
+        ("", "a", None),
+    ];
+    for (pattern, e, expected) in cases {
+        let mut ctx = Context::default();
+        let pattern = parse_pattern(&mut ctx, pattern);
+        let e = ctx.parse_expr(e).unwrap();
+        let expected = expected.map(|(path, items)| Fragment { path, items });
+        assert_eq!(
+            find_pattern(&e, &pattern),
+            expected,
+            "Failed on {:?}",
+            pattern
+        );
+    }
+}
+
+#[test]
+fn test_grep() {
+    let mut ctx = Context::default();
+    for src in [
+        "{fn twice = clone compose apply}",
+        "{fn apply2 = [apply] (clone apply)}",
+        "{fn thrice = clone clone compose compose apply}",
+    ] {
+        let fn_def = ctx.parse_fn_def(src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let pattern = parse_pattern(&mut ctx, "clone apply");
+    let lines: Vec<_> = ctx
+        .grep(&GrepQuery::Pattern(pattern))
+        .into_iter()
+        .map(|m| (m.line.clone(), m.line[m.highlight.clone()].to_owned()))
+        .collect();
+    assert_eq!(
+        lines,
+        [(
+            "{fn apply2 = [apply] (clone apply)}".to_owned(),
+            "clone apply".to_owned()
+        )]
+    );
+    let lines: Vec<_> = ctx
+        .grep(&GrepQuery::Name("ice".to_owned()))
+        .into_iter()
+        .map(|m| (m.line.clone(), m.line[m.highlight.clone()].to_owned()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (
+                "{fn twice = clone compose apply}".to_owned(),
+                "ice".to_owned()
+            ),
+            (
+                "{fn thrice = clone clone compose compose apply}".to_owned(),
+                "ice".to_owned()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,93 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::hamt::*;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+#[test]
+fn test_hamt() {
+    let mut map: Hamt<u32, u32> = (0..1000).map(|i| (i, i * i)).collect();
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.get(&30), Some(&900));
+    assert_eq!(map.get(&1000), None);
+    assert_eq!(map.insert(30, 0), Some(900));
+    assert_eq!(map.get(&30), Some(&0));
+    assert_eq!(map.remove(&30), Some(0));
+    assert_eq!(map.remove(&30), None);
+    assert_eq!(map.len(), 999);
+    let mut keys: Vec<u32> = map.keys().copied().collect();
+    keys.sort_unstable();
+    assert_eq!(keys, (0..1000).filter(|i| *i != 30).collect::<Vec<_>>());
+
+    let mut empty = Hamt::<u32, u32>::new();
+    assert!(empty.is_empty());
+    assert_eq!(empty.remove(&1), None);
+    assert_eq!(format!("{:?}", empty), "{}");
+    assert_eq!(empty, Hamt::default());
+}
+
+/// Copies share their entries until changed, and changing one leaves the
+/// others as they were
+#[test]
+fn test_hamt_persistence() {
+    let mut map: Hamt<u32, u32> = (0..100).map(|i| (i, i)).collect();
+    let snapshot = map.clone();
+    map.insert(100, 100);
+    map.remove(&0);
+    map.insert(1, 0);
+    assert_eq!(snapshot.len(), 100);
+    assert_eq!(snapshot.get(&0), Some(&0));
+    assert_eq!(snapshot.get(&1), Some(&1));
+    assert_eq!(snapshot.get(&100), None);
+    assert_ne!(snapshot, map);
+}
+
+/// Maps with the same entries are equal, and hash the same, whatever order
+/// they were made in
+#[test]
+fn test_hamt_eq() {
+    let forwards: Hamt<u32, u32> = (0..200).map(|i| (i, i)).collect();
+    let mut backwards: Hamt<u32, u32> = (0..300).rev().map(|i| (i, i)).collect();
+    for i in 200..300 {
+        backwards.remove(&i);
+    }
+    assert_eq!(forwards, backwards);
+    let hash = |map: &Hamt<u32, u32>| {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        map.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&forwards), hash(&backwards));
+}
+
+/// A key whose hash is that of its value modulo 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Colliding(u32);
+
+impl Hash for Colliding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 % 4).hash(state);
+    }
+}
+
+/// Keys with the same hash share a leaf, and otherwise behave as any
+#[test]
+fn test_hamt_collisions() {
+    let mut map = Hamt::new();
+    let mut expected = HashMap::new();
+    for i in 0..40 {
+        map.insert(Colliding(i), i);
+        expected.insert(i, i);
+    }
+    for i in (0..40).step_by(3) {
+        assert_eq!(map.remove(&Colliding(i)), expected.remove(&i));
+    }
+    assert_eq!(map.len(), expected.len());
+    for i in 0..40 {
+        assert_eq!(map.get(&Colliding(i)), expected.get(&i));
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+
+#[test]
+fn test_stable_hash() {
+    let mut ctx1 = Context::default();
+    let mut ctx2 = Context::default();
+    // Intern the names in a different order, so their IDs differ
+    ctx2.parse_expr("bar foo").unwrap();
+    let src = "[foo 'bar swap] apply";
+    let e1 = ctx1.parse_expr(src).unwrap();
+    let e2 = ctx2.parse_expr(src).unwrap();
+    assert_eq!(
+        e1.stable_hash(&ctx1.interner),
+        e2.stable_hash(&ctx2.interner)
+    );
+    // Hashes mustn't change between versions, as they may be stored
+    assert_eq!(e1.stable_hash(&ctx1.interner), 0xa0bc_eb41_1c45_a300);
+
+    let mut hashes: Vec<u64> = [
+        "foo",
+        "'foo",
+        "[foo]",
+        "foo bar",
+        "bar foo",
+        "(foo bar) baz",
+    ]
+    .iter()
+    .map(|src| ctx1.parse_expr(src).unwrap().stable_hash(&ctx1.interner))
+    .collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    assert_eq!(hashes.len(), 6);
+
+    let vs1 = ctx1.parse_value_stack("⟨[foo] 'bar⟩").unwrap();
+    let vs2 = ctx2.parse_value_stack("⟨[foo] 'bar⟩").unwrap();
+    assert_eq!(
+        vs1.stable_hash(&ctx1.interner),
+        vs2.stable_hash(&ctx2.interner)
+    );
+    assert_ne!(
+        vs1.stable_hash(&ctx1.interner),
+        ctx1.parse_value_stack("⟨'bar [foo]⟩")
+            .unwrap()
+            .stable_hash(&ctx1.interner)
+    );
+}
@@ -0,0 +1,44 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::help::*;
+use crate::parse::*;
+
+#[test]
+fn test_help_topics() {
+    for topic in TOPICS {
+        assert!(help(Some(topic.name)).is_some(), "{}", topic.name);
+    }
+    assert_eq!(help(None), help(Some("commands")));
+    assert!(help(None).unwrap().ends_with(&topics()));
+    assert_eq!(help(Some("nothing")), None);
+}
+
+/// Every intrinsic has an entry, named as the parser spells it
+#[test]
+fn test_intrinsic_help() {
+    let mut interner = Interner::default();
+    let mut names = vec![];
+    for intrinsic in INTRINSICS {
+        let e = ExprParser::new()
+            .parse(&mut interner, intrinsic.name)
+            .unwrap();
+        match e {
+            Expr::Intrinsic(i) => names.push(i.to_string()),
+            _ => panic!("`{}` is not an intrinsic", intrinsic.name),
+        }
+        assert!(intrinsic.rule.contains(&format!(" {} ⟶ ", intrinsic.name)));
+        assert!(help(Some(intrinsic.name)).unwrap().contains(intrinsic.rule));
+    }
+    assert_eq!(
+        names,
+        [
+            "swap", "clone", "drop", "quote", "compose", "apply", "defined?", "body-of", "define!",
+            "evalq"
+        ]
+    );
+}
@@ -0,0 +1,45 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn test_hooks() {
+    let mut ctx = Context::default();
+    let swap2 = ctx.parse_fn_def("{fn swap2 = [swap] apply}").unwrap();
+    ctx.define_fn(swap2).unwrap();
+    let events = Rc::new(RefCell::new(vec![]));
+    let log = Rc::clone(&events);
+    ctx.on_step(move |vs, _| log.borrow_mut().push(format!("step {}", vs.len())));
+    let log = Rc::clone(&events);
+    ctx.on_call(move |_, vs| log.borrow_mut().push(format!("call {}", vs.len())));
+    let log = Rc::clone(&events);
+    ctx.on_error(move |err, _, _| log.borrow_mut().push(format!("error {:?}", err)));
+
+    let mut vs = ValueStack::default();
+    let mut e = ctx.parse_expr("[a] swap2").unwrap();
+    while e != Expr::default() && ctx.small_step(&mut vs, &mut e).is_ok() {}
+    assert_eq!(
+        *events.borrow(),
+        [
+            "step 0",
+            "step 1",
+            "call 1",
+            "step 1",
+            "step 2",
+            "step 1",
+            "error TooFewValues { available: 1, expected: 2 }",
+        ]
+    );
+
+    events.borrow_mut().clear();
+    ctx.clear_hooks();
+    let mut e = ctx.parse_expr("drop").unwrap();
+    ctx.small_step(&mut vs, &mut e).unwrap();
+    assert!(events.borrow().is_empty());
+}
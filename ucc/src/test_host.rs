@@ -0,0 +1,91 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::diagnostics::Warning;
+use crate::display::*;
+use crate::parse::*;
+
+fn host_ctx() -> Context {
+    let mut ctx = Context::default();
+    // `[a] [b] pair` ⟶ `[a b]`
+    ctx.register_intrinsic("pair", 2, |vs| {
+        let b = vs.pop().unwrap();
+        let a = vs.pop().unwrap();
+        match (a, b) {
+            (Value::Quote(a), Value::Quote(b)) => {
+                vs.push(Value::Quote(Box::new(Expr::Compose(vec![*a, *b]))));
+                Ok(Expr::default())
+            }
+            _ => Err(EvalError::Host("pair expects quotes".to_owned())),
+        }
+    });
+    // `v twice` ⟶ `v v`, continuing with an expression
+    ctx.register_intrinsic("twice", 0, |_| Ok(Expr::Intrinsic(Intrinsic::Clone)));
+    ctx
+}
+
+#[test]
+fn test_host_intrinsic() {
+    let cases = [
+        ("⟨[a] [b]⟩ pair ⟶ ⟨[a b]⟩", Ok(())),
+        ("⟨[a]⟩ twice ⟶ ⟨[a]⟩ clone", Ok(())),
+        (
+            "⟨[a]⟩ pair ⟶ ⟨[a]⟩ pair",
+            Err(ResolvedEvalError::TooFewValues {
+                available: 1,
+                expected: 2,
+            }),
+        ),
+        (
+            "⟨'a [b]⟩ pair ⟶ ⟨⟩ pair",
+            Err(ResolvedEvalError::Host("pair expects quotes".to_owned())),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = host_ctx();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+#[test]
+fn test_host_intrinsic_shadowed() {
+    let mut ctx = host_ctx();
+    let fn_def = FnDefParser::new()
+        .parse(&mut ctx.interner, "{fn pair = compose}")
+        .unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨[a] [b]⟩ pair ⟶ ⟨[a] [b]⟩ compose")
+        .unwrap();
+    assert_eq!(ctx.small_step(&mut ssa.0, &mut ssa.1), Ok(()));
+    assert_eq!(ssa.1, ssa.3);
+}
+
+#[test]
+fn test_host_intrinsic_display_and_diagnostics() {
+    let mut ctx = host_ctx();
+    let pair = Symbol(ctx.interner.get("pair").unwrap());
+    let e = Expr::Intrinsic(Intrinsic::Host(pair));
+    assert_eq!(e.resolve(&ctx.interner).to_string(), "pair");
+
+    let fn_def = FnDefParser::new()
+        .parse(&mut ctx.interner, "{fn foo = [clone] pair}")
+        .unwrap();
+    let foo = fn_def.0;
+    assert_eq!(ctx.diagnose(&fn_def), vec![Warning::AlwaysUnderflows(foo)]);
+}
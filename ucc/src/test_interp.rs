@@ -4,10 +4,18 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use crate::interp::{Interp, HELP};
+use crate::builder::{InterpBuilder, Prelude};
+use crate::config::Config;
+use crate::help::{help, topics};
+use crate::interp::{Failure, Interp};
+use crate::settings::ShowSteps;
+use crate::test_util::eval_input;
 
 #[test]
 fn test_non_blocking_interp() {
+    let help_text = help(None).unwrap();
+    let trace_help_text = help(Some("trace")).unwrap();
+    let no_help_text = format!("No help on `nothing`.\n{}", topics());
     let sessions = [
         &[
             (":drop", "Values dropped.\n"),
@@ -15,7 +23,13 @@ fn test_non_blocking_interp() {
             ("{fn foo = }", "Defined `foo`.\n"),
             (":list", "foo\n"),
             (":show foo", "{fn foo = }\n"),
-            ("{fn foo = drop}", "Redefined `foo`.\n"),
+            (
+                "{fn foo = drop}",
+                concat!(
+                    "Redefined `foo`.\n",
+                    "Warning: `foo` always underflows on an empty stack.\n",
+                ),
+            ),
             (":show foo", "{fn foo = drop}\n"),
             ("{fn bar = }", "Defined `bar`.\n"),
             (":list", "bar foo\n"),
@@ -23,6 +37,9 @@ fn test_non_blocking_interp() {
                 "{fn foo = }{fn bar = }",
                 "Redefined `foo`.\nRedefined `bar`.\n",
             ),
+            (":copy bar baz", "Defined `baz`.\n"),
+            (":show baz", "{fn baz = }\n"),
+            (":copy qux baz", "Not defined.\n"),
             (":reset", "Reset.\n"),
             ("false", "⟨⟩ false\n⇓ ⟨false⟩ \n"),
             ("true", "⟨false⟩ true\n⇓ ⟨false true⟩ \n"),
@@ -44,6 +61,27 @@ fn test_non_blocking_interp() {
             ),
             ("drop", "⟨true⟩ drop\n⇓ ⟨⟩ \n"),
         ][..],
+        &[
+            (":ref", "No results yet.\n"),
+            ("[a] [b c]", "⟨⟩ [a] [b c]\n⇓ ⟨[a] [b c]⟩ \n"),
+            (":drop", "Values dropped.\n"),
+            ("%1 [%1] drop", "⟨⟩ [b c] [[b c]] drop\n⇓ ⟨[b c]⟩ \n"),
+            ("drop", "⟨[b c]⟩ drop\n⇓ ⟨⟩ \n"),
+            (":ref", "%1 = [b c]\n%2 = [b c]\n"),
+            ("%3", "⟨⟩ %3\n⇓ ⟨⟩ %3\nUndefinedFn(\"%3\")\n"),
+            (":clear", "Definitions cleared.\n"),
+            (":ref", "No results yet.\n"),
+        ][..],
+        &[
+            ("it", "⟨⟩ it\n⇓ ⟨⟩ it\nUndefinedFn(\"it\")\n"),
+            ("[a] [b]", "⟨⟩ [a] [b]\n⇓ ⟨[a] [b]⟩ \n"),
+            ("it it", "⟨[a] [b]⟩ it it\n⇓ ⟨[a] [b] [b] [b]⟩ \n"),
+            ("drop drop drop it", "⟨[a] [b] [b] [b]⟩ drop drop drop it\n⇓ ⟨[a] [b]⟩ \n"),
+            ("'it defined?", "⟨[a] [b]⟩ 'it defined?\n⇓ ⟨[a] [b] n0⟩ \n"),
+            ("drop it", "⟨[a] [b] n0⟩ drop it\n⇓ ⟨[a] [b] n0⟩ \n"),
+            ("{fn it = [drop]}", "Defined `it`.\n"),
+            ("it", "⟨[a] [b] n0⟩ it\n⇓ ⟨[a] [b] n0 it⟩ \n"),
+        ][..],
         &[(
             ":trace false false or",
             concat!(
@@ -56,7 +94,13 @@ fn test_non_blocking_interp() {
                 "⟶ ⟨false⟩ \n"
             ),
         )][..],
-        &[(":help", HELP)][..],
+        &[
+            (":help", help_text.as_str()),
+            (":help swap", "swap: exchange the top two values\n\n   ⟨v1 v2⟩ swap ⟶ ⟨v2 v1⟩\n"),
+            (":help body-of", "body-of: get the definition of a symbol\n\n   ⟨'s⟩ body-of ⟶ ⟨[e]⟩, given {fn s = e}\n"),
+            (":help trace", trace_help_text.as_str()),
+            (":help nothing", no_help_text.as_str()),
+        ][..],
         &[("n0 succ", "⟨⟩ n0 succ\n⇓ ⟨n1⟩ \n")][..],
         &[("n0 n1 add", "⟨⟩ n0 n1 add\n⇓ ⟨n1⟩ \n")][..],
         &[("n1 n1 add", "⟨⟩ n1 n1 add\n⇓ ⟨n2⟩ \n")][..],
@@ -66,10 +110,307 @@ fn test_non_blocking_interp() {
             "true foo",
             "⟨⟩ true foo\n⇓ ⟨true⟩ foo\nUndefinedFn(\"foo\")\n",
         )][..],
+        &[
+            ("{rule swap swap => }", "Defined rule.\n"),
+            (":rules", "{rule swap swap => }\n"),
+            (
+                ":trace [a] [b] swap swap",
+                concat!(
+                    "⟨⟩ [a] [b] swap swap\n",
+                    "⟶ ⟨[a]⟩ [b] swap swap\n",
+                    "⟶ ⟨[a] [b]⟩ swap swap\n",
+                    "⟶ ⟨[a] [b]⟩ \n",
+                ),
+            ),
+            ("{rule foo => $x}", "UnboundVar(\"$x\")\n"),
+            (":clear", "Definitions cleared.\n"),
+            (":rules", ""),
+        ][..],
+        &[
+            (
+                "{fn f = [clone] g}{fn g = drop drop}",
+                concat!(
+                    "Defined `f`.\n",
+                    "Warning: `f` calls undefined `g`.\n",
+                    "Defined `g`.\n",
+                    "Warning: `g` always underflows on an empty stack.\n",
+                ),
+            ),
+            (
+                "f",
+                concat!(
+                    "⟨⟩ f\n",
+                    "⇓ ⟨⟩ drop\n",
+                    "TooFewValues { available: 0, expected: 1 }\n",
+                    "in f → in g → drop\n",
+                ),
+            ),
+            (
+                "[b] f apply",
+                concat!(
+                    "⟨⟩ [b] f apply\n",
+                    "⇓ ⟨⟩ apply\n",
+                    "TooFewValues { available: 0, expected: 1 }\n",
+                ),
+            ),
+        ][..],
+        &[
+            (":set prompt \"λ> \"", "Set `prompt`.\n"),
+            (":set colour \"red\"", "Unknown(\"colour\")\n"),
+            (":set show-steps off", "Set `show-steps`.\n"),
+            ("n1 n1 add", "⇓ ⟨n2⟩ \n"),
+            (":set show-steps 4", "Set `show-steps`.\n"),
+            (
+                "true [drop] apply false",
+                concat!(
+                    "⟨n2⟩ true [drop] apply false\n",
+                    "⟶ ⟨n2⟩ false\n",
+                    "⇓ ⟨n2 false⟩ \n",
+                ),
+            ),
+            (":set show-steps 2", "Set `show-steps`.\n"),
+            (
+                "true [drop] apply false",
+                concat!(
+                    "⟨n2 false⟩ true [drop] apply false\n",
+                    "⟶ ⟨n2 false true n0⟩ apply false\n",
+                    "⟶ ⟨n2 false⟩ false\n",
+                    "⇓ ⟨n2 false false⟩ \n",
+                ),
+            ),
+            (
+                ":set show-steps lots",
+                "InvalidValue { name: \"show-steps\", value: \"lots\" }\n",
+            ),
+            (
+                ":settings",
+                concat!(
+                    "prompt = \"λ> \"    -- the REPL prompt\n",
+                    "show-steps = 2    -- off (result only), on (show inputs), ",
+                    "or <n> (every <n>th step)\n",
+                    "theme = \"unicode\"    -- unicode, or ascii to print `<`, `>`, `->`, ",
+                    "and `=>` instead\n",
+                    "prelude = on    -- whether :reset defines the builtin functions\n",
+                    "compact-symbols = off    -- whether :clear also drops unused symbols ",
+                    "(:reset always does)\n",
+                    "intrinsic-typos = \"warn\"    -- ignore, warn about, or fix calls one typo ",
+                    "away from an intrinsic\n",
+                    "search-size = 3    -- the most intrinsics and calls :search puts together\n",
+                    "normalize-input = on    -- whether input may use look-alikes of ASCII, like Cyrillic `о`\n",
+                    "provenance = off    -- whether to track how each value was made, for :why\n",
+                    "prefold = off    -- whether definitions are made with closed prefixes evaluated\n",
+                    "stack-top = \"right\"    -- right, or left to show stacks reversed, ",
+                    "as ⟨top: [b] [a]⟩\n",
+                    "stack-display-max = 100    -- the most values of a stack shown, ",
+                    "those nearest the top (0 for all)\n",
+                    "output-max = 1000000    -- the most bytes a command prints before the rest ",
+                    "is cut (0 for all)\n",
+                    "output-overflow = \"continue\"    -- continue, or abort a command once its ",
+                    "output is cut\n",
+                    "align = off    -- whether :trace lines up its columns, printing once it ",
+                    "finishes\n",
+                ),
+            ),
+            (":unset show-steps", "Unset `show-steps`.\n"),
+            ("n0", "⟨n2 false false⟩ n0\n⇓ ⟨n2 false false n0⟩ \n"),
+            (":stack", "⟨n2 false false n0⟩\n"),
+            (":set stack-top left", "Set `stack-top`.\n"),
+            (
+                "drop",
+                "⟨top: n0 false false n2⟩ drop\n⇓ ⟨top: false false n2⟩ \n",
+            ),
+            (":stack", "⟨top: false false n2⟩\n"),
+            (":set stack-display-max 2", "Set `stack-display-max`.\n"),
+            (":stack", "⟨top: false false …(1 more)…⟩\n"),
+            (":stack full", "⟨top: false false n2⟩\n"),
+            (":unset stack-top", "Unset `stack-top`.\n"),
+            (":stack", "⟨…(1 more)… false false⟩\n"),
+            (":unset stack-display-max", "Unset `stack-display-max`.\n"),
+            (":unset colour", "Unknown(\"colour\")\n"),
+        ][..],
+        &[
+            (
+                "[a] [b] swpa",
+                concat!(
+                    "Warning: `swpa` may be a misspelling of `swap`.\n",
+                    "⟨⟩ [a] [b] swpa\n",
+                    "⇓ ⟨[a] [b]⟩ swpa\n",
+                    "UndefinedFn(\"swpa\")\n",
+                ),
+            ),
+            (":set intrinsic-typos fix", "Set `intrinsic-typos`.\n"),
+            (
+                "swpa",
+                concat!(
+                    "Corrected `swpa` to `swap`.\n",
+                    "⟨[a] [b]⟩ swap\n",
+                    "⇓ ⟨[b] [a]⟩ \n",
+                ),
+            ),
+            (
+                "{fn clon = [clone]} clon",
+                concat!(
+                    "Defined `clon`.\n",
+                    "⟨[b] [a]⟩ clon\n",
+                    "⇓ ⟨[b] [a] clon⟩ \n",
+                ),
+            ),
+            (":set intrinsic-typos ignore", "Set `intrinsic-typos`.\n"),
+            (
+                ":trace dorp",
+                "⟨[b] [a] clon⟩ dorp\nUndefinedFn(\"dorp\")\n",
+            ),
+        ][..],
+        &[
+            (
+                ":search ⟨[a] [b]⟩ ⇓ ⟨[b] [a]⟩",
+                "⟨[a] [b]⟩ swap ⇓ ⟨[b] [a]⟩\n",
+            ),
+            (":set search-size 2", "Set `search-size`.\n"),
+            (
+                ":search ⟨[a]⟩ ⇓ ⟨[a a]⟩",
+                "⟨[a]⟩ clone compose ⇓ ⟨[a a]⟩\n⟨[a]⟩ clone compose2 ⇓ ⟨[a a]⟩\n",
+            ),
+            (
+                ":search ⟨[a]⟩ ⇓ ⟨[b]⟩",
+                "Nothing of size 2 or less found.\n",
+            ),
+            (":terminates? rotate3", "Yes.\n"),
+            (":terminates? mul", "Unknown.\n"),
+            (
+                "{fn omega = [clone apply] clone apply}",
+                "Defined `omega`.\n",
+            ),
+            (
+                ":terminates? omega",
+                concat!(
+                    "No, on ⟨⟩ it loops:\n",
+                    "⟨[clone apply]⟩ clone apply\n",
+                    "⟶ ⟨[clone apply] [clone apply]⟩ apply\n",
+                    "⟶ ⟨[clone apply]⟩ clone apply\n",
+                ),
+            ),
+            (
+                ":check linear omega",
+                "No:\n`clone` at 1.1 in `omega`\n`clone` at 2 in `omega`\n",
+            ),
+            (":check affine rotate3", "Yes, `rotate3` is affine.\n"),
+            ("{fn first = [swap] [swap swap] pair fst}", "Defined `first`.\n"),
+            (
+                ":check linear first",
+                "Unknown, as host intrinsics can't be checked:\n`pair` at 3 in `first`\n`fst` at 4 in `first`\n",
+            ),
+            ("{fn second = first drop}", "Defined `second`.\n"),
+            (":check linear second", "No:\n`drop` at 2 in `second`\n"),
+            (
+                ":check relevant omega",
+                "Unknown check `relevant`; try linear or affine.\n",
+            ),
+            (":equiv [[] apply] []", "Indistinguishable up to bound.\n"),
+            (
+                ":equiv [swap drop] [swap swap drop]",
+                concat!(
+                    "Distinguishable.\n",
+                    "   ⟨[a] [b]⟩ ⇓ ⟨[b]⟩\n",
+                    "   ⟨[a] [b]⟩ ⇓ ⟨[a]⟩\n",
+                    "Applying value 1 of each on the stack:\n",
+                    "   ⟨⟩ ⇓ UndefinedFn(\"b\")\n",
+                    "   ⟨⟩ ⇓ UndefinedFn(\"a\")\n",
+                ),
+            ),
+            (
+                ":atlas 6 [swap] \"atlas.json\"",
+                "The most nodes allowed is 5.\n",
+            ),
+            (
+                ":search ⟨false⟩ ⇓ ⟨true⟩",
+                concat!(
+                    "⟨false⟩ drop true ⇓ ⟨true⟩\n",
+                    "⟨false⟩ quote defined? ⇓ ⟨true⟩\n",
+                    "⟨false⟩ true or ⇓ ⟨true⟩\n",
+                    "⟨false⟩ or true ⇓ ⟨true⟩\n",
+                    "⟨false⟩ add true ⇓ ⟨true⟩\n",
+                    "⟨false⟩ fix true ⇓ ⟨true⟩\n",
+                ),
+            ),
+        ][..],
+        &[
+            (
+                "{fn fool = }",
+                "Defined `fool`.\n",
+            ),
+            (
+                "{fn foo1 = }",
+                "Defined `foo1`.\nWarning: `foo1` looks like `fool`.\n",
+            ),
+            (
+                "fооl",
+                concat!(
+                    "Warning: read `о` (U+043E) as `o`.\n",
+                    "⟨⟩ fool\n",
+                    "⇓ ⟨⟩ \n",
+                ),
+            ),
+            (":set normalize-input off", "Set `normalize-input`.\n"),
+            ("fооl", "InvalidToken { location: 1 }\n"),
+        ][..],
+        &[
+            (":set show-steps off", "Set `show-steps`.\n"),
+            (":watchpoint ⟨[b] ...⟩", "Set watchpoint 1.\n"),
+            (":watchpoint ⟨$x $x⟩", "Set watchpoint 2.\n"),
+            (":watchpoints", "1: ⟨[b] ...⟩\n2: ⟨$x $x⟩\n"),
+            (
+                "[a] [b] swap clone drop",
+                "Paused at watchpoint 1: ⟨[b] [a]⟩ clone drop\n",
+            ),
+            (
+                ":jobs",
+                "[1] [a] [b] swap clone drop    -- shared value stack, paused\n",
+            ),
+            (":unwatch 2", "Removed watchpoint 2.\n"),
+            (":continue", "Continuing job 1.\n⇓ ⟨[b] [a]⟩ \n"),
+            (":continue", "No paused job.\n"),
+            (":unwatch 2", "No watchpoint 2.\n"),
+        ][..],
         &[(
             ":trace true foo",
             "⟨⟩ true foo\n⟶ ⟨true⟩ foo\nUndefinedFn(\"foo\")\n",
         )][..],
+        &[
+            (
+                "{local fn sq = clone compose fn quad = sq sq in [a] quad}",
+                "⟨⟩ [a] quad\n⇓ ⟨[a a a a]⟩ \n",
+            ),
+            (":show sq", "Not defined.\n"),
+            ("{fn v = [clone]}", "Defined `v`.\n"),
+            // Calls of local quotations are replaced once out of scope
+            ("{local fn v = [clone drop] in v}", "⟨[a a a a]⟩ v\n⇓ ⟨[a a a a] [clone drop]⟩ \n"),
+            ("v", "⟨[a a a a] [clone drop]⟩ v\n⇓ ⟨[a a a a] [clone drop] v⟩ \n"),
+            (":drop", "Values dropped.\n"),
+            ("{local fn f = drop in f}", "⟨⟩ f\n⇓ ⟨⟩ drop\nTooFewValues { available: 0, expected: 1 }\nin f → drop\n"),
+            (":show f", "Not defined.\n"),
+            (":watchpoint ⟨[b] [b]⟩", "Set watchpoint 1.\n"),
+            ("{local fn f = [b] clone in f drop}", "⟨⟩ f drop\nPaused at watchpoint 1: ⟨[b] [b]⟩ drop\n"),
+            (":show f", "{local fn f = [b] clone}\n"),
+            (":continue", "Continuing job 6.\n⇓ ⟨[b]⟩ \n"),
+            (":show f", "Not defined.\n"),
+        ][..],
+        &[
+            // Shadowing a prelude definition leaves those calling it be
+            ("{fn n0 = [clone]}", "Defined `n0`.\n"),
+            ("[b] [clone] n2 apply", "⟨⟩ [b] [clone] n2 apply\n⇓ ⟨[b] [b] [b]⟩ \n"),
+            (":drop", "Values dropped.\n"),
+            (
+                "n0 succ",
+                "⟨⟩ n0 succ\n⇓ ⟨[[clone] n0 apply [compose] n0 apply apply]⟩ \n",
+            ),
+            (":show n0", "{fn n0 = [clone]}\n"),
+            (
+                ":show n1",
+                "{fn n1 = [[clone] core.n0 apply [compose] core.n0 apply apply]}\n",
+            ),
+            (":show core.n0", "{fn core.n0 = [drop]}\n"),
+        ][..],
     ];
     let mut buffer = Vec::with_capacity(4096);
     for session in sessions {
@@ -90,3 +431,724 @@ fn test_non_blocking_interp() {
         }
     }
 }
+
+#[test]
+fn test_results_expire() {
+    let mut interp = Interp::default();
+    for i in 1..=101 {
+        eval_input(&mut interp, ":drop");
+        eval_input(&mut interp, &format!("'r{}", i));
+    }
+    let refs = eval_input(&mut interp, ":ref");
+    assert_eq!(refs.lines().count(), 100);
+    assert!(refs.starts_with("%2 = 'r2\n"), "{}", refs);
+    assert!(refs.ends_with("%101 = 'r101\n"), "{}", refs);
+    let cases = [
+        (":drop", "Values dropped.\n"),
+        (
+            "%1",
+            "`%1` has expired, as only the last 100 results are kept.\n⟨⟩ %1\n⇓ ⟨⟩ %1\nUndefinedFn(\"%1\")\n",
+        ),
+        ("%2 %101", "⟨⟩ 'r2 'r101\n⇓ ⟨'r2 'r101⟩ \n"),
+        // Which is the 102nd result, so the 2nd expires
+        ("%2", "`%2` has expired, as only the last 100 results are kept.\n⟨'r2 'r101⟩ %2\n⇓ ⟨'r2 'r101⟩ %2\nUndefinedFn(\"%2\")\n"),
+        ("%102", "⟨'r2 'r101⟩ 'r101\n⇓ ⟨'r2 'r101 'r101⟩ \n"),
+    ];
+    for (input, output) in cases {
+        assert_eq!(
+            eval_input(&mut interp, input),
+            output,
+            "Failed on {:?}",
+            input
+        );
+    }
+}
+
+/// The number of symbols `:stats interner` reports
+fn symbols_interned(interp: &mut Interp) -> usize {
+    let output = eval_input(interp, ":stats interner");
+    output.split(' ').next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn test_compact_symbols() {
+    let mut interp = Interp::default();
+    let before = symbols_interned(&mut interp);
+    assert_eq!(eval_input(&mut interp, ":show typo"), "Not defined.\n");
+    eval_input(&mut interp, "{fn keep = [typo2]}");
+    eval_input(&mut interp, "'value");
+    // `typo`, `keep`, `typo2` and `value`
+    assert_eq!(symbols_interned(&mut interp), before + 4);
+    assert_eq!(eval_input(&mut interp, ":clear"), "Definitions cleared.\n");
+    assert_eq!(symbols_interned(&mut interp), before + 4);
+
+    eval_input(&mut interp, "{fn keep = [typo2]}");
+    eval_input(&mut interp, ":set compact-symbols on");
+    let output = eval_input(&mut interp, ":clear");
+    let dropped: usize = output
+        .strip_prefix("Definitions cleared, and ")
+        .and_then(|rest| rest.strip_suffix(" unused symbols dropped.\n"))
+        .unwrap()
+        .parse()
+        .unwrap();
+    // `typo`, `keep` and `typo2` among them, as nothing uses them now
+    assert!(dropped >= 3, "{}", output);
+    assert_eq!(symbols_interned(&mut interp), before + 4 - dropped);
+    assert_eq!(
+        eval_input(&mut interp, "'value"),
+        "⟨'value⟩ 'value\n⇓ ⟨'value 'value⟩ \n"
+    );
+
+    // Symbols are kept while a watchpoint holds them
+    eval_input(&mut interp, ":watchpoint ⟨zzz_unique_name ...⟩");
+    let count = symbols_interned(&mut interp);
+    assert_eq!(
+        eval_input(&mut interp, ":clear"),
+        "Definitions cleared, but symbols kept for the jobs and watchpoints.\n"
+    );
+    assert_eq!(symbols_interned(&mut interp), count);
+    assert_eq!(
+        eval_input(&mut interp, ":watchpoints"),
+        "1: ⟨zzz_unique_name ...⟩\n"
+    );
+}
+
+#[test]
+fn test_load() {
+    let path = std::env::temp_dir().join("ucc_test_load.ucc");
+    std::fs::write(
+        &path,
+        "-- a small library\n{fn foo = [clone] apply}\n{fn bar = foo foo}\n",
+    )
+    .unwrap();
+    let cases = [
+        (
+            format!(":load \"{}\"", path.display()),
+            "Defined `foo`.\nDefined `bar`.\n".to_owned(),
+        ),
+        (":show bar".to_owned(), "{fn bar = foo foo}\n".to_owned()),
+        (
+            ":load \"/nonexistent/ucc_test_load.ucc\"".to_owned(),
+            "Io(\"No such file or directory (os error 2)\")\n".to_owned(),
+        ),
+        (
+            ":load \"http://example.com/lib.ucc\"".to_owned(),
+            "InsecureUrl(\"http://example.com/lib.ucc\")\n".to_owned(),
+        ),
+        (
+            ":load \"https://example.com/lib.ucc\"".to_owned(),
+            if cfg!(feature = "net") {
+                "".to_owned()
+            } else {
+                "NetUnsupported\n".to_owned()
+            },
+        ),
+    ];
+    let mut interp = Interp::default();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        if expected_output.is_empty() {
+            continue;
+        }
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, expected_output, "Failed on {:?}", input);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn test_read_cache() {
+    use crate::load::read_cache;
+    let path = std::env::temp_dir().join("ucc_test_read_cache.ucc");
+    // sha256 of "{fn foo = }\n"
+    let sum = "9abfe97f47a82b462c246cbc056f8e8ef65fbba7d14dc4365042d10cb3f422e1";
+    std::fs::write(&path, "{fn foo = }\n").unwrap();
+    assert_eq!(
+        read_cache(&path, Some(sum)),
+        Some("{fn foo = }\n".to_owned())
+    );
+    // A cached source that doesn't match is removed, to be fetched again
+    std::fs::write(&path, "{fn foo = evil}\n").unwrap();
+    assert_eq!(
+        read_cache(&path, None),
+        Some("{fn foo = evil}\n".to_owned())
+    );
+    assert_eq!(read_cache(&path, Some(sum)), None);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_diff_defs() {
+    let path = std::env::temp_dir().join("ucc_test_diff_defs.ucc");
+    std::fs::write(
+        &path,
+        "{fn foo = [clone] [drop] [swap] drop swap}\n{fn baz = foo}\n",
+    )
+    .unwrap();
+    let diff_defs = format!(":diff-defs \"{}\"", path.display());
+    let load = format!(":load \"{}\"", path.display());
+    let cases = [
+        ("{fn foo = [clone] [drop] swap}", "Defined `foo`.\n"),
+        ("{fn bar = foo foo}", "Defined `bar`.\n"),
+        (
+            &diff_defs[..],
+            "~ {fn foo = [clone] [drop] {+[swap] drop+} swap}\n+ {fn baz = foo}\n- {fn bar = foo foo}\n",
+        ),
+        ("{fn foo = [clone] [drop] [swap] drop swap}", "Redefined `foo`.\n"),
+        ("{fn baz = foo}", "Defined `baz`.\n"),
+        (&diff_defs[..], "- {fn bar = foo foo}\n"),
+        (":clear", "Definitions cleared.\n"),
+        (&load[..], "Defined `foo`.\nDefined `baz`.\n"),
+        (&diff_defs[..], "No differences.\n"),
+        (
+            ":diff-defs \"/nonexistent/ucc_test_diff_defs.ucc\"",
+            "Io(\"No such file or directory (os error 2)\")\n",
+        ),
+    ];
+    let mut interp = Interp::default();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_trace_vm() {
+    let cases = [
+        ("{fn pair = [clone] apply}", "Defined `pair`.\n"),
+        (":trace-vm [a] swap pair", "   0  push [a]\n   1  swap\n   2  call pair\n   3  return\n⟨[z]⟩\n@0 push [a] ⟶ ⟨[z] [a]⟩\n@1 swap ⟶ ⟨[a] [z]⟩\n@2 call pair ⟶ ⟨[a] [z]⟩\n   4  push [clone]\n   5  apply\n   6  return\n@4 push [clone] ⟶ ⟨[a] [z] [clone]⟩\n@5 apply ⟶ ⟨[a] [z]⟩\n   7  clone\n   8  return\n@7 clone ⟶ ⟨[a] [z] [z]⟩\n@8 return ⟶ ⟨[a] [z] [z]⟩\n"),
+        (":trace-vm drop drop", "   0  drop\n   1  drop\n   2  return\n⟨[z]⟩\n@0 drop ⟶ ⟨⟩\n@1 drop\nTooFewValues { available: 0, expected: 1 }\n"),
+        (":trace-vm [b] evalq", "Unsupported(EvalQ)\n"),
+    ];
+    let mut interp = Interp::default();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(":drop", &mut buffer).unwrap();
+        interp.interp_start("[z]", &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_disasm() {
+    let cases = [
+        ("{fn f = [true] compose3 swap swap}", "Defined `f`.\n"),
+        (":disasm f", "f:\n   0  push #0\n   1  call compose3 @5\n   2  swap\n   3  swap\n   4  return\ncompose3:\n   5  compose\n   6  call core.compose2 @8\n   7  return\ncore.compose2:\n   8  compose\n   9  return\nconstants:\n  #0  [true]\noptimized:\nf:\n   0  push #0\n   1  compose\n   2  call core.compose2 @5\n   3  check 2\n   4  return\ncore.compose2:\n   5  compose\n   6  return\nconstants:\n  #0  [true]\n"),
+        (":disasm g", "Not defined.\n"),
+        ("{fn h = evalq}", "Defined `h`.\nWarning: `h` always underflows on an empty stack.\n"),
+        (":disasm h", "Unsupported(EvalQ)\n"),
+    ];
+    let mut interp = Interp::default();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_grep() {
+    let cases = [
+        ("{fn f = [clone] [b] drop apply}", "Defined `f`.\nWarning: `f` calls undefined `b`.\n"),
+        ("{fn g = [[a] drop] clone}", "Defined `g`.\nWarning: `g` calls undefined `a`.\n"),
+        (":grep [$x] drop", "{fn f = [clone] [b] drop apply}\n                ^^^^^^^^\n{fn g = [[a] drop] clone}\n         ^^^^^^^^\n"),
+        (":grep \"g\"", "{fn g = [[a] drop] clone}\n    ^\n"),
+        (":grep swap swap", "No matches.\n"),
+    ];
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_output_max() {
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut buffer = Vec::with_capacity(4096);
+    let mut run = |input: &str| {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        std::str::from_utf8(&buffer[..]).unwrap().to_owned()
+    };
+    assert_eq!(run(":set output-max 40"), "Set `output-max`.\n");
+    assert_eq!(
+        run(":trace [a] [b] swap swap drop"),
+        "⟨⟩ [a] [b] swap swap drop\n⟶ ⟨[a]\nOutput cut after 40 bytes; see :set output-max.\n"
+    );
+    assert_eq!(run(":stack"), "⟨[a]⟩\n");
+    assert_eq!(
+        run(":set output-overflow abort"),
+        "Set `output-overflow`.\n"
+    );
+    assert_eq!(
+        run(":trace drop [a] [b] swap swap drop"),
+        concat!(
+            "⟨[a]⟩ drop [a] [b] swap swap drop\n",
+            "Output cut after 38 bytes; see :set output-max.\n",
+            "Stopped.\n",
+        )
+    );
+    assert_eq!(run(":stack"), "⟨⟩\n");
+}
+
+#[test]
+fn test_minimize() {
+    let cases = [
+        (
+            ":minimize [a] [b] swap clone apply drop",
+            "a\nUndefinedFn(\"a\")\n",
+        ),
+        (":minimize [a] drop", "NoFailure\n"),
+    ];
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_flatten() {
+    let cases = [
+        ("{fn f = [drop] apply}", "Defined `f`.\n"),
+        (
+            "{fn g = [a] f [b]}",
+            "Defined `g`.\nWarning: `g` calls undefined `a`.\nWarning: `g` calls undefined `b`.\n",
+        ),
+        (":flatten g", "[b]\n"),
+        (":flatten [x] g swap", "[b] [x]\n"),
+        ("{fn h = [h] apply}", "Defined `h`.\n"),
+        (":flatten h", "Cycle([\"h\", \"h\"])\n"),
+    ];
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_subst() {
+    let cases = [
+        ("{fn f = [drop] apply}", "Defined `f`.\n"),
+        (":subst f swap in [a] f 'f", "[a] swap 'f\n"),
+        (":subst g [f] clone in g apply", "([f] clone) apply\n"),
+        (":show f", "{fn f = [drop] apply}\n"),
+        (":show g", "Not defined.\n"),
+    ];
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_prefold() {
+    let cases = [
+        ("{fn f = [true] [false] compose}", "Defined `f`.\n"),
+        (":show f", "{fn f = [true] [false] compose}\n"),
+        (":set prefold on", "Set `prefold`.\n"),
+        ("{fn f = [true] [false] compose}", "Redefined `f`.\n"),
+        (":show f", "{fn f = [true false]}\n"),
+    ];
+    let mut interp = Interp::default();
+    let mut buffer = Vec::with_capacity(4096);
+    for (input, expected_output) in cases.iter() {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        let output = std::str::from_utf8(&buffer[..]).unwrap();
+        assert_eq!(output, *expected_output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_version_info() {
+    let mut interp = Interp::default();
+    let info = interp.version_info();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(info.prelude.first().map(String::as_str), Some("v1"));
+    assert_eq!(
+        info.prelude.last().map(String::as_str),
+        Some("quote_to_pair")
+    );
+    assert!(interp.banner().contains(&info.prelude.join(" ")));
+    assert_eq!(info.capabilities, crate::capabilities());
+
+    let mut buffer = vec![];
+    interp.interp_start(":version", &mut buffer).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        format!("{}\n", info.capabilities)
+    );
+
+    assert_eq!(interp.prompt(), ">>> ");
+    interp
+        .interp_start(":set prompt \"λ> \"", &mut buffer)
+        .unwrap();
+    interp.interp_start(":reset", &mut buffer).unwrap();
+    assert_eq!(interp.prompt(), "λ> ");
+}
+
+#[test]
+fn test_save_settings() {
+    let path = std::env::temp_dir().join("ucc_test_save_settings.toml");
+    let _ = std::fs::remove_file(&path);
+    let mut interp = Interp::default();
+    interp.set_settings_path(Some(path.clone()));
+    let mut buffer = vec![];
+    interp
+        .interp_start(":set show-steps off", &mut buffer)
+        .unwrap();
+    buffer.clear();
+    interp.interp_start(":save-settings", &mut buffer).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        format!("Saved settings to {}.\n", path.display())
+    );
+
+    let mut interp = Interp::default();
+    interp.set_settings_path(Some(path.clone()));
+    assert_eq!(interp.load_settings(), Ok(()));
+    assert_eq!(interp.settings().show_steps(), ShowSteps::Off);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_apply_config() {
+    let path = std::env::temp_dir().join("ucc_test_apply_config.ucc");
+    std::fs::write(&path, "{fn foo = [clone]}").unwrap();
+    let config = Config::parse(&format!(
+        "prelude = false\ntheme = \"ascii\"\nload = [{:?}]\n",
+        path.display().to_string()
+    ))
+    .unwrap();
+    let mut interp = Interp::default();
+    let mut buffer = vec![];
+    interp.apply_config(&config, &mut buffer).unwrap();
+    assert_eq!(std::str::from_utf8(&buffer).unwrap(), "Defined `foo`.\n");
+    assert!(interp.version_info().prelude.is_empty());
+
+    let sessions = [
+        (":list", "foo\n"),
+        ("foo foo", "<> foo foo\n=> <foo foo> \n"),
+        (":reset", "Reset.\n"),
+        (":list", "\n"),
+    ];
+    for (input, expected_output) in sessions {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            expected_output,
+            "Failed on {}",
+            input
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_register_intrinsic() {
+    let mut interp = Interp::default();
+    interp.register_intrinsic("dup", 1, |vs| {
+        let v = vs.pop().unwrap();
+        vs.push(v.clone());
+        vs.push(v);
+        Ok(crate::core::Expr::default())
+    });
+    let sessions = [
+        ("n1 dup", "⟨⟩ n1 dup\n⇓ ⟨n1 n1⟩ \n"),
+        (":reset", "Reset.\n"),
+        ("n1 dup", "⟨⟩ n1 dup\n⇓ ⟨n1 n1⟩ \n"),
+    ];
+    let mut buffer = vec![];
+    for (input, expected_output) in sessions {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            expected_output,
+            "Failed on {}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_load_binary() {
+    let path = std::env::temp_dir().join("ucc_test_load_binary.uccb");
+    let bytes = crate::binary::pack("{fn foo = [clone]}\n{fn bar = foo foo}").unwrap();
+    std::fs::write(&path, bytes).unwrap();
+    let mut interp = Interp::default();
+    let mut buffer = vec![];
+    interp
+        .interp_start(&format!(":load \"{}\"", path.display()), &mut buffer)
+        .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "Defined `foo`.\nDefined `bar`.\n"
+    );
+    buffer.clear();
+    interp.interp_start(":show bar", &mut buffer).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "{fn bar = foo foo}\n"
+    );
+    std::fs::write(&path, b"not uccb").unwrap();
+    buffer.clear();
+    interp
+        .interp_start(&format!(":load \"{}\"", path.display()), &mut buffer)
+        .unwrap();
+    assert_eq!(std::str::from_utf8(&buffer).unwrap(), "Binary(BadMagic)\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_run() {
+    let mut interp = Interp::default();
+    let mut buffer = vec![];
+    let src = "{fn x = [swap swap]}\nx clone\n[\n  drop\n] apply\n";
+    assert!(interp.run(src.as_bytes(), &mut buffer).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "Defined `x`.\n\
+         ⟨⟩ x clone\n\
+         ⇓ ⟨x x⟩ \n\
+         ⟨x x⟩ [drop] apply\n\
+         ⇓ ⟨x⟩ \n"
+    );
+    buffer.clear();
+    assert!(!interp.run("drop\ndrop\n".as_bytes(), &mut buffer).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "⟨x⟩ drop\n\
+         ⇓ ⟨⟩ \n\
+         ⟨⟩ drop\n\
+         ⇓ ⟨⟩ drop\n\
+         TooFewValues { available: 0, expected: 1 }\n"
+    );
+}
+
+#[test]
+fn test_jobs() {
+    let mut interp = Interp::default();
+    let mut buffer = vec![];
+    interp.interp_start("n1 n1 add", &mut buffer).unwrap();
+    interp.interp_start("[a] clone", &mut buffer).unwrap();
+    interp.interp_start("[b]", &mut buffer).unwrap();
+    interp.interp_start(":jobs", &mut buffer).unwrap();
+    for _ in 0..4 {
+        interp.interp_step(&mut buffer).unwrap();
+    }
+    interp.interp_start(":kill 3", &mut buffer).unwrap();
+    interp.interp_start(":kill 3", &mut buffer).unwrap();
+    while !interp.is_done() {
+        interp.interp_step(&mut buffer).unwrap();
+    }
+    interp.interp_start(":jobs", &mut buffer).unwrap();
+    interp.interp_start("clone", &mut buffer).unwrap();
+    while !interp.is_done() {
+        interp.interp_step(&mut buffer).unwrap();
+    }
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        concat!(
+            "[1] n1 n1 add    -- shared value stack\n",
+            "[2] [a] clone    -- own value stack\n",
+            "[3] [b]    -- own value stack\n",
+            "[1] ⟨⟩ n1 n1 add\n",
+            "[2] ⟨⟩ [a] clone\n",
+            "[3] ⟨⟩ [b]\n",
+            "[1] ⇓ ⟨n2⟩ \n",
+            "Killed job 3.\n",
+            "No job 3.\n",
+            "[2] ⇓ ⟨[a] [a]⟩ \n",
+            "No jobs.\n",
+            "⟨n2⟩ clone\n",
+            "⇓ ⟨n2 n2⟩ \n",
+        )
+    );
+}
+
+#[test]
+fn test_align() {
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut buffer = Vec::with_capacity(4096);
+    let mut run = |input: &str| {
+        buffer.clear();
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+        std::str::from_utf8(&buffer[..]).unwrap().to_owned()
+    };
+    assert_eq!(run(":set align on"), "Set `align`.\n");
+    assert_eq!(
+        run(":trace [a] [bb] swap drop"),
+        concat!(
+            "  ⟨⟩         [a] [bb] swap drop\n",
+            "⟶ ⟨[a]⟩      [bb] swap drop\n",
+            "⟶ ⟨[a] [bb]⟩ swap drop\n",
+            "⟶ ⟨[bb] [a]⟩ drop\n",
+            "⟶ ⟨[bb]⟩\n",
+        )
+    );
+    assert_eq!(
+        run(":trace drop drop"),
+        concat!(
+            "  ⟨[bb]⟩ drop drop\n",
+            "⟶ ⟨⟩     drop\n",
+            "TooFewValues { available: 0, expected: 1 }\n",
+        )
+    );
+    assert_eq!(run(":set theme ascii"), "Set `theme`.\n");
+    assert_eq!(
+        run(":trace [a] drop"),
+        concat!("   <>    [a] drop\n", "-> <[a]> drop\n", "-> <>\n")
+    );
+}
+
+#[test]
+fn test_failure() {
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let mut run = |src: &str| {
+        let mut output = vec![];
+        let ok = interp.run(src.as_bytes(), &mut output).unwrap();
+        (ok, interp.failure().map(Failure::exit_code))
+    };
+    assert_eq!(run("[a] drop"), (true, None));
+    assert_eq!(run("drop"), (false, Some(1)));
+    assert_eq!(run("[a"), (false, Some(2)));
+    assert_eq!(run("{rule foo => $x}"), (false, Some(4)));
+    assert_eq!(interp.quit_code(), None);
+    let mut output = vec![];
+    interp.interp_start(":quit 256", &mut output).unwrap();
+    assert_eq!(output, b"Exit codes are 0 to 255.\n");
+    assert_eq!(interp.quit_code(), None);
+    interp.interp_start(":quit 3", &mut output).unwrap();
+    assert_eq!(interp.quit_code(), Some(3));
+}
+
+#[test]
+fn test_interrupt() {
+    use std::sync::atomic::Ordering;
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    let interrupt = interp.interrupt_flag();
+    let mut output = vec![];
+    interp
+        .interp_start("{fn loop = clone apply}[clone apply] loop", &mut output)
+        .unwrap();
+    for _ in 0..3 {
+        interp.interp_step(&mut output).unwrap();
+    }
+    assert!(!interp.is_done());
+    interrupt.store(true, Ordering::SeqCst);
+    interp.interp_step(&mut output).unwrap();
+    assert!(interp.is_done());
+    assert!(!interrupt.load(Ordering::SeqCst));
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.ends_with("Interrupted.\n"), "{}", output);
+}
+
+#[test]
+fn test_redefine_on_stack() {
+    let mut interp = InterpBuilder::new()
+        .prelude(Prelude::None)
+        .settings_path(None)
+        .build();
+    eval_input(&mut interp, "{fn x = [a]}");
+    eval_input(&mut interp, "x");
+    assert!(eval_input(&mut interp, "{fn x = b}").starts_with("Redefined `x`.\n"));
+    let output = eval_input(&mut interp, "apply");
+    assert!(output.ends_with("UndefinedFn(\"a\")\n"), "{}", output);
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::latex::*;
+
+#[test]
+fn test_derivation_latex() {
+    let mut ctx = Context::default();
+    let f = ctx.parse_fn_def("{fn f_1 = clone}").unwrap();
+    ctx.define_fn(f).unwrap();
+    let e = ctx.parse_expr("[a] f_1").unwrap();
+    let (tex, steps) = ctx.derivation_latex(&e).unwrap();
+    assert_eq!(steps, 3);
+    assert_eq!(
+        tex,
+        concat!(
+            "% The derivation of `[a] f_1`, by ucc\n",
+            "\\providecommand{\\uccstate}[2]{\\langle #1 \\rangle\\ #2}\n",
+            "\\providecommand{\\uccstep}{\\longrightarrow}\n",
+            "\\providecommand{\\uccrule}[1]{\\textsc{#1}}\n",
+            "\\[\n",
+            "\\begin{array}{lll}\n",
+            "  & \\uccstate{}{\\texttt{[a] f\\_1}} & \\\\\n",
+            "  \\uccstep & \\uccstate{\\texttt{[a]}}{\\texttt{f\\_1}} & \\uccrule{quote-push} \\\\\n",
+            "  \\uccstep & \\uccstate{\\texttt{[a]}}{\\texttt{clone}} & \\uccrule{call-expand}~\\texttt{f\\_1} \\\\\n",
+            "  \\uccstep & \\uccstate{\\texttt{[a] [a]}}{} & \\uccrule{clone} \\\\\n",
+            "\\end{array}\n",
+            "\\]\n",
+        )
+    );
+}
+
+#[test]
+fn test_derivation_latex_errors() {
+    let mut ctx = Context::default();
+    let e = ctx.parse_expr("[a] drop drop").unwrap();
+    assert_eq!(
+        ctx.derivation_latex(&e),
+        Err(LatexError::Eval(
+            EvalError::TooFewValues {
+                available: 0,
+                expected: 1
+            },
+            2
+        ))
+    );
+    let e = ctx.parse_expr("[clone apply] clone apply").unwrap();
+    assert_eq!(
+        ctx.derivation_latex(&e),
+        Err(LatexError::TooManySteps(LATEX_MAX_STEPS))
+    );
+}
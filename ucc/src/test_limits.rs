@@ -0,0 +1,125 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::interp::Interp;
+use crate::limits::*;
+
+#[test]
+fn test_definition_limits() {
+    let mut ctx = Context::default();
+    ctx.set_definition_limits(DefinitionLimits {
+        body_size: Some(3),
+        fns: Some(2),
+        name_len: Some(4),
+    });
+    let cases = [
+        ("{fn a = [swap] apply}", Ok(None)),
+        (
+            "{fn a = [swap drop] apply}",
+            Err(LimitError::BodyTooLarge { size: 4, limit: 3 }),
+        ),
+        (
+            "{fn abcde = }",
+            Err(LimitError::NameTooLong { len: 5, limit: 4 }),
+        ),
+        (
+            "{fn b = 'abcde}",
+            Err(LimitError::NameTooLong { len: 5, limit: 4 }),
+        ),
+        ("{fn b = a}", Ok(None)),
+        ("{fn c = a}", Err(LimitError::TooManyFns { limit: 2 })),
+        // Redefinitions don't add functions
+        ("{fn b = }", Ok(Some(()))),
+    ];
+    for (src, expected) in cases {
+        let fn_def = ctx.parse_fn_def(src).unwrap();
+        assert_eq!(
+            ctx.define_fn(fn_def).map(|old| old.map(|_| ())),
+            expected,
+            "Failed on {}",
+            src
+        );
+    }
+
+    let mut interp = Interp::default();
+    interp.set_definition_limits(DefinitionLimits {
+        body_size: Some(1),
+        ..DefinitionLimits::default()
+    });
+    let mut buffer = vec![];
+    assert!(!interp
+        .run("{fn x = swap swap}\n".as_bytes(), &mut buffer)
+        .unwrap());
+    assert!(!interp
+        .run("'x [swap swap] define!\n".as_bytes(), &mut buffer)
+        .unwrap());
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "BodyTooLarge { size: 2, limit: 1 }\n\
+         ⟨⟩ 'x [swap swap] define!\n\
+         ⇓ ⟨'x [swap swap]⟩ define!\n\
+         Limit(BodyTooLarge { size: 2, limit: 1 })\n"
+    );
+}
+
+/// A `.uccb` file with a definition over the limits loads nothing either
+#[test]
+fn test_load_binary_over_limits() {
+    let path = std::env::temp_dir().join("ucc_test_load_binary_over_limits.uccb");
+    let bytes = crate::binary::pack("{fn ok = swap}\n{fn big = swap swap}\n").unwrap();
+    std::fs::write(&path, bytes).unwrap();
+    let mut interp = Interp::default();
+    interp.set_definition_limits(DefinitionLimits {
+        body_size: Some(1),
+        ..DefinitionLimits::default()
+    });
+    let mut buffer = vec![];
+    for input in [
+        format!(":load \"{}\"", path.display()),
+        ":show ok".to_owned(),
+    ] {
+        interp.interp_start(&input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+    }
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "`big`: BodyTooLarge { size: 2, limit: 1 }\n\
+         Nothing loaded.\n\
+         Not defined.\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// A file with a definition over the limits loads nothing
+#[test]
+fn test_load_over_limits() {
+    let path = std::env::temp_dir().join("ucc_test_load_over_limits.ucc");
+    std::fs::write(&path, "{fn ok = swap}\n[a] ok\n{fn big = swap swap}\n").unwrap();
+    let mut interp = Interp::default();
+    interp.set_definition_limits(DefinitionLimits {
+        body_size: Some(1),
+        ..DefinitionLimits::default()
+    });
+    let mut buffer = vec![];
+    for input in [
+        format!(":load \"{}\"", path.display()),
+        ":show ok".to_owned(),
+    ] {
+        interp.interp_start(&input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+    }
+    assert_eq!(
+        std::str::from_utf8(&buffer).unwrap(),
+        "`big`: BodyTooLarge { size: 2, limit: 1 }\n\
+         Nothing loaded.\n\
+         Not defined.\n"
+    );
+}
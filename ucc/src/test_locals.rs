@@ -0,0 +1,56 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::test_util::eval;
+
+/// Evaluate `src` to completion on `vs`, then compress it
+fn eval_src(ctx: &mut Context, vs: &mut ValueStack, src: &str) -> Result<(), EvalError> {
+    let e = ctx.parse_expr(src).unwrap();
+    eval(ctx, vs, e)?;
+    ctx.compress(vs);
+    Ok(())
+}
+
+#[test]
+fn test_locals() {
+    let mut ctx = Context::default();
+    let fn_def = ctx.parse_fn_def("{fn v = [x]}").unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    let v = Symbol(ctx.interner.get("v").unwrap());
+    let mut vs = ValueStack::default();
+
+    let fn_defs = vec![
+        ctx.parse_fn_def("{fn sq = clone compose}").unwrap(),
+        ctx.parse_fn_def("{fn v = [y]}").unwrap(),
+    ];
+    ctx.push_locals(fn_defs);
+    assert!(ctx.is_local(&v));
+    // Inner scopes shadow outer ones
+    let fn_def = ctx.parse_fn_def("{fn w = v sq}").unwrap();
+    ctx.push_locals(vec![fn_def]);
+    eval_src(&mut ctx, &mut vs, "w [x]").unwrap();
+    // `[x]` isn't compressed to `v`, which is shadowed
+    assert_eq!(ctx.display(&vs).to_string(), "⟨[y y] [x]⟩");
+    ctx.pop_locals(&mut vs);
+    assert_eq!(
+        eval_src(&mut ctx, &mut vs, "w").resolve(&ctx.interner),
+        Err(ResolvedEvalError::UndefinedFn("w".to_owned()))
+    );
+
+    // Calls of local quotations are replaced once out of scope
+    eval_src(&mut ctx, &mut vs, "v").unwrap();
+    assert_eq!(vs.0.last(), Some(&Value::Call(v)));
+    ctx.pop_locals(&mut vs);
+    assert!(!ctx.is_local(&v));
+    ctx.compress(&mut vs);
+    assert_eq!(ctx.display(&vs).to_string(), "⟨[y y] v [y]⟩");
+
+    // Popping with no scope open does nothing
+    ctx.pop_locals(&mut vs);
+    assert_eq!(ctx.fns.len(), 1);
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::interp::Interp;
+use crate::parse::*;
+
+#[test]
+fn test_map_intrinsics() {
+    let cases = [
+        ("⟨⟩ #{} ⟶ ⟨#{}⟩", Ok(())),
+        ("⟨⟩ #{'a => [x],} ⟶ ⟨#{'a => [x]}⟩", Ok(())),
+        ("⟨#{} 'a [x]⟩ map.insert ⟶ ⟨#{'a => [x]}⟩", Ok(())),
+        ("⟨#{'a => [x]} 'a [y]⟩ map.insert ⟶ ⟨#{'a => [y]}⟩", Ok(())),
+        ("⟨#{'a => [x]} 'a⟩ map.lookup ⟶ ⟨[x]⟩", Ok(())),
+        ("⟨#{'a => [x]} 'a⟩ map.contains ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨#{'a => [x]} 'b⟩ map.contains ⟶ ⟨[drop]⟩", Ok(())),
+        ("⟨#{'a => [x]} 'a⟩ map.remove ⟶ ⟨#{}⟩", Ok(())),
+        ("⟨#{'a => [x]} 'b⟩ map.remove ⟶ ⟨#{'a => [x]}⟩", Ok(())),
+        ("⟨#{('a, 'b) => [x]}⟩ map.keys ⟶ ⟨[('a, 'b)]⟩", Ok(())),
+        (
+            "⟨#{'a => [x]} 'b⟩ map.lookup ⟶ ⟨⟩ map.lookup",
+            Err(ResolvedEvalError::Host("key not found".to_owned())),
+        ),
+        (
+            "⟨[a] 'a⟩ map.lookup ⟶ ⟨⟩ map.lookup",
+            Err(ResolvedEvalError::Host("expected a map".to_owned())),
+        ),
+        (
+            "⟨#{}⟩ apply ⟶ ⟨⟩ apply",
+            Err(ResolvedEvalError::ExpectedQuote),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+/// Changing a copy of a map leaves the map as it was
+#[test]
+fn test_map_persistence() {
+    let mut interp = Interp::default();
+    let mut output = vec![];
+    interp
+        .interp_start(
+            "#{'a => [x]} clone 'b [y] map.insert swap 'a map.remove",
+            &mut output,
+        )
+        .unwrap();
+    while !interp.is_done() {
+        interp.interp_step(&mut output).unwrap();
+    }
+    let output = String::from_utf8(output).unwrap();
+    assert!(
+        output.ends_with("⇓ ⟨#{'b => [y], 'a => [x]} #{}⟩ \n")
+            || output.ends_with("⇓ ⟨#{'a => [x], 'b => [y]} #{}⟩ \n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_map_binary() {
+    let mut ctx = Context::default();
+    let fn_def = ctx
+        .parse_fn_def("{fn table = #{'a => [x], ('b, 'c) => #{'d => [y]}} 'a map.lookup}")
+        .unwrap();
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+
+    // Hashes don't depend on the order entries are kept in
+    let ab = ctx.parse_value("#{'a => [x], 'b => [y]}").unwrap();
+    let mut other = Context::default();
+    other.interner.get_or_intern("b");
+    let ba = other.parse_value("#{'b => [y], 'a => [x]}").unwrap();
+    assert_eq!(
+        ab.stable_hash(&ctx.interner),
+        ba.stable_hash(&other.interner)
+    );
+    let a = ctx.parse_value("#{'a => [x]}").unwrap();
+    assert_ne!(a.stable_hash(&ctx.interner), ab.stable_hash(&ctx.interner));
+}
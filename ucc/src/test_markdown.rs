@@ -0,0 +1,43 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::markdown::*;
+
+fn block(code: &str, expected: Option<&str>) -> CodeBlock {
+    CodeBlock {
+        code: code.to_owned(),
+        expected: expected.map(str::to_owned),
+    }
+}
+
+#[test]
+fn test_code_blocks() {
+    let cases = [
+        ("No code.\n", vec![]),
+        (
+            "# Swap\n\n```ucc\n[a] [b] swap\n```\n\n```output\n⟨[b] [a]⟩\n```\n",
+            vec![block("[a] [b] swap\n", Some("⟨[b] [a]⟩\n"))],
+        ),
+        (
+            "```ucc\n{fn f = }\n```\nProse.\n```output\n⟨⟩\n```\n",
+            vec![block("{fn f = }\n", None)],
+        ),
+        (
+            "```rust\nfn main() {}\n```\n~~~ ucc extra\na\n```\nb\n~~~\n````ucc\n```\n````\n",
+            vec![block("a\n```\nb\n", None), block("```\n", None)],
+        ),
+        ("```ucc\nunclosed\n", vec![block("unclosed\n", None)]),
+    ];
+    for (src, expected) in cases {
+        assert_eq!(code_blocks(src), expected, "Failed on {:?}", src);
+    }
+}
+
+#[test]
+fn test_matches_expected() {
+    assert!(matches_expected("⟨[b] [a]⟩ ", "⟨[b]  [a]⟩\n"));
+    assert!(!matches_expected("⟨[b] [a]⟩", "⟨[a] [b]⟩"));
+}
@@ -0,0 +1,41 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::interp::InterpStats;
+use crate::metrics::*;
+
+#[test]
+fn test_to_prometheus() {
+    let metrics = Metrics {
+        totals: InterpStats {
+            evaluations: 3,
+            small_steps: 120,
+            errors: 1,
+        },
+        sessions: vec![("a".to_owned(), 2048), ("b\"c".to_owned(), 512)],
+    };
+    assert_eq!(
+        metrics.to_prometheus(),
+        concat!(
+            "# HELP ucc_evaluations_total Commands started that evaluate.\n",
+            "# TYPE ucc_evaluations_total counter\n",
+            "ucc_evaluations_total 3\n",
+            "# HELP ucc_small_steps_total Small steps taken.\n",
+            "# TYPE ucc_small_steps_total counter\n",
+            "ucc_small_steps_total 120\n",
+            "# HELP ucc_errors_total Small steps that failed.\n",
+            "# TYPE ucc_errors_total counter\n",
+            "ucc_errors_total 1\n",
+            "# HELP ucc_active_sessions Sessions open.\n",
+            "# TYPE ucc_active_sessions gauge\n",
+            "ucc_active_sessions 2\n",
+            "# HELP ucc_session_memory_bytes Estimated bytes each session holds.\n",
+            "# TYPE ucc_session_memory_bytes gauge\n",
+            "ucc_session_memory_bytes{session=\"a\"} 2048\n",
+            "ucc_session_memory_bytes{session=\"b\\\"c\"} 512\n",
+        )
+    );
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::minimize::*;
+
+fn minimize(ctx: &mut Context, src: &str) -> Result<(String, EvalError), MinimizeError> {
+    let e = ctx.parse_expr(src).unwrap();
+    ctx.minimize(&e)
+        .map(|(e, err)| (ctx.display(&e).to_string(), err))
+}
+
+#[test]
+fn test_minimize() {
+    let mut ctx = Context::default();
+    assert_eq!(
+        minimize(&mut ctx, "[a] [b] swap [c] compose drop drop drop"),
+        Ok((
+            "drop".to_owned(),
+            EvalError::TooFewValues {
+                available: 0,
+                expected: 1
+            }
+        ))
+    );
+    let c = Symbol(ctx.interner.get_or_intern("c"));
+    assert_eq!(
+        minimize(&mut ctx, "[a] [b] [[c] [f] compose apply] apply drop"),
+        Ok(("c".to_owned(), EvalError::UndefinedFn(c)))
+    );
+    assert_eq!(
+        minimize(&mut ctx, "[a] [b] swap drop"),
+        Err(MinimizeError::NoFailure)
+    );
+}
+
+#[test]
+fn test_minimize_keeps_definitions() {
+    let mut ctx = Context::default();
+    let fn_def = ctx.parse_fn_def("{fn f = drop}").unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    assert_eq!(
+        minimize(&mut ctx, "[a] [[clone]] 'f define! f f f"),
+        Ok(("define!".to_owned(), EvalError::DefineDisabled))
+    );
+    let f = Symbol(ctx.interner.get_or_intern("f"));
+    assert_eq!(ctx.lookup_fn(&f), Some(&Expr::Intrinsic(Intrinsic::Drop)));
+}
+
+#[test]
+fn test_shrink_counterexample() {
+    let mut ctx = Context::default();
+    let vs = ctx.parse_value_stack("⟨[a] [b] [c]⟩").unwrap();
+    let e = ctx.parse_expr("[x] drop swap clone drop").unwrap();
+    let b = ctx.parse_value("[b]").unwrap();
+    // Standing in for two engines differing: `[b]` ends up on top
+    let (vs, e) = ctx.shrink_counterexample(&vs, &e, |ctx, vs, e| {
+        let run = ctx.run_interpreted(vs, e, 100);
+        run.result.is_ok() && run.vs.0.last() == Some(&b)
+    });
+    assert_eq!(ctx.display(&vs).to_string(), "⟨[b] [c]⟩");
+    assert_eq!(ctx.display(&e).to_string(), "swap");
+    assert_eq!(
+        ctx.big_step_assertion(&vs, &e, 100),
+        "⟨[b] [c]⟩ swap ⇓ ⟨[c] [b]⟩"
+    );
+    let e = ctx.parse_expr("swap swap swap").unwrap();
+    assert_eq!(
+        ctx.big_step_assertion(&vs, &e, 2),
+        "⟨[b] [c]⟩ swap swap swap ⇓ ⟨[b] [c]⟩ swap"
+    );
+}
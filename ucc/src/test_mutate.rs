@@ -0,0 +1,43 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::mutate::*;
+
+#[test]
+fn test_mutation_test() {
+    let lib = "{fn swap2 = [swap] apply}";
+    let results = mutation_test(lib, "⟨[a] [b]⟩ swap2 ⇓ ⟨[b] [a]⟩\n", 100).unwrap();
+    assert_eq!(results.len(), 17);
+    assert!(results.iter().all(|result| !result.survived));
+
+    let lib = "{fn drop2 = drop drop}";
+    let tests = "-- Drops two values\n\
+                 ⟨[a] [b]⟩ drop2 ⇓ ⟨⟩\n";
+    let survivors: Vec<_> = mutation_test(lib, tests, 100)
+        .unwrap()
+        .into_iter()
+        .filter(|result| result.survived)
+        .map(|result| (result.fn_def, result.mutation))
+        .collect();
+    assert_eq!(
+        survivors,
+        [
+            (
+                "{fn drop2 = swap drop drop}".to_owned(),
+                "swapped the arguments of `drop`".to_owned()
+            ),
+            (
+                "{fn drop2 = compose drop}".to_owned(),
+                "replaced `drop` with `compose`".to_owned()
+            ),
+        ]
+    );
+
+    assert_eq!(
+        mutation_test(lib, "⟨[a] [b]⟩ drop2 ⇓ ⟨[a]⟩", 100),
+        Err(MutateError::Fails("⟨[a] [b]⟩ drop2 ⇓ ⟨[a]⟩".to_owned()))
+    );
+}
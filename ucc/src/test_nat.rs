@@ -0,0 +1,129 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::interp::Interp;
+use crate::nat::*;
+use crate::parse::*;
+
+#[test]
+fn test_nat_intrinsics() {
+    let cases = [
+        ("⟨⟩ 12345678901234567890 ⟶ ⟨12345678901234567890⟩", Ok(())),
+        (
+            "⟨99999999999999999999 1⟩ nat.add ⟶ ⟨100000000000000000000⟩",
+            Ok(()),
+        ),
+        ("⟨7 2⟩ nat.sub ⟶ ⟨5⟩", Ok(())),
+        ("⟨2 7⟩ nat.sub ⟶ ⟨0⟩", Ok(())),
+        ("⟨6 7⟩ nat.mul ⟶ ⟨42⟩", Ok(())),
+        ("⟨7 2⟩ nat.div ⟶ ⟨3⟩", Ok(())),
+        ("⟨7 2⟩ nat.mod ⟶ ⟨1⟩", Ok(())),
+        ("⟨7 7⟩ nat.eq ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨7 2⟩ nat.lt ⟶ ⟨[drop]⟩", Ok(())),
+        ("⟨0⟩ nat.succ ⟶ ⟨1⟩", Ok(())),
+        (
+            "⟨3⟩ nat.to_church ⟶ ⟨[clone clone compose compose apply]⟩",
+            Ok(()),
+        ),
+        ("⟨1⟩ nat.to_church ⟶ ⟨[apply]⟩", Ok(())),
+        ("⟨0⟩ nat.to_church ⟶ ⟨[drop]⟩", Ok(())),
+        (
+            "⟨7 0⟩ nat.div ⟶ ⟨⟩ nat.div",
+            Err(ResolvedEvalError::Host("division by zero".to_owned())),
+        ),
+        (
+            "⟨[a] 1⟩ nat.add ⟶ ⟨⟩ nat.add",
+            Err(ResolvedEvalError::Host("expected a number".to_owned())),
+        ),
+        (
+            "⟨1⟩ nat.add ⟶ ⟨1⟩ nat.add",
+            Err(ResolvedEvalError::TooFewValues {
+                available: 1,
+                expected: 2,
+            }),
+        ),
+        (
+            "⟨3⟩ apply ⟶ ⟨⟩ apply",
+            Err(ResolvedEvalError::ExpectedQuote),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+#[test]
+fn test_church() {
+    let mut interp = Interp::default();
+    let cases = [
+        ("n3 nat.from_church", "⇓ ⟨3⟩ \n"),
+        ("n0 nat.from_church", "⇓ ⟨0⟩ \n"),
+        ("n3 n4 mul nat.from_church", "⇓ ⟨12⟩ \n"),
+        ("200 nat.to_church nat.from_church", "⇓ ⟨200⟩ \n"),
+        ("[v1] 2 nat.to_church apply", "⇓ ⟨v1 v1⟩ \n"),
+    ];
+    for (input, expected) in cases {
+        let mut output = vec![];
+        interp.interp_start(":drop", &mut output).unwrap();
+        output.clear();
+        interp.interp_start(input, &mut output).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut output).unwrap();
+        }
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.ends_with(expected),
+            "Failed on {}: {}",
+            input,
+            output
+        );
+    }
+
+    let mut ctx = Context::default();
+    let mut vs = ValueStack::from(vec![Value::Nat((MAX_CHURCH + 1).into())]);
+    let mut e = ctx.parse_expr("nat.to_church").unwrap();
+    assert_eq!(
+        ctx.small_step(&mut vs, &mut e).resolve(&ctx.interner),
+        Err(ResolvedEvalError::Host(format!(
+            "{} is over {}, too large for a numeral",
+            MAX_CHURCH + 1,
+            MAX_CHURCH
+        )))
+    );
+}
+
+#[test]
+fn test_nat_binary() {
+    let mut ctx = Context::default();
+    let fn_def = ctx
+        .parse_fn_def("{fn big = [340282366920938463463374607431768211456 0] apply}")
+        .unwrap();
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+
+    let one = ctx.parse_expr("1").unwrap();
+    let two = ctx.parse_expr("2").unwrap();
+    assert_ne!(
+        one.stable_hash(&ctx.interner),
+        two.stable_hash(&ctx.interner)
+    );
+    let v = ctx.parse_value("1000000000000000000000").unwrap();
+    assert_eq!(ctx.display(&v).to_string(), "1000000000000000000000");
+}
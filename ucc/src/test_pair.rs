@@ -0,0 +1,100 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::interp::Interp;
+use crate::parse::*;
+
+#[test]
+fn test_pair_intrinsics() {
+    let cases = [
+        ("⟨⟩ ('a, [b]) ⟶ ⟨('a, [b])⟩", Ok(())),
+        ("⟨⟩ (('a, 'b), []) ⟶ ⟨(('a, 'b), [])⟩", Ok(())),
+        ("⟨'a [b]⟩ pair ⟶ ⟨('a, [b])⟩", Ok(())),
+        ("⟨('a, [b])⟩ fst ⟶ ⟨'a⟩", Ok(())),
+        ("⟨('a, [b])⟩ snd ⟶ ⟨[b]⟩", Ok(())),
+        (
+            "⟨[a]⟩ fst ⟶ ⟨⟩ fst",
+            Err(ResolvedEvalError::Host("expected a pair".to_owned())),
+        ),
+        (
+            "⟨[a]⟩ pair ⟶ ⟨[a]⟩ pair",
+            Err(ResolvedEvalError::TooFewValues {
+                available: 1,
+                expected: 2,
+            }),
+        ),
+        (
+            "⟨('a, 'b)⟩ apply ⟶ ⟨⟩ apply",
+            Err(ResolvedEvalError::ExpectedQuote),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+#[test]
+fn test_pair_parse() {
+    let mut ctx = Context::default();
+    let e = ctx.parse_expr("('a, [b c]) (swap drop) fst").unwrap();
+    assert_eq!(ctx.display(&e).to_string(), "('a, [b c]) (swap drop) fst");
+    assert!(ctx.parse_expr("('a, swap)").is_err());
+    assert!(ctx.parse_expr("('a, 'b, 'c)").is_err());
+}
+
+#[test]
+fn test_pair_quote_conversions() {
+    let mut interp = Interp::default();
+    let cases = [
+        ("('a, [b]) pair_to_quote", "⇓ ⟨['a [b]]⟩ \n"),
+        ("['a [b]] quote_to_pair", "⇓ ⟨('a, [b])⟩ \n"),
+        ("[v1] [v2] pair snd", "⇓ ⟨[v2]⟩ \n"),
+    ];
+    for (input, expected) in cases {
+        let mut output = vec![];
+        interp.interp_start(":drop", &mut output).unwrap();
+        output.clear();
+        interp.interp_start(input, &mut output).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut output).unwrap();
+        }
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.ends_with(expected),
+            "Failed on {}: {}",
+            input,
+            output
+        );
+    }
+}
+
+#[test]
+fn test_pair_binary() {
+    let mut ctx = Context::default();
+    let fn_def = ctx
+        .parse_fn_def("{fn p = (('a, [b]), c) clone fst}")
+        .unwrap();
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+
+    let ab = ctx.parse_expr("('a, 'b)").unwrap();
+    let ba = ctx.parse_expr("('b, 'a)").unwrap();
+    assert_ne!(ab.stable_hash(&ctx.interner), ba.stable_hash(&ctx.interner));
+}
@@ -5,6 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use crate::core::*;
+use crate::display::*;
 use crate::interp::*;
 use crate::parse::*;
 
@@ -142,3 +143,49 @@ fn test_parse_expr_quote_call2() {
         assert_eq!(e, e2);
     }
 }
+
+#[test]
+fn test_parse_symbol() {
+    let interner = &mut Interner::default();
+    let e = ExprParser::new().parse(interner, "'foo").unwrap();
+    assert_eq!(e, Expr::Symbol(Symbol(interner.get("foo").unwrap())));
+    let v = ValueParser::new().parse(interner, "'foo").unwrap();
+    assert_eq!(v, Value::Symbol(Symbol(interner.get("foo").unwrap())));
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn test_parse_number_needs_bignum() {
+    let interner = &mut Interner::default();
+    assert_eq!(
+        ExprParser::new().parse(interner, "[a] 42"),
+        Err(lalrpop_util::ParseError::User {
+            error: "number literals need the `bignum` feature"
+        })
+    );
+}
+
+#[cfg(not(feature = "rational"))]
+#[test]
+fn test_parse_ratio_needs_rational() {
+    let interner = &mut Interner::default();
+    assert_eq!(
+        ExprParser::new().parse(interner, "[a] 3/4"),
+        Err(lalrpop_util::ParseError::User {
+            error: "ratio literals need the `rational` feature"
+        })
+    );
+}
+
+#[test]
+fn test_display_round_trip() {
+    let cases = ["'foo ['bar.baz] quux", "swap [clone] (foo 'bar) apply"];
+    for src in cases {
+        let interner = &mut Interner::default();
+        let e = ExprParser::new().parse(interner, src).unwrap();
+        let displayed = e.resolve(interner).to_string();
+        assert_eq!(displayed, src);
+        let e2 = ExprParser::new().parse(interner, &displayed).unwrap();
+        assert_eq!(e, e2);
+    }
+}
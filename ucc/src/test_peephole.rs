@@ -0,0 +1,93 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::peephole::*;
+use crate::shared::SharedPrelude;
+use crate::vm::*;
+
+#[test]
+fn test_peephole() {
+    let swap = Op::Intrinsic(Intrinsic::Swap);
+    let clone = Op::Intrinsic(Intrinsic::Clone);
+    let drop = Op::Intrinsic(Intrinsic::Drop);
+    let apply = Op::Intrinsic(Intrinsic::Apply);
+    let cases = [
+        (vec![Op::Push(0), drop.clone()], vec![]),
+        (vec![swap.clone(), swap.clone()], vec![Op::Check(2)]),
+        (vec![clone.clone(), drop.clone()], vec![Op::Check(1)]),
+        (
+            vec![swap.clone(), swap.clone(), swap.clone()],
+            vec![Op::Check(2), swap.clone()],
+        ),
+        // Pairs that come together once the pairs between them are removed
+        (
+            vec![Op::Push(0), Op::Push(1), drop.clone(), drop.clone()],
+            vec![],
+        ),
+        (
+            vec![apply.clone(), drop.clone()],
+            vec![apply.clone(), drop.clone()],
+        ),
+    ];
+    for (ops, expected) in cases {
+        assert_eq!(peephole(ops.clone()), expected, "Failed on {:?}", ops);
+    }
+}
+
+#[test]
+fn test_optimized() {
+    let mut ctx = Context::with_prelude(&SharedPrelude::default());
+    let vs = ctx.parse_value_stack("⟨[a] [b]⟩").unwrap();
+    let cases = [
+        ("swap swap", "⟨[a] [b]⟩", 1),
+        ("[c] drop clone drop", "⟨[a] [b]⟩", 1),
+        ("true false or", "⟨[a] [b] true⟩", 0),
+    ];
+    for (e, expected, saved) in cases {
+        let e = ctx.parse_expr(e).unwrap();
+        let compiled = ctx.run_compiled(&vs, &e, 1000);
+        let mut optimized = ctx.run_optimized(&vs, &e, 1000);
+        assert_eq!(optimized.result, Ok(()), "Failed on {}", ctx.display(&e));
+        ctx.compress(&mut optimized.vs);
+        assert_eq!(ctx.display(&optimized.vs).to_string(), expected);
+        assert!(
+            optimized.fuel >= compiled.fuel + saved,
+            "Failed on {}: {} {}",
+            ctx.display(&e),
+            optimized.fuel,
+            compiled.fuel,
+        );
+        assert_eq!(ctx.differential_optimized(&vs, &e, 1000), None);
+    }
+}
+
+#[test]
+fn test_optimized_errors() {
+    let mut ctx = Context::with_prelude(&SharedPrelude::default());
+    let vs = ctx.parse_value_stack("⟨[a]⟩").unwrap();
+    for e in ["swap swap", "drop clone drop", "[b] drop swap swap"] {
+        let e = ctx.parse_expr(e).unwrap();
+        assert_eq!(
+            ctx.run_optimized(&vs, &e, 1000).result,
+            ctx.run_interpreted(&vs, &e, 1000).result,
+            "Failed on {}",
+            ctx.display(&e)
+        );
+        assert_eq!(ctx.differential_optimized(&vs, &e, 1000), None);
+    }
+}
+
+#[test]
+fn test_inlinable() {
+    let mut ctx = Context::default();
+    let small = ctx.parse_expr("swap drop").unwrap();
+    let large = ctx
+        .parse_expr("[a] [b] [c] [d] [e] [f] [g] [h] [i]")
+        .unwrap();
+    assert!(inlinable(&small));
+    assert!(!inlinable(&large));
+}
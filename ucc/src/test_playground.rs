@@ -0,0 +1,76 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::capabilities::Capabilities;
+use crate::interp::TraceStep;
+use crate::playground::*;
+
+#[test]
+fn test_parse_client_message() {
+    let cases = [
+        (
+            r#"{"type": "eval", "input": "[a] clone"}"#,
+            Ok(ClientMessage::Eval("[a] clone".to_owned())),
+        ),
+        (
+            r#" { "input" : "\"é😀\n", "type":"eval" } "#,
+            Ok(ClientMessage::Eval("\"é😀\n".to_owned())),
+        ),
+        (r#"{"type": "pause"}"#, Ok(ClientMessage::Pause)),
+        (r#"{"type": "resume"}"#, Ok(ClientMessage::Resume)),
+        (r#"{"type": "cancel"}"#, Ok(ClientMessage::Cancel)),
+        (
+            r#"{"type": "capabilities"}"#,
+            Ok(ClientMessage::Capabilities),
+        ),
+        (r#"{"type": "eval"}"#, Err("expected an `input`".to_owned())),
+        (
+            r#"{"type": "stop"}"#,
+            Err("unknown message type `stop`".to_owned()),
+        ),
+        (r#"{}"#, Err("expected a `type`".to_owned())),
+        (r#"{"type": 1}"#, Err("expected a JSON object".to_owned())),
+        (
+            r#"{"type": "pause"} x"#,
+            Err("expected a JSON object".to_owned()),
+        ),
+    ];
+    for (json, message) in cases {
+        assert_eq!(ClientMessage::parse(json), message, "Failed on {:?}", json);
+    }
+}
+
+#[test]
+fn test_server_message_to_json() {
+    let cases = [
+        (
+            ServerMessage::Step(TraceStep {
+                stack: "⟨[a]⟩".to_owned(),
+                expr: "clone".to_owned(),
+            }),
+            r#"{"type": "step", "stack": "⟨[a]⟩", "expr": "clone"}"#,
+        ),
+        (
+            ServerMessage::Output("⇓ \"a\"\n".to_owned()),
+            r#"{"type": "output", "text": "⇓ \"a\"\n"}"#,
+        ),
+        (ServerMessage::Done, r#"{"type": "done"}"#),
+        (
+            ServerMessage::Capabilities(Capabilities {
+                version: "0.1.0",
+                features: vec!["net", "vm"],
+            }),
+            r#"{"type": "capabilities", "capabilities": {"version": "0.1.0", "features": ["net", "vm"]}}"#,
+        ),
+        (
+            ServerMessage::Error("bad".to_owned()),
+            r#"{"type": "error", "message": "bad"}"#,
+        ),
+    ];
+    for (message, json) in cases {
+        assert_eq!(message.to_json(), json);
+    }
+}
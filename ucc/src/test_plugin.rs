@@ -0,0 +1,88 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+use crate::plugin::*;
+
+fn register(registrar: &mut PluginRegistrar) -> Result<(), String> {
+    registrar.register_intrinsic("dup", 1, |vs| {
+        let v = vs.pop().unwrap();
+        vs.push(v.clone());
+        vs.push(v);
+        Ok(Expr::default())
+    });
+    registrar.define("{fn dup2 = dup dup}")
+}
+
+fn register_bad(registrar: &mut PluginRegistrar) -> Result<(), String> {
+    registrar.define("{fn = }")
+}
+
+#[test]
+fn test_register_plugin() {
+    let mut ctx = Context::default();
+    assert_eq!(
+        ctx.register_plugin(register),
+        Ok(vec!["dup".to_owned(), "dup2".to_owned()])
+    );
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨[a]⟩ dup2 ⟶ ⟨[a]⟩ dup dup")
+        .unwrap();
+    assert_eq!(ctx.small_step(&mut ssa.0, &mut ssa.1), Ok(()));
+    assert_eq!(ssa.1, ssa.3);
+    assert_eq!(ctx.small_step(&mut ssa.0, &mut ssa.1), Ok(()));
+    assert_eq!(ssa.0.len(), 2);
+
+    assert!(matches!(
+        ctx.register_plugin(register_bad),
+        Err(PluginError::Register(_))
+    ));
+}
+
+/// Fails after registering an intrinsic
+fn register_partial(registrar: &mut PluginRegistrar) -> Result<(), String> {
+    registrar.register_intrinsic("dup", 1, |vs| {
+        let v = vs.pop().unwrap();
+        vs.push(v.clone());
+        vs.push(v);
+        Ok(Expr::default())
+    });
+    registrar.define("{fn dup2 = dup dup}")?;
+    Err("out of luck".to_owned())
+}
+
+#[test]
+fn test_register_plugin_rollback() {
+    let mut ctx = Context::default();
+    assert_eq!(
+        ctx.register_plugin(register_partial),
+        Err(PluginError::Register("out of luck".to_owned()))
+    );
+    // Neither the intrinsic nor the definition was kept
+    for src in ["dup", "dup2"] {
+        let mut vs = ctx.parse_value_stack("⟨[a]⟩").unwrap();
+        let mut e = ctx.parse_expr(src).unwrap();
+        assert_eq!(
+            ctx.small_step(&mut vs, &mut e).resolve(&ctx.interner),
+            Err(ResolvedEvalError::UndefinedFn(src.to_owned())),
+            "Failed on {:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_load_plugin_error() {
+    let mut ctx = Context::default();
+    let result = ctx.load_plugin("/nonexistent/libucc_plugin.so");
+    if cfg!(feature = "plugins") {
+        assert!(matches!(result, Err((PluginError::Load(_), None))));
+    } else {
+        assert!(matches!(result, Err((PluginError::Unsupported, None))));
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::shared::SharedPrelude;
+use crate::transaction::Validation;
+
+#[test]
+fn test_prefold() {
+    let cases = [
+        ("[a] [b] compose", "[a b]"),
+        ("[a] [b] compose swap", "[a b] swap"),
+        ("[a] [b] [c] compose compose apply", "[a b c] apply"),
+        ("[a] [b] swap drop", "[b]"),
+        // Values as large as the prefix
+        ("[a] clone", "[a] clone"),
+        // Prefixes that underflow, or run out of fuel
+        ("drop [a] [b] compose", "drop [a] [b] compose"),
+        ("[clone apply] clone apply", "[clone apply] clone apply"),
+        // Calls of quotations push them
+        ("true true swap drop", "true"),
+        ("[a] [b] swap evalq", "[b] [a] evalq"),
+    ];
+    let mut ctx = Context::with_prelude(&SharedPrelude::default());
+    for (e, expected) in cases {
+        let e = ctx.parse_expr(e).unwrap();
+        let folded = ctx.prefold(&e);
+        assert_eq!(
+            ctx.display(&folded).to_string(),
+            expected,
+            "Failed on {}",
+            ctx.display(&e)
+        );
+    }
+}
+
+#[test]
+fn test_define_prefolded() {
+    let mut ctx = Context::with_prelude(&SharedPrelude::default());
+    let fn_def = ctx.parse_fn_def("{fn f = [a] [b] compose}").unwrap();
+    ctx.define_fn(fn_def.clone()).unwrap();
+    assert_eq!(
+        ctx.display(ctx.lookup_fn(&fn_def.0).unwrap()).to_string(),
+        "[a] [b] compose"
+    );
+
+    ctx.set_prefold(true);
+    ctx.define_fn(fn_def.clone()).unwrap();
+    assert_eq!(
+        ctx.display(ctx.lookup_fn(&fn_def.0).unwrap()).to_string(),
+        "[a b]"
+    );
+
+    // Recursive prefixes call the definition as written
+    let fn_def = ctx
+        .parse_fn_def("{fn g = [[c] [d] compose] [g] drop apply}")
+        .unwrap();
+    ctx.define_fn(fn_def.clone()).unwrap();
+    assert_eq!(
+        ctx.display(ctx.lookup_fn(&fn_def.0).unwrap()).to_string(),
+        "[c d]"
+    );
+
+    let fn_defs = vec![
+        ctx.parse_fn_def("{fn h = [a] k swap drop}").unwrap(),
+        ctx.parse_fn_def("{fn k = [b] [c] compose}").unwrap(),
+    ];
+    ctx.define_all(fn_defs.clone(), Validation::Limits).unwrap();
+    let h = ctx.lookup_fn(&fn_defs[0].0).unwrap();
+    assert_eq!(ctx.display(h).to_string(), "[b c]");
+}
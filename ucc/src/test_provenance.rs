@@ -0,0 +1,72 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::interp::Interp;
+
+fn session(inputs: &[&str]) -> String {
+    let mut interp = Interp::default();
+    let mut buffer = vec![];
+    for input in inputs {
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+    }
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn test_why() {
+    let output = session(&[
+        ":set show-steps off",
+        "[a]",
+        ":why",
+        ":set provenance on",
+        "{fn pair = quote swap quote swap compose}",
+        "[b] pair 'c clone",
+        ":why",
+        ":why c",
+        ":why d",
+        "drop drop",
+        ":why",
+    ]);
+    assert_eq!(
+        output,
+        concat!(
+            "Set `show-steps`.\n",
+            "⇓ ⟨[a]⟩ \n",
+            "Provenance is off; `:set provenance on` to track it.\n",
+            "Set `provenance`.\n",
+            "Defined `pair`.\n",
+            "Warning: `pair` always underflows on an empty stack.\n",
+            "⇓ ⟨[[a] [b]] 'c 'c⟩ \n",
+            "`'c`: pushed at step 8\n",
+            "`'c`: pushed at step 8\n",
+            "No such value on the stack.\n",
+            "⇓ ⟨[[a] [b]]⟩ \n",
+            "`[[a] [b]]`: made by `compose` at step 7, in pair, from\n",
+            "  `[[a]]`: made by `quote` at step 5, in pair, from\n",
+            "    `[a]`: from before provenance was on\n",
+            "  `[[b]]`: made by `quote` at step 3, in pair, from\n",
+            "    `[b]`: pushed at step 1\n",
+        )
+    );
+}
+
+#[test]
+fn test_why_rewrites() {
+    let output = session(&[
+        ":set show-steps off",
+        ":set provenance on",
+        "{rule [$x] dup => [$x] clone}",
+        "[a] dup quote",
+        ":why",
+    ]);
+    assert!(output.ends_with(concat!(
+        "`[[a]]`: made by `quote` at step 4, from\n",
+        "  `[a]`: pushed at step 2, after a rewrite\n",
+    )));
+}
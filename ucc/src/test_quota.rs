@@ -0,0 +1,63 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::limits::DefinitionLimits;
+use crate::quota::*;
+use crate::settings::SettingError;
+
+#[test]
+fn test_parse_quotas() {
+    assert_eq!(Quotas::parse(""), Ok(Quotas::default()));
+    assert_eq!(Quotas::default().isolation, Isolation::Private);
+    assert_eq!(
+        Quotas::parse(
+            "isolation = \"shared\"\nmax-sessions = 10\nsession-idle-seconds = 60\nsteps-per-second = 1000\nmax-definitions = 50\n\
+             max-term-size = 100\nmax-name-length = 16\n"
+        ),
+        Ok(Quotas {
+            isolation: Isolation::Shared,
+            max_sessions: Some(10),
+            session_idle_seconds: Some(60),
+            steps_per_second: Some(1000),
+            limits: DefinitionLimits {
+                body_size: Some(100),
+                fns: Some(50),
+                name_len: Some(16),
+            },
+        })
+    );
+}
+
+#[test]
+fn test_bad_quotas() {
+    let cases = [
+        (
+            "isolation = \"none\"",
+            SettingError::InvalidValue {
+                name: "isolation".to_owned(),
+                value: "\"none\"".to_owned(),
+            },
+        ),
+        (
+            "max-definitions = -1",
+            SettingError::InvalidValue {
+                name: "max-definitions".to_owned(),
+                value: "-1".to_owned(),
+            },
+        ),
+        (
+            "max-steps = 10",
+            SettingError::Unknown("max-steps".to_owned()),
+        ),
+    ];
+    for (src, err) in cases {
+        assert_eq!(Quotas::parse(src), Err(err), "Failed on {:?}", src);
+    }
+    assert!(matches!(
+        Quotas::parse("steps-per-second ="),
+        Err(SettingError::BadSettingsFile(_))
+    ));
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_ratio_intrinsics() {
+    let cases = [
+        ("⟨⟩ 2/4 ⟶ ⟨1/2⟩", Ok(())),
+        ("⟨⟩ -3/1 ⟶ ⟨-3/1⟩", Ok(())),
+        ("⟨1/2 1/3⟩ ratio.add ⟶ ⟨5/6⟩", Ok(())),
+        ("⟨1/2 1/3⟩ ratio.sub ⟶ ⟨1/6⟩", Ok(())),
+        ("⟨1/3 1/2⟩ ratio.sub ⟶ ⟨-1/6⟩", Ok(())),
+        ("⟨2/3 3/4⟩ ratio.mul ⟶ ⟨1/2⟩", Ok(())),
+        ("⟨1/2 1/4⟩ ratio.div ⟶ ⟨2/1⟩", Ok(())),
+        ("⟨1 1/2⟩ ratio.sub ⟶ ⟨1/2⟩", Ok(())),
+        ("⟨7 2⟩ ratio.div ⟶ ⟨7/2⟩", Ok(())),
+        ("⟨1/2 2/4⟩ ratio.eq ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨-1/2 1/3⟩ ratio.lt ⟶ ⟨[swap drop]⟩", Ok(())),
+        ("⟨1/2 1/3⟩ ratio.lt ⟶ ⟨[drop]⟩", Ok(())),
+        (
+            "⟨1/2 0/1⟩ ratio.div ⟶ ⟨⟩ ratio.div",
+            Err(ResolvedEvalError::Host("division by zero".to_owned())),
+        ),
+        (
+            "⟨[a] 1/2⟩ ratio.add ⟶ ⟨⟩ ratio.add",
+            Err(ResolvedEvalError::Host("expected a ratio".to_owned())),
+        ),
+        (
+            "⟨1/2⟩ apply ⟶ ⟨⟩ apply",
+            Err(ResolvedEvalError::ExpectedQuote),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+#[test]
+fn test_parse_ratio_zero_denominator() {
+    let mut ctx = Context::default();
+    assert_eq!(
+        ExprParser::new().parse(&mut ctx.interner, "1/0"),
+        Err(lalrpop_util::ParseError::User {
+            error: "a ratio's denominator can't be 0"
+        })
+    );
+}
+
+#[test]
+fn test_ratio_binary() {
+    let mut ctx = Context::default();
+    let fn_def = ctx
+        .parse_fn_def("{fn third = [-1/3 340282366920938463463374607431768211456/7] apply}")
+        .unwrap();
+    let bytes = ctx.encode_fn_defs(std::slice::from_ref(&fn_def)).unwrap();
+    assert_eq!(ctx.decode_fn_defs(&bytes), Ok(vec![fn_def]));
+
+    let half = ctx.parse_expr("1/2").unwrap();
+    let minus_half = ctx.parse_expr("-1/2").unwrap();
+    let one = ctx.parse_expr("1/1").unwrap();
+    let nat_one = ctx.parse_expr("1").unwrap();
+    assert_ne!(
+        half.stable_hash(&ctx.interner),
+        minus_half.stable_hash(&ctx.interner)
+    );
+    assert_ne!(
+        one.stable_hash(&ctx.interner),
+        nat_one.stable_hash(&ctx.interner)
+    );
+    let v = ctx.parse_value("6/4").unwrap();
+    assert_eq!(ctx.display(&v).to_string(), "3/2");
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::parse::*;
+use crate::rules::RuleId;
+
+/// The steps from `⟨vs⟩ e`, written as `⟨vs⟩ e` with the rule
+fn all_steps(ctx: &mut Context, vs: &str, e: &str) -> Vec<(String, RuleId)> {
+    let vs = ctx.parse_value_stack(vs).unwrap();
+    let e = ctx.parse_expr(e).unwrap();
+    ctx.all_steps(&vs, &e)
+        .into_iter()
+        .map(|(vs, e, rule)| {
+            let state = format!("{} {}", ctx.display(&vs), ctx.display(&e));
+            (state.trim_end().to_owned(), rule)
+        })
+        .collect()
+}
+
+#[test]
+fn test_all_steps() {
+    let mut ctx = Context::default();
+    let f = ctx.parse_fn_def("{fn f = [a] drop}").unwrap();
+    let f_sym = f.0;
+    ctx.define_fn(f).unwrap();
+    let cases = [
+        ("⟨⟩", "", vec![]),
+        ("⟨[x]⟩", "clone", vec![("⟨[x] [x]⟩", RuleId::Clone)]),
+        ("⟨⟩", "drop", vec![]),
+        (
+            "⟨[x]⟩",
+            "drop [a] [b] swap",
+            vec![
+                ("⟨⟩ [a] [b] swap", RuleId::Drop),
+                ("⟨[x]⟩ drop [b] [a]", RuleId::Swap),
+            ],
+        ),
+        (
+            "⟨⟩",
+            "[b] f [c [d] apply]",
+            vec![
+                ("⟨[b]⟩ f [c [d] apply]", RuleId::QuotePush),
+                ("⟨⟩ [b] [a] drop [c [d] apply]", RuleId::CallExpand(f_sym)),
+                ("⟨⟩ [b] f [c d]", RuleId::Apply),
+            ],
+        ),
+        ("⟨⟩", "f", vec![("⟨⟩ [a] drop", RuleId::CallExpand(f_sym))]),
+        // Pushed values are only known in the same composition
+        ("⟨⟩", "x [a] [[b] swap]", vec![]),
+    ];
+    for (vs, e, expected) in cases {
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(state, rule)| (state.to_owned(), rule))
+            .collect();
+        assert_eq!(
+            all_steps(&mut ctx, vs, e),
+            expected,
+            "Failed on {} {}",
+            vs,
+            e
+        );
+    }
+}
+
+#[test]
+fn test_all_steps_rules() {
+    let mut ctx = Context::default();
+    let rule = RuleParser::new()
+        .parse(&mut ctx.interner, "{rule swap swap => }")
+        .unwrap();
+    ctx.define_rule(rule).unwrap();
+    assert_eq!(
+        all_steps(&mut ctx, "⟨[a] [b]⟩", "swap swap [swap swap]"),
+        [
+            ("⟨[a] [b]⟩ [swap swap]".to_owned(), RuleId::Rewrite(0)),
+            ("⟨[b] [a]⟩ swap [swap swap]".to_owned(), RuleId::Swap),
+            ("⟨[a] [b]⟩ swap swap []".to_owned(), RuleId::Rewrite(0)),
+        ]
+    );
+}
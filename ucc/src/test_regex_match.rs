@@ -0,0 +1,59 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_regex_match() {
+    let cases = [
+        (
+            "⟨\"a(b)\" \"xab\"⟩ regex.match ⟶ ⟨[\"ab\" \"b\"] [swap drop]⟩",
+            Ok(()),
+        ),
+        (
+            "⟨\"b+\" \"abbc\"⟩ regex.match ⟶ ⟨[\"bb\"] [swap drop]⟩",
+            Ok(()),
+        ),
+        (
+            "⟨\"a(x)?\" \"a\"⟩ regex.match ⟶ ⟨[\"a\" \"\"] [swap drop]⟩",
+            Ok(()),
+        ),
+        ("⟨\"z\" \"abc\"⟩ regex.match ⟶ ⟨[] [drop]⟩", Ok(())),
+        (
+            "⟨'a' \"abc\"⟩ regex.match ⟶ ⟨⟩ regex.match",
+            Err(ResolvedEvalError::Host("expected a string".to_owned())),
+        ),
+    ];
+    for (case, expected_result) in cases {
+        let mut ctx = Context::default();
+        let mut ssa = SmallStepAssertionParser::new()
+            .parse(&mut ctx.interner, case)
+            .unwrap();
+        let result = ctx.small_step(&mut ssa.0, &mut ssa.1);
+        assert_eq!(
+            result.resolve(&ctx.interner),
+            expected_result,
+            "Failed on {}",
+            case
+        );
+        assert_eq!(ssa.0, ssa.2, "Failed on {}", case);
+        assert_eq!(ssa.1, ssa.3, "Failed on {}", case);
+    }
+}
+
+#[test]
+fn test_regex_match_bad_pattern() {
+    let mut ctx = Context::default();
+    let mut ssa = SmallStepAssertionParser::new()
+        .parse(&mut ctx.interner, "⟨\"a(\" \"a\"⟩ regex.match ⟶ ⟨⟩")
+        .unwrap();
+    match ctx.small_step(&mut ssa.0, &mut ssa.1) {
+        Err(EvalError::Host(err)) => assert!(err.contains("unclosed group"), "{}", err),
+        result => panic!("{:?}", result),
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+use crate::rewrite::*;
+
+#[test]
+fn test_rewrite() {
+    let cases = [
+        ("{rule swap swap => }", "swap swap drop", "drop", true),
+        (
+            "{rule swap swap => }",
+            "drop swap swap",
+            "drop swap swap",
+            false,
+        ),
+        (
+            "{rule [$a] [$b] swap => [$b] [$a]}",
+            "[x] [y z] swap apply",
+            "[y z] [x] apply",
+            true,
+        ),
+        (
+            "{rule [$a] [$a] => [$a] clone}",
+            "[x] [x] drop",
+            "[x] clone drop",
+            true,
+        ),
+        (
+            "{rule [$a] [$a] => [$a] clone}",
+            "[x] [y] drop",
+            "[x] [y] drop",
+            false,
+        ),
+        ("{rule foo => bar baz}", "foo", "bar baz", true),
+    ];
+    for (rule_src, input_src, expected_src, expected_result) in cases {
+        let mut ctx = Context::default();
+        let rule = RuleParser::new()
+            .parse(&mut ctx.interner, rule_src)
+            .unwrap();
+        assert_eq!(ctx.define_rule(rule), Ok(()), "Failed on {}", rule_src);
+        let mut e = ExprParser::new()
+            .parse(&mut ctx.interner, input_src)
+            .unwrap();
+        let expected = ExprParser::new()
+            .parse(&mut ctx.interner, expected_src)
+            .unwrap();
+        let result = ctx.rewrite(&mut e);
+        assert_eq!(
+            (e.resolve(&ctx.interner), result),
+            (expected.resolve(&ctx.interner), Ok(expected_result)),
+            "Failed on ({}, {})",
+            rule_src,
+            input_src
+        );
+    }
+}
+
+#[test]
+fn test_define_rule_error() {
+    let mut ctx = Context::default();
+    let cases = [
+        ("{rule => foo}", ResolvedRuleError::EmptyLhs),
+        (
+            "{rule foo => $x}",
+            ResolvedRuleError::UnboundVar("$x".to_owned()),
+        ),
+    ];
+    for (rule_src, expected) in cases {
+        let rule = RuleParser::new()
+            .parse(&mut ctx.interner, rule_src)
+            .unwrap();
+        assert_eq!(
+            ctx.define_rule(rule).resolve(&ctx.interner),
+            Err(expected),
+            "Failed on {}",
+            rule_src
+        );
+    }
+    assert!(ctx.rules.is_empty());
+}
+
+#[test]
+fn test_rewrite_limit() {
+    let mut ctx = Context::default();
+    let rule = RuleParser::new()
+        .parse(&mut ctx.interner, "{rule foo => foo}")
+        .unwrap();
+    ctx.define_rule(rule).unwrap();
+    let mut vs = ValueStack::default();
+    let mut e = ExprParser::new().parse(&mut ctx.interner, "foo").unwrap();
+    for _ in 0..MAX_CONSECUTIVE_REWRITES {
+        assert_eq!(ctx.small_step(&mut vs, &mut e), Ok(()));
+    }
+    assert_eq!(
+        ctx.small_step(&mut vs, &mut e),
+        Err(EvalError::RewriteLimitExceeded)
+    );
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::parse::*;
+use crate::rules::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn test_next_rule() {
+    let mut ctx = Context::default();
+    let f = ctx.parse_fn_def("{fn f = swap}").unwrap();
+    let t = ctx.parse_fn_def("{fn t = [swap drop]}").unwrap();
+    let (f_sym, t_sym) = (f.0, t.0);
+    ctx.define_fn(f).unwrap();
+    ctx.define_fn(t).unwrap();
+    let cases = [
+        ("", None),
+        ("swap drop", Some(RuleId::Swap)),
+        ("((drop) swap)", Some(RuleId::Drop)),
+        ("f", Some(RuleId::CallExpand(f_sym))),
+        ("t", Some(RuleId::CallPush(t_sym))),
+        ("undefined", None),
+        ("[a] b", Some(RuleId::QuotePush)),
+        ("'a", Some(RuleId::SymbolPush)),
+    ];
+    for (e, expected) in cases {
+        let e = ctx.parse_expr(e).unwrap();
+        assert_eq!(ctx.next_rule(&e), expected, "Failed on {}", ctx.display(&e));
+    }
+    let e = Expr::Compose(vec![Expr::default(), Expr::Intrinsic(Intrinsic::Drop)]);
+    assert_eq!(ctx.next_rule(&e), Some(RuleId::ComposeFlatten));
+
+    let rule = RuleParser::new()
+        .parse(&mut ctx.interner, "{rule swap swap => }")
+        .unwrap();
+    ctx.define_rule(rule).unwrap();
+    let e = ctx.parse_expr("swap swap").unwrap();
+    assert_eq!(ctx.next_rule(&e), Some(RuleId::Rewrite(0)));
+}
+
+#[test]
+fn test_formal() {
+    for intr in [
+        Intrinsic::Swap,
+        Intrinsic::EvalQ,
+        Intrinsic::Dup,
+        Intrinsic::K,
+    ] {
+        let rule = RuleId::intrinsic(intr);
+        assert_eq!(rule.name(), intr.to_string());
+        assert!(rule.formal().contains(rule.name()), "Failed on {:?}", rule);
+    }
+    assert_eq!(RuleId::Swap.formal(), "⟨v1 v2⟩ swap ⟶ ⟨v2 v1⟩");
+}
+
+#[test]
+fn test_rule_hooks() {
+    let mut ctx = Context::default();
+    let rules = Rc::new(RefCell::new(vec![]));
+    let log = Rc::clone(&rules);
+    ctx.on_rule(move |rule, vs, _| log.borrow_mut().push((rule, vs.len())));
+    let mut vs = ValueStack::default();
+    let mut e = ctx.parse_expr("[a] [b] swap apply drop").unwrap();
+    while e != Expr::default() && ctx.small_step(&mut vs, &mut e).is_ok() {}
+    // Not the `drop` that fails
+    assert_eq!(
+        *rules.borrow(),
+        [
+            (RuleId::QuotePush, 1),
+            (RuleId::QuotePush, 2),
+            (RuleId::Swap, 2),
+            (RuleId::Apply, 1),
+        ]
+    );
+}
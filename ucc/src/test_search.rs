@@ -0,0 +1,48 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+use crate::search::*;
+
+#[test]
+fn test_search() {
+    let mut ctx = Context::default();
+    let fn_def = FnDefParser::new()
+        .parse(
+            &mut ctx.interner,
+            "{fn swapd = [swap] swap quote compose apply}",
+        )
+        .unwrap();
+    ctx.define_fn(fn_def).unwrap();
+    let cases = [
+        ("⟨[a] [b]⟩", "⟨[b] [a]⟩", 1, vec!["swap"]),
+        ("⟨[a] [b]⟩", "⟨[a]⟩", 2, vec!["drop"]),
+        ("⟨[a]⟩", "⟨[a] [a] [a]⟩", 2, vec!["clone clone"]),
+        ("⟨[a] [b] [c]⟩", "⟨[b] [a] [c]⟩", 2, vec!["swapd"]),
+        ("⟨[a] [b]⟩", "⟨[b a]⟩", 2, vec!["swap compose"]),
+        ("⟨[a] [b]⟩", "⟨[c]⟩", 3, vec![]),
+    ];
+    for (input, output, size, expected) in cases {
+        let input = ValueStackParser::new()
+            .parse(&mut ctx.interner, input)
+            .unwrap();
+        let output = ValueStackParser::new()
+            .parse(&mut ctx.interner, output)
+            .unwrap();
+        let limits = SearchLimits {
+            size,
+            ..SearchLimits::default()
+        };
+        let found: Vec<_> = ctx
+            .search(&input, &output, limits)
+            .iter()
+            .map(|e| e.resolve(&ctx.interner).to_string())
+            .collect();
+        assert_eq!(found, expected, "{:?} {}", input, size);
+    }
+}
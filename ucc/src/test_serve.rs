@@ -0,0 +1,539 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::builder::Clock;
+use crate::interp::InterpStats;
+use crate::limits::DefinitionLimits;
+use crate::quota::{Isolation, Quotas};
+use crate::serve::*;
+use crate::websocket::{accept_key, encode_frame, Frame};
+use std::cell::Cell;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A clock that moves only when the test moves it
+struct TestClock(Rc<Cell<Duration>>);
+
+impl Clock for TestClock {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+fn request(method: &str, path: &str, body: &str) -> Request {
+    Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        headers: vec![],
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+#[test]
+fn test_sessions() {
+    let mut server = Server::default();
+    let cases = [
+        (
+            request("POST", "/sessions/a", "[x] clone"),
+            200,
+            "⟨⟩ [x] clone\n⇓ ⟨[x] [x]⟩ \n",
+        ),
+        (
+            request("POST", "/sessions/a", "drop"),
+            200,
+            "⟨[x] [x]⟩ drop\n⇓ ⟨[x]⟩ \n",
+        ),
+        // Sessions are separate
+        (
+            request("POST", "/sessions/b", "drop"),
+            200,
+            "⟨⟩ drop\n⇓ ⟨⟩ drop\nTooFewValues { available: 0, expected: 1 }\n",
+        ),
+        (
+            request("POST", "/sessions/a", ":load \"/etc/passwd\""),
+            200,
+            "Io(\"permission denied\")\n",
+        ),
+        (
+            request("POST", "/sessions/a b", "drop"),
+            400,
+            "Bad session id.\n",
+        ),
+        (request("DELETE", "/sessions/b", ""), 200, "Ended.\n"),
+        (request("DELETE", "/sessions/b", ""), 404, "No session.\n"),
+        (request("GET", "/", ""), 404, "Not found.\n"),
+        (
+            request("GET", "/sessions/a/ws", ""),
+            400,
+            "Expected a WebSocket upgrade.\n",
+        ),
+    ];
+    for (request, status, body) in cases {
+        let response = server.handle(&request);
+        assert_eq!(
+            (response.status, response.body.as_str()),
+            (status, body),
+            "Failed on {:?}",
+            request
+        );
+    }
+    let metrics = server.metrics();
+    assert_eq!(
+        metrics.totals,
+        InterpStats {
+            evaluations: 4,
+            small_steps: 4,
+            errors: 1,
+        }
+    );
+    assert_eq!(metrics.sessions.len(), 1);
+    assert_eq!(metrics.sessions[0].0, "a");
+    let response = server.handle(&request("GET", "/metrics", ""));
+    assert!(response.body.contains("ucc_active_sessions 1\n"));
+}
+
+#[test]
+fn test_quotas() {
+    let now = Rc::new(Cell::new(Duration::default()));
+    let mut server = Server::with_quotas(Quotas {
+        isolation: Isolation::Private,
+        max_sessions: None,
+        session_idle_seconds: None,
+        steps_per_second: Some(2),
+        limits: DefinitionLimits {
+            body_size: Some(2),
+            ..DefinitionLimits::default()
+        },
+    })
+    .clock(TestClock(now.clone()));
+    let cases = [
+        (Duration::default(), "[x] clone clone clone", "⟨⟩ [x] clone clone clone\nOver the quota of 2 steps a second; the rest runs in later requests.\n"),
+        // Still waiting
+        (Duration::default(), "", "Over the quota of 2 steps a second; the rest runs in later requests.\n"),
+        (Duration::from_secs(1), "", "⇓ ⟨[x] [x] [x] [x]⟩ \n"),
+        // Waiting longer saves up no more than a second's steps
+        (Duration::from_secs(10), "clone clone clone", "⟨[x] [x] [x] [x]⟩ clone clone clone\nOver the quota of 2 steps a second; the rest runs in later requests.\n"),
+        (Duration::from_secs(1), "{fn f = a b c}", "[5] BodyTooLarge { size: 3, limit: 2 }\n⇓ ⟨[x] [x] [x] [x] [x] [x] [x]⟩ \n"),
+    ];
+    for (wait, input, output) in cases {
+        now.set(now.get() + wait);
+        let response = server.handle(&request("POST", "/sessions/a", input));
+        assert_eq!(response.body, output, "Failed on {:?}", input);
+    }
+}
+
+#[test]
+fn test_respond() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"POST /sessions/a HTTP/1.1\r\nContent-Length: 9\r\n\r\n[x] clone")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+    let mut server = Server::default();
+    let (stream, _) = listener.accept().unwrap();
+    server.respond(stream).unwrap();
+    let body = "⟨⟩ [x] clone\n⇓ ⟨[x] [x]⟩ \n";
+    assert_eq!(
+        client.join().unwrap(),
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    );
+}
+
+#[test]
+fn test_read_request_limits() {
+    let long = "a".repeat(9000);
+    let headers = |n: usize| "X-A: b\r\n".repeat(n);
+    let cases = [
+        (format!("GET /{} HTTP/1.1\r\n\r\n", long), Some(400)),
+        (
+            format!("GET / HTTP/1.1\r\nX-A: {}\r\n\r\n", long),
+            Some(431),
+        ),
+        // Lines that aren't headers count too
+        (
+            format!("GET / HTTP/1.1\r\n{}\r\n", "x\r\n".repeat(101)),
+            Some(431),
+        ),
+        (format!("GET / HTTP/1.1\r\n{}\r\n", headers(101)), Some(431)),
+        (format!("GET / HTTP/1.1\r\n{}\r\n", headers(100)), None),
+    ];
+    for (src, status) in cases {
+        let result = read_request(&mut src.as_bytes()).unwrap();
+        assert_eq!(
+            result.err().map(|response| response.status),
+            status,
+            "Failed on {:?}",
+            &src[..40]
+        );
+    }
+}
+
+/// A client that sends its request a byte at a time is cut off once its
+/// time is up, however often it sends
+#[test]
+fn test_read_deadline() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        for byte in b"POST /sessions/a HTTP/1.1\r\n".iter().cycle() {
+            if stream.write_all(&[*byte]).is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+    });
+    let mut server = Server::default();
+    let (stream, _) = listener.accept().unwrap();
+    let err = server.respond(stream).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    client.join().unwrap();
+}
+
+/// Read a frame the server sent, which is never masked
+fn read_frame(stream: &mut TcpStream) -> Frame {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head).unwrap();
+    let len = match head[1] {
+        126 => {
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            u16::from_be_bytes(len) as usize
+        }
+        len => len as usize,
+    };
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).unwrap();
+    match head[0] & 0x0f {
+        1 => Frame::Text(String::from_utf8(payload).unwrap()),
+        8 => Frame::Close,
+        opcode => panic!("unexpected opcode {}", opcode),
+    }
+}
+
+/// Send `frame` as a client must, masked
+fn write_frame(stream: &mut TcpStream, frame: &Frame) {
+    let mut bytes = encode_frame(frame);
+    let at = bytes.len()
+        - if let Frame::Text(text) = frame {
+            text.len()
+        } else {
+            0
+        };
+    bytes[1] |= 0x80;
+    bytes.splice(at..at, [0; 4]);
+    stream.write_all(&bytes).unwrap();
+}
+
+#[test]
+fn test_playground() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        stream
+            .write_all(
+                b"GET /sessions/a/ws HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        let mut handshake = vec![];
+        while !handshake.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            handshake.push(byte[0]);
+        }
+        let mut messages = vec![String::from_utf8(handshake).unwrap()];
+        // Send `json`, then read the replies up to one of type `last`
+        let mut send = |stream: &mut TcpStream, json: &str, last: Option<&str>| {
+            write_frame(stream, &Frame::Text(json.to_owned()));
+            let last = match last {
+                Some(last) => format!("{{\"type\": \"{}\"", last),
+                None => return,
+            };
+            loop {
+                match read_frame(stream) {
+                    Frame::Text(text) => {
+                        let done = text.starts_with(&last);
+                        messages.push(text);
+                        if done {
+                            break;
+                        }
+                    }
+                    frame => panic!("unexpected {:?}", frame),
+                }
+            }
+        };
+        send(&mut stream, r#"{"type": "pause"}"#, Some("paused"));
+        send(
+            &mut stream,
+            r#"{"type": "eval", "input": "[clone apply] clone apply"}"#,
+            None,
+        );
+        send(&mut stream, r#"{"type": "cancel"}"#, Some("cancelled"));
+        send(
+            &mut stream,
+            r#"{"type": "eval", "input": "[x] clone swap"}"#,
+            None,
+        );
+        send(&mut stream, r#"{"type": "resume"}"#, Some("done"));
+        send(
+            &mut stream,
+            r#"{"type": "eval", "input": "{"}"#,
+            Some("done"),
+        );
+        send(&mut stream, r#"{"type": "stop"}"#, Some("error"));
+        write_frame(&mut stream, &Frame::Close);
+        assert_eq!(read_frame(&mut stream), Frame::Close);
+        messages
+    });
+    let mut server = Server::default();
+    let (stream, _) = listener.accept().unwrap();
+    let mut playground = server.respond(stream).unwrap().unwrap();
+    while !playground.is_closed() {
+        server.poll(&mut playground).unwrap();
+    }
+    let messages = client.join().unwrap();
+    assert_eq!(
+        messages[0],
+        format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key("dGhlIHNhbXBsZSBub25jZQ==")
+        )
+    );
+    assert_eq!(
+        messages[1..],
+        [
+            r#"{"type": "paused"}"#,
+            r#"{"type": "cancelled"}"#,
+            r#"{"type": "resumed"}"#,
+            r#"{"type": "step", "stack": "⟨⟩", "expr": "[x] clone swap"}"#,
+            r#"{"type": "step", "stack": "⟨[x]⟩", "expr": "clone swap"}"#,
+            r#"{"type": "step", "stack": "⟨[x] [x]⟩", "expr": "swap"}"#,
+            r#"{"type": "output", "text": "⟨⟩ [x] clone swap\n"}"#,
+            r#"{"type": "output", "text": "⇓ ⟨[x] [x]⟩ \n"}"#,
+            r#"{"type": "done"}"#,
+            r#"{"type": "output", "text": "UnrecognizedEOF { location: 1, expected: [\"FN\", \"LOCAL\", \"RULE\"] }\n"}"#,
+            r#"{"type": "done"}"#,
+            r#"{"type": "error", "message": "unknown message type `stop`"}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_slow_playground() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (backing_up, backed_up) = mpsc::channel();
+    let client = thread::spawn(move || {
+        let (mut stream, head) = connect_playground(addr, "a");
+        assert_eq!(head, "HTTP/1.1 101 Switching Protocols");
+        // Each step shows the stack, so a long value under the loop makes
+        // for long messages
+        let input = format!("[{}] [clone apply] clone apply", ["x"; 500].join(" "));
+        let eval = format!(r#"{{"type": "eval", "input": "{}"}}"#, input);
+        write_frame(&mut stream, &Frame::Text(eval));
+        backed_up.recv().unwrap();
+        write_frame(
+            &mut stream,
+            &Frame::Text(r#"{"type": "cancel"}"#.to_owned()),
+        );
+        let mut steps = 0;
+        loop {
+            match read_frame(&mut stream) {
+                Frame::Text(text) if text == r#"{"type": "cancelled"}"# => break,
+                // Cancelling shows where the job started
+                Frame::Text(text) if text.starts_with(r#"{"type": "output""#) => (),
+                Frame::Text(text) => {
+                    let expr = match steps {
+                        0 => &input,
+                        1 => "[clone apply] clone apply",
+                        _ => ["clone apply", "apply"][steps % 2],
+                    };
+                    assert!(
+                        text.ends_with(&format!("\"expr\": \"{}\"}}", expr)),
+                        "{}",
+                        text
+                    );
+                    steps += 1;
+                }
+                frame => panic!("unexpected {:?}", frame),
+            }
+        }
+        write_frame(&mut stream, &Frame::Close);
+        assert_eq!(read_frame(&mut stream), Frame::Close);
+        steps
+    });
+    let mut server = Server::default();
+    let (stream, _) = listener.accept().unwrap();
+    let mut playground = server.respond(stream).unwrap().unwrap();
+    // Step until the client, taking none of the steps, holds them back,
+    // and for longer than a response may take to be written
+    while !server.poll(&mut playground).unwrap() {}
+    while server.poll(&mut playground).unwrap() {}
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(1500) {
+        server.poll(&mut playground).unwrap();
+    }
+    backing_up.send(()).unwrap();
+    // The playground is neither dropped nor sent less for taking its time
+    while !playground.is_closed() {
+        server.poll(&mut playground).unwrap();
+    }
+    let steps = client.join().unwrap();
+    assert_eq!(server.metrics().totals.small_steps, steps as u64);
+}
+
+#[test]
+fn test_session_limits() {
+    let mut server = Server::with_quotas(Quotas {
+        max_sessions: Some(1),
+        steps_per_second: Some(3),
+        ..Quotas::default()
+    })
+    .clock(TestClock(Rc::new(Cell::new(Duration::default()))));
+    let cases = [
+        ("a", ":plugin load \"libevil.so\"", "Disabled\n"),
+        ("a", ":load \"https://example.com/lib.ucc\"", "NetDisabled\n"),
+        // A request takes no more than its allowance, even within a chunk
+        (
+            "a",
+            "[x] clone clone clone",
+            "⟨⟩ [x] clone clone clone\nOver the quota of 3 steps a second; the rest runs in later requests.\n",
+        ),
+        ("b", "[x]", "Too many sessions.\n"),
+    ];
+    for (id, input, output) in cases {
+        let response = server.handle(&request("POST", &format!("/sessions/{}", id), input));
+        assert_eq!(response.body, output, "Failed on {:?}", input);
+    }
+    assert_eq!(server.metrics().totals.small_steps, 3);
+}
+
+#[test]
+fn test_idle_sessions() {
+    let now = Rc::new(Cell::new(Duration::default()));
+    let mut server = Server::with_quotas(Quotas {
+        max_sessions: Some(1),
+        session_idle_seconds: Some(60),
+        ..Quotas::default()
+    })
+    .clock(TestClock(now.clone()));
+    let cases = [
+        (Duration::default(), "a", "[x]", "⟨⟩ [x]\n⇓ ⟨[x]⟩ \n"),
+        (Duration::from_secs(30), "b", "[y]", "Too many sessions.\n"),
+        // Using `a` keeps it going
+        (Duration::from_secs(0), "a", "", ""),
+        (Duration::from_secs(59), "b", "[y]", "Too many sessions.\n"),
+        (Duration::from_secs(1), "b", "[y]", "⟨⟩ [y]\n⇓ ⟨[y]⟩ \n"),
+        // `a` ended, so starts over
+        (Duration::from_secs(60), "a", "", ""),
+        (Duration::default(), "a", ":drop", "Values dropped.\n"),
+    ];
+    for (wait, id, input, output) in cases {
+        now.set(now.get() + wait);
+        let response = server.handle(&request("POST", &format!("/sessions/{}", id), input));
+        assert_eq!(response.body, output, "Failed on {:?} in {}", input, id);
+    }
+    assert_eq!(server.metrics().sessions.len(), 1);
+    // The work of ended sessions still counts
+    assert_eq!(server.metrics().totals.small_steps, 2);
+}
+
+/// Request an upgrade of a connection to `addr` to a playground of the
+/// session `id`, returning the connection and the response's status line
+fn connect_playground(addr: std::net::SocketAddr, id: &str) -> (TcpStream, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET /sessions/{}/ws HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        id
+    )
+    .unwrap();
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head).unwrap();
+    (stream, head.lines().next().unwrap().to_owned())
+}
+
+#[test]
+fn test_playground_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let (mut first, head) = connect_playground(addr, "a");
+        assert_eq!(head, "HTTP/1.1 101 Switching Protocols");
+        // Even to the same session
+        let (mut second, head) = connect_playground(addr, "a");
+        assert_eq!(head, "HTTP/1.1 503 Service Unavailable");
+        let mut body = String::new();
+        second.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "Too many playgrounds.\n");
+        write_frame(&mut first, &Frame::Close);
+        // Wait for the server to close the first before connecting again
+        let mut rest = vec![];
+        first.read_to_end(&mut rest).unwrap();
+        let (_, head) = connect_playground(addr, "a");
+        assert_eq!(head, "HTTP/1.1 101 Switching Protocols");
+    });
+    let mut server = Server::with_quotas(Quotas {
+        max_sessions: Some(1),
+        ..Quotas::default()
+    });
+    let (stream, _) = listener.accept().unwrap();
+    let mut first = server.respond(stream).unwrap().unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    assert!(server.respond(stream).unwrap().is_none());
+    while !first.is_closed() {
+        server.poll(&mut first).unwrap();
+    }
+    drop(first);
+    let (stream, _) = listener.accept().unwrap();
+    assert!(server.respond(stream).unwrap().is_some());
+    client.join().unwrap();
+}
+
+#[test]
+fn test_value_size() {
+    let mut server = Server::with_quotas(Quotas {
+        limits: DefinitionLimits {
+            body_size: Some(4),
+            ..DefinitionLimits::default()
+        },
+        ..Quotas::default()
+    });
+    let response = server.handle(&request(
+        "POST",
+        "/sessions/a",
+        "[x] clone compose clone compose",
+    ));
+    assert_eq!(
+        response.body,
+        "⟨⟩ [x] clone compose clone compose\n⇓ ⟨[x x x x]⟩ \nLimit(ValueTooLarge { size: 5, limit: 4 })\n"
+    );
+}
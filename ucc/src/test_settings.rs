@@ -0,0 +1,120 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::settings::*;
+
+#[test]
+fn test_set() {
+    let cases = [
+        ("show-steps", "off", Ok(ShowSteps::Off)),
+        ("show-steps", "on", Ok(ShowSteps::On)),
+        ("show-steps", "10", Ok(ShowSteps::Every(10))),
+        (
+            "show-steps",
+            "0",
+            Err(SettingError::InvalidValue {
+                name: "show-steps".to_owned(),
+                value: "0".to_owned(),
+            }),
+        ),
+        (
+            "show-stops",
+            "on",
+            Err(SettingError::Unknown("show-stops".to_owned())),
+        ),
+    ];
+    for (name, value, expected) in cases {
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.set(name, value).map(|()| settings.show_steps()),
+            expected,
+            "Failed on {:?}",
+            (name, value)
+        );
+    }
+}
+
+#[test]
+fn test_unset() {
+    let mut settings = Settings::default();
+    settings.set("prompt", "λ> ").unwrap();
+    assert_eq!(settings.prompt(), "λ> ");
+    settings.unset("prompt").unwrap();
+    assert_eq!(settings, Settings::default());
+    assert_eq!(
+        settings.unset("colour"),
+        Err(SettingError::Unknown("colour".to_owned()))
+    );
+}
+
+#[test]
+fn test_set_value() {
+    let mut settings = Settings::default();
+    assert_eq!(
+        settings.set_value("show-steps", SettingValue::Int(3)),
+        Ok(())
+    );
+    assert_eq!(settings.get("show-steps"), Some(&SettingValue::Int(3)));
+    assert_eq!(
+        settings.set_value("prompt", SettingValue::Bool(true)),
+        Err(SettingError::InvalidValue {
+            name: "prompt".to_owned(),
+            value: "on".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn test_settings_file() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.to_file_string(), "");
+    settings.set("prompt", "say \"hi\"> ").unwrap();
+    settings.set("show-steps", "off").unwrap();
+    let src = settings.to_file_string();
+    assert_eq!(src, "prompt = \"say \\\"hi\\\"> \"\nshow-steps = false\n");
+
+    let mut loaded = Settings::default();
+    assert_eq!(
+        loaded.update_from_file_string(&format!("# comment\n\n{}", src)),
+        Ok(())
+    );
+    assert_eq!(loaded, settings);
+
+    assert_eq!(
+        loaded.update_from_file_string("show-steps = 0"),
+        Err(SettingError::InvalidValue {
+            name: "show-steps".to_owned(),
+            value: "0".to_owned(),
+        })
+    );
+    assert!(matches!(
+        loaded.update_from_file_string("show-steps"),
+        Err(SettingError::BadSettingsFile(_))
+    ));
+    assert_eq!(
+        loaded.update_from_file_string("show-steps = -1"),
+        Err(SettingError::InvalidValue {
+            name: "show-steps".to_owned(),
+            value: "-1".to_owned(),
+        })
+    );
+
+    // Any TOML will do, as another tool may have written it
+    let mut loaded = Settings::default();
+    assert_eq!(
+        loaded.update_from_file_string(
+            "show-steps = 10 # note
+prompt = '> '
+"
+        ),
+        Ok(())
+    );
+    assert_eq!(loaded.get("show-steps"), Some(&SettingValue::Int(10)));
+    assert_eq!(
+        loaded.get("prompt"),
+        Some(&SettingValue::Str("> ".to_owned()))
+    );
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::interp::Interp;
+use crate::shared::*;
+use std::thread;
+
+fn interp(interp: &mut Interp, input: &str) -> String {
+    let mut buffer = vec![];
+    interp.interp_start(input, &mut buffer).unwrap();
+    while !interp.is_done() {
+        interp.interp_step(&mut buffer).unwrap();
+    }
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn test_shared_prelude() {
+    let prelude = SharedPrelude::default();
+    let sessions: Vec<_> = ["{fn or = swap}", "{fn x = }"]
+        .iter()
+        .map(|def| {
+            let prelude = prelude.clone();
+            thread::spawn(move || {
+                let mut session = Interp::with_prelude(&prelude);
+                interp(&mut session, def);
+                (
+                    interp(&mut session, "true false or"),
+                    interp(&mut session, ":list")
+                        .split_whitespace()
+                        .any(|name| name == "x"),
+                )
+            })
+        })
+        .collect();
+    let results: Vec<_> = sessions.into_iter().map(|s| s.join().unwrap()).collect();
+    assert_eq!(
+        results,
+        [
+            ("⟨⟩ true false or\n⇓ ⟨false true⟩ \n".to_string(), false),
+            ("⟨⟩ true false or\n⇓ ⟨true⟩ \n".to_string(), true),
+        ]
+    );
+
+    let mut session = Interp::with_prelude(&prelude);
+    for (input, expected_output) in [
+        (":clear", "Definitions cleared.\n"),
+        ("true", "⟨⟩ true\n⇓ ⟨⟩ true\nUndefinedFn(\"true\")\n"),
+        (":reset", "Reset.\n"),
+        ("true false or", "⟨⟩ true false or\n⇓ ⟨true⟩ \n"),
+    ] {
+        assert_eq!(interp(&mut session, input), expected_output);
+    }
+}
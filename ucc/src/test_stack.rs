@@ -0,0 +1,53 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::stack::*;
+
+#[test]
+fn test_stack() {
+    let mut stack: Stack<u32> = (1..=4).collect();
+    assert_eq!(stack.len(), 4);
+    assert_eq!(stack.first(), Some(&1));
+    assert_eq!(stack.last(), Some(&4));
+    assert_eq!(stack[1], 2);
+    assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+    assert_eq!(stack.remove(1), 2);
+    assert_eq!(format!("{:?}", stack), "[1, 3, 4]");
+    stack.push(5);
+    assert_eq!(stack.pop(), Some(5));
+    assert_eq!(stack.into_iter().collect::<Vec<_>>(), [1, 3, 4]);
+
+    let mut empty = Stack::<u32>::new();
+    assert!(empty.is_empty());
+    assert_eq!(empty.pop(), None);
+    assert_eq!(empty, Stack::default());
+}
+
+/// Copies share their values until changed, and changing one leaves the
+/// others as they were
+#[test]
+fn test_stack_persistence() {
+    let mut stack: Stack<Vec<u32>> = (1..=3).map(|i| vec![i]).collect();
+    let snapshot = stack.clone();
+    stack.pop();
+    stack.push(vec![4]);
+    stack.iter_mut().for_each(|v| v.push(0));
+    assert_eq!(
+        format!("{:?} {:?}", snapshot, stack),
+        "[[1], [2], [3]] [[1, 0], [2, 0], [4, 0]]"
+    );
+    assert_ne!(snapshot, stack);
+    assert_eq!(snapshot, (1..=3).map(|i| vec![i]).collect());
+}
+
+/// Long stacks are dropped without overflowing the call stack
+#[test]
+fn test_stack_drop() {
+    let stack: Stack<u32> = (0..1_000_000).collect();
+    let snapshot = stack.clone();
+    drop(stack);
+    assert_eq!(snapshot.len(), 1_000_000);
+}
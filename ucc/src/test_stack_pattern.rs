@@ -0,0 +1,91 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::interp::Interp;
+
+/// A pattern, a stack, and the captures of a match, if any
+type Case = (
+    &'static str,
+    &'static str,
+    Option<&'static [(&'static str, &'static str)]>,
+);
+
+#[test]
+fn test_matches() {
+    let cases: &[Case] = &[
+        ("⟨⟩", "⟨⟩", Some(&[])),
+        ("⟨⟩", "⟨[a]⟩", None),
+        ("⟨...⟩", "⟨[a] 'b⟩", Some(&[])),
+        ("⟨_ 'b⟩", "⟨[a] 'b⟩", Some(&[])),
+        ("⟨_⟩", "⟨[a] 'b⟩", None),
+        ("⟨... $x⟩", "⟨[a] 'b⟩", Some(&[("$x", "'b")])),
+        ("⟨$x ...⟩", "⟨[a] 'b⟩", Some(&[("$x", "[a]")])),
+        ("⟨$x ... $y⟩", "⟨[a]⟩", None),
+        ("⟨[$f] ... _⟩", "⟨[a b] c⟩", Some(&[("$f", "a b")])),
+        ("⟨$x [$x]⟩", "⟨[a] [[a]]⟩", Some(&[("$x", "[a]")])),
+        ("⟨$x $x⟩", "⟨[a] [b]⟩", None),
+        (
+            "⟨[$x swap] [$y $x]⟩",
+            "⟨[a swap] [b a]⟩",
+            Some(&[("$x", "a"), ("$y", "b")]),
+        ),
+        ("⟨[$x swap] [$y $x]⟩", "⟨[a swap] [b c]⟩", None),
+        ("⟨true ...⟩", "⟨true⟩", Some(&[])),
+        ("⟨[_]⟩", "⟨[a]⟩", None),
+        ("⟨[_]⟩", "⟨[_]⟩", Some(&[])),
+    ];
+    let mut ctx = Context::default();
+    for &(pattern, vs, expected) in cases {
+        let pattern = ctx.parse_stack_pattern(pattern).unwrap();
+        let vs = ctx.parse_value_stack(vs).unwrap();
+        let captures = pattern.matches(&vs).map(|captures| {
+            captures
+                .iter()
+                .map(|(sym, e)| {
+                    (
+                        ctx.display(&Expr::Call(*sym)).to_string(),
+                        ctx.display(e).to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+        let expected = expected.map(|expected| {
+            expected
+                .iter()
+                .map(|&(sym, e)| (sym.to_owned(), e.to_owned()))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(captures, expected, "{:?}", (pattern, vs));
+    }
+}
+
+#[test]
+fn test_trace_until() {
+    let mut interp = Interp::default();
+    let mut buffer = vec![];
+    for input in [
+        ":trace-until ⟨... [$x] _⟩ [a] [b] quote swap",
+        ":trace-until ⟨_ _ _⟩ swap",
+    ] {
+        interp.interp_start(input, &mut buffer).unwrap();
+        while !interp.is_done() {
+            interp.interp_step(&mut buffer).unwrap();
+        }
+    }
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        concat!(
+            "⟨⟩ [a] [b] quote swap\n",
+            "⟶ ⟨[a]⟩ [b] quote swap\n",
+            "⟶ ⟨[a] [b]⟩ quote swap\n",
+            "Matched, with $x = a.\n",
+            "⟨[a] [b]⟩ swap\n",
+            "⟶ ⟨[b] [a]⟩ \n",
+            "Never matched.\n",
+        )
+    );
+}
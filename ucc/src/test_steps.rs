@@ -0,0 +1,39 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+
+#[test]
+fn test_steps() {
+    let mut ctx = Context::default();
+    let vs = ctx.parse_value_stack("⟨[a] [b]⟩").unwrap();
+    let e = ctx.parse_expr("swap [c] compose").unwrap();
+    let states: Vec<String> = ctx
+        .steps(&vs, &e)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+        .iter()
+        .map(|(vs, e)| format!("{} {}", ctx.display(vs), ctx.display(e)))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            "⟨[b] [a]⟩ [c] compose",
+            "⟨[b] [a] [c]⟩ compose",
+            "⟨[b] [a c]⟩ ",
+        ]
+    );
+
+    // Evaluation ends with the error a step fails with
+    let e = ctx.parse_expr("drop drop drop swap").unwrap();
+    let results: Vec<_> = ctx.steps(&vs, &e).map(|state| state.is_ok()).collect();
+    assert_eq!(results, [true, true, false]);
+
+    // Loops can be cut short
+    let e = ctx.parse_expr("[clone apply] clone apply").unwrap();
+    assert_eq!(ctx.steps(&ValueStack::default(), &e).take(100).count(), 100);
+    assert_eq!(ctx.steps(&vs, &Expr::default()).count(), 0);
+}
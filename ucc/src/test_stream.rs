@@ -0,0 +1,64 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::interp::InterpItem;
+use crate::load::LoadError;
+use crate::parse::InterpItemsParser;
+use crate::stream::*;
+
+fn read_all(src: &str) -> (Interner, Result<Vec<InterpItem>, LoadError>) {
+    let mut interner = Interner::default();
+    let mut reader = ItemReader::new(src.as_bytes());
+    let mut items = vec![];
+    loop {
+        match reader.parse_next_item(&mut interner) {
+            Ok(Some(item)) => items.push(item),
+            Ok(None) => return (interner, Ok(items)),
+            Err(err) => return (interner, Err(err)),
+        }
+    }
+}
+
+#[test]
+fn test_parse_next_item() {
+    for (src, expected) in [
+        ("", ""),
+        ("\n\n-- comment\n", ""),
+        ("{fn a = [clone]}", "{fn a = [clone]}"),
+        ("[] [clone]\n[drop]", "[] [clone]\n[drop]"),
+        ("[\n[clone]\n] apply", "[[clone]] apply"),
+        (
+            "{fn a = [\nclone\n]}{rule a => b} a\n",
+            "{fn a = [clone]}\n{rule a => b}\na",
+        ),
+        ("[] -- { [ comment\n{fn a = b}", "[]\n{fn a = b}"),
+        ("[drop] {fn a = b} a", "[drop]\n{fn a = b}\na"),
+        (
+            "[a] #{'k => [v]}\n#{\n'k => [v]} a",
+            "[a] #{'k => [v]}\n#{'k => [v]} a",
+        ),
+        ("#{} {fn a = b}", "#{}\n{fn a = b}"),
+        ("'[' '\\'' 'a\n[b]", "'[' '\\'' 'a\n[b]"),
+        ("\"a[{\" chars\n[b]", "\"a[{\" chars\n[b]"),
+        ("{fn a = \"}\"}", "{fn a = \"}\"}"),
+    ] {
+        let (mut interner, items) = read_all(src);
+        let expected: Vec<InterpItem> = expected
+            .lines()
+            .flat_map(|line| InterpItemsParser::new().parse(&mut interner, line).unwrap())
+            .collect();
+        assert_eq!(items, Ok(expected), "{:?}", src);
+    }
+}
+
+#[test]
+fn test_parse_next_item_error() {
+    let (_, items) = read_all("[]\n\n{fn a = ]}\n[]");
+    assert!(matches!(items, Err(LoadError::Parse(err)) if err.starts_with("line 3: ")));
+    let (_, items) = read_all("{fn a = [");
+    assert!(matches!(items, Err(LoadError::Parse(err)) if err.starts_with("line 1: ")));
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+use crate::substructural::*;
+
+#[test]
+fn test_check_substructural() {
+    let mut ctx = Context::default();
+    for src in [
+        "{fn false = [drop]}",
+        "{fn swap2 = swap swap}",
+        "{fn dup2 = [a clone] apply swap2 clone}",
+        "{fn not = [false] swap apply}",
+        "{fn loop = swap loop}",
+        "{fn even = [odd] apply drop}",
+        "{fn odd = [even] apply}",
+    ] {
+        let fn_def = FnDefParser::new().parse(&mut ctx.interner, src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let cases = [
+        ("swap2", Discipline::Linear, Some(vec![])),
+        ("loop", Discipline::Linear, Some(vec![])),
+        ("false", Discipline::Affine, Some(vec![])),
+        ("false", Discipline::Linear, Some(vec!["`drop` in `false`"])),
+        (
+            "dup2",
+            Discipline::Affine,
+            Some(vec!["`clone` at 1.2 in `dup2`", "`clone` at 4 in `dup2`"]),
+        ),
+        (
+            "not",
+            Discipline::Linear,
+            Some(vec!["`drop` in `false`, called at 1 in `not`"]),
+        ),
+        (
+            "odd",
+            Discipline::Linear,
+            Some(vec!["`drop` at 3 in `even`, called at 1 in `odd`"]),
+        ),
+        ("undefined", Discipline::Linear, None),
+    ];
+    for (name, discipline, expected) in cases {
+        let sym = Symbol(ctx.interner.get_or_intern(name));
+        let violations = ctx.check_substructural(sym, discipline).map(|violations| {
+            violations
+                .iter()
+                .map(|violation| violation.resolve(&ctx.interner).to_string())
+                .collect::<Vec<_>>()
+        });
+        let expected = expected.map(|lines| lines.iter().map(|line| line.to_string()).collect());
+        assert_eq!(violations, expected, "{} {:?}", name, discipline);
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+use crate::symbols::*;
+
+#[test]
+fn test_compact_interner() {
+    let mut ctx = Context::default();
+    // Those any context has, such as the names of intrinsics
+    let base = ctx.interner_stats();
+    for src in [
+        "{fn swap2 = [swap] apply}",
+        "{rule $a swap2 => swap2 $a}",
+        "junk1 junk2",
+    ] {
+        for item in InterpItemsParser::new()
+            .parse(&mut ctx.interner, src)
+            .unwrap()
+        {
+            match item {
+                crate::interp::InterpItem::FnDef(fn_def) => {
+                    ctx.define_fn(fn_def).unwrap();
+                }
+                crate::interp::InterpItem::Rule(rule) => ctx.define_rule(rule).unwrap(),
+                _ => {}
+            }
+        }
+    }
+    ctx.register_intrinsic("host", 0, |_| Ok(Expr::default()));
+    let kept = Symbol(ctx.interner.get_or_intern("kept"));
+    let more = Symbol(ctx.interner.get_or_intern("more"));
+    let mut vs = ValueStack::from(vec![
+        Value::Symbol(kept),
+        Value::Quote(Box::new(Expr::Call(more))),
+    ]);
+    let mut syms = [Symbol(ctx.interner.get_or_intern("named"))];
+    assert_eq!(
+        ctx.interner_stats(),
+        InternerStats {
+            symbols: base.symbols + 8,
+            bytes: base.bytes + 34
+        }
+    );
+
+    assert_eq!(ctx.compact_interner(&mut vs, &mut syms), 2);
+    assert_eq!(
+        ctx.interner_stats(),
+        InternerStats {
+            symbols: base.symbols + 6,
+            bytes: base.bytes + 24
+        }
+    );
+    assert_eq!(syms[0].resolve(&ctx.interner), "named");
+    assert_eq!(vs.resolve(&ctx.interner).to_string(), "⟨'kept [more]⟩");
+    let swap2 = Symbol(ctx.interner.get("swap2").unwrap());
+    assert_eq!(
+        ctx.lookup_fn(&swap2)
+            .unwrap()
+            .resolve(&ctx.interner)
+            .to_string(),
+        "[swap] apply"
+    );
+    assert_eq!(ctx.rules.len(), 1);
+    assert!(ctx
+        .host_intrinsic(&Symbol(ctx.interner.get("host").unwrap()))
+        .is_some());
+}
@@ -0,0 +1,59 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+use crate::termination::*;
+
+#[test]
+fn test_terminates() {
+    let mut ctx = Context::default();
+    for src in [
+        "{fn false = [drop]}",
+        "{fn not = [false] swap apply}",
+        "{fn dup2 = [clone] apply clone swap2}",
+        "{fn swap2 = swap}",
+        "{fn loop = loop}",
+        "{fn omega = [clone apply] clone apply}",
+        "{fn grow = [a] grow}",
+        "{fn twice = clone compose apply}",
+    ] {
+        let fn_def = FnDefParser::new().parse(&mut ctx.interner, src).unwrap();
+        ctx.define_fn(fn_def).unwrap();
+    }
+    let cases = [
+        ("false", Some("yes")),
+        ("dup2", Some("yes")),
+        ("not", Some("unknown")),
+        ("grow", Some("unknown")),
+        ("twice", Some("unknown")),
+        ("loop", Some("⟨⟩ loop ⟶ ⟨⟩ loop")),
+        (
+            "omega",
+            Some("⟨[clone apply]⟩ clone apply ⟶ ⟨[clone apply] [clone apply]⟩ apply ⟶ ⟨[clone apply]⟩ clone apply"),
+        ),
+        ("undefined", None),
+    ];
+    for (name, expected) in cases {
+        let sym = Symbol(ctx.interner.get_or_intern(name));
+        let termination = ctx.terminates(sym).map(|termination| match termination {
+            Termination::Terminates => "yes".to_owned(),
+            Termination::Unknown => "unknown".to_owned(),
+            Termination::Loops { input, cycle } => {
+                assert_eq!(input, ValueStack::default());
+                let states: Vec<String> = cycle
+                    .iter()
+                    .map(|(vs, e)| {
+                        format!("{} {}", vs.resolve(&ctx.interner), e.resolve(&ctx.interner))
+                    })
+                    .collect();
+                states.join(" ⟶ ")
+            }
+        });
+        assert_eq!(termination.as_deref(), expected, "{}", name);
+    }
+}
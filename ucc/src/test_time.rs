@@ -0,0 +1,49 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::builder::*;
+use crate::test_util::eval_input;
+use std::cell::Cell;
+use std::time::Duration;
+
+/// A clock that moves on 250 milliseconds each time it is read
+#[derive(Default)]
+struct Ticking(Cell<Duration>);
+
+impl Clock for Ticking {
+    fn now(&self) -> Duration {
+        let now = self.0.get();
+        self.0.set(now + Duration::from_millis(250));
+        now
+    }
+}
+
+#[test]
+fn test_time_intrinsics() {
+    let mut interp = InterpBuilder::new()
+        .clock(Ticking::default())
+        .settings_path(None)
+        .build();
+    let cases = [
+        ("time.now_millis", "⇓ ⟨0⟩ \n"),
+        ("time.now_millis", "⇓ ⟨250⟩ \n"),
+        ("time.now_millis time.since", "⇓ ⟨250⟩ \n"),
+        ("1000 time.since", "⇓ ⟨0⟩ \n"),
+        ("2 time.from_secs", "⇓ ⟨2000⟩ \n"),
+        ("2999 time.to_secs", "⇓ ⟨2⟩ \n"),
+        ("[a] time.since", "expected a number"),
+    ];
+    for (input, expected) in cases {
+        eval_input(&mut interp, ":drop");
+        let output = eval_input(&mut interp, input);
+        assert!(output.contains(expected), "Failed on {}: {}", input, output);
+    }
+
+    // The clock is kept by `:reset`
+    eval_input(&mut interp, ":reset");
+    let output = eval_input(&mut interp, "time.now_millis");
+    assert!(output.ends_with("⇓ ⟨1250⟩ \n"), "{}", output);
+}
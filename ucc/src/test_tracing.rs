@@ -0,0 +1,87 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// A subscriber recording the names of the spans and events it's given
+#[derive(Default, Clone)]
+struct Recorder {
+    names: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.names
+            .lock()
+            .unwrap()
+            .push(span.metadata().name().to_owned());
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let level = event.metadata().level();
+        self.names.lock().unwrap().push(format!("{} event", level));
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn test_tracing() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut ctx = Context::default();
+        let fn_def = ctx.parse_fn_def("{fn f = swap}").unwrap();
+        ctx.define_fn(fn_def).unwrap();
+        let mut vs = ctx.parse_value_stack("⟨[a]⟩").unwrap();
+        let mut e = ctx.parse_expr("f").unwrap();
+        while e != Expr::default() {
+            if ctx.small_step(&mut vs, &mut e).is_err() {
+                break;
+            }
+        }
+    });
+    let names = recorder.names.lock().unwrap();
+    assert_eq!(
+        *names,
+        [
+            "parse_fn_def",
+            "define_fn",
+            "parse_value_stack",
+            "parse_expr",
+            "small_step",
+            "small_step",
+            // The error of the second step
+            "ERROR event",
+        ]
+    );
+}
+
+#[test]
+fn test_tracing_interp() {
+    let mut interp = crate::interp::Interp::default();
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        interp.interp_start("]", &mut vec![]).unwrap();
+    });
+    let names = recorder.names.lock().unwrap();
+    assert_eq!(*names, ["DEBUG event"]);
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::limits::*;
+use crate::transaction::*;
+
+fn fn_defs(ctx: &mut Context, srcs: &[&str]) -> Vec<FnDef> {
+    srcs.iter()
+        .map(|src| ctx.parse_fn_def(src).unwrap())
+        .collect()
+}
+
+/// The definitions, in order, and what each body compresses to
+fn state(ctx: &mut Context) -> (Vec<String>, Vec<String>) {
+    let fns = ctx
+        .fns
+        .iter()
+        .map(|(sym, e)| ctx.display(&FnDef(*sym, e.clone())).to_string())
+        .collect();
+    let exprs = ctx
+        .fns
+        .values()
+        .map(|e| {
+            let sym = ctx.exprs.get(e).unwrap();
+            sym.resolve(&ctx.interner)
+        })
+        .collect();
+    (fns, exprs)
+}
+
+#[test]
+fn test_define_all() {
+    let mut ctx = Context::default();
+    ctx.set_definition_limits(DefinitionLimits {
+        body_size: Some(2),
+        fns: Some(3),
+        name_len: None,
+    });
+    let defs = fn_defs(&mut ctx, &["{fn a = [swap]}", "{fn b = [clone]}"]);
+    assert_eq!(
+        ctx.define_all(defs, Validation::Limits),
+        Ok(vec![None, None])
+    );
+    let before = state(&mut ctx);
+
+    // `c` is too large, and `e` one too many
+    let defs = fn_defs(
+        &mut ctx,
+        &[
+            "{fn b = [swap]}",
+            "{fn c = swap swap swap}",
+            "{fn d = }",
+            "{fn e = }",
+        ],
+    );
+    let err = ctx
+        .define_all(defs.clone(), Validation::Limits)
+        .unwrap_err();
+    let errors: Vec<_> = err
+        .errors
+        .iter()
+        .map(|err| err.resolve(&ctx.interner))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ResolvedDefineError::Limit(
+                "c".to_owned(),
+                LimitError::BodyTooLarge { size: 3, limit: 2 }
+            ),
+            ResolvedDefineError::Limit("e".to_owned(), LimitError::TooManyFns { limit: 3 }),
+        ]
+    );
+    assert_eq!(err.rolled_back, defs);
+    // `b` is as it was, and `[swap]` still compresses to `a`
+    assert_eq!(state(&mut ctx), before);
+    assert!(ctx.take_warnings().is_empty());
+
+    let defs = fn_defs(&mut ctx, &["{fn b = [swap]}", "{fn d = drop}"]);
+    let replaced = ctx.define_all(defs, Validation::Limits).unwrap();
+    let old_b = ctx.parse_fn_def("{fn b = [clone]}").unwrap();
+    assert_eq!(replaced, [Some(old_b), None]);
+    let warnings: Vec<_> = ctx
+        .take_warnings()
+        .iter()
+        .map(|warning| warning.resolve(&ctx.interner))
+        .collect();
+    assert_eq!(
+        warnings,
+        [ResolvedWarning::AlwaysUnderflows("d".to_owned())]
+    );
+}
+
+#[test]
+fn test_define_all_strict() {
+    let mut ctx = Context::default();
+    let defs = fn_defs(&mut ctx, &["{fn f = g}", "{fn g = }"]);
+    let err = ctx.define_all(defs, Validation::Strict).unwrap_err();
+    assert_eq!(
+        err.errors[0].resolve(&ctx.interner),
+        ResolvedDefineError::Warning(ResolvedWarning::UndefinedCall {
+            caller: "f".to_owned(),
+            callee: "g".to_owned(),
+        })
+    );
+    assert!(ctx.fns.is_empty() && ctx.exprs.is_empty());
+
+    let defs = fn_defs(&mut ctx, &["{fn g = }", "{fn f = g}"]);
+    assert_eq!(
+        ctx.define_all(defs, Validation::Strict),
+        Ok(vec![None, None])
+    );
+}
+
+#[test]
+fn test_define_many() {
+    let mut ctx = Context::default();
+    ctx.set_definition_limits(DefinitionLimits {
+        body_size: Some(2),
+        fns: None,
+        name_len: None,
+    });
+    let err = ctx
+        .define_many(["{fn a = swap}", "{fn b = [}", "c"].iter().copied())
+        .unwrap_err();
+    let indices: Vec<usize> = err
+        .iter()
+        .map(|err| match err {
+            DefineError::Parse(i, _) => *i,
+            _ => panic!("{:?}", err),
+        })
+        .collect();
+    assert_eq!(indices, [1, 2]);
+    assert!(ctx.fns.is_empty());
+
+    let report = ctx
+        .define_many(["{fn a = swap}", "{fn b = clone}"].iter().copied())
+        .unwrap();
+    assert_eq!(report.replaced, [None, None]);
+    let report = ctx.define_many(["{fn b = drop}"].iter().copied()).unwrap();
+    assert_eq!(report.defined[0].resolve(&ctx.interner), "b".to_owned());
+    assert_eq!(
+        ctx.display(report.replaced[0].as_ref().unwrap())
+            .to_string(),
+        "{fn b = clone}"
+    );
+
+    let err = ctx
+        .define_many(["{fn c = swap swap swap}"].iter().copied())
+        .unwrap_err();
+    assert_eq!(
+        err[0].resolve(&ctx.interner),
+        ResolvedDefineError::Limit(
+            "c".to_owned(),
+            LimitError::BodyTooLarge { size: 3, limit: 2 }
+        )
+    );
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::tutor::*;
+
+#[test]
+fn test_lessons() {
+    let mut tutor = Tutor::default();
+    let titles: Vec<_> = tutor.chapters().iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles, ["Quotes", "Composition", "Booleans", "Numerals"]);
+    let answers = [
+        "[a] [b]",
+        "[a] clone",
+        "[a] [b] drop",
+        "[a] [b] swap",
+        "[a] [b] compose",
+        "[a] quote",
+        "[a] [clone] apply",
+        "[a] [b] true apply",
+        "[a] [b] false apply",
+        "false true or",
+        "[] [[a] compose] n3 apply",
+        "[] [[a] compose] n2 succ apply",
+        "[] [[a] compose] n1 n2 add apply",
+    ];
+    for answer in answers.iter() {
+        assert_eq!(tutor.input(answer), Reply::Correct, "{}", answer);
+    }
+    assert!(tutor.is_done());
+}
+
+#[test]
+fn test_replies() {
+    let mut tutor = Tutor::default();
+    tutor.skip();
+    let step = tutor.step().unwrap();
+    assert_eq!(step.task, "Push [a] and clone it.");
+    assert_eq!(step.expected, "⟨[a] [a]⟩");
+    assert_eq!(step.using, ["clone"]);
+    assert!(step.text.starts_with("`clone` copies"));
+
+    assert_eq!(tutor.input("[a]"), Reply::Wrong("⟨[a]⟩".to_owned()));
+    assert_eq!(tutor.input("[a] [a]"), Reply::Unused("clone".to_owned()));
+    assert!(matches!(tutor.input("[a"), Reply::Error(_)));
+    assert!(matches!(tutor.input("clone"), Reply::Error(_)));
+    assert_eq!(tutor.position(), (0, 1));
+
+    // Definitions are kept from step to step
+    assert_eq!(
+        tutor.input("{fn twice = clone} [a] twice"),
+        Reply::Unused("clone".to_owned())
+    );
+    assert_eq!(tutor.input("[a] clone"), Reply::Correct);
+    assert_eq!(tutor.input("[a] [b] twice drop drop"), Reply::Correct);
+    assert_eq!(tutor.position(), (0, 3));
+
+    tutor.go_to(3);
+    assert_eq!(tutor.step().unwrap().using, ["n3", "apply"]);
+    tutor.go_to(10);
+    assert!(tutor.is_done());
+}
+
+#[test]
+fn test_parse_lessons() {
+    let src = "\
+-- A comment
+# One
+
+Some prose.
+
+> Push [a].
+⟨[a]⟩
+
+> Push [b].
+⟨[b]⟩
+using b
+";
+    assert_eq!(
+        parse_lessons(src),
+        Ok(vec![Chapter {
+            title: "One".to_owned(),
+            steps: vec![
+                Step {
+                    text: "Some prose.".to_owned(),
+                    task: "Push [a].".to_owned(),
+                    expected: "⟨[a]⟩".to_owned(),
+                    using: vec![],
+                },
+                Step {
+                    text: "".to_owned(),
+                    task: "Push [b].".to_owned(),
+                    expected: "⟨[b]⟩".to_owned(),
+                    using: vec!["b".to_owned()],
+                },
+            ],
+        }])
+    );
+    assert_eq!(
+        parse_lessons("> Push [a].\n⟨[a]⟩"),
+        Err(TutorError::Syntax(1))
+    );
+    assert_eq!(
+        parse_lessons("# One\n> Push [a].\n"),
+        Err(TutorError::Syntax(3))
+    );
+    let chapters = parse_lessons("# One\n> Push [a.\n⟨[a⟩").unwrap();
+    assert!(matches!(Tutor::new(chapters), Err(TutorError::Expected(_))));
+}
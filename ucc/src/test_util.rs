@@ -0,0 +1,28 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Drivers shared by the tests
+
+use crate::core::{Context, EvalError, Expr, ValueStack};
+use crate::interp::Interp;
+
+/// Evaluate `e` to completion on `vs`
+pub(crate) fn eval(ctx: &mut Context, vs: &mut ValueStack, mut e: Expr) -> Result<(), EvalError> {
+    while e != Expr::default() {
+        ctx.small_step(vs, &mut e)?;
+    }
+    Ok(())
+}
+
+/// Evaluate `input` to completion, returning what was written
+pub(crate) fn eval_input(interp: &mut Interp, input: &str) -> String {
+    let mut output = vec![];
+    interp.interp_start(input, &mut output).unwrap();
+    while !interp.is_done() {
+        interp.interp_step(&mut output).unwrap();
+    }
+    String::from_utf8(output).unwrap()
+}
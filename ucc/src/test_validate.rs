@@ -0,0 +1,46 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::validate::*;
+
+/// A line, and the start, end and message of each problem with it
+type Case = (&'static str, &'static [(usize, usize, &'static str)]);
+
+#[test]
+fn test_validate() {
+    let cases: &[Case] = &[
+        ("[a] [b] swap", &[]),
+        ("{fn f = [clone] (swap)} -- [ (", &[]),
+        (":stack full", &[]),
+        (":terminates? f", &[]),
+        (":set prompt \"[> \"", &[]),
+        (":search ⟨[a]⟩ ⇓ ⟨[a] [a]⟩", &[]),
+        ("[a", &[(0, 1, "`[` is never closed")]),
+        ("a]", &[(1, 2, "`]` closes nothing")]),
+        ("[a)", &[(0, 3, "`)` can't close `[`")]),
+        ("⟨[a]", &[(0, 3, "`⟨` is never closed")]),
+        (":load \"a", &[(6, 8, "`\"` is never closed")]),
+        ("  :stak", &[(2, 7, "`:stak` isn't a command")]),
+        (
+            ":tracee [a",
+            &[
+                (0, 7, "`:tracee` isn't a command"),
+                (8, 9, "`[` is never closed"),
+            ],
+        ),
+    ];
+    for (line, expected) in cases {
+        let diagnostics: Vec<(usize, usize, String)> = validate(line)
+            .into_iter()
+            .map(|d| (d.start, d.end, d.kind.to_string()))
+            .collect();
+        let expected: Vec<(usize, usize, String)> = expected
+            .iter()
+            .map(|(start, end, message)| (*start, *end, message.to_string()))
+            .collect();
+        assert_eq!(diagnostics, expected, "{}", line);
+    }
+}
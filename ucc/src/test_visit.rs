@@ -0,0 +1,89 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::display::*;
+use crate::parse::*;
+
+#[test]
+fn test_visit() {
+    let mut interner = Interner::default();
+    let e = ExprParser::new()
+        .parse(&mut interner, "a [b [c] 'd] swap")
+        .unwrap();
+    let mut visited = vec![];
+    e.visit(&mut |e| visited.push(e.resolve(&interner).to_string()));
+    assert_eq!(
+        visited,
+        [
+            "a [b [c] 'd] swap",
+            "a",
+            "[b [c] 'd]",
+            "b [c] 'd",
+            "b",
+            "[c]",
+            "c",
+            "'d",
+            "swap",
+        ]
+    );
+}
+
+#[test]
+fn test_visit_mut() {
+    let mut interner = Interner::default();
+    let mut e = ExprParser::new().parse(&mut interner, "a [a [a]]").unwrap();
+    let a = Symbol(interner.get_or_intern("a"));
+    let b = Symbol(interner.get_or_intern("b"));
+    // Children of replacements are visited too
+    let mut bs = 0;
+    e.visit_mut(&mut |e| match e {
+        Expr::Call(sym) if *sym == a => *e = Expr::Quote(Box::new(Expr::Call(b))),
+        Expr::Call(_) => bs += 1,
+        _ => {}
+    });
+    assert_eq!(e.resolve(&interner).to_string(), "[b] [[b] [[b]]]");
+    assert_eq!(bs, 3);
+}
+
+#[test]
+fn test_subst() {
+    let cases = [
+        ("a", "b", "b"),
+        ("a [a] 'a c", "b", "b [b] 'a c"),
+        ("[c a]", "a b", "[c (a b)]"),
+        ("c", "b", "c"),
+    ];
+    let mut interner = Interner::default();
+    let a = Symbol(interner.get_or_intern("a"));
+    for (e, by, expected) in cases {
+        let e = ExprParser::new().parse(&mut interner, e).unwrap();
+        let by = ExprParser::new().parse(&mut interner, by).unwrap();
+        assert_eq!(e.subst(a, &by).resolve(&interner).to_string(), expected);
+    }
+}
+
+#[test]
+fn test_fold() {
+    let mut interner = Interner::default();
+    let e = ExprParser::new()
+        .parse(&mut interner, "[clone drop] apply [[]]")
+        .unwrap();
+    // Remove compositions of just `clone drop`
+    let e = e.fold(&mut |e| match e {
+        Expr::Compose(es)
+            if es
+                == [
+                    Expr::Intrinsic(Intrinsic::Clone),
+                    Expr::Intrinsic(Intrinsic::Drop),
+                ] =>
+        {
+            Expr::default()
+        }
+        e => e,
+    });
+    assert_eq!(e.resolve(&interner).to_string(), "[] apply [[]]");
+}
@@ -0,0 +1,176 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::core::*;
+use crate::shared::SharedPrelude;
+use crate::vm::*;
+
+fn prelude_context() -> Context {
+    Context::with_prelude(&SharedPrelude::default())
+}
+
+#[test]
+fn test_vm() {
+    let cases = [
+        ("⟨[a] [b]⟩", "swap", "⟨[b] [a]⟩"),
+        ("⟨[a]⟩", "[clone] apply", "⟨[a] [a]⟩"),
+        ("⟨[a]⟩", "[clone] apply [drop] compose", "⟨[a] [a drop]⟩"),
+        ("⟨⟩", "true false or", "⟨true⟩"),
+        ("⟨⟩", "'a [b] quote", "⟨'a [[b]]⟩"),
+        ("⟨⟩", "n1 succ succ [[x]] swap apply", "⟨[x] [x] [x]⟩"),
+    ];
+    for (vs, e, expected) in cases {
+        let mut ctx = prelude_context();
+        let vs = ctx.parse_value_stack(vs).unwrap();
+        let e = ctx.parse_expr(e).unwrap();
+        let mut run = ctx.run_compiled(&vs, &e, 10_000);
+        assert_eq!(run.result, Ok(()), "Failed on {}", ctx.display(&e));
+        ctx.compress(&mut run.vs);
+        assert_eq!(
+            ctx.display(&run.vs).to_string(),
+            expected,
+            "Failed on {}",
+            ctx.display(&e)
+        );
+        assert_eq!(ctx.differential(&vs, &e, 10_000), None);
+        assert_eq!(ctx.differential_optimized(&vs, &e, 10_000), None);
+    }
+}
+
+#[test]
+fn test_vm_errors() {
+    let mut ctx = prelude_context();
+    let vs = ctx.parse_value_stack("⟨[a]⟩").unwrap();
+    let cases = [
+        (
+            "swap",
+            Err(VmError::Eval(EvalError::TooFewValues {
+                available: 1,
+                expected: 2,
+            })),
+        ),
+        (
+            "apply",
+            Err(VmError::Eval(EvalError::UndefinedFn(Symbol(
+                ctx.interner.get_or_intern("a"),
+            )))),
+        ),
+        ("[clone apply] clone apply", Err(VmError::OutOfFuel)),
+        ("evalq", Err(VmError::Unsupported(Intrinsic::EvalQ))),
+    ];
+    for (e, expected) in cases {
+        let e = ctx.parse_expr(e).unwrap();
+        let run = ctx.run_compiled(&vs, &e, 1000);
+        assert_eq!(run.result, expected, "Failed on {}", ctx.display(&e));
+        if run.result != Err(VmError::Unsupported(Intrinsic::EvalQ)) {
+            assert_eq!(ctx.differential(&vs, &e, 1000), None);
+            assert_eq!(ctx.differential_optimized(&vs, &e, 1000), None);
+        }
+    }
+}
+
+/// Calls in tail position don't keep frames, so loops run in constant
+/// space, and deep recursion is bounded only by memory
+#[test]
+fn test_vm_depth() {
+    const DEPTH: usize = 100_000;
+    let mut ctx = prelude_context();
+    let mut vs = ctx.parse_value_stack("⟨[drop]⟩").unwrap();
+    let recurse = ctx.parse_value("[apply]").unwrap();
+    vs.0.resize(DEPTH + 1, recurse);
+    let e = ctx.parse_expr("[swap apply] fix").unwrap();
+    let run = ctx.run_compiled(&vs, &e, usize::MAX);
+    assert_eq!(run.result, Ok(()));
+    assert!(run.vs.is_empty());
+}
+
+/// A generator of pseudorandom numbers, xorshift64
+#[cfg(feature = "vm")]
+struct Rng(u64);
+
+#[cfg(feature = "vm")]
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    /// A program of up to 6 items, with quotations nested up to `depth`
+    fn program(&mut self, vocabulary: &[Expr], depth: usize) -> Expr {
+        let es = (0..self.below(7))
+            .map(|_| {
+                if depth > 0 && self.below(4) == 0 {
+                    Expr::Quote(Box::new(self.program(vocabulary, depth - 1)))
+                } else {
+                    vocabulary[self.below(vocabulary.len())].clone()
+                }
+            })
+            .collect();
+        Expr::Compose(es)
+    }
+}
+
+/// The machine, optimizing or not, and the interpreter agree on random
+/// programs, including where they fail or run out of fuel
+#[cfg(feature = "vm")]
+#[test]
+fn test_vm_differential() {
+    let mut ctx = prelude_context();
+    let vocabulary: Vec<Expr> = [
+        "swap", "clone", "drop", "quote", "compose", "apply", "'a", "a", "n0", "n1", "succ", "add",
+        "true", "false", "or", "fix",
+    ]
+    .iter()
+    .map(|src| ctx.parse_expr(src).unwrap())
+    .collect();
+    let vs = ctx.parse_value_stack("⟨[a] [b] [c]⟩").unwrap();
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut outcomes = [0; 3];
+    for _ in 0..5000 {
+        let e = rng.program(&vocabulary, 3);
+        outcomes[match ctx.run_compiled(&vs, &e, 2000).result {
+            Ok(()) => 0,
+            Err(VmError::OutOfFuel) => 1,
+            Err(_) => 2,
+        }] += 1;
+        if let Some((interpreted, compiled)) = ctx.differential(&vs, &e, 2000) {
+            let (shrunk_vs, shrunk_e) = ctx.shrink_counterexample(&vs, &e, |ctx, vs, e| {
+                ctx.differential(vs, e, 2000).is_some()
+            });
+            let shrunk = ctx.big_step_assertion(&shrunk_vs, &shrunk_e, 2000);
+            panic!(
+                "Engines differ on {}: {:?} {}, {:?} {}\nShrunk: {}",
+                ctx.display(&e),
+                interpreted.result,
+                ctx.display(&interpreted.vs),
+                compiled.result,
+                ctx.display(&compiled.vs),
+                shrunk,
+            );
+        }
+        if let Some((interpreted, optimized)) = ctx.differential_optimized(&vs, &e, 2000) {
+            let (shrunk_vs, shrunk_e) = ctx.shrink_counterexample(&vs, &e, |ctx, vs, e| {
+                ctx.differential_optimized(vs, e, 2000).is_some()
+            });
+            let shrunk = ctx.big_step_assertion(&shrunk_vs, &shrunk_e, 2000);
+            panic!(
+                "Optimizer differs on {}: {:?} {} {}, {:?} {} {}\nShrunk: {}",
+                ctx.display(&e),
+                interpreted.result,
+                ctx.display(&interpreted.vs),
+                interpreted.fuel,
+                optimized.result,
+                ctx.display(&optimized.vs),
+                optimized.fuel,
+                shrunk,
+            );
+        }
+    }
+    // Every outcome is tested
+    assert!(outcomes.iter().all(|&n| n > 50), "{:?}", outcomes);
+}
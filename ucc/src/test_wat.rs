@@ -0,0 +1,48 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::codegen::CodegenError;
+use crate::wat::*;
+
+#[test]
+fn test_wat() {
+    let src = wat("{fn not = [false] [true] rotate3 apply} {fn f = 'g apply undefined}").unwrap();
+    ::wat::parse_str(&src).unwrap();
+    let fns: Vec<_> = src
+        .lines()
+        .filter(|line| line.starts_with("  (func $f") && line.ends_with("\")"))
+        .collect();
+    assert_eq!(
+        fns,
+        [
+            "  (func $f0 (export \"not\")",
+            "  (func $f1 (export \"f\")",
+            "  (func $f2 (export \"false\")",
+            "  (func $f3 (export \"true\")",
+            "  (func $f4 (export \"rotate3\")",
+            "  (func $f7 (export \"core.quote2\")",
+        ]
+    );
+    assert!(src.contains(
+        "  (func $f1 (export \"f\")\n    \
+             (call $push (i32.const 48))\n    \
+             (call $apply)\n    \
+             (call $call_index (i32.const 6)))\n"
+    ));
+    assert!(src.contains(
+        "  (elem (i32.const 0) $swap $clone $drop $quote $compose $apply $clone $over $rot \
+         $cake $k $f0 $f1 $f2 $f3 $f4 $undefined $undefined $f7)\n"
+    ));
+}
+
+#[test]
+fn test_wat_errors() {
+    assert_eq!(
+        wat("{fn f = ['a defined?]}"),
+        Err(CodegenError::Unsupported("defined?".to_owned()))
+    );
+    assert!(matches!(wat("{fn f"), Err(CodegenError::Parse(_))));
+}
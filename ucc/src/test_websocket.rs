@@ -0,0 +1,74 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::websocket::*;
+
+/// `payload` as a client would send it, masked by `mask`
+fn masked(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut bytes = encode_frame(&match opcode {
+        1 => Frame::Text(String::from_utf8(payload.to_vec()).unwrap()),
+        _ => Frame::Binary(payload.to_vec()),
+    });
+    let at = bytes.len() - payload.len();
+    bytes[1] |= 0x80;
+    for (i, b) in bytes[at..].iter_mut().enumerate() {
+        *b ^= mask[i % 4];
+    }
+    bytes.splice(at..at, mask);
+    bytes
+}
+
+#[test]
+fn test_accept_key() {
+    // The example from RFC 6455
+    assert_eq!(
+        accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+}
+
+#[test]
+fn test_parse_frame() {
+    // A masked "Hello", from RFC 6455
+    let hello = [
+        0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+    ];
+    assert_eq!(
+        parse_frame(&hello),
+        Ok(Some((Frame::Text("Hello".to_owned()), hello.len())))
+    );
+    for len in 0..hello.len() {
+        assert_eq!(parse_frame(&hello[..len]), Ok(None));
+    }
+    let long = vec![7; 300];
+    let bytes = masked(2, &long, [1, 2, 3, 4]);
+    assert_eq!(
+        parse_frame(&bytes),
+        Ok(Some((Frame::Binary(long), bytes.len())))
+    );
+    let cases = [
+        (vec![0x81, 0x00], FrameError::Unmasked),
+        (vec![0x01, 0x80, 0, 0, 0, 0], FrameError::Fragmented),
+        (vec![0x83, 0x80, 0, 0, 0, 0], FrameError::BadOpcode(3)),
+        (
+            vec![0x81, 0xff, 0, 0, 0, 0, 0x10, 0, 0, 0],
+            FrameError::TooLong(1 << 28),
+        ),
+        (vec![0x81, 0x81, 0, 0, 0, 0, 0xff], FrameError::BadUtf8),
+    ];
+    for (bytes, err) in cases {
+        assert_eq!(parse_frame(&bytes), Err(err), "Failed on {:?}", bytes);
+    }
+}
+
+#[test]
+fn test_encode_frame() {
+    assert_eq!(encode_frame(&Frame::Text("Hi".to_owned())), b"\x81\x02Hi");
+    assert_eq!(encode_frame(&Frame::Close), [0x88, 0x00]);
+    let bytes = encode_frame(&Frame::Binary(vec![0; 70000]));
+    assert_eq!(bytes[..10], [0x82, 127, 0, 0, 0, 0, 0, 1, 0x11, 0x70]);
+    assert_eq!(bytes.len(), 70010);
+}
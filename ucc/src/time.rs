@@ -0,0 +1,69 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Reading the interpreter's clock, so programs can time themselves. This
+//! module exists only with the `bignum` feature. Durations are numbers of
+//! milliseconds, added and subtracted with `nat.add` and `nat.sub`, and an
+//! interpreter has the intrinsics
+//!
+//! - `time.now_millis`, pushing the milliseconds on its clock,
+//! - `time.since`, `⟨t⟩ time.since ⟶ ⟨d⟩`, the milliseconds since `t`
+//!   was read,
+//! - `time.from_secs` and `time.to_secs`, `⟨2⟩ time.from_secs ⟶ ⟨2000⟩`,
+//!   rounding down to whole seconds.
+//!
+//! The clock is the one the `InterpBuilder` was given, so a test with a
+//! fake clock sees the same times on every run. It only counts from some
+//! fixed instant, such as the interpreter's start, so there are no dates.
+
+use crate::builder::Clock;
+use crate::core::{Context, EvalError, Expr, Value};
+use num_bigint::BigUint;
+use std::rc::Rc;
+
+/// The milliseconds on `clock`
+fn now_millis(clock: &dyn Clock) -> BigUint {
+    clock.now().as_millis().into()
+}
+
+impl Context {
+    /// Register the `time.` intrinsics, reading `clock`, replacing any
+    /// reading another clock
+    pub(crate) fn register_time_intrinsics(&mut self, clock: Rc<dyn Clock>) {
+        let expected_number = || EvalError::Host("expected a number".to_owned());
+        let now = clock.clone();
+        self.register_intrinsic("time.now_millis", 0, move |vs| {
+            vs.push(Value::Nat(now_millis(&*now)));
+            Ok(Expr::default())
+        });
+        self.register_intrinsic("time.since", 1, move |vs| match vs.pop() {
+            Some(Value::Nat(t)) => {
+                let now = now_millis(&*clock);
+                vs.push(Value::Nat(if now > t {
+                    now - t
+                } else {
+                    BigUint::default()
+                }));
+                Ok(Expr::default())
+            }
+            _ => Err(expected_number()),
+        });
+        self.register_intrinsic("time.from_secs", 1, move |vs| match vs.pop() {
+            Some(Value::Nat(n)) => {
+                vs.push(Value::Nat(n * 1000u32));
+                Ok(Expr::default())
+            }
+            _ => Err(expected_number()),
+        });
+        self.register_intrinsic("time.to_secs", 1, move |vs| match vs.pop() {
+            Some(Value::Nat(n)) => {
+                vs.push(Value::Nat(n / 1000u32));
+                Ok(Expr::default())
+            }
+            _ => Err(expected_number()),
+        });
+    }
+}
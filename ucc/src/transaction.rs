@@ -0,0 +1,154 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Defining many functions at once, all or none, so that a file with a
+//! broken definition can't leave a session with only some of its own.
+
+use crate::core::{Context, Expr, FnDef, Symbol};
+use crate::diagnostics::Warning;
+use crate::limits::LimitError;
+use crate::load::LoadError;
+use std::sync::Arc;
+
+/// What definitions made together are checked for before they are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /// That they are within the definition limits
+    Limits,
+    /// That they are within the limits, and that none would be warned
+    /// about, as for calling an undefined function or always underflowing
+    Strict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefineError {
+    Limit(Symbol, LimitError),
+    Warning(Warning),
+    /// The source at this index, counting from 0, isn't a definition
+    Parse(usize, LoadError),
+}
+
+/// Why `Context::define_all` defined nothing, with the definitions it was
+/// given back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefineAllError {
+    pub errors: Vec<DefineError>,
+    pub rolled_back: Vec<FnDef>,
+}
+
+/// What `Context::define_many` defined, with an entry for each source, in
+/// order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DefineReport {
+    /// The name each source defined
+    pub defined: Vec<Symbol>,
+    /// The definition each replaced, if any, as with `define_fn`
+    pub replaced: Vec<Option<FnDef>>,
+}
+
+/// How to undo a definition: what its name was defined as, and what its
+/// body was the definition of, before
+struct Undo {
+    sym: Symbol,
+    old_body: Option<Expr>,
+    old_owner: Option<Symbol>,
+}
+
+impl Context {
+    /// Define every one of `fn_defs`, in order, or none of them if any
+    /// fails `validation`. Returns the definitions each replaced, like
+    /// `define_fn`, and makes the same warnings and folds.
+    pub fn define_all(
+        &mut self,
+        fn_defs: Vec<FnDef>,
+        validation: Validation,
+    ) -> Result<Vec<Option<FnDef>>, DefineAllError> {
+        let mut errors = vec![];
+        let mut warnings = vec![];
+        let mut undos = Vec::with_capacity(fn_defs.len());
+        let mut replaced = Vec::with_capacity(fn_defs.len());
+        for fn_def in fn_defs.iter() {
+            // Later definitions are checked against those before them
+            if let Err(err) = self.check_limits(fn_def) {
+                errors.push(DefineError::Limit(fn_def.0, err));
+                replaced.push(None);
+                continue;
+            }
+            warnings.extend(self.diagnose(fn_def));
+            let FnDef(sym, e) = fn_def.clone();
+            let old_body = Arc::make_mut(&mut self.fns).insert(sym, e.clone());
+            let old_owner = Arc::make_mut(&mut self.exprs).insert(e, sym);
+            replaced.push(old_body.clone().map(|old| FnDef(sym, old)));
+            undos.push(Undo {
+                sym,
+                old_body,
+                old_owner,
+            });
+        }
+        if validation == Validation::Strict {
+            errors.extend(warnings.drain(..).map(DefineError::Warning));
+        }
+        if !errors.is_empty() {
+            self.undo(undos);
+            return Err(DefineAllError {
+                errors,
+                rolled_back: fn_defs,
+            });
+        }
+        self.warnings.extend(warnings);
+        if self.prefold {
+            // Once all are defined, as they may call each other
+            for FnDef(sym, _) in fn_defs.iter() {
+                self.prefold_fn(*sym);
+            }
+        }
+        Ok(replaced)
+    }
+
+    /// Parse each of `srcs` as a definition, `{fn <sym> = <expr>}`, and
+    /// define them all, or none of them if any doesn't parse or goes over
+    /// the limits. The errors are those of every source that doesn't parse,
+    /// or if all do, those `define_all` finds.
+    pub fn define_many<'a>(
+        &mut self,
+        srcs: impl Iterator<Item = &'a str>,
+    ) -> Result<DefineReport, Vec<DefineError>> {
+        let mut fn_defs = vec![];
+        let mut errors = vec![];
+        for (i, src) in srcs.enumerate() {
+            match self.parse_fn_def(src) {
+                Ok(fn_def) => fn_defs.push(fn_def),
+                Err(err) => errors.push(DefineError::Parse(i, err)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let defined = fn_defs.iter().map(|FnDef(sym, _)| *sym).collect();
+        let replaced = self
+            .define_all(fn_defs, Validation::Limits)
+            .map_err(|err| err.errors)?;
+        Ok(DefineReport { defined, replaced })
+    }
+
+    /// Undo the definitions logged in `undos`, latest first
+    fn undo(&mut self, undos: Vec<Undo>) {
+        let fns = Arc::make_mut(&mut self.fns);
+        let exprs = Arc::make_mut(&mut self.exprs);
+        for undo in undos.into_iter().rev() {
+            let body = match undo.old_body {
+                // Redefinitions keep their original position
+                Some(old_body) => fns.insert(undo.sym, old_body),
+                None => fns.shift_remove(&undo.sym),
+            };
+            let body = body.unwrap();
+            match undo.old_owner {
+                Some(owner) => exprs.insert(body, owner),
+                None => exprs.remove(&body),
+            };
+        }
+    }
+}
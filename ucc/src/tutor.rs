@@ -0,0 +1,233 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The guided tutorial of `ucci tutor`: chapters on quotes, composition,
+//! booleans, and numerals, each a series of steps that explain something
+//! and set a task, checked by the value stack the input leaves. The
+//! lessons are in `tutor.txt`, and definitions made along the way are kept
+//! from step to step.
+
+use crate::core::{Context, Expr, ValueStack};
+use crate::interp::InterpItem;
+use crate::parse::InterpItemsParser;
+use crate::shared::SharedPrelude;
+
+/// The builtin lessons
+pub static LESSONS: &str = include_str!("tutor.txt");
+
+/// The most small steps the input for a step may take
+pub const TUTOR_FUEL: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// The prose before the task
+    pub text: String,
+    pub task: String,
+    /// The value stack the input should leave, as written
+    pub expected: String,
+    /// The words the input should use
+    pub using: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TutorError {
+    /// A line of the lessons, from 1, out of place
+    Syntax(usize),
+    /// The expected value stack of a step, given by its task, is invalid
+    Expected(String),
+}
+
+/// What the tutor makes of an input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// The input did the task, and the tutor moved on to the next
+    Correct,
+    /// The input left this value stack instead
+    Wrong(String),
+    /// The input left the value stack expected, but without this word
+    Unused(String),
+    /// The input couldn't be parsed or evaluated
+    Error(String),
+}
+
+/// The chapters of the lessons `src`
+pub fn parse_lessons(src: &str) -> Result<Vec<Chapter>, TutorError> {
+    let mut chapters: Vec<Chapter> = vec![];
+    let mut text = String::new();
+    let mut lines = src.lines().enumerate().peekable();
+    while let Some((i, line)) = lines.next() {
+        if line.starts_with("--") {
+            continue;
+        }
+        if let Some(title) = line.strip_prefix("# ") {
+            chapters.push(Chapter {
+                title: title.trim().to_owned(),
+                steps: vec![],
+            });
+            text.clear();
+        } else if let Some(task) = line.strip_prefix("> ") {
+            let chapter = chapters.last_mut().ok_or(TutorError::Syntax(i + 1))?;
+            let expected = match lines.next() {
+                Some((_, expected)) if expected.starts_with('⟨') => expected,
+                _ => return Err(TutorError::Syntax(i + 2)),
+            };
+            let using = match lines.peek() {
+                Some((_, line)) if line.starts_with("using ") => {
+                    let words = line["using ".len()..].split_whitespace();
+                    let using = words.map(str::to_owned).collect();
+                    lines.next();
+                    using
+                }
+                _ => vec![],
+            };
+            chapter.steps.push(Step {
+                text: text.trim().to_owned(),
+                task: task.trim().to_owned(),
+                expected: expected.trim().to_owned(),
+                using,
+            });
+            text.clear();
+        } else if line.trim().is_empty() && text.is_empty() {
+            continue;
+        } else {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    Ok(chapters)
+}
+
+/// A tutorial in progress
+pub struct Tutor {
+    chapters: Vec<Chapter>,
+    chapter: usize,
+    step: usize,
+    ctx: Context,
+}
+
+impl Default for Tutor {
+    /// The builtin lessons, from the start
+    fn default() -> Self {
+        Tutor::new(parse_lessons(LESSONS).unwrap()).unwrap()
+    }
+}
+
+impl Tutor {
+    /// A tutorial of `chapters`, from the start, with the prelude
+    pub fn new(chapters: Vec<Chapter>) -> Result<Self, TutorError> {
+        let mut ctx = Context::with_prelude(&SharedPrelude::default());
+        for step in chapters.iter().flat_map(|chapter| chapter.steps.iter()) {
+            if ctx.parse_value_stack(&step.expected).is_err() {
+                return Err(TutorError::Expected(step.task.clone()));
+            }
+        }
+        Ok(Tutor {
+            chapters,
+            chapter: 0,
+            step: 0,
+            ctx,
+        })
+    }
+
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
+
+    /// The indices of the current chapter and step
+    pub fn position(&self) -> (usize, usize) {
+        (self.chapter, self.step)
+    }
+
+    /// Start over at the first step of the chapter at `chapter`, or finish
+    /// if there is none
+    pub fn go_to(&mut self, chapter: usize) {
+        self.chapter = chapter.min(self.chapters.len());
+        self.step = 0;
+    }
+
+    /// The current step, or `None` once all are done
+    pub fn step(&self) -> Option<&Step> {
+        self.chapters.get(self.chapter)?.steps.get(self.step)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.step().is_none()
+    }
+
+    /// Move on to the next step without doing this one
+    pub fn skip(&mut self) {
+        if self.is_done() {
+            return;
+        }
+        self.step += 1;
+        if self.step == self.chapters[self.chapter].steps.len() {
+            self.chapter += 1;
+            self.step = 0;
+        }
+    }
+
+    /// Evaluate `input` from an empty value stack, making any definitions
+    /// in it, and check it against the current step
+    pub fn input(&mut self, input: &str) -> Reply {
+        let step = match self.step() {
+            Some(step) => step.clone(),
+            None => return Reply::Error("The tutorial is over.".to_owned()),
+        };
+        let items = match InterpItemsParser::new().parse(&mut self.ctx.interner, input) {
+            Ok(items) => items,
+            Err(err) => return Reply::Error(format!("{:?}", err)),
+        };
+        let mut vs = ValueStack::default();
+        let mut words = vec![];
+        for item in items {
+            match item {
+                InterpItem::FnDef(fn_def) => {
+                    if let Err(err) = self.ctx.define_fn(fn_def) {
+                        return Reply::Error(format!("{:?}", err));
+                    }
+                }
+                InterpItem::Expr(mut e) => {
+                    e.visit(&mut |e| {
+                        if let Expr::Intrinsic(_) | Expr::Call(_) = e {
+                            words.push(self.ctx.display(e).to_string());
+                        }
+                    });
+                    if let Err(err) = self.eval(&mut vs, &mut e) {
+                        return Reply::Error(err);
+                    }
+                }
+                _ => return Reply::Error("Only definitions and expressions, please.".to_owned()),
+            }
+        }
+        let expected = self.ctx.parse_value_stack(&step.expected).unwrap();
+        if vs != expected {
+            return Reply::Wrong(self.ctx.display(&vs).to_string());
+        }
+        if let Some(word) = step.using.iter().find(|word| !words.contains(word)) {
+            return Reply::Unused(word.clone());
+        }
+        self.skip();
+        Reply::Correct
+    }
+
+    fn eval(&mut self, vs: &mut ValueStack, e: &mut Expr) -> Result<(), String> {
+        for _ in 0..TUTOR_FUEL {
+            if *e == Expr::default() {
+                return Ok(());
+            }
+            self.ctx
+                .small_step(vs, e)
+                .map_err(|err| format!("{:?}", err))?;
+        }
+        Err(format!("Stopped after {} steps.", TUTOR_FUEL))
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The parts of writing the `.uccb` format (see `binary`) that don't need
+//! the rest of the crate, so `build.rs` includes this file to precompile
+//! the prelude with the same tags and version the crate reads.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub const MAGIC: &[u8; 4] = b"UCCB";
+pub const VERSION: u8 = 2;
+
+pub(crate) const TAG_COMPOSE: u8 = 0x00;
+pub(crate) const TAG_QUOTE: u8 = 0x01;
+pub(crate) const TAG_CALL: u8 = 0x02;
+pub(crate) const TAG_SYMBOL: u8 = 0x03;
+pub(crate) const TAG_HOST: u8 = 0x04;
+#[cfg(feature = "bignum")]
+pub(crate) const TAG_NAT: u8 = 0x05;
+#[cfg(feature = "rational")]
+pub(crate) const TAG_RATIO: u8 = 0x06;
+pub(crate) const TAG_PAIR: u8 = 0x07;
+pub(crate) const TAG_MAP: u8 = 0x08;
+pub(crate) const TAG_CHAR: u8 = 0x09;
+pub(crate) const TAG_STR: u8 = 0x0a;
+pub(crate) const TAG_INTRINSIC: u8 = 0x10;
+
+/// The intrinsics, as written in source, in the order of their tags from
+/// `TAG_INTRINSIC`, for `build.rs`, which can't name `binary::INTRINSICS`
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) const INTRINSIC_NAMES: [&str; 15] = [
+    "swap", "clone", "drop", "quote", "compose", "apply", "defined?", "body-of", "define!", "dup",
+    "over", "rot", "cake", "k", "evalq",
+];
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Writes a file's definitions, giving each name an index in the order it
+/// is first written
+pub(crate) struct Writer<N> {
+    names: HashMap<N, usize>,
+    order: Vec<N>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl<N> Default for Writer<N> {
+    fn default() -> Self {
+        Writer {
+            names: HashMap::new(),
+            order: vec![],
+            body: vec![],
+        }
+    }
+}
+
+impl<N: Copy + Eq + Hash> Writer<N> {
+    pub(crate) fn name(&mut self, name: N) {
+        let next = self.order.len();
+        let i = *self.names.entry(name).or_insert(next);
+        if i == next {
+            self.order.push(name);
+        }
+        self.varint(i);
+    }
+
+    pub(crate) fn varint(&mut self, n: usize) {
+        write_varint(&mut self.body, n);
+    }
+
+    /// The whole file, with the text of each name from `resolve`
+    pub(crate) fn finish<'a>(self, resolve: impl Fn(N) -> &'a str) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        write_varint(&mut out, self.order.len());
+        for name in self.order {
+            let name = resolve(name);
+            write_varint(&mut out, name.len());
+            out.extend_from_slice(name.as_bytes());
+        }
+        out.extend(self.body);
+        out
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Checks of a line of input as it is typed, before it is submitted, for
+//! frontends to underline what won't parse. Only delimiters and command
+//! names are checked, in one pass over the line without parsing it, so
+//! this is cheap enough to run on every keystroke.
+
+use crate::help::command_names;
+use crate::interp::Interp;
+use std::fmt;
+
+/// A problem with the text from byte `start` to byte `end` of a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// An opening delimiter, or `"`, never closed
+    Unclosed(char),
+    /// A closing delimiter with nothing open
+    Unopened(char),
+    /// A closing delimiter for another than the one open
+    Mismatched {
+        open: char,
+        close: char,
+    },
+    UnknownCommand(String),
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::Unclosed(c) => write!(f, "`{}` is never closed", c),
+            DiagnosticKind::Unopened(c) => write!(f, "`{}` closes nothing", c),
+            DiagnosticKind::Mismatched { open, close } => {
+                write!(f, "`{}` can't close `{}`", close, open)
+            }
+            DiagnosticKind::UnknownCommand(name) => write!(f, "`{}` isn't a command", name),
+        }
+    }
+}
+
+/// The closing delimiter for each opening one
+const DELIMITERS: [(char, char); 4] = [('[', ']'), ('(', ')'), ('{', '}'), ('⟨', '⟩')];
+
+/// The problems with `line`, in the order they occur in it
+pub fn validate(line: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let trimmed = line.trim_start();
+    if trimmed.starts_with(':') {
+        let start = line.len() - trimmed.len();
+        let len = trimmed
+            .find(|c: char| c.is_whitespace() || c == '"' || c == '⟨')
+            .unwrap_or(trimmed.len());
+        let name = &trimmed[..len];
+        if !command_names().any(|command| command == name) {
+            diagnostics.push(Diagnostic {
+                start,
+                end: start + len,
+                kind: DiagnosticKind::UnknownCommand(name.to_owned()),
+            });
+        }
+    }
+    // The opening delimiters not yet closed, and where they are
+    let mut open: Vec<(usize, char)> = vec![];
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '-' && matches!(chars.peek(), Some((_, '-'))) {
+            // A comment, up to the end of the line
+            while matches!(chars.peek(), Some((_, c)) if *c != '\n') {
+                chars.next();
+            }
+        } else if c == '"' {
+            if chars.by_ref().all(|(_, c)| c != '"') {
+                diagnostics.push(Diagnostic {
+                    start: i,
+                    end: line.len(),
+                    kind: DiagnosticKind::Unclosed('"'),
+                });
+            }
+        } else if DELIMITERS.iter().any(|(o, _)| *o == c) {
+            open.push((i, c));
+        } else if let Some((o, _)) = DELIMITERS.iter().find(|(_, close)| *close == c) {
+            let end = i + c.len_utf8();
+            match open.pop() {
+                Some((_, top)) if top == *o => {}
+                Some((start, top)) => {
+                    diagnostics.push(Diagnostic {
+                        start,
+                        end,
+                        kind: DiagnosticKind::Mismatched {
+                            open: top,
+                            close: c,
+                        },
+                    });
+                }
+                None => diagnostics.push(Diagnostic {
+                    start: i,
+                    end,
+                    kind: DiagnosticKind::Unopened(c),
+                }),
+            }
+        }
+    }
+    for (start, c) in open {
+        diagnostics.push(Diagnostic {
+            start,
+            end: start + c.len_utf8(),
+            kind: DiagnosticKind::Unclosed(c),
+        });
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.start);
+    diagnostics
+}
+
+impl Interp {
+    /// The problems with `line`, typed but not yet submitted, that would
+    /// keep it from parsing: unbalanced delimiters, and unknown commands
+    pub fn validate(&self, line: &str) -> Vec<Diagnostic> {
+        validate(line)
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Traversals over expressions, so tools needn't write their own recursion
+//! over every `Expr` variant, e.g.
+//!
+//! ```
+//! # use ucc::core::*;
+//! // Replace every `swap` with `swap swap swap`
+//! let mut e = Expr::Quote(Box::new(Expr::Intrinsic(Intrinsic::Swap)));
+//! e = e.fold(&mut |e| match e {
+//!     Expr::Intrinsic(Intrinsic::Swap) => Expr::Compose(vec![e.clone(), e.clone(), e]),
+//!     e => e,
+//! });
+//! let mut swaps = 0;
+//! e.visit(&mut |e| {
+//!     if *e == Expr::Intrinsic(Intrinsic::Swap) {
+//!         swaps += 1
+//!     }
+//! });
+//! assert_eq!(swaps, 3);
+//! ```
+
+use crate::core::{Expr, Symbol};
+
+impl Expr {
+    /// Call `f` on this expression and each of its subexpressions, parents
+    /// before their children, and in order.
+    pub fn visit<F: FnMut(&Expr)>(&self, f: &mut F) {
+        f(self);
+        match self {
+            Expr::Intrinsic(_)
+            | Expr::Call(_)
+            | Expr::Symbol(_)
+            | Expr::Map(_)
+            | Expr::Char(_)
+            | Expr::Str(_) => {}
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => {}
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => {}
+            Expr::Quote(e) => e.visit(f),
+            Expr::Pair(p) => {
+                p.0.visit(f);
+                p.1.visit(f);
+            }
+            Expr::Compose(es) => {
+                for e in es {
+                    e.visit(f);
+                }
+            }
+        }
+    }
+
+    /// Call `f` on this expression and each of its subexpressions, parents
+    /// before their children, so the children visited are those of the
+    /// expression `f` leaves.
+    pub fn visit_mut<F: FnMut(&mut Expr)>(&mut self, f: &mut F) {
+        f(self);
+        match self {
+            Expr::Intrinsic(_)
+            | Expr::Call(_)
+            | Expr::Symbol(_)
+            | Expr::Map(_)
+            | Expr::Char(_)
+            | Expr::Str(_) => {}
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => {}
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => {}
+            Expr::Quote(e) => e.visit_mut(f),
+            Expr::Pair(p) => {
+                p.0.visit_mut(f);
+                p.1.visit_mut(f);
+            }
+            Expr::Compose(es) => {
+                for e in es {
+                    e.visit_mut(f);
+                }
+            }
+        }
+    }
+
+    /// Rebuild this expression bottom-up, replacing each subexpression with
+    /// the result of `f` on it after its children have been replaced.
+    pub fn fold<F: FnMut(Expr) -> Expr>(self, f: &mut F) -> Expr {
+        let e = match self {
+            e @ Expr::Intrinsic(_)
+            | e @ Expr::Call(_)
+            | e @ Expr::Symbol(_)
+            | e @ Expr::Map(_)
+            | e @ Expr::Char(_)
+            | e @ Expr::Str(_) => e,
+            #[cfg(feature = "bignum")]
+            e @ Expr::Nat(_) => e,
+            #[cfg(feature = "rational")]
+            e @ Expr::Ratio(_) => e,
+            Expr::Quote(e) => Expr::Quote(Box::new(e.fold(f))),
+            Expr::Pair(p) => {
+                let (e1, e2) = *p;
+                Expr::Pair(Box::new((e1.fold(f), e2.fold(f))))
+            }
+            Expr::Compose(es) => Expr::Compose(es.into_iter().map(|e| e.fold(f)).collect()),
+        };
+        f(e)
+    }
+
+    /// This expression with every call to `sym` replaced by `by`, which
+    /// isn't itself searched. Symbol literals `'sym` are left as they are.
+    pub fn subst(&self, sym: Symbol, by: &Expr) -> Expr {
+        self.clone().fold(&mut |e| match e {
+            Expr::Call(call) if call == sym => by.clone(),
+            e => e,
+        })
+    }
+}
@@ -0,0 +1,396 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A bytecode virtual machine, which evaluates expressions as small steps
+//! do, but runs each definition and quotation from a flat list of ops it
+//! compiles once, the first time it's called or applied, instead of
+//! rebuilding the remaining expression at every step.
+//!
+//! An op that isn't `Return` takes as much fuel as the small step it
+//! stands for, so both take the same fuel to evaluate an expression, and
+//! run out of it at the same point. Definitions are compiled as they are
+//! when first called, so a machine should be made afresh after they
+//! change. It doesn't apply rewrite rules or run hooks, and can't run the
+//! reflection intrinsics, `evalq`, or host intrinsics.
+
+use crate::core::{Context, EvalError, Expr, Interner, Intrinsic, Map, Symbol, Value, ValueStack};
+use crate::display::Resolve;
+use crate::pair::pair_value;
+use crate::peephole::{inlinable, peephole};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// An intrinsic, which runs as a small step of it would
+    Intrinsic(Intrinsic),
+    /// Push the constant at an index into the constant pool
+    Push(usize),
+    /// Call a function, compiling it if it hasn't been, or push it if its
+    /// body is a quotation
+    Call(Symbol),
+    /// Fail unless there are at least this many values, as the ops it
+    /// replaced would have
+    Check(usize),
+    /// Return to the caller, or stop if there is none
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    Eval(EvalError),
+    /// An intrinsic the machine can't run
+    Unsupported(Intrinsic),
+    /// The context has rewrite rules, which the machine doesn't apply
+    Rules,
+    OutOfFuel,
+}
+
+impl From<EvalError> for VmError {
+    fn from(err: EvalError) -> Self {
+        VmError::Eval(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Vm {
+    pub(crate) code: Vec<Op>,
+    pub(crate) constants: Vec<Value>,
+    /// Where the code of each function compiled starts
+    pub(crate) entries: Map<Symbol, usize>,
+    /// Where the code of each quotation compiled starts
+    quotations: Map<Expr, usize>,
+    constant_indices: Map<Value, usize>,
+    /// Whether to optimize what is compiled
+    optimize: bool,
+}
+
+/// Whether the machine can run `intr`
+fn is_supported(intr: Intrinsic) -> bool {
+    matches!(
+        intr,
+        Intrinsic::Swap
+            | Intrinsic::Clone
+            | Intrinsic::Drop
+            | Intrinsic::Quote
+            | Intrinsic::Compose
+            | Intrinsic::Apply
+            | Intrinsic::Dup
+            | Intrinsic::Over
+            | Intrinsic::Rot
+            | Intrinsic::Cake
+            | Intrinsic::K
+    )
+}
+
+impl Vm {
+    /// A machine that optimizes what it compiles (see `crate::peephole`)
+    pub fn optimized() -> Self {
+        Vm {
+            optimize: true,
+            ..Vm::default()
+        }
+    }
+
+    /// Compile `e`, if it hasn't been, returning where its code starts.
+    pub fn compile(&mut self, ctx: &Context, e: &Expr) -> Result<usize, VmError> {
+        if let Some(&entry) = self.quotations.get(e) {
+            return Ok(entry);
+        }
+        let entry = self.emit_body(ctx, e)?;
+        self.quotations.insert(e.clone(), entry);
+        Ok(entry)
+    }
+
+    /// Compile the definition of `sym`, if it hasn't been, returning where
+    /// its code starts.
+    pub(crate) fn compile_fn(
+        &mut self,
+        ctx: &Context,
+        sym: Symbol,
+        body: &Expr,
+    ) -> Result<usize, VmError> {
+        if let Some(&entry) = self.entries.get(&sym) {
+            return Ok(entry);
+        }
+        let entry = self.emit_body(ctx, body)?;
+        self.entries.insert(sym, entry);
+        Ok(entry)
+    }
+
+    /// Emit `e` followed by `Return`
+    fn emit_body(&mut self, ctx: &Context, e: &Expr) -> Result<usize, VmError> {
+        let mut ops = vec![];
+        self.emit(ctx, e, self.optimize, &mut ops)?;
+        if self.optimize {
+            ops = peephole(ops);
+        }
+        let entry = self.code.len();
+        self.code.extend(ops);
+        self.code.push(Op::Return);
+        Ok(entry)
+    }
+
+    /// Emit the ops of `e` to `ops`, with the bodies of small functions it
+    /// calls in place of the calls if `inline`
+    fn emit(
+        &mut self,
+        ctx: &Context,
+        e: &Expr,
+        inline: bool,
+        ops: &mut Vec<Op>,
+    ) -> Result<(), VmError> {
+        let op = match e {
+            Expr::Compose(es) => {
+                return es.iter().try_for_each(|e| self.emit(ctx, e, inline, ops));
+            }
+            Expr::Intrinsic(intr) if is_supported(*intr) => Op::Intrinsic(*intr),
+            Expr::Intrinsic(intr) => return Err(VmError::Unsupported(*intr)),
+            Expr::Call(sym) if inline => match ctx.lookup_fn(sym) {
+                Some(Expr::Quote(_)) => Op::Push(self.constant(Value::Call(*sym))),
+                // Only a level deep, so recursive functions are inlined once
+                Some(body) if inlinable(body) => return self.emit(ctx, body, false, ops),
+                _ => Op::Call(*sym),
+            },
+            Expr::Call(sym) => Op::Call(*sym),
+            Expr::Symbol(sym) => Op::Push(self.constant(Value::Symbol(*sym))),
+            #[cfg(feature = "bignum")]
+            Expr::Nat(n) => Op::Push(self.constant(Value::Nat(n.clone()))),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(r) => Op::Push(self.constant(Value::Ratio(r.clone()))),
+            Expr::Pair(p) => Op::Push(self.constant(pair_value(p))),
+            Expr::Map(m) => Op::Push(self.constant(Value::Map(m.clone()))),
+            Expr::Char(c) => Op::Push(self.constant(Value::Char(*c))),
+            Expr::Str(s) => Op::Push(self.constant(Value::Str(s.clone()))),
+            Expr::Quote(e) => Op::Push(self.constant(Value::Quote(e.clone()))),
+        };
+        ops.push(op);
+        Ok(())
+    }
+
+    /// The index of `v` in the constant pool, adding it if it isn't
+    fn constant(&mut self, v: Value) -> usize {
+        let constants = &mut self.constants;
+        *self.constant_indices.entry(v).or_insert_with_key(|v| {
+            constants.push(v.clone());
+            constants.len() - 1
+        })
+    }
+
+    /// Start evaluating `e`, compiling it if it hasn't been.
+    pub fn start(&mut self, ctx: &Context, e: &Expr) -> Result<Thread, VmError> {
+        if !ctx.rules.is_empty() {
+            return Err(VmError::Rules);
+        }
+        Ok(Thread {
+            pc: self.compile(ctx, e)?,
+            frames: vec![],
+        })
+    }
+
+    /// Run the op `thread` is at, returning whether there are more to run.
+    pub fn step(
+        &mut self,
+        ctx: &mut Context,
+        vs: &mut ValueStack,
+        thread: &mut Thread,
+    ) -> Result<bool, VmError> {
+        let op = self.code[thread.pc].clone();
+        thread.pc += 1;
+        let entry = match op {
+            Op::Return => match thread.frames.pop() {
+                Some(ret) => {
+                    thread.pc = ret;
+                    return Ok(true);
+                }
+                None => return Ok(false),
+            },
+            Op::Intrinsic(intr) => {
+                let mut e = Expr::Intrinsic(intr);
+                ctx.step(vs, &mut e)?;
+                // `apply` and `k` leave the quotation to run
+                if e == Expr::default() {
+                    return Ok(true);
+                }
+                self.compile(ctx, &e)?
+            }
+            Op::Push(i) => {
+                vs.0.push(self.constants[i].clone());
+                return Ok(true);
+            }
+            Op::Check(expected) => {
+                if vs.0.len() < expected {
+                    return Err(EvalError::TooFewValues {
+                        available: vs.0.len(),
+                        expected,
+                    }
+                    .into());
+                }
+                return Ok(true);
+            }
+            Op::Call(sym) => match ctx.lookup_fn(&sym) {
+                Some(Expr::Quote(_)) => {
+                    vs.0.push(Value::Call(sym));
+                    return Ok(true);
+                }
+                Some(body) => self.compile_fn(ctx, sym, body)?,
+                None => match ctx.host_intrinsic(&sym) {
+                    Some(intr) => return Err(VmError::Unsupported(intr)),
+                    None => return Err(EvalError::UndefinedFn(sym).into()),
+                },
+            },
+        };
+        // Calls in tail position return straight to their caller's caller
+        if self.code[thread.pc] != Op::Return {
+            thread.frames.push(thread.pc);
+        }
+        thread.pc = entry;
+        Ok(true)
+    }
+
+    /// Evaluate `e` on `vs`, taking a unit of `fuel` for each op but
+    /// `Return`.
+    pub fn run(
+        &mut self,
+        ctx: &mut Context,
+        vs: &mut ValueStack,
+        e: &Expr,
+        fuel: &mut usize,
+    ) -> Result<(), VmError> {
+        let mut thread = self.start(ctx, e)?;
+        loop {
+            if self.code[thread.pc] != Op::Return {
+                if *fuel == 0 {
+                    return Err(VmError::OutOfFuel);
+                }
+                *fuel -= 1;
+            }
+            if !self.step(ctx, vs, &mut thread)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// The op at `pc`, as source text, e.g. `push [a]` or `call f`
+    pub fn op_text(&self, pc: usize, interner: &Interner) -> String {
+        match &self.code[pc] {
+            Op::Intrinsic(intr) => Expr::Intrinsic(*intr).resolve(interner).to_string(),
+            Op::Push(i) => format!("push {}", self.constants[*i].resolve(interner)),
+            Op::Call(sym) => format!("call {}", sym.resolve(interner)),
+            Op::Check(n) => format!("check {}", n),
+            Op::Return => "return".to_owned(),
+        }
+    }
+
+    /// The ops from `start` to `end`, a line each, after its address
+    pub fn listing(&self, start: usize, end: usize, interner: &Interner) -> String {
+        (start..end)
+            .map(|pc| format!("{:>4}  {}\n", pc, self.op_text(pc, interner)))
+            .collect()
+    }
+
+    pub fn code_len(&self) -> usize {
+        self.code.len()
+    }
+}
+
+/// Where a machine is in evaluating an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thread {
+    /// The address of the next op to run
+    pub pc: usize,
+    /// The return addresses of the calls in progress
+    frames: Vec<usize>,
+}
+
+/// How an evaluation ended, and the stack and fuel it left
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub result: Result<(), VmError>,
+    pub vs: ValueStack,
+    pub fuel: usize,
+}
+
+impl Context {
+    /// Evaluate `e` on `vs` by small steps, taking at most `fuel`.
+    pub fn run_interpreted(&mut self, vs: &ValueStack, e: &Expr, mut fuel: usize) -> Run {
+        let mut vs = vs.clone();
+        let mut e = e.clone();
+        let mut result = Ok(());
+        while e != Expr::default() {
+            if fuel == 0 {
+                result = Err(VmError::OutOfFuel);
+                break;
+            }
+            fuel -= 1;
+            if let Err(err) = self.small_step(&mut vs, &mut e) {
+                result = Err(VmError::Eval(err));
+                break;
+            }
+        }
+        Run { result, vs, fuel }
+    }
+
+    /// Evaluate `e` on `vs` with a new `Vm`, taking at most `fuel`.
+    pub fn run_compiled(&mut self, vs: &ValueStack, e: &Expr, mut fuel: usize) -> Run {
+        let mut vs = vs.clone();
+        let result = Vm::default().run(self, &mut vs, e, &mut fuel);
+        Run { result, vs, fuel }
+    }
+
+    /// Evaluate `e` on `vs` with a new optimizing `Vm`, taking at most
+    /// `fuel`.
+    pub fn run_optimized(&mut self, vs: &ValueStack, e: &Expr, mut fuel: usize) -> Run {
+        let mut vs = vs.clone();
+        let result = Vm::optimized().run(self, &mut vs, e, &mut fuel);
+        Run { result, vs, fuel }
+    }
+
+    /// Evaluate `e` on `vs` both by small steps and with a `Vm`, returning
+    /// both runs if they differ. Expressions the machine can't run are
+    /// taken to agree.
+    pub fn differential(&mut self, vs: &ValueStack, e: &Expr, fuel: usize) -> Option<(Run, Run)> {
+        let compiled = self.run_compiled(vs, e, fuel);
+        if let Err(VmError::Unsupported(_)) | Err(VmError::Rules) = compiled.result {
+            return None;
+        }
+        let interpreted = self.run_interpreted(vs, e, fuel);
+        if interpreted == compiled {
+            None
+        } else {
+            Some((interpreted, compiled))
+        }
+    }
+
+    /// Evaluate `e` on `vs` both by small steps and with an optimizing
+    /// `Vm`, returning both runs if they differ: if the optimized run ends
+    /// differently, leaves another stack, or takes more fuel. Optimized
+    /// code takes less fuel, so if the small steps run out of it, the
+    /// optimized run only has to take no more.
+    pub fn differential_optimized(
+        &mut self,
+        vs: &ValueStack,
+        e: &Expr,
+        fuel: usize,
+    ) -> Option<(Run, Run)> {
+        let optimized = self.run_optimized(vs, e, fuel);
+        if let Err(VmError::Unsupported(_)) | Err(VmError::Rules) = optimized.result {
+            return None;
+        }
+        let interpreted = self.run_interpreted(vs, e, fuel);
+        let agree = match interpreted.result {
+            Err(VmError::OutOfFuel) => optimized.fuel >= interpreted.fuel,
+            _ => {
+                optimized.result == interpreted.result
+                    && optimized.vs == interpreted.vs
+                    && optimized.fuel >= interpreted.fuel
+            }
+        };
+        if agree {
+            None
+        } else {
+            Some((interpreted, optimized))
+        }
+    }
+}
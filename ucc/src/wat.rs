@@ -0,0 +1,487 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Compilation of definitions to a WebAssembly text module, for running ucc
+//! programs standalone in wasm runtimes. Each definition becomes a function
+//! on a shadow stack of values in linear memory, exported by its name, and
+//! the prelude's also unqualified, as they are called in the interpreter.
+//!
+//! A value is a pointer to an object of 32-bit words: a tag (0 for a
+//! quotation, 1 for the body of a function, shown as its name, and 2 for a
+//! symbol), an index into the names, the number of ops, then the ops. An op
+//! is an intrinsic's number shifted left by two, a call of a name's index
+//! shifted left by two and or'd with 1, or a pointer to a value to push
+//! or'd with 2. Quotations in the source are placed in the data segment,
+//! and new ones are allocated after the shadow stack and never freed.
+//!
+//! The module exports, besides the functions:
+//! - `ucc:memory`
+//! - `ucc:stack` and `ucc:sp`, the bottom and top of the shadow stack
+//! - `ucc:names`, a table of the offset and length of each name
+//! - `ucc:error`, set before a trap: 1 for too few values, 2 for a call of
+//!   an undefined function (whose index is in `ucc:callee`), 3 for a full
+//!   shadow stack, and 4 for out of memory
+//! - the intrinsics, as `ucc:swap` and so on
+//!
+//! The reflection intrinsics and host intrinsics can't be compiled.
+
+use crate::codegen::{load, runtime_name, Codegen, CodegenError};
+use crate::core::{Context, Expr, Map, Symbol, CORE};
+use std::fmt::Write;
+
+/// The `Op`s of `codegen::runtime_name`, by number
+const INTRINSICS: [&str; 11] = [
+    "Swap", "Clone", "Drop", "Quote", "Compose", "Apply", "Dup", "Over", "Rot", "Cake", "K",
+];
+
+const TAG_QUOTE: u32 = 0;
+const TAG_CALL: u32 = 1;
+const TAG_SYMBOL: u32 = 2;
+
+/// Where the data segment starts, leaving address 0 unused
+const DATA_START: u32 = 16;
+
+/// The size of the shadow stack, in bytes
+const STACK_SIZE: u32 = 65536;
+
+const PAGE_SIZE: u32 = 65536;
+
+/// The functions every generated module has, after its globals and before
+/// its definitions
+const RUNTIME: &str = r#"
+  (func $fail (param $code i32)
+    (global.set $error (local.get $code))
+    unreachable)
+
+  (func $need (param $n i32)
+    (if (i32.lt_u (i32.sub (global.get $sp) (global.get $stack))
+                  (i32.shl (local.get $n) (i32.const 2)))
+      (then (call $fail (i32.const 1)))))
+
+  (func $push (param $v i32)
+    (if (i32.ge_u (global.get $sp) (global.get $stack_end))
+      (then (call $fail (i32.const 3))))
+    (i32.store (global.get $sp) (local.get $v))
+    (global.set $sp (i32.add (global.get $sp) (i32.const 4))))
+
+  (func $pop (result i32)
+    (global.set $sp (i32.sub (global.get $sp) (i32.const 4)))
+    (i32.load (global.get $sp)))
+
+  ;; The value `$i` below the top of the stack
+  (func $peek (param $i i32) (result i32)
+    (i32.load (i32.sub (global.get $sp)
+                       (i32.shl (i32.add (local.get $i) (i32.const 1)) (i32.const 2)))))
+
+  (func $poke (param $i i32) (param $v i32)
+    (i32.store (i32.sub (global.get $sp)
+                        (i32.shl (i32.add (local.get $i) (i32.const 1)) (i32.const 2)))
+               (local.get $v)))
+
+  (func $alloc (param $words i32) (result i32)
+    (local $p i32)
+    (local $size i32)
+    (local.set $p (global.get $hp))
+    (global.set $hp (i32.add (local.get $p) (i32.shl (local.get $words) (i32.const 2))))
+    (local.set $size (i32.shl (memory.size) (i32.const 16)))
+    (if (i32.gt_u (global.get $hp) (local.get $size))
+      (then
+        (if (i32.eq (memory.grow (i32.add (i32.shr_u (i32.sub (global.get $hp) (local.get $size))
+                                                     (i32.const 16))
+                                          (i32.const 1)))
+                    (i32.const -1))
+          (then (call $fail (i32.const 4))))))
+    (local.get $p))
+
+  (func $copy (param $dst i32) (param $src i32) (param $n i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $n)))
+        (i32.store (local.get $dst) (i32.load (local.get $src)))
+        (local.set $dst (i32.add (local.get $dst) (i32.const 4)))
+        (local.set $src (i32.add (local.get $src) (i32.const 4)))
+        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+        (br $next))))
+
+  ;; A quotation of the single op `$op`
+  (func $single (param $op i32) (result i32)
+    (local $q i32)
+    (local.set $q (call $alloc (i32.const 4)))
+    (i32.store (local.get $q) (i32.const 0))
+    (i32.store offset=4 (local.get $q) (i32.const 0))
+    (i32.store offset=8 (local.get $q) (i32.const 1))
+    (i32.store offset=12 (local.get $q) (local.get $op))
+    (local.get $q))
+
+  ;; Symbols unquote to a call of the function they name
+  (func $unquote (param $v i32) (result i32)
+    (if (result i32) (i32.eq (i32.load (local.get $v)) (i32.const 2))
+      (then (call $single (i32.or (i32.shl (i32.load offset=4 (local.get $v)) (i32.const 2))
+                                  (i32.const 1))))
+      (else (local.get $v))))
+
+  (func $concat (param $a i32) (param $b i32) (result i32)
+    (local $la i32)
+    (local $lb i32)
+    (local $q i32)
+    (local.set $la (i32.load offset=8 (local.get $a)))
+    (local.set $lb (i32.load offset=8 (local.get $b)))
+    (local.set $q (call $alloc (i32.add (i32.const 3) (i32.add (local.get $la) (local.get $lb)))))
+    (i32.store (local.get $q) (i32.const 0))
+    (i32.store offset=4 (local.get $q) (i32.const 0))
+    (i32.store offset=8 (local.get $q) (i32.add (local.get $la) (local.get $lb)))
+    (call $copy (i32.add (local.get $q) (i32.const 12))
+                (i32.add (local.get $a) (i32.const 12))
+                (local.get $la))
+    (call $copy (i32.add (local.get $q) (i32.add (i32.const 12) (i32.shl (local.get $la) (i32.const 2))))
+                (i32.add (local.get $b) (i32.const 12))
+                (local.get $lb))
+    (local.get $q))
+
+  ;; Run the ops of a quotation
+  (func $run (param $q i32)
+    (local $i i32)
+    (local $end i32)
+    (local $op i32)
+    (local.set $i (i32.add (local.get $q) (i32.const 12)))
+    (local.set $end (i32.add (local.get $i) (i32.shl (i32.load offset=8 (local.get $q)) (i32.const 2))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $end)))
+        (local.set $op (i32.load (local.get $i)))
+        (if (i32.eqz (i32.and (local.get $op) (i32.const 3)))
+          (then (call_indirect (type $fn) (i32.shr_u (local.get $op) (i32.const 2))))
+          (else
+            (if (i32.eq (i32.and (local.get $op) (i32.const 3)) (i32.const 1))
+              (then (call $call_index (i32.shr_u (local.get $op) (i32.const 2))))
+              (else (call $push (i32.and (local.get $op) (i32.const -4)))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 4)))
+        (br $next))))
+
+  (func $call_index (param $i i32)
+    (global.set $callee (local.get $i))
+    (call_indirect (type $fn) (i32.add (local.get $i) (i32.const 11))))
+
+  (func $undefined
+    (call $fail (i32.const 2)))
+
+  (func $swap (export "ucc:swap")
+    (local $v i32)
+    (call $need (i32.const 2))
+    (local.set $v (call $peek (i32.const 0)))
+    (call $poke (i32.const 0) (call $peek (i32.const 1)))
+    (call $poke (i32.const 1) (local.get $v)))
+
+  (func $clone (export "ucc:clone")
+    (call $need (i32.const 1))
+    (call $push (call $peek (i32.const 0))))
+
+  (func $drop (export "ucc:drop")
+    (call $need (i32.const 1))
+    (drop (call $pop)))
+
+  (func $quote (export "ucc:quote")
+    (call $need (i32.const 1))
+    (call $push (call $single (i32.or (call $pop) (i32.const 2)))))
+
+  (func $compose (export "ucc:compose")
+    (local $b i32)
+    (call $need (i32.const 2))
+    (local.set $b (call $unquote (call $pop)))
+    (call $push (call $concat (call $unquote (call $pop)) (local.get $b))))
+
+  (func $apply (export "ucc:apply")
+    (call $need (i32.const 1))
+    (call $run (call $unquote (call $pop))))
+
+  (func $over (export "ucc:over")
+    (call $need (i32.const 2))
+    (call $push (call $peek (i32.const 1))))
+
+  (func $rot (export "ucc:rot")
+    (local $v i32)
+    (call $need (i32.const 3))
+    (local.set $v (call $peek (i32.const 2)))
+    (call $poke (i32.const 2) (call $peek (i32.const 1)))
+    (call $poke (i32.const 1) (call $peek (i32.const 0)))
+    (call $poke (i32.const 0) (local.get $v)))
+
+  (func $cake (export "ucc:cake")
+    (local $b i32)
+    (local $p i32)
+    (call $need (i32.const 2))
+    (local.set $b (call $unquote (call $pop)))
+    (local.set $p (call $single (i32.or (call $pop) (i32.const 2))))
+    (call $push (call $concat (local.get $p) (local.get $b)))
+    (call $push (call $concat (local.get $b) (local.get $p))))
+
+  (func $k (export "ucc:k")
+    (local $b i32)
+    (call $need (i32.const 2))
+    (local.set $b (call $unquote (call $pop)))
+    (drop (call $pop))
+    (call $run (local.get $b)))
+"#;
+
+/// The data segment: the values in the source, then the names
+struct Data {
+    words: Vec<u32>,
+    /// The address of each value already placed, by its words
+    placed: Map<Vec<u32>, u32>,
+}
+
+impl Data {
+    /// Place a value, or find the same one placed already
+    fn place(&mut self, value: Vec<u32>) -> u32 {
+        let words = &mut self.words;
+        *self.placed.entry(value.clone()).or_insert_with(|| {
+            let addr = DATA_START + 4 * words.len() as u32;
+            words.extend(value);
+            addr
+        })
+    }
+
+    fn quote(&mut self, tag: u32, name: u32, ops: Vec<u32>) -> u32 {
+        let mut value = vec![tag, name, ops.len() as u32];
+        value.extend(ops);
+        self.place(value)
+    }
+}
+
+impl Codegen<'_> {
+    /// The instructions that evaluate `e`
+    fn instrs(&mut self, e: &Expr, data: &mut Data, out: &mut String) -> Result<(), CodegenError> {
+        match e {
+            Expr::Intrinsic(intr) => {
+                let n = intrinsic_number(runtime_name(*intr)?);
+                writeln!(out, "    (call ${})", intrinsic_fn(n)).unwrap();
+            }
+            Expr::Call(sym) => {
+                let i = self.index(*sym);
+                if self.ctx.lookup_fn(sym).is_some() {
+                    writeln!(out, "    (call $f{})", i).unwrap();
+                } else {
+                    // As in the interpreter, calling it is an error
+                    writeln!(out, "    (call $call_index (i32.const {}))", i).unwrap();
+                }
+            }
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            Expr::Pair(_) => return Err(CodegenError::Unsupported("pairs".to_owned())),
+            Expr::Map(_) => return Err(CodegenError::Unsupported("maps".to_owned())),
+            Expr::Char(_) => return Err(CodegenError::Unsupported("characters".to_owned())),
+            Expr::Str(_) => return Err(CodegenError::Unsupported("strings".to_owned())),
+            Expr::Symbol(_) | Expr::Quote(_) => {
+                let addr = self.value(e, data)?;
+                writeln!(out, "    (call $push (i32.const {}))", addr).unwrap();
+            }
+            Expr::Compose(es) => {
+                for e in es {
+                    self.instrs(e, data, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Place the value `e` pushes, a symbol or quotation, in the data
+    fn value(&mut self, e: &Expr, data: &mut Data) -> Result<u32, CodegenError> {
+        match e {
+            Expr::Symbol(sym) => Ok(data.place(vec![TAG_SYMBOL, self.index(*sym) as u32, 0])),
+            Expr::Quote(e) => {
+                let mut ops = vec![];
+                self.wasm_ops(e, data, &mut ops)?;
+                Ok(data.quote(TAG_QUOTE, 0, ops))
+            }
+            _ => unreachable!("only symbols and quotations are values"),
+        }
+    }
+
+    fn wasm_ops(
+        &mut self,
+        e: &Expr,
+        data: &mut Data,
+        ops: &mut Vec<u32>,
+    ) -> Result<(), CodegenError> {
+        match e {
+            Expr::Intrinsic(intr) => ops.push((intrinsic_number(runtime_name(*intr)?) as u32) << 2),
+            Expr::Call(sym) => ops.push((self.index(*sym) as u32) << 2 | 1),
+            Expr::Symbol(_) | Expr::Quote(_) => ops.push(self.value(e, data)? | 2),
+            #[cfg(feature = "bignum")]
+            Expr::Nat(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            #[cfg(feature = "rational")]
+            Expr::Ratio(_) => return Err(CodegenError::Unsupported("numbers".to_owned())),
+            Expr::Pair(_) => return Err(CodegenError::Unsupported("pairs".to_owned())),
+            Expr::Map(_) => return Err(CodegenError::Unsupported("maps".to_owned())),
+            Expr::Char(_) => return Err(CodegenError::Unsupported("characters".to_owned())),
+            Expr::Str(_) => return Err(CodegenError::Unsupported("strings".to_owned())),
+            Expr::Compose(es) => {
+                for e in es {
+                    self.wasm_ops(e, data, ops)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn intrinsic_number(runtime_name: &str) -> usize {
+    INTRINSICS.iter().position(|n| *n == runtime_name).unwrap()
+}
+
+/// The runtime function for an intrinsic's number
+fn intrinsic_fn(n: usize) -> String {
+    match INTRINSICS[n] {
+        "Dup" => "clone".to_owned(),
+        name => name.to_lowercase(),
+    }
+}
+
+/// `bytes` as a WAT string
+fn wat_string(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for b in bytes {
+        write!(s, "\\{:02x}", b).unwrap();
+    }
+    s
+}
+
+impl Context {
+    /// Compile the definitions of `syms`, and of the functions they use, to
+    /// a WebAssembly text module. Calls of undefined functions are compiled
+    /// to traps, as they are errors in the interpreter.
+    pub fn codegen_wat(&self, syms: &[Symbol]) -> Result<String, CodegenError> {
+        let mut codegen = Codegen::new(self, syms);
+        let mut data = Data {
+            words: vec![],
+            placed: Map::default(),
+        };
+        let mut fns = String::new();
+        // Names are added as the functions using them are compiled
+        let mut i = 0;
+        while i < codegen.names.len() {
+            let sym = codegen.names[i];
+            if let Some(body) = self.lookup_fn(&sym) {
+                let name = self.interner.resolve(&sym.0);
+                writeln!(fns, "\n  ;; {}", name).unwrap();
+                writeln!(fns, "  (func $f{} (export {:?})", i, name).unwrap();
+                match body {
+                    // As in the interpreter, these push their name
+                    Expr::Quote(e) => {
+                        let mut ops = vec![];
+                        codegen.wasm_ops(e, &mut data, &mut ops)?;
+                        let addr = data.quote(TAG_CALL, i as u32, ops);
+                        writeln!(fns, "    (call $push (i32.const {}))", addr).unwrap();
+                    }
+                    body => codegen.instrs(body, &mut data, &mut fns)?,
+                }
+                fns.truncate(fns.trim_end().len());
+                fns.push_str(")\n");
+            }
+            i += 1;
+        }
+
+        // The names table, then the names
+        let names_addr = DATA_START + 4 * data.words.len() as u32;
+        let mut offset = names_addr + 8 * codegen.names.len() as u32;
+        let mut bytes: Vec<u8> = data.words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        for sym in codegen.names.iter() {
+            let len = self.interner.resolve(&sym.0).len() as u32;
+            bytes.extend(offset.to_le_bytes());
+            bytes.extend(len.to_le_bytes());
+            offset += len;
+        }
+        for sym in codegen.names.iter() {
+            bytes.extend(self.interner.resolve(&sym.0).as_bytes());
+        }
+        let stack = (DATA_START + bytes.len() as u32).div_ceil(PAGE_SIZE) * PAGE_SIZE;
+        let heap = stack + STACK_SIZE;
+
+        let mut out = String::from(";; Generated by `ucci codegen`\n\n(module\n");
+        out.push_str("  (type $fn (func))\n");
+        writeln!(
+            out,
+            "  (memory (export \"ucc:memory\") {})",
+            heap / PAGE_SIZE + 1
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  (global $stack (export \"ucc:stack\") i32 (i32.const {}))",
+            stack
+        )
+        .unwrap();
+        writeln!(out, "  (global $stack_end i32 (i32.const {}))", heap).unwrap();
+        writeln!(
+            out,
+            "  (global $sp (export \"ucc:sp\") (mut i32) (i32.const {}))",
+            stack
+        )
+        .unwrap();
+        writeln!(out, "  (global $hp (mut i32) (i32.const {}))", heap).unwrap();
+        out.push_str("  (global $error (export \"ucc:error\") (mut i32) (i32.const 0))\n");
+        out.push_str("  (global $callee (export \"ucc:callee\") (mut i32) (i32.const 0))\n");
+        writeln!(
+            out,
+            "  (global $names (export \"ucc:names\") i32 (i32.const {}))",
+            names_addr
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  (data (i32.const {}) \"{}\")",
+            DATA_START,
+            wat_string(&bytes)
+        )
+        .unwrap();
+
+        // The intrinsics by number, then the functions by index
+        let mut elems: Vec<String> = (0..INTRINSICS.len()).map(intrinsic_fn).collect();
+        for (i, sym) in codegen.names.iter().enumerate() {
+            if self.lookup_fn(sym).is_some() {
+                elems.push(format!("f{}", i));
+            } else {
+                elems.push("undefined".to_owned());
+            }
+        }
+        writeln!(out, "  (table {} funcref)", elems.len()).unwrap();
+        out.push_str("  (elem (i32.const 0)");
+        for elem in elems {
+            write!(out, " ${}", elem).unwrap();
+        }
+        out.push_str(")\n");
+        out.push_str(RUNTIME);
+        out.push_str(&fns);
+        // As in the interpreter, the prelude's functions may be called
+        // unqualified when nothing else has the name
+        for (i, sym) in codegen.names.iter().enumerate() {
+            let name = self.interner.resolve(&sym.0);
+            let unqualified = match name.strip_prefix(CORE).and_then(|n| n.strip_prefix('.')) {
+                Some(unqualified) => unqualified,
+                None => continue,
+            };
+            let is_taken = codegen
+                .names
+                .iter()
+                .any(|sym| self.interner.resolve(&sym.0) == unqualified);
+            if !is_taken && self.lookup_fn(sym).is_some() {
+                writeln!(out, "  (export {:?} (func $f{}))", unqualified, i).unwrap();
+            }
+        }
+        out.push_str(")\n");
+        Ok(out)
+    }
+}
+
+/// Compile the definitions in the source `lib`, and the prelude functions
+/// they use, to a WebAssembly text module. Expressions and rules in `lib`
+/// are left out.
+pub fn wat(lib: &str) -> Result<String, CodegenError> {
+    let (ctx, syms) = load(lib)?;
+    ctx.codegen_wat(&syms)
+}
@@ -0,0 +1,180 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Just enough of WebSocket (RFC 6455) for the server's playground: the
+//! opening handshake's accept key, and reading the frames a client sends
+//! and writing those a server does. Fragmented messages and extensions
+//! aren't supported.
+
+/// Appended to the client's key to make the accept key
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The longest payload read
+pub const MAX_PAYLOAD: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// Clients must mask every frame
+    Unmasked,
+    Fragmented,
+    BadOpcode(u8),
+    TooLong(u64),
+    BadUtf8,
+}
+
+/// The `Sec-WebSocket-Accept` value answering the `Sec-WebSocket-Key`
+/// `key`
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+/// The first frame in `buf`, and its length, or `None` if `buf` doesn't
+/// hold all of it yet
+pub fn parse_frame(buf: &[u8]) -> Result<Option<(Frame, usize)>, FrameError> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let (fin, opcode) = (buf[0] & 0x80 != 0, buf[0] & 0x0f);
+    if buf[1] & 0x80 == 0 {
+        return Err(FrameError::Unmasked);
+    }
+    let (len, mut at) = match buf[1] & 0x7f {
+        126 if buf.len() >= 4 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+        127 if buf.len() >= 10 => {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&buf[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        126 | 127 => return Ok(None),
+        len => (len as u64, 2),
+    };
+    if len > MAX_PAYLOAD as u64 {
+        return Err(FrameError::TooLong(len));
+    }
+    let len = len as usize;
+    if buf.len() < at + 4 + len {
+        return Ok(None);
+    }
+    let mask = &buf[at..at + 4];
+    at += 4;
+    let payload: Vec<u8> = buf[at..at + len]
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b ^ mask[i % 4])
+        .collect();
+    if !fin || opcode == 0 {
+        return Err(FrameError::Fragmented);
+    }
+    let frame = match opcode {
+        1 => Frame::Text(String::from_utf8(payload).map_err(|_| FrameError::BadUtf8)?),
+        2 => Frame::Binary(payload),
+        8 => Frame::Close,
+        9 => Frame::Ping(payload),
+        10 => Frame::Pong(payload),
+        opcode => return Err(FrameError::BadOpcode(opcode)),
+    };
+    Ok(Some((frame, at + len)))
+}
+
+/// `frame` as a server sends it, unmasked
+pub fn encode_frame(frame: &Frame) -> Vec<u8> {
+    let (opcode, payload) = match frame {
+        Frame::Text(text) => (1, text.as_bytes()),
+        Frame::Binary(bytes) => (2, &bytes[..]),
+        Frame::Close => (8, &[][..]),
+        Frame::Ping(bytes) => (9, &bytes[..]),
+        Frame::Pong(bytes) => (10, &bytes[..]),
+    };
+    let mut bytes = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => bytes.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            bytes.push(126);
+            bytes.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            bytes.push(127);
+            bytes.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 20];
+    for (i, h) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
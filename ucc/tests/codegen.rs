@@ -0,0 +1,190 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Checks that `tests/codegen/numerals.rs` is what `ucc::codegen::rust`
+//! generates from `numerals.ucc`, and that it, and the WebAssembly
+//! `ucc::wat` generates, agree with the interpreter. Run with
+//! `UPDATE_GOLDEN=1` to regenerate `numerals.rs` instead.
+
+// Kept as `ucci codegen` writes it
+#[rustfmt::skip]
+#[path = "codegen/numerals.rs"]
+mod numerals;
+
+use std::convert::TryInto;
+use std::env;
+use std::fs;
+use std::path::Path;
+use ucc::core::{Context, Expr};
+use ucc::shared::SharedPrelude;
+
+#[test]
+fn generated() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let lib = fs::read_to_string(dir.join("numerals.ucc")).unwrap();
+    let src = ucc::codegen::rust(&lib).unwrap();
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(dir.join("numerals.rs"), src).unwrap();
+    } else {
+        let expected = fs::read_to_string(dir.join("numerals.rs")).unwrap();
+        assert!(src == expected, "numerals.rs is out of date");
+    }
+}
+
+/// Expressions of calls and intrinsics, run both interpreted and compiled
+const CASES: [&str; 10] = [
+    "n2 n2 mul",
+    "n6 flip",
+    "n6 n1 add flip",
+    "pairs",
+    "pairs swap compose omega",
+    "n2 quote n3 compose",
+    "indirect",
+    "v1 v2 swap",
+    "pairs undefined",
+    "omega swap",
+];
+
+const INTRINSICS: [&str; 11] = [
+    "swap", "clone", "drop", "quote", "compose", "apply", "dup", "over", "rot", "cake", "k",
+];
+
+/// A context with the definitions in `numerals.ucc`
+fn context(prelude: &SharedPrelude) -> Context {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let lib = fs::read_to_string(dir.join("numerals.ucc")).unwrap();
+    let mut ctx = Context::with_prelude(prelude);
+    ctx.define_many(lib.lines()).unwrap();
+    ctx
+}
+
+/// The value stack `case` leaves, or an error
+fn interpret(ctx: &mut Context, case: &str) -> Result<String, String> {
+    let mut vs = Default::default();
+    let mut e = ctx.parse_expr(case).unwrap();
+    loop {
+        if e == Expr::default() {
+            return Ok(ctx.display(&vs).to_string());
+        }
+        if let Err(err) = ctx.small_step(&mut vs, &mut e) {
+            return Err(format!("{:?}", err));
+        }
+    }
+}
+
+fn check_agrees(case: &str, interpreted: Result<String, String>, compiled: Result<String, String>) {
+    match (interpreted, compiled) {
+        (Ok(interpreted), Ok(compiled)) => assert_eq!(interpreted, compiled, "{}", case),
+        (Err(_), Err(_)) => {}
+        (interpreted, compiled) => panic!("{}: {:?} {:?}", case, interpreted, compiled),
+    }
+}
+
+#[test]
+fn agrees_with_interpreter() {
+    let prelude = SharedPrelude::default();
+    let mut ctx = context(&prelude);
+    for case in CASES {
+        let mut s = vec![];
+        let compiled = case
+            .split_whitespace()
+            .try_for_each(|name| match name {
+                "quote" => numerals::quote(&mut s),
+                "compose" => numerals::compose(&mut s),
+                "apply" => numerals::apply(&mut s),
+                "swap" => numerals::swap(&mut s),
+                name => numerals::call(name, &mut s),
+            })
+            .map(|()| {
+                let vs: Vec<_> = s.iter().map(|v| v.to_string()).collect();
+                format!("⟨{}⟩", vs.join(" "))
+            })
+            .map_err(|err| format!("{:?}", err));
+        check_agrees(case, interpret(&mut ctx, case), compiled);
+    }
+}
+
+fn word(memory: &[u8], addr: u32) -> u32 {
+    let addr = addr as usize;
+    u32::from_le_bytes(memory[addr..addr + 4].try_into().unwrap())
+}
+
+/// A value in the memory of a module from `ucc::wat`, as source
+fn wasm_value(memory: &[u8], names: u32, v: u32) -> String {
+    let word = |addr| word(memory, addr);
+    let name = |i: u32| {
+        let (offset, len) = (
+            word(names + 8 * i) as usize,
+            word(names + 8 * i + 4) as usize,
+        );
+        String::from_utf8(memory[offset..offset + len].to_vec()).unwrap()
+    };
+    match word(v) {
+        0 => {
+            let ops: Vec<_> = (0..word(v + 8))
+                .map(|i| {
+                    let op = word(v + 12 + 4 * i);
+                    match op & 3 {
+                        0 => INTRINSICS[(op >> 2) as usize].to_owned(),
+                        1 => name(op >> 2),
+                        _ => wasm_value(memory, names, op & !3),
+                    }
+                })
+                .collect();
+            format!("[{}]", ops.join(" "))
+        }
+        1 => name(word(v + 4)),
+        _ => format!("'{}", name(word(v + 4))),
+    }
+}
+
+#[test]
+fn wasm_agrees_with_interpreter() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/codegen");
+    let lib = fs::read_to_string(dir.join("numerals.ucc")).unwrap();
+    let wasm = wat::parse_str(ucc::wat::wat(&lib).unwrap()).unwrap();
+    let engine = wasmi::Engine::default();
+    let module = wasmi::Module::new(&engine, &wasm[..]).unwrap();
+    let prelude = SharedPrelude::default();
+    let mut ctx = context(&prelude);
+    for case in CASES {
+        // A fresh instance, since compiled code never frees memory
+        let mut store = wasmi::Store::new(&engine, ());
+        let instance = wasmi::Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)
+            .unwrap()
+            .start(&mut store)
+            .unwrap();
+        let compiled = case
+            .split_whitespace()
+            .try_for_each(|name| {
+                let export = match INTRINSICS.contains(&name) {
+                    true => format!("ucc:{}", name),
+                    false => name.to_owned(),
+                };
+                let f = instance
+                    .get_typed_func::<(), ()>(&store, &export)
+                    .map_err(|err| err.to_string())?;
+                f.call(&mut store, ()).map_err(|err| err.to_string())
+            })
+            .map(|()| {
+                let global = |name| {
+                    let value = instance.get_global(&store, name).unwrap().get(&store);
+                    value.i32().unwrap() as u32
+                };
+                let (stack, sp, names) =
+                    (global("ucc:stack"), global("ucc:sp"), global("ucc:names"));
+                let memory = instance.get_memory(&store, "ucc:memory").unwrap();
+                let memory = memory.data(&store);
+                let vs: Vec<_> = (stack..sp)
+                    .step_by(4)
+                    .map(|addr| wasm_value(memory, names, word(memory, addr)))
+                    .collect();
+                format!("⟨{}⟩", vs.join(" "))
+            });
+        check_agrees(case, interpret(&mut ctx, case), compiled);
+    }
+}
@@ -0,0 +1,443 @@
+// Generated by `ucci codegen`
+
+#![allow(dead_code)]
+
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Quote(Rc<Vec<Op>>),
+    /// A quotation that is the body of the function at an index into
+    /// `NAMES`, shown as its name
+    Call(usize, Rc<Vec<Op>>),
+    /// An index into `NAMES`
+    Symbol(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Swap,
+    Clone,
+    Drop,
+    Quote,
+    Compose,
+    Apply,
+    Dup,
+    Over,
+    Rot,
+    Cake,
+    K,
+    /// A call of the function at an index into `NAMES`
+    Call(usize),
+    Push(Value),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    TooFewValues { available: usize, expected: usize },
+    UndefinedFn(String),
+}
+
+type Func = fn(&mut Vec<Value>) -> Result<(), Error>;
+
+fn need(s: &[Value], expected: usize) -> Result<(), Error> {
+    if s.len() < expected {
+        Err(Error::TooFewValues {
+            available: s.len(),
+            expected,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn unquote(v: Value) -> Rc<Vec<Op>> {
+    match v {
+        Value::Quote(ops) | Value::Call(_, ops) => ops,
+        // Symbols unquote to a call of the function they name
+        Value::Symbol(i) => Rc::new(vec![Op::Call(i)]),
+    }
+}
+
+fn concat(ops1: &[Op], ops2: &[Op]) -> Rc<Vec<Op>> {
+    Rc::new(ops1.iter().chain(ops2).cloned().collect())
+}
+
+pub fn swap(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let v = s.remove(s.len() - 2);
+    s.push(v);
+    Ok(())
+}
+
+pub fn clone(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    s.push(s[s.len() - 1].clone());
+    Ok(())
+}
+
+pub fn drop(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    s.pop();
+    Ok(())
+}
+
+pub fn quote(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    let v = s.pop().unwrap();
+    s.push(Value::Quote(Rc::new(vec![Op::Push(v)])));
+    Ok(())
+}
+
+pub fn compose(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let ops2 = unquote(s.pop().unwrap());
+    let ops1 = unquote(s.pop().unwrap());
+    s.push(Value::Quote(concat(&ops1, &ops2)));
+    Ok(())
+}
+
+pub fn apply(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 1)?;
+    let ops = unquote(s.pop().unwrap());
+    run(s, &ops)
+}
+
+pub fn over(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    s.push(s[s.len() - 2].clone());
+    Ok(())
+}
+
+pub fn rot(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 3)?;
+    let v = s.remove(s.len() - 3);
+    s.push(v);
+    Ok(())
+}
+
+pub fn cake(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let ops2 = unquote(s.pop().unwrap());
+    let push1 = [Op::Push(s.pop().unwrap())];
+    s.push(Value::Quote(concat(&push1, &ops2)));
+    s.push(Value::Quote(concat(&ops2, &push1)));
+    Ok(())
+}
+
+pub fn k(s: &mut Vec<Value>) -> Result<(), Error> {
+    need(s, 2)?;
+    let ops = unquote(s.pop().unwrap());
+    s.pop();
+    run(s, &ops)
+}
+
+/// Run the ops of a quotation
+pub fn run(s: &mut Vec<Value>, ops: &[Op]) -> Result<(), Error> {
+    for op in ops {
+        match op {
+            Op::Swap => swap(s)?,
+            Op::Clone | Op::Dup => clone(s)?,
+            Op::Drop => drop(s)?,
+            Op::Quote => quote(s)?,
+            Op::Compose => compose(s)?,
+            Op::Apply => apply(s)?,
+            Op::Over => over(s)?,
+            Op::Rot => rot(s)?,
+            Op::Cake => cake(s)?,
+            Op::K => k(s)?,
+            Op::Call(i) => call_index(*i, s)?,
+            Op::Push(v) => s.push(v.clone()),
+        }
+    }
+    Ok(())
+}
+
+fn call_index(i: usize, s: &mut Vec<Value>) -> Result<(), Error> {
+    match NAMES[i] {
+        (_, Some(f)) => f(s),
+        (name, None) => Err(Error::UndefinedFn(name.to_owned())),
+    }
+}
+
+/// Run the function named `name`, or else, as in the interpreter, the
+/// prelude's `core.<name>`
+pub fn call(name: &str, s: &mut Vec<Value>) -> Result<(), Error> {
+    let position = |name: &str| NAMES.iter().position(|(n, _)| *n == name);
+    match position(name).or_else(|| position(&format!("core.{}", name))) {
+        Some(i) => call_index(i, s),
+        None => Err(Error::UndefinedFn(name.to_owned())),
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Quote(ops) => {
+                write!(f, "[")?;
+                for (i, op) in ops.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", op)?;
+                }
+                write!(f, "]")
+            }
+            Value::Call(i, _) => write!(f, "{}", NAMES[*i].0),
+            Value::Symbol(i) => write!(f, "'{}", NAMES[*i].0),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Swap => write!(f, "swap"),
+            Op::Clone => write!(f, "clone"),
+            Op::Drop => write!(f, "drop"),
+            Op::Quote => write!(f, "quote"),
+            Op::Compose => write!(f, "compose"),
+            Op::Apply => write!(f, "apply"),
+            Op::Dup => write!(f, "dup"),
+            Op::Over => write!(f, "over"),
+            Op::Rot => write!(f, "rot"),
+            Op::Cake => write!(f, "cake"),
+            Op::K => write!(f, "k"),
+            Op::Call(i) => write!(f, "{}", NAMES[*i].0),
+            Op::Push(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Every name used, with its function if it is defined
+pub static NAMES: [(&str, Option<Func>); 26] = [
+    ("not", Some(f0)),
+    ("n6", Some(f1)),
+    ("flip", Some(f2)),
+    ("pairs", Some(f3)),
+    ("omega", Some(f4)),
+    ("indirect", Some(f5)),
+    ("false", Some(f6)),
+    ("true", Some(f7)),
+    ("rotate3", Some(f8)),
+    ("n2", Some(f9)),
+    ("n3", Some(f10)),
+    ("mul", Some(f11)),
+    ("v1", Some(f12)),
+    ("v2", Some(f13)),
+    ("compose2", Some(f14)),
+    ("core.quote2", Some(f15)),
+    ("core.n1", Some(f16)),
+    ("core.n2", Some(f17)),
+    ("core.n0", Some(f18)),
+    ("core.rotate3", Some(f19)),
+    ("core.add", Some(f20)),
+    ("core.succ", Some(f21)),
+    ("core.compose5", Some(f22)),
+    ("core.compose4", Some(f23)),
+    ("core.compose3", Some(f24)),
+    ("core.compose2", Some(f25)),
+];
+
+/// `not`
+pub fn f0(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Quote(Rc::new(vec![Op::Call(6)])));
+    s.push(Value::Quote(Rc::new(vec![Op::Call(7)])));
+    f8(s)?;
+    apply(s)?;
+    Ok(())
+}
+
+/// `n6`
+pub fn f1(s: &mut Vec<Value>) -> Result<(), Error> {
+    f9(s)?;
+    f10(s)?;
+    f11(s)?;
+    Ok(())
+}
+
+/// `flip`
+pub fn f2(s: &mut Vec<Value>) -> Result<(), Error> {
+    f7(s)?;
+    swap(s)?;
+    s.push(Value::Quote(Rc::new(vec![Op::Call(0)])));
+    swap(s)?;
+    apply(s)?;
+    apply(s)?;
+    Ok(())
+}
+
+/// `pairs`
+pub fn f3(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Quote(Rc::new(vec![Op::Call(12)])));
+    s.push(Value::Quote(Rc::new(vec![Op::Call(13)])));
+    f14(s)?;
+    quote(s)?;
+    Ok(())
+}
+
+/// `omega`
+pub fn f4(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(4, Rc::new(vec![Op::Clone, Op::Apply])));
+    Ok(())
+}
+
+/// `indirect`
+pub fn f5(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Symbol(3));
+    apply(s)?;
+    Ok(())
+}
+
+/// `false`
+pub fn f6(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(6, Rc::new(vec![Op::Drop])));
+    Ok(())
+}
+
+/// `true`
+pub fn f7(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(7, Rc::new(vec![Op::Swap, Op::Drop])));
+    Ok(())
+}
+
+/// `rotate3`
+pub fn f8(s: &mut Vec<Value>) -> Result<(), Error> {
+    f15(s)?;
+    swap(s)?;
+    quote(s)?;
+    compose(s)?;
+    apply(s)?;
+    Ok(())
+}
+
+/// `n2`
+pub fn f9(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(9, Rc::new(vec![Op::Push(Value::Quote(Rc::new(vec![Op::Clone]))), Op::Call(16), Op::Apply, Op::Push(Value::Quote(Rc::new(vec![Op::Compose]))), Op::Call(16), Op::Apply, Op::Apply])));
+    Ok(())
+}
+
+/// `n3`
+pub fn f10(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(10, Rc::new(vec![Op::Push(Value::Quote(Rc::new(vec![Op::Clone]))), Op::Call(17), Op::Apply, Op::Push(Value::Quote(Rc::new(vec![Op::Compose]))), Op::Call(17), Op::Apply, Op::Apply])));
+    Ok(())
+}
+
+/// `mul`
+pub fn f11(s: &mut Vec<Value>) -> Result<(), Error> {
+    f18(s)?;
+    f19(s)?;
+    quote(s)?;
+    s.push(Value::Quote(Rc::new(vec![Op::Call(20)])));
+    compose(s)?;
+    f19(s)?;
+    apply(s)?;
+    Ok(())
+}
+
+/// `v1`
+pub fn f12(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(12, Rc::new(vec![])));
+    Ok(())
+}
+
+/// `v2`
+pub fn f13(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(13, Rc::new(vec![])));
+    Ok(())
+}
+
+/// `compose2`
+pub fn f14(s: &mut Vec<Value>) -> Result<(), Error> {
+    compose(s)?;
+    Ok(())
+}
+
+/// `core.quote2`
+pub fn f15(s: &mut Vec<Value>) -> Result<(), Error> {
+    quote(s)?;
+    swap(s)?;
+    quote(s)?;
+    swap(s)?;
+    compose(s)?;
+    Ok(())
+}
+
+/// `core.n1`
+pub fn f16(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(16, Rc::new(vec![Op::Push(Value::Quote(Rc::new(vec![Op::Clone]))), Op::Call(18), Op::Apply, Op::Push(Value::Quote(Rc::new(vec![Op::Compose]))), Op::Call(18), Op::Apply, Op::Apply])));
+    Ok(())
+}
+
+/// `core.n2`
+pub fn f17(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(17, Rc::new(vec![Op::Push(Value::Quote(Rc::new(vec![Op::Clone]))), Op::Call(16), Op::Apply, Op::Push(Value::Quote(Rc::new(vec![Op::Compose]))), Op::Call(16), Op::Apply, Op::Apply])));
+    Ok(())
+}
+
+/// `core.n0`
+pub fn f18(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Call(18, Rc::new(vec![Op::Drop])));
+    Ok(())
+}
+
+/// `core.rotate3`
+pub fn f19(s: &mut Vec<Value>) -> Result<(), Error> {
+    f15(s)?;
+    swap(s)?;
+    quote(s)?;
+    compose(s)?;
+    apply(s)?;
+    Ok(())
+}
+
+/// `core.add`
+pub fn f20(s: &mut Vec<Value>) -> Result<(), Error> {
+    s.push(Value::Quote(Rc::new(vec![Op::Call(21)])));
+    swap(s)?;
+    apply(s)?;
+    Ok(())
+}
+
+/// `core.succ`
+pub fn f21(s: &mut Vec<Value>) -> Result<(), Error> {
+    quote(s)?;
+    s.push(Value::Quote(Rc::new(vec![Op::Apply])));
+    compose(s)?;
+    s.push(Value::Quote(Rc::new(vec![Op::Push(Value::Quote(Rc::new(vec![Op::Clone])))])));
+    swap(s)?;
+    clone(s)?;
+    s.push(Value::Quote(Rc::new(vec![Op::Push(Value::Quote(Rc::new(vec![Op::Compose])))])));
+    swap(s)?;
+    s.push(Value::Quote(Rc::new(vec![Op::Apply])));
+    f22(s)?;
+    Ok(())
+}
+
+/// `core.compose5`
+pub fn f22(s: &mut Vec<Value>) -> Result<(), Error> {
+    compose(s)?;
+    f23(s)?;
+    Ok(())
+}
+
+/// `core.compose4`
+pub fn f23(s: &mut Vec<Value>) -> Result<(), Error> {
+    compose(s)?;
+    f24(s)?;
+    Ok(())
+}
+
+/// `core.compose3`
+pub fn f24(s: &mut Vec<Value>) -> Result<(), Error> {
+    compose(s)?;
+    f25(s)?;
+    Ok(())
+}
+
+/// `core.compose2`
+pub fn f25(s: &mut Vec<Value>) -> Result<(), Error> {
+    compose(s)?;
+    Ok(())
+}
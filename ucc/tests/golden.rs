@@ -0,0 +1,31 @@
+// Copyright (c) 2021 Scott J Maddox
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Replays each transcript in `tests/golden`. Run with `UPDATE_GOLDEN=1`
+//! to rewrite them with the current output instead.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn golden_transcripts() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(OsStr::new("txt")))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        if let Err(err) = ucc::golden::check(&path, update) {
+            panic!("{}: {}", path.display(), err);
+        }
+    }
+}
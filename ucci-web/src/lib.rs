@@ -3,9 +3,26 @@
 // This Source Code Form is subject to the terms of the Mozilla Public
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
+use std::time::Duration;
+use ucc::builder::{Clock, InterpBuilder};
 use ucc::interp::Interp;
 use wasm_bindgen::prelude::*;
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// The page's monotonic clock, as `std::time::Instant` panics on wasm32
+struct PerformanceClock;
+
+impl Clock for PerformanceClock {
+    fn now(&self) -> Duration {
+        Duration::from_secs_f64(performance_now().max(0.0) / 1000.0)
+    }
+}
+
 #[wasm_bindgen]
 pub struct Ucci {
     interp: Interp,
@@ -17,11 +34,19 @@ impl Ucci {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
         Self {
-            interp: Interp::default(),
+            interp: InterpBuilder::new().clock(PerformanceClock).build(),
             buffer: Vec::with_capacity(4096),
         }
     }
 
+    pub fn prompt(&self) -> String {
+        self.interp.prompt().to_owned()
+    }
+
+    pub fn banner(&self) -> String {
+        self.interp.banner()
+    }
+
     pub fn is_done(&self) -> bool {
         self.interp.is_done()
     }
@@ -43,4 +68,37 @@ impl Ucci {
             .call1(&JsValue::null(), &JsValue::from(output))
             .unwrap();
     }
+
+    /// The problems with `line` that would keep it from parsing, each as
+    /// `[start, end, message]`, with offsets in UTF-16 code units, as in
+    /// JavaScript strings
+    pub fn validate(&self, line: &str) -> js_sys::Array {
+        let offset = |i: usize| line[..i].encode_utf16().count() as u32;
+        self.interp
+            .validate(line)
+            .into_iter()
+            .map(|diagnostic| {
+                js_sys::Array::of3(
+                    &JsValue::from(offset(diagnostic.start)),
+                    &JsValue::from(offset(diagnostic.end)),
+                    &JsValue::from(diagnostic.kind.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    /// Take steps for up to `millis` milliseconds, returning how many
+    pub fn interp_run_for(&mut self, millis: f64, write_output: &js_sys::Function) -> usize {
+        self.buffer.clear();
+        let budget = Duration::from_secs_f64(millis.max(0.0) / 1000.0);
+        let steps = self
+            .interp
+            .interp_run_for(budget, &mut self.buffer)
+            .unwrap();
+        let output = unsafe { std::str::from_utf8_unchecked(&self.buffer[..]) };
+        write_output
+            .call1(&JsValue::null(), &JsValue::from(output))
+            .unwrap();
+        steps
+    }
 }
@@ -6,8 +6,6 @@
 
 import init, { Ucci } from "./pkg/ucci_web.js";
 
-const PROMPT = "\n>>> ";
-
 async function run() {
   await init();
   let textarea = document.createElement("textarea");
@@ -40,29 +38,39 @@ async function run() {
     spellcheck: false;
   `;
 
-  textarea.value =
-    `\
-Untyped Concatenative Calculus Interpreter (UCCI)
-Type ":help" to see the available commands.
-` + PROMPT;
+  let ucci = new Ucci();
+  let input_start = 0;
+
+  function write_prompt() {
+    textarea.value += "\n" + ucci.prompt();
+    textarea.scrollTop = textarea.scrollHeight;
+    input_start = textarea.value.length;
+  }
 
   function write_output(output) {
     textarea.value += output;
     textarea.scrollTop = textarea.scrollHeight;
   }
 
-  let ucci = new Ucci();
+  textarea.value = ucci.banner();
+  write_prompt();
 
   function step() {
     if (ucci.is_done()) {
-      textarea.value += PROMPT;
-      textarea.scrollTop = textarea.scrollHeight;
+      write_prompt();
     } else {
       ucci.interp_step(write_output);
       setTimeout(step);
     }
   }
 
+  // Mark input that won't parse with a red caret, and say why on hover
+  textarea.addEventListener("input", () => {
+    let diagnostics = ucci.validate(textarea.value.slice(input_start));
+    textarea.style.caretColor = diagnostics.length > 0 ? "red" : "";
+    textarea.title = diagnostics.map(([, , message]) => message).join("\n");
+  });
+
   textarea.addEventListener("keydown", (ev) => {
     if (ev.key == "Enter") {
       if (
@@ -71,10 +79,7 @@ Type ":help" to see the available commands.
       ) {
         ev.preventDefault();
         textarea.value += "\n";
-        let input = textarea.value.slice(
-          textarea.value.lastIndexOf(PROMPT) + PROMPT.length,
-          textarea.selectionEnd
-        );
+        let input = textarea.value.slice(input_start, textarea.selectionEnd);
         ucci.interp_start(input, write_output);
         setTimeout(step);
       }
@@ -6,22 +6,366 @@
 
 use linefeed::{Interface, ReadResult};
 use std::error::Error;
-use std::io::stdout;
-use ucc::interp::Interp;
+use std::fs::{self, File};
+use std::io::{stdin, stdout, BufReader};
+use std::net::TcpListener;
+use std::path::Path;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::sync::{Arc, OnceLock};
+use ucc::binary;
+use ucc::codegen;
+use ucc::config::{default_config_path, Config};
+use ucc::doctest::run_doc_tests;
+use ucc::grammar;
+use ucc::interp::{Failure, Interp};
+use ucc::mutate::mutation_test;
+use ucc::quota::Quotas;
+use ucc::serve::Server;
+use ucc::tutor::{Reply, Tutor};
+use ucc::wat;
+
+/// The most small steps `ucci mutate` takes checking each assertion, so
+/// mutants that loop are killed
+const MUTANT_FUEL: usize = 10000;
+
+/// The most small steps `ucci test --doc` takes checking each doctest
+const DOC_TEST_FUEL: usize = 10000;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("pack") => return pack(&args[1..]),
+        Some("unpack") => return unpack(&args[1..]),
+        Some("grammar") => return grammar(&args[1..]),
+        Some("mutate") => return mutate(&args[1..]),
+        Some("codegen") => return codegen(&args[1..]),
+        Some("serve") => return serve(&args[1..]),
+        Some("tutor") => return tutor(&args[1..]),
+        _ => {}
+    }
     let mut interp = Interp::default();
+    if !args.iter().any(|arg| arg == "--no-config") {
+        if let Some(path) = default_config_path() {
+            match Config::load(&path) {
+                Ok(config) => interp.apply_config(&config, &mut stdout())?,
+                Err(err) => eprintln!("Failed to load {}: {:?}", path.display(), err),
+            }
+        }
+        if let Err(err) = interp.load_settings() {
+            eprintln!("Failed to load settings: {:?}", err);
+        }
+    }
+    match args.first().map(String::as_str) {
+        Some("run") => return run(&mut interp, &args[1..]),
+        Some("test") => return test(&mut interp, &args[1..]),
+        _ => {}
+    }
+    if args.iter().any(|arg| arg == "--eval" || arg == "--define") {
+        return eval(&mut interp, &args);
+    }
 
-    println!("Untyped Concatenative Calculus Interpreter (UCCI)");
-    println!("Type \":help\" to see the available commands.");
+    if !args.iter().any(|arg| arg == "--no-banner") {
+        print!("{}", interp.banner());
+    }
+    #[cfg(unix)]
+    handle_interrupts(&interp);
     let reader = Interface::new("ucci")?;
-    reader.set_prompt("\n>>> ")?;
+    reader.set_prompt(&format!("\n{}", interp.prompt()))?;
     while let ReadResult::Input(input) = reader.read_line()? {
         reader.add_history(input.clone());
         interp.interp_start(input.as_str(), &mut stdout()).unwrap();
         while !interp.is_done() {
             interp.interp_step(&mut stdout()).unwrap();
         }
+        if let Some(code) = interp.quit_code() {
+            std::process::exit(code);
+        }
+        reader.set_prompt(&format!("\n{}", interp.prompt()))?;
+    }
+    Ok(())
+}
+
+/// Have Ctrl-C stop the evaluation in progress, returning to the prompt,
+/// or exit if pressed again before the interpreter has stopped
+#[cfg(unix)]
+fn handle_interrupts(interp: &Interp) {
+    static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        if let Some(flag) = FLAG.get() {
+            if flag.swap(true, Ordering::SeqCst) {
+                // Only async-signal-safe calls are allowed here
+                unsafe { libc::_exit(130) };
+            }
+        }
+    }
+    let _ = FLAG.set(interp.interrupt_flag());
+    let handler: extern "C" fn(libc::c_int) = on_interrupt;
+    unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+}
+
+/// `ucci run <src.ucc>`: evaluate a source file, or stdin if the path is
+/// `-`, one item at a time without reading it all into memory.
+fn run(interp: &mut Interp, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let ok = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) if path == "-" => interp.run(stdin().lock(), &mut stdout())?,
+        Some(path) => interp.run(BufReader::new(File::open(path)?), &mut stdout())?,
+        None => return Err("usage: ucci run <src.ucc>".into()),
+    };
+    if !ok {
+        exit_failed(interp);
+    }
+    Ok(())
+}
+
+/// Exit with the code for why the last evaluation failed: 1 if evaluation
+/// failed, 2 if the input didn't parse, 3 if an expected stack didn't
+/// match, and 4 if a definition was refused
+fn exit_failed(interp: &Interp) -> ! {
+    std::process::exit(interp.failure().map_or(1, Failure::exit_code))
+}
+
+/// `ucci --define "{fn <sym> = <expr>}" --eval "<expr>"`: make the
+/// definitions and evaluate the expressions, in the order given, then print
+/// just the stack left. On an error, print what the REPL would have, and
+/// exit through `exit_failed`, with the `Failure::exit_code` of the error.
+fn eval(interp: &mut Interp, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: ucci [--define \"<definition>\"]... [--eval \"<expr>\"]...";
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--define" || arg == "--eval" {
+            let src = args.next().ok_or(usage)?;
+            let mut output = vec![];
+            if !interp.run(src.as_bytes(), &mut output)? {
+                eprint!("{}", String::from_utf8_lossy(&output));
+                exit_failed(interp);
+            }
+        }
+    }
+    interp.interp_start(":stack", &mut stdout())?;
+    while !interp.is_done() {
+        interp.interp_step(&mut stdout())?;
+    }
+    Ok(())
+}
+
+/// `ucci test [--coverage <report.html>] <src.ucc>...`: evaluate source
+/// files in turn, stopping at the first error. With `--coverage`, also
+/// print which functions they define were never called, and write a report
+/// of the calls of each. With `--doc`, check the big step assertions in the
+/// doc comments of their definitions instead.
+fn test(interp: &mut Interp, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: ucci test [--doc | --coverage <report.html>] <src.ucc>...";
+    let mut report_path = None;
+    let mut doc = false;
+    let mut paths = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--doc" {
+            doc = true;
+        } else if arg == "--coverage" {
+            report_path = Some(args.next().ok_or(usage)?);
+        } else if !arg.starts_with("--") {
+            paths.push(arg);
+        }
+    }
+    if paths.is_empty() {
+        return Err(usage.into());
+    }
+    if doc {
+        return doc_test(&paths);
+    }
+    let coverage = report_path.map(|_| interp.track_coverage());
+    for path in paths {
+        if !interp.run(BufReader::new(File::open(path)?), &mut stdout())? {
+            exit_failed(interp);
+        }
+    }
+    if let (Some(coverage), Some(report_path)) = (coverage, report_path) {
+        let report = interp.coverage_report(&coverage);
+        print!("{}", report);
+        fs::write(report_path, report.to_html())?;
+    }
+    Ok(())
+}
+
+/// Check the doctests of each source file, printing those that fail
+fn doc_test(paths: &[&String]) -> Result<(), Box<dyn Error>> {
+    let mut failed = 0;
+    let mut total = 0;
+    for path in paths {
+        let src = fs::read_to_string(path)?;
+        let results = run_doc_tests(&src, DOC_TEST_FUEL).map_err(|err| format!("{:?}", err))?;
+        for (test, passed) in results {
+            total += 1;
+            if !passed {
+                failed += 1;
+                println!("Failed: {}:{}: {}", path, test.line, test.assertion);
+            }
+        }
+    }
+    println!("Passed {} of {} doctests.", total - failed, total);
+    if failed > 0 {
+        std::process::exit(Failure::Assertion.exit_code());
+    }
+    Ok(())
+}
+
+/// `ucci tutor [<chapter>]`: a guided tutorial, from the chapter with this
+/// number, from 1. `:skip` moves on without doing a step.
+fn tutor(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut tutor = Tutor::default();
+    match args.first().map(|arg| arg.parse::<usize>()) {
+        Some(Ok(chapter)) if chapter >= 1 => tutor.go_to(chapter - 1),
+        None => {}
+        _ => return Err("usage: ucci tutor [<chapter>]".into()),
+    }
+    let reader = Interface::new("ucci")?;
+    reader.set_prompt("\n> ")?;
+    let mut chapter = None;
+    while let Some(step) = tutor.step() {
+        let (at, _) = tutor.position();
+        if chapter != Some(at) {
+            println!("\n# {}. {}", at + 1, tutor.chapters()[at].title);
+            chapter = Some(at);
+        }
+        if !step.text.is_empty() {
+            println!("\n{}", step.text);
+        }
+        println!("\n{}", step.task);
+        loop {
+            let input = match reader.read_line()? {
+                ReadResult::Input(input) => input,
+                _ => return Ok(()),
+            };
+            reader.add_history(input.clone());
+            if input.trim() == ":skip" {
+                tutor.skip();
+                break;
+            }
+            match tutor.input(&input) {
+                Reply::Correct => {
+                    println!("Correct!");
+                    break;
+                }
+                Reply::Wrong(vs) => println!("That leaves {}. Try again.", vs),
+                Reply::Unused(word) => println!("Right, but try it using `{}`.", word),
+                Reply::Error(err) => println!("{}", err),
+            }
+        }
+    }
+    println!("\nThat's the end of the tutorial.");
+    Ok(())
+}
+
+/// `ucci mutate <lib.ucc> <tests>`: check that the big step assertions in
+/// `tests`, one per line, fail for every small change to the definitions in
+/// `lib.ucc`, and print the changes they miss.
+fn mutate(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (lib_path, tests_path) = match args {
+        [lib_path, tests_path] => (lib_path, tests_path),
+        _ => return Err("usage: ucci mutate <lib.ucc> <tests>".into()),
+    };
+    let lib = fs::read_to_string(lib_path)?;
+    let tests = fs::read_to_string(tests_path)?;
+    let results = mutation_test(&lib, &tests, MUTANT_FUEL).map_err(|err| format!("{:?}", err))?;
+    let survivors: Vec<_> = results.iter().filter(|result| result.survived).collect();
+    println!(
+        "Killed {} of {} mutants.",
+        results.len() - survivors.len(),
+        results.len()
+    );
+    for result in survivors {
+        println!("Survived: {}    -- {}", result.fn_def, result.mutation);
+    }
+    Ok(())
+}
+
+/// `ucci codegen <lib.ucc> <out.rs|out.wat>`: compile the definitions in a
+/// source file, and the prelude functions they use, to a Rust module or a
+/// WebAssembly text module, by the extension of the output path.
+fn codegen(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: ucci codegen <lib.ucc> <out.rs|out.wat>";
+    let (lib_path, out_path) = match args {
+        [lib_path, out_path] => (lib_path, Path::new(out_path)),
+        _ => return Err(usage.into()),
+    };
+    let lib = fs::read_to_string(lib_path)?;
+    let src = match out_path.extension().and_then(|ext| ext.to_str()) {
+        Some("rs") => codegen::rust(&lib),
+        Some("wat") => wat::wat(&lib),
+        _ => return Err(usage.into()),
+    };
+    fs::write(out_path, src.map_err(|err| format!("{:?}", err))?)?;
+    Ok(())
+}
+
+/// `ucci serve [--config <quotas.toml>] [<addr>]`: serve sessions over
+/// HTTP on `addr`, by default `127.0.0.1:8080`, with their metrics on
+/// `/metrics`, holding each to the quotas in the config file, if any.
+fn serve(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: ucci serve [--config <quotas.toml>] [<addr>]";
+    let mut quotas = Quotas::default();
+    let mut addr = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            let path = args.next().ok_or(usage)?;
+            quotas = Quotas::load(Path::new(path)).map_err(|err| format!("{:?}", err))?;
+        } else if addr.is_none() && !arg.starts_with("--") {
+            addr = Some(arg.as_str());
+        } else {
+            return Err(usage.into());
+        }
+    }
+    let listener = TcpListener::bind(addr.unwrap_or("127.0.0.1:8080"))?;
+    println!("Serving on {}", listener.local_addr()?);
+    Server::with_quotas(quotas).serve(&listener)?;
+    Ok(())
+}
+
+/// `ucci pack <src.ucc> <out.uccb>`: pack the definitions in a source file.
+fn pack(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (src_path, out_path) = match args {
+        [src_path, out_path] => (src_path, out_path),
+        _ => return Err("usage: ucci pack <src.ucc> <out.uccb>".into()),
+    };
+    let src = fs::read_to_string(src_path)?;
+    let bytes = binary::pack(&src).map_err(|err| format!("{:?}", err))?;
+    fs::write(out_path, bytes)?;
+    Ok(())
+}
+
+/// `ucci unpack <in.uccb>`: print the definitions in a packed file.
+fn unpack(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let in_path = match args {
+        [in_path] => in_path,
+        _ => return Err("usage: ucci unpack <in.uccb>".into()),
+    };
+    let bytes = fs::read(in_path)?;
+    let src = binary::unpack(&bytes).map_err(|err| format!("{:?}", err))?;
+    print!("{}", src);
+    Ok(())
+}
+
+/// `ucci grammar --ebnf`: print the grammar the parsers accept.
+/// `ucci grammar --tree-sitter <dir>`: write a tree-sitter grammar for ucc
+/// source to `<dir>/grammar.js`, and its highlight queries to
+/// `<dir>/queries/highlights.scm`.
+fn grammar(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args {
+        [format] if format == "--ebnf" => print!("{}", grammar::EBNF),
+        [format, dir] if format == "--tree-sitter" => {
+            let dir = Path::new(dir);
+            fs::create_dir_all(dir.join("queries"))?;
+            fs::write(dir.join("grammar.js"), grammar::TREE_SITTER_GRAMMAR)?;
+            fs::write(
+                dir.join("queries").join("highlights.scm"),
+                grammar::TREE_SITTER_HIGHLIGHTS,
+            )?;
+        }
+        _ => return Err("usage: ucci grammar --ebnf | --tree-sitter <dir>".into()),
     }
     Ok(())
 }